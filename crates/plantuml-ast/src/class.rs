@@ -2,7 +2,12 @@
 
 use serde::{Deserialize, Serialize};
 
-use crate::common::{Color, DiagramMetadata, Direction, Identifier, LineStyle, Note, Stereotype};
+use std::collections::HashSet;
+
+use crate::common::{
+    Color, DiagramMetadata, Direction, Identifier, InlineStyle, LineStyle, Link, Note, Span, Stereotype,
+};
+use crate::visibility::{is_removed, Filterable, HiddenParts, VisibilityRule};
 
 /// Диаграмма классов
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -17,6 +22,8 @@ pub struct ClassDiagram {
     pub packages: Vec<Package>,
     /// Заметки
     pub notes: Vec<Note>,
+    /// Правила `hide`/`show`/`remove`/`restore` в порядке появления
+    pub visibility_rules: Vec<VisibilityRule>,
 }
 
 impl ClassDiagram {
@@ -34,6 +41,82 @@ impl ClassDiagram {
     pub fn add_relationship(&mut self, rel: Relationship) {
         self.relationships.push(rel);
     }
+
+    /// Применяет правила видимости
+    ///
+    /// Удалённые классификаторы исчезают вместе со своими отношениями,
+    /// оставшимся проставляются скрытые части. Классы, объявленные только
+    /// в отношениях, сопоставляются по имени. Связность для `@unlinked`
+    /// считается по исходной диаграмме. Правила расходуются: повторный
+    /// вызов ничего не меняет.
+    pub fn apply_visibility_rules(&mut self) {
+        if self.visibility_rules.is_empty() {
+            return;
+        }
+
+        let rules = std::mem::take(&mut self.visibility_rules);
+        let linked: HashSet<String> = self
+            .relationships
+            .iter()
+            .flat_map(|rel| [rel.from.clone(), rel.to.clone()])
+            .collect();
+        let declared = self.all_classifier_names();
+
+        let mut keep = |c: &mut Classifier| {
+            let linked = c.names().iter().any(|name| linked.contains(*name));
+            if is_removed(&rules, c, linked) {
+                return false;
+            }
+            c.hidden_parts = HiddenParts::resolve(&rules, c, linked, c.fields.is_empty(), c.methods.is_empty());
+            true
+        };
+        self.classifiers.retain_mut(&mut keep);
+        filter_packages(&mut self.packages, &rules, &mut keep);
+
+        // Удалены объявленные классификаторы и неявные классы из отношений
+        let remaining = self.all_classifier_names();
+        let removed: HashSet<&String> = declared
+            .difference(&remaining)
+            .chain(linked.iter().filter(|name| {
+                !declared.contains(*name) && is_removed(&rules, &Classifier::new(name.as_str()), true)
+            }))
+            .collect();
+        self.relationships
+            .retain(|rel| !removed.contains(&rel.from) && !removed.contains(&rel.to));
+    }
+
+    /// Имена (и алиасы) всех объявленных классификаторов, включая вложенные в пакеты
+    fn all_classifier_names(&self) -> HashSet<String> {
+        fn collect(packages: &[Package], names: &mut HashSet<String>) {
+            for package in packages {
+                for c in &package.classifiers {
+                    names.extend(c.names().into_iter().map(str::to_string));
+                }
+                collect(&package.packages, names);
+            }
+        }
+
+        let mut names: HashSet<String> = self
+            .classifiers
+            .iter()
+            .flat_map(|c| c.names().into_iter().map(str::to_string))
+            .collect();
+        collect(&self.packages, &mut names);
+        names
+    }
+}
+
+/// Удаляет пакеты и классификаторы по правилам видимости (рекурсивно)
+fn filter_packages(
+    packages: &mut Vec<Package>,
+    rules: &[VisibilityRule],
+    keep: &mut impl FnMut(&mut Classifier) -> bool,
+) {
+    packages.retain(|package| !is_removed(rules, package, true));
+    for package in packages {
+        package.classifiers.retain_mut(&mut *keep);
+        filter_packages(&mut package.packages, rules, keep);
+    }
 }
 
 /// Тип классификатора
@@ -84,8 +167,20 @@ pub struct Classifier {
     pub background_color: Option<Color>,
     /// Цвет границы
     pub border_color: Option<Color>,
+    /// Цвет текста (`#pink;text:blue`)
+    pub text_color: Option<Color>,
+    /// Стиль линии рамки (`#pink;line.dashed`)
+    pub line_style: Option<LineStyle>,
     /// Обобщённые параметры (generics)
     pub generics: Option<String>,
+    /// Гиперссылка (`[[url]]`)
+    pub link: Option<Link>,
+    /// Теги (`$tag`) для правил видимости
+    pub tags: Vec<String>,
+    /// Части, скрытые правилами видимости (`hide members`, `hide circle`)
+    pub hidden_parts: HiddenParts,
+    /// Позиция объявления в исходнике
+    pub span: Span,
 }
 
 impl Classifier {
@@ -99,7 +194,13 @@ impl Classifier {
             stereotype: None,
             background_color: None,
             border_color: None,
+            text_color: None,
+            line_style: None,
             generics: None,
+            link: None,
+            tags: Vec::new(),
+            hidden_parts: HiddenParts::default(),
+            span: Span::empty(),
         }
     }
 
@@ -138,6 +239,24 @@ impl Classifier {
     }
 }
 
+impl Filterable for Classifier {
+    fn names(&self) -> Vec<&str> {
+        std::iter::once(self.id.name.as_str()).chain(self.id.alias.as_deref()).collect()
+    }
+
+    fn is_kind(&self, keyword: &str) -> bool {
+        ClassifierType::parse(keyword) == Some(self.classifier_type)
+    }
+
+    fn stereotype(&self) -> Option<&Stereotype> {
+        self.stereotype.as_ref()
+    }
+
+    fn tags(&self) -> &[String] {
+        &self.tags
+    }
+}
+
 /// Модификатор видимости
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum Visibility {
@@ -298,8 +417,14 @@ pub struct Relationship {
     pub to_cardinality: Option<String>,
     /// Стиль линии
     pub line_style: LineStyle,
-    /// Направление
+    /// Встроенный стиль связи: `-[#red,bold]->`, `Foo --> Bar #blue;line.bold`
+    pub style: InlineStyle,
+    /// Подсказка размещения (`-up->`): где `to` относительно `from`
     pub direction: Option<Direction>,
+    /// Скрытая связь (`-[hidden]->`): влияет на layout, но не рисуется
+    pub hidden: bool,
+    /// Позиция в исходнике
+    pub span: Span,
 }
 
 impl Relationship {
@@ -317,7 +442,10 @@ impl Relationship {
             from_cardinality: None,
             to_cardinality: None,
             line_style: LineStyle::Solid,
+            style: InlineStyle::default(),
             direction: None,
+            hidden: false,
+            span: Span::empty(),
         }
     }
 
@@ -378,6 +506,24 @@ impl Package {
     }
 }
 
+impl Filterable for Package {
+    fn names(&self) -> Vec<&str> {
+        vec![self.name.as_str()]
+    }
+
+    fn is_kind(&self, keyword: &str) -> bool {
+        keyword == "package"
+    }
+
+    fn stereotype(&self) -> Option<&Stereotype> {
+        self.stereotype.as_ref()
+    }
+
+    fn tags(&self) -> &[String] {
+        &[]
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -411,4 +557,39 @@ mod tests {
         assert_eq!(rel.to, "Animal");
         assert_eq!(rel.relationship_type, RelationshipType::Inheritance);
     }
+
+    #[test]
+    fn test_apply_visibility_rules() {
+        let mut diagram = ClassDiagram::new();
+        let mut user = Classifier::new("User");
+        user.tags.push("internal".to_string());
+        diagram.add_class(user);
+        diagram.add_class(Classifier::new("Order"));
+        diagram.add_class(Classifier::new("Lonely"));
+        let mut package = Package::new("billing");
+        package.classifiers.push(Classifier::interface("Invoice"));
+        diagram.packages.push(package);
+        diagram.add_relationship(Relationship::new("Order", "User", RelationshipType::Association));
+        diagram.add_relationship(Relationship::new("Order", "Invoice", RelationshipType::Association));
+        diagram.add_relationship(Relationship::new("Order", "Implicit", RelationshipType::Association));
+        diagram.visibility_rules = [
+            "remove $internal",
+            "hide @unlinked",
+            "hide interface",
+            "hide Impl*",
+            "hide empty members",
+        ]
+        .iter()
+        .map(|line| VisibilityRule::parse(line).unwrap())
+        .collect();
+
+        diagram.apply_visibility_rules();
+
+        let names: Vec<&str> = diagram.classifiers.iter().map(|c| c.id.name.as_str()).collect();
+        assert_eq!(names, vec!["Order"]);
+        assert!(diagram.packages[0].classifiers.is_empty());
+        assert!(diagram.relationships.is_empty());
+        assert!(diagram.classifiers[0].hidden_parts.fields);
+        assert!(diagram.classifiers[0].hidden_parts.methods);
+    }
 }
@@ -1,7 +1,11 @@
 //! Общие типы, используемые во всех типах диаграмм.
 
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
+use crate::sprite::SpriteTable;
+
 /// Позиция в исходном коде
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Span {
@@ -182,6 +186,84 @@ impl Stereotype {
     }
 }
 
+/// Гиперссылка элемента: `[[url]]`, `[[url{tooltip} label]]`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Link {
+    /// Адрес (пустой для ссылки только с подсказкой `[[{tooltip}]]`)
+    pub url: String,
+    /// Всплывающая подсказка
+    pub tooltip: Option<String>,
+    /// Текст ссылки
+    pub label: Option<String>,
+}
+
+impl Link {
+    /// Создаёт ссылку без подсказки и текста
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            tooltip: None,
+            label: None,
+        }
+    }
+
+    /// Разбирает `[[url{tooltip} label]]` (скобки необязательны)
+    pub fn parse(markup: &str) -> Option<Self> {
+        let markup = markup.trim();
+        let inner = markup
+            .strip_prefix("[[")
+            .and_then(|m| m.strip_suffix("]]"))
+            .unwrap_or(markup)
+            .trim();
+
+        let (target, label) = match inner.find(char::is_whitespace) {
+            // Пробел внутри подсказки не отделяет текст
+            Some(index) if !inner[..index].contains('{') || inner[..index].contains('}') => {
+                (&inner[..index], Some(inner[index..].trim()))
+            }
+            _ => match inner.find('}') {
+                Some(index) => (&inner[..=index], Some(inner[index + 1..].trim())),
+                None => (inner, None),
+            },
+        };
+        let (url, tooltip) = match target.split_once('{') {
+            Some((url, tooltip)) => (url, Some(tooltip.trim_end_matches('}').trim())),
+            None => (target, None),
+        };
+
+        if url.is_empty() && tooltip.is_none() {
+            return None;
+        }
+        let non_empty = |value: Option<&str>| value.filter(|v| !v.is_empty()).map(str::to_string);
+        Some(Self {
+            url: url.to_string(),
+            tooltip: non_empty(tooltip),
+            label: non_empty(label),
+        })
+    }
+
+    /// Извлекает первую ссылку из текста
+    ///
+    /// `[[...]]` заменяется текстом ссылки (или удаляется), остальной текст
+    /// сохраняется.
+    pub fn extract(text: &str) -> (String, Option<Self>) {
+        let Some(start) = text.find("[[") else {
+            return (text.to_string(), None);
+        };
+        let Some(length) = text[start..].find("]]") else {
+            return (text.to_string(), None);
+        };
+        let end = start + length + 2;
+        let Some(link) = Self::parse(&text[start..end]) else {
+            return (text.to_string(), None);
+        };
+
+        let label = link.label.as_deref().unwrap_or_default();
+        let rest = format!("{}{}{}", &text[..start], label, &text[end..]);
+        (rest.trim().to_string(), Some(link))
+    }
+}
+
 /// Заметка (note)
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Note {
@@ -193,6 +275,8 @@ pub struct Note {
     pub anchors: Vec<String>,
     /// Цвет фона
     pub background_color: Option<Color>,
+    /// Гиперссылка
+    pub link: Option<Link>,
 }
 
 /// Позиция заметки
@@ -215,6 +299,46 @@ pub enum Direction {
     RightToLeft,
 }
 
+impl Direction {
+    /// Противоположное направление
+    pub fn opposite(self) -> Self {
+        match self {
+            Self::TopToBottom => Self::BottomToTop,
+            Self::BottomToTop => Self::TopToBottom,
+            Self::LeftToRight => Self::RightToLeft,
+            Self::RightToLeft => Self::LeftToRight,
+        }
+    }
+
+    /// Горизонтальное направление (слева направо или справа налево)
+    pub fn is_horizontal(self) -> bool {
+        matches!(self, Self::LeftToRight | Self::RightToLeft)
+    }
+}
+
+/// Форма линий связей (`skinparam linetype ortho|polyline|spline`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LineType {
+    /// Только горизонтальные и вертикальные отрезки
+    Ortho,
+    /// Ломаная с произвольными отрезками
+    Polyline,
+    /// Сглаженная кривая
+    Spline,
+}
+
+impl LineType {
+    /// Разбирает значение skinparam (без учёта регистра)
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "ortho" => Some(Self::Ortho),
+            "polyline" => Some(Self::Polyline),
+            "spline" | "splines" => Some(Self::Spline),
+            _ => None,
+        }
+    }
+}
+
 /// Стиль линии
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum LineStyle {
@@ -225,6 +349,198 @@ pub enum LineStyle {
     Bold,
 }
 
+impl LineStyle {
+    /// Разбирает стиль линии (`dashed`, `dotted`, `bold`, `solid`, `plain`)
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "solid" | "plain" => Some(Self::Solid),
+            "dashed" => Some(Self::Dashed),
+            "dotted" => Some(Self::Dotted),
+            "bold" => Some(Self::Bold),
+            _ => None,
+        }
+    }
+}
+
+/// Встроенный стиль элемента: `#pink;line:red;line.dashed;text:blue`
+///
+/// Заливка может быть градиентом (`#red/blue`, `#red|blue`, `#red-blue`,
+/// `#red\blue`) — он хранится строкой в `fill` и разбирается при рендеринге.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InlineStyle {
+    /// Заливка (`#pink`, `back:pink`)
+    pub fill: Option<Color>,
+    /// Цвет линии (`line:red`)
+    pub line_color: Option<Color>,
+    /// Стиль линии (`line.dashed`, `line.dotted`, `line.bold`)
+    pub line_style: Option<LineStyle>,
+    /// Толщина линии (`line.thickness:2`)
+    pub line_thickness: Option<f64>,
+    /// Цвет текста (`text:blue`)
+    pub text_color: Option<Color>,
+}
+
+impl InlineStyle {
+    /// Разбирает спецификацию стиля; неизвестные части пропускаются
+    pub fn parse(spec: &str) -> Self {
+        let mut style = Self::default();
+        for part in spec.split(';') {
+            let part = part.trim().trim_start_matches('#');
+            if part.is_empty() {
+                continue;
+            }
+            let (key, value) = match part.split_once([':', '=']) {
+                Some((key, value)) => (key.trim().to_ascii_lowercase(), Some(value.trim())),
+                None => (part.to_ascii_lowercase(), None),
+            };
+            match (key.as_str(), value) {
+                ("back" | "background", Some(value)) => style.fill = Some(Color::parse(value)),
+                ("line" | "border", Some(value)) => style.line_color = Some(Color::parse(value)),
+                ("line.thickness" | "thickness", Some(value)) => {
+                    style.line_thickness = value.parse().ok();
+                }
+                ("text" | "fontcolor", Some(value)) => style.text_color = Some(Color::parse(value)),
+                (line, None) if line.starts_with("line.") => {
+                    style.line_style = LineStyle::parse(&line["line.".len()..]);
+                }
+                (_, None) if style.fill.is_none() => style.fill = Some(Color::parse(part)),
+                _ => {}
+            }
+        }
+        style
+    }
+
+    /// Пустой ли стиль
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Директива `scale`: `scale 1.5`, `scale 2/3`, `scale 200 width`,
+/// `scale 300 height`, `scale 200*100`, `scale max 1024 width`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DiagramScale {
+    /// Множитель
+    Factor(f64),
+    /// Точная ширина
+    Width(f64),
+    /// Точная высота
+    Height(f64),
+    /// Вписать в прямоугольник
+    Size { width: f64, height: f64 },
+    /// Уменьшить до ограничений, не увеличивая
+    Max {
+        width: Option<f64>,
+        height: Option<f64>,
+    },
+}
+
+impl DiagramScale {
+    /// Разбирает аргументы директивы `scale`
+    pub fn parse(spec: &str) -> Option<Self> {
+        let spec = spec.trim().to_lowercase();
+        if let Some(rest) = spec.strip_prefix("max") {
+            return match Self::parse(rest)? {
+                Self::Width(width) => Some(Self::Max {
+                    width: Some(width),
+                    height: None,
+                }),
+                Self::Height(height) => Some(Self::Max {
+                    width: None,
+                    height: Some(height),
+                }),
+                Self::Size { width, height } => Some(Self::Max {
+                    width: Some(width),
+                    height: Some(height),
+                }),
+                Self::Factor(_) | Self::Max { .. } => None,
+            };
+        }
+
+        let number = |s: &str| s.trim().parse::<f64>().ok().filter(|v| *v > 0.0);
+        if let Some(width) = spec.strip_suffix("width") {
+            return number(width).map(Self::Width);
+        }
+        if let Some(height) = spec.strip_suffix("height") {
+            return number(height).map(Self::Height);
+        }
+        if let Some((width, height)) = spec.split_once('*') {
+            return Some(Self::Size {
+                width: number(width)?,
+                height: number(height)?,
+            });
+        }
+        if let Some((num, den)) = spec.split_once('/') {
+            return Some(Self::Factor(number(num)? / number(den)?));
+        }
+        number(&spec).map(Self::Factor)
+    }
+
+    /// Множитель для изображения исходного размера `width` × `height`
+    pub fn factor(&self, width: f64, height: f64) -> f64 {
+        let ratio = |target: f64, size: f64| if size > 0.0 { target / size } else { 1.0 };
+        match *self {
+            Self::Factor(factor) => factor,
+            Self::Width(target) => ratio(target, width),
+            Self::Height(target) => ratio(target, height),
+            Self::Size {
+                width: w,
+                height: h,
+            } => ratio(w, width).min(ratio(h, height)),
+            Self::Max {
+                width: w,
+                height: h,
+            } => {
+                let fit_w = w.map_or(1.0, |w| ratio(w, width));
+                let fit_h = h.map_or(1.0, |h| ratio(h, height));
+                fit_w.min(fit_h).min(1.0)
+            }
+        }
+    }
+}
+
+/// Горизонтальное выравнивание
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum HorizontalAlign {
+    Left,
+    #[default]
+    Center,
+    Right,
+}
+
+impl HorizontalAlign {
+    /// Разбирает `left`, `center`, `right`
+    pub fn parse(word: &str) -> Option<Self> {
+        match word.to_ascii_lowercase().as_str() {
+            "left" => Some(Self::Left),
+            "center" => Some(Self::Center),
+            "right" => Some(Self::Right),
+            _ => None,
+        }
+    }
+}
+
+/// Легенда диаграммы: `legend [top|bottom] [left|right|center] … endlegend`
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Legend {
+    /// Текст легенды (creole)
+    pub text: String,
+    /// Над диаграммой (`legend top`), по умолчанию — под ней
+    pub top: bool,
+    /// Выравнивание по горизонтали
+    pub align: HorizontalAlign,
+}
+
+impl Legend {
+    /// Создаёт легенду под диаграммой по центру
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+}
+
 /// Метаданные диаграммы
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct DiagramMetadata {
@@ -233,19 +549,111 @@ pub struct DiagramMetadata {
     /// Подпись
     pub caption: Option<String>,
     /// Легенда
-    pub legend: Option<String>,
+    pub legend: Option<Legend>,
     /// Верхний колонтитул
     pub header: Option<String>,
+    /// Выравнивание верхнего колонтитула (по умолчанию справа)
+    pub header_align: Option<HorizontalAlign>,
     /// Нижний колонтитул
     pub footer: Option<String>,
-    /// Масштаб
-    pub scale: Option<f64>,
+    /// Выравнивание нижнего колонтитула (по умолчанию по центру)
+    pub footer_align: Option<HorizontalAlign>,
+    /// Масштаб (директива `scale`)
+    pub scale: Option<DiagramScale>,
+    /// Направление раскладки (`left to right direction`)
+    pub direction: Direction,
+    /// Форма линий связей (`skinparam linetype`; None — по умолчанию для типа диаграммы)
+    pub line_type: Option<LineType>,
+    /// Спрайты (`sprite $name ...`)
+    pub sprites: SpriteTable,
+    /// Прагмы (`!pragma teoz true`): имя в нижнем регистре → значение
+    pub pragmas: BTreeMap<String, String>,
+    /// Заголовок рамки вокруг всей диаграммы (`mainframe`)
+    pub mainframe: Option<String>,
+}
+
+impl DiagramMetadata {
+    /// Значение прагмы (пустая строка для `!pragma name` без значения)
+    pub fn pragma(&self, name: &str) -> Option<&str> {
+        self.pragmas.get(&name.to_ascii_lowercase()).map(String::as_str)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_diagram_scale_parse() {
+        assert_eq!(DiagramScale::parse("1.5"), Some(DiagramScale::Factor(1.5)));
+        assert_eq!(DiagramScale::parse("2/4"), Some(DiagramScale::Factor(0.5)));
+        assert_eq!(DiagramScale::parse("200 width"), Some(DiagramScale::Width(200.0)));
+        assert_eq!(DiagramScale::parse("300 height"), Some(DiagramScale::Height(300.0)));
+        assert_eq!(
+            DiagramScale::parse("200*100"),
+            Some(DiagramScale::Size {
+                width: 200.0,
+                height: 100.0
+            })
+        );
+        assert_eq!(
+            DiagramScale::parse("max 1024 width"),
+            Some(DiagramScale::Max {
+                width: Some(1024.0),
+                height: None
+            })
+        );
+        assert_eq!(DiagramScale::parse("max 2"), None);
+        assert_eq!(DiagramScale::parse("big"), None);
+    }
+
+    #[test]
+    fn test_diagram_scale_factor() {
+        assert_eq!(DiagramScale::Factor(2.0).factor(100.0, 50.0), 2.0);
+        assert_eq!(DiagramScale::Width(200.0).factor(100.0, 50.0), 2.0);
+        assert_eq!(
+            DiagramScale::Size {
+                width: 200.0,
+                height: 50.0
+            }
+            .factor(100.0, 50.0),
+            1.0
+        );
+        let max = DiagramScale::Max {
+            width: Some(400.0),
+            height: None,
+        };
+        assert_eq!(max.factor(100.0, 50.0), 1.0);
+        assert_eq!(max.factor(800.0, 50.0), 0.5);
+    }
+
+    #[test]
+    fn test_link_parse() {
+        assert_eq!(Link::parse("[[http://example.com]]"), Some(Link::new("http://example.com")));
+
+        let link = Link::parse("[[http://example.com{Подсказка с пробелами} Текст ссылки]]").unwrap();
+        assert_eq!(link.url, "http://example.com");
+        assert_eq!(link.tooltip.as_deref(), Some("Подсказка с пробелами"));
+        assert_eq!(link.label.as_deref(), Some("Текст ссылки"));
+
+        let tooltip_only = Link::parse("[[{only tooltip}]]").unwrap();
+        assert_eq!((tooltip_only.url.as_str(), tooltip_only.tooltip.as_deref()), ("", Some("only tooltip")));
+
+        assert!(Link::parse("[[]]").is_none());
+    }
+
+    #[test]
+    fn test_link_extract() {
+        let (text, link) = Link::extract("see [[http://x.org docs]] here");
+        assert_eq!(text, "see docs here");
+        assert_eq!(link.unwrap().url, "http://x.org");
+
+        let (text, link) = Link::extract("Hello [[http://x.org]]");
+        assert_eq!((text.as_str(), link.is_some()), ("Hello", true));
+
+        assert_eq!(Link::extract("no [[link"), ("no [[link".to_string(), None));
+    }
+
     #[test]
     fn test_identifier_display_name() {
         let id = Identifier::new("Alice");
@@ -279,4 +687,23 @@ mod tests {
         assert_eq!(Color::parse("#LightBlue").to_css(), "LightBlue");
         assert_eq!(Color::parse("DarkGreen").to_css(), "DarkGreen");
     }
+
+    #[test]
+    fn test_inline_style_parse() {
+        let style = InlineStyle::parse("#pink;line:red;line.dashed;text:blue");
+        assert_eq!(style.fill, Some(Color::named("pink")));
+        assert_eq!(style.line_color, Some(Color::named("red")));
+        assert_eq!(style.line_style, Some(LineStyle::Dashed));
+        assert_eq!(style.text_color, Some(Color::named("blue")));
+
+        // Градиент остаётся строкой заливки, `#line:` — без заливки
+        let gradient = InlineStyle::parse("#red/blue;line.bold");
+        assert_eq!(gradient.fill.map(|fill| fill.to_css()).as_deref(), Some("red/blue"));
+        assert_eq!(gradient.line_style, Some(LineStyle::Bold));
+        let line = InlineStyle::parse("#line:00FF00;line.thickness:3");
+        assert_eq!(line.fill, None);
+        assert_eq!(line.line_color.map(|color| color.to_css()).as_deref(), Some("#00FF00"));
+        assert_eq!(line.line_thickness, Some(3.0));
+        assert!(InlineStyle::parse("").is_empty());
+    }
 }
@@ -1,8 +1,11 @@
 //! AST типы для Component и Deployment Diagrams.
 
+use std::collections::HashSet;
+
 use serde::{Deserialize, Serialize};
 
-use crate::common::{Color, DiagramMetadata, Note, Stereotype};
+use crate::common::{Color, DiagramMetadata, Direction, Note, Stereotype};
+use crate::visibility::{is_removed, Filterable, VisibilityRule};
 
 /// Диаграмма компонентов/развёртывания
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -17,6 +20,8 @@ pub struct ComponentDiagram {
     pub packages: Vec<ComponentPackage>,
     /// Заметки
     pub notes: Vec<Note>,
+    /// Правила `hide`/`show`/`remove`/`restore` в порядке появления
+    pub visibility_rules: Vec<VisibilityRule>,
 }
 
 impl ComponentDiagram {
@@ -24,6 +29,77 @@ impl ComponentDiagram {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Применяет правила видимости
+    ///
+    /// Удалённые компоненты и контейнеры исчезают вместе с содержимым и
+    /// связями. Элементы, упомянутые только в связях, сопоставляются по
+    /// имени. Правила расходуются: повторный вызов ничего не меняет.
+    pub fn apply_visibility_rules(&mut self) {
+        if self.visibility_rules.is_empty() {
+            return;
+        }
+
+        let rules = std::mem::take(&mut self.visibility_rules);
+        let linked: HashSet<String> = self
+            .connections
+            .iter()
+            .flat_map(|conn| [conn.from.clone(), conn.to.clone()])
+            .collect();
+        let declared = self.all_component_names();
+
+        filter_components(&mut self.components, &rules, &linked);
+        filter_packages(&mut self.packages, &rules, &linked);
+
+        let remaining = self.all_component_names();
+        let removed: HashSet<&String> = declared
+            .difference(&remaining)
+            .chain(linked.iter().filter(|name| {
+                !declared.contains(*name) && is_removed(&rules, &Component::new(name.as_str()), true)
+            }))
+            .collect();
+        self.connections
+            .retain(|conn| !removed.contains(&conn.from) && !removed.contains(&conn.to));
+    }
+
+    /// Имена (и алиасы) всех компонентов и контейнеров, включая вложенные
+    fn all_component_names(&self) -> HashSet<String> {
+        fn components(list: &[Component], names: &mut HashSet<String>) {
+            for component in list {
+                names.extend(component.names().into_iter().map(str::to_string));
+                components(&component.children, names);
+            }
+        }
+        fn packages(list: &[ComponentPackage], names: &mut HashSet<String>) {
+            for package in list {
+                names.insert(package.name.clone());
+                components(&package.components, names);
+                packages(&package.packages, names);
+            }
+        }
+
+        let mut names = HashSet::new();
+        components(&self.components, &mut names);
+        packages(&self.packages, &mut names);
+        names
+    }
+}
+
+/// Удаляет компоненты по правилам видимости (рекурсивно)
+fn filter_components(components: &mut Vec<Component>, rules: &[VisibilityRule], linked: &HashSet<String>) {
+    components.retain(|c| !is_removed(rules, c, c.names().iter().any(|name| linked.contains(*name))));
+    for component in components {
+        filter_components(&mut component.children, rules, linked);
+    }
+}
+
+/// Удаляет контейнеры и их содержимое по правилам видимости (рекурсивно)
+fn filter_packages(packages: &mut Vec<ComponentPackage>, rules: &[VisibilityRule], linked: &HashSet<String>) {
+    packages.retain(|package| !is_removed(rules, package, linked.contains(&package.name)));
+    for package in packages {
+        filter_components(&mut package.components, rules, linked);
+        filter_packages(&mut package.packages, rules, linked);
+    }
 }
 
 /// Тип компонента
@@ -129,6 +205,8 @@ pub struct Component {
     pub interfaces: Vec<ComponentInterface>,
     /// Порты
     pub ports: Vec<Port>,
+    /// Теги (`$tag`) для правил видимости
+    pub tags: Vec<String>,
 }
 
 impl Component {
@@ -143,6 +221,7 @@ impl Component {
             children: Vec::new(),
             interfaces: Vec::new(),
             ports: Vec::new(),
+            tags: Vec::new(),
         }
     }
 
@@ -171,6 +250,24 @@ impl Component {
     }
 }
 
+impl Filterable for Component {
+    fn names(&self) -> Vec<&str> {
+        std::iter::once(self.name.as_str()).chain(self.alias.as_deref()).collect()
+    }
+
+    fn is_kind(&self, keyword: &str) -> bool {
+        ComponentType::parse(keyword) == Some(self.component_type)
+    }
+
+    fn stereotype(&self) -> Option<&Stereotype> {
+        self.stereotype.as_ref()
+    }
+
+    fn tags(&self) -> &[String] {
+        &self.tags
+    }
+}
+
 /// Интерфейс компонента
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ComponentInterface {
@@ -238,6 +335,10 @@ pub struct Connection {
     pub color: Option<Color>,
     /// Пунктирная линия
     pub dashed: bool,
+    /// Подсказка размещения (`-up->`): где `to` относительно `from`
+    pub direction: Option<Direction>,
+    /// Скрытая связь (`-[hidden]->`): влияет на layout, но не рисуется
+    pub hidden: bool,
 }
 
 impl Connection {
@@ -250,6 +351,8 @@ impl Connection {
             label: None,
             color: None,
             dashed: false,
+            direction: None,
+            hidden: false,
         }
     }
 
@@ -293,6 +396,9 @@ pub enum PackageType {
     Frame,
     Cloud,
     Rectangle,
+    Database,
+    Storage,
+    Queue,
 }
 
 impl ComponentPackage {
@@ -309,6 +415,35 @@ impl ComponentPackage {
     }
 }
 
+impl Filterable for ComponentPackage {
+    fn names(&self) -> Vec<&str> {
+        vec![self.name.as_str()]
+    }
+
+    fn is_kind(&self, keyword: &str) -> bool {
+        let kind = match self.package_type {
+            PackageType::Package => "package",
+            PackageType::Node => "node",
+            PackageType::Folder => "folder",
+            PackageType::Frame => "frame",
+            PackageType::Cloud => "cloud",
+            PackageType::Rectangle => "rectangle",
+            PackageType::Database => "database",
+            PackageType::Storage => "storage",
+            PackageType::Queue => "queue",
+        };
+        keyword == kind
+    }
+
+    fn stereotype(&self) -> Option<&Stereotype> {
+        self.stereotype.as_ref()
+    }
+
+    fn tags(&self) -> &[String] {
+        &[]
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -331,4 +466,32 @@ mod tests {
         assert_eq!(conn.to, "Database");
         assert_eq!(conn.label, Some("uses".to_string()));
     }
+
+    #[test]
+    fn test_apply_visibility_rules() {
+        let mut diagram = ComponentDiagram::new();
+        let mut api = Component::new("Api");
+        api.stereotype = Some(Stereotype::new("internal"));
+        diagram.components.push(api);
+        diagram.components.push(Component::new("Web"));
+        diagram.components.push(Component::database("Db"));
+        let mut node = ComponentPackage::new("Server");
+        node.package_type = PackageType::Node;
+        node.components.push(Component::new("Worker"));
+        diagram.packages.push(node);
+        diagram.connections.push(Connection::new("Web", "Api"));
+        diagram.connections.push(Connection::new("Web", "Db"));
+        diagram.connections.push(Connection::new("Web", "Worker"));
+        diagram.visibility_rules = ["hide <<internal>>", "remove database", "remove node"]
+            .iter()
+            .map(|line| VisibilityRule::parse(line).unwrap())
+            .collect();
+
+        diagram.apply_visibility_rules();
+
+        assert_eq!(diagram.components.len(), 1);
+        assert_eq!(diagram.components[0].name, "Web");
+        assert!(diagram.packages.is_empty());
+        assert!(diagram.connections.is_empty());
+    }
 }
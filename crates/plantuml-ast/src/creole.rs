@@ -0,0 +1,450 @@
+//! Creole: разметка текста в подписях всех диаграмм
+//!
+//! Подпись разбирается построчно в [`CreoleLine`]: обычная строка, пункт
+//! списка (`* item`, `# item`), строка таблицы (`|= a | b |`) или
+//! горизонтальная линия (`----`). Внутри строки разметка (`**bold**`,
+//! `<color:red>`, `<$sprite>`, …) раскрывается в последовательность
+//! фрагментов [`Inline`] с уже вычисленным стилем.
+
+use serde::{Deserialize, Serialize};
+
+use crate::sprite::SpriteRef;
+
+/// Стиль фрагмента текста
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TextStyle {
+    /// Полужирный (`**`, `<b>`)
+    pub bold: bool,
+    /// Курсив (`//`, `<i>`)
+    pub italic: bool,
+    /// Моноширинный (`""`)
+    pub monospace: bool,
+    /// Подчёркнутый (`__`, `<u>`)
+    pub underline: bool,
+    /// Волнистое подчёркивание (`~~`, `<w>`)
+    pub wave: bool,
+    /// Зачёркнутый (`--`, `<s>`)
+    pub strike: bool,
+    /// Цвет текста (`<color:red>`)
+    pub color: Option<String>,
+    /// Цвет фона (`<back:yellow>`)
+    pub background: Option<String>,
+    /// Размер шрифта (`<size:18>`)
+    pub size: Option<f64>,
+}
+
+impl TextStyle {
+    /// Стиль без оформления
+    pub fn is_plain(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Фрагмент строки
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Inline {
+    /// Текст одного стиля
+    Text {
+        /// Текст без разметки
+        text: String,
+        /// Стиль
+        style: TextStyle,
+    },
+    /// Ссылка на спрайт `<$name>`
+    Sprite {
+        /// Разобранная ссылка
+        reference: SpriteRef,
+        /// Исходная разметка — для вывода, если спрайт не определён
+        markup: String,
+    },
+}
+
+/// Ячейка строки таблицы
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TableCell {
+    /// Ячейка заголовка (`|=`)
+    pub header: bool,
+    /// Содержимое
+    pub content: Vec<Inline>,
+}
+
+/// Строка подписи
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum CreoleLine {
+    /// Обычная строка
+    Text(Vec<Inline>),
+    /// Пункт списка: `*` — маркированный, `#` — нумерованный
+    ListItem {
+        /// Нумерованный список
+        ordered: bool,
+        /// Уровень вложенности, с 1
+        level: usize,
+        /// Содержимое
+        content: Vec<Inline>,
+    },
+    /// Строка таблицы
+    TableRow(Vec<TableCell>),
+    /// Горизонтальная линия (`----`, `====`, `....`, `____`)
+    Rule,
+}
+
+/// Разбирает строку подписи (без переносов)
+pub fn parse_line(line: &str) -> CreoleLine {
+    let trimmed = line.trim();
+
+    if trimmed.len() >= 4 {
+        let first = trimmed.chars().next().unwrap_or_default();
+        if matches!(first, '-' | '=' | '.' | '_') && trimmed.chars().all(|c| c == first) {
+            return CreoleLine::Rule;
+        }
+    }
+
+    if trimmed.len() >= 2 && trimmed.starts_with('|') && trimmed.ends_with('|') {
+        let cells = trimmed[1..trimmed.len() - 1]
+            .split('|')
+            .map(|cell| match cell.strip_prefix('=') {
+                Some(header) => TableCell {
+                    header: true,
+                    content: parse_inline(header.trim()),
+                },
+                None => TableCell {
+                    header: false,
+                    content: parse_inline(cell.trim()),
+                },
+            })
+            .collect();
+        return CreoleLine::TableRow(cells);
+    }
+
+    for marker in ['*', '#'] {
+        let level = trimmed.chars().take_while(|c| *c == marker).count();
+        if level > 0 {
+            if let Some(content) = trimmed[level..].strip_prefix(' ') {
+                return CreoleLine::ListItem {
+                    ordered: marker == '#',
+                    level,
+                    content: parse_inline(content.trim_start()),
+                };
+            }
+        }
+    }
+
+    CreoleLine::Text(parse_inline(line))
+}
+
+/// Есть ли в тексте creole разметка или ссылки на спрайты
+///
+/// Текст без разметки выводится как есть, без разбора на фрагменты.
+pub fn has_markup(text: &str) -> bool {
+    text.split('\n')
+        .flat_map(|line| line.split("\\n"))
+        .any(|line| match parse_line(line) {
+            CreoleLine::Text(inlines) => match &inlines[..] {
+                [] => false,
+                [Inline::Text { text, style }] => !style.is_plain() || text != line,
+                _ => true,
+            },
+            _ => true,
+        })
+}
+
+/// Вики-маркеры, включающие стиль до парного маркера
+const WIKI_MARKERS: [&str; 6] = ["**", "//", "\"\"", "--", "__", "~~"];
+
+/// Разбирает разметку внутри строки
+pub fn parse_inline(line: &str) -> Vec<Inline> {
+    InlineParser::default().parse(line)
+}
+
+/// Состояние разбора строки
+#[derive(Default)]
+struct InlineParser {
+    inlines: Vec<Inline>,
+    text: String,
+    /// Стили открытых HTML-тегов: (тег, стиль до него)
+    tags: Vec<(String, TextStyle)>,
+    /// Стиль HTML-тегов
+    style: TextStyle,
+    /// Включённые вики-маркеры
+    wiki: [bool; WIKI_MARKERS.len()],
+}
+
+impl InlineParser {
+    fn parse(mut self, line: &str) -> Vec<Inline> {
+        let mut rest = line;
+        while let Some(c) = rest.chars().next() {
+            // `~` экранирует следующий символ
+            if c == '~' && !rest.starts_with("~~") {
+                let mut chars = rest[1..].chars();
+                match chars.next() {
+                    Some(escaped) => {
+                        self.text.push(escaped);
+                        rest = chars.as_str();
+                    }
+                    None => {
+                        self.text.push('~');
+                        rest = "";
+                    }
+                }
+                continue;
+            }
+
+            if let Some(after) = self.wiki_marker(rest, line) {
+                rest = after;
+                continue;
+            }
+            if let Some(after) = rest.strip_prefix("~~") {
+                self.text.push_str("~~");
+                rest = after;
+                continue;
+            }
+
+            if c == '<' && !self.monospace() {
+                if let Some(after) = self.tag(rest) {
+                    rest = after;
+                    continue;
+                }
+            }
+
+            self.text.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+        self.flush();
+        self.inlines
+    }
+
+    /// Внутри `""` разметка не действует
+    fn monospace(&self) -> bool {
+        self.wiki[2]
+    }
+
+    /// Переключает вики-маркер; парный маркер должен встречаться дальше в строке
+    fn wiki_marker<'a>(&mut self, rest: &'a str, line: &str) -> Option<&'a str> {
+        let index = WIKI_MARKERS.iter().position(|marker| rest.starts_with(marker))?;
+        if self.monospace() && index != 2 {
+            return None;
+        }
+        let marker = WIKI_MARKERS[index];
+        let after = &rest[marker.len()..];
+
+        if !self.wiki[index] {
+            // `//` в `http://` — не курсив
+            let position = line.len() - rest.len();
+            if index == 1 && line[..position].ends_with(':') {
+                return None;
+            }
+            if !after.contains(marker) {
+                return None;
+            }
+        }
+        self.flush();
+        self.wiki[index] = !self.wiki[index];
+        Some(after)
+    }
+
+    /// Разбирает HTML-тег или ссылку на спрайт; неизвестные теги — текст
+    fn tag<'a>(&mut self, rest: &'a str) -> Option<&'a str> {
+        let end = rest.find('>')?;
+        let tag = &rest[1..end];
+        let after = &rest[end + 1..];
+
+        if tag.starts_with('$') {
+            let reference = SpriteRef::parse(tag)?;
+            self.flush();
+            self.inlines.push(Inline::Sprite {
+                reference,
+                markup: rest[..=end].to_string(),
+            });
+            return Some(after);
+        }
+
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim().to_ascii_lowercase();
+            let index = self.tags.iter().rposition(|(open, _)| *open == name)?;
+            self.flush();
+            self.style = self.tags[index].1.clone();
+            self.tags.truncate(index);
+            return Some(after);
+        }
+
+        let (name, value) = match tag.split_once([':', ' ']) {
+            Some((name, value)) => (name, Some(value.trim())),
+            None => (tag, None),
+        };
+        let name = name.trim().to_ascii_lowercase();
+        let mut style = self.style.clone();
+        match (name.as_str(), value) {
+            ("b", None) => style.bold = true,
+            ("i", None) => style.italic = true,
+            ("u", None) => style.underline = true,
+            ("w", None) => style.wave = true,
+            ("s" | "strike" | "del", None) => style.strike = true,
+            ("color", Some(color)) => style.color = Some(color.to_string()),
+            ("back", Some(color)) => style.background = Some(color.to_string()),
+            ("size", Some(size)) => style.size = Some(size.parse().ok()?),
+            ("font", Some(attributes)) => {
+                let color = attributes
+                    .strip_prefix("color")?
+                    .trim_start()
+                    .strip_prefix('=')?
+                    .trim()
+                    .trim_matches(|c| c == '"' || c == '\'');
+                style.color = Some(color.to_string());
+            }
+            _ => return None,
+        }
+
+        self.flush();
+        self.tags.push((name, std::mem::replace(&mut self.style, style)));
+        Some(after)
+    }
+
+    /// Текущий стиль: HTML-теги и вики-маркеры
+    fn current_style(&self) -> TextStyle {
+        let mut style = self.style.clone();
+        let [bold, italic, monospace, strike, underline, wave] = self.wiki;
+        style.bold |= bold;
+        style.italic |= italic;
+        style.monospace |= monospace;
+        style.strike |= strike;
+        style.underline |= underline;
+        style.wave |= wave;
+        style
+    }
+
+    /// Завершает текущий фрагмент текста
+    fn flush(&mut self) {
+        if self.text.is_empty() {
+            return;
+        }
+        let text = std::mem::take(&mut self.text);
+        let style = self.current_style();
+        match self.inlines.last_mut() {
+            Some(Inline::Text { text: last, style: last_style }) if *last_style == style => {
+                last.push_str(&text)
+            }
+            _ => self.inlines.push(Inline::Text { text, style }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(text: &str, style: TextStyle) -> Inline {
+        Inline::Text {
+            text: text.to_string(),
+            style,
+        }
+    }
+
+    #[test]
+    fn test_wiki_markup() {
+        let bold = TextStyle {
+            bold: true,
+            ..TextStyle::default()
+        };
+        let bold_italic = TextStyle {
+            italic: true,
+            ..bold.clone()
+        };
+        assert_eq!(
+            parse_inline("a **b //c//** d"),
+            vec![
+                text("a ", TextStyle::default()),
+                text("b ", bold),
+                text("c", bold_italic),
+                text(" d", TextStyle::default()),
+            ]
+        );
+
+        // Непарные маркеры, URL и экранирование остаются текстом
+        for plain in ["a -- b", "see http://example.com//x", "x ~**y**", "a ~~ b"] {
+            assert_eq!(parse_inline(plain).len(), 1, "{}", plain);
+        }
+        assert_eq!(parse_inline("x ~**y**"), vec![text("x **y**", TextStyle::default())]);
+        assert_eq!(
+            parse_inline("\"\"a **b**\"\""),
+            vec![text(
+                "a **b**",
+                TextStyle {
+                    monospace: true,
+                    ..TextStyle::default()
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn test_html_tags() {
+        let inlines = parse_inline("<color:red>R <size:18>big</size></color> <b>b</b> <<x>>");
+        let red = TextStyle {
+            color: Some("red".to_string()),
+            ..TextStyle::default()
+        };
+        assert_eq!(
+            inlines,
+            vec![
+                text("R ", red.clone()),
+                text(
+                    "big",
+                    TextStyle {
+                        size: Some(18.0),
+                        ..red
+                    }
+                ),
+                text(" ", TextStyle::default()),
+                text(
+                    "b",
+                    TextStyle {
+                        bold: true,
+                        ..TextStyle::default()
+                    }
+                ),
+                text(" <<x>>", TextStyle::default()),
+            ]
+        );
+
+        let font = parse_inline("<font color=\"blue\">x</font>");
+        assert!(matches!(&font[0], Inline::Text { style, .. } if style.color.as_deref() == Some("blue")));
+    }
+
+    #[test]
+    fn test_sprite_inline() {
+        let inlines = parse_inline("<$db{scale=2}> DB");
+        assert!(matches!(
+            &inlines[0],
+            Inline::Sprite { reference, markup } if reference.scale == 2.0 && markup == "<$db{scale=2}>"
+        ));
+        assert_eq!(inlines[1], text(" DB", TextStyle::default()));
+    }
+
+    #[test]
+    fn test_block_lines() {
+        assert_eq!(parse_line("----"), CreoleLine::Rule);
+        assert_eq!(parse_line("  ===="), CreoleLine::Rule);
+        assert!(matches!(
+            parse_line("** nested"),
+            CreoleLine::ListItem { ordered: false, level: 2, .. }
+        ));
+        assert!(matches!(parse_line("# one"), CreoleLine::ListItem { ordered: true, level: 1, .. }));
+        assert!(matches!(parse_line("**bold**"), CreoleLine::Text(_)));
+
+        let CreoleLine::TableRow(cells) = parse_line("|= A | b |") else {
+            panic!("ожидается строка таблицы");
+        };
+        assert_eq!(cells.len(), 2);
+        assert!(cells[0].header && !cells[1].header);
+        assert_eq!(cells[1].content, vec![text("b", TextStyle::default())]);
+    }
+
+    #[test]
+    fn test_has_markup() {
+        assert!(!has_markup("Alice -> Bob\\nsecond -- line"));
+        assert!(!has_markup("<<stereotype>>"));
+        assert!(has_markup("plain\n**bold**"));
+        assert!(has_markup("* item"));
+        assert!(has_markup("<$icon>"));
+    }
+}
@@ -25,6 +25,14 @@ pub struct GanttDiagram {
     pub closed_days: Vec<ClosedDay>,
     /// Праздники
     pub holidays: Vec<Holiday>,
+    /// Сегодняшняя дата (`today is 2024-01-10`) — подсвечивается на шкале
+    pub today: Option<GanttDate>,
+    /// Цвет сегодняшнего дня (`and is colored in #AAF`)
+    pub today_color: Option<Color>,
+    /// `hide resources names` — не подписывать ресурсы у баров задач
+    pub hide_resource_names: bool,
+    /// `hide resources footbox` — не показывать загрузку ресурсов под диаграммой
+    pub hide_resource_load: bool,
 }
 
 impl GanttDiagram {
@@ -42,6 +50,13 @@ impl GanttDiagram {
     pub fn add_milestone(&mut self, milestone: GanttMilestone) {
         self.milestones.push(milestone);
     }
+
+    /// Находит задачу по алиасу или имени
+    pub fn find_task_mut(&mut self, id: &str) -> Option<&mut GanttTask> {
+        self.tasks
+            .iter_mut()
+            .find(|task| task.id.as_deref() == Some(id) || task.name == id)
+    }
 }
 
 /// Масштаб диаграммы Ганта
@@ -75,7 +90,7 @@ impl GanttScale {
 }
 
 /// Дата в формате Gantt
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct GanttDate {
     pub year: i32,
     pub month: u32,
@@ -126,9 +141,10 @@ pub struct GanttTask {
     pub complete: Option<u8>,
     /// Цвет задачи
     pub color: Option<Color>,
-    /// Ресурс (исполнитель)
-    pub resource: Option<String>,
-    /// Ссылки на другие задачи (зависимости)
+    /// Ресурсы (исполнители) с долей занятости
+    pub resources: Vec<GanttResource>,
+    /// Задачи, которые должны завершиться до начала этой (`requires [T1]`,
+    /// `[T1] -> [T2]`)
     pub links: Vec<String>,
     /// Задача активна/неактивна
     pub is_active: bool,
@@ -145,7 +161,7 @@ impl GanttTask {
             duration: TaskDuration::Days(1),
             complete: None,
             color: None,
-            resource: None,
+            resources: Vec::new(),
             links: Vec::new(),
             is_active: true,
         }
@@ -176,6 +192,41 @@ impl GanttTask {
     }
 }
 
+/// Ресурс задачи: `on {Alice}`, `on {Bob:50%}`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GanttResource {
+    /// Имя исполнителя
+    pub name: String,
+    /// Доля рабочего дня, которую исполнитель отдаёт задаче (в процентах)
+    pub percent: u32,
+}
+
+impl GanttResource {
+    /// Создаёт ресурс с полной занятостью
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            percent: 100,
+        }
+    }
+
+    /// Парсит ресурс из содержимого фигурных скобок: `Alice` или `Alice:50%`
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let resource = match s.rsplit_once(':') {
+            Some((name, percent)) => match percent.trim().strip_suffix('%') {
+                Some(value) => Self {
+                    name: name.trim().to_string(),
+                    percent: value.trim().parse().ok()?,
+                },
+                None => Self::new(s),
+            },
+            None => Self::new(s),
+        };
+        (!resource.name.is_empty()).then_some(resource)
+    }
+}
+
 /// Начало задачи
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TaskStart {
@@ -185,7 +236,8 @@ pub enum TaskStart {
     After(String),
     /// Начинается с указанной даты
     AtDate(GanttDate),
-    /// Начинается одновременно с указанной задачей
+    /// Начинается одновременно с указанной задачей (`starts with [T1]`,
+    /// `starts at [T1]'s start`)
     With(String),
     /// Начинается в конце указанной задачи
     AtEnd(String),
@@ -275,6 +327,8 @@ pub enum MilestoneTime {
     AfterPrevious,
     /// После указанной задачи
     After(String),
+    /// В начале указанной задачи
+    AtStart(String),
     /// В указанную дату
     AtDate(GanttDate),
 }
@@ -381,6 +435,17 @@ mod tests {
         assert_eq!(Weekday::parse("Mon"), Some(Weekday::Monday));
     }
 
+    #[test]
+    fn test_resource_parse() {
+        assert_eq!(GanttResource::parse("Alice"), Some(GanttResource::new("Alice")));
+        let bob = GanttResource::parse(" Bob : 50% ").unwrap();
+        assert_eq!(bob.name, "Bob");
+        assert_eq!(bob.percent, 50);
+        // Двоеточие без процентов — часть имени
+        assert_eq!(GanttResource::parse("Bob:Developer").unwrap().name, "Bob:Developer");
+        assert_eq!(GanttResource::parse(""), None);
+    }
+
     #[test]
     fn test_scale_parse() {
         assert_eq!(GanttScale::parse("daily"), Some(GanttScale::Daily));
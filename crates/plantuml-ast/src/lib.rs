@@ -8,6 +8,7 @@
 pub mod activity;
 pub mod class;
 pub mod common;
+pub mod creole;
 pub mod component;
 pub mod diagram;
 pub mod er;
@@ -19,8 +20,10 @@ pub mod sequence;
 pub mod state;
 pub mod timing;
 pub mod usecase;
+pub mod visibility;
 pub mod network;
 pub mod salt;
+pub mod sprite;
 pub mod wbs;
 pub mod yaml;
 
@@ -5,7 +5,7 @@
 
 use serde::{Deserialize, Serialize};
 
-use crate::common::{Color, DiagramMetadata, Note, Stereotype};
+use crate::common::{Color, DiagramMetadata, Direction, Note, Stereotype};
 
 /// Диаграмма объектов
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -119,6 +119,10 @@ pub struct ObjectLink {
     pub label: Option<String>,
     /// Тип связи
     pub link_type: ObjectLinkType,
+    /// Подсказка размещения (`-up->`): где `to` относительно `from`
+    pub direction: Option<Direction>,
+    /// Скрытая связь (`-[hidden]->`): влияет на layout, но не рисуется
+    pub hidden: bool,
 }
 
 impl ObjectLink {
@@ -129,6 +133,8 @@ impl ObjectLink {
             to: to.into(),
             label: None,
             link_type: ObjectLinkType::Association,
+            direction: None,
+            hidden: false,
         }
     }
 
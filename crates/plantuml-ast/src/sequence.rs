@@ -2,7 +2,7 @@
 
 use serde::{Deserialize, Serialize};
 
-use crate::common::{Color, DiagramMetadata, Identifier, LineStyle, Note, Stereotype};
+use crate::common::{Color, DiagramMetadata, Identifier, LineStyle, Link, Note, Span, Stereotype};
 
 /// Диаграмма последовательностей
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -15,6 +15,37 @@ pub struct SequenceDiagram {
     pub elements: Vec<SequenceElement>,
     /// Box группировки участников
     pub boxes: Vec<ParticipantBox>,
+    /// Настройки отображения (`hide footbox`, skinparam)
+    pub display: SequenceDisplay,
+}
+
+/// Настройки отображения sequence диаграммы
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SequenceDisplay {
+    /// `hide footbox` — без нижних блоков участников
+    pub hide_footbox: bool,
+    /// `hide unlinked` — без участников, не задействованных в сообщениях
+    pub hide_unlinked: bool,
+    /// `skinparam maxMessageSize` — ширина, после которой подпись переносится
+    pub max_message_size: Option<f64>,
+    /// `skinparam responseMessageBelowArrow true` — подпись ответа под стрелкой
+    pub response_message_below_arrow: bool,
+    /// `skinparam lifelineStrategy solid` — сплошные линии жизни
+    pub solid_lifelines: bool,
+}
+
+impl SequenceDisplay {
+    /// Применяет `skinparam` отображения; возвращает false для чужих параметров
+    pub fn apply_skinparam(&mut self, name: &str, value: &str) -> bool {
+        let value = value.trim();
+        match name.to_ascii_lowercase().as_str() {
+            "maxmessagesize" => self.max_message_size = value.parse().ok().filter(|size| *size > 0.0),
+            "responsemessagebelowarrow" => self.response_message_below_arrow = value.eq_ignore_ascii_case("true"),
+            "lifelinestrategy" => self.solid_lifelines = value.eq_ignore_ascii_case("solid"),
+            _ => return false,
+        }
+        true
+    }
 }
 
 impl SequenceDiagram {
@@ -37,6 +68,170 @@ impl SequenceDiagram {
     pub fn add_box(&mut self, participant_box: ParticipantBox) {
         self.boxes.push(participant_box);
     }
+
+    /// Разбивает диаграмму на страницы по `newpage`
+    ///
+    /// Каждая страница содержит всех участников диаграммы (включая неявно
+    /// объявленные сообщениями), поэтому заголовки участников повторяются.
+    /// Заголовок из `newpage title` заменяет общий заголовок страницы,
+    /// а нумерация autonumber продолжается с предыдущей страницы.
+    /// Диаграмма без `newpage` возвращается одной страницей.
+    pub fn split_pages(&self) -> Vec<SequenceDiagram> {
+        if !self
+            .elements
+            .iter()
+            .any(|e| matches!(e, SequenceElement::NewPage(_)))
+        {
+            return vec![self.clone()];
+        }
+
+        let participants = self.all_participants();
+        let new_page = |title: Option<&String>, elements: Vec<SequenceElement>| {
+            let mut metadata = self.metadata.clone();
+            if let Some(title) = title {
+                metadata.title = Some(title.clone());
+            }
+            SequenceDiagram {
+                metadata,
+                participants: participants.clone(),
+                elements,
+                boxes: self.boxes.clone(),
+                display: self.display.clone(),
+            }
+        };
+
+        let mut pages = Vec::new();
+        let mut numbering = AutonumberState::default();
+        let mut page_title: Option<&String> = None;
+        let mut current = Vec::new();
+
+        for element in &self.elements {
+            if let SequenceElement::NewPage(title) = element {
+                pages.push(new_page(page_title, std::mem::take(&mut current)));
+                current.extend(numbering.restore());
+                page_title = title.as_ref();
+                continue;
+            }
+            numbering.track(element);
+            current.push(element.clone());
+        }
+        pages.push(new_page(page_title, current));
+
+        pages
+    }
+
+    /// Все сообщения, включая вложенные во фрагменты, в порядке появления
+    pub fn messages(&self) -> Vec<&Message> {
+        fn collect<'a>(elements: &'a [SequenceElement], out: &mut Vec<&'a Message>) {
+            for element in elements {
+                match element {
+                    SequenceElement::Message(msg) => out.push(msg),
+                    SequenceElement::Fragment(frag) => {
+                        for section in &frag.sections {
+                            collect(&section.elements, out);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut messages = Vec::new();
+        collect(&self.elements, &mut messages);
+        messages
+    }
+
+    /// Все участники: объявленные и неявные (из сообщений) в порядке появления
+    fn all_participants(&self) -> Vec<Participant> {
+        let mut participants = self.participants.clone();
+        let mut known: Vec<String> = participants
+            .iter()
+            .map(|p| p.id.alias.clone().unwrap_or_else(|| p.id.name.clone()))
+            .collect();
+
+        fn collect(elements: &[SequenceElement], known: &mut Vec<String>, out: &mut Vec<Participant>) {
+            for element in elements {
+                match element {
+                    SequenceElement::Message(msg) => {
+                        for name in msg.participants() {
+                            if !known.iter().any(|k| k == name) {
+                                known.push(name.to_string());
+                                out.push(Participant::as_participant(name));
+                            }
+                        }
+                    }
+                    SequenceElement::Fragment(frag) => {
+                        for section in &frag.sections {
+                            collect(&section.elements, known, out);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        collect(&self.elements, &mut known, &mut participants);
+        participants
+    }
+}
+
+/// Состояние autonumber при разбиении на страницы
+#[derive(Debug, Default)]
+struct AutonumberState {
+    enabled: bool,
+    next: u32,
+    step: u32,
+    format: Option<String>,
+}
+
+impl AutonumberState {
+    /// Учитывает элемент страницы (команды autonumber и пронумерованные сообщения)
+    fn track(&mut self, element: &SequenceElement) {
+        match element {
+            SequenceElement::Autonumber(AutonumberCommand::Start(params)) => {
+                self.enabled = true;
+                self.next = params.start.unwrap_or(1);
+                self.step = params.step.unwrap_or(1);
+                self.format = params.format.clone();
+            }
+            SequenceElement::Autonumber(AutonumberCommand::Stop) => self.enabled = false,
+            SequenceElement::Autonumber(AutonumberCommand::Resume(params)) => {
+                self.enabled = true;
+                if let Some(p) = params {
+                    self.next = p.start.unwrap_or(self.next);
+                    self.step = p.step.unwrap_or(self.step);
+                    if p.format.is_some() {
+                        self.format = p.format.clone();
+                    }
+                }
+            }
+            SequenceElement::Message(_) if self.enabled => {
+                self.next += self.step;
+            }
+            SequenceElement::Fragment(frag) => {
+                for section in &frag.sections {
+                    for inner in &section.elements {
+                        self.track(inner);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Команды, восстанавливающие нумерацию в начале новой страницы
+    fn restore(&self) -> Vec<SequenceElement> {
+        // autonumber ещё не встречался
+        if self.step == 0 {
+            return Vec::new();
+        }
+        let start = AutonumberStart::new(Some(self.next), Some(self.step), self.format.clone());
+        let mut commands = vec![SequenceElement::Autonumber(AutonumberCommand::Start(start))];
+        if !self.enabled {
+            commands.push(SequenceElement::Autonumber(AutonumberCommand::Stop));
+        }
+        commands
+    }
 }
 
 /// Тип участника диаграммы
@@ -83,6 +278,10 @@ pub struct Participant {
     pub color: Option<Color>,
     /// Порядок (для явного указания позиции)
     pub order: Option<i32>,
+    /// Гиперссылка (`[[url]]`)
+    pub link: Option<Link>,
+    /// Позиция объявления в исходнике
+    pub span: Span,
 }
 
 impl Participant {
@@ -94,6 +293,8 @@ impl Participant {
             stereotype: None,
             color: None,
             order: None,
+            link: None,
+            span: Span::empty(),
         }
     }
 
@@ -131,6 +332,10 @@ pub enum SequenceElement {
     Autonumber(AutonumberCommand),
     /// Return (возврат к вызывающему)
     Return(Return),
+    /// Разрыв страницы (`newpage [title]`)
+    NewPage(Option<String>),
+    /// Длительность между якорями (`{start} <-> {end} : текст`)
+    Duration(Duration),
 }
 
 /// Тип стрелки сообщения
@@ -151,36 +356,131 @@ pub enum ArrowType {
     HalfBottom,
 }
 
+/// Сторона диаграммы для сообщений с границы
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EndpointSide {
+    /// Слева от участников
+    Left,
+    /// Справа от участников
+    Right,
+}
+
+/// Конец сообщения
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MessageEndpoint {
+    /// Участник (имя или алиас)
+    Participant(String),
+    /// Рамка диаграммы: `[-> A`, `A ->]`
+    Border(EndpointSide),
+    /// Короткая стрелка рядом с участником: `?-> A`, `A ->?`
+    Short(EndpointSide),
+}
+
+impl MessageEndpoint {
+    /// Имя участника, если конец — участник
+    pub fn participant(&self) -> Option<&str> {
+        match self {
+            Self::Participant(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for MessageEndpoint {
+    fn from(name: &str) -> Self {
+        Self::Participant(name.to_string())
+    }
+}
+
+impl From<String> for MessageEndpoint {
+    fn from(name: String) -> Self {
+        Self::Participant(name)
+    }
+}
+
+impl From<&String> for MessageEndpoint {
+    fn from(name: &String) -> Self {
+        Self::Participant(name.clone())
+    }
+}
+
+impl PartialEq<&str> for MessageEndpoint {
+    fn eq(&self, other: &&str) -> bool {
+        self.participant() == Some(*other)
+    }
+}
+
+impl std::fmt::Display for MessageEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Participant(name) => f.write_str(name),
+            Self::Border(EndpointSide::Left) => f.write_str("["),
+            Self::Border(EndpointSide::Right) => f.write_str("]"),
+            Self::Short(_) => f.write_str("?"),
+        }
+    }
+}
+
+/// Украшение конца сообщения
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EndpointDecoration {
+    /// Кружок: `o->`, `->o`
+    Circle,
+    /// Крест: `x->`, `->x`
+    Cross,
+}
+
 /// Сообщение между участниками
+///
+/// `from` — всегда отправитель, `to` — получатель (головка стрелки),
+/// даже если в исходнике стрелка записана налево (`A <- B`).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
     /// Отправитель
-    pub from: String,
+    pub from: MessageEndpoint,
     /// Получатель
-    pub to: String,
+    pub to: MessageEndpoint,
     /// Текст сообщения
     pub label: String,
     /// Стиль линии
     pub line_style: LineStyle,
     /// Тип стрелки
     pub arrow_type: ArrowType,
-    /// Направление стрелки (true = влево)
+    /// Стрелка записана налево (`A <- B`): получатель стоит первым
     pub arrow_left: bool,
-    /// Цвет стрелки
+    /// Украшение у отправителя (`o->`, `x->`); у получателя — `arrow_type`
+    pub source_decoration: Option<EndpointDecoration>,
+    /// Цвет стрелки (`-[#red]>`)
     pub color: Option<Color>,
     /// Активировать получателя
     pub activate: bool,
+    /// Цвет активации получателя (`B++ #FFBBBB`)
+    pub activation_color: Option<Color>,
     /// Деактивировать отправителя
     pub deactivate: bool,
     /// Создать нового участника
     pub create: bool,
     /// Уничтожить участника
     pub destroy: bool,
+    /// Гиперссылка (`[[url]]` в тексте)
+    pub link: Option<Link>,
+    /// Отправляется одновременно с предыдущим сообщением (`& A -> B`)
+    pub parallel: bool,
+    /// Наклон стрелки: получатель ниже отправителя на столько пикселей (`->(10)`)
+    pub slant: Option<u32>,
+    /// Якорь для отметок длительности (`{start} A -> B`)
+    pub anchor: Option<String>,
+    /// Позиция в исходнике
+    pub span: Span,
 }
 
 impl Message {
     /// Создаёт новое сообщение
-    pub fn new(from: impl Into<String>, to: impl Into<String>, label: impl Into<String>) -> Self {
+    pub fn new(
+        from: impl Into<MessageEndpoint>,
+        to: impl Into<MessageEndpoint>,
+        label: impl Into<String>,
+    ) -> Self {
         Self {
             from: from.into(),
             to: to.into(),
@@ -188,14 +488,36 @@ impl Message {
             line_style: LineStyle::Solid,
             arrow_type: ArrowType::Normal,
             arrow_left: false,
+            source_decoration: None,
             color: None,
             activate: false,
+            activation_color: None,
             deactivate: false,
             create: false,
             destroy: false,
+            link: None,
+            parallel: false,
+            slant: None,
+            anchor: None,
+            span: Span::empty(),
         }
     }
 
+    /// Участники сообщения в порядке записи в исходнике
+    pub fn participants(&self) -> impl Iterator<Item = &str> {
+        let (first, second) = if self.arrow_left {
+            (&self.to, &self.from)
+        } else {
+            (&self.from, &self.to)
+        };
+        [first, second].into_iter().filter_map(MessageEndpoint::participant)
+    }
+
+    /// Сообщение самому себе
+    pub fn is_self_message(&self) -> bool {
+        self.from.participant().is_some() && self.from == self.to
+    }
+
     /// Устанавливает пунктирную линию (return message)
     pub fn dashed(mut self) -> Self {
         self.line_style = LineStyle::Dashed;
@@ -317,6 +639,17 @@ pub struct Delay {
     pub text: Option<String>,
 }
 
+/// Длительность между сообщениями с якорями (`{start} <-> {end}`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Duration {
+    /// Якорь начала
+    pub from: String,
+    /// Якорь конца
+    pub to: String,
+    /// Подпись (время)
+    pub label: Option<String>,
+}
+
 /// Ссылка на другую диаграмму
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Reference {
@@ -419,6 +752,35 @@ mod tests {
         assert_eq!(return_msg.line_style, LineStyle::Dashed);
     }
 
+    #[test]
+    fn test_message_endpoints() {
+        let mut incoming = Message::new(MessageEndpoint::Border(EndpointSide::Left), "Bob", "in");
+        assert_eq!(incoming.participants().collect::<Vec<_>>(), vec!["Bob"]);
+        assert!(!incoming.is_self_message());
+
+        // `Bob <- Alice`: получатель записан первым
+        incoming.from = "Alice".into();
+        incoming.arrow_left = true;
+        assert_eq!(incoming.participants().collect::<Vec<_>>(), vec!["Bob", "Alice"]);
+
+        let lost = Message::new("Alice", MessageEndpoint::Short(EndpointSide::Right), "lost");
+        assert_eq!(lost.to.participant(), None);
+        assert_eq!(lost.to.to_string(), "?");
+    }
+
+    #[test]
+    fn test_display_skinparams() {
+        let mut display = SequenceDisplay::default();
+        assert!(display.apply_skinparam("maxMessageSize", "80"));
+        assert!(display.apply_skinparam("ResponseMessageBelowArrow", "true"));
+        assert!(display.apply_skinparam("lifelineStrategy", "solid"));
+        assert!(!display.apply_skinparam("backgroundColor", "#fff"));
+
+        assert_eq!(display.max_message_size, Some(80.0));
+        assert!(display.response_message_below_arrow);
+        assert!(display.solid_lifelines);
+    }
+
     #[test]
     fn test_create_diagram() {
         let mut diagram = SequenceDiagram::new();
@@ -431,4 +793,60 @@ mod tests {
         assert_eq!(diagram.participants.len(), 2);
         assert_eq!(diagram.elements.len(), 1);
     }
+
+    #[test]
+    fn test_split_pages_without_newpage() {
+        let mut diagram = SequenceDiagram::new();
+        diagram.add_element(SequenceElement::Message(Message::new("Alice", "Bob", "Hi")));
+
+        let pages = diagram.split_pages();
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].elements.len(), 1);
+    }
+
+    #[test]
+    fn test_split_pages_repeats_participants() {
+        let mut diagram = SequenceDiagram::new();
+        diagram.metadata.title = Some("Общий".to_string());
+        diagram.add_participant(Participant::actor("User"));
+        diagram.add_element(SequenceElement::Message(Message::new("User", "Api", "req")));
+        diagram.add_element(SequenceElement::NewPage(None));
+        diagram.add_element(SequenceElement::Message(Message::new("Api", "Db", "query")));
+        diagram.add_element(SequenceElement::NewPage(Some("Ответ".to_string())));
+        diagram.add_element(SequenceElement::Message(Message::new("Api", "User", "resp")));
+
+        let pages = diagram.split_pages();
+        assert_eq!(pages.len(), 3);
+
+        for page in &pages {
+            let names: Vec<_> = page.participants.iter().map(|p| p.id.name.as_str()).collect();
+            assert_eq!(names, vec!["User", "Api", "Db"]);
+            assert_eq!(page.elements.len(), 1);
+        }
+        assert_eq!(pages[0].participants[0].participant_type, ParticipantType::Actor);
+        assert_eq!(pages[1].metadata.title.as_deref(), Some("Общий"));
+        assert_eq!(pages[2].metadata.title.as_deref(), Some("Ответ"));
+    }
+
+    #[test]
+    fn test_split_pages_continues_autonumber() {
+        let mut diagram = SequenceDiagram::new();
+        diagram.add_element(SequenceElement::Autonumber(AutonumberCommand::Start(
+            AutonumberStart::new(Some(10), Some(5), None),
+        )));
+        diagram.add_element(SequenceElement::Message(Message::new("A", "B", "1")));
+        diagram.add_element(SequenceElement::Message(Message::new("B", "A", "2")));
+        diagram.add_element(SequenceElement::NewPage(None));
+        diagram.add_element(SequenceElement::Message(Message::new("A", "B", "3")));
+
+        let pages = diagram.split_pages();
+        match &pages[1].elements[0] {
+            SequenceElement::Autonumber(AutonumberCommand::Start(params)) => {
+                assert_eq!(params.start, Some(20));
+                assert_eq!(params.step, Some(5));
+            }
+            other => panic!("ожидался autonumber, получено {:?}", other),
+        }
+    }
 }
+
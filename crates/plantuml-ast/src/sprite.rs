@@ -0,0 +1,264 @@
+//! Спрайты: `sprite $name [WxH/16] {...}` и ссылки `<$name>` в подписях.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Спрайт, определённый в диаграмме
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sprite {
+    /// Имя без `$`
+    pub name: String,
+    /// Ширина в пикселях
+    pub width: u32,
+    /// Высота в пикселях
+    pub height: u32,
+    /// Содержимое
+    pub data: SpriteData,
+}
+
+/// Содержимое спрайта
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpriteData {
+    /// Оттенки серого: по уровню на пиксель, построчно
+    ///
+    /// Уровень 0 — прозрачный фон, `levels - 1` — цвет переднего плана.
+    Gray {
+        /// Число уровней (4, 8 или 16)
+        levels: u8,
+        /// Уровни пикселей, `width * height` значений
+        pixels: Vec<u8>,
+    },
+    /// SVG: содержимое `<svg>` и его `viewBox`
+    Svg {
+        /// `viewBox` (`"0 0 16 16"`); без атрибута — `"0 0 W H"`
+        view_box: String,
+        /// Разметка между `<svg ...>` и `</svg>`
+        content: String,
+    },
+}
+
+impl Sprite {
+    /// Уровень пикселя (0 для координат вне спрайта и SVG спрайтов)
+    pub fn gray(&self, x: u32, y: u32) -> u8 {
+        match &self.data {
+            SpriteData::Gray { pixels, .. } if x < self.width && y < self.height => {
+                pixels[(y * self.width + x) as usize]
+            }
+            _ => 0,
+        }
+    }
+}
+
+/// Таблица спрайтов диаграммы
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpriteTable {
+    sprites: BTreeMap<String, Sprite>,
+}
+
+impl SpriteTable {
+    /// Создаёт пустую таблицу
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Добавляет спрайт (заменяет одноимённый)
+    pub fn insert(&mut self, sprite: Sprite) {
+        self.sprites.insert(sprite.name.clone(), sprite);
+    }
+
+    /// Ищет спрайт по имени (с `$` или без)
+    pub fn get(&self, name: &str) -> Option<&Sprite> {
+        self.sprites.get(name.trim_start_matches('$'))
+    }
+
+    /// Нет ни одного спрайта
+    pub fn is_empty(&self) -> bool {
+        self.sprites.is_empty()
+    }
+
+    /// Количество спрайтов
+    pub fn len(&self) -> usize {
+        self.sprites.len()
+    }
+
+    /// Все спрайты по имени
+    pub fn iter(&self) -> impl Iterator<Item = &Sprite> {
+        self.sprites.values()
+    }
+
+    /// Добавляет спрайты другой таблицы
+    pub fn extend(&mut self, other: SpriteTable) {
+        self.sprites.extend(other.sprites);
+    }
+}
+
+/// Ссылка на спрайт в подписи: `<$name>`, `<$name{scale=2,color=red}>`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpriteRef {
+    /// Имя без `$`
+    pub name: String,
+    /// Масштаб
+    pub scale: f64,
+    /// Цвет переднего плана (для спрайтов в оттенках серого)
+    pub color: Option<String>,
+}
+
+impl SpriteRef {
+    /// Создаёт ссылку с масштабом 1
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            scale: 1.0,
+            color: None,
+        }
+    }
+
+    /// Разбирает содержимое `<...>`: `$name`, `$name{scale=2,color=red}`
+    /// или устаревшее `$name,scale=2,color=red`
+    pub fn parse(markup: &str) -> Option<Self> {
+        let markup = markup.trim().strip_prefix('$')?;
+        let name_end = markup
+            .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '.' | '-' | '/')))
+            .unwrap_or(markup.len());
+        let (name, rest) = markup.split_at(name_end);
+        if name.is_empty() {
+            return None;
+        }
+
+        let options = match rest.trim() {
+            "" => "",
+            rest => rest
+                .strip_prefix('{')
+                .and_then(|r| r.strip_suffix('}'))
+                .or_else(|| rest.strip_prefix(','))?,
+        };
+
+        let mut sprite = Self::new(name);
+        for option in options.split(',').filter(|o| !o.trim().is_empty()) {
+            let (key, value) = option.split_once('=')?;
+            match key.trim() {
+                "scale" => sprite.scale = value.trim().parse().ok()?,
+                "color" => sprite.color = Some(value.trim().to_string()),
+                _ => {}
+            }
+        }
+        Some(sprite)
+    }
+
+    /// Спрайт в стереотипе: `<<$name>>` даёт `$name`
+    pub fn from_stereotype(stereotype: &str) -> Option<Self> {
+        let stereotype = stereotype.trim();
+        let inner = stereotype
+            .strip_prefix('<')
+            .and_then(|s| s.strip_suffix('>'))
+            .unwrap_or(stereotype);
+        Self::parse(inner)
+    }
+}
+
+/// Часть подписи
+#[derive(Debug, Clone, PartialEq)]
+pub enum LabelPart<'a> {
+    /// Обычный текст
+    Text(&'a str),
+    /// Ссылка на спрайт
+    Sprite(SpriteRef),
+}
+
+/// Делит строку подписи на текст и ссылки на спрайты
+///
+/// `<...>`, не являющиеся ссылкой на спрайт, остаются текстом.
+pub fn split_sprite_refs(line: &str) -> Vec<LabelPart<'_>> {
+    let mut parts = Vec::new();
+    let mut text_start = 0;
+    let mut search = 0;
+
+    while let Some(offset) = line[search..].find("<$") {
+        let start = search + offset;
+        let Some(len) = line[start..].find('>') else {
+            break;
+        };
+        let end = start + len;
+        match SpriteRef::parse(&line[start + 1..end]) {
+            Some(sprite) => {
+                if text_start < start {
+                    parts.push(LabelPart::Text(&line[text_start..start]));
+                }
+                parts.push(LabelPart::Sprite(sprite));
+                text_start = end + 1;
+                search = end + 1;
+            }
+            None => search = start + 2,
+        }
+    }
+
+    if text_start < line.len() {
+        parts.push(LabelPart::Text(&line[text_start..]));
+    }
+    parts
+}
+
+/// Есть ли в тексте ссылки на спрайты
+pub fn has_sprite_refs(text: &str) -> bool {
+    text.contains("<$")
+        && split_sprite_refs(text)
+            .iter()
+            .any(|part| matches!(part, LabelPart::Sprite(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sprite_ref() {
+        assert_eq!(SpriteRef::parse("$EC2"), Some(SpriteRef::new("EC2")));
+
+        let sprite = SpriteRef::parse("$ma_account{scale=0.5,color=#ED7100}").unwrap();
+        assert_eq!(sprite.scale, 0.5);
+        assert_eq!(sprite.color.as_deref(), Some("#ED7100"));
+
+        let legacy = SpriteRef::parse("$pod,scale=2").unwrap();
+        assert_eq!((legacy.name.as_str(), legacy.scale), ("pod", 2.0));
+
+        assert!(SpriteRef::parse("b").is_none());
+        assert!(SpriteRef::parse("$x{scale=big}").is_none());
+    }
+
+    #[test]
+    fn test_split_sprite_refs() {
+        let parts = split_sprite_refs("<$EC2> Web <b>server</b> <$db{scale=2}>");
+        assert_eq!(
+            parts,
+            vec![
+                LabelPart::Sprite(SpriteRef::new("EC2")),
+                LabelPart::Text(" Web <b>server</b> "),
+                LabelPart::Sprite(SpriteRef {
+                    name: "db".to_string(),
+                    scale: 2.0,
+                    color: None,
+                }),
+            ]
+        );
+        assert!(has_sprite_refs("a <$b>"));
+        assert!(!has_sprite_refs("a <$ b> <c>"));
+    }
+
+    #[test]
+    fn test_table_lookup() {
+        let mut table = SpriteTable::new();
+        table.insert(Sprite {
+            name: "dot".to_string(),
+            width: 1,
+            height: 1,
+            data: SpriteData::Gray {
+                levels: 16,
+                pixels: vec![15],
+            },
+        });
+        assert_eq!(table.get("$dot").unwrap().gray(0, 0), 15);
+        assert_eq!(table.get("dot").unwrap().gray(1, 0), 0);
+        assert!(table.get("missing").is_none());
+    }
+}
@@ -2,7 +2,7 @@
 
 use serde::{Deserialize, Serialize};
 
-use crate::common::{Color, DiagramMetadata, Note, Stereotype};
+use crate::common::{Color, DiagramMetadata, Direction, LineStyle, Note, Stereotype};
 
 /// Диаграмма состояний
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -55,6 +55,12 @@ pub struct State {
     pub regions: Vec<Vec<State>>,
     /// Цвет
     pub color: Option<Color>,
+    /// Цвет границы (`#pink;line:red`)
+    pub border_color: Option<Color>,
+    /// Цвет текста (`#pink;text:blue`)
+    pub text_color: Option<Color>,
+    /// Стиль линии рамки (`#pink;line.dashed`)
+    pub line_style: Option<LineStyle>,
     /// Действие при входе
     pub entry_action: Option<String>,
     /// Действие при выходе
@@ -76,6 +82,9 @@ impl State {
             internal_transitions: Vec::new(),
             regions: Vec::new(),
             color: None,
+            border_color: None,
+            text_color: None,
+            line_style: None,
             entry_action: None,
             exit_action: None,
             do_action: None,
@@ -158,6 +167,12 @@ pub struct Transition {
     pub action: Option<String>,
     /// Цвет
     pub color: Option<Color>,
+    /// Стиль линии (`-[dashed]->`, `-[#red,bold]->`)
+    pub line_style: Option<LineStyle>,
+    /// Подсказка размещения (`-up->`): где `to` относительно `from`
+    pub direction: Option<Direction>,
+    /// Скрытый переход (`-[hidden]->`): влияет на layout, но не рисуется
+    pub hidden: bool,
 }
 
 impl Transition {
@@ -170,6 +185,9 @@ impl Transition {
             guard: None,
             action: None,
             color: None,
+            line_style: None,
+            direction: None,
+            hidden: false,
         }
     }
 
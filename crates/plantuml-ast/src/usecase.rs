@@ -2,7 +2,7 @@
 
 use serde::{Deserialize, Serialize};
 
-use crate::common::{Color, DiagramMetadata, Direction, Note, Stereotype};
+use crate::common::{Color, DiagramMetadata, Direction, Link, Note, Stereotype};
 
 /// Диаграмма вариантов использования
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -41,6 +41,8 @@ pub struct UseCaseActor {
     pub stereotype: Option<Stereotype>,
     /// Цвет
     pub color: Option<Color>,
+    /// Гиперссылка (`[[url]]`)
+    pub link: Option<Link>,
 }
 
 impl UseCaseActor {
@@ -51,6 +53,7 @@ impl UseCaseActor {
             alias: None,
             stereotype: None,
             color: None,
+            link: None,
         }
     }
 }
@@ -66,6 +69,8 @@ pub struct UseCase {
     pub stereotype: Option<Stereotype>,
     /// Цвет
     pub color: Option<Color>,
+    /// Гиперссылка (`[[url]]`)
+    pub link: Option<Link>,
 }
 
 impl UseCase {
@@ -76,6 +81,7 @@ impl UseCase {
             alias: None,
             stereotype: None,
             color: None,
+            link: None,
         }
     }
 }
@@ -105,6 +111,12 @@ pub struct UseCaseRelationship {
     pub relation_type: UseCaseRelationType,
     /// Метка
     pub label: Option<String>,
+    /// Цвет линии (`-[#red]->`)
+    pub color: Option<Color>,
+    /// Подсказка размещения (`-up->`): где `to` относительно `from`
+    pub direction: Option<Direction>,
+    /// Скрытая связь (`-[hidden]->`): влияет на layout, но не рисуется
+    pub hidden: bool,
 }
 
 impl UseCaseRelationship {
@@ -115,6 +127,9 @@ impl UseCaseRelationship {
             to: to.into(),
             relation_type: UseCaseRelationType::Association,
             label: None,
+            color: None,
+            direction: None,
+            hidden: false,
         }
     }
 
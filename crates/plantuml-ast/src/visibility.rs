@@ -0,0 +1,440 @@
+//! Правила видимости элементов: `hide`, `show`, `remove`, `restore`.
+//!
+//! Правила собираются парсером в порядке появления в исходнике и
+//! применяются к диаграмме после парсинга, до layout. Для каждого элемента
+//! побеждает последнее подходящее правило. `hide` и `remove` для целых
+//! элементов равнозначны: элемент и его связи удаляются из диаграммы.
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::Stereotype;
+
+/// Действие правила
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VisibilityAction {
+    /// `hide`
+    Hide,
+    /// `show`
+    Show,
+    /// `remove`
+    Remove,
+    /// `restore`
+    Restore,
+}
+
+impl VisibilityAction {
+    /// Парсит ключевое слово действия
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "hide" => Some(Self::Hide),
+            "show" => Some(Self::Show),
+            "remove" => Some(Self::Remove),
+            "restore" => Some(Self::Restore),
+            _ => None,
+        }
+    }
+
+    /// Скрывает ли действие выбранное
+    pub fn hides(self) -> bool {
+        matches!(self, Self::Hide | Self::Remove)
+    }
+}
+
+/// Какие элементы затрагивает правило
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ElementSelector {
+    /// Все элементы (`*` или селектор не указан)
+    All,
+    /// Имя или алиас, `*` — любая подстрока (`hide Foo`, `remove Api*`)
+    Name(String),
+    /// Стереотип (`hide <<Serializable>>`)
+    Stereotype(String),
+    /// Тег (`remove $internal`)
+    Tag(String),
+    /// Вид элемента (`hide class`, `hide interface`, `remove database`)
+    Kind(String),
+    /// Элементы без связей (`hide @unlinked`)
+    Unlinked,
+}
+
+impl ElementSelector {
+    /// Парсит селектор; ключевые слова видов проверяет `is_kind`
+    fn parse(s: &str) -> Option<Self> {
+        if s == "*" {
+            return Some(Self::All);
+        }
+        if s.eq_ignore_ascii_case("@unlinked") || s.eq_ignore_ascii_case("unlinked") {
+            return Some(Self::Unlinked);
+        }
+        if let Some(tag) = s.strip_prefix('$') {
+            return is_name(tag).then(|| Self::Tag(tag.to_string()));
+        }
+        if let Some(stereotype) = s.strip_prefix("<<").and_then(|s| s.strip_suffix(">>")) {
+            let stereotype = stereotype.trim();
+            return (!stereotype.is_empty()).then(|| Self::Stereotype(stereotype.to_string()));
+        }
+        if KINDS.contains(&s.to_lowercase().as_str()) {
+            return Some(Self::Kind(s.to_lowercase()));
+        }
+        let name = s
+            .strip_prefix('"')
+            .and_then(|s| s.strip_suffix('"'))
+            .unwrap_or(s);
+        (is_name(name) || s.starts_with('"')).then(|| Self::Name(name.to_string()))
+    }
+
+    /// Подходит ли элемент под селектор
+    ///
+    /// `linked` — есть ли у элемента связи в исходной диаграмме.
+    pub fn matches(&self, element: &impl Filterable, linked: bool) -> bool {
+        match self {
+            Self::All => true,
+            Self::Name(pattern) => element.names().iter().any(|name| wildcard_match(pattern, name)),
+            Self::Stereotype(name) => element
+                .stereotype()
+                .is_some_and(|s| s.names.iter().any(|n| n.trim() == name)),
+            Self::Tag(tag) => element.tags().iter().any(|t| t == tag),
+            Self::Kind(keyword) => element.is_kind(keyword),
+            Self::Unlinked => !linked,
+        }
+    }
+}
+
+/// Часть элемента, на которую действует правило
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ElementPart {
+    /// Элемент целиком
+    Element,
+    /// Поля и методы (`members`)
+    Members,
+    /// Поля (`fields`, `attributes`)
+    Fields,
+    /// Методы (`methods`)
+    Methods,
+    /// Иконка-кружок класса (`circle`)
+    Circle,
+    /// Подпись стереотипа (`stereotype`)
+    Stereotype,
+}
+
+impl ElementPart {
+    /// Парсит название части
+    fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "members" => Some(Self::Members),
+            "fields" | "attributes" => Some(Self::Fields),
+            "methods" => Some(Self::Methods),
+            "circle" | "circles" | "spot" => Some(Self::Circle),
+            "stereotype" | "stereotypes" => Some(Self::Stereotype),
+            _ => None,
+        }
+    }
+}
+
+/// Правило видимости: `hide <<Entity>> methods`, `remove $internal`, …
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VisibilityRule {
+    /// Действие
+    pub action: VisibilityAction,
+    /// Затрагиваемые элементы
+    pub selector: ElementSelector,
+    /// Затрагиваемая часть элемента
+    pub part: ElementPart,
+    /// Только пустые секции (`hide empty members`)
+    pub empty_only: bool,
+}
+
+impl VisibilityRule {
+    /// Создаёт правило для элементов целиком
+    pub fn new(action: VisibilityAction, selector: ElementSelector) -> Self {
+        Self {
+            action,
+            selector,
+            part: ElementPart::Element,
+            empty_only: false,
+        }
+    }
+
+    /// Устанавливает часть элемента
+    pub fn with_part(mut self, part: ElementPart) -> Self {
+        self.part = part;
+        self
+    }
+
+    /// Парсит строку `hide|show|remove|restore [empty] [селектор] [часть]`
+    ///
+    /// Строки, которые не похожи на правило (например, метод `show()` в
+    /// теле класса), не распознаются.
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut words = line.split_whitespace();
+        let action = VisibilityAction::parse(words.next()?)?;
+        let mut rest: Vec<&str> = words.collect();
+
+        let empty_only = rest.len() == 2 && rest[0].eq_ignore_ascii_case("empty");
+        if empty_only {
+            rest.remove(0);
+        }
+
+        let (selector, part) = match rest.as_slice() {
+            // `hide circle` — часть, а не вид `circle`
+            [word] => match ElementPart::parse(word) {
+                Some(part) => (ElementSelector::All, part),
+                None => (ElementSelector::parse(word)?, ElementPart::Element),
+            },
+            [selector, part] => (ElementSelector::parse(selector)?, ElementPart::parse(part)?),
+            _ => return None,
+        };
+
+        // `empty` имеет смысл только для секций
+        if empty_only && !matches!(part, ElementPart::Members | ElementPart::Fields | ElementPart::Methods) {
+            return None;
+        }
+
+        Some(Self {
+            action,
+            selector,
+            part,
+            empty_only,
+        })
+    }
+}
+
+/// Элемент диаграммы, к которому применяются правила видимости
+pub trait Filterable {
+    /// Имя и алиас элемента
+    fn names(&self) -> Vec<&str>;
+    /// Соответствует ли элемент ключевому слову вида (`class`, `database`)
+    fn is_kind(&self, keyword: &str) -> bool;
+    /// Стереотип элемента
+    fn stereotype(&self) -> Option<&Stereotype>;
+    /// Теги элемента (`$tag`)
+    fn tags(&self) -> &[String];
+}
+
+/// Удаляется ли элемент правилами (последнее подходящее правило побеждает)
+pub fn is_removed(rules: &[VisibilityRule], element: &impl Filterable, linked: bool) -> bool {
+    rules
+        .iter()
+        .rev()
+        .find(|rule| rule.part == ElementPart::Element && rule.selector.matches(element, linked))
+        .is_some_and(|rule| rule.action.hides())
+}
+
+/// Скрытые части элемента (`hide members`, `hide circle`, `hide stereotype`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HiddenParts {
+    /// Секция полей
+    pub fields: bool,
+    /// Секция методов
+    pub methods: bool,
+    /// Иконка-кружок
+    pub circle: bool,
+    /// Подпись стереотипа
+    pub stereotype: bool,
+}
+
+impl HiddenParts {
+    /// Вычисляет скрытые части по правилам
+    ///
+    /// `empty_fields`/`empty_methods` — пусты ли секции (для `hide empty …`).
+    pub fn resolve(
+        rules: &[VisibilityRule],
+        element: &impl Filterable,
+        linked: bool,
+        empty_fields: bool,
+        empty_methods: bool,
+    ) -> Self {
+        let mut hidden = Self::default();
+        for rule in rules.iter().filter(|rule| rule.selector.matches(element, linked)) {
+            let hides = rule.action.hides();
+            let fields = !rule.empty_only || empty_fields;
+            let methods = !rule.empty_only || empty_methods;
+            match rule.part {
+                ElementPart::Element => {}
+                ElementPart::Members => {
+                    if fields {
+                        hidden.fields = hides;
+                    }
+                    if methods {
+                        hidden.methods = hides;
+                    }
+                }
+                ElementPart::Fields if fields => hidden.fields = hides,
+                ElementPart::Methods if methods => hidden.methods = hides,
+                ElementPart::Fields | ElementPart::Methods => {}
+                ElementPart::Circle => hidden.circle = hides,
+                ElementPart::Stereotype => hidden.stereotype = hides,
+            }
+        }
+        hidden
+    }
+}
+
+/// Ключевые слова видов элементов классов и компонентов
+const KINDS: &[&str] = &[
+    "class", "interface", "abstract", "enum", "annotation", "entity", "package", "component",
+    "database", "queue", "folder", "frame", "cloud", "node", "rectangle", "actor", "artifact",
+    "file", "storage", "card", "hexagon", "stack", "port", "device", "agent", "control",
+    "boundary", "collections",
+];
+
+/// Имя элемента в правиле: буквы, цифры, `_`, `.` и `*`
+fn is_name(s: &str) -> bool {
+    !s.is_empty()
+        && s
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | '*'))
+}
+
+/// Сопоставление с шаблоном, где `*` — любая подстрока
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !text.starts_with(first) || text.len() < first.len() + last.len() || !text.ends_with(last) {
+        return false;
+    }
+
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Element {
+        name: &'static str,
+        kind: &'static str,
+        stereotype: Option<Stereotype>,
+        tags: Vec<String>,
+    }
+
+    impl Filterable for Element {
+        fn names(&self) -> Vec<&str> {
+            vec![self.name]
+        }
+
+        fn is_kind(&self, keyword: &str) -> bool {
+            self.kind == keyword
+        }
+
+        fn stereotype(&self) -> Option<&Stereotype> {
+            self.stereotype.as_ref()
+        }
+
+        fn tags(&self) -> &[String] {
+            &self.tags
+        }
+    }
+
+    fn element(name: &'static str) -> Element {
+        Element {
+            name,
+            kind: "class",
+            stereotype: None,
+            tags: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let rule = VisibilityRule::parse("hide empty members").unwrap();
+        assert_eq!(rule.selector, ElementSelector::All);
+        assert_eq!(rule.part, ElementPart::Members);
+        assert!(rule.empty_only);
+
+        let rule = VisibilityRule::parse("hide circle").unwrap();
+        assert_eq!(rule.part, ElementPart::Circle);
+
+        let rule = VisibilityRule::parse("hide <<Serializable>>").unwrap();
+        assert_eq!(rule.selector, ElementSelector::Stereotype("Serializable".into()));
+        assert_eq!(rule.part, ElementPart::Element);
+
+        let rule = VisibilityRule::parse("remove $internal").unwrap();
+        assert_eq!(rule.action, VisibilityAction::Remove);
+        assert_eq!(rule.selector, ElementSelector::Tag("internal".into()));
+
+        let rule = VisibilityRule::parse("show Foo fields").unwrap();
+        assert_eq!(rule.action, VisibilityAction::Show);
+        assert_eq!(rule.selector, ElementSelector::Name("Foo".into()));
+        assert_eq!(rule.part, ElementPart::Fields);
+
+        assert_eq!(
+            VisibilityRule::parse("hide class").unwrap().selector,
+            ElementSelector::Kind("class".into())
+        );
+        assert_eq!(
+            VisibilityRule::parse("hide @unlinked").unwrap().selector,
+            ElementSelector::Unlinked
+        );
+
+        // Не правила: методы и поля в теле класса
+        assert!(VisibilityRule::parse("show()").is_none());
+        assert!(VisibilityRule::parse("show : bool").is_none());
+        assert!(VisibilityRule::parse("hide").is_none());
+        assert!(VisibilityRule::parse("hide empty circle").is_none());
+    }
+
+    #[test]
+    fn test_last_matching_rule_wins() {
+        let rules = vec![
+            VisibilityRule::parse("remove *").unwrap(),
+            VisibilityRule::parse("restore Api*").unwrap(),
+        ];
+        assert!(is_removed(&rules, &element("Repository"), true));
+        assert!(!is_removed(&rules, &element("ApiController"), true));
+
+        let tagged = Element {
+            tags: vec!["internal".into()],
+            stereotype: Some(Stereotype::new("Entity")),
+            ..element("User")
+        };
+        let rules = vec![VisibilityRule::parse("remove $internal").unwrap()];
+        assert!(is_removed(&rules, &tagged, true));
+        let rules = vec![VisibilityRule::parse("hide <<Entity>>").unwrap()];
+        assert!(is_removed(&rules, &tagged, true));
+
+        let rules = vec![VisibilityRule::parse("hide @unlinked").unwrap()];
+        assert!(is_removed(&rules, &element("Lonely"), false));
+        assert!(!is_removed(&rules, &element("Lonely"), true));
+    }
+
+    #[test]
+    fn test_hidden_parts() {
+        let rules = vec![
+            VisibilityRule::parse("hide empty members").unwrap(),
+            VisibilityRule::parse("hide circle").unwrap(),
+            VisibilityRule::parse("show Foo fields").unwrap(),
+        ];
+
+        let parts = HiddenParts::resolve(&rules, &element("Bar"), true, true, false);
+        assert!(parts.fields);
+        assert!(!parts.methods);
+        assert!(parts.circle);
+
+        let parts = HiddenParts::resolve(&rules, &element("Foo"), true, true, true);
+        assert!(!parts.fields);
+        assert!(parts.methods);
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("Api*", "ApiController"));
+        assert!(wildcard_match("*Service", "UserService"));
+        assert!(wildcard_match("a*b*c", "aXbYc"));
+        assert!(!wildcard_match("a*b*c", "aXc"));
+        assert!(!wildcard_match("ab*ba", "aba"));
+        assert!(wildcard_match("Foo", "Foo"));
+        assert!(!wildcard_match("Foo", "Foobar"));
+    }
+}
@@ -0,0 +1,226 @@
+//! Разбор аргументов командной строки
+//!
+//! Флаги повторяют синтаксис `plantuml.jar`: однобуквенные опции с одним
+//! дефисом, формат вывода слитно (`-tsvg`), переменные в виде `-DNAME=value`.
+
+use std::path::PathBuf;
+
+use crate::CliError;
+
+/// Формат выходного файла
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// SVG (по умолчанию)
+    #[default]
+    Svg,
+    /// PNG (растеризация через resvg)
+    Png,
+}
+
+impl Format {
+    /// Расширение выходного файла
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Svg => "svg",
+            Format::Png => "png",
+        }
+    }
+}
+
+/// Параметры запуска рендеринга
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Args {
+    /// Формат вывода (`-tsvg`, `-tpng`)
+    pub format: Format,
+    /// Директория для результатов (`-o <dir>`)
+    pub output_dir: Option<PathBuf>,
+    /// Читать stdin и писать в stdout (`-pipe`)
+    pub pipe: bool,
+    /// Имя темы (`-theme <name>`)
+    pub theme: Option<String>,
+    /// Переменные препроцессора (`-D name=value`)
+    pub defines: Vec<(String, String)>,
+    /// Пути поиска для `!include` (`-I <dir>`)
+    pub include_paths: Vec<PathBuf>,
+    /// Файлы, директории и glob-шаблоны
+    pub inputs: Vec<String>,
+}
+
+/// Команда, выбранная аргументами
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Рендеринг диаграмм
+    Render(Args),
+    /// Вывод справки
+    Help,
+    /// Вывод версии
+    Version,
+}
+
+/// Текст справки
+pub const USAGE: &str = "\
+Использование: plantuml [опции] [файлы | директории | шаблоны]
+
+Опции:
+  -tsvg              Генерировать SVG (по умолчанию)
+  -tpng              Генерировать PNG
+  -o, -output <dir>  Директория для результатов (относительная — от файла-источника)
+  -pipe, -p          Читать диаграмму из stdin и писать результат в stdout
+  -theme <name>      Тема оформления (default, dark, minimal, sketchy, cerulean)
+  -D<name>=<value>   Определить переменную препроцессора (также -D name=value)
+  -I<dir>            Добавить путь поиска для !include (также -I dir)
+  -version           Показать версию
+  -h, -help          Показать эту справку
+
+Директории обрабатываются без рекурсии (*.puml, *.plantuml, *.pu, *.iuml, *.wsd).
+Встроенная stdlib (<C4/...>, <awslib/...>, <azure/...>, <kubernetes/...>, <material/...>)
+содержит только часть файлов этих библиотек; остальные ищутся в путях -I.
+Шаблоны поддерживают `*`, `?` и `**` (любая глубина вложенности).";
+
+/// Разбирает аргументы командной строки (без имени программы)
+pub fn parse_args<I>(args: I) -> Result<Command, CliError>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut parsed = Args::default();
+    let mut iter = args.into_iter();
+
+    while let Some(arg) = iter.next() {
+        if !arg.starts_with('-') || arg.len() == 1 {
+            parsed.inputs.push(arg);
+            continue;
+        }
+
+        // plantuml.jar принимает и `-flag`, и `--flag`
+        let flag = arg.strip_prefix("--").unwrap_or(&arg[1..]);
+
+        match flag {
+            "h" | "help" | "?" => return Ok(Command::Help),
+            "version" => return Ok(Command::Version),
+            "tsvg" => parsed.format = Format::Svg,
+            "tpng" => parsed.format = Format::Png,
+            "pipe" | "p" => parsed.pipe = true,
+            "o" | "output" => {
+                let dir = next_value(&mut iter, &arg)?;
+                parsed.output_dir = Some(PathBuf::from(dir));
+            }
+            "theme" => {
+                parsed.theme = Some(next_value(&mut iter, &arg)?);
+            }
+            _ if flag.starts_with('t') => {
+                return Err(CliError::Usage(format!(
+                    "неподдерживаемый формат вывода: {}",
+                    &flag[1..]
+                )));
+            }
+            _ if flag.starts_with('D') => {
+                let spec = match &flag[1..] {
+                    "" => next_value(&mut iter, &arg)?,
+                    inline => inline.to_string(),
+                };
+                parsed.defines.push(parse_define(&spec)?);
+            }
+            _ if flag.starts_with('I') => {
+                let path = match &flag[1..] {
+                    "" => next_value(&mut iter, &arg)?,
+                    inline => inline.to_string(),
+                };
+                parsed.include_paths.push(PathBuf::from(path));
+            }
+            _ => return Err(CliError::Usage(format!("неизвестная опция: {}", arg))),
+        }
+    }
+
+    Ok(Command::Render(parsed))
+}
+
+/// Берёт значение опции из следующего аргумента
+fn next_value(iter: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, CliError> {
+    iter.next()
+        .ok_or_else(|| CliError::Usage(format!("опция {} требует значение", flag)))
+}
+
+/// Разбирает `name=value` (или просто `name` — пустое значение)
+fn parse_define(spec: &str) -> Result<(String, String), CliError> {
+    let (name, value) = spec.split_once('=').unwrap_or((spec, ""));
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(CliError::Usage(format!("неверное определение переменной: -D{}", spec)));
+    }
+    Ok((name.to_string(), value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, CliError> {
+        parse_args(args.iter().map(|s| s.to_string()))
+    }
+
+    fn render_args(args: &[&str]) -> Args {
+        match parse(args).unwrap() {
+            Command::Render(args) => args,
+            other => panic!("ожидалась команда рендеринга, получено {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_defaults() {
+        let args = render_args(&["diagram.puml"]);
+        assert_eq!(args.format, Format::Svg);
+        assert_eq!(args.inputs, vec!["diagram.puml"]);
+        assert!(!args.pipe);
+    }
+
+    #[test]
+    fn test_format_and_output() {
+        let args = render_args(&["-tpng", "-o", "out", "a.puml", "b.puml"]);
+        assert_eq!(args.format, Format::Png);
+        assert_eq!(args.output_dir, Some(PathBuf::from("out")));
+        assert_eq!(args.inputs, vec!["a.puml", "b.puml"]);
+    }
+
+    #[test]
+    fn test_defines_inline_and_separate() {
+        let args = render_args(&["-DENV=prod", "-D", "DEBUG", "-D", "NAME=a=b"]);
+        assert_eq!(
+            args.defines,
+            vec![
+                ("ENV".to_string(), "prod".to_string()),
+                ("DEBUG".to_string(), String::new()),
+                ("NAME".to_string(), "a=b".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_include_paths() {
+        let args = render_args(&["-Ilib", "-I", "shared/styles"]);
+        assert_eq!(
+            args.include_paths,
+            vec![PathBuf::from("lib"), PathBuf::from("shared/styles")]
+        );
+    }
+
+    #[test]
+    fn test_pipe_and_theme() {
+        let args = render_args(&["-pipe", "-theme", "dark"]);
+        assert!(args.pipe);
+        assert_eq!(args.theme.as_deref(), Some("dark"));
+    }
+
+    #[test]
+    fn test_help_and_version() {
+        assert_eq!(parse(&["-help"]).unwrap(), Command::Help);
+        assert_eq!(parse(&["--version"]).unwrap(), Command::Version);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(parse(&["-tpdf"]), Err(CliError::Usage(_))));
+        assert!(matches!(parse(&["-o"]), Err(CliError::Usage(_))));
+        assert!(matches!(parse(&["-D=1"]), Err(CliError::Usage(_))));
+        assert!(matches!(parse(&["-unknown"]), Err(CliError::Usage(_))));
+    }
+}
@@ -0,0 +1,219 @@
+//! Раскрытие входных аргументов в список файлов
+//!
+//! Аргумент может быть файлом, директорией (берутся файлы диаграмм без
+//! рекурсии) или glob-шаблоном с `*`, `?` и `**`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::CliError;
+
+/// Расширения файлов, которые считаются исходниками диаграмм
+const DIAGRAM_EXTENSIONS: &[&str] = &["puml", "plantuml", "pu", "iuml", "wsd"];
+
+/// Раскрывает все аргументы в отсортированный список файлов без повторов
+pub fn expand_inputs(inputs: &[String]) -> Result<Vec<PathBuf>, CliError> {
+    let mut files = Vec::new();
+
+    for input in inputs {
+        let expanded = if is_glob(input) {
+            let matched = expand_glob(input)?;
+            if matched.is_empty() {
+                return Err(CliError::NotFound(input.clone()));
+            }
+            matched
+        } else {
+            let path = Path::new(input);
+            if path.is_dir() {
+                list_directory(path)?
+            } else if path.is_file() {
+                vec![path.to_path_buf()]
+            } else {
+                return Err(CliError::NotFound(input.clone()));
+            }
+        };
+
+        for file in expanded {
+            if !files.contains(&file) {
+                files.push(file);
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// Проверяет, содержит ли аргумент glob-символы
+fn is_glob(input: &str) -> bool {
+    input.contains(['*', '?'])
+}
+
+/// Проверяет расширение файла диаграммы
+fn is_diagram_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| DIAGRAM_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Файлы диаграмм в директории (без рекурсии)
+fn list_directory(dir: &Path) -> Result<Vec<PathBuf>, CliError> {
+    let mut files: Vec<PathBuf> = read_dir(dir)?
+        .into_iter()
+        .filter(|path| path.is_file() && is_diagram_file(path))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Раскрывает glob-шаблон
+///
+/// Ведущие компоненты без wildcard образуют базовую директорию, остаток
+/// сопоставляется с путями относительно неё.
+fn expand_glob(pattern: &str) -> Result<Vec<PathBuf>, CliError> {
+    let normalized = pattern.replace('\\', "/");
+    let components: Vec<&str> = normalized.split('/').collect();
+    let split = components
+        .iter()
+        .position(|c| is_glob(c))
+        .unwrap_or(components.len());
+
+    let base = match components[..split].join("/") {
+        b if b.is_empty() && normalized.starts_with('/') => PathBuf::from("/"),
+        b if b.is_empty() => PathBuf::from("."),
+        b => PathBuf::from(b),
+    };
+    let rest = components[split..].join("/");
+
+    // Без `**` глубина обхода ограничена числом компонентов шаблона
+    let max_depth = if rest.contains("**") {
+        usize::MAX
+    } else {
+        components.len() - split
+    };
+
+    let mut files = Vec::new();
+    walk(&base, &base, max_depth, &rest, &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+/// Рекурсивный обход директории с сопоставлением относительных путей
+fn walk(
+    base: &Path,
+    dir: &Path,
+    depth_left: usize,
+    pattern: &str,
+    files: &mut Vec<PathBuf>,
+) -> Result<(), CliError> {
+    if depth_left == 0 || !dir.is_dir() {
+        return Ok(());
+    }
+
+    for path in read_dir(dir)? {
+        if path.is_dir() {
+            walk(base, &path, depth_left - 1, pattern, files)?;
+        } else if let Ok(relative) = path.strip_prefix(base) {
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            if glob_match(pattern, &relative) {
+                files.push(path);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Читает содержимое директории
+fn read_dir(dir: &Path) -> Result<Vec<PathBuf>, CliError> {
+    let entries = fs::read_dir(dir).map_err(|e| CliError::Io {
+        path: dir.to_path_buf(),
+        source: e,
+    })?;
+    Ok(entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+}
+
+/// Сопоставляет путь с glob-шаблоном
+///
+/// `*` и `?` не пересекают `/`, `**` соответствует любому числу директорий.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    match_from(&pattern, &text)
+}
+
+fn match_from(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some('*') if pattern.get(1) == Some(&'*') => {
+            // `**/` может соответствовать и нулю директорий
+            let rest = &pattern[2..];
+            let rest_no_slash = rest.strip_prefix(&['/']).unwrap_or(rest);
+            (0..=text.len()).any(|i| match_from(rest, &text[i..]))
+                || match_from(rest_no_slash, text)
+        }
+        Some('*') => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != '/')
+            .any(|i| match_from(&pattern[1..], &text[i..])),
+        Some('?') => {
+            matches!(text.first(), Some(c) if *c != '/') && match_from(&pattern[1..], &text[1..])
+        }
+        Some(p) => text.first() == Some(p) && match_from(&pattern[1..], &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn touch(dir: &Path, name: &str) {
+        let path = dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "@startuml\n@enduml").unwrap();
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.puml", "a.puml"));
+        assert!(!glob_match("*.puml", "dir/a.puml"));
+        assert!(glob_match("**/*.puml", "a.puml"));
+        assert!(glob_match("**/*.puml", "x/y/a.puml"));
+        assert!(glob_match("docs/**", "docs/a/b.puml"));
+        assert!(glob_match("seq_?.pu", "seq_1.pu"));
+        assert!(!glob_match("seq_?.pu", "seq_12.pu"));
+    }
+
+    #[test]
+    fn test_expand_directory_non_recursive() {
+        let temp = TempDir::new().unwrap();
+        touch(temp.path(), "a.puml");
+        touch(temp.path(), "b.txt");
+        touch(temp.path(), "nested/c.puml");
+
+        let inputs = vec![temp.path().to_string_lossy().to_string()];
+        let files = expand_inputs(&inputs).unwrap();
+        assert_eq!(files, vec![temp.path().join("a.puml")]);
+    }
+
+    #[test]
+    fn test_expand_recursive_glob() {
+        let temp = TempDir::new().unwrap();
+        touch(temp.path(), "a.puml");
+        touch(temp.path(), "nested/c.puml");
+        touch(temp.path(), "nested/d.txt");
+
+        let pattern = format!("{}/**/*.puml", temp.path().display());
+        let files = expand_inputs(&[pattern]).unwrap();
+        assert_eq!(
+            files,
+            vec![temp.path().join("a.puml"), temp.path().join("nested/c.puml")]
+        );
+    }
+
+    #[test]
+    fn test_missing_input() {
+        let result = expand_inputs(&["/nonexistent/diagram.puml".to_string()]);
+        assert!(matches!(result, Err(CliError::NotFound(_))));
+    }
+}
@@ -0,0 +1,473 @@
+//! # plantuml
+//!
+//! Командная строка plantuml-rs, совместимая с основными флагами `plantuml.jar`.
+//!
+//! ```text
+//! plantuml -tpng -o out docs/**/*.puml
+//! cat diagram.puml | plantuml -pipe > diagram.svg
+//! ```
+//!
+//! Коды возврата: `0` — успех, `1` — ошибка в одной из диаграмм,
+//! `2` — неверные аргументы.
+
+mod args;
+mod inputs;
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use plantuml_core::{OutputFormat, RenderOptions, Theme};
+use thiserror::Error;
+
+use args::{Args, Command, Format};
+
+/// Ошибки командной строки
+#[derive(Error, Debug)]
+pub enum CliError {
+    /// Неверные аргументы
+    #[error("{0}")]
+    Usage(String),
+
+    /// Входной файл или шаблон не найден
+    #[error("{0}: файл не найден")]
+    NotFound(String),
+
+    /// Ошибка ввода-вывода
+    #[error("{}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    /// Ошибка рендеринга диаграммы
+    #[error("{}", diagnostic(path, *line, message))]
+    Render {
+        path: String,
+        line: Option<usize>,
+        message: String,
+    },
+}
+
+/// Форматирует диагностику в виде `file:line: message`
+fn diagnostic(path: &str, line: Option<usize>, message: &str) -> String {
+    match line {
+        Some(line) => format!("{}:{}: {}", path, line, message),
+        None => format!("{}: {}", path, message),
+    }
+}
+
+fn main() -> ExitCode {
+    let command = match args::parse_args(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("plantuml: {}", e);
+            eprintln!("Используйте -help для справки");
+            return ExitCode::from(2);
+        }
+    };
+
+    match command {
+        Command::Help => {
+            println!("{}", args::USAGE);
+            ExitCode::SUCCESS
+        }
+        Command::Version => {
+            println!("plantuml-rs {}", plantuml_core::version());
+            ExitCode::SUCCESS
+        }
+        Command::Render(args) => run(&args),
+    }
+}
+
+/// Выполняет рендеринг по разобранным аргументам
+fn run(args: &Args) -> ExitCode {
+    let options = match render_options(args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("plantuml: {}", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    if args.pipe {
+        return match run_pipe(&options) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("{}", e);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    if args.inputs.is_empty() {
+        eprintln!("plantuml: не указаны входные файлы");
+        eprintln!("Используйте -help для справки");
+        return ExitCode::from(2);
+    }
+
+    let files = match inputs::expand_inputs(&args.inputs) {
+        Ok(files) => files,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut failed = 0;
+    for file in &files {
+        if let Err(e) = render_file(file, args, &options) {
+            eprintln!("{}", e);
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        eprintln!("plantuml: ошибок: {} из {}", failed, files.len());
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Собирает опции рендеринга из аргументов
+fn render_options(args: &Args) -> Result<RenderOptions, CliError> {
+    let mut options = RenderOptions::new().with_format(match args.format {
+        Format::Svg => OutputFormat::Svg,
+        Format::Png => OutputFormat::Png,
+    });
+
+    if let Some(name) = &args.theme {
+        let theme = Theme::by_name(name)
+            .ok_or_else(|| CliError::Usage(format!("неизвестная тема: {}", name)))?;
+        options = options.with_theme(theme);
+    }
+
+    for (name, value) in &args.defines {
+        options = options.with_define(name.clone(), value.clone());
+    }
+    for path in &args.include_paths {
+        options = options.with_include_path(path.clone());
+    }
+
+    Ok(options)
+}
+
+/// Режим `-pipe`: stdin → stdout
+///
+/// Все страницы всех диаграмм выводятся подряд.
+fn run_pipe(options: &RenderOptions) -> Result<(), CliError> {
+    let stdin_path = PathBuf::from("<stdin>");
+    let mut source = String::new();
+    std::io::stdin()
+        .read_to_string(&mut source)
+        .map_err(|e| CliError::Io {
+            path: stdin_path.clone(),
+            source: e,
+        })?;
+
+    let base_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let pages = render_source(&source, &base_dir, options)
+        .map_err(|e| render_error("<stdin>", e))?;
+
+    let mut stdout = std::io::stdout();
+    for (_, bytes) in pages {
+        stdout.write_all(&bytes).map_err(|e| CliError::Io {
+            path: PathBuf::from("<stdout>"),
+            source: e,
+        })?;
+    }
+    Ok(())
+}
+
+/// Рендерит один файл и записывает результаты рядом или в `-o`
+///
+/// Каждая диаграмма и каждая страница (`newpage`) пишется в отдельный файл.
+fn render_file(file: &Path, args: &Args, options: &RenderOptions) -> Result<(), CliError> {
+    let source = std::fs::read_to_string(file).map_err(|e| CliError::Io {
+        path: file.to_path_buf(),
+        source: e,
+    })?;
+
+    let base_dir = file.parent().unwrap_or_else(|| Path::new("."));
+    let pages = render_source(&source, base_dir, options)
+        .map_err(|e| render_error(&file.display().to_string(), e))?;
+
+    let mut counters: HashMap<String, usize> = HashMap::new();
+    for (name, bytes) in pages {
+        let stem = name.unwrap_or_else(|| file_stem(file));
+        let counter = counters.entry(stem.clone()).or_insert(0);
+        let stem = page_stem(&stem, *counter);
+        *counter += 1;
+
+        let output = output_path(file, &stem, args.output_dir.as_deref(), args.format);
+        if let Some(dir) = output.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| CliError::Io {
+                path: dir.to_path_buf(),
+                source: e,
+            })?;
+        }
+        std::fs::write(&output, bytes).map_err(|e| CliError::Io {
+            path: output.clone(),
+            source: e,
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Рендерит исходник в байты выбранного формата
+///
+/// Возвращает по одной паре (имя блока, содержимое) на страницу.
+fn render_source(
+    source: &str,
+    base_dir: &Path,
+    options: &RenderOptions,
+) -> plantuml_core::Result<Vec<(Option<String>, Vec<u8>)>> {
+    let pages = plantuml_core::render_all_with_includes(source, base_dir, options)?;
+
+    pages
+        .into_iter()
+        .map(|page| Ok((page.name, plantuml_core::svg_to_bytes(page.svg, options)?)))
+        .collect()
+}
+
+/// Преобразует ошибку библиотеки в диагностику с именем файла
+///
+/// Ошибка во включённом файле указывает на него (путь как в `!include`).
+fn render_error(path: &str, e: plantuml_core::Error) -> CliError {
+    CliError::Render {
+        path: e.file().unwrap_or(path).to_string(),
+        line: e.line(),
+        message: e.message(),
+    }
+}
+
+/// Имя файла-источника без расширения
+fn file_stem(file: &Path) -> String {
+    file.file_stem()
+        .unwrap_or(file.as_os_str())
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Имя выходного файла для очередной страницы с тем же именем
+///
+/// Как и `plantuml.jar`: `seq`, `seq_001`, `seq_002`, …
+fn page_stem(stem: &str, index: usize) -> String {
+    if index == 0 {
+        stem.to_string()
+    } else {
+        format!("{}_{:03}", stem, index)
+    }
+}
+
+/// Путь выходного файла
+///
+/// Как и в `plantuml.jar`, относительная `-o` директория отсчитывается
+/// от директории файла-источника.
+fn output_path(file: &Path, stem: &str, output_dir: Option<&Path>, format: Format) -> PathBuf {
+    let source_dir = file.parent().unwrap_or_else(|| Path::new(""));
+    let dir = match output_dir {
+        Some(dir) if dir.is_absolute() => dir.to_path_buf(),
+        Some(dir) => source_dir.join(dir),
+        None => source_dir.to_path_buf(),
+    };
+
+    dir.join(format!("{}.{}", stem, format.extension()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_output_path() {
+        let file = Path::new("docs/seq.puml");
+        assert_eq!(
+            output_path(file, "seq", None, Format::Svg),
+            PathBuf::from("docs/seq.svg")
+        );
+        assert_eq!(
+            output_path(file, "seq", Some(Path::new("out")), Format::Png),
+            PathBuf::from("docs/out/seq.png")
+        );
+        assert_eq!(
+            output_path(file, "seq_001", Some(Path::new("/tmp/out")), Format::Svg),
+            PathBuf::from("/tmp/out/seq_001.svg")
+        );
+    }
+
+    #[test]
+    fn test_render_file_multiple_pages() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("flow.puml");
+        std::fs::write(
+            &file,
+            "@startuml\nAlice -> Bob\nnewpage\nBob -> Alice\n@enduml\n\
+             @startuml login\nUser -> App\n@enduml\n",
+        )
+        .unwrap();
+
+        let args = Args::default();
+        let options = render_options(&args).unwrap();
+        render_file(&file, &args, &options).unwrap();
+
+        assert!(temp.path().join("flow.svg").exists());
+        assert!(temp.path().join("flow_001.svg").exists());
+        assert!(temp.path().join("login.svg").exists());
+    }
+
+    #[test]
+    fn test_diagnostic_format() {
+        assert_eq!(diagnostic("a.puml", Some(3), "msg"), "a.puml:3: msg");
+        assert_eq!(diagnostic("a.puml", None, "msg"), "a.puml: msg");
+    }
+
+    #[test]
+    fn test_render_file_with_include_path() {
+        let temp = TempDir::new().unwrap();
+        let lib = temp.path().join("lib");
+        std::fs::create_dir_all(&lib).unwrap();
+        std::fs::write(lib.join("names.iuml"), "!$target = \"Bob\"\nparticipant Carol\n").unwrap();
+
+        let file = temp.path().join("seq.puml");
+        std::fs::write(
+            &file,
+            "@startuml\n!include names.iuml\nAlice -> $target: $greeting\n@enduml\n",
+        )
+        .unwrap();
+
+        let args = Args {
+            include_paths: vec![lib],
+            defines: vec![("$greeting".to_string(), "Hello".to_string())],
+            ..Args::default()
+        };
+        let options = render_options(&args).unwrap();
+        render_file(&file, &args, &options).unwrap();
+
+        let svg = std::fs::read_to_string(temp.path().join("seq.svg")).unwrap();
+        assert!(svg.contains("Bob"));
+        assert!(svg.contains("Hello"));
+        assert!(svg.contains(r#"data-source-line="3" id="msg_Alice_Bob""#), "{}", svg);
+    }
+
+    #[test]
+    fn test_render_file_reports_source_line_after_directives() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("err.puml");
+        std::fs::write(
+            &file,
+            "@startuml\n!$a = 1\nskinparam classBorderColor red\n!define B 2\nclass A {\n@enduml\n",
+        )
+        .unwrap();
+
+        let args = Args::default();
+        let options = render_options(&args).unwrap();
+        let err = render_file(&file, &args, &options).unwrap_err().to_string();
+
+        // Разбор останавливается на `@enduml` внутри незакрытого тела класса
+        let prefix = format!("{}:6: ошибка парсинга: ", file.display());
+        assert!(err.starts_with(&prefix), "{}", err);
+        assert!(!err.contains("в строке"), "{}", err);
+        assert!(!err.contains("-->"), "{}", err);
+    }
+
+    #[test]
+    fn test_render_file_reports_line_after_include_and_procedure() {
+        let temp = TempDir::new().unwrap();
+        std::fs::write(temp.path().join("inc.puml"), "class A\nclass B\nclass C\n").unwrap();
+        let args = Args::default();
+        let options = render_options(&args).unwrap();
+
+        let file = temp.path().join("inc_err.puml");
+        std::fs::write(&file, "@startuml\n!include inc.puml\nclass D\nclass E {\n@enduml\n")
+            .unwrap();
+        let err = render_file(&file, &args, &options).unwrap_err().to_string();
+        assert!(err.starts_with(&format!("{}:5: ", file.display())), "{}", err);
+
+        let file = temp.path().join("proc_err.puml");
+        std::fs::write(
+            &file,
+            "@startuml\n!procedure $two()\nclass P1\nclass P2\n!endprocedure\n$two()\n\
+             $two()\nclass E {\n@enduml\n",
+        )
+        .unwrap();
+        let err = render_file(&file, &args, &options).unwrap_err().to_string();
+        assert!(err.starts_with(&format!("{}:9: ", file.display())), "{}", err);
+    }
+
+    #[test]
+    fn test_render_file_reports_included_file() {
+        let temp = TempDir::new().unwrap();
+        std::fs::write(temp.path().join("broken.iuml"), "class A\nclass B <<\nclass C\n").unwrap();
+        let file = temp.path().join("main.puml");
+        std::fs::write(&file, "@startuml\n!include broken.iuml\nclass D\n@enduml\n").unwrap();
+
+        let args = Args::default();
+        let options = render_options(&args).unwrap();
+        let err = render_file(&file, &args, &options).unwrap_err().to_string();
+        assert!(err.starts_with("broken.iuml:2: "), "{}", err);
+    }
+
+    #[test]
+    fn test_render_file_reports_preprocessor_error_line() {
+        let temp = TempDir::new().unwrap();
+        let args = Args::default();
+        let options = render_options(&args).unwrap();
+
+        let file = temp.path().join("assert.puml");
+        std::fs::write(&file, "@startuml\nclass A\n!assert 1 == 2 : \"нет\"\n@enduml\n").unwrap();
+        let err = render_file(&file, &args, &options).unwrap_err().to_string();
+        let expected = format!(
+            "{}:3: ошибка препроцессора: !assert не выполнено: нет",
+            file.display()
+        );
+        assert_eq!(err, expected);
+
+        let file = temp.path().join("missing.puml");
+        std::fs::write(&file, "@startuml\n\n!include nope.puml\n@enduml\n").unwrap();
+        let err = render_file(&file, &args, &options).unwrap_err().to_string();
+        assert!(err.starts_with(&format!("{}:3: ", file.display())), "{}", err);
+
+        let file = temp.path().join("open_if.puml");
+        std::fs::write(&file, "@startuml\nclass A\n!if 1 == 1\nclass B\n@enduml\n").unwrap();
+        let err = render_file(&file, &args, &options).unwrap_err().to_string();
+        assert!(err.starts_with(&format!("{}:3: ", file.display())), "{}", err);
+        assert!(err.ends_with("!if 1 == 1 без !endif"), "{}", err);
+
+        // Файл известной библиотеки, которого нет во встроенной stdlib
+        let file = temp.path().join("athena.puml");
+        std::fs::write(&file, "@startuml\n!include <awslib/Analytics/Athena>\n@enduml\n").unwrap();
+        let err = render_file(&file, &args, &options).unwrap_err().to_string();
+        assert!(err.starts_with(&format!("{}:2: ", file.display())), "{}", err);
+        assert!(err.contains("только часть библиотеки awslib"), "{}", err);
+    }
+
+    #[test]
+    fn test_render_file_reports_line() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("broken.puml");
+        std::fs::write(&file, "@startuml\nclass A {\n@enduml\n").unwrap();
+
+        let args = Args::default();
+        let options = render_options(&args).unwrap();
+        let err = render_file(&file, &args, &options).unwrap_err();
+
+        match err {
+            CliError::Render { line, .. } => assert!(line.is_some()),
+            other => panic!("ожидалась ошибка рендеринга, получено {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unknown_theme() {
+        let args = Args {
+            theme: Some("nope".to_string()),
+            ..Args::default()
+        };
+        assert!(matches!(render_options(&args), Err(CliError::Usage(_))));
+    }
+}
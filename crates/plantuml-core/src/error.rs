@@ -1,5 +1,6 @@
 //! Типы ошибок для plantuml-core
 
+use plantuml_preprocessor::PreprocessError;
 use thiserror::Error;
 
 /// Тип результата для plantuml-core
@@ -12,10 +13,30 @@ pub enum Error {
     #[error("ошибка препроцессора: {0}")]
     Preprocess(String),
 
+    /// Ошибка препроцессора в известной строке исходника
+    ///
+    /// `file` — файл `!include`, в котором находится строка (`None` — сам исходник).
+    #[error("ошибка препроцессора в строке {line}: {message}")]
+    PreprocessAt {
+        file: Option<String>,
+        line: usize,
+        message: String,
+    },
+
     /// Ошибка парсинга
     #[error("ошибка парсинга: {0}")]
     Parse(String),
 
+    /// Ошибка парсинга с известной строкой исходника
+    ///
+    /// `file` — файл `!include`, в котором находится строка (`None` — сам исходник).
+    #[error("ошибка парсинга в строке {line}: {message}")]
+    Syntax {
+        file: Option<String>,
+        line: usize,
+        message: String,
+    },
+
     /// Ошибка layout
     #[error("ошибка layout: {0}")]
     Layout(String),
@@ -36,3 +57,95 @@ pub enum Error {
     #[error("пустой исходный код")]
     EmptySource,
 }
+
+impl Error {
+    /// Номер строки исходника (1-indexed), если ошибка к ней привязана
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Error::Syntax { line, .. } | Error::PreprocessAt { line, .. } => Some(*line),
+            _ => None,
+        }
+    }
+
+    /// Включённый файл, к строке которого привязана ошибка
+    pub fn file(&self) -> Option<&str> {
+        match self {
+            Error::Syntax { file, .. } | Error::PreprocessAt { file, .. } => file.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Сообщение без номера строки — для диагностик вида `file:line: message`
+    pub fn message(&self) -> String {
+        match self {
+            Error::Syntax { message, .. } => format!("ошибка парсинга: {}", message),
+            Error::PreprocessAt { message, .. } => format!("ошибка препроцессора: {}", message),
+            other => other.to_string(),
+        }
+    }
+}
+
+impl From<PreprocessError> for Error {
+    fn from(e: PreprocessError) -> Self {
+        match e {
+            PreprocessError::At { file, line, error } => Error::PreprocessAt {
+                file,
+                line,
+                message: error.to_string(),
+            },
+            other => Error::Preprocess(other.to_string()),
+        }
+    }
+}
+
+impl From<plantuml_parser::ParseError> for Error {
+    fn from(e: plantuml_parser::ParseError) -> Self {
+        match e {
+            plantuml_parser::ParseError::SyntaxError { line, message } if line > 0 => {
+                Error::Syntax {
+                    file: None,
+                    line,
+                    message,
+                }
+            }
+            plantuml_parser::ParseError::UnexpectedToken {
+                token,
+                line,
+                column,
+            } if line > 0 => Error::Syntax {
+                file: None,
+                line,
+                message: format!("неожиданный токен '{}', позиция {}", token, column),
+            },
+            other => Error::Parse(other.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_error_line() {
+        let err = Error::from(plantuml_parser::ParseError::UnexpectedToken {
+            token: "{".to_string(),
+            line: 4,
+            column: 9,
+        });
+        assert_eq!(err.line(), Some(4));
+        assert_eq!(err.message(), "ошибка парсинга: неожиданный токен '{', позиция 9");
+        assert_eq!(Error::EmptySource.message(), "пустой исходный код");
+    }
+
+    #[test]
+    fn test_preprocess_error_location() {
+        let err = Error::from(PreprocessError::At {
+            file: Some("inc.puml".to_string()),
+            line: 3,
+            error: Box::new(PreprocessError::AssertionFailed("x".to_string())),
+        });
+        assert_eq!((err.file(), err.line()), (Some("inc.puml"), Some(3)));
+        assert_eq!(err.message(), "ошибка препроцессора: !assert не выполнено: x");
+    }
+}
@@ -35,17 +35,22 @@
 //! - `plantuml-themes` — темы и skinparam
 
 mod error;
+mod links;
 mod options;
+mod pages;
 mod pipeline;
+mod source_map;
 
 pub use error::{Error, Result};
-pub use options::RenderOptions;
+pub use links::ElementLink;
+pub use options::{LayoutOptions, OutputFormat, RenderOptions};
+pub use pages::RenderedPage;
 
 // Re-exports для удобства
 pub use plantuml_ast::Diagram;
 pub use plantuml_parser::parse;
-pub use plantuml_preprocessor::FsFileResolver;
-pub use plantuml_themes::Theme;
+pub use plantuml_preprocessor::{FsFileResolver, StdlibFileResolver};
+pub use plantuml_themes::{SkinParams, Theme};
 
 // PNG рендеринг (требует feature "png")
 #[cfg(feature = "png")]
@@ -116,6 +121,97 @@ pub fn render_with_includes(
     pipeline::render_pipeline_with_includes(source, base_path, options)
 }
 
+/// Рендерит все диаграммы исходника, по одному SVG на страницу.
+///
+/// Исходник делится на блоки `@startXXX … @endXXX` (имя блока берётся из
+/// `@startuml name` или `@startuml(id=name)`), а sequence диаграммы — на
+/// страницы по `newpage [title]`. Заголовки участников повторяются на
+/// каждой странице.
+///
+/// # Пример
+///
+/// ```rust
+/// use plantuml_core::{render_all, RenderOptions};
+///
+/// let source = "@startuml\nAlice -> Bob\nnewpage\nBob -> Alice\n@enduml";
+/// let pages = render_all(source, &RenderOptions::default()).unwrap();
+/// assert_eq!(pages.len(), 2);
+/// ```
+pub fn render_all(source: &str, options: &RenderOptions) -> Result<Vec<RenderedPage>> {
+    pipeline::render_all_pipeline(source, None, options)
+}
+
+/// Рендерит все диаграммы исходника с поддержкой !include.
+///
+/// Аналог [`render_all`] с разрешением `!include` относительно `base_path`.
+pub fn render_all_with_includes(
+    source: &str,
+    base_path: &std::path::Path,
+    options: &RenderOptions,
+) -> Result<Vec<RenderedPage>> {
+    pipeline::render_all_pipeline(source, Some(base_path), options)
+}
+
+/// Возвращает гиперссылки элементов диаграммы (`[[url{tooltip} label]]`).
+///
+/// Координаты совпадают с координатами SVG из [`render`], `id` — с атрибутом
+/// `id` группы элемента.
+///
+/// # Пример
+///
+/// ```rust
+/// use plantuml_core::{element_links, RenderOptions};
+///
+/// let source = "@startuml\nparticipant Alice [[https://example.com]]\n@enduml";
+/// let links = element_links(source, &RenderOptions::default()).unwrap();
+/// assert!(links.iter().all(|l| l.url == "https://example.com"));
+/// ```
+pub fn element_links(source: &str, options: &RenderOptions) -> Result<Vec<ElementLink>> {
+    pipeline::links_pipeline(source, options)
+}
+
+/// Рендерит PlantUML диаграмму в формате `options.format`.
+///
+/// Единая точка входа для SVG и PNG: возвращает байты выходного файла.
+///
+/// # Пример
+///
+/// ```rust
+/// use plantuml_core::{render_to_bytes, RenderOptions};
+///
+/// let source = "@startuml\nAlice -> Bob\n@enduml";
+/// let bytes = render_to_bytes(source, &RenderOptions::default()).unwrap();
+/// assert!(String::from_utf8(bytes).unwrap().contains("<svg"));
+/// ```
+pub fn render_to_bytes(source: &str, options: &RenderOptions) -> Result<Vec<u8>> {
+    let svg = render(source, options)?;
+    svg_to_bytes(svg, options)
+}
+
+/// Кодирует готовый SVG в формат `options.format`.
+///
+/// PNG требует feature `png`, без неё возвращается [`Error::Render`].
+pub fn svg_to_bytes(svg: String, options: &RenderOptions) -> Result<Vec<u8>> {
+    match options.format {
+        OutputFormat::Svg => Ok(svg.into_bytes()),
+        #[cfg(feature = "png")]
+        OutputFormat::Png => {
+            // Масштаб уже применён к размерам SVG
+            let mut png_options = PngOptions::default();
+            if let Some(color) = &options.background_color {
+                png_options = png_options.with_background(color);
+            }
+            PngRenderer::new(png_options)
+                .render_svg(&svg)
+                .map_err(|e| Error::Render(e.to_string()))
+        }
+        #[cfg(not(feature = "png"))]
+        OutputFormat::Png => Err(Error::Render(
+            "PNG недоступен: соберите plantuml-core с feature `png`".to_string(),
+        )),
+    }
+}
+
 /// Рендерит PlantUML диаграмму в PNG.
 ///
 /// Требует feature `png`.
@@ -165,12 +261,11 @@ pub fn render_png(
 /// ```
 pub fn parse_diagram(source: &str) -> Result<Diagram> {
     // Препроцессинг
-    let processed = plantuml_preprocessor::preprocess(source)
-        .map_err(|e: plantuml_preprocessor::PreprocessError| Error::Preprocess(e.to_string()))?;
+    let processed = plantuml_preprocessor::preprocess(source).map_err(Error::from)?;
 
     // Парсинг
     let diagram = plantuml_parser::parse(&processed)
-        .map_err(|e: plantuml_parser::ParseError| Error::Parse(e.to_string()))?;
+        .map_err(Error::from)?;
 
     Ok(diagram)
 }
@@ -217,4 +312,18 @@ mod tests {
         let v = version();
         assert!(!v.is_empty());
     }
+
+    #[test]
+    fn test_render_to_bytes() {
+        let source = "@startuml\nAlice -> Bob\n@enduml";
+        let svg = render_to_bytes(source, &RenderOptions::default()).unwrap();
+        assert!(svg.starts_with(b"<?xml"));
+
+        let png = render_to_bytes(source, &RenderOptions::new().with_format(OutputFormat::Png));
+        if cfg!(feature = "png") {
+            assert!(png.unwrap().starts_with(b"\x89PNG"));
+        } else {
+            assert!(matches!(png, Err(Error::Render(_))));
+        }
+    }
 }
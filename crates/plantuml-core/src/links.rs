@@ -0,0 +1,56 @@
+//! Карта гиперссылок диаграммы
+//!
+//! Позволяет встраивающему приложению (например, playground) обрабатывать
+//! клики по элементам со ссылками `[[url{tooltip}]]` без разбора SVG.
+
+use plantuml_layout::LayoutResult;
+use plantuml_renderer::semantic::rendered_ids;
+use plantuml_renderer::SvgRenderer;
+
+/// Ссылка элемента с его положением на диаграмме
+///
+/// Положение задано в пикселях SVG документа: с учётом смещения viewBox
+/// и масштаба (`scale`, `max_width`, `max_height`).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ElementLink {
+    /// Идентификатор элемента (атрибут `id` группы в SVG)
+    pub id: String,
+    /// Адрес ссылки (пустой, если задана только подсказка)
+    pub url: String,
+    /// Всплывающая подсказка
+    pub tooltip: Option<String>,
+    /// Текст ссылки (`[[url текст]]`)
+    pub label: Option<String>,
+    /// X левого верхнего угла элемента
+    pub x: f64,
+    /// Y левого верхнего угла элемента
+    pub y: f64,
+    /// Ширина элемента
+    pub width: f64,
+    /// Высота элемента
+    pub height: f64,
+}
+
+/// Собирает ссылки всех элементов layout, включая вложенные группы
+///
+/// `renderer` — тот же, что рисует SVG: от него зависят id и масштаб.
+pub(crate) fn collect_links(layout: &LayoutResult, renderer: &SvgRenderer) -> Vec<ElementLink> {
+    rendered_ids(layout)
+        .into_iter()
+        .filter_map(|(id, element)| {
+            let link = element.link()?;
+            let bounds = renderer.to_document(layout, &element.bounds);
+            Some(ElementLink {
+                id,
+                url: link.url,
+                tooltip: link.tooltip,
+                label: link.label,
+                x: bounds.x,
+                y: bounds.y,
+                width: bounds.width,
+                height: bounds.height,
+            })
+        })
+        .collect()
+}
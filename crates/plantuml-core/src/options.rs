@@ -1,6 +1,14 @@
 //! Опции рендеринга
 
-use plantuml_themes::Theme;
+use std::path::PathBuf;
+
+use plantuml_layout::{
+    ActivityLayoutConfig, ClassLayoutConfig, ComponentLayoutConfig, ErLayoutConfig,
+    GanttLayoutConfig, JsonLayoutConfig, MindMapLayoutConfig, NetworkLayoutConfig,
+    ObjectLayoutConfig, SaltLayoutConfig, SequenceLayoutConfig, StateLayoutConfig,
+    TimingLayoutConfig, UseCaseLayoutConfig, WbsLayoutConfig, YamlLayoutConfig,
+};
+use plantuml_themes::{SkinParams, Theme};
 
 /// Опции рендеринга диаграмм
 #[derive(Debug, Clone)]
@@ -8,6 +16,9 @@ pub struct RenderOptions {
     /// Тема оформления
     pub theme: Theme,
 
+    /// skinparam поверх темы (дополняются `skinparam` исходника)
+    pub skin_params: SkinParams,
+
     /// Формат вывода
     pub format: OutputFormat,
 
@@ -25,6 +36,54 @@ pub struct RenderOptions {
 
     /// Максимальная высота (None = без ограничений)
     pub max_height: Option<f64>,
+
+    /// Переменные препроцессора, заданные извне (аналог `-DVAR=value`)
+    pub defines: Vec<(String, String)>,
+
+    /// Дополнительные пути поиска для `!include` (аналог `-I`)
+    pub include_paths: Vec<PathBuf>,
+
+    /// Конфигурации layout engines (None = по умолчанию)
+    pub layout: LayoutOptions,
+}
+
+/// Конфигурации layout engines по типам диаграмм
+///
+/// Шрифт в конфигурации всегда берётся из темы.
+#[derive(Debug, Clone, Default)]
+pub struct LayoutOptions {
+    /// Sequence диаграммы
+    pub sequence: Option<SequenceLayoutConfig>,
+    /// Class диаграммы
+    pub class: Option<ClassLayoutConfig>,
+    /// Activity диаграммы
+    pub activity: Option<ActivityLayoutConfig>,
+    /// State диаграммы
+    pub state: Option<StateLayoutConfig>,
+    /// Component, Deployment и Archimate диаграммы
+    pub component: Option<ComponentLayoutConfig>,
+    /// Use Case диаграммы
+    pub usecase: Option<UseCaseLayoutConfig>,
+    /// Object диаграммы
+    pub object: Option<ObjectLayoutConfig>,
+    /// Timing диаграммы
+    pub timing: Option<TimingLayoutConfig>,
+    /// Gantt диаграммы
+    pub gantt: Option<GanttLayoutConfig>,
+    /// MindMap диаграммы
+    pub mindmap: Option<MindMapLayoutConfig>,
+    /// WBS диаграммы
+    pub wbs: Option<WbsLayoutConfig>,
+    /// JSON диаграммы
+    pub json: Option<JsonLayoutConfig>,
+    /// YAML диаграммы
+    pub yaml: Option<YamlLayoutConfig>,
+    /// ER диаграммы
+    pub er: Option<ErLayoutConfig>,
+    /// Network диаграммы
+    pub network: Option<NetworkLayoutConfig>,
+    /// Salt диаграммы
+    pub salt: Option<SaltLayoutConfig>,
 }
 
 /// Формат вывода
@@ -33,19 +92,24 @@ pub enum OutputFormat {
     /// SVG (по умолчанию)
     #[default]
     Svg,
-    // PNG будет добавлен позже через resvg
+    /// PNG (требует feature `png`)
+    Png,
 }
 
 impl Default for RenderOptions {
     fn default() -> Self {
         Self {
             theme: Theme::default(),
+            skin_params: SkinParams::new(),
             format: OutputFormat::default(),
             scale: 1.0,
             xml_header: true,
             background_color: None,
             max_width: None,
             max_height: None,
+            defines: Vec::new(),
+            include_paths: Vec::new(),
+            layout: LayoutOptions::default(),
         }
     }
 }
@@ -70,6 +134,24 @@ impl RenderOptions {
         self
     }
 
+    /// Устанавливает skinparam (как `skinparam key value` в начале исходника)
+    pub fn with_skin_param(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.skin_params.set(key, value);
+        self
+    }
+
+    /// Устанавливает формат вывода
+    pub fn with_format(mut self, format: OutputFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Устанавливает конфигурации layout engines
+    pub fn with_layout(mut self, layout: LayoutOptions) -> Self {
+        self.layout = layout;
+        self
+    }
+
     /// Устанавливает масштаб
     pub fn with_scale(mut self, scale: f64) -> Self {
         self.scale = scale;
@@ -105,6 +187,18 @@ impl RenderOptions {
         self.max_height = Some(height);
         self
     }
+
+    /// Определяет переменную препроцессора (как `!define name value`)
+    pub fn with_define(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.defines.push((name.into(), value.into()));
+        self
+    }
+
+    /// Добавляет путь поиска для `!include`
+    pub fn with_include_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.include_paths.push(path.into());
+        self
+    }
 }
 
 #[cfg(test)]
@@ -129,4 +223,31 @@ mod tests {
         assert_eq!(opts.scale, 2.0);
         assert!(!opts.xml_header);
     }
+
+    #[test]
+    fn test_defines_and_include_paths() {
+        let opts = RenderOptions::new()
+            .with_define("ENV", "prod")
+            .with_include_path("lib");
+
+        assert_eq!(opts.defines, vec![("ENV".to_string(), "prod".to_string())]);
+        assert_eq!(opts.include_paths, vec![PathBuf::from("lib")]);
+    }
+
+    #[test]
+    fn test_format_and_layout() {
+        let opts = RenderOptions::new()
+            .with_format(OutputFormat::Png)
+            .with_layout(LayoutOptions {
+                sequence: Some(SequenceLayoutConfig {
+                    message_spacing: 60.0,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            });
+
+        assert_eq!(opts.format, OutputFormat::Png);
+        assert_eq!(opts.layout.sequence.unwrap().message_spacing, 60.0);
+        assert!(opts.layout.class.is_none());
+    }
 }
@@ -0,0 +1,164 @@
+//! Разбиение исходника на блоки `@startXXX … @endXXX`
+//!
+//! Один файл может содержать несколько диаграмм, а sequence диаграмма —
+//! несколько страниц (`newpage`). Каждая страница рендерится в отдельный SVG.
+
+/// Отрендеренная страница диаграммы
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderedPage {
+    /// Имя блока из `@startuml name` или `@startuml(id=name)`
+    pub name: Option<String>,
+    /// Заголовок страницы (`title` или `newpage title`)
+    pub title: Option<String>,
+    /// Номер блока в исходнике (с 0)
+    pub block: usize,
+    /// Номер страницы внутри блока (с 0)
+    pub page: usize,
+    /// SVG документ
+    pub svg: String,
+}
+
+/// Блок исходника с одной диаграммой
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SourceBlock {
+    /// Имя блока
+    pub name: Option<String>,
+    /// Текст блока, начальная строка приведена к виду `@startXXX`
+    pub text: String,
+    /// Номер строки `@startXXX` в исходнике (с 1)
+    pub start_line: usize,
+}
+
+impl SourceBlock {
+    /// Переводит номер строки внутри блока в номер строки исходника
+    pub fn source_line(&self, line: usize) -> usize {
+        line + self.start_line - 1
+    }
+}
+
+/// Делит исходник на блоки `@startXXX … @endXXX`
+///
+/// Текст вне блоков игнорируется. Если в исходнике нет ни одного
+/// `@start`, он целиком считается одним блоком.
+pub(crate) fn split_blocks(source: &str) -> Vec<SourceBlock> {
+    let mut blocks = Vec::new();
+    let mut current: Option<SourceBlock> = None;
+
+    for (index, line) in source.lines().enumerate() {
+        let trimmed = line.trim();
+
+        if current.is_none() {
+            if let Some((tag, name)) = parse_start_tag(trimmed) {
+                current = Some(SourceBlock {
+                    name,
+                    text: format!("@start{}\n", tag),
+                    start_line: index + 1,
+                });
+            }
+            continue;
+        }
+
+        if let Some(block) = current.as_mut() {
+            block.text.push_str(line);
+            block.text.push('\n');
+        }
+
+        if trimmed.to_lowercase().starts_with("@end") {
+            blocks.extend(current.take());
+        }
+    }
+
+    // Незакрытый блок отдаём парсеру как есть — он сообщит об ошибке
+    blocks.extend(current);
+
+    if blocks.is_empty() {
+        blocks.push(SourceBlock {
+            name: None,
+            text: source.to_string(),
+            start_line: 1,
+        });
+    }
+
+    blocks
+}
+
+/// Разбирает строку `@startXXX [name]` или `@startXXX(id=name)`
+///
+/// Возвращает тип диаграммы (`uml`, `mindmap`, …) и имя блока.
+fn parse_start_tag(line: &str) -> Option<(String, Option<String>)> {
+    let rest = line.strip_prefix("@start")?;
+    let tag_len = rest
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(rest.len());
+    let (tag, rest) = rest.split_at(tag_len);
+    if tag.is_empty() {
+        return None;
+    }
+
+    let rest = rest.trim();
+    let name = if let Some(args) = rest.strip_prefix('(').and_then(|r| r.strip_suffix(')')) {
+        args.split(',')
+            .filter_map(|arg| arg.split_once('='))
+            .find(|(key, _)| key.trim() == "id")
+            .map(|(_, value)| value.trim())
+    } else {
+        Some(rest)
+    };
+
+    let name = name
+        .map(|n| n.trim_matches('"').to_string())
+        .filter(|n| !n.is_empty());
+
+    Some((tag.to_string(), name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_block() {
+        let blocks = split_blocks("@startuml\nA -> B\n@enduml");
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].name, None);
+        assert_eq!(blocks[0].text, "@startuml\nA -> B\n@enduml\n");
+    }
+
+    #[test]
+    fn test_multiple_named_blocks() {
+        let source = "' общий комментарий\n\
+                      @startuml first\nA -> B\n@enduml\n\
+                      \n\
+                      @startmindmap(id=tree)\n* root\n@endmindmap\n";
+        let blocks = split_blocks(source);
+
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].name.as_deref(), Some("first"));
+        assert_eq!(blocks[0].start_line, 2);
+        assert!(blocks[0].text.starts_with("@startuml\n"));
+        assert_eq!(blocks[1].name.as_deref(), Some("tree"));
+        assert_eq!(blocks[1].text, "@startmindmap\n* root\n@endmindmap\n");
+        assert_eq!(blocks[1].source_line(2), 7);
+    }
+
+    #[test]
+    fn test_no_start_tag() {
+        let blocks = split_blocks("A -> B");
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].text, "A -> B");
+    }
+
+    #[test]
+    fn test_parse_start_tag() {
+        assert_eq!(parse_start_tag("@startuml"), Some(("uml".to_string(), None)));
+        assert_eq!(
+            parse_start_tag("@startuml \"my diagram\""),
+            Some(("uml".to_string(), Some("my diagram".to_string())))
+        );
+        assert_eq!(
+            parse_start_tag("@startgantt(id=plan, other=1)"),
+            Some(("gantt".to_string(), Some("plan".to_string())))
+        );
+        assert_eq!(parse_start_tag("@enduml"), None);
+    }
+}
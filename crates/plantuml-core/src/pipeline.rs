@@ -2,36 +2,43 @@
 
 use std::path::Path;
 
+use crate::links::{collect_links, ElementLink};
+use crate::pages::{split_blocks, RenderedPage, SourceBlock};
+use crate::source_map::SourceMap;
 use crate::{Error, RenderOptions, Result};
 use plantuml_ast::Diagram;
 use plantuml_layout::{
-    ActivityLayoutEngine, ClassLayoutEngine, ComponentLayoutEngine, ErLayoutEngine,
+    decorate, ActivityLayoutEngine, ClassLayoutEngine, ComponentLayoutEngine, ErLayoutEngine,
     GanttLayoutEngine, JsonLayoutEngine, LayoutConfig, LayoutResult, MindMapLayoutEngine,
     NetworkLayoutEngine, ObjectLayoutEngine, SaltLayoutEngine, SequenceLayoutEngine,
-    StateLayoutEngine, TimingLayoutEngine, UseCaseLayoutEngine, WbsLayoutEngine, YamlLayoutEngine,
+    StateLayoutEngine, TextMetrics, TimingLayoutEngine, UseCaseLayoutEngine, WbsLayoutEngine,
+    YamlLayoutEngine,
+};
+use plantuml_preprocessor::{
+    FsFileResolver, NoopFileResolver, PreprocessContext, Preprocessor, StdlibFileResolver,
 };
-use plantuml_preprocessor::{FsFileResolver, Preprocessor};
 use plantuml_renderer::{Renderer, SvgRenderer};
 
 /// Выполняет полный pipeline рендеринга
 pub fn render_pipeline(source: &str, options: &RenderOptions) -> Result<String> {
-    // Проверка на пустой исходник
-    let source = source.trim();
-    if source.is_empty() {
+    // Проверка на пустой исходник; сам исходник не обрезается,
+    // чтобы номера строк в span совпадали с файлом
+    if source.trim().is_empty() {
         return Err(Error::EmptySource);
     }
 
     // 1. Препроцессинг
-    let processed = preprocess(source)?;
+    let (processed, source_map, options) = preprocess(source, options)?;
+    let options = &options;
 
     // 2. Парсинг
-    let diagram = parse(&processed)?;
+    let diagram = parse(&processed).map_err(|e| source_map.locate(e))?;
 
     // 3. Layout
-    let layout = layout(&diagram, options)?;
+    let layout = layout(&diagram, options, &source_map)?;
 
     // 4. Рендеринг
-    let svg = render_svg(&layout, options)?;
+    let svg = render_svg(&layout, &diagram, options)?;
 
     Ok(svg)
 }
@@ -42,164 +49,365 @@ pub fn render_pipeline_with_includes(
     base_path: &Path,
     options: &RenderOptions,
 ) -> Result<String> {
-    // Проверка на пустой исходник
-    let source = source.trim();
-    if source.is_empty() {
+    // Проверка на пустой исходник; сам исходник не обрезается,
+    // чтобы номера строк в span совпадали с файлом
+    if source.trim().is_empty() {
         return Err(Error::EmptySource);
     }
 
     // 1. Препроцессинг с поддержкой файлов
-    let processed = preprocess_with_includes(source, base_path)?;
+    let (processed, source_map, options) = preprocess_with_includes(source, base_path, options)?;
+    let options = &options;
 
     // 2. Парсинг
-    let diagram = parse(&processed)?;
+    let diagram = parse(&processed).map_err(|e| source_map.locate(e))?;
 
     // 3. Layout
-    let layout = layout(&diagram, options)?;
+    let layout = layout(&diagram, options, &source_map)?;
 
     // 4. Рендеринг
-    let svg = render_svg(&layout, options)?;
+    let svg = render_svg(&layout, &diagram, options)?;
 
     Ok(svg)
 }
 
-/// Этап препроцессинга
-fn preprocess(source: &str) -> Result<String> {
-    plantuml_preprocessor::preprocess(source)
-        .map_err(|e: plantuml_preprocessor::PreprocessError| Error::Preprocess(e.to_string()))
+/// Строит layout и возвращает гиперссылки элементов
+pub fn links_pipeline(source: &str, options: &RenderOptions) -> Result<Vec<ElementLink>> {
+    // Исходник не обрезается, чтобы номера строк в span совпадали с файлом
+    if source.trim().is_empty() {
+        return Err(Error::EmptySource);
+    }
+
+    let (processed, source_map, options) = preprocess(source, options)?;
+    let diagram = parse(&processed).map_err(|e| source_map.locate(e))?;
+    let layout = layout(&diagram, &options, &source_map)?;
+
+    Ok(collect_links(&layout, &svg_renderer(&diagram, &options)))
+}
+
+/// Рендерит все диаграммы исходника постранично
+///
+/// `base_path` включает поддержку `!include` относительно указанной директории.
+pub fn render_all_pipeline(
+    source: &str,
+    base_path: Option<&Path>,
+    options: &RenderOptions,
+) -> Result<Vec<RenderedPage>> {
+    if source.trim().is_empty() {
+        return Err(Error::EmptySource);
+    }
+
+    let mut pages = Vec::new();
+    for (block_index, block) in split_blocks(source).iter().enumerate() {
+        let (diagram, source_map, options) = parse_block(block, base_path, options)?;
+
+        for (page_index, diagram) in split_diagram_pages(diagram).into_iter().enumerate() {
+            let layout = layout(&diagram, &options, &source_map)?;
+            pages.push(RenderedPage {
+                name: block.name.clone(),
+                title: diagram.metadata().title.clone(),
+                block: block_index,
+                page: page_index,
+                svg: render_svg(&layout, &diagram, &options)?,
+            });
+        }
+    }
+
+    Ok(pages)
+}
+
+/// Препроцессинг и парсинг одного блока; возвращает диаграмму, карту строк
+/// и опции блока
+///
+/// Номер строки в ошибке препроцессора или парсера пересчитывается в номер
+/// строки исходника (или включённого файла).
+fn parse_block(
+    block: &SourceBlock,
+    base_path: Option<&Path>,
+    options: &RenderOptions,
+) -> Result<(Diagram, SourceMap, RenderOptions)> {
+    let (processed, source_map, options) = match base_path {
+        Some(base_path) => preprocess_with_includes(&block.text, base_path, options),
+        None => preprocess(&block.text, options),
+    }
+    .map_err(|e| in_block(block, e))?;
+
+    let diagram = parse(&processed).map_err(|e| in_block(block, source_map.locate(e)))?;
+    Ok((diagram, source_map, options))
+}
+
+/// Переводит строку ошибки внутри блока в строку исходника
+fn in_block(block: &SourceBlock, error: Error) -> Error {
+    match error {
+        Error::Syntax {
+            file: None,
+            line,
+            message,
+        } => Error::Syntax {
+            file: None,
+            line: block.source_line(line),
+            message,
+        },
+        Error::PreprocessAt {
+            file: None,
+            line,
+            message,
+        } => Error::PreprocessAt {
+            file: None,
+            line: block.source_line(line),
+            message,
+        },
+        other => other,
+    }
+}
+
+/// Делит диаграмму на страницы (`newpage` в sequence диаграммах)
+fn split_diagram_pages(diagram: Diagram) -> Vec<Diagram> {
+    match diagram {
+        Diagram::Sequence(seq) => seq
+            .split_pages()
+            .into_iter()
+            .map(Diagram::Sequence)
+            .collect(),
+        other => vec![other],
+    }
+}
+
+/// Создаёт контекст препроцессора с переменными, темой и skinparam из опций
+fn preprocess_context(options: &RenderOptions) -> PreprocessContext {
+    let mut ctx = PreprocessContext::new();
+    for (name, value) in &options.defines {
+        ctx.set_variable(name.clone(), value.clone());
+    }
+    ctx.theme = options.theme.clone();
+    ctx.skin_params = options.skin_params.clone();
+    ctx.apply_skin_params();
+    ctx
+}
+
+/// Опции с темой и skinparam, накопленными препроцессором (`!theme`, `skinparam`)
+fn styled_options(options: &RenderOptions, ctx: PreprocessContext) -> RenderOptions {
+    let mut options = options.clone();
+    // Фон диаграммы рисуется только при явном `skinparam backgroundColor`
+    if options.background_color.is_none() && ctx.skin_params.get("backgroundColor").is_some() {
+        options.background_color = Some(ctx.theme.background_color.to_css());
+    }
+    options.theme = ctx.theme;
+    options.skin_params = ctx.skin_params;
+    options
+}
+
+/// Этап препроцессинга: текст для парсера, происхождение его строк и опции
+/// с оформлением исходника
+fn preprocess(source: &str, options: &RenderOptions) -> Result<(String, SourceMap, RenderOptions)> {
+    let mut ctx = preprocess_context(options);
+    // Без базового пути доступна только стандартная библиотека
+    let processed = Preprocessor::with_resolver(StdlibFileResolver::new(NoopFileResolver))
+        .process_with_context(source, &mut ctx)
+        .map_err(Error::from)?;
+    let source_map = SourceMap::new(std::mem::take(&mut ctx.line_map));
+    Ok((processed, source_map, styled_options(options, ctx)))
 }
 
 /// Этап препроцессинга с поддержкой !include
-fn preprocess_with_includes(source: &str, base_path: &Path) -> Result<String> {
-    let resolver = FsFileResolver::new(base_path);
+fn preprocess_with_includes(
+    source: &str,
+    base_path: &Path,
+    options: &RenderOptions,
+) -> Result<(String, SourceMap, RenderOptions)> {
+    let resolver = StdlibFileResolver::new(
+        FsFileResolver::new(base_path).with_search_paths(options.include_paths.iter().cloned()),
+    );
     let preprocessor = Preprocessor::with_resolver(resolver);
-    preprocessor
-        .process(source)
-        .map_err(|e: plantuml_preprocessor::PreprocessError| Error::Preprocess(e.to_string()))
+    let mut ctx = preprocess_context(options);
+    let processed = preprocessor
+        .process_with_context(source, &mut ctx)
+        .map_err(Error::from)?;
+    let source_map = SourceMap::new(std::mem::take(&mut ctx.line_map));
+    Ok((processed, source_map, styled_options(options, ctx)))
 }
 
 /// Этап парсинга
+///
+/// Правила `hide`/`remove` применяются сразу: отфильтрованные элементы
+/// не попадают в layout.
 fn parse(source: &str) -> Result<Diagram> {
-    plantuml_parser::parse(source)
-        .map_err(|e: plantuml_parser::ParseError| Error::Parse(e.to_string()))
+    let mut diagram = plantuml_parser::parse(source).map_err(Error::from)?;
+    diagram.apply_visibility_rules();
+    Ok(diagram)
+}
+
+/// Этап layout: диаграмма и её оформление (title, header, legend, …)
+///
+/// Строки элементов пересчитываются по `source_map` в строки исходника.
+fn layout(
+    diagram: &Diagram,
+    options: &RenderOptions,
+    source_map: &SourceMap,
+) -> Result<LayoutResult> {
+    let result = layout_diagram(diagram, options)?;
+    let mut result = decorate(result, diagram.metadata(), &text_metrics(diagram, options));
+    source_map.remap_layout(&mut result);
+    Ok(result)
+}
+
+/// Конфигурация layout со шрифтом темы
+fn layout_config(options: &RenderOptions) -> LayoutConfig {
+    LayoutConfig::default().with_font(options.theme.font_family.clone(), options.theme.font_size)
 }
 
-/// Этап layout
-fn layout(diagram: &Diagram, _options: &RenderOptions) -> Result<LayoutResult> {
-    let _config = LayoutConfig::default();
+/// Метрики текста: шрифт темы и спрайты `<$name>` диаграммы
+fn text_metrics(diagram: &Diagram, options: &RenderOptions) -> TextMetrics {
+    layout_config(options)
+        .text_metrics()
+        .with_sprites(diagram.metadata().sprites.clone())
+}
+
+/// Размещение элементов диаграммы подходящим layout engine
+fn layout_diagram(diagram: &Diagram, options: &RenderOptions) -> Result<LayoutResult> {
+    let config = layout_config(options);
+    let text = text_metrics(diagram, options);
+
+    let overrides = &options.layout;
 
     // Выбираем layout engine в зависимости от типа диаграммы
     match diagram {
         Diagram::Sequence(seq) => {
             // Используем SequenceLayoutEngine для sequence diagrams
-            let engine = SequenceLayoutEngine::new();
+            let engine = SequenceLayoutEngine::with_config(overrides.sequence.clone().unwrap_or_default())
+                .with_text_metrics(text);
             Ok(engine.layout(seq))
         }
         Diagram::Class(class) => {
             // Используем ClassLayoutEngine для class diagrams (Sugiyama algorithm)
-            let engine = ClassLayoutEngine::new();
+            let engine = ClassLayoutEngine::with_config(overrides.class.clone().unwrap_or_default())
+                .with_text_metrics(text);
             Ok(engine.layout_diagram(class))
         }
         Diagram::Activity(act) => {
             // Используем ActivityLayoutEngine для activity diagrams
-            let engine = ActivityLayoutEngine::new();
+            let engine = ActivityLayoutEngine::with_config(overrides.activity.clone().unwrap_or_default())
+                .with_text_metrics(text);
             Ok(engine.layout(act))
         }
         Diagram::State(state) => {
             // Используем StateLayoutEngine для state diagrams
-            let engine = StateLayoutEngine::new();
+            let engine = StateLayoutEngine::with_config(overrides.state.clone().unwrap_or_default())
+                .with_text_metrics(text);
             Ok(engine.layout(state))
         }
         Diagram::Component(comp) => {
             // Используем ComponentLayoutEngine для component diagrams
-            let engine = ComponentLayoutEngine::new();
+            let engine = ComponentLayoutEngine::with_config(overrides.component.clone().unwrap_or_default())
+                .with_text_metrics(text);
             Ok(engine.layout(comp))
         }
         Diagram::UseCase(uc) => {
             // Используем UseCaseLayoutEngine для use case diagrams
-            let engine = UseCaseLayoutEngine::new();
+            let engine = UseCaseLayoutEngine::with_config(overrides.usecase.clone().unwrap_or_default())
+                .with_text_metrics(text);
             Ok(engine.layout(uc))
         }
         Diagram::Deployment(dep) => {
             // Deployment использует ComponentLayoutEngine (та же структура)
-            let engine = ComponentLayoutEngine::new();
+            let engine = ComponentLayoutEngine::with_config(overrides.component.clone().unwrap_or_default())
+                .with_text_metrics(text);
             Ok(engine.layout(dep))
         }
         Diagram::Object(obj) => {
             // Используем ObjectLayoutEngine для object diagrams
-            let engine = ObjectLayoutEngine::new();
+            let engine = ObjectLayoutEngine::with_config(overrides.object.clone().unwrap_or_default())
+                .with_text_metrics(text);
             Ok(engine.layout(obj))
         }
         Diagram::Timing(timing) => {
             // Используем TimingLayoutEngine для timing diagrams
-            let engine = TimingLayoutEngine::new();
+            let engine = TimingLayoutEngine::with_config(overrides.timing.clone().unwrap_or_default())
+                .with_text_metrics(text);
             Ok(engine.layout(timing))
         }
         Diagram::Gantt(gantt) => {
             // Используем GanttLayoutEngine для gantt diagrams
-            let engine = GanttLayoutEngine::new();
+            let engine = GanttLayoutEngine::with_config(overrides.gantt.clone().unwrap_or_default())
+                .with_text_metrics(text);
             Ok(engine.layout(gantt))
         }
         Diagram::MindMap(mindmap) => {
             // Используем MindMapLayoutEngine для mindmap diagrams
-            let engine = MindMapLayoutEngine::new();
+            let engine = MindMapLayoutEngine::with_config(overrides.mindmap.clone().unwrap_or_default())
+                .with_text_metrics(text);
             Ok(engine.layout(mindmap))
         }
         Diagram::Wbs(wbs) => {
             // Используем WbsLayoutEngine для wbs diagrams
-            let engine = WbsLayoutEngine::new();
+            let engine = WbsLayoutEngine::with_config(overrides.wbs.clone().unwrap_or_default())
+                .with_text_metrics(text);
             Ok(engine.layout(wbs))
         }
         Diagram::Json(json) => {
             // Используем JsonLayoutEngine для json diagrams
             use plantuml_layout::traits::LayoutEngine as _;
-            let engine = JsonLayoutEngine::new();
-            Ok(engine.layout(json, &_config))
+            let engine = JsonLayoutEngine::with_config(overrides.json.clone().unwrap_or_default())
+                .with_text_metrics(text);
+            Ok(engine.layout(json, &config))
         }
         Diagram::Yaml(yaml) => {
             // Используем YamlLayoutEngine для yaml diagrams
             use plantuml_layout::traits::LayoutEngine as _;
-            let engine = YamlLayoutEngine::new();
-            Ok(engine.layout(yaml, &_config))
+            let engine = YamlLayoutEngine::with_config(overrides.yaml.clone().unwrap_or_default())
+                .with_text_metrics(text);
+            Ok(engine.layout(yaml, &config))
         }
         Diagram::Er(er) => {
             // Используем ErLayoutEngine для ER diagrams
             use plantuml_layout::traits::LayoutEngine as _;
-            let engine = ErLayoutEngine::new();
-            Ok(engine.layout(er, &_config))
+            let engine = ErLayoutEngine::with_config(overrides.er.clone().unwrap_or_default())
+                .with_text_metrics(text);
+            Ok(engine.layout(er, &config))
         }
         Diagram::Network(net) => {
             // Используем NetworkLayoutEngine для network diagrams
             use plantuml_layout::traits::LayoutEngine as _;
-            let engine = NetworkLayoutEngine::new();
-            Ok(engine.layout(net, &_config))
+            let engine = NetworkLayoutEngine::with_config(overrides.network.clone().unwrap_or_default())
+                .with_text_metrics(text);
+            Ok(engine.layout(net, &config))
         }
         Diagram::Salt(salt) => {
             // Используем SaltLayoutEngine для salt diagrams
             use plantuml_layout::traits::LayoutEngine as _;
-            let engine = SaltLayoutEngine::new();
-            Ok(engine.layout(salt, &_config))
+            let engine = SaltLayoutEngine::with_config(overrides.salt.clone().unwrap_or_default())
+                .with_text_metrics(text);
+            Ok(engine.layout(salt, &config))
         }
         Diagram::Archimate(arch) => {
             // Archimate использует ComponentLayoutEngine
-            let engine = ComponentLayoutEngine::new();
+            let engine = ComponentLayoutEngine::with_config(overrides.component.clone().unwrap_or_default())
+                .with_text_metrics(text);
             Ok(engine.layout(arch))
         }
     }
 }
 
 /// Этап SVG рендеринга
-fn render_svg(layout: &LayoutResult, options: &RenderOptions) -> Result<String> {
+fn render_svg(layout: &LayoutResult, diagram: &Diagram, options: &RenderOptions) -> Result<String> {
+    Ok(svg_renderer(diagram, options).render(layout, &options.theme))
+}
+
+/// SVG рендерер с опциями диаграммы
+fn svg_renderer(diagram: &Diagram, options: &RenderOptions) -> SvgRenderer {
     let render_options = plantuml_renderer::RenderOptions {
         xml_header: options.xml_header,
         scale: options.scale,
         // None означает использовать PlantUML default (#FEFECE)
         background_color: options.background_color.clone(),
+        diagram_scale: diagram.metadata().scale,
+        max_width: options.max_width,
+        max_height: options.max_height,
     };
 
-    let renderer = SvgRenderer::with_options(render_options);
-
-    Ok(renderer.render(layout, &options.theme))
+    SvgRenderer::with_options(render_options)
+        .with_sprites(diagram.metadata().sprites.clone())
+        .with_skin_params(options.skin_params.clone())
 }
 
 #[cfg(test)]
@@ -225,6 +433,67 @@ mod tests {
         assert!(matches!(result, Err(Error::EmptySource)));
     }
 
+    #[test]
+    fn test_pipeline_defines() {
+        let source = "@startuml\n!ifdef SHOW\nAlice -> Bob: visible\n!endif\n@enduml";
+        let options = RenderOptions::default().with_define("SHOW", "");
+        let svg = render_pipeline(source, &options).unwrap();
+        assert!(svg.contains("visible"));
+    }
+
+    #[test]
+    fn test_pipeline_syntax_error_line() {
+        let source = "@startuml\nclass A {\n@enduml";
+        let err = render_pipeline(source, &RenderOptions::default()).unwrap_err();
+        assert_eq!(err.line(), Some(3), "ожидалась строка в ошибке: {:?}", err);
+    }
+
+    #[test]
+    fn test_layout_uses_theme_font_size() {
+        let source = "@startuml\nparticipant \"Очень длинное имя участника\" as A\n@enduml";
+        let width = |font_size: f64| {
+            let theme = crate::Theme {
+                font_size,
+                ..Default::default()
+            };
+            let svg = render_pipeline(source, &RenderOptions::default().with_theme(theme)).unwrap();
+            let start = svg.find("width=\"").unwrap() + 7;
+            let end = start + svg[start..].find('"').unwrap();
+            svg[start..end].parse::<f64>().unwrap()
+        };
+        assert!(width(20.0) > width(12.0));
+    }
+
+    #[test]
+    fn test_render_all_blocks_and_pages() {
+        let source = "@startuml first\nAlice -> Bob: ping\nnewpage Второй шаг\nBob -> Carol: pong\n@enduml\n\n@startuml\nclass A\n@enduml\n";
+        let pages = render_all_pipeline(source, None, &RenderOptions::default()).unwrap();
+
+        assert_eq!(pages.len(), 3);
+        assert_eq!(pages[0].name.as_deref(), Some("first"));
+        assert_eq!((pages[1].block, pages[1].page), (0, 1));
+        assert_eq!(pages[1].title.as_deref(), Some("Второй шаг"));
+        assert_eq!((pages[2].block, pages[2].page), (1, 0));
+
+        // Заголовки участников повторяются на каждой странице
+        assert!(pages[1].svg.contains("Alice"));
+        assert!(pages[1].svg.contains("pong"));
+        assert!(!pages[1].svg.contains("ping"));
+    }
+
+    #[test]
+    fn test_render_all_error_line_in_source() {
+        // Разбор незакрытого класса останавливается на `@enduml`
+        let source = "@startuml\nA -> B\n@enduml\n@startuml\nclass A {\n@enduml\n";
+        let err = render_all_pipeline(source, None, &RenderOptions::default()).unwrap_err();
+        assert_eq!(err.line(), Some(6), "строка должна отсчитываться от начала файла: {:?}", err);
+
+        // Директивы внутри второго блока не сдвигают номер строки
+        let source = "@startuml\nA -> B\n@enduml\n@startuml\n!$x = 1\nskinparam classBorderColor red\n!if $x == 1\nclass A {\n!endif\n@enduml\n";
+        let err = render_all_pipeline(source, None, &RenderOptions::default()).unwrap_err();
+        assert_eq!(err.line(), Some(10), "{:?}", err);
+    }
+
     #[test]
     fn test_pipeline_box_sequence() {
         let source = r#"@startuml
@@ -251,4 +520,152 @@ Redux --> React: state updated
         let result = render_pipeline(source, &RenderOptions::default());
         assert!(result.is_ok(), "Pipeline error: {:?}", result.err());
     }
+
+    #[test]
+    fn test_pipeline_sprites() {
+        let source = r#"@startuml
+sprite $dot [4x4/16] {
+0FF0
+FFFF
+FFFF
+0FF0
+}
+participant "<$dot> Web" as W
+participant "<$dot{scale=2,color=red}>" as D
+W -> D: hello
+@enduml"#;
+        let svg = render_pipeline(source, &RenderOptions::default()).unwrap();
+        // Участники рисуются сверху и снизу
+        assert_eq!(svg.matches("class=\"sprite sprite-dot\"").count(), 4);
+        assert!(svg.contains("scale(2)"));
+        assert!(svg.contains("fill=\"red\""));
+        assert!(!svg.contains("&lt;$dot"));
+    }
+
+    #[test]
+    fn test_pipeline_links() {
+        let source = r#"@startuml
+participant Alice [[https://example.com/alice{Профиль}]]
+Alice -> Bob: [[https://example.com/api запрос]]
+note over Bob: см. [[https://example.com/docs документацию]]
+@enduml"#;
+        let svg = render_pipeline(source, &RenderOptions::default()).unwrap();
+        assert!(svg.contains("xlink:href=\"https://example.com/alice\""));
+        assert!(svg.contains("<title>Профиль</title>"));
+        assert!(svg.contains("xlink:href=\"https://example.com/api\""));
+        assert!(svg.contains("запрос"));
+        assert!(svg.contains("xlink:href=\"https://example.com/docs\""));
+        assert!(!svg.contains("[["));
+
+        let links = links_pipeline(source, &RenderOptions::default()).unwrap();
+        assert!(links
+            .iter()
+            .any(|l| l.url == "https://example.com/alice" && l.tooltip.as_deref() == Some("Профиль")));
+        assert!(links.iter().any(|l| l.url == "https://example.com/api"));
+    }
+
+    #[test]
+    fn test_pipeline_links_match_svg() {
+        let source = "@startuml\nscale 2\n\
+            class Foo [[https://example.com/foo{Подсказка} Документация]]\n\
+            class Bar\nFoo --> Bar\n@enduml";
+        let svg = render_pipeline(source, &RenderOptions::default()).unwrap();
+        let links = links_pipeline(source, &RenderOptions::default()).unwrap();
+        assert_eq!(links.len(), 1);
+        let link = &links[0];
+
+        // id совпадает с группой в SVG, текст ссылки сохраняется
+        assert_eq!(link.id, "class_Foo");
+        assert!(svg.contains(&format!("id=\"{}\"", link.id)));
+        assert_eq!(link.label.as_deref(), Some("Документация"));
+
+        // Координаты — пиксели документа: viewBox смещён, масштаб 2
+        let number = |name: &str| -> f64 {
+            let start = svg.find(&format!(" {}=\"", name)).unwrap() + name.len() + 3;
+            svg[start..start + svg[start..].find('"').unwrap()].parse().unwrap()
+        };
+        let view_box = svg.split("viewBox=\"").nth(1).unwrap();
+        let view_box: Vec<f64> = view_box[..view_box.find('"').unwrap()]
+            .split(' ')
+            .map(|value| value.parse().unwrap())
+            .collect();
+        assert_eq!(number("width"), view_box[2] * 2.0);
+
+        let diagram = crate::parse_diagram(source).unwrap();
+        let layout = layout(&diagram, &RenderOptions::default(), &SourceMap::default()).unwrap();
+        let foo = layout.elements.iter().find(|e| e.id == "Foo").unwrap();
+        assert_eq!(link.x, (foo.bounds.x - view_box[0]) * 2.0);
+        assert_eq!(link.y, (foo.bounds.y - view_box[1]) * 2.0);
+        assert_eq!(link.width, foo.bounds.width * 2.0);
+    }
+
+    #[test]
+    fn test_pipeline_decorations() {
+        for body in ["Alice -> Bob", "class Foo", "[*] --> Idle", "start\n:step;\nstop"] {
+            let source = format!(
+                "@startuml\ntitle\n**Заголовок**\nend title\nheader Шапка\nfooter Подвал\ncaption Подпись\n{}\nlegend right\nЛегенда\nendlegend\n@enduml",
+                body
+            );
+            let svg = render_pipeline(&source, &RenderOptions::default()).unwrap();
+            for text in ["Заголовок", "Шапка", "Подвал", "Подпись", "Легенда"] {
+                assert!(svg.contains(text), "{} не найден для {:?}", text, body);
+            }
+            assert!(svg.contains("id=\"legend-frame\""));
+        }
+    }
+
+    /// Ширина и высота документа из атрибутов `<svg>`
+    fn svg_size(svg: &str) -> (f64, f64) {
+        let attr = |name: &str| {
+            let start = svg.find(&format!(" {}=\"", name)).unwrap() + name.len() + 3;
+            let end = start + svg[start..].find('"').unwrap();
+            svg[start..end].parse::<f64>().unwrap()
+        };
+        (attr("width"), attr("height"))
+    }
+
+    #[test]
+    fn test_pipeline_scale() {
+        let plain = "@startuml\nAlice -> Bob\n@enduml";
+        let (width, height) = svg_size(&render_pipeline(plain, &RenderOptions::default()).unwrap());
+
+        // Директива `scale` умножается на масштаб из опций
+        let scaled = "@startuml\nscale 2\nAlice -> Bob\n@enduml";
+        let options = RenderOptions::default().with_scale(1.5);
+        let (w, h) = svg_size(&render_pipeline(scaled, &options).unwrap());
+        assert!((w - width * 3.0).abs() < 1e-6);
+        assert!((h - height * 3.0).abs() < 1e-6);
+
+        let fixed = "@startuml\nscale 100 width\nAlice -> Bob\n@enduml";
+        let (w, _) = svg_size(&render_pipeline(fixed, &RenderOptions::default()).unwrap());
+        assert!((w - 100.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_pipeline_max_size() {
+        let source = "@startuml\nAlice -> Bob\n@enduml";
+        let options = RenderOptions::default().with_scale(4.0).with_max_width(200.0);
+        let (w, _) = svg_size(&render_pipeline(source, &options).unwrap());
+        assert!((w - 200.0).abs() < 1e-6);
+
+        let options = RenderOptions::default().with_max_height(50.0);
+        let (_, h) = svg_size(&render_pipeline(source, &options).unwrap());
+        assert!((h - 50.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_pipeline_layout_overrides() {
+        let source = "@startuml\nAlice -> Bob\nBob -> Alice\n@enduml";
+        let (_, height) = svg_size(&render_pipeline(source, &RenderOptions::default()).unwrap());
+
+        let options = RenderOptions::default().with_layout(crate::LayoutOptions {
+            sequence: Some(plantuml_layout::SequenceLayoutConfig {
+                message_spacing: 120.0,
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        let (_, taller) = svg_size(&render_pipeline(source, &options).unwrap());
+        assert!(taller > height);
+    }
 }
@@ -0,0 +1,146 @@
+//! Пересчёт строк текста после препроцессора в строки исходника
+//!
+//! `!include`, процедуры и циклы добавляют строки, поэтому номер строки
+//! текста, который разбирает парсер, не совпадает с номером строки файла.
+//! Препроцессор записывает происхождение каждой строки (`LineOrigin`);
+//! по нему пересчитываются синтаксические ошибки и `data-source-line`.
+
+use crate::Error;
+use plantuml_layout::{ElementType, LayoutElement, LayoutResult, PROPERTY_SOURCE_LINE};
+use plantuml_preprocessor::LineOrigin;
+
+/// Происхождение строк текста после препроцессора
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SourceMap {
+    lines: Vec<LineOrigin>,
+}
+
+impl SourceMap {
+    /// Карта по `PreprocessContext::line_map`
+    pub fn new(lines: Vec<LineOrigin>) -> Self {
+        Self { lines }
+    }
+
+    /// Происхождение строки (с 1); строки вне карты относятся к самим себе
+    pub fn origin(&self, line: usize) -> LineOrigin {
+        line.checked_sub(1)
+            .and_then(|index| self.lines.get(index))
+            .cloned()
+            .unwrap_or_else(|| LineOrigin::new(None, line))
+    }
+
+    /// Переводит строку синтаксической ошибки в файл и строку исходника
+    pub fn locate(&self, error: Error) -> Error {
+        match error {
+            Error::Syntax {
+                file: None,
+                line,
+                message,
+            } => {
+                let origin = self.origin(line);
+                Error::Syntax {
+                    file: origin.file,
+                    line: origin.line,
+                    message,
+                }
+            }
+            other => other,
+        }
+    }
+
+    /// Переводит строки элементов layout (`source_line`) в строки исходника
+    pub fn remap_layout(&self, layout: &mut LayoutResult) {
+        for element in &mut layout.elements {
+            self.remap_element(element);
+        }
+    }
+
+    fn remap_element(&self, element: &mut LayoutElement) {
+        if let Some(line) = element.source_line() {
+            let line = self.origin(line).source_line;
+            element
+                .properties
+                .insert(PROPERTY_SOURCE_LINE.to_string(), line.to_string());
+        }
+        match &mut element.element_type {
+            ElementType::Group { children, .. } => {
+                for child in children {
+                    self.remap_element(child);
+                }
+            }
+            ElementType::Fragment { sections, .. } => {
+                for child in sections.iter_mut().flat_map(|section| &mut section.children) {
+                    self.remap_element(child);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plantuml_layout::Rect;
+
+    fn map() -> SourceMap {
+        SourceMap::new(vec![
+            LineOrigin::new(None, 1),
+            LineOrigin {
+                file: Some("inc.puml".to_string()),
+                line: 4,
+                source_line: 2,
+            },
+            LineOrigin::new(None, 3),
+        ])
+    }
+
+    #[test]
+    fn test_locate_syntax_error() {
+        let error = map().locate(Error::Syntax {
+            file: None,
+            line: 2,
+            message: "x".to_string(),
+        });
+        assert_eq!(error.file(), Some("inc.puml"));
+        assert_eq!(error.line(), Some(4));
+
+        let error = map().locate(Error::Syntax {
+            file: None,
+            line: 7,
+            message: "x".to_string(),
+        });
+        assert_eq!((error.file(), error.line()), (None, Some(7)));
+    }
+
+    #[test]
+    fn test_remap_layout() {
+        let element = |line: usize| {
+            let bounds = Rect::new(0.0, 0.0, 1.0, 1.0);
+            let mut element = LayoutElement::new("e", bounds, ElementType::Path);
+            element
+                .properties
+                .insert(PROPERTY_SOURCE_LINE.to_string(), line.to_string());
+            element
+        };
+        let group = LayoutElement::new(
+            "g",
+            Rect::new(0.0, 0.0, 1.0, 1.0),
+            ElementType::Group {
+                label: None,
+                children: vec![element(2)],
+                kind: Default::default(),
+            },
+        );
+        let mut layout = LayoutResult::empty();
+        layout.elements = vec![element(3), group];
+
+        map().remap_layout(&mut layout);
+
+        assert_eq!(layout.elements[0].source_line(), Some(3));
+        match &layout.elements[1].element_type {
+            ElementType::Group { children, .. } => assert_eq!(children[0].source_line(), Some(2)),
+            other => panic!("ожидалась группа, получено {:?}", other),
+        }
+    }
+}
@@ -146,3 +146,104 @@ ProductRepository --|> AbstractRepository
 
     insta::assert_snapshot!("complex_hierarchy", svg);
 }
+
+/// Тест фильтрации `hide`/`remove`: элементы исчезают вместе со связями
+#[test]
+fn test_visibility_rules_svg() {
+    let source = r#"@startuml
+hide empty members
+hide circle
+remove $internal
+hide <<Deprecated>>
+class User {
+    -id: Long
+}
+class AuditLog $internal
+class LegacyUser <<Deprecated>>
+class Order
+Order --> User
+Order --> AuditLog
+LegacyUser --|> User
+@enduml"#;
+
+    let svg = render(source, &RenderOptions::default()).unwrap();
+
+    assert!(svg.contains("User"));
+    assert!(svg.contains("Order"));
+    assert!(!svg.contains("AuditLog"));
+    assert!(!svg.contains("LegacyUser"));
+    // Иконки классов скрыты
+    assert!(!svg.contains("#ADD1B2"));
+    // Осталась одна связь Order → User
+    assert_eq!(svg.matches("id=\"edge_").count(), 1);
+    // Пустой Order без секций — только заголовок, ниже минимальной высоты
+    let order = &svg[svg.find(r#"id="class_Order""#).unwrap()..];
+    assert!(order[..order.find("</g>").unwrap()].contains(r##"<rect fill="#E2E2F0" height="40""##));
+}
+
+/// skinparam блоком, по стереотипу и без иконок видимости
+#[test]
+fn test_class_skinparam_svg() {
+    let source = r#"@startuml
+skinparam classAttributeIconSize 0
+skinparam class {
+    BackgroundColor PaleGreen
+    BackgroundColor<<Service>> Wheat
+    BorderColor #FF0000
+}
+class Repository {
+    -id: Long
+}
+class Billing <<Service>>
+@enduml"#;
+
+    let svg = render(source, &RenderOptions::default()).unwrap();
+
+    assert!(svg.contains(r#"fill="PaleGreen""#));
+    assert!(svg.contains(r#"fill="Wheat""#));
+    assert!(svg.contains(r##"stroke="#FF0000""##));
+    assert!(svg.contains("-id: Long"));
+    assert!(!svg.contains("#C82829"));
+}
+
+#[test]
+fn test_relationship_inline_style_svg() {
+    let source = r#"@startuml
+class Foo
+class Bar
+class Baz
+Foo --> Bar #blue
+Foo --> Baz #blue;line.bold
+@enduml"#;
+
+    let svg = render(source, &RenderOptions::default()).unwrap();
+
+    let plain = &svg[svg.find(r#"id="edge_Foo_Bar""#).unwrap()..];
+    assert!(plain.contains(r#"stroke="blue" stroke-width="1""#));
+    let bold = &svg[svg.find(r#"id="edge_Foo_Baz""#).unwrap()..];
+    assert!(bold.contains(r#"stroke="blue" stroke-width="2""#));
+}
+
+/// `data-source-line` указывает строку исходника после вызовов процедур и циклов
+#[test]
+fn test_source_lines_after_procedure_and_loop_svg() {
+    let source = r#"@startuml
+!procedure $pair($name)
+class $name
+class ${name}Impl
+!endprocedure
+$pair("Repo")
+!$i = 0
+!while $i < 2
+class Gen$i
+!$i = $i + 1
+!endwhile
+class Mine
+@enduml"#;
+
+    let svg = render(source, &RenderOptions::default()).unwrap();
+    assert!(svg.contains(r#"data-source-line="6" id="class_Repo""#));
+    assert!(svg.contains(r#"data-source-line="6" id="class_RepoImpl""#));
+    assert!(svg.contains(r#"data-source-line="8" id="class_Gen1""#));
+    assert!(svg.contains(r#"data-source-line="12" id="class_Mine""#));
+}
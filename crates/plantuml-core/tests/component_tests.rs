@@ -0,0 +1,91 @@
+//! Интеграционные тесты для Component диаграмм
+
+use plantuml_core::{render, RenderOptions};
+
+/// Контейнеры разных видов рисуются своими формами
+#[test]
+fn test_container_kinds_svg() {
+    let source = r#"@startuml
+package Backend {
+  [Api]
+}
+node Server {
+  [Worker]
+}
+cloud Aws {
+  [Lambda]
+}
+database Storage {
+  [Tables]
+}
+frame Ui {
+  [Web]
+}
+folder Files {
+  [Docs]
+}
+@enduml"#;
+
+    let svg = render(source, &RenderOptions::default()).unwrap();
+    let cluster = |kind: &str, id: &str| {
+        let start = svg
+            .find(&format!(r#"<g class="cluster {}" id="package_{}""#, kind, id))
+            .unwrap_or_else(|| panic!("нет контейнера {} {}", kind, id));
+        &svg[start..start + svg[start..].find("</g>").unwrap()]
+    };
+
+    // package — вкладка с названием над рамкой
+    assert!(cluster("package", "Backend").contains("<path d=\"M30,30 L"));
+    // node — передняя грань и объём
+    assert_eq!(cluster("node", "Server").matches("<path").count(), 3);
+    // cloud — сильно скруглённые углы
+    assert!(cluster("cloud", "Aws").contains(r#"rx="20""#));
+    // database — цилиндр из дуг
+    assert!(cluster("database", "Storage").contains(" A90,8 0 0 1 "));
+    // frame — пятиугольник заголовка
+    assert!(cluster("frame", "Ui").contains(r##"fill="#E2E2F0""##));
+    // folder — вкладка без текста
+    assert!(cluster("folder", "Files").contains("<path"));
+    assert!(!svg.contains(r#"class="cluster group""#));
+}
+
+/// Минимальный пример C4 Container из stdlib рисуется целиком
+#[test]
+fn test_c4_container_svg() {
+    let source = r#"@startuml
+!include <C4/C4_Container>
+LAYOUT_LEFT_RIGHT()
+
+Person(user, "Customer", "A customer of the shop")
+System_Boundary(shop, "Online Shop") {
+    Container(web, "Web App", "React", "Delivers the UI")
+    Container(api, "API", "Rust, axum", "Business logic")
+    ContainerDb(db, "Database", "PostgreSQL", "Orders and users")
+}
+Rel(user, web, "Uses", "HTTPS")
+Rel(web, api, "Calls", "JSON/HTTPS")
+Rel(api, db, "Reads/writes", "SQL")
+
+SHOW_LEGEND()
+@enduml"#;
+
+    let svg = render(source, &RenderOptions::default()).unwrap();
+
+    // Граница системы — контейнер с типом в названии
+    let boundary = r#"<g class="cluster rectangle" id="package_Online_Shop__5b_System_5d_""#;
+    assert!(svg.contains(boundary));
+    // Название, технология и описание — отдельные строки
+    for line in ["Web App", ">[React]<", ">Delivers the UI<", ">[PostgreSQL]<"] {
+        assert!(svg.contains(line), "нет строки {}", line);
+    }
+    // Подпись связи и технология под ней
+    assert!(svg.contains(">Uses<") && svg.contains(">[HTTPS]<"));
+
+    // LAYOUT_LEFT_RIGHT: элементы в ряд — ширина больше высоты
+    let view_box = svg.split("viewBox=\"").nth(1).unwrap();
+    let size: Vec<f64> = view_box[..view_box.find('"').unwrap()]
+        .split(' ')
+        .map(|value| value.parse().unwrap())
+        .collect();
+    assert!(size[2] > size[3] * 2.0, "viewBox {:?}", size);
+}
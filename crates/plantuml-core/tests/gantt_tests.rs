@@ -0,0 +1,125 @@
+//! Визуальные тесты для gantt diagrams
+//!
+//! Используем insta для snapshot тестирования SVG вывода.
+
+use plantuml_core::{render, RenderOptions};
+
+/// Тест календаря: настоящие даты, выходные, праздники и сегодняшний день
+#[test]
+fn test_gantt_calendar_svg() {
+    let source = r#"@startgantt
+project starts 2024-01-29
+saturday are closed
+sunday are closed
+2024-02-01 is closed (Release freeze)
+today is 2024-02-06
+[Design] lasts 5 days
+then [Build] ends 2024-02-13
+@endgantt"#;
+
+    let svg = render(source, &RenderOptions::default()).unwrap();
+
+    assert!(svg.contains("<svg"));
+    assert!(svg.contains("Feb 2024"));
+    assert!(svg.contains(r#"class="decoration holiday""#));
+    assert!(svg.contains(r#"class="decoration today""#));
+    // Даты не выходят за конец месяца
+    assert!(!svg.contains(">32<"));
+    // Дни календаря — заливка без рамки, не в цвет баров задач
+    let cell = |class: &str| {
+        let start = svg.find(&format!(r#"class="decoration {}""#, class)).unwrap();
+        &svg[start..start + svg[start..].find("</g>").unwrap()]
+    };
+    for class in ["closed", "holiday", "today"] {
+        assert!(cell(class).contains(r#"stroke="none""#), "{}", class);
+        assert!(!cell(class).contains("#E2E2F0"), "{}", class);
+    }
+    assert!(cell("closed").contains(r##"fill="#F1F1F1""##));
+    assert!(cell("holiday").contains(r##"fill="#F1F1F1""##));
+    assert!(cell("today").contains(r##"fill="#FFE0E0""##));
+
+    insta::assert_snapshot!("gantt_calendar", svg);
+}
+
+/// Цвет сегодняшнего дня из `today is ... and is colored in`
+#[test]
+fn test_gantt_today_color_svg() {
+    let source = r#"@startgantt
+project starts 2024-01-29
+today is 2024-01-31 and is colored in #AAF
+[Design] lasts 5 days
+@endgantt"#;
+
+    let svg = render(source, &RenderOptions::default()).unwrap();
+    let start = svg.find(r#"class="decoration today""#).unwrap();
+    let today = &svg[start..start + svg[start..].find("</g>").unwrap()];
+    assert!(today.contains(r##"fill="#AAF""##));
+    assert!(today.contains(r#"stroke="none""#));
+}
+
+/// Тест зависимостей, вех и загрузки ресурсов
+#[test]
+fn test_gantt_dependencies_svg() {
+    let source = r#"@startgantt
+project starts 2024-03-04
+[Design] as [D] lasts 4 days on {Alice:50%}
+[Review] starts at [D]'s start lasts 2 days on {Alice}
+[Build] lasts 2 days on {Bob}
+[D] -> [Build]
+[Release] happens at [Build]'s end
+@endgantt"#;
+
+    let svg = render(source, &RenderOptions::default()).unwrap();
+
+    assert!(svg.contains(r#"class="link dependency""#));
+    assert!(svg.contains(r#"class="entity milestone""#));
+    assert!(svg.contains(r#"class="decoration overload""#));
+    assert!(svg.contains("Alice:50%"));
+
+    insta::assert_snapshot!("gantt_dependencies", svg);
+}
+
+/// Повторное упоминание задачи дополняет её, а не добавляет второй бар
+#[test]
+fn test_gantt_repeated_task_svg() {
+    let source = r#"@startgantt
+[Design] lasts 3 days
+[Build] lasts 5 days
+[Build] starts at [Design]'s end
+@endgantt"#;
+
+    let svg = render(source, &RenderOptions::default()).unwrap();
+
+    assert_eq!(svg.matches(r#"class="entity task""#).count(), 2);
+    assert!(!svg.contains("task_bar_2"));
+    // Build: 5 дней по 20px сразу после трёх дней Design
+    let build = &svg[svg.find(r#"id="task_bar_1""#).unwrap()..];
+    assert!(build.contains(r#"width="100" x="230""#));
+}
+
+/// Объединённая задача ссылается на объявленную ниже: зависимость и загрузка
+#[test]
+fn test_gantt_repeated_task_dependency_svg() {
+    let source = r#"@startgantt
+[Build] lasts 2 days on {Alice}
+[Design] as [D] lasts 3 days on {Alice}
+[Build] starts at [D]'s end
+@endgantt"#;
+
+    let svg = render(source, &RenderOptions::default()).unwrap();
+
+    // Build (строка 0) — после трёх дней Design (строка 1)
+    let build = &svg[svg.find(r#"id="task_bar_0""#).unwrap()..];
+    assert!(build.contains(r#"width="40" x="230""#));
+    let design = &svg[svg.find(r#"id="task_bar_1""#).unwrap()..];
+    assert!(design.contains(r#"width="60" x="170""#));
+
+    // Стрелка из конца Design в Build
+    let dependency = &svg[svg.find(r#"id="dependency_1_0""#).unwrap()..];
+    assert!(dependency.contains(r#"d="M230,110 "#));
+
+    // Alice занята по 100% пять дней подряд, без перегрузки
+    assert!(svg.contains(r#"id="resource_load_0_4""#));
+    assert!(!svg.contains(r#"id="resource_load_0_5""#));
+    assert!(!svg.contains(r#"class="decoration overload""#));
+}
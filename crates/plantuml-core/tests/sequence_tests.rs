@@ -87,3 +87,147 @@ end
 
     insta::assert_snapshot!("alt_fragment", svg);
 }
+
+/// Тест сообщений с границы диаграммы и потерянных/найденных сообщений
+#[test]
+fn test_boundary_messages_svg() {
+    let source = r#"@startuml
+[-> Alice: Begin
+Alice -> Bob: Request
+Bob ->x? : Lost
+?o-> Bob : Found
+Bob ->] : Done
+@enduml"#;
+
+    let svg = render(source, &RenderOptions::default()).unwrap();
+
+    assert!(svg.contains(r#"id="msg_border_left_Alice""#));
+    assert!(svg.contains(r#"id="msg_Bob_border_right""#));
+    // Рамка и `?` не рисуются участниками
+    assert!(!svg.contains("participant_["));
+    assert!(!svg.contains("participant_?"));
+
+    insta::assert_snapshot!("boundary_messages", svg);
+}
+
+/// Тест teoz: параллельные и наклонные сообщения, длительность между якорями
+#[test]
+fn test_teoz_messages_svg() {
+    let source = r#"@startuml
+!pragma teoz true
+{start} Alice -> Bob : request
+& Bob -> Carol : notify
+Bob ->(10) Alice : response
+{end} Alice -> Bob : ack
+{start} <-> {end} : latency
+@enduml"#;
+
+    let svg = render(source, &RenderOptions::default()).unwrap();
+
+    assert!(svg.contains("latency"));
+    assert!(svg.contains(r#"id="msg_Bob_Carol""#));
+
+    insta::assert_snapshot!("teoz_messages", svg);
+}
+
+#[test]
+fn test_display_directives_svg() {
+    let source = r#"@startuml
+mainframe sd Checkout
+hide footbox
+hide unlinked
+skinparam maxMessageSize 80
+skinparam responseMessageBelowArrow true
+skinparam lifelineStrategy solid
+participant Alice
+participant Bob
+participant Unused
+Alice -> Bob : please process the order now
+Bob --> Alice : done
+@enduml"#;
+
+    let svg = render(source, &RenderOptions::default()).unwrap();
+
+    assert!(svg.contains("sd Checkout"));
+    assert!(!svg.contains("Unused"));
+    assert!(!svg.contains(r#"id="footer_Alice""#));
+
+    insta::assert_snapshot!("display_directives", svg);
+}
+
+/// Стабильные id, семантические классы и строки исходника в SVG
+#[test]
+fn test_semantic_markup_svg() {
+    let source = r#"@startuml
+participant "Alice Smith" as A
+A -> Bob: first
+A -> Bob: second
+@enduml"#;
+
+    let svg = render(source, &RenderOptions::default()).unwrap();
+
+    assert!(svg.contains(r#"class="entity participant" data-source-line="2" id="participant_A""#));
+    assert!(svg.contains(r#"class="link message" data-source-line="3" id="msg_A_Bob""#));
+    assert!(svg.contains(r#"class="link message" data-source-line="4" id="msg_A_Bob-2""#));
+}
+
+/// Строки исходника после директив препроцессора и блока skinparam
+#[test]
+fn test_source_lines_after_directives_svg() {
+    let source = r#"
+@startuml
+!$timeout = 30
+skinparam participant {
+    BorderColor red
+}
+!if $timeout > 10
+participant Slow
+!endif
+participant Api
+Api -> Slow: call $timeout
+@enduml"#;
+
+    let svg = render(source, &RenderOptions::default()).unwrap();
+
+    assert!(svg.contains(r#"class="entity participant" data-source-line="8" id="participant_Slow""#));
+    assert!(svg.contains(r#"class="entity participant" data-source-line="10" id="participant_Api""#));
+    assert!(svg.contains(r#"class="link message" data-source-line="11" id="msg_Api_Slow""#));
+}
+
+/// skinparam участников, стрелок и заметок
+#[test]
+fn test_sequence_skinparam_svg() {
+    let source = r#"@startuml
+skinparam ParticipantBorderColor DarkRed
+skinparam sequence {
+    ArrowColor Navy
+    ParticipantBackgroundColor<<Db>> Khaki
+}
+skinparam NoteBackgroundColor #LightYellow
+participant Alice
+participant Store <<Db>>
+Alice -> Store: save
+note right: persisted
+@enduml"#;
+
+    let svg = render(source, &RenderOptions::default()).unwrap();
+
+    assert!(svg.contains(r#"stroke="DarkRed""#));
+    assert!(svg.contains(r#"stroke="Navy""#));
+    assert!(svg.contains(r#"fill="Khaki""#));
+    assert!(svg.contains(r#"fill="LightYellow""#));
+}
+
+/// `\n` в строке препроцессора переносит метку, а не строку диаграммы
+#[test]
+fn test_preprocessor_newline_label_svg() {
+    let source = r#"@startuml
+!$label = "first\nsecond"
+Alice -> Bob: $label
+@enduml"#;
+
+    let svg = render(source, &RenderOptions::default()).unwrap();
+    assert!(svg.contains(r#"<tspan x="46.835" y="70.1328125">first</tspan>"#));
+    assert!(svg.contains(r#"<tspan x="46.835" y="85.1328125">second</tspan>"#));
+    assert!(!svg.contains(r"first\nsecond"));
+}
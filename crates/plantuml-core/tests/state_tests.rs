@@ -69,3 +69,31 @@ Invalid --> [*]
 
     insta::assert_snapshot!("state_with_choice", svg);
 }
+
+/// Цвета состояний и переходов из AST доходят до SVG
+#[test]
+fn test_state_colors_svg() {
+    let source = r#"@startuml
+state Active #pink
+state Idle #red/blue;line:green;line.dashed;text:white
+[*] --> Active
+Active --> Idle
+Idle -[#orange,bold]-> Busy
+@enduml"#;
+
+    let svg = render(source, &RenderOptions::default()).unwrap();
+
+    assert!(svg.contains(r#"fill="pink""#));
+    assert!(svg.contains(r#"<linearGradient id="state_Idle_fill""#));
+
+    // Рамка, пунктир и текст состояния из `line:green;line.dashed;text:white`
+    let idle = &svg[svg.find(r#"id="state_Idle""#).unwrap()..svg.find(r#"id="state_Busy""#).unwrap()];
+    assert!(idle.contains(r#"stroke-dasharray="5,5""#));
+    assert!(idle.contains(r#"<rect fill="url(#state_Idle_fill)""#));
+    assert!(idle.contains(r#"stroke="green" stroke-width="1""#));
+    assert!(idle.contains(r#"fill="white""#));
+
+    // `-[#orange,bold]->`: цвет и утолщённая линия
+    let transition = &svg[svg.find(r#"id="trans_Idle_Busy""#).unwrap()..];
+    assert!(transition.contains(r#"stroke="orange" stroke-width="2""#));
+}
@@ -1,5 +1,7 @@
 //! Конфигурация layout для Activity Diagrams
 
+use crate::text::TextMetrics;
+
 /// Конфигурация Activity Layout Engine
 #[derive(Debug, Clone)]
 pub struct ActivityLayoutConfig {
@@ -27,6 +29,12 @@ pub struct ActivityLayoutConfig {
     pub action_corner_radius: f64,
     /// Размер стрелки
     pub arrow_size: f64,
+    /// Высота заголовка дорожки (swimlane)
+    pub swimlane_header_height: f64,
+    /// Отступ содержимого от границ дорожки
+    pub swimlane_padding: f64,
+    /// Метрики текста (шрифт темы)
+    pub text: TextMetrics,
 }
 
 impl Default for ActivityLayoutConfig {
@@ -44,6 +52,9 @@ impl Default for ActivityLayoutConfig {
             bar_width: 50.0,
             action_corner_radius: 10.0,
             arrow_size: 8.0,
+            swimlane_header_height: 30.0,
+            swimlane_padding: 20.0,
+            text: TextMetrics::default(),
         }
     }
 }
@@ -1,14 +1,87 @@
 //! Activity Diagram Layout Engine
 //!
 //! Flowchart-based layout algorithm для activity diagrams.
+//!
+//! Элементы располагаются сверху вниз. Каждая дорожка (`|Lane|`) — колонка
+//! на всю высоту диаграммы; элемент ставится в колонку текущей дорожки,
+//! ветки условий и fork — рядом друг с другом внутри неё. Layout
+//! выполняется дважды: первый проход измеряет ширину содержимого дорожек,
+//! второй расставляет элементы по готовым колонкам.
 
 use plantuml_ast::activity::{
     Action, ActivityDiagram, ActivityElement, Condition, Fork, RepeatLoop, WhileLoop,
 };
+use std::collections::HashMap;
+
 use plantuml_model::{Point, Rect};
 
 use super::config::ActivityLayoutConfig;
-use crate::{EdgeType, ElementType, LayoutElement, LayoutResult};
+use crate::text::TextMetrics;
+use crate::{EdgeType, ElementStyle, ElementType, LayoutElement, LayoutResult};
+
+/// Продолжение потока: путь от предыдущего элемента, уходящий вниз
+#[derive(Debug, Clone)]
+struct Exit {
+    /// Уже пройденные точки (последняя — место выхода)
+    points: Vec<Point>,
+    /// Подпись стрелки к следующему элементу
+    label: Option<String>,
+}
+
+impl Exit {
+    /// Выход вниз из точки
+    fn down(point: Point) -> Self {
+        Self {
+            points: vec![point],
+            label: None,
+        }
+    }
+
+    fn with_label(mut self, label: Option<String>) -> Self {
+        self.label = label;
+        self
+    }
+
+    fn end(&self) -> Point {
+        self.points[self.points.len() - 1]
+    }
+}
+
+/// Состояние обхода диаграммы
+struct Flow<'a> {
+    elements: Vec<LayoutElement>,
+    /// Имена дорожек
+    lanes: &'a [String],
+    /// Центры колонок дорожек по X
+    centers: Vec<f64>,
+    /// Занятый диапазон X каждой дорожки относительно её центра
+    extents: Vec<Option<(f64, f64)>>,
+    /// Текущая дорожка
+    lane: usize,
+    /// Выход предыдущего элемента (None — поток прерван)
+    exit: Option<Exit>,
+}
+
+impl Flow<'_> {
+    /// X координата смещения `offset` в текущей дорожке
+    fn x(&self, offset: f64) -> f64 {
+        self.centers[self.lane] + offset
+    }
+
+    /// Отмечает занятый диапазон смещений в дорожке
+    fn occupy(&mut self, lane: usize, left: f64, right: f64) {
+        let extent = &mut self.extents[lane];
+        *extent = Some(match *extent {
+            Some((min, max)) => (min.min(left), max.max(right)),
+            None => (left, right),
+        });
+    }
+
+    /// Id нового элемента в стиле `prefix_N`
+    fn next_id(&self, prefix: &str) -> String {
+        format!("{}_{}", prefix, self.elements.len())
+    }
+}
 
 /// Layout engine для activity diagrams
 pub struct ActivityLayoutEngine {
@@ -28,18 +101,45 @@ impl ActivityLayoutEngine {
         Self { config }
     }
 
+    /// Устанавливает метрики текста (шрифт темы)
+    pub fn with_text_metrics(mut self, text: TextMetrics) -> Self {
+        self.config.text = text;
+        self
+    }
+
     /// Выполняет layout диаграммы
     pub fn layout(&self, diagram: &ActivityDiagram) -> LayoutResult {
+        let lanes = Self::lane_names(diagram);
+        let lane_count = lanes.len().max(1);
+
+        // Первый проход: ширины содержимого дорожек при нулевых центрах
+        let (measure, _) = self.run(diagram, &lanes, vec![0.0; lane_count]);
+        let columns = self.lane_columns(&lanes, &measure.extents);
+        let centers = columns.iter().map(|(_, _, center)| *center).collect();
+
+        let (flow, bottom) = self.run(diagram, &lanes, centers);
         let mut elements = Vec::new();
-        let mut current_y = self.config.margin;
-        
-        // Центр диаграммы по X
-        let center_x = self.config.margin + self.config.action_width / 2.0;
-
-        // Обрабатываем элементы последовательно
-        for element in &diagram.elements {
-            current_y = self.layout_element(element, center_x, current_y, &mut elements);
+
+        // Дорожки — под остальными элементами
+        for (i, name) in lanes.iter().enumerate() {
+            let (left, width, _) = columns[i];
+            let color = diagram
+                .swimlanes
+                .iter()
+                .find(|lane| &lane.name == name)
+                .and_then(|lane| lane.color.as_ref());
+            elements.push(LayoutElement {
+                id: format!("swimlane_{}", i),
+                bounds: Rect::new(left, self.config.margin, width, bottom - self.config.margin),
+                text: None,
+                properties: HashMap::new(),
+                element_type: ElementType::Swimlane {
+                    title: name.clone(),
+                    header_height: self.config.swimlane_header_height,
+                },
+            }.with_style(&ElementStyle::new().with_fill(color)));
         }
+        elements.extend(flow.elements);
 
         // Вычисляем bounds
         let mut result = LayoutResult {
@@ -55,35 +155,228 @@ impl ActivityLayoutEngine {
         result
     }
 
+    /// Имена дорожек в порядке объявления
+    fn lane_names(diagram: &ActivityDiagram) -> Vec<String> {
+        if !diagram.swimlanes.is_empty() {
+            return diagram.swimlanes.iter().map(|lane| lane.name.clone()).collect();
+        }
+        let mut names = Vec::new();
+        Self::collect_lane_names(&diagram.elements, &mut names);
+        names
+    }
+
+    /// Собирает имена дорожек из `SwimlaneChange` (включая вложенные блоки)
+    fn collect_lane_names(elements: &[ActivityElement], names: &mut Vec<String>) {
+        for element in elements {
+            match element {
+                ActivityElement::SwimlaneChange(name) if !names.contains(name) => {
+                    names.push(name.clone());
+                }
+                ActivityElement::Condition(cond) => {
+                    Self::collect_lane_names(&cond.then_branch, names);
+                    for branch in &cond.elseif_branches {
+                        Self::collect_lane_names(&branch.elements, names);
+                    }
+                    if let Some(else_branch) = &cond.else_branch {
+                        Self::collect_lane_names(else_branch, names);
+                    }
+                }
+                ActivityElement::While(while_loop) => Self::collect_lane_names(&while_loop.body, names),
+                ActivityElement::Repeat(repeat_loop) => Self::collect_lane_names(&repeat_loop.body, names),
+                ActivityElement::Fork(fork) => {
+                    for branch in &fork.branches {
+                        Self::collect_lane_names(branch, names);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Колонки дорожек: левый край, ширина и центр содержимого
+    ///
+    /// Без дорожек — одна колонка, содержимое которой начинается от отступа.
+    fn lane_columns(&self, lanes: &[String], extents: &[Option<(f64, f64)>]) -> Vec<(f64, f64, f64)> {
+        let padding = self.config.swimlane_padding;
+        if lanes.is_empty() {
+            let (min, max) = extents[0].unwrap_or((0.0, 0.0));
+            return vec![(self.config.margin, max - min, self.config.margin - min)];
+        }
+
+        let mut left = self.config.margin;
+        lanes
+            .iter()
+            .zip(extents)
+            .map(|(name, extent)| {
+                let (min, max) = extent.unwrap_or((0.0, 0.0));
+                let title = self.config.text.bold_width_at(name, self.config.text.font_size);
+                let width = (max - min).max(title).max(self.config.action_width) + padding * 2.0;
+                let column = (left, width, left + width / 2.0 - (min + max) / 2.0);
+                left += width;
+                column
+            })
+            .collect()
+    }
+
+    /// Проход layout с заданными центрами дорожек; возвращает состояние и нижнюю границу
+    fn run<'a>(&self, diagram: &ActivityDiagram, lanes: &'a [String], centers: Vec<f64>) -> (Flow<'a>, f64) {
+        let lane_count = centers.len();
+        let mut flow = Flow {
+            elements: Vec::new(),
+            lanes,
+            centers,
+            extents: vec![None; lane_count],
+            lane: 0,
+            exit: None,
+        };
+
+        let mut y = self.config.margin;
+        if !lanes.is_empty() {
+            y += self.config.swimlane_header_height + self.config.vertical_spacing;
+        }
+        let y = self.layout_block(&diagram.elements, 0.0, y, &mut flow);
+
+        // Недорисованный путь выхода (например, из цикла в конце диаграммы)
+        if let Some(exit) = flow.exit.take() {
+            if exit.points.len() > 1 {
+                self.add_path(exit.points, exit.label, false, &mut flow);
+            }
+        }
+
+        (flow, y)
+    }
+
+    /// Размер действия по тексту, но не меньше стандартного
+    fn action_size(&self, action: &Action) -> (f64, f64) {
+        let text = self.config.text.size_at(&action.label, self.config.text.font_size);
+        (
+            self.config.action_width.max(text.width + 20.0),
+            self.config.action_height.max(text.height + 16.0),
+        )
+    }
+
+    /// Размер ромба с подписью (вытянут по ширине текста)
+    fn diamond_size(&self, label: &str) -> (f64, f64) {
+        if label.is_empty() {
+            return (self.config.diamond_width, self.config.diamond_height);
+        }
+        let text = self.config.text.size_at(label, self.config.text.font_size);
+        let height = self.config.diamond_height.max(text.height + 8.0);
+        (self.config.diamond_width.max(text.width + height), height)
+    }
+
+    /// Ширина блока: ветки условий и fork стоят рядом друг с другом
+    fn block_width(&self, elements: &[ActivityElement]) -> f64 {
+        let spacing = self.config.horizontal_spacing;
+        elements
+            .iter()
+            .map(|element| match element {
+                ActivityElement::Action(action) => self.action_size(action).0,
+                ActivityElement::Start | ActivityElement::Stop | ActivityElement::End => {
+                    self.config.node_radius * 2.0
+                }
+                ActivityElement::Condition(cond) => {
+                    let diamond = self.diamond_size(&cond.condition).0;
+                    let branches = Self::condition_branches(cond);
+                    if branches.len() == 1 {
+                        // Ветка then под ромбом, обход справа — симметричный запас
+                        let half = self.block_width(branches[0].0).max(diamond) / 2.0;
+                        (half + spacing / 2.0) * 2.0
+                    } else {
+                        self.row_width(branches.iter().map(|(branch, _)| *branch)).max(diamond)
+                    }
+                }
+                ActivityElement::While(while_loop) => {
+                    let diamond = self.diamond_size(&while_loop.condition).0;
+                    self.block_width(&while_loop.body).max(diamond) + spacing * 2.0
+                }
+                ActivityElement::Repeat(repeat_loop) => {
+                    let diamond = self.diamond_size(&repeat_loop.condition).0;
+                    self.block_width(&repeat_loop.body).max(diamond) + spacing * 2.0
+                }
+                ActivityElement::Fork(fork) => self
+                    .row_width(fork.branches.iter().map(Vec::as_slice))
+                    .max(self.config.bar_width),
+                _ => 0.0,
+            })
+            .fold(0.0, f64::max)
+    }
+
+    /// Ширина веток, стоящих в ряд
+    fn row_width<'b>(&self, branches: impl Iterator<Item = &'b [ActivityElement]>) -> f64 {
+        let widths: Vec<f64> = branches.map(|branch| self.block_width(branch)).collect();
+        widths.iter().sum::<f64>() + widths.len().saturating_sub(1) as f64 * self.config.horizontal_spacing
+    }
+
+    /// Смещения центров веток, стоящих в ряд вокруг `offset`
+    fn row_offsets<'b>(&self, branches: impl Iterator<Item = &'b [ActivityElement]>, offset: f64) -> Vec<f64> {
+        let widths: Vec<f64> = branches.map(|branch| self.block_width(branch)).collect();
+        let total = widths.iter().sum::<f64>()
+            + widths.len().saturating_sub(1) as f64 * self.config.horizontal_spacing;
+        let mut left = offset - total / 2.0;
+        widths
+            .iter()
+            .map(|width| {
+                let center = left + width / 2.0;
+                left += width + self.config.horizontal_spacing;
+                center
+            })
+            .collect()
+    }
+
+    /// Ветки условия с подписями: then, elseif…, else
+    fn condition_branches(cond: &Condition) -> Vec<(&[ActivityElement], Option<String>)> {
+        let mut branches = vec![(cond.then_branch.as_slice(), cond.then_label.clone())];
+        for branch in &cond.elseif_branches {
+            let label = branch.label.clone().or_else(|| Some(branch.condition.clone()));
+            branches.push((branch.elements.as_slice(), label));
+        }
+        if let Some(else_branch) = &cond.else_branch {
+            branches.push((else_branch.as_slice(), cond.else_label.clone()));
+        }
+        branches
+    }
+
+    /// Располагает последовательность элементов и возвращает новую Y позицию
+    fn layout_block(&self, elements: &[ActivityElement], offset: f64, current_y: f64, flow: &mut Flow) -> f64 {
+        let mut offset = offset;
+        let mut y = current_y;
+        for element in elements {
+            if let ActivityElement::SwimlaneChange(name) = element {
+                // Новая дорожка — элементы встают в центр её колонки
+                if let Some(lane) = flow.lanes.iter().position(|lane| lane == name) {
+                    flow.lane = lane;
+                    offset = 0.0;
+                }
+                continue;
+            }
+            y = self.layout_element(element, offset, y, flow);
+        }
+        y
+    }
+
     /// Располагает элемент и возвращает новую Y позицию
     fn layout_element(
         &self,
         element: &ActivityElement,
-        center_x: f64,
+        offset: f64,
         current_y: f64,
-        elements: &mut Vec<LayoutElement>,
+        flow: &mut Flow,
     ) -> f64 {
         match element {
-            ActivityElement::Start => self.layout_start(center_x, current_y, elements),
-            ActivityElement::Stop => self.layout_stop(center_x, current_y, elements),
-            ActivityElement::End => self.layout_end(center_x, current_y, elements),
-            ActivityElement::Action(action) => {
-                self.layout_action(action, center_x, current_y, elements)
-            }
-            ActivityElement::Condition(cond) => {
-                self.layout_condition(cond, center_x, current_y, elements)
-            }
-            ActivityElement::While(while_loop) => {
-                self.layout_while(while_loop, center_x, current_y, elements)
-            }
+            ActivityElement::Start => self.layout_start(offset, current_y, flow),
+            ActivityElement::Stop => self.layout_stop(offset, current_y, flow),
+            ActivityElement::End => self.layout_end(offset, current_y, flow),
+            ActivityElement::Action(action) => self.layout_action(action, offset, current_y, flow),
+            ActivityElement::Condition(cond) => self.layout_condition(cond, offset, current_y, flow),
+            ActivityElement::While(while_loop) => self.layout_while(while_loop, offset, current_y, flow),
             ActivityElement::Repeat(repeat_loop) => {
-                self.layout_repeat(repeat_loop, center_x, current_y, elements)
-            }
-            ActivityElement::Fork(fork) => {
-                self.layout_fork(fork, center_x, current_y, elements)
+                self.layout_repeat(repeat_loop, offset, current_y, flow)
             }
+            ActivityElement::Fork(fork) => self.layout_fork(fork, offset, current_y, flow),
             ActivityElement::Detach | ActivityElement::Kill => {
-                // Detach/Kill просто прерывают поток, не рисуем ничего
+                // Detach/Kill прерывают поток, не рисуем ничего
+                flow.exit = None;
                 current_y
             }
             ActivityElement::Note(_) => {
@@ -91,423 +384,366 @@ impl ActivityLayoutEngine {
                 current_y
             }
             ActivityElement::SwimlaneChange(_) | ActivityElement::Connector(_) => {
-                // TODO: swimlanes и коннекторы
+                // Дорожки переключает layout_block; TODO: коннекторы
                 current_y
             }
         }
     }
 
     /// Располагает начальный узел (filled circle)
-    fn layout_start(
-        &self,
-        center_x: f64,
-        current_y: f64,
-        elements: &mut Vec<LayoutElement>,
-    ) -> f64 {
+    fn layout_start(&self, offset: f64, current_y: f64, flow: &mut Flow) -> f64 {
         let r = self.config.node_radius;
-        
-        elements.push(LayoutElement {
-            id: format!("start_{}", elements.len()),
-            bounds: Rect::new(center_x - r, current_y, r * 2.0, r * 2.0),
-            text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Ellipse { label: None },
+        let x = flow.x(offset);
+        flow.occupy(flow.lane, offset - r, offset + r);
+        self.connect(Point::new(x, current_y), None, flow);
+
+        flow.elements.push(LayoutElement {
+            id: flow.next_id("start"),
+            bounds: Rect::new(x - r, current_y, r * 2.0, r * 2.0),
+            text: None,
+            properties: HashMap::new(),
+            element_type: ElementType::Ellipse { label: None },
         });
+        flow.exit = Some(Exit::down(Point::new(x, current_y + r * 2.0)));
 
-        let next_y = current_y + r * 2.0 + self.config.vertical_spacing;
-
-        // Стрелка вниз от start
-        self.add_arrow(
-            center_x,
-            current_y + r * 2.0,
-            center_x,
-            next_y,
-            None,
-            elements,
-        );
-
-        next_y
+        current_y + r * 2.0 + self.config.vertical_spacing
     }
 
     /// Располагает конечный узел (filled circle with ring)
-    fn layout_stop(
-        &self,
-        center_x: f64,
-        current_y: f64,
-        elements: &mut Vec<LayoutElement>,
-    ) -> f64 {
+    fn layout_stop(&self, offset: f64, current_y: f64, flow: &mut Flow) -> f64 {
         let r = self.config.node_radius;
-        
-        elements.push(LayoutElement {
-            id: format!("stop_{}", elements.len()),
-            bounds: Rect::new(center_x - r, current_y, r * 2.0, r * 2.0),
-            text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Ellipse { 
+        let x = flow.x(offset);
+        flow.occupy(flow.lane, offset - r, offset + r);
+        self.connect(Point::new(x, current_y), None, flow);
+
+        flow.elements.push(LayoutElement {
+            id: flow.next_id("stop"),
+            bounds: Rect::new(x - r, current_y, r * 2.0, r * 2.0),
+            text: None,
+            properties: HashMap::new(),
+            element_type: ElementType::Ellipse {
                 label: Some("●".to_string()) // Внутренний круг
             },
         });
+        flow.exit = None;
 
         current_y + r * 2.0 + self.config.vertical_spacing
     }
 
     /// Располагает конечный узел (альтернативный)
-    fn layout_end(
-        &self,
-        center_x: f64,
-        current_y: f64,
-        elements: &mut Vec<LayoutElement>,
-    ) -> f64 {
-        self.layout_stop(center_x, current_y, elements)
+    fn layout_end(&self, offset: f64, current_y: f64, flow: &mut Flow) -> f64 {
+        self.layout_stop(offset, current_y, flow)
     }
 
     /// Располагает действие (rounded rectangle)
-    fn layout_action(
-        &self,
-        action: &Action,
-        center_x: f64,
-        current_y: f64,
-        elements: &mut Vec<LayoutElement>,
-    ) -> f64 {
-        let w = self.config.action_width;
-        let h = self.config.action_height;
-
-        elements.push(LayoutElement {
-            id: format!("action_{}", elements.len()),
-            bounds: Rect::new(center_x - w / 2.0, current_y, w, h),
-            text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Rectangle {
+    fn layout_action(&self, action: &Action, offset: f64, current_y: f64, flow: &mut Flow) -> f64 {
+        let (w, h) = self.action_size(action);
+        let x = flow.x(offset);
+        flow.occupy(flow.lane, offset - w / 2.0, offset + w / 2.0);
+        self.connect(Point::new(x, current_y), action.arrow_label.clone(), flow);
+
+        flow.elements.push(LayoutElement {
+            id: flow.next_id("action"),
+            bounds: Rect::new(x - w / 2.0, current_y, w, h),
+            text: None,
+            properties: HashMap::new(),
+            element_type: ElementType::Rectangle {
                 label: action.label.clone(),
                 corner_radius: self.config.action_corner_radius,
             },
-        });
-
-        let next_y = current_y + h + self.config.vertical_spacing;
+        }.with_style(&ElementStyle::new().with_fill(action.background_color.as_ref())));
+        flow.exit = Some(Exit::down(Point::new(x, current_y + h)));
 
-        // Стрелка вниз
-        self.add_arrow(center_x, current_y + h, center_x, next_y, None, elements);
-
-        next_y
+        current_y + h + self.config.vertical_spacing
     }
 
-    /// Располагает условие (if/else)
-    fn layout_condition(
-        &self,
-        cond: &Condition,
-        center_x: f64,
-        current_y: f64,
-        elements: &mut Vec<LayoutElement>,
-    ) -> f64 {
-        let dw = self.config.diamond_width;
-        let dh = self.config.diamond_height;
-
-        // Ромб условия
-        elements.push(LayoutElement {
-            id: format!("diamond_{}", elements.len()),
-            bounds: Rect::new(center_x - dw / 2.0, current_y, dw, dh),
-            text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Text {
-                text: cond.condition.clone(),
-                font_size: 12.0,
+    /// Добавляет ромб с центром по X в `x`, возвращает его границы
+    fn add_diamond(&self, prefix: &str, label: &str, x: f64, y: f64, flow: &mut Flow) -> Rect {
+        let (w, h) = self.diamond_size(label);
+        let bounds = Rect::new(x - w / 2.0, y, w, h);
+        flow.elements.push(LayoutElement {
+            id: flow.next_id(prefix),
+            bounds,
+            text: None,
+            properties: HashMap::new(),
+            element_type: ElementType::Diamond {
+                label: (!label.is_empty()).then(|| label.to_string()),
             },
         });
+        bounds
+    }
 
-        let after_diamond = current_y + dh + self.config.vertical_spacing / 2.0;
-        let branch_start_y = after_diamond;
-
-        // Then branch (left)
-        let left_x = center_x - self.config.horizontal_spacing;
-        let mut then_end_y = branch_start_y;
-        
-        // Стрелка от ромба влево + вниз
-        self.add_arrow(
-            center_x - dw / 2.0,
-            current_y + dh / 2.0,
-            left_x,
-            branch_start_y,
-            cond.then_label.clone(),
-            elements,
-        );
+    /// Выход из ромба к ветке с центром в `branch_x`: снизу или из бокового угла
+    fn diamond_exit(diamond: &Rect, branch_x: f64) -> Exit {
+        let center = diamond.center();
+        if (branch_x - center.x).abs() < 0.5 {
+            return Exit::down(diamond.bottom_center());
+        }
+        let tip = if branch_x < center.x {
+            diamond.left_center()
+        } else {
+            diamond.right_center()
+        };
+        Exit {
+            points: vec![tip, Point::new(branch_x, tip.y)],
+            label: None,
+        }
+    }
 
-        for elem in &cond.then_branch {
-            then_end_y = self.layout_element(elem, left_x, then_end_y, elements);
+    /// Сводит выход ветки в ромб слияния: сверху или в боковой угол
+    fn join_diamond(&self, exit: Exit, diamond: &Rect, flow: &mut Flow) {
+        let center = diamond.center();
+        let from = exit.end();
+        let mut points = exit.points;
+        if (from.x - center.x).abs() < 0.5 {
+            points.push(diamond.top_center());
+        } else {
+            let tip = if from.x < center.x {
+                diamond.left_center()
+            } else {
+                diamond.right_center()
+            };
+            points.push(Point::new(from.x, tip.y));
+            points.push(tip);
         }
+        self.add_path(points, exit.label, true, flow);
+    }
 
-        // Else branch (right) if exists
-        let mut else_end_y = branch_start_y;
-        if let Some(else_branch) = &cond.else_branch {
-            let right_x = center_x + self.config.horizontal_spacing;
-            
-            // Стрелка от ромба вправо + вниз
-            self.add_arrow(
-                center_x + dw / 2.0,
-                current_y + dh / 2.0,
-                right_x,
-                branch_start_y,
-                cond.else_label.clone(),
-                elements,
-            );
-
-            for elem in else_branch {
-                else_end_y = self.layout_element(elem, right_x, else_end_y, elements);
-            }
+    /// Располагает условие (if/elseif/else) с ромбами ветвления и слияния
+    fn layout_condition(&self, cond: &Condition, offset: f64, current_y: f64, flow: &mut Flow) -> f64 {
+        let x = flow.x(offset);
+        let lane = flow.lane;
+        self.connect(Point::new(x, current_y), None, flow);
+        let diamond = self.add_diamond("diamond", &cond.condition, x, current_y, flow);
+        flow.occupy(lane, offset - diamond.width / 2.0, offset + diamond.width / 2.0);
+
+        let branches = Self::condition_branches(cond);
+        let branch_start_y = current_y + diamond.height + self.config.vertical_spacing;
+
+        // Без else ветка then идёт прямо вниз, а обход — справа от неё
+        let (offsets, bypass) = if branches.len() == 1 {
+            let half = self.block_width(branches[0].0).max(diamond.width) / 2.0;
+            (vec![offset], Some(offset + half + self.config.horizontal_spacing / 2.0))
+        } else {
+            (self.row_offsets(branches.iter().map(|(branch, _)| *branch), offset), None)
+        };
+
+        let mut exits = Vec::new();
+        let mut merge_y = branch_start_y;
+        for ((branch, label), branch_offset) in branches.into_iter().zip(offsets) {
+            flow.lane = lane;
+            let branch_x = x + (branch_offset - offset);
+            flow.exit = Some(Self::diamond_exit(&diamond, branch_x).with_label(label));
+            let end_y = self.layout_block(branch, branch_offset, branch_start_y, flow);
+            merge_y = merge_y.max(end_y);
+            exits.extend(flow.exit.take());
+        }
+        if let Some(bypass) = bypass {
+            flow.occupy(lane, bypass, bypass);
+            let bypass_x = x + (bypass - offset);
+            exits.push(Self::diamond_exit(&diamond, bypass_x).with_label(cond.else_label.clone()));
         }
 
-        // Точка слияния
-        let merge_y = then_end_y.max(else_end_y);
-        
-        // Стрелки к точке слияния
-        if then_end_y < merge_y {
-            self.add_arrow(left_x, then_end_y, center_x, merge_y, None, elements);
+        // Все ветки завершились (stop/detach) — слияния нет
+        if exits.is_empty() {
+            return merge_y;
         }
-        if cond.else_branch.is_some() && else_end_y < merge_y {
-            let right_x = center_x + self.config.horizontal_spacing;
-            self.add_arrow(right_x, else_end_y, center_x, merge_y, None, elements);
+
+        let merge = self.add_diamond("merge", "", x, merge_y, flow);
+        for exit in exits {
+            self.join_diamond(exit, &merge, flow);
         }
+        flow.exit = Some(Exit::down(merge.bottom_center()));
 
-        merge_y + self.config.vertical_spacing
+        merge_y + merge.height + self.config.vertical_spacing
     }
 
     /// Располагает цикл while
-    fn layout_while(
-        &self,
-        while_loop: &WhileLoop,
-        center_x: f64,
-        current_y: f64,
-        elements: &mut Vec<LayoutElement>,
-    ) -> f64 {
-        let dw = self.config.diamond_width;
-        let dh = self.config.diamond_height;
-
-        // Ромб условия
-        elements.push(LayoutElement {
-            id: format!("while_diamond_{}", elements.len()),
-            bounds: Rect::new(center_x - dw / 2.0, current_y, dw, dh),
-            text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Text {
-                text: while_loop.condition.clone(),
-                font_size: 12.0,
-            },
-        });
+    fn layout_while(&self, while_loop: &WhileLoop, offset: f64, current_y: f64, flow: &mut Flow) -> f64 {
+        let x = flow.x(offset);
+        let lane = flow.lane;
+        self.connect(Point::new(x, current_y), None, flow);
+        let diamond = self.add_diamond("while_diamond", &while_loop.condition, x, current_y, flow);
 
-        let body_start_y = current_y + dh + self.config.vertical_spacing;
-        let mut body_end_y = body_start_y;
+        let half = self.block_width(&while_loop.body).max(diamond.width) / 2.0
+            + self.config.horizontal_spacing / 2.0;
+        flow.occupy(lane, offset - half, offset + half);
 
         // Тело цикла
-        for elem in &while_loop.body {
-            body_end_y = self.layout_element(elem, center_x, body_end_y, elements);
+        flow.exit = Some(Exit::down(diamond.bottom_center()));
+        let body_end_y = self.layout_block(
+            &while_loop.body,
+            offset,
+            current_y + diamond.height + self.config.vertical_spacing,
+            flow,
+        );
+
+        // Обратная стрелка: вниз -> влево -> вверх -> вправо к ромбу
+        if let Some(exit) = flow.exit.take() {
+            let from = exit.end();
+            let turn_y = from.y + self.config.vertical_spacing / 2.0;
+            let tip = diamond.left_center();
+            let mut points = exit.points;
+            points.extend([
+                Point::new(from.x, turn_y),
+                Point::new(x - half, turn_y),
+                Point::new(x - half, tip.y),
+                tip,
+            ]);
+            self.add_path(points, while_loop.backward_label.clone(), true, flow);
         }
 
-        // Обратная стрелка (loop back)
-        let loop_x = center_x - self.config.horizontal_spacing - 20.0;
-        
-        // Вниз -> влево -> вверх -> вправо к ромбу
-        elements.push(LayoutElement {
-            id: format!("while_loop_{}", elements.len()),
-            bounds: Rect::new(loop_x, current_y, center_x - loop_x, body_end_y - current_y),
-            text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Edge {
-                points: vec![
-                    Point::new(center_x, body_end_y - self.config.vertical_spacing),
-                    Point::new(loop_x, body_end_y - self.config.vertical_spacing),
-                    Point::new(loop_x, current_y + dh / 2.0),
-                    Point::new(center_x - dw / 2.0, current_y + dh / 2.0),
-                ],
-                label: while_loop.backward_label.clone(),
-                arrow_start: false,
-                arrow_end: true,
-                dashed: false,
-                edge_type: EdgeType::Association, from_cardinality: None, to_cardinality: None,
-            },
+        // Выход из цикла: из правого угла ромба вниз мимо тела
+        let tip = diamond.right_center();
+        flow.lane = lane;
+        flow.exit = Some(Exit {
+            points: vec![tip, Point::new(x + half, tip.y), Point::new(x + half, body_end_y)],
+            label: while_loop.end_label.clone(),
         });
 
-        // Стрелка выхода из цикла (вправо)
-        let exit_x = center_x + self.config.horizontal_spacing;
-        self.add_arrow(
-            center_x + dw / 2.0,
-            current_y + dh / 2.0,
-            exit_x,
-            body_end_y,
-            while_loop.end_label.clone(),
-            elements,
-        );
-
-        body_end_y + self.config.vertical_spacing
+        body_end_y + self.config.vertical_spacing / 2.0
     }
 
     /// Располагает цикл repeat
-    fn layout_repeat(
-        &self,
-        repeat_loop: &RepeatLoop,
-        center_x: f64,
-        current_y: f64,
-        elements: &mut Vec<LayoutElement>,
-    ) -> f64 {
-        let body_start_y = current_y;
-        let mut body_end_y = body_start_y;
-
-        // Тело цикла (выполняется первым)
-        for elem in &repeat_loop.body {
-            body_end_y = self.layout_element(elem, center_x, body_end_y, elements);
-        }
+    fn layout_repeat(&self, repeat_loop: &RepeatLoop, offset: f64, current_y: f64, flow: &mut Flow) -> f64 {
+        let x = flow.x(offset);
+        let lane = flow.lane;
+        self.connect(Point::new(x, current_y), None, flow);
+        let start = self.add_diamond("repeat_start", "", x, current_y, flow);
 
-        // Ромб условия внизу
-        let dw = self.config.diamond_width;
-        let dh = self.config.diamond_height;
+        let condition_width = self.diamond_size(&repeat_loop.condition).0;
+        let half = self.block_width(&repeat_loop.body).max(condition_width) / 2.0
+            + self.config.horizontal_spacing / 2.0;
+        flow.occupy(lane, offset - half, offset + half);
 
-        elements.push(LayoutElement {
-            id: format!("repeat_diamond_{}", elements.len()),
-            bounds: Rect::new(center_x - dw / 2.0, body_end_y, dw, dh),
-            text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Text {
-                text: repeat_loop.condition.clone(),
-                font_size: 12.0,
-            },
-        });
+        // Тело цикла (выполняется первым)
+        flow.exit = Some(Exit::down(start.bottom_center()));
+        let body_end_y = self.layout_block(
+            &repeat_loop.body,
+            offset,
+            current_y + start.height + self.config.vertical_spacing,
+            flow,
+        );
 
-        // Обратная стрелка
-        let loop_x = center_x + self.config.horizontal_spacing + 20.0;
-        
-        elements.push(LayoutElement {
-            id: format!("repeat_loop_{}", elements.len()),
-            bounds: Rect::new(center_x, body_start_y, loop_x - center_x, body_end_y - body_start_y + dh),
-            text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Edge {
-                points: vec![
-                    Point::new(center_x + dw / 2.0, body_end_y + dh / 2.0),
-                    Point::new(loop_x, body_end_y + dh / 2.0),
-                    Point::new(loop_x, body_start_y),
-                    Point::new(center_x, body_start_y),
-                ],
-                label: repeat_loop.backward_label.clone(),
-                arrow_start: false,
-                arrow_end: true,
-                dashed: false,
-                edge_type: EdgeType::Association, from_cardinality: None, to_cardinality: None,
-            },
-        });
+        // Ромб условия внизу, в колонке начала цикла
+        flow.lane = lane;
+        self.connect(Point::new(x, body_end_y), None, flow);
+        let diamond = self.add_diamond("repeat_diamond", &repeat_loop.condition, x, body_end_y, flow);
+
+        // Обратная стрелка: из правого угла условия вверх к началу цикла
+        let from = diamond.right_center();
+        let to = start.right_center();
+        self.add_path(
+            vec![from, Point::new(x + half, from.y), Point::new(x + half, to.y), to],
+            repeat_loop.backward_label.clone(),
+            true,
+            flow,
+        );
 
-        body_end_y + dh + self.config.vertical_spacing
+        flow.exit = Some(Exit::down(diamond.bottom_center()));
+        body_end_y + diamond.height + self.config.vertical_spacing
     }
 
     /// Располагает fork/join
-    fn layout_fork(
-        &self,
-        fork: &Fork,
-        center_x: f64,
-        current_y: f64,
-        elements: &mut Vec<LayoutElement>,
-    ) -> f64 {
-        let num_branches = fork.branches.len();
-        if num_branches == 0 {
+    fn layout_fork(&self, fork: &Fork, offset: f64, current_y: f64, flow: &mut Flow) -> f64 {
+        if fork.branches.is_empty() {
             return current_y;
         }
-
-        // Fork bar
-        let total_width = (num_branches as f64 - 1.0) * self.config.horizontal_spacing + 
-                          self.config.action_width;
-        let fork_bar_x = center_x - total_width / 2.0;
-
-        elements.push(LayoutElement {
-            id: format!("fork_bar_{}", elements.len()),
-            bounds: Rect::new(
-                fork_bar_x,
-                current_y,
-                total_width,
-                self.config.bar_height,
-            ),
-            text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Rectangle {
+        let x = flow.x(offset);
+        let lane = flow.lane;
+        self.connect(Point::new(x, current_y), None, flow);
+
+        // Fork bar на ширину всех веток
+        let offsets = self.row_offsets(fork.branches.iter().map(Vec::as_slice), offset);
+        let total_width = self
+            .row_width(fork.branches.iter().map(Vec::as_slice))
+            .max(self.config.bar_width);
+        flow.occupy(lane, offset - total_width / 2.0, offset + total_width / 2.0);
+        let bar_x = x - total_width / 2.0;
+
+        flow.elements.push(LayoutElement {
+            id: flow.next_id("fork_bar"),
+            bounds: Rect::new(bar_x, current_y, total_width, self.config.bar_height),
+            text: None,
+            properties: HashMap::new(),
+            element_type: ElementType::Rectangle {
                 label: String::new(),
                 corner_radius: 0.0,
             },
         });
 
         let branches_start_y = current_y + self.config.bar_height + self.config.vertical_spacing;
-        let mut max_branch_end_y = branches_start_y;
+        let mut join_y = branches_start_y;
+        let mut exits = Vec::new();
 
         // Располагаем каждую ветку
-        let branch_spacing = if num_branches > 1 {
-            total_width / (num_branches as f64 - 1.0)
-        } else {
-            0.0
-        };
-
-        for (i, branch) in fork.branches.iter().enumerate() {
-            let branch_x = if num_branches > 1 {
-                fork_bar_x + i as f64 * branch_spacing
-            } else {
-                center_x
-            };
-
-            // Стрелка от fork bar к началу ветки
-            self.add_arrow(
-                branch_x,
-                current_y + self.config.bar_height,
-                branch_x,
-                branches_start_y,
-                None,
-                elements,
-            );
-
-            let mut branch_y = branches_start_y;
-            for elem in branch {
-                branch_y = self.layout_element(elem, branch_x, branch_y, elements);
-            }
-
-            max_branch_end_y = max_branch_end_y.max(branch_y);
+        for (branch, branch_offset) in fork.branches.iter().zip(offsets) {
+            flow.lane = lane;
+            let branch_x = x + (branch_offset - offset);
+            flow.exit = Some(Exit::down(Point::new(branch_x, current_y + self.config.bar_height)));
+            let end_y = self.layout_block(branch, branch_offset, branches_start_y, flow);
+            join_y = join_y.max(end_y);
+            exits.extend(flow.exit.take());
         }
 
-        // Join bar
-        let join_y = max_branch_end_y;
-        
-        elements.push(LayoutElement {
-            id: format!("join_bar_{}", elements.len()),
-            bounds: Rect::new(
-                fork_bar_x,
-                join_y,
-                total_width,
-                self.config.bar_height,
-            ),
-            text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Rectangle {
+        // Join bar и стрелки от веток к нему
+        flow.lane = lane;
+        flow.elements.push(LayoutElement {
+            id: flow.next_id("join_bar"),
+            bounds: Rect::new(bar_x, join_y, total_width, self.config.bar_height),
+            text: None,
+            properties: HashMap::new(),
+            element_type: ElementType::Rectangle {
                 label: String::new(),
                 corner_radius: 0.0,
             },
         });
-
-        // Стрелки от веток к join bar
-        for i in 0..num_branches {
-            let branch_x = if num_branches > 1 {
-                fork_bar_x + i as f64 * branch_spacing
-            } else {
-                center_x
-            };
-
-            self.add_arrow(
-                branch_x,
-                max_branch_end_y - self.config.vertical_spacing,
-                branch_x,
-                join_y,
-                None,
-                elements,
-            );
+        for exit in exits {
+            let from = exit.end();
+            flow.exit = Some(exit);
+            self.connect(Point::new(from.x.clamp(bar_x, bar_x + total_width), join_y), None, flow);
         }
+        flow.exit = Some(Exit::down(Point::new(x, join_y + self.config.bar_height)));
 
         join_y + self.config.bar_height + self.config.vertical_spacing
     }
 
-    /// Добавляет стрелку
-    fn add_arrow(
-        &self,
-        x1: f64,
-        y1: f64,
-        x2: f64,
-        y2: f64,
-        label: Option<String>,
-        elements: &mut Vec<LayoutElement>,
-    ) {
-        let min_x = x1.min(x2);
-        let min_y = y1.min(y2);
-        let max_x = x1.max(x2);
-        let max_y = y1.max(y2);
-
-        elements.push(LayoutElement {
-            id: format!("arrow_{}", elements.len()),
+    /// Соединяет выход предыдущего элемента с точкой входа сверху
+    ///
+    /// Если элементы в разных колонках (другая дорожка или ветка), стрелка
+    /// поворачивает посередине между ними.
+    fn connect(&self, entry: Point, label: Option<String>, flow: &mut Flow) {
+        let Some(exit) = flow.exit.take() else {
+            return;
+        };
+        let from = exit.end();
+        let mut points = exit.points;
+        if (from.x - entry.x).abs() >= 0.5 {
+            let mid_y = (from.y + entry.y) / 2.0;
+            points.push(Point::new(from.x, mid_y));
+            points.push(Point::new(entry.x, mid_y));
+        }
+        points.push(entry);
+        self.add_path(points, exit.label.or(label), true, flow);
+    }
+
+    /// Добавляет ломаную стрелку
+    fn add_path(&self, points: Vec<Point>, label: Option<String>, arrow_end: bool, flow: &mut Flow) {
+        let min_x = points.iter().map(|p| p.x).fold(f64::MAX, f64::min);
+        let min_y = points.iter().map(|p| p.y).fold(f64::MAX, f64::min);
+        let max_x = points.iter().map(|p| p.x).fold(f64::MIN, f64::max);
+        let max_y = points.iter().map(|p| p.y).fold(f64::MIN, f64::max);
+
+        flow.elements.push(LayoutElement {
+            id: flow.next_id("arrow"),
             bounds: Rect::new(min_x, min_y, (max_x - min_x).max(1.0), (max_y - min_y).max(1.0)),
-            text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Edge {
-                points: vec![Point::new(x1, y1), Point::new(x2, y2)],
+            text: None,
+            properties: HashMap::new(),
+            element_type: ElementType::Edge {
+                points,
                 label,
                 arrow_start: false,
-                arrow_end: true,
+                arrow_end,
                 dashed: false,
                 edge_type: EdgeType::Association, from_cardinality: None, to_cardinality: None,
             },
@@ -526,6 +762,29 @@ mod tests {
     use super::*;
     use plantuml_ast::activity::ActionStyle;
 
+    #[test]
+    fn test_action_width_fits_label() {
+        let diagram = ActivityDiagram {
+            elements: vec![ActivityElement::Action(Action {
+                label: "Очень длинное название действия, которое не влезает".to_string(),
+                background_color: None,
+                style: ActionStyle::default(),
+                arrow_label: None,
+            })],
+            ..Default::default()
+        };
+        let engine = ActivityLayoutEngine::new();
+        let result = engine.layout(&diagram);
+
+        let action = result
+            .elements
+            .iter()
+            .find(|e| e.id.starts_with("action_"))
+            .unwrap();
+        assert!(action.bounds.width > engine.config.action_width);
+        assert!(action.bounds.x >= engine.config.margin);
+    }
+
     #[test]
     fn test_layout_simple() {
         let mut diagram = ActivityDiagram::new();
@@ -585,4 +844,133 @@ mod tests {
         // Должны быть fork bar, 2 ветки, join bar
         assert!(result.elements.len() >= 10);
     }
+
+    /// Элемент-действие по подписи
+    fn action<'a>(result: &'a LayoutResult, label: &str) -> &'a LayoutElement {
+        result
+            .elements
+            .iter()
+            .find(|e| matches!(&e.element_type, ElementType::Rectangle { label: l, .. } if l == label))
+            .unwrap()
+    }
+
+    fn swimlane_diagram() -> ActivityDiagram {
+        let mut diagram = ActivityDiagram::new();
+        diagram.elements = vec![
+            ActivityElement::SwimlaneChange("Клиент".to_string()),
+            ActivityElement::Start,
+            ActivityElement::Action(Action::new("заказ")),
+            ActivityElement::SwimlaneChange("Склад".to_string()),
+            ActivityElement::Action(Action::new("сборка")),
+            ActivityElement::SwimlaneChange("Клиент".to_string()),
+            ActivityElement::Action(Action::new("получение")),
+            ActivityElement::Stop,
+        ];
+        diagram
+    }
+
+    #[test]
+    fn test_swimlanes_are_full_height_columns() {
+        let engine = ActivityLayoutEngine::new();
+        let result = engine.layout(&swimlane_diagram());
+
+        let lanes: Vec<_> = result
+            .elements
+            .iter()
+            .filter(|e| matches!(e.element_type, ElementType::Swimlane { .. }))
+            .collect();
+        assert_eq!(lanes.len(), 2);
+
+        // Колонки идут встык слева направо и одинаковой высоты
+        assert!((lanes[0].bounds.x + lanes[0].bounds.width - lanes[1].bounds.x).abs() < 0.01);
+        assert_eq!(lanes[0].bounds.height, lanes[1].bounds.height);
+
+        // Каждое действие внутри колонки своей дорожки
+        let inside = |action: &LayoutElement, lane: &LayoutElement| {
+            action.bounds.x >= lane.bounds.x
+                && action.bounds.x + action.bounds.width <= lane.bounds.x + lane.bounds.width
+                && action.bounds.y >= lane.bounds.y + engine.config.swimlane_header_height
+                && action.bounds.y + action.bounds.height <= lane.bounds.y + lane.bounds.height
+        };
+        assert!(inside(action(&result, "заказ"), lanes[0]));
+        assert!(inside(action(&result, "сборка"), lanes[1]));
+        assert!(inside(action(&result, "получение"), lanes[0]));
+    }
+
+    #[test]
+    fn test_cross_lane_arrow_has_elbow() {
+        let engine = ActivityLayoutEngine::new();
+        let result = engine.layout(&swimlane_diagram());
+
+        let from = action(&result, "заказ").bounds;
+        let to = action(&result, "сборка").bounds;
+        let edge = result
+            .elements
+            .iter()
+            .find_map(|e| match &e.element_type {
+                ElementType::Edge { points, .. }
+                    if points.first() == Some(&from.bottom_center())
+                        && points.last() == Some(&to.top_center()) =>
+                {
+                    Some(points)
+                }
+                _ => None,
+            })
+            .unwrap();
+
+        // Вниз, поперёк дорожек, вниз — только ортогональные отрезки
+        assert_eq!(edge.len(), 4);
+        for pair in edge.windows(2) {
+            assert!(pair[0].x == pair[1].x || pair[0].y == pair[1].y);
+        }
+    }
+
+    #[test]
+    fn test_condition_branches_merge_in_diamond() {
+        let mut diagram = ActivityDiagram::new();
+        diagram.elements = vec![
+            ActivityElement::Start,
+            ActivityElement::Condition(Condition {
+                condition: "оплачено?".to_string(),
+                then_branch: vec![ActivityElement::Action(Action::new("отгрузить"))],
+                then_label: Some("да".to_string()),
+                elseif_branches: vec![],
+                else_branch: Some(vec![ActivityElement::Action(Action::new("напомнить"))]),
+                else_label: Some("нет".to_string()),
+            }),
+            ActivityElement::Stop,
+        ];
+
+        let result = ActivityLayoutEngine::new().layout(&diagram);
+
+        let diamonds: Vec<_> = result
+            .elements
+            .iter()
+            .filter(|e| matches!(e.element_type, ElementType::Diamond { .. }))
+            .collect();
+        assert_eq!(diamonds.len(), 2);
+        let (branch, merge) = (diamonds[0], diamonds[1]);
+        assert!(matches!(&branch.element_type, ElementType::Diamond { label: Some(l) } if l == "оплачено?"));
+
+        // Ветки рядом друг с другом между ромбами
+        let yes = action(&result, "отгрузить").bounds;
+        let no = action(&result, "напомнить").bounds;
+        assert!(yes.x + yes.width <= no.x);
+        assert!(yes.y > branch.bounds.y + branch.bounds.height);
+        assert!(merge.bounds.y > yes.y + yes.height);
+
+        // Обе ветки входят в ромб слияния
+        let into_merge = result
+            .elements
+            .iter()
+            .filter(|e| match &e.element_type {
+                ElementType::Edge { points, .. } => {
+                    let end = *points.last().unwrap();
+                    end == merge.bounds.left_center() || end == merge.bounds.right_center()
+                }
+                _ => false,
+            })
+            .count();
+        assert_eq!(into_merge, 2);
+    }
 }
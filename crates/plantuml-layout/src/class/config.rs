@@ -1,5 +1,7 @@
 //! Конфигурация для Class Layout Engine
 
+use crate::text::TextMetrics;
+
 /// Конфигурация layout'а class diagrams
 #[derive(Debug, Clone)]
 pub struct ClassLayoutConfig {
@@ -19,8 +21,12 @@ pub struct ClassLayoutConfig {
     pub class_padding: f64,
     /// Отступ от границ диаграммы
     pub margin: f64,
-    /// Ширина символа (приблизительно)
-    pub char_width: f64,
+    /// Внутренний отступ рамки пакета
+    pub package_padding: f64,
+    /// Высота вкладки пакета с названием
+    pub package_header_height: f64,
+    /// Метрики текста (шрифт темы)
+    pub text: TextMetrics,
 }
 
 impl Default for ClassLayoutConfig {
@@ -34,7 +40,9 @@ impl Default for ClassLayoutConfig {
             line_height: 20.0,
             class_padding: 10.0,
             margin: 20.0,
-            char_width: 8.0,
+            package_padding: 15.0,
+            package_header_height: 22.0,
+            text: TextMetrics::default(),
         }
     }
 }
@@ -1,13 +1,19 @@
 //! ClassLayoutEngine - layout engine для диаграмм классов.
 
-use plantuml_ast::class::{ClassDiagram, ClassifierType, RelationshipType};
-use plantuml_model::{Point, Rect};
+use plantuml_ast::class::{ClassDiagram, Classifier, ClassifierType, RelationshipType};
+use plantuml_ast::common::LineType;
+use plantuml_model::Rect;
 
+use crate::routing::{EdgeSpec, Router, Side};
+use crate::text::TextMetrics;
 use crate::traits::LayoutEngine;
-use crate::{ClassMember, ClassifierKind, EdgeType, ElementType, LayoutConfig, LayoutElement, LayoutResult, MemberVisibility};
+use crate::{
+    ClassMember, ClassifierKind, EdgeType, ElementStyle, ElementType, LayoutConfig, LayoutElement, LayoutResult,
+    MemberVisibility,
+};
 
 use super::config::ClassLayoutConfig;
-use super::graph::Graph;
+use super::graph::{Cluster, Edge, Graph, Node};
 use super::sugiyama::SugiyamaLayout;
 
 /// Layout engine для Class Diagrams
@@ -30,6 +36,12 @@ impl ClassLayoutEngine {
         Self { config }
     }
 
+    /// Устанавливает метрики текста (шрифт темы)
+    pub fn with_text_metrics(mut self, text: TextMetrics) -> Self {
+        self.config.text = text;
+        self
+    }
+
     /// Выполняет layout диаграммы классов
     pub fn layout_diagram(&self, diagram: &ClassDiagram) -> LayoutResult {
         if diagram.classifiers.is_empty() && diagram.packages.is_empty() {
@@ -38,12 +50,19 @@ impl ClassLayoutEngine {
 
         // Строим граф и выполняем Sugiyama layout
         let mut graph = Graph::from_diagram(diagram, &self.config);
-        let mut sugiyama = SugiyamaLayout::new(&mut graph, &self.config);
+        let direction = diagram.metadata.direction;
+        let mut sugiyama = SugiyamaLayout::new(&mut graph, &self.config).with_direction(direction);
         sugiyama.run();
+        let horizontal = direction.is_horizontal();
 
         // Преобразуем результат в LayoutElements
         let mut elements = Vec::new();
 
+        // Рамки пакетов — под классами, внешние раньше вложенных
+        for cluster in graph.clusters.iter().filter(|c| c.layers.is_some()) {
+            elements.push(self.create_package_element(cluster));
+        }
+
         // Добавляем узлы (классы)
         for node in &graph.nodes {
             // Ищем оригинальный classifier для получения деталей
@@ -56,16 +75,36 @@ impl ClassLayoutEngine {
                     Self::find_classifier_in_packages(&diagram.packages, &node.classifier_name)
                 });
 
-            let element = self.create_class_element(node, classifier, diagram);
+            let element = self
+                .create_class_element(node, classifier, diagram)
+                .with_link(classifier.and_then(|c| c.link.as_ref()))
+                .with_source(classifier.map(|c| c.span).unwrap_or_default())
+                .with_style(&classifier.map(classifier_style).unwrap_or_default())
+                .with_stereotype(classifier.and_then(|c| c.stereotype.as_ref()));
             elements.push(element);
         }
 
-        // Добавляем рёбра (отношения)
-        for edge in &graph.edges {
-            let from_node = &graph.nodes[edge.from];
-            let to_node = &graph.nodes[edge.to];
-
-            let edge_element = self.create_edge_element(edge, from_node, to_node);
+        // Добавляем рёбра (отношения); скрытые только влияли на layout.
+        // Рёбра обходят классы и рамки чужих пакетов
+        let visible: Vec<&Edge> = graph.edges.iter().filter(|edge| !edge.hidden).collect();
+        let router = Router::new(diagram.metadata.line_type.unwrap_or(LineType::Ortho), self.config.text.clone())
+            .with_obstacles(graph.nodes.iter().map(Self::node_rect))
+            .with_frames(
+                graph
+                    .clusters
+                    .iter()
+                    .filter(|c| c.layers.is_some())
+                    .map(|c| Rect::new(c.x, c.y, c.width, c.height)),
+            );
+        let specs: Vec<EdgeSpec> = visible
+            .iter()
+            .map(|edge| Self::edge_spec(edge, &graph, horizontal))
+            .collect();
+        for (edge, route) in visible.iter().zip(router.route_all(&specs)) {
+            let mut edge_element = self
+                .create_edge_element(edge, &graph.nodes[edge.from], &graph.nodes[edge.to])
+                .with_source(edge.span);
+            route.apply(&mut edge_element);
             elements.push(edge_element);
         }
 
@@ -83,6 +122,88 @@ impl ClassLayoutEngine {
         result
     }
 
+    /// Создаёт LayoutElement рамки пакета
+    fn create_package_element(&self, cluster: &Cluster) -> LayoutElement {
+        LayoutElement {
+            id: format!("package_{}", cluster.name.replace(' ', "_")),
+            bounds: Rect::new(cluster.x, cluster.y, cluster.width, cluster.height),
+            text: None,
+            properties: std::collections::HashMap::new(),
+            element_type: ElementType::Package {
+                name: cluster.name.clone(),
+                stereotype: cluster.stereotype.clone(),
+                tab_width: cluster.tab_width,
+                tab_height: cluster.tab_height,
+            },
+        }
+        .with_style(&ElementStyle::new().with_fill(cluster.background_color.as_ref()))
+    }
+
+    /// Прямоугольник узла
+    fn node_rect(node: &Node) -> Rect {
+        Rect::new(node.x, node.y, node.size.width, node.size.height)
+    }
+
+    /// Концы ребра в направлении рисования
+    ///
+    /// В графе родитель наследования — начало ребра (слой выше), а стрелка
+    /// идёт от потомка к родителю; композиция и агрегация — от владельца
+    /// к части.
+    fn visual_ends<'a>(edge: &Edge, from_node: &'a Node, to_node: &'a Node) -> (&'a Node, &'a Node) {
+        match edge.relationship_type {
+            RelationshipType::Inheritance | RelationshipType::Realization => (to_node, from_node),
+            _ => (from_node, to_node),
+        }
+    }
+
+    /// Кардинальности у визуальных начала и конца ребра
+    fn cardinalities(edge: &Edge) -> (Option<String>, Option<String>) {
+        let (from_card, to_card) = match edge.relationship_type {
+            RelationshipType::Inheritance | RelationshipType::Realization => {
+                // Визуально стрелка идёт от to_node к from_node
+                (edge.to_cardinality.clone(), edge.from_cardinality.clone())
+            }
+            _ => (edge.from_cardinality.clone(), edge.to_cardinality.clone()),
+        };
+        if edge.reversed {
+            (to_card, from_card)
+        } else {
+            (from_card, to_card)
+        }
+    }
+
+    /// Описание ребра для маршрутизатора
+    ///
+    /// Наследование между слоями соединяет грань потомка, обращённую к
+    /// родителю, с противоположной гранью родителя независимо от их
+    /// взаимного смещения; остальные связи выбирают грани сами.
+    fn edge_spec(edge: &Edge, graph: &Graph, horizontal: bool) -> EdgeSpec {
+        let (from_node, to_node) = (&graph.nodes[edge.from], &graph.nodes[edge.to]);
+        let (visual_from, visual_to) = Self::visual_ends(edge, from_node, to_node);
+
+        let (from_side, to_side) = match edge.relationship_type {
+            RelationshipType::Inheritance | RelationshipType::Realization if from_node.layer != to_node.layer => {
+                let sides = if visual_from.layer > visual_to.layer {
+                    (Side::Top, Side::Bottom)
+                } else {
+                    (Side::Bottom, Side::Top)
+                };
+                if horizontal {
+                    (Some(sides.0.transpose()), Some(sides.1.transpose()))
+                } else {
+                    (Some(sides.0), Some(sides.1))
+                }
+            }
+            _ => (None, None),
+        };
+
+        let (from_card, to_card) = Self::cardinalities(edge);
+        EdgeSpec::new(Self::node_rect(visual_from), Self::node_rect(visual_to))
+            .with_sides(from_side, to_side)
+            .with_label(edge.label.as_deref())
+            .with_cardinalities(from_card.as_deref(), to_card.as_deref())
+    }
+
     /// Ищет classifier в пакетах рекурсивно
     fn find_classifier_in_packages<'a>(
         packages: &'a [plantuml_ast::class::Package],
@@ -125,9 +246,10 @@ impl ClassLayoutEngine {
                     ClassifierType::Entity => Some("entity".to_string()),
                     _ => None,
                 };
-                (kind, stereo)
+                (kind, stereo.filter(|_| !c.hidden_parts.stereotype))
             })
             .unwrap_or((ClassifierKind::Class, None));
+        let hidden = classifier.map(|c| c.hidden_parts).unwrap_or_default();
 
         // Конвертируем поля
         let fields: Vec<ClassMember> = classifier
@@ -187,6 +309,9 @@ impl ClassLayoutEngine {
                 stereotype,
                 fields,
                 methods,
+                show_circle: !hidden.circle,
+                show_fields: !hidden.fields,
+                show_methods: !hidden.methods,
             },
         }
     }
@@ -202,34 +327,9 @@ impl ClassLayoutEngine {
     }
 
     /// Создаёт LayoutElement для ребра (отношения)
-    fn create_edge_element(
-        &self,
-        edge: &super::graph::Edge,
-        from_node: &super::graph::Node,
-        to_node: &super::graph::Node,
-    ) -> LayoutElement {
-        // Определяем визуальное направление стрелки
-        // В графе: from_node = родитель (слой 0, вверху), to_node = потомок (ниже)
-        // Для наследования стрелка должна идти ОТ потомка К родителю (снизу вверх)
-        // Для композиции/агрегации стрелка идёт ОТ владельца К части
-        let (visual_from, visual_to) = match edge.relationship_type {
-            RelationshipType::Inheritance | RelationshipType::Realization => {
-                // Стрелка от потомка к родителю
-                (to_node, from_node)
-            }
-            _ => (from_node, to_node),
-        };
-
-        // Вычисляем точки соединения (передаём тип связи для правильного выбора грани)
-        let (start_point, end_point) = self.calculate_connection_points(
-            visual_from, 
-            visual_to, 
-            edge.relationship_type
-        );
-
-        // Создаём путь с ортогональными линиями
-        let points = self.create_orthogonal_path(start_point, end_point, visual_from, visual_to);
-
+    ///
+    /// Путь и позиции подписей задаёт маршрутизатор.
+    fn create_edge_element(&self, edge: &Edge, from_node: &Node, to_node: &Node) -> LayoutElement {
         // Определяем стрелки и тип линии на основе типа отношения
         // arrow_end = маркер на конце линии (у целевого узла)
         let (arrow_start, arrow_end, dashed, edge_type) = match edge.relationship_type {
@@ -249,22 +349,15 @@ impl ClassLayoutEngine {
             (arrow_start, arrow_end)
         };
 
-        // Для кардинальностей: если ребро было инвертировано для визуала, меняем местами
-        let (from_card, to_card) = match edge.relationship_type {
-            RelationshipType::Inheritance | RelationshipType::Realization => {
-                // Визуально стрелка идёт от to_node к from_node
-                (edge.to_cardinality.clone(), edge.from_cardinality.clone())
-            }
-            _ => (edge.from_cardinality.clone(), edge.to_cardinality.clone()),
-        };
+        let (from_card, to_card) = Self::cardinalities(edge);
 
         LayoutElement {
             id: format!("edge_{}_{}", from_node.id, to_node.id),
-            bounds: self.calculate_edge_bounds(&points),
+            bounds: Rect::new(0.0, 0.0, 0.0, 0.0),
             text: None,
             properties: std::collections::HashMap::new(),
             element_type: ElementType::Edge {
-                points,
+                points: Vec::new(),
                 label: edge.label.clone(),
                 arrow_start,
                 arrow_end,
@@ -274,118 +367,7 @@ impl ClassLayoutEngine {
                 to_cardinality: to_card,
             },
         }
-    }
-
-    /// Вычисляет точки соединения между двумя узлами
-    fn calculate_connection_points(
-        &self,
-        from: &super::graph::Node,
-        to: &super::graph::Node,
-        relationship_type: RelationshipType,
-    ) -> (Point, Point) {
-        let from_center_x = from.x + from.size.width / 2.0;
-        let to_center_x = to.x + to.size.width / 2.0;
-
-        // Для наследования и реализации ВСЕГДА используем верхнюю/нижнюю грань
-        // независимо от горизонтального расположения узлов
-        match relationship_type {
-            RelationshipType::Inheritance | RelationshipType::Realization => {
-                // from = потомок (снизу), to = родитель (сверху)
-                // Стрелка выходит из верхней грани потомка, входит в нижнюю грань родителя
-                let start = Point::new(from_center_x, from.y); // верх потомка
-                let end = Point::new(to_center_x, to.y + to.size.height); // низ родителя
-                (start, end)
-            }
-            _ => {
-                // Для других типов связей - автоопределение направления
-                let from_center_y = from.y + from.size.height / 2.0;
-                let to_center_y = to.y + to.size.height / 2.0;
-                let dx = to_center_x - from_center_x;
-                let dy = to_center_y - from_center_y;
-
-                if dy.abs() > dx.abs() {
-                    // Вертикальное соединение
-                    if dy > 0.0 {
-                        (
-                            Point::new(from_center_x, from.y + from.size.height),
-                            Point::new(to_center_x, to.y),
-                        )
-                    } else {
-                        (
-                            Point::new(from_center_x, from.y),
-                            Point::new(to_center_x, to.y + to.size.height),
-                        )
-                    }
-                } else {
-                    // Горизонтальное соединение
-                    if dx > 0.0 {
-                        (
-                            Point::new(from.x + from.size.width, from_center_y),
-                            Point::new(to.x, to_center_y),
-                        )
-                    } else {
-                        (
-                            Point::new(from.x, from_center_y),
-                            Point::new(to.x + to.size.width, to_center_y),
-                        )
-                    }
-                }
-            }
-        }
-    }
-
-    /// Создаёт ортогональный путь между точками (с коленом)
-    fn create_orthogonal_path(
-        &self,
-        start: Point,
-        end: Point,
-        _from: &super::graph::Node,
-        _to: &super::graph::Node,
-    ) -> Vec<Point> {
-        let dx = end.x - start.x;
-        let dy = end.y - start.y;
-
-        // Если точки почти на одной линии - прямая
-        if dx.abs() < 1.0 || dy.abs() < 1.0 {
-            return vec![start, end];
-        }
-
-        // Ортогональный путь с коленом
-        // Для вертикального наследования (потомок снизу, родитель сверху):
-        // start = верх потомка, end = низ родителя
-        // Линия: вверх от потомка → горизонтально → вниз к родителю
-        
-        // Вычисляем Y для горизонтального сегмента
-        // Это должно быть между нижней гранью родителя и верхней гранью потомка
-        let mid_y = if dy < 0.0 {
-            // end выше start (типичное наследование: потомок внизу)
-            // mid_y = середина между end.y и start.y
-            end.y + (start.y - end.y) / 2.0
-        } else {
-            // end ниже start
-            start.y + (end.y - start.y) / 2.0
-        };
-
-        vec![
-            start,
-            Point::new(start.x, mid_y),
-            Point::new(end.x, mid_y),
-            end,
-        ]
-    }
-
-    /// Вычисляет bounds для ребра
-    fn calculate_edge_bounds(&self, points: &[Point]) -> Rect {
-        if points.is_empty() {
-            return Rect::new(0.0, 0.0, 0.0, 0.0);
-        }
-
-        let min_x = points.iter().map(|p| p.x).fold(f64::MAX, f64::min);
-        let min_y = points.iter().map(|p| p.y).fold(f64::MAX, f64::min);
-        let max_x = points.iter().map(|p| p.x).fold(f64::MIN, f64::max);
-        let max_y = points.iter().map(|p| p.y).fold(f64::MIN, f64::max);
-
-        Rect::new(min_x, min_y, max_x - min_x, max_y - min_y)
+        .with_style(&edge.style)
     }
 }
 
@@ -403,6 +385,18 @@ impl LayoutEngine for ClassLayoutEngine {
     }
 }
 
+/// Стиль рамки класса: `#pink;line:red;line.dashed;text:blue`
+fn classifier_style(classifier: &Classifier) -> ElementStyle {
+    let style = ElementStyle::new()
+        .with_fill(classifier.background_color.as_ref())
+        .with_stroke(classifier.border_color.as_ref())
+        .with_text_color(classifier.text_color.as_ref());
+    match classifier.line_style {
+        Some(line_style) => style.with_line_style(line_style),
+        None => style,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -522,4 +516,75 @@ mod tests {
         assert!(result.bounds.width > 0.0);
         assert!(result.bounds.height > 0.0);
     }
+
+    #[test]
+    fn test_packages_frames_and_routing() {
+        use plantuml_ast::class::Package;
+
+        let mut package = Package::new("core");
+        package.stereotype = Some(plantuml_ast::common::Stereotype::new("Layer"));
+        package.classifiers.push(Classifier::new("Mid"));
+
+        let mut diagram = ClassDiagram::new();
+        diagram.add_class(Classifier::new("Top"));
+        diagram.add_class(Classifier::new("Bottom"));
+        diagram.packages.push(package);
+        diagram.add_relationship(Relationship::new("Top", "Mid", RelationshipType::Association));
+        diagram.add_relationship(Relationship::new("Mid", "Bottom", RelationshipType::Association));
+        diagram.add_relationship(Relationship::new("Top", "Bottom", RelationshipType::Association));
+
+        let result = ClassLayoutEngine::new().layout_diagram(&diagram);
+        let element = |id: &str| result.elements.iter().find(|e| e.id == id).unwrap();
+
+        let frame = element("package_core");
+        match &frame.element_type {
+            ElementType::Package { name, stereotype, .. } => {
+                assert_eq!(name, "core");
+                assert_eq!(stereotype.as_deref(), Some("Layer"));
+            }
+            other => panic!("ожидался пакет, получено {:?}", other),
+        }
+        let mid = element("Mid").bounds;
+        assert!(mid.x > frame.bounds.x && mid.x + mid.width < frame.bounds.x + frame.bounds.width);
+        assert!(mid.y > frame.bounds.y && mid.y + mid.height < frame.bounds.y + frame.bounds.height);
+
+        // Ребро Top → Bottom обходит чужой пакет
+        let ElementType::Edge { points, .. } = &element("edge_Top_Bottom").element_type else {
+            panic!("ожидалось ребро");
+        };
+        assert!(points
+            .windows(2)
+            .all(|segment| !crate::routing::segment_hits_rect(segment[0], segment[1], &frame.bounds)));
+    }
+
+    #[test]
+    fn test_left_to_right_and_hidden_edges() {
+        use plantuml_ast::common::Direction;
+
+        let mut diagram = ClassDiagram::new();
+        diagram.metadata.direction = Direction::LeftToRight;
+        diagram.add_class(Classifier::new("A"));
+        diagram.add_class(Classifier::new("B"));
+        diagram.add_class(Classifier::new("C"));
+        diagram.add_relationship(Relationship::new("A", "B", RelationshipType::Association));
+        let mut hidden = Relationship::new("B", "C", RelationshipType::Association);
+        hidden.hidden = true;
+        diagram.add_relationship(hidden);
+
+        let result = ClassLayoutEngine::new().layout_diagram(&diagram);
+        let bounds = |id: &str| result.elements.iter().find(|e| e.id == id).unwrap().bounds;
+
+        // Слои слева направо: скрытая связь B → C тоже задаёт слой
+        let (a, b, c) = (bounds("A"), bounds("B"), bounds("C"));
+        assert!(b.x >= a.x + a.width);
+        assert!(c.x >= b.x + b.width);
+
+        // Видимое ребро выходит из правой грани A, скрытое не рисуется
+        let edge = result.elements.iter().find(|e| e.id == "edge_A_B").unwrap();
+        let ElementType::Edge { points, .. } = &edge.element_type else {
+            panic!("ожидалось ребро");
+        };
+        assert_eq!(points[0].x, a.x + a.width);
+        assert!(!result.elements.iter().any(|e| e.id == "edge_B_C"));
+    }
 }
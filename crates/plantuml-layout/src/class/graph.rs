@@ -1,13 +1,18 @@
 //! Структуры данных графа для алгоритма Sugiyama.
 //!
-//! Граф строится из ClassDiagram: узлы = классы, рёбра = отношения.
+//! Граф строится из ClassDiagram: узлы = классы, рёбра = отношения,
+//! кластеры = пакеты и namespace (составной граф).
+//! Другие диаграммы собирают граф вручную через [`Graph::add_node`] и
+//! [`Graph::add_edge`].
 
 use std::collections::HashMap;
 
-use plantuml_ast::class::{ClassDiagram, Classifier, Relationship, RelationshipType};
+use plantuml_ast::class::{ClassDiagram, Classifier, Member, Package, Relationship, RelationshipType};
+use plantuml_ast::common::{Color, Direction, Span};
 use plantuml_model::Size;
 
 use super::config::ClassLayoutConfig;
+use crate::ElementStyle;
 
 /// Узел графа (класс/интерфейс)
 #[derive(Debug, Clone)]
@@ -28,6 +33,8 @@ pub struct Node {
     pub x: f64,
     /// Y координата (после layout)
     pub y: f64,
+    /// Ближайший кластер (пакет), содержащий узел
+    pub cluster: Option<usize>,
 }
 
 impl Node {
@@ -48,19 +55,39 @@ impl Node {
             position: 0,
             x: 0.0,
             y: 0.0,
+            cluster: None,
+        }
+    }
+
+    /// Создаёт узел заданного размера (не класс)
+    pub fn with_size(id: impl Into<String>, index: usize, size: Size) -> Self {
+        let id = id.into();
+        Self {
+            classifier_name: id.clone(),
+            id,
+            index,
+            size,
+            layer: 0,
+            position: 0,
+            x: 0.0,
+            y: 0.0,
+            cluster: None,
         }
     }
 
     /// Вычисляет размер узла на основе содержимого класса
     fn calculate_size(classifier: &Classifier, config: &ClassLayoutConfig) -> Size {
         // Ширина: max(имя класса, поля, методы)
-        // Добавляем место для иконки класса (~30px)
-        let icon_width = 30.0;
+        // Добавляем место для иконки класса (~30px), если она не скрыта
+        let hidden = classifier.hidden_parts;
+        let icon_width = if hidden.circle { 0.0 } else { 30.0 };
+        // Скрытые секции (`hide fields`, `hide methods`) не занимают места
+        let fields: &[Member] = if hidden.fields { &[] } else { &classifier.fields };
+        let methods: &[Member] = if hidden.methods { &[] } else { &classifier.methods };
         let name_width =
-            classifier.id.name.len() as f64 * config.char_width + icon_width + config.class_padding * 2.0;
+            config.text.bold_width_at(&classifier.id.name, config.text.font_size) + icon_width + config.class_padding * 2.0;
 
-        let field_max_width = classifier
-            .fields
+        let field_max_width = fields
             .iter()
             .map(|f| {
                 // Учитываем тип поля: "+name: type"
@@ -69,13 +96,12 @@ impl Node {
                 } else {
                     format!("{}{}", f.visibility.to_char(), f.name)
                 };
-                text.len() as f64 * config.char_width
+                config.text.width(&text)
             })
             .max_by(|a, b| a.partial_cmp(b).unwrap())
             .unwrap_or(0.0);
 
-        let method_max_width = classifier
-            .methods
+        let method_max_width = methods
             .iter()
             .map(|m| {
                 // Учитываем return type (member_type в AST): "+method(): type"
@@ -84,7 +110,7 @@ impl Node {
                 } else {
                     format!("{}{}()", m.visibility.to_char(), m.name)
                 };
-                text.len() as f64 * config.char_width
+                config.text.width(&text)
             })
             .max_by(|a, b| a.partial_cmp(b).unwrap())
             .unwrap_or(0.0);
@@ -96,7 +122,8 @@ impl Node {
 
         // Высота: заголовок + поля + методы
         // Заголовок включает: иконку + стереотип (если есть) + имя класса
-        let has_stereotype = classifier.classifier_type != plantuml_ast::class::ClassifierType::Class;
+        let has_stereotype =
+            classifier.classifier_type != plantuml_ast::class::ClassifierType::Class && !hidden.stereotype;
         let header_height = if has_stereotype {
             // Стереотип + имя = больше высоты
             config.class_header_height + 12.0
@@ -105,21 +132,27 @@ impl Node {
         };
 
         // Секция полей
-        let fields_height = if classifier.fields.is_empty() {
+        let fields_height = if fields.is_empty() {
             0.0
         } else {
-            classifier.fields.len() as f64 * config.line_height + config.class_padding
+            fields.len() as f64 * config.line_height + config.class_padding
         };
 
         // Секция методов
-        let methods_height = if classifier.methods.is_empty() {
+        let methods_height = if methods.is_empty() {
             0.0
         } else {
-            classifier.methods.len() as f64 * config.line_height + config.class_padding
+            methods.len() as f64 * config.line_height + config.class_padding
         };
 
         let height = header_height + fields_height + methods_height + config.class_padding;
-        let height = height.max(config.min_class_height);
+        // Минимальная высота — место под пустые секции; скрытые секции
+        // (`hide empty members`, `hide fields`) её не занимают
+        let height = if hidden.fields || hidden.methods {
+            height
+        } else {
+            height.max(config.min_class_height)
+        };
 
         Size::new(width, height)
     }
@@ -142,6 +175,15 @@ pub struct Edge {
     pub to_cardinality: Option<String>,
     /// Обратное ребро (для удаления циклов)
     pub reversed: bool,
+    /// Подсказка размещения вдоль слоёв: где `to` относительно `from`
+    /// (`TopToBottom` — следующий слой, `LeftToRight` — тот же слой правее)
+    pub direction: Option<Direction>,
+    /// Скрытое ребро: участвует в layout, но не рисуется
+    pub hidden: bool,
+    /// Цвет, стиль линии и цвет текста связи
+    pub style: ElementStyle,
+    /// Позиция отношения в исходнике
+    pub span: Span,
 }
 
 impl Edge {
@@ -155,6 +197,95 @@ impl Edge {
             from_cardinality: rel.from_cardinality.clone(),
             to_cardinality: rel.to_cardinality.clone(),
             reversed: false,
+            direction: rel.direction,
+            hidden: rel.hidden,
+            style: ElementStyle::from_inline(&rel.style),
+            span: rel.span,
+        }
+    }
+
+    /// Создаёт простую связь без метки и кардинальностей
+    pub fn link(from: usize, to: usize) -> Self {
+        Self {
+            from,
+            to,
+            relationship_type: RelationshipType::Link,
+            label: None,
+            from_cardinality: None,
+            to_cardinality: None,
+            reversed: false,
+            direction: None,
+            hidden: false,
+            style: ElementStyle::default(),
+            span: Span::empty(),
+        }
+    }
+
+    /// Ребро внутри слоя (подсказка `-left->`/`-right->`)
+    pub fn is_flat(&self) -> bool {
+        self.direction.is_some_and(Direction::is_horizontal)
+    }
+}
+
+/// Кластер графа — пакет или namespace, рамка вокруг своих узлов
+#[derive(Debug, Clone)]
+pub struct Cluster {
+    /// Имя пакета
+    pub name: String,
+    /// Родительский кластер
+    pub parent: Option<usize>,
+    /// Стереотип (имена через запятую)
+    pub stereotype: Option<String>,
+    /// Цвет фона
+    pub background_color: Option<Color>,
+    /// Ширина вкладки с названием
+    pub tab_width: f64,
+    /// Высота вкладки с названием
+    pub tab_height: f64,
+    /// Первый и последний слой узлов кластера (после layout;
+    /// None — в кластере нет узлов)
+    pub layers: Option<(usize, usize)>,
+    /// X координата рамки (после layout)
+    pub x: f64,
+    /// Y координата рамки (после layout)
+    pub y: f64,
+    /// Ширина рамки (после layout)
+    pub width: f64,
+    /// Высота рамки (после layout)
+    pub height: f64,
+}
+
+impl Cluster {
+    /// Создаёт кластер для пакета
+    pub fn new(package: &Package, parent: Option<usize>, config: &ClassLayoutConfig) -> Self {
+        let stereotype = package
+            .stereotype
+            .as_ref()
+            .map(|s| s.names.join(", "));
+        let font_size = config.text.font_size;
+        let name_width = config.text.bold_width_at(&package.name, font_size);
+        let stereotype_width = stereotype
+            .as_ref()
+            .map(|s| config.text.width(&format!("«{}»", s)))
+            .unwrap_or(0.0);
+        let tab_height = if stereotype.is_some() {
+            config.package_header_height + config.text.line_height()
+        } else {
+            config.package_header_height
+        };
+
+        Self {
+            name: package.name.clone(),
+            parent,
+            stereotype,
+            background_color: package.background_color.clone(),
+            tab_width: name_width.max(stereotype_width) + config.class_padding * 2.0,
+            tab_height,
+            layers: None,
+            x: 0.0,
+            y: 0.0,
+            width: 0.0,
+            height: 0.0,
         }
     }
 }
@@ -172,13 +303,53 @@ pub struct Graph {
     pub adjacency: Vec<Vec<usize>>,
     /// Обратные списки смежности (входящие рёбра)
     pub reverse_adjacency: Vec<Vec<usize>>,
+    /// Кластеры в порядке обхода в глубину (родитель раньше вложенных)
+    pub clusters: Vec<Cluster>,
 }
 
 impl Graph {
+    /// Создаёт пустой граф
+    pub fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            node_index: HashMap::new(),
+            adjacency: Vec::new(),
+            reverse_adjacency: Vec::new(),
+            clusters: Vec::new(),
+        }
+    }
+
+    /// Добавляет узел заданного размера и возвращает его индекс
+    ///
+    /// Повторное добавление узла с тем же id возвращает существующий индекс.
+    pub fn add_node(&mut self, id: impl Into<String>, size: Size) -> usize {
+        let id = id.into();
+        if let Some(&index) = self.node_index.get(&id) {
+            return index;
+        }
+        let index = self.nodes.len();
+        self.node_index.insert(id.clone(), index);
+        self.nodes.push(Node::with_size(id, index, size));
+        self.adjacency.push(Vec::new());
+        self.reverse_adjacency.push(Vec::new());
+        index
+    }
+
+    /// Добавляет связь между узлами по их индексам и возвращает индекс ребра
+    pub fn add_edge(&mut self, from: usize, to: usize) -> usize {
+        let edge_idx = self.edges.len();
+        self.edges.push(Edge::link(from, to));
+        self.adjacency[from].push(edge_idx);
+        self.reverse_adjacency[to].push(edge_idx);
+        edge_idx
+    }
+
     /// Создаёт граф из ClassDiagram
     pub fn from_diagram(diagram: &ClassDiagram, config: &ClassLayoutConfig) -> Self {
         let mut nodes = Vec::new();
         let mut node_index = HashMap::new();
+        let mut clusters = Vec::new();
 
         // Создаём узлы из классификаторов
         for classifier in &diagram.classifiers {
@@ -193,8 +364,10 @@ impl Graph {
         // Также добавляем узлы из пакетов (рекурсивно)
         Self::collect_classifiers_from_packages(
             &diagram.packages,
+            None,
             &mut nodes,
             &mut node_index,
+            &mut clusters,
             config,
         );
 
@@ -205,16 +378,11 @@ impl Graph {
                     let index = nodes.len();
                     node_index.insert(name.clone(), index);
                     // Создаём минимальный узел
-                    nodes.push(Node {
-                        id: name.clone(),
+                    nodes.push(Node::with_size(
+                        name.clone(),
                         index,
-                        classifier_name: name.clone(),
-                        size: Size::new(config.min_class_width, config.min_class_height),
-                        layer: 0,
-                        position: 0,
-                        x: 0.0,
-                        y: 0.0,
-                    });
+                        Size::new(config.min_class_width, config.min_class_height),
+                    ));
                 }
             }
         }
@@ -231,13 +399,17 @@ impl Graph {
             {
                 // Для наследования/реализации: в AST from=дочерний, to=родитель
                 // В графе для layout: родитель → потомок (чтобы родитель был на слое 0)
-                let (graph_from, graph_to) = match rel.relationship_type {
+                let edge = match rel.relationship_type {
                     RelationshipType::Inheritance | RelationshipType::Realization => {
-                        (to_idx, from_idx) // Родитель → Потомок (родитель на слое 0)
+                        // Родитель → Потомок (родитель на слое 0); подсказка
+                        // задана для записанного порядка и разворачивается
+                        let mut edge = Edge::new(to_idx, from_idx, rel);
+                        edge.direction = rel.direction.map(Direction::opposite);
+                        edge
                     }
-                    _ => (from_idx, to_idx),
+                    _ => Edge::new(from_idx, to_idx, rel),
                 };
-                edges.push(Edge::new(graph_from, graph_to, rel));
+                edges.push(edge);
             }
         }
 
@@ -257,27 +429,42 @@ impl Graph {
             node_index,
             adjacency,
             reverse_adjacency,
+            clusters,
         }
     }
 
-    /// Собирает классификаторы из пакетов рекурсивно
+    /// Собирает классификаторы из пакетов рекурсивно, создавая кластеры
     fn collect_classifiers_from_packages(
-        packages: &[plantuml_ast::class::Package],
+        packages: &[Package],
+        parent: Option<usize>,
         nodes: &mut Vec<Node>,
         node_index: &mut HashMap<String, usize>,
+        clusters: &mut Vec<Cluster>,
         config: &ClassLayoutConfig,
     ) {
         for package in packages {
+            let cluster = clusters.len();
+            clusters.push(Cluster::new(package, parent, config));
+
             for classifier in &package.classifiers {
                 let id = classifier.id.name.clone();
                 if !node_index.contains_key(&id) {
                     let index = nodes.len();
                     node_index.insert(id.clone(), index);
-                    nodes.push(Node::new(id, index, classifier, config));
+                    let mut node = Node::new(id, index, classifier, config);
+                    node.cluster = Some(cluster);
+                    nodes.push(node);
                 }
             }
             // Рекурсивно обрабатываем вложенные пакеты
-            Self::collect_classifiers_from_packages(&package.packages, nodes, node_index, config);
+            Self::collect_classifiers_from_packages(
+                &package.packages,
+                Some(cluster),
+                nodes,
+                node_index,
+                clusters,
+                config,
+            );
         }
     }
 
@@ -317,12 +504,42 @@ impl Graph {
         self.nodes.iter().map(|n| n.layer).max().unwrap_or(0)
     }
 
+    /// Цепочка кластеров узла от внешнего к ближайшему
+    pub fn cluster_chain(&self, node: usize) -> Vec<usize> {
+        let mut chain = Vec::new();
+        let mut cluster = self.nodes[node].cluster;
+        while let Some(c) = cluster {
+            chain.push(c);
+            cluster = self.clusters[c].parent;
+        }
+        chain.reverse();
+        chain
+    }
+
+    /// Является ли `ancestor` кластером `cluster` или его предком
+    pub fn cluster_contains(&self, ancestor: usize, cluster: usize) -> bool {
+        let mut current = Some(cluster);
+        while let Some(c) = current {
+            if c == ancestor {
+                return true;
+            }
+            current = self.clusters[c].parent;
+        }
+        false
+    }
+
     /// Получает узел по имени
     pub fn get_node_by_name(&self, name: &str) -> Option<&Node> {
         self.node_index.get(name).map(|&idx| &self.nodes[idx])
     }
 }
 
+impl Default for Graph {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -362,4 +579,68 @@ mod tests {
         assert!(node.size.width >= config.min_class_width);
         assert!(node.size.height >= config.min_class_height);
     }
+
+    #[test]
+    fn test_node_size_with_hidden_members() {
+        let config = ClassLayoutConfig::default();
+        let header = config.class_header_height + config.class_padding;
+
+        // `hide empty members`: пустой класс — только заголовок
+        let mut classifier = Classifier::new("Empty");
+        classifier.hidden_parts.fields = true;
+        classifier.hidden_parts.methods = true;
+        let node = Node::new("Empty".to_string(), 0, &classifier, &config);
+        assert_eq!(node.size.height, header);
+        assert!(node.size.height < config.min_class_height);
+
+        // `hide methods`: заголовок и одна строка полей
+        let mut classifier = Classifier::new("User");
+        classifier.add_field(Member::field("id", "Long"));
+        classifier.hidden_parts.methods = true;
+        let node = Node::new("User".to_string(), 0, &classifier, &config);
+        assert_eq!(node.size.height, header + config.line_height + config.class_padding);
+
+        // Без скрытых секций минимальная высота сохраняется
+        let node = Node::new("Plain".to_string(), 0, &Classifier::new("Plain"), &config);
+        assert_eq!(node.size.height, config.min_class_height);
+    }
+
+    #[test]
+    fn test_graph_builder() {
+        let mut graph = Graph::new();
+        let a = graph.add_node("A", Size::new(100.0, 50.0));
+        let b = graph.add_node("B", Size::new(100.0, 50.0));
+        assert_eq!(graph.add_node("A", Size::new(10.0, 10.0)), a);
+        graph.add_edge(a, b);
+
+        assert_eq!(graph.node_count(), 2);
+        assert_eq!(graph.outgoing_edges(a).count(), 1);
+        assert_eq!(graph.incoming_edges(b).count(), 1);
+        assert_eq!(graph.get_node_by_name("B").unwrap().size.width, 100.0);
+    }
+
+    #[test]
+    fn test_packages_become_clusters() {
+        use plantuml_ast::class::Package;
+
+        let mut inner = Package::new("model");
+        inner.classifiers.push(Classifier::new("User"));
+        let mut outer = Package::new("app");
+        outer.classifiers.push(Classifier::new("Service"));
+        outer.packages.push(inner);
+
+        let mut diagram = ClassDiagram::new();
+        diagram.add_class(Classifier::new("Main"));
+        diagram.packages.push(outer);
+
+        let graph = Graph::from_diagram(&diagram, &ClassLayoutConfig::default());
+
+        assert_eq!(graph.clusters.len(), 2);
+        assert_eq!(graph.clusters[1].parent, Some(0));
+        let user = graph.get_node_by_name("User").unwrap().index;
+        assert_eq!(graph.cluster_chain(user), vec![0, 1]);
+        assert!(graph.get_node_by_name("Main").unwrap().cluster.is_none());
+        assert!(graph.cluster_contains(0, 1));
+        assert!(!graph.cluster_contains(1, 0));
+    }
 }
@@ -5,22 +5,67 @@
 //! 2. Присвоение слоёв (layer assignment)
 //! 3. Минимизация пересечений (crossing minimization)
 //! 4. Присвоение координат (coordinate assignment)
+//!
+//! Граф может быть составным: узлы кластера (пакета) остаются подряд в
+//! каждом слое, а рамки кластеров не пересекаются с чужими узлами и рамками.
+//!
+//! Слои идут сверху вниз, а при горизонтальном направлении
+//! (`left to right direction`) — слева направо: layout считается в осях
+//! слоёв с повёрнутыми размерами узлов и затем транспонируется.
+//! Подсказки рёбер задаются в тех же осях: `-down->` — следующий слой,
+//! `-up->` — предыдущий, `-left->`/`-right->` — тот же слой левее/правее.
 
+use std::cmp::Ordering;
 use std::collections::VecDeque;
 
+use plantuml_ast::common::Direction;
+use plantuml_model::Size;
+
 use super::config::ClassLayoutConfig;
-use super::graph::Graph;
+use super::graph::{Cluster, Graph};
+
+/// Элемент слоя внутри кластера (или корня): узел или вложенный кластер
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Item {
+    Node(usize),
+    Cluster(usize),
+}
 
 /// Алгоритм Sugiyama
 pub struct SugiyamaLayout<'a> {
     graph: &'a mut Graph,
     config: &'a ClassLayoutConfig,
+    /// Итоговые ключи порядка узлов в слое
+    node_keys: Vec<f64>,
+    /// Итоговые ключи порядка кластеров (общие для всех слоёв)
+    cluster_keys: Vec<f64>,
+    /// Направление слоёв
+    direction: Direction,
+    /// Представитель группы узлов одного слоя (связанных `-left->`/`-right->`)
+    rank_group: Vec<usize>,
 }
 
 impl<'a> SugiyamaLayout<'a> {
     /// Создаёт новый экземпляр алгоритма
     pub fn new(graph: &'a mut Graph, config: &'a ClassLayoutConfig) -> Self {
-        Self { graph, config }
+        let rank_group = (0..graph.node_count()).collect();
+        Self {
+            graph,
+            config,
+            node_keys: Vec::new(),
+            cluster_keys: Vec::new(),
+            direction: Direction::TopToBottom,
+            rank_group,
+        }
+    }
+
+    /// Устанавливает направление слоёв
+    ///
+    /// `BottomToTop` и `RightToLeft` раскладываются по своей оси так же,
+    /// как `TopToBottom` и `LeftToRight`.
+    pub fn with_direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
     }
 
     /// Выполняет полный layout
@@ -29,6 +74,15 @@ impl<'a> SugiyamaLayout<'a> {
             return;
         }
 
+        // Layout в осях слоёв: ширина узла — поперёк слоёв
+        let horizontal = self.direction.is_horizontal();
+        if horizontal {
+            self.transpose();
+        }
+
+        // Подсказки рёбер: обратные рёбра и группы одного слоя
+        self.apply_hints();
+
         // Шаг 1: Удаление циклов
         self.remove_cycles();
 
@@ -37,9 +91,139 @@ impl<'a> SugiyamaLayout<'a> {
 
         // Шаг 3: Минимизация пересечений (barycenter)
         self.minimize_crossings();
+        self.fix_order();
+        self.apply_flat_order();
 
         // Шаг 4: Присвоение координат
         self.assign_coordinates();
+
+        if horizontal {
+            self.transpose();
+        }
+    }
+
+    /// Меняет местами оси координат и размеров узлов и рамок
+    fn transpose(&mut self) {
+        for node in &mut self.graph.nodes {
+            std::mem::swap(&mut node.x, &mut node.y);
+            node.size = Size::new(node.size.height, node.size.width);
+        }
+        for cluster in &mut self.graph.clusters {
+            std::mem::swap(&mut cluster.x, &mut cluster.y);
+            std::mem::swap(&mut cluster.width, &mut cluster.height);
+        }
+    }
+
+    // =========================================================================
+    // Подсказки рёбер (`-up->`, `-left->`)
+    // =========================================================================
+
+    /// Применяет подсказки направления рёбер
+    ///
+    /// Ребро `-up->` обращается (как обратное ребро цикла), рёбра
+    /// `-left->`/`-right->` объединяют концы в группу одного слоя и в
+    /// назначении слоёв и барицентрах не участвуют.
+    fn apply_hints(&mut self) {
+        for edge in &mut self.graph.edges {
+            if edge.direction == Some(Direction::BottomToTop) {
+                edge.reversed = !edge.reversed;
+                std::mem::swap(&mut edge.from, &mut edge.to);
+            }
+        }
+
+        let flat: Vec<(usize, usize)> = self
+            .graph
+            .edges
+            .iter()
+            .filter(|edge| edge.is_flat())
+            .map(|edge| (edge.from, edge.to))
+            .collect();
+        for (a, b) in flat {
+            let (group_a, group_b) = (self.group_of(a), self.group_of(b));
+            if group_a != group_b {
+                self.rank_group[group_a.max(group_b)] = group_a.min(group_b);
+            }
+        }
+        for node in 0..self.graph.node_count() {
+            self.rank_group[node] = self.group_of(node);
+        }
+
+        self.rebuild_adjacency();
+    }
+
+    /// Представитель группы узла
+    fn group_of(&self, mut node: usize) -> usize {
+        while self.rank_group[node] != node {
+            node = self.rank_group[node];
+        }
+        node
+    }
+
+    /// Узлы каждой группы одного слоя (по индексу представителя)
+    fn group_members(&self) -> Vec<Vec<usize>> {
+        let mut members = vec![Vec::new(); self.graph.node_count()];
+        for (node, &group) in self.rank_group.iter().enumerate() {
+            members[group].push(node);
+        }
+        members
+    }
+
+    /// Упорядочивает концы рёбер `-left->`/`-right->` внутри слоя
+    ///
+    /// Правый конец переносится сразу за левый (между ними остаются только
+    /// другие правые соседи того же узла); концы из разных кластеров не
+    /// переставляются, чтобы не разрывать кластеры.
+    fn apply_flat_order(&mut self) {
+        let constraints: Vec<(usize, usize)> = self
+            .graph
+            .edges
+            .iter()
+            .filter_map(|edge| match edge.direction {
+                Some(Direction::LeftToRight) => Some((edge.from, edge.to)),
+                Some(Direction::RightToLeft) => Some((edge.to, edge.from)),
+                _ => None,
+            })
+            .filter(|&(left, right)| {
+                let (left, right) = (&self.graph.nodes[left], &self.graph.nodes[right]);
+                left.layer == right.layer && left.cluster == right.cluster
+            })
+            .collect();
+        if constraints.is_empty() {
+            return;
+        }
+
+        for layer in 0..=self.graph.max_layer() {
+            let mut order = self.graph.nodes_on_layer(layer);
+            order.sort_by_key(|&node| self.graph.nodes[node].position);
+
+            // Каждый проход ставит хотя бы одну пару; циклы не сходятся
+            for _ in 0..order.len() {
+                let mut changed = false;
+                for &(left, right) in &constraints {
+                    let l = order.iter().position(|&n| n == left);
+                    let r = order.iter().position(|&n| n == right);
+                    if let (Some(l), Some(r)) = (l, r) {
+                        let adjacent = r > l
+                            && order[l + 1..r]
+                                .iter()
+                                .all(|&between| constraints.contains(&(left, between)));
+                        if !adjacent {
+                            let node = order.remove(r);
+                            order.insert(if r < l { l } else { l + 1 }, node);
+                            changed = true;
+                        }
+                    }
+                }
+                if !changed {
+                    break;
+                }
+            }
+
+            for (pos, &node) in order.iter().enumerate() {
+                self.graph.nodes[node].position = pos;
+                self.node_keys[node] = pos as f64;
+            }
+        }
     }
 
     // =========================================================================
@@ -53,21 +237,23 @@ impl<'a> SugiyamaLayout<'a> {
             return;
         }
 
-        // Используем DFS для поиска обратных рёбер
+        // Используем DFS по группам одного слоя для поиска обратных рёбер
+        let members = self.group_members();
         let mut visited = vec![false; n];
         let mut in_stack = vec![false; n];
         let mut back_edges = Vec::new();
 
         for start in 0..n {
-            if !visited[start] {
-                self.dfs_find_back_edges(start, &mut visited, &mut in_stack, &mut back_edges);
+            let group = self.rank_group[start];
+            if !visited[group] {
+                self.dfs_find_back_edges(group, &members, &mut visited, &mut in_stack, &mut back_edges);
             }
         }
 
         // Обращаем обратные рёбра
         for edge_idx in back_edges {
-            self.graph.edges[edge_idx].reversed = true;
             let edge = &mut self.graph.edges[edge_idx];
+            edge.reversed = !edge.reversed;
             std::mem::swap(&mut edge.from, &mut edge.to);
         }
 
@@ -78,27 +264,33 @@ impl<'a> SugiyamaLayout<'a> {
     /// DFS для поиска обратных рёбер
     fn dfs_find_back_edges(
         &self,
-        node: usize,
+        group: usize,
+        members: &[Vec<usize>],
         visited: &mut [bool],
         in_stack: &mut [bool],
         back_edges: &mut Vec<usize>,
     ) {
-        visited[node] = true;
-        in_stack[node] = true;
-
-        for &edge_idx in &self.graph.adjacency[node] {
-            let edge = &self.graph.edges[edge_idx];
-            let target = edge.to;
-
-            if !visited[target] {
-                self.dfs_find_back_edges(target, visited, in_stack, back_edges);
-            } else if in_stack[target] {
-                // Нашли обратное ребро
-                back_edges.push(edge_idx);
+        visited[group] = true;
+        in_stack[group] = true;
+
+        for &node in &members[group] {
+            for &edge_idx in &self.graph.adjacency[node] {
+                let target = self.rank_group[self.graph.edges[edge_idx].to];
+                if target == group {
+                    // Ребро внутри группы одного слоя
+                    continue;
+                }
+
+                if !visited[target] {
+                    self.dfs_find_back_edges(target, members, visited, in_stack, back_edges);
+                } else if in_stack[target] {
+                    // Нашли обратное ребро
+                    back_edges.push(edge_idx);
+                }
             }
         }
 
-        in_stack[node] = false;
+        in_stack[group] = false;
     }
 
     /// Перестраивает списки смежности после обращения рёбер
@@ -108,6 +300,9 @@ impl<'a> SugiyamaLayout<'a> {
         self.graph.reverse_adjacency = vec![Vec::new(); n];
 
         for (idx, edge) in self.graph.edges.iter().enumerate() {
+            if edge.is_flat() {
+                continue;
+            }
             self.graph.adjacency[edge.from].push(idx);
             self.graph.reverse_adjacency[edge.to].push(idx);
         }
@@ -124,65 +319,73 @@ impl<'a> SugiyamaLayout<'a> {
             return;
         }
 
-        // Топологическая сортировка (Kahn's algorithm)
-        let topo_order = self.topological_sort();
+        // Топологическая сортировка групп одного слоя (Kahn's algorithm)
+        let members = self.group_members();
+        let topo_order = self.topological_sort(&members);
 
-        // Longest path: для каждого узла layer = max(layer предшественников) + 1
+        // Longest path: для каждой группы layer = max(layer предшественников) + 1
         let mut layers = vec![0usize; n];
 
-        for &node in &topo_order {
+        for &group in &topo_order {
             let mut max_pred_layer = 0;
-            for &edge_idx in &self.graph.reverse_adjacency[node] {
-                let edge = &self.graph.edges[edge_idx];
-                max_pred_layer = max_pred_layer.max(layers[edge.from] + 1);
+            for &node in &members[group] {
+                for &edge_idx in &self.graph.reverse_adjacency[node] {
+                    let from = self.rank_group[self.graph.edges[edge_idx].from];
+                    if from != group {
+                        max_pred_layer = max_pred_layer.max(layers[from] + 1);
+                    }
+                }
             }
-            layers[node] = max_pred_layer;
+            layers[group] = max_pred_layer;
         }
 
         // Записываем слои в узлы
-        for (idx, &layer) in layers.iter().enumerate() {
-            self.graph.nodes[idx].layer = layer;
+        for idx in 0..n {
+            self.graph.nodes[idx].layer = layers[self.rank_group[idx]];
         }
     }
 
-    /// Топологическая сортировка (Kahn's algorithm)
-    fn topological_sort(&self) -> Vec<usize> {
+    /// Топологическая сортировка групп одного слоя (Kahn's algorithm)
+    fn topological_sort(&self, members: &[Vec<usize>]) -> Vec<usize> {
         let n = self.graph.node_count();
         let mut in_degree = vec![0usize; n];
+        let crossing = |edge_idx: &usize| {
+            let edge = &self.graph.edges[*edge_idx];
+            (self.rank_group[edge.from] != self.rank_group[edge.to]).then_some(self.rank_group[edge.to])
+        };
 
         // Подсчитываем входящие степени
-        for edge in &self.graph.edges {
-            in_degree[edge.to] += 1;
+        for node in 0..n {
+            for target in self.graph.adjacency[node].iter().filter_map(crossing) {
+                in_degree[target] += 1;
+            }
         }
 
-        // Начинаем с узлов без входящих рёбер
-        let mut queue: VecDeque<usize> = in_degree
-            .iter()
-            .enumerate()
-            .filter(|(_, &deg)| deg == 0)
-            .map(|(idx, _)| idx)
-            .collect();
+        // Начинаем с групп без входящих рёбер
+        let groups: Vec<usize> = (0..n).filter(|&node| self.rank_group[node] == node).collect();
+        let mut queue: VecDeque<usize> = groups.iter().copied().filter(|&g| in_degree[g] == 0).collect();
 
-        let mut result = Vec::with_capacity(n);
+        let mut result = Vec::with_capacity(groups.len());
 
-        while let Some(node) = queue.pop_front() {
-            result.push(node);
+        while let Some(group) = queue.pop_front() {
+            result.push(group);
 
-            for &edge_idx in &self.graph.adjacency[node] {
-                let target = self.graph.edges[edge_idx].to;
-                in_degree[target] -= 1;
-                if in_degree[target] == 0 {
-                    queue.push_back(target);
+            for &node in &members[group] {
+                for target in self.graph.adjacency[node].iter().filter_map(crossing) {
+                    in_degree[target] -= 1;
+                    if in_degree[target] == 0 {
+                        queue.push_back(target);
+                    }
                 }
             }
         }
 
-        // Если не все узлы обработаны, значит есть цикл (не должно случиться после remove_cycles)
-        if result.len() < n {
-            // Добавляем оставшиеся узлы
-            for i in 0..n {
-                if !result.contains(&i) {
-                    result.push(i);
+        // Если не все группы обработаны, значит есть цикл (не должно случиться после remove_cycles)
+        if result.len() < groups.len() {
+            // Добавляем оставшиеся группы
+            for group in groups {
+                if !result.contains(&group) {
+                    result.push(group);
                 }
             }
         }
@@ -239,8 +442,9 @@ impl<'a> SugiyamaLayout<'a> {
             })
             .collect();
 
-        // Сортируем по барицентру
-        barycenters.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+        // Сортируем по барицентру, не разрывая кластеры
+        let cluster_keys = self.cluster_barycenters();
+        barycenters.sort_by(|a, b| self.compare_compound(*a, *b, &cluster_keys));
 
         // Обновляем позиции
         for (pos, (node, _)) in barycenters.iter().enumerate() {
@@ -248,6 +452,75 @@ impl<'a> SugiyamaLayout<'a> {
         }
     }
 
+    /// Средняя позиция узлов каждого кластера по всем слоям
+    fn cluster_barycenters(&self) -> Vec<f64> {
+        let count = self.graph.clusters.len();
+        let mut sums = vec![0.0; count];
+        let mut counts = vec![0usize; count];
+        for node in &self.graph.nodes {
+            let mut cluster = node.cluster;
+            while let Some(c) = cluster {
+                sums[c] += node.position as f64;
+                counts[c] += 1;
+                cluster = self.graph.clusters[c].parent;
+            }
+        }
+        sums.iter()
+            .zip(&counts)
+            .map(|(&sum, &n)| if n > 0 { sum / n as f64 } else { 0.0 })
+            .collect()
+    }
+
+    /// Сравнивает узлы слоя `(узел, ключ)` с учётом кластеров
+    ///
+    /// Узлы сравниваются по первому различию в цепочках кластеров: узлы
+    /// одного кластера получают общий префикс и поэтому идут подряд, а
+    /// кластеры упорядочены по общему для всех слоёв ключу.
+    fn compare_compound(&self, a: (usize, f64), b: (usize, f64), cluster_keys: &[f64]) -> Ordering {
+        let chain_a = self.graph.cluster_chain(a.0);
+        let chain_b = self.graph.cluster_chain(b.0);
+        let key = |chain: &[usize], (node, node_key): (usize, f64), depth: usize| match chain.get(depth) {
+            Some(&c) => (cluster_keys[c], 1, c),
+            None => (node_key, 0, node),
+        };
+
+        for depth in 0.. {
+            let key_a = key(&chain_a, a, depth);
+            let key_b = key(&chain_b, b, depth);
+            let ordering = key_a
+                .0
+                .total_cmp(&key_b.0)
+                .then(key_a.1.cmp(&key_b.1))
+                .then(key_a.2.cmp(&key_b.2));
+            if ordering != Ordering::Equal || key_a.1 == 0 {
+                return ordering;
+            }
+        }
+        Ordering::Equal
+    }
+
+    /// Фиксирует итоговый порядок слоёв
+    ///
+    /// Ключи кластеров считаются один раз, поэтому порядок кластеров-соседей
+    /// одинаков во всех слоях — на этом основано размещение рамок.
+    fn fix_order(&mut self) {
+        self.cluster_keys = self.cluster_barycenters();
+        self.node_keys = self.graph.nodes.iter().map(|n| n.position as f64).collect();
+
+        for layer in 0..=self.graph.max_layer() {
+            let mut nodes: Vec<(usize, f64)> = self
+                .graph
+                .nodes_on_layer(layer)
+                .into_iter()
+                .map(|n| (n, self.node_keys[n]))
+                .collect();
+            nodes.sort_by(|a, b| self.compare_compound(*a, *b, &self.cluster_keys));
+            for (pos, (node, _)) in nodes.iter().enumerate() {
+                self.graph.nodes[*node].position = pos;
+            }
+        }
+    }
+
     /// Вычисляет барицентр узла
     fn calculate_barycenter(&self, node: usize, use_upper: bool) -> f64 {
         let edges = if use_upper {
@@ -279,16 +552,41 @@ impl<'a> SugiyamaLayout<'a> {
     // Шаг 4: Присвоение координат (Coordinate Assignment)
     // =========================================================================
 
-    /// Присваивает X и Y координаты узлам
+    /// Присваивает X и Y координаты узлам и рамкам кластеров
     fn assign_coordinates(&mut self) {
         let max_layer = self.graph.max_layer();
+        self.assign_cluster_layers();
+
+        // Место над слоем под вкладки начинающихся кластеров
+        // и под слоем — под нижние отступы заканчивающихся
+        let padding = self.config.package_padding;
+        let mut top_room = vec![0.0f64; max_layer + 1];
+        let mut bottom_room = vec![0.0f64; max_layer + 1];
+        for node in 0..self.graph.node_count() {
+            let layer = self.graph.nodes[node].layer;
+            let mut top = 0.0;
+            let mut bottom = 0.0;
+            for c in self.graph.cluster_chain(node) {
+                let cluster = &self.graph.clusters[c];
+                if let Some((first, last)) = cluster.layers {
+                    if first == layer {
+                        top += self.rank_tab(cluster) + padding;
+                    }
+                    if last == layer {
+                        bottom += padding;
+                    }
+                }
+            }
+            top_room[layer] = top_room[layer].max(top);
+            bottom_room[layer] = bottom_room[layer].max(bottom);
+        }
 
         // Y координаты: PlantUML располагает родителей ВВЕРХУ, детей ВНИЗУ
         // Слой 0 = верх (корень/родитель), слой N = низ (потомки)
         // Вычисляем Y на основе фактических высот узлов предыдущих слоёв
         let mut layer_y = vec![0.0f64; max_layer + 1];
-        layer_y[0] = self.config.margin;
-        
+        layer_y[0] = self.config.margin + top_room[0];
+
         for layer in 1..=max_layer {
             // Находим максимальную высоту узлов на предыдущем слое
             let prev_layer_max_height = self.graph.nodes_on_layer(layer - 1)
@@ -296,74 +594,225 @@ impl<'a> SugiyamaLayout<'a> {
                 .map(|&n| self.graph.nodes[n].size.height)
                 .max_by(|a, b| a.partial_cmp(b).unwrap())
                 .unwrap_or(self.config.min_class_height);
-            
-            layer_y[layer] = layer_y[layer - 1] + prev_layer_max_height + self.config.layer_vertical_spacing;
+            let spacing = self
+                .config
+                .layer_vertical_spacing
+                .max(bottom_room[layer - 1] + top_room[layer] + padding);
+
+            layer_y[layer] = layer_y[layer - 1] + prev_layer_max_height + spacing;
         }
-        
+
         for node in &mut self.graph.nodes {
             node.y = layer_y[node.layer];
         }
 
-        // X координаты: позиционируем узлы внутри каждого слоя
-        for layer in 0..=max_layer {
-            self.position_layer_x(layer);
+        // X координаты: слои корня, кластеры — рекурсивно внутри
+        self.place_scope(None, 0, max_layer, self.config.margin);
+
+        self.assign_cluster_heights();
+    }
+
+    /// Вычисляет диапазоны слоёв кластеров
+    fn assign_cluster_layers(&mut self) {
+        for cluster in &mut self.graph.clusters {
+            cluster.layers = None;
+        }
+        for node in 0..self.graph.node_count() {
+            let layer = self.graph.nodes[node].layer;
+            for c in self.graph.cluster_chain(node) {
+                let layers = &mut self.graph.clusters[c].layers;
+                *layers = Some(match *layers {
+                    Some((first, last)) => (first.min(layer), last.max(layer)),
+                    None => (layer, layer),
+                });
+            }
         }
     }
 
-    /// Позиционирует узлы слоя по оси X
-    fn position_layer_x(&mut self, layer: usize) {
-        let mut nodes_in_layer: Vec<usize> = self.graph.nodes_on_layer(layer);
+    /// Вычисляет вертикальные границы рамок (вложенные раньше внешних)
+    fn assign_cluster_heights(&mut self) {
+        let padding = self.config.package_padding;
 
-        if nodes_in_layer.is_empty() {
-            return;
+        for c in (0..self.graph.clusters.len()).rev() {
+            if self.graph.clusters[c].layers.is_none() {
+                continue;
+            }
+            let nodes = self
+                .graph
+                .nodes
+                .iter()
+                .filter(|n| n.cluster == Some(c))
+                .map(|n| (n.y, n.y + n.size.height));
+            let children = self
+                .graph
+                .clusters
+                .iter()
+                .filter(|child| child.parent == Some(c) && child.layers.is_some())
+                .map(|child| (child.y, child.y + child.height));
+            let (top, bottom) = nodes
+                .chain(children)
+                .fold((f64::MAX, f64::MIN), |(top, bottom), (t, b)| (top.min(t), bottom.max(b)));
+
+            let rank_tab = self.rank_tab(&self.graph.clusters[c]);
+            let min_height = if self.direction.is_horizontal() {
+                self.graph.clusters[c].tab_width
+            } else {
+                0.0
+            };
+            let cluster = &mut self.graph.clusters[c];
+            cluster.y = top - padding - rank_tab;
+            cluster.height = (bottom + padding - cluster.y).max(min_height);
         }
+    }
 
-        // Сортируем по позиции (результат минимизации пересечений)
-        nodes_in_layer.sort_by_key(|&n| self.graph.nodes[n].position);
+    /// Место под вкладку рамки вдоль оси слоёв
+    ///
+    /// Вкладка всегда сверху: при горизонтальном направлении ось слоёв
+    /// станет осью X, и место под вкладку резервируется поперёк слоёв.
+    fn rank_tab(&self, cluster: &Cluster) -> f64 {
+        if self.direction.is_horizontal() {
+            0.0
+        } else {
+            cluster.tab_height
+        }
+    }
 
-        // Вычисляем общую ширину слоя
-        let total_width: f64 = nodes_in_layer
-            .iter()
-            .map(|&n| self.graph.nodes[n].size.width)
-            .sum();
+    /// Место под вкладку рамки поперёк слоёв
+    fn cross_tab(&self, cluster: &Cluster) -> f64 {
+        if self.direction.is_horizontal() {
+            cluster.tab_height
+        } else {
+            0.0
+        }
+    }
 
-        let spacing = (nodes_in_layer.len() - 1) as f64 * self.config.node_horizontal_spacing;
-        let layer_width = total_width + spacing;
+    /// Ключ порядка элемента слоя
+    fn item_key(&self, item: Item) -> (f64, u8, usize) {
+        match item {
+            Item::Node(n) => (self.node_keys[n], 0, n),
+            Item::Cluster(c) => (self.cluster_keys[c], 1, c),
+        }
+    }
 
-        // Начинаем с центрирования (можно улучшить)
-        let mut x = self.config.margin;
+    /// Сравнивает элементы слоя в итоговом порядке
+    fn compare_items(&self, a: Item, b: Item) -> Ordering {
+        let (key_a, kind_a, id_a) = self.item_key(a);
+        let (key_b, kind_b, id_b) = self.item_key(b);
+        key_a
+            .total_cmp(&key_b)
+            .then(kind_a.cmp(&kind_b))
+            .then(id_a.cmp(&id_b))
+    }
 
-        for &node_idx in &nodes_in_layer {
-            self.graph.nodes[node_idx].x = x;
-            x += self.graph.nodes[node_idx].size.width + self.config.node_horizontal_spacing;
-        }
+    /// Элементы слоя внутри кластера `scope` (None — корень) в итоговом порядке
+    ///
+    /// Вложенный кластер входит в каждый слой своего диапазона, даже если
+    /// на этом слое у него нет узлов: рамка занимает место и там.
+    fn scope_items(&self, scope: Option<usize>, layer: usize) -> VecDeque<Item> {
+        let nodes = self
+            .graph
+            .nodes
+            .iter()
+            .filter(|n| n.cluster == scope && n.layer == layer)
+            .map(|n| Item::Node(n.index));
+        let clusters = self
+            .graph
+            .clusters
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| {
+                c.parent == scope
+                    && matches!(c.layers, Some((first, last)) if first <= layer && layer <= last)
+            })
+            .map(|(idx, _)| Item::Cluster(idx));
 
-        // Центрируем слой относительно максимальной ширины
-        let max_layer_width = self.calculate_max_layer_width();
-        if layer_width < max_layer_width {
-            let offset = (max_layer_width - layer_width) / 2.0;
-            for &node_idx in &nodes_in_layer {
-                self.graph.nodes[node_idx].x += offset;
-            }
-        }
+        let mut items: Vec<Item> = nodes.chain(clusters).collect();
+        items.sort_by(|a, b| self.compare_items(*a, *b));
+        items.into()
     }
 
-    /// Вычисляет максимальную ширину слоя
-    fn calculate_max_layer_width(&self) -> f64 {
-        let max_layer = self.graph.max_layer();
-        let mut max_width = 0.0f64;
+    /// Позиционирует по оси X содержимое кластера `scope` на слоях
+    /// `first..=last`, начиная с `left`; возвращает правую границу
+    ///
+    /// Слои обходятся одновременно: узлы ставятся друг за другом, а
+    /// очередной кластер — правее всего, что уже стоит на любом его слое.
+    /// Слои без вложенных кластеров центрируются по ширине содержимого.
+    fn place_scope(&mut self, scope: Option<usize>, first: usize, last: usize, left: f64) -> f64 {
+        let spacing = self.config.node_horizontal_spacing;
+        let padding = self.config.package_padding;
+
+        let mut queues: Vec<VecDeque<Item>> =
+            (first..=last).map(|layer| self.scope_items(scope, layer)).collect();
+        let plain: Vec<bool> = queues
+            .iter()
+            .map(|queue| queue.iter().all(|item| matches!(item, Item::Node(_))))
+            .collect();
+        let mut cursor = vec![left; queues.len()];
+        let mut layer_right = vec![left; queues.len()];
+        let mut right = left;
+
+        loop {
+            for (i, queue) in queues.iter_mut().enumerate() {
+                while let Some(&Item::Node(node)) = queue.front() {
+                    queue.pop_front();
+                    let node = &mut self.graph.nodes[node];
+                    node.x = cursor[i];
+                    layer_right[i] = cursor[i] + node.size.width;
+                    cursor[i] = layer_right[i] + spacing;
+                }
+                right = right.max(layer_right[i]);
+            }
 
-        for layer in 0..=max_layer {
-            let nodes = self.graph.nodes_on_layer(layer);
-            let width: f64 = nodes
+            // Ближайший в общем порядке кластер среди голов очередей
+            let next = queues
                 .iter()
-                .map(|&n| self.graph.nodes[n].size.width)
-                .sum::<f64>()
-                + (nodes.len().saturating_sub(1)) as f64 * self.config.node_horizontal_spacing;
-            max_width = max_width.max(width);
+                .filter_map(|queue| match queue.front() {
+                    Some(&Item::Cluster(c)) => Some(c),
+                    _ => None,
+                })
+                .min_by(|&a, &b| self.compare_items(Item::Cluster(a), Item::Cluster(b)));
+            let Some(cluster) = next else {
+                break;
+            };
+
+            let (cluster_first, cluster_last) = self.graph.clusters[cluster]
+                .layers
+                .unwrap_or((first, last));
+            let span = cluster_first - first..=cluster_last - first;
+            let x = span.clone().map(|i| cursor[i]).fold(left, f64::max);
+            let cross_tab = self.cross_tab(&self.graph.clusters[cluster]);
+            let inner_right = self.place_scope(Some(cluster), cluster_first, cluster_last, x + padding + cross_tab);
+            let min_width = if self.direction.is_horizontal() {
+                0.0
+            } else {
+                self.graph.clusters[cluster].tab_width
+            };
+            let width = (inner_right + padding - x).max(min_width);
+            self.graph.clusters[cluster].x = x;
+            self.graph.clusters[cluster].width = width;
+
+            for i in span {
+                layer_right[i] = x + width;
+                cursor[i] = layer_right[i] + spacing;
+                queues[i].retain(|item| *item != Item::Cluster(cluster));
+            }
+            right = right.max(x + width);
+        }
+
+        // Центрируем слои из одних узлов относительно ширины содержимого
+        for (i, layer) in (first..=last).enumerate() {
+            if !plain[i] || layer_right[i] >= right {
+                continue;
+            }
+            let offset = (right - layer_right[i]) / 2.0;
+            for node in &mut self.graph.nodes {
+                if node.cluster == scope && node.layer == layer {
+                    node.x += offset;
+                }
+            }
         }
 
-        max_width
+        right
     }
 }
 
@@ -463,4 +912,100 @@ mod tests {
             "Должно быть обращено хотя бы одно ребро для разрыва цикла"
         );
     }
+
+    #[test]
+    fn test_clusters_contiguous_and_disjoint() {
+        use plantuml_ast::class::{Package, RelationshipType};
+
+        // Без кластеров барицентры чередуют a1, b1, a2, b2
+        let mut diagram = ClassDiagram::new();
+        for name in ["X0", "X1", "X2", "X3"] {
+            diagram.add_class(Classifier::new(name));
+        }
+        let mut a = Package::new("A");
+        a.classifiers.push(Classifier::new("a1"));
+        a.classifiers.push(Classifier::new("a2"));
+        let mut b = Package::new("B");
+        b.classifiers.push(Classifier::new("b1"));
+        b.classifiers.push(Classifier::new("b2"));
+        diagram.packages.push(a);
+        diagram.packages.push(b);
+        for (from, to) in [("X0", "a1"), ("X1", "b1"), ("X2", "a2"), ("X3", "b2")] {
+            diagram.add_relationship(Relationship::new(from, to, RelationshipType::Association));
+        }
+
+        let config = ClassLayoutConfig::default();
+        let mut graph = Graph::from_diagram(&diagram, &config);
+        SugiyamaLayout::new(&mut graph, &config).run();
+
+        let position = |name: &str| graph.get_node_by_name(name).unwrap().position;
+        assert_eq!(position("a1").abs_diff(position("a2")), 1);
+        assert_eq!(position("b1").abs_diff(position("b2")), 1);
+
+        let frame = |c: usize| {
+            let cluster = &graph.clusters[c];
+            (cluster.x, cluster.y, cluster.x + cluster.width, cluster.y + cluster.height)
+        };
+        let (a_frame, b_frame) = (frame(0), frame(1));
+        assert!(a_frame.2 <= b_frame.0 || b_frame.2 <= a_frame.0);
+
+        for node in &graph.nodes {
+            let inside = |(left, top, right, bottom): (f64, f64, f64, f64)| {
+                node.x >= left
+                    && node.y >= top
+                    && node.x + node.size.width <= right
+                    && node.y + node.size.height <= bottom
+            };
+            let overlaps = |(left, top, right, bottom): (f64, f64, f64, f64)| {
+                node.x < right && node.x + node.size.width > left && node.y < bottom && node.y + node.size.height > top
+            };
+            match node.cluster {
+                Some(c) => assert!(inside(frame(c)), "{} вне рамки", node.id),
+                None => assert!(!overlaps(a_frame) && !overlaps(b_frame), "{} в рамке", node.id),
+            }
+        }
+    }
+
+    #[test]
+    fn test_direction_hints() {
+        use plantuml_ast::common::Direction;
+
+        // A → B вниз, A → C вверх, B → D вправо (тот же слой)
+        let mut graph = Graph::new();
+        for name in ["A", "B", "C", "D"] {
+            graph.add_node(name, Size::new(100.0, 50.0));
+        }
+        graph.add_edge(0, 1);
+        let up = graph.add_edge(0, 2);
+        graph.edges[up].direction = Some(Direction::BottomToTop);
+        let right = graph.add_edge(1, 3);
+        graph.edges[right].direction = Some(Direction::LeftToRight);
+
+        let config = ClassLayoutConfig::default();
+        SugiyamaLayout::new(&mut graph, &config).run();
+
+        let node = |name: &str| graph.get_node_by_name(name).unwrap();
+        assert!(node("C").layer < node("A").layer);
+        assert!(node("A").layer < node("B").layer);
+        assert_eq!(node("B").layer, node("D").layer);
+        assert!(node("D").x >= node("B").x + node("B").size.width);
+    }
+
+    #[test]
+    fn test_left_to_right_transposes_layers() {
+        let (mut graph, config) = create_test_graph();
+        let sizes: Vec<Size> = graph.nodes.iter().map(|node| node.size).collect();
+        SugiyamaLayout::new(&mut graph, &config)
+            .with_direction(plantuml_ast::common::Direction::LeftToRight)
+            .run();
+
+        // Слои идут слева направо, размеры узлов не меняются
+        let animal = graph.get_node_by_name("Animal").unwrap();
+        let dog = graph.get_node_by_name("Dog").unwrap();
+        let cat = graph.get_node_by_name("Cat").unwrap();
+        assert!(dog.x >= animal.x + animal.size.width);
+        assert!(cat.x >= animal.x + animal.size.width);
+        assert_eq!(dog.x, cat.x);
+        assert!(graph.nodes.iter().zip(sizes).all(|(node, size)| node.size == size));
+    }
 }
@@ -1,5 +1,7 @@
 //! Конфигурация layout для Component Diagrams
 
+use crate::text::TextMetrics;
+
 /// Конфигурация Component Layout Engine
 #[derive(Debug, Clone)]
 pub struct ComponentLayoutConfig {
@@ -23,6 +25,8 @@ pub struct ComponentLayoutConfig {
     pub corner_radius: f64,
     /// Размер иконки компонента
     pub icon_size: f64,
+    /// Метрики текста (шрифт темы)
+    pub text: TextMetrics,
 }
 
 impl Default for ComponentLayoutConfig {
@@ -38,6 +42,7 @@ impl Default for ComponentLayoutConfig {
             package_header_height: 25.0,
             corner_radius: 5.0,
             icon_size: 16.0,
+            text: TextMetrics::default(),
         }
     }
 }
@@ -1,14 +1,43 @@
 //! Component Diagram Layout Engine
 //!
-//! Алгоритм layout для диаграмм компонентов.
+//! Алгоритм layout для диаграмм компонентов (а также deployment и archimate).
+//!
+//! Элементы размещаются алгоритмом Sugiyama по связям между ними. Пакеты,
+//! узлы и облака — кластеры: сначала раскладывается содержимое пакета,
+//! затем пакет участвует в layout родителя как один узел своего размера.
+//! Связь между элементами разных кластеров учитывается на уровне их
+//! ближайших общих предков. Направление диаграммы и подсказки стрелок
+//! (`-up->`, `-[hidden]->`) передаются Sugiyama каждого кластера.
+//! Связи прокладываются в обход элементов и чужих пакетов.
 
 use std::collections::HashMap;
 
-use plantuml_ast::component::{Component, ComponentDiagram, ComponentType, Connection};
-use plantuml_model::{Point, Rect};
+use plantuml_ast::common::{Direction, LineType};
+use plantuml_ast::component::{
+    Component, ComponentDiagram, ComponentPackage, ComponentType, Connection, PackageType,
+};
+use plantuml_model::{Point, Rect, Size};
 
 use super::config::ComponentLayoutConfig;
-use crate::{EdgeType, ElementType, LayoutElement, LayoutResult};
+use crate::class::sugiyama::SugiyamaLayout;
+use crate::class::{ClassLayoutConfig, Graph};
+use crate::routing::{EdgeSpec, Router};
+use crate::text::TextMetrics;
+use crate::{ContainerKind, EdgeType, ElementStyle, ElementType, LayoutElement, LayoutResult};
+
+/// Элемент кластера
+enum Item<'a> {
+    Component(&'a Component),
+    Package(&'a ComponentPackage, Cluster<'a>),
+}
+
+/// Размещённое содержимое кластера в локальных координатах
+struct Cluster<'a> {
+    /// Элементы и их левый верхний угол
+    items: Vec<(Item<'a>, Rect)>,
+    /// Размер содержимого
+    size: Size,
+}
 
 /// Layout engine для component diagrams
 pub struct ComponentLayoutEngine {
@@ -28,100 +57,278 @@ impl ComponentLayoutEngine {
         Self { config }
     }
 
+    /// Устанавливает метрики текста (шрифт темы)
+    pub fn with_text_metrics(mut self, text: TextMetrics) -> Self {
+        self.config.text = text;
+        self
+    }
+
     /// Выполняет layout диаграммы
     pub fn layout(&self, diagram: &ComponentDiagram) -> LayoutResult {
+        let cluster = self.layout_cluster(
+            &diagram.components,
+            &diagram.packages,
+            &diagram.connections,
+            diagram.metadata.direction,
+        );
+
         let mut elements = Vec::new();
-        let mut component_positions: HashMap<String, Rect> = HashMap::new();
-
-        // Сначала располагаем компоненты в grid-layout
-        let components: Vec<&Component> = diagram.components.iter().collect();
-        let num_cols = ((components.len() as f64).sqrt().ceil() as usize).max(1);
-
-        for (i, comp) in components.iter().enumerate() {
-            let row = i / num_cols;
-            let col = i % num_cols;
-
-            let x = self.config.margin
-                + col as f64 * (self.config.component_width + self.config.horizontal_spacing);
-            let y = self.config.margin
-                + row as f64 * (self.config.component_height + self.config.vertical_spacing);
-
-            let (elem, bounds) = self.create_component_element(comp, x, y);
-            
-            // Сохраняем позицию по имени и алиасу
-            component_positions.insert(comp.name.clone(), bounds.clone());
-            if let Some(alias) = &comp.alias {
-                component_positions.insert(alias.clone(), bounds.clone());
-            }
-            
-            elements.push(elem);
+        let mut positions: HashMap<String, Rect> = HashMap::new();
+        let origin = Point::new(self.config.margin, self.config.margin);
+        self.place_cluster(&cluster, origin, &mut elements, &mut positions);
+
+        // Создаём связи; скрытые только влияли на layout
+        let (frames, obstacles): (Vec<&LayoutElement>, Vec<&LayoutElement>) = elements
+            .iter()
+            .partition(|element| matches!(element.element_type, ElementType::Group { .. }));
+        let router = Router::new(
+            diagram.metadata.line_type.unwrap_or(LineType::Polyline),
+            self.config.text.clone(),
+        )
+        .with_obstacles(obstacles.iter().map(|element| element.bounds))
+        .with_frames(frames.iter().map(|element| element.bounds));
+
+        let visible: Vec<(&Connection, Rect, Rect)> = diagram
+            .connections
+            .iter()
+            .filter(|conn| !conn.hidden)
+            .filter_map(|conn| Some((conn, *positions.get(&conn.from)?, *positions.get(&conn.to)?)))
+            .collect();
+        let specs: Vec<EdgeSpec> = visible
+            .iter()
+            .map(|(conn, from, to)| EdgeSpec::new(*from, *to).with_label(conn.label.as_deref()))
+            .collect();
+        for ((conn, _, _), route) in visible.iter().zip(router.route_all(&specs)) {
+            let mut edge = self.create_connection_element(conn);
+            route.apply(&mut edge);
+            elements.push(edge);
         }
 
-        // Располагаем пакеты
-        let mut package_y = self.config.margin
-            + ((components.len() / num_cols.max(1) + 1) as f64)
-                * (self.config.component_height + self.config.vertical_spacing);
+        // Вычисляем bounds
+        let mut result = LayoutResult {
+            elements,
+            bounds: Rect::new(0.0, 0.0, 0.0, 0.0),
+        };
+        result.calculate_bounds();
+
+        // Добавляем отступы
+        result.bounds.width += self.config.margin * 2.0;
+        result.bounds.height += self.config.margin * 2.0;
+
+        result
+    }
 
-        for pkg in &diagram.packages {
-            let (pkg_elements, pkg_bounds, inner_positions) =
-                self.layout_package(pkg, self.config.margin, package_y);
+    /// Раскладывает содержимое кластера (диаграммы или пакета)
+    ///
+    /// Вложенные пакеты раскладываются рекурсивно и становятся узлами графа
+    /// своего размера. Рёбра графа — связи между элементами, которые лежат
+    /// в разных прямых потомках кластера.
+    fn layout_cluster<'a>(
+        &self,
+        components: &'a [Component],
+        packages: &'a [ComponentPackage],
+        connections: &[Connection],
+        direction: Direction,
+    ) -> Cluster<'a> {
+        let mut items: Vec<Item<'a>> = components.iter().map(Item::Component).collect();
+        for pkg in packages {
+            let inner = self.layout_cluster(&pkg.components, &pkg.packages, connections, direction);
+            items.push(Item::Package(pkg, inner));
+        }
 
-            for elem in pkg_elements {
-                elements.push(elem);
+        // Имя (или алиас) элемента → индекс прямого потомка, который его содержит
+        let mut owners: HashMap<&str, usize> = HashMap::new();
+        for (index, item) in items.iter().enumerate() {
+            match item {
+                Item::Component(comp) => Self::collect_component_names(comp, index, &mut owners),
+                Item::Package(pkg, _) => Self::collect_package_names(pkg, index, &mut owners),
             }
+        }
 
-            // Добавляем позиции вложенных компонентов
-            for (name, rect) in inner_positions {
-                component_positions.insert(name, rect);
+        let mut graph = Graph::new();
+        for (index, item) in items.iter().enumerate() {
+            graph.add_node(index.to_string(), self.item_size(item));
+        }
+        for conn in connections {
+            if let (Some(&from), Some(&to)) =
+                (owners.get(conn.from.as_str()), owners.get(conn.to.as_str()))
+            {
+                if from != to {
+                    let edge = graph.add_edge(from, to);
+                    graph.edges[edge].direction = conn.direction;
+                }
             }
+        }
 
-            package_y = pkg_bounds.y + pkg_bounds.height + self.config.vertical_spacing;
+        let sugiyama_config = ClassLayoutConfig {
+            margin: 0.0,
+            node_horizontal_spacing: self.config.horizontal_spacing,
+            layer_vertical_spacing: self.config.vertical_spacing,
+            min_class_height: self.config.component_height,
+            ..ClassLayoutConfig::default()
+        };
+        SugiyamaLayout::new(&mut graph, &sugiyama_config)
+            .with_direction(direction)
+            .run();
+
+        let mut size = Size::zero();
+        let items: Vec<(Item<'a>, Rect)> = items
+            .into_iter()
+            .zip(&graph.nodes)
+            .map(|(item, node)| {
+                let rect = Rect::new(node.x, node.y, node.size.width, node.size.height);
+                size.width = size.width.max(rect.x + rect.width);
+                size.height = size.height.max(rect.y + rect.height);
+                (item, rect)
+            })
+            .collect();
+
+        Cluster { items, size }
+    }
+
+    /// Имя и алиас компонента
+    fn collect_component_names<'a>(
+        comp: &'a Component,
+        index: usize,
+        owners: &mut HashMap<&'a str, usize>,
+    ) {
+        owners.insert(comp.name.as_str(), index);
+        if let Some(alias) = &comp.alias {
+            owners.insert(alias.as_str(), index);
         }
+    }
 
-        // Создаём связи
-        for conn in &diagram.connections {
-            if let Some(edge) = self.create_connection_element(conn, &component_positions) {
-                elements.push(edge);
+    /// Имя пакета и все имена внутри него
+    fn collect_package_names<'a>(
+        pkg: &'a ComponentPackage,
+        index: usize,
+        owners: &mut HashMap<&'a str, usize>,
+    ) {
+        owners.insert(pkg.name.as_str(), index);
+        for comp in &pkg.components {
+            Self::collect_component_names(comp, index, owners);
+        }
+        for inner in &pkg.packages {
+            Self::collect_package_names(inner, index, owners);
+        }
+    }
+
+    /// Размер элемента кластера
+    fn item_size(&self, item: &Item) -> Size {
+        match item {
+            Item::Component(comp) => self.component_size(comp),
+            Item::Package(pkg, inner) => {
+                let title_width = self.config.text.width(&pkg.name);
+                let width = inner.size.width.max(title_width) + self.config.package_padding * 2.0;
+                let height = inner.size.height
+                    + self.config.package_header_height
+                    + self.config.package_padding * 2.0;
+                Size::new(width.max(150.0), height.max(100.0))
             }
         }
+    }
 
-        // Вычисляем bounds
-        let mut result = LayoutResult {
-            elements,
-            bounds: Rect::new(0.0, 0.0, 0.0, 0.0),
-        };
-        result.calculate_bounds();
+    /// Размер компонента: не меньше подписи
+    /// (спрайты `<$name>` делают подпись выше строки текста)
+    fn component_size(&self, comp: &Component) -> Size {
+        let width = (self.config.text.width(&comp.name) + self.config.icon_size * 2.0 + 20.0)
+            .max(self.config.component_width);
+        let height = (self.config.text.size_at(&comp.name, self.config.text.font_size).height
+            + 20.0)
+            .max(self.config.component_height);
+
+        match comp.component_type {
+            ComponentType::Cloud => Size::new(width * 1.2, height),
+            ComponentType::Interface => {
+                Size::new(self.config.interface_radius * 2.0, self.config.interface_radius * 2.0)
+            }
+            ComponentType::Actor => Size::new(width * 0.6, height),
+            _ => Size::new(width, height),
+        }
+    }
 
-        // Добавляем отступы
-        result.bounds.width += self.config.margin * 2.0;
-        result.bounds.height += self.config.margin * 2.0;
+    /// Создаёт элементы кластера со смещением `origin`
+    ///
+    /// Пакет добавляется перед своим содержимым, чтобы рисоваться под ним.
+    fn place_cluster(
+        &self,
+        cluster: &Cluster,
+        origin: Point,
+        elements: &mut Vec<LayoutElement>,
+        positions: &mut HashMap<String, Rect>,
+    ) {
+        for (item, rect) in &cluster.items {
+            let bounds = Rect::new(origin.x + rect.x, origin.y + rect.y, rect.width, rect.height);
+            match item {
+                Item::Component(comp) => {
+                    positions.insert(comp.name.clone(), bounds);
+                    if let Some(alias) = &comp.alias {
+                        positions.insert(alias.clone(), bounds);
+                    }
+                    elements.push(self.create_component_element(comp, bounds));
+                }
+                Item::Package(pkg, inner) => {
+                    positions.insert(pkg.name.clone(), bounds);
+                    elements.push(self.create_package_element(pkg, bounds));
+
+                    // Содержимое центрируется по ширине пакета
+                    let inner_origin = Point::new(
+                        bounds.x + (bounds.width - inner.size.width) / 2.0,
+                        bounds.y + self.config.package_header_height + self.config.package_padding,
+                    );
+                    self.place_cluster(inner, inner_origin, elements, positions);
+                }
+            }
+        }
+    }
 
-        result
+    /// Создаёт элемент пакета (group); форма рамки — по виду контейнера
+    fn create_package_element(&self, pkg: &ComponentPackage, bounds: Rect) -> LayoutElement {
+        let kind = match pkg.package_type {
+            PackageType::Package => ContainerKind::Package,
+            PackageType::Node => ContainerKind::Node,
+            PackageType::Folder => ContainerKind::Folder,
+            PackageType::Frame => ContainerKind::Frame,
+            PackageType::Cloud => ContainerKind::Cloud,
+            PackageType::Rectangle => ContainerKind::Rectangle,
+            PackageType::Database => ContainerKind::Database,
+            PackageType::Storage => ContainerKind::Storage,
+            PackageType::Queue => ContainerKind::Queue,
+        };
+        LayoutElement {
+            id: format!("package_{}", pkg.name.replace(' ', "_")),
+            bounds,
+            text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Group {
+                label: Some(pkg.name.clone()),
+                children: Vec::new(),
+                kind,
+            },
+        }
+        .with_style(&ElementStyle::new().with_fill(pkg.color.as_ref()))
+        .with_stereotype(pkg.stereotype.as_ref())
     }
 
     /// Создаёт элемент компонента
-    fn create_component_element(&self, comp: &Component, x: f64, y: f64) -> (LayoutElement, Rect) {
-        let bounds = Rect::new(x, y, self.config.component_width, self.config.component_height);
-
-        let elem = match comp.component_type {
-            ComponentType::Database => self.create_database_element(&comp.name, x, y),
-            ComponentType::Cloud => self.create_cloud_element(&comp.name, x, y),
-            ComponentType::Interface => self.create_interface_element(&comp.name, x, y),
-            ComponentType::Queue => self.create_queue_element(&comp.name, x, y),
-            ComponentType::Node => self.create_node_element(&comp.name, x, y),
-            ComponentType::Folder => self.create_folder_element(&comp.name, x, y),
-            ComponentType::Actor => self.create_actor_element(&comp.name, x, y),
-            _ => self.create_standard_component_element(&comp.name, x, y),
+    fn create_component_element(&self, comp: &Component, bounds: Rect) -> LayoutElement {
+        let style = ElementStyle::new().with_fill(comp.color.as_ref());
+        let element = match comp.component_type {
+            ComponentType::Database => self.create_database_element(&comp.name, bounds),
+            ComponentType::Cloud => self.create_cloud_element(&comp.name, bounds),
+            ComponentType::Interface => self.create_interface_element(&comp.name, bounds),
+            ComponentType::Queue => self.create_queue_element(&comp.name, bounds),
+            ComponentType::Node => self.create_node_element(&comp.name, bounds),
+            ComponentType::Folder => self.create_folder_element(&comp.name, bounds),
+            ComponentType::Actor => self.create_actor_element(&comp.name, bounds),
+            _ => self.create_standard_component_element(&comp.name, bounds),
         };
-
-        (elem, bounds)
+        element.with_style(&style).with_stereotype(comp.stereotype.as_ref())
     }
 
     /// Создаёт стандартный компонент
-    fn create_standard_component_element(&self, name: &str, x: f64, y: f64) -> LayoutElement {
+    fn create_standard_component_element(&self, name: &str, bounds: Rect) -> LayoutElement {
         LayoutElement {
             id: format!("component_{}", name.replace(' ', "_")),
-            bounds: Rect::new(x, y, self.config.component_width, self.config.component_height),
+            bounds,
             text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Rectangle {
                 label: format!("⬡ {}", name), // Добавляем иконку компонента
                 corner_radius: self.config.corner_radius,
@@ -130,10 +337,10 @@ impl ComponentLayoutEngine {
     }
 
     /// Создаёт элемент базы данных (цилиндр)
-    fn create_database_element(&self, name: &str, x: f64, y: f64) -> LayoutElement {
+    fn create_database_element(&self, name: &str, bounds: Rect) -> LayoutElement {
         LayoutElement {
             id: format!("database_{}", name.replace(' ', "_")),
-            bounds: Rect::new(x, y, self.config.component_width, self.config.component_height),
+            bounds,
             text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Rectangle {
                 label: format!("🛢 {}", name),
                 corner_radius: self.config.corner_radius,
@@ -142,28 +349,22 @@ impl ComponentLayoutEngine {
     }
 
     /// Создаёт элемент облака
-    fn create_cloud_element(&self, name: &str, x: f64, y: f64) -> LayoutElement {
+    fn create_cloud_element(&self, name: &str, bounds: Rect) -> LayoutElement {
         LayoutElement {
             id: format!("cloud_{}", name.replace(' ', "_")),
-            bounds: Rect::new(
-                x,
-                y,
-                self.config.component_width * 1.2,
-                self.config.component_height,
-            ),
+            bounds,
             text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Rectangle {
                 label: format!("☁ {}", name),
-                corner_radius: self.config.component_height / 2.0,
+                corner_radius: bounds.height / 2.0,
             },
         }
     }
 
     /// Создаёт элемент интерфейса (кружок)
-    fn create_interface_element(&self, name: &str, x: f64, y: f64) -> LayoutElement {
-        let r = self.config.interface_radius;
+    fn create_interface_element(&self, name: &str, bounds: Rect) -> LayoutElement {
         LayoutElement {
             id: format!("interface_{}", name.replace(' ', "_")),
-            bounds: Rect::new(x, y, r * 2.0, r * 2.0),
+            bounds,
             text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Ellipse {
                 label: Some(name.to_string()),
             },
@@ -171,22 +372,22 @@ impl ComponentLayoutEngine {
     }
 
     /// Создаёт элемент очереди
-    fn create_queue_element(&self, name: &str, x: f64, y: f64) -> LayoutElement {
+    fn create_queue_element(&self, name: &str, bounds: Rect) -> LayoutElement {
         LayoutElement {
             id: format!("queue_{}", name.replace(' ', "_")),
-            bounds: Rect::new(x, y, self.config.component_width, self.config.component_height),
+            bounds,
             text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Rectangle {
                 label: format!("⟿ {}", name),
-                corner_radius: self.config.component_height / 4.0,
+                corner_radius: bounds.height / 4.0,
             },
         }
     }
 
     /// Создаёт элемент node
-    fn create_node_element(&self, name: &str, x: f64, y: f64) -> LayoutElement {
+    fn create_node_element(&self, name: &str, bounds: Rect) -> LayoutElement {
         LayoutElement {
             id: format!("node_{}", name.replace(' ', "_")),
-            bounds: Rect::new(x, y, self.config.component_width, self.config.component_height),
+            bounds,
             text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Rectangle {
                 label: format!("⬢ {}", name),
                 corner_radius: 0.0, // Node — с углами
@@ -195,10 +396,10 @@ impl ComponentLayoutEngine {
     }
 
     /// Создаёт элемент folder
-    fn create_folder_element(&self, name: &str, x: f64, y: f64) -> LayoutElement {
+    fn create_folder_element(&self, name: &str, bounds: Rect) -> LayoutElement {
         LayoutElement {
             id: format!("folder_{}", name.replace(' ', "_")),
-            bounds: Rect::new(x, y, self.config.component_width, self.config.component_height),
+            bounds,
             text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Rectangle {
                 label: format!("📁 {}", name),
                 corner_radius: self.config.corner_radius,
@@ -207,10 +408,10 @@ impl ComponentLayoutEngine {
     }
 
     /// Создаёт элемент actor
-    fn create_actor_element(&self, name: &str, x: f64, y: f64) -> LayoutElement {
+    fn create_actor_element(&self, name: &str, bounds: Rect) -> LayoutElement {
         LayoutElement {
             id: format!("actor_{}", name.replace(' ', "_")),
-            bounds: Rect::new(x, y, self.config.component_width * 0.6, self.config.component_height),
+            bounds,
             text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Text {
                 text: format!("👤\n{}", name),
                 font_size: 12.0,
@@ -218,136 +419,25 @@ impl ComponentLayoutEngine {
         }
     }
 
-    /// Располагает пакет и возвращает элементы, bounds и позиции вложенных компонентов
-    fn layout_package(
-        &self,
-        pkg: &plantuml_ast::component::ComponentPackage,
-        x: f64,
-        y: f64,
-    ) -> (Vec<LayoutElement>, Rect, HashMap<String, Rect>) {
-        let mut elements = Vec::new();
-        let mut positions = HashMap::new();
-
-        // Располагаем вложенные компоненты
-        let num_cols = ((pkg.components.len() as f64).sqrt().ceil() as usize).max(1);
-        let mut max_row = 0;
-        let mut max_col = 0;
-
-        for (i, comp) in pkg.components.iter().enumerate() {
-            let row = i / num_cols;
-            let col = i % num_cols;
-            max_row = max_row.max(row);
-            max_col = max_col.max(col);
-
-            let comp_x = x + self.config.package_padding
-                + col as f64 * (self.config.component_width + self.config.horizontal_spacing / 2.0);
-            let comp_y = y + self.config.package_header_height + self.config.package_padding
-                + row as f64 * (self.config.component_height + self.config.vertical_spacing / 2.0);
-
-            let (elem, bounds) = self.create_component_element(comp, comp_x, comp_y);
-            positions.insert(comp.name.clone(), bounds.clone());
-            if let Some(alias) = &comp.alias {
-                positions.insert(alias.clone(), bounds);
-            }
-            elements.push(elem);
-        }
-
-        // Вычисляем размер пакета
-        let inner_width = (max_col + 1) as f64
-            * (self.config.component_width + self.config.horizontal_spacing / 2.0)
-            - self.config.horizontal_spacing / 2.0;
-        let inner_height = (max_row + 1) as f64
-            * (self.config.component_height + self.config.vertical_spacing / 2.0)
-            - self.config.vertical_spacing / 2.0;
-
-        let pkg_width = inner_width + self.config.package_padding * 2.0;
-        let pkg_height =
-            inner_height + self.config.package_header_height + self.config.package_padding * 2.0;
-
-        let pkg_bounds = Rect::new(x, y, pkg_width.max(150.0), pkg_height.max(100.0));
-
-        // Создаём элемент пакета (group)
-        let pkg_elem = LayoutElement {
-            id: format!("package_{}", pkg.name.replace(' ', "_")),
-            bounds: pkg_bounds.clone(),
-            text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Group {
-                label: Some(pkg.name.clone()),
-                children: Vec::new(),
-            },
-        };
-
-        // Вставляем пакет первым (под компонентами)
-        elements.insert(0, pkg_elem);
-
-        (elements, pkg_bounds, positions)
-    }
-
-    /// Создаёт элемент связи
-    fn create_connection_element(
-        &self,
-        conn: &Connection,
-        positions: &HashMap<String, Rect>,
-    ) -> Option<LayoutElement> {
-        let from_rect = positions.get(&conn.from)?;
-        let to_rect = positions.get(&conn.to)?;
-
-        let (start, end) = self.calculate_connection_points(from_rect, to_rect);
-
-        let min_x = start.x.min(end.x);
-        let min_y = start.y.min(end.y);
-        let max_x = start.x.max(end.x);
-        let max_y = start.y.max(end.y);
-
-        Some(LayoutElement {
+    /// Создаёт элемент связи; путь и подпись размещает маршрутизатор
+    fn create_connection_element(&self, conn: &Connection) -> LayoutElement {
+        LayoutElement {
             id: format!(
                 "conn_{}_{}",
                 conn.from.replace(' ', "_"),
                 conn.to.replace(' ', "_")
             ),
-            bounds: Rect::new(min_x, min_y, (max_x - min_x).max(1.0), (max_y - min_y).max(1.0)),
+            bounds: Rect::new(0.0, 0.0, 0.0, 0.0),
             text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Edge {
-                points: vec![start, end],
+                points: Vec::new(),
                 label: conn.label.clone(),
                 arrow_start: false,
                 arrow_end: true,
                 dashed: conn.dashed,
                 edge_type: EdgeType::Association, from_cardinality: None, to_cardinality: None,
             },
-        })
-    }
-
-    /// Вычисляет точки соединения для связи
-    fn calculate_connection_points(&self, from: &Rect, to: &Rect) -> (Point, Point) {
-        let from_center_x = from.x + from.width / 2.0;
-        let from_center_y = from.y + from.height / 2.0;
-        let to_center_x = to.x + to.width / 2.0;
-        let to_center_y = to.y + to.height / 2.0;
-
-        let dx = to_center_x - from_center_x;
-        let dy = to_center_y - from_center_y;
-
-        let start;
-        let end;
-
-        if dy.abs() > dx.abs() {
-            if dy > 0.0 {
-                start = Point::new(from_center_x, from.y + from.height);
-                end = Point::new(to_center_x, to.y);
-            } else {
-                start = Point::new(from_center_x, from.y);
-                end = Point::new(to_center_x, to.y + to.height);
-            }
-        } else {
-            if dx > 0.0 {
-                start = Point::new(from.x + from.width, from_center_y);
-                end = Point::new(to.x, to_center_y);
-            } else {
-                start = Point::new(from.x, from_center_y);
-                end = Point::new(to.x + to.width, to_center_y);
-            }
         }
-
-        (start, end)
+        .with_style(&ElementStyle::new().with_stroke(conn.color.as_ref()))
     }
 }
 
@@ -405,4 +495,83 @@ mod tests {
 
         assert_eq!(result.elements.len(), 4);
     }
+
+    fn bounds_of<'a>(result: &'a LayoutResult, id: &str) -> &'a Rect {
+        &result.elements.iter().find(|e| e.id == id).unwrap().bounds
+    }
+
+    fn contains(outer: &Rect, inner: &Rect) -> bool {
+        inner.x >= outer.x
+            && inner.y >= outer.y
+            && inner.x + inner.width <= outer.x + outer.width
+            && inner.y + inner.height <= outer.y + outer.height
+    }
+
+    #[test]
+    fn test_connections_define_layers() {
+        let mut diagram = ComponentDiagram::new();
+        diagram.components.push(Component::new("Web"));
+        diagram.components.push(Component::new("API"));
+        diagram.components.push(Component::database("DB"));
+        diagram.connections.push(Connection::new("Web", "API"));
+        diagram.connections.push(Connection::new("API", "DB"));
+
+        let result = ComponentLayoutEngine::new().layout(&diagram);
+
+        let web = bounds_of(&result, "component_Web");
+        let api = bounds_of(&result, "component_API");
+        let db = bounds_of(&result, "database_DB");
+        assert!(web.y + web.height < api.y);
+        assert!(api.y + api.height < db.y);
+    }
+
+    #[test]
+    fn test_nested_packages_contain_children() {
+        use plantuml_ast::component::{ComponentPackage, PackageType};
+
+        let mut inner = ComponentPackage::new("Runtime");
+        inner.package_type = PackageType::Node;
+        inner.components.push(Component::new("Worker"));
+        inner.components.push(Component::new("Scheduler"));
+
+        let mut outer = ComponentPackage::new("Cluster");
+        outer.package_type = PackageType::Cloud;
+        outer.components.push(Component::new("Gateway"));
+        outer.packages.push(inner);
+
+        let mut diagram = ComponentDiagram::new();
+        diagram.components.push(Component::new("Client"));
+        diagram.packages.push(outer);
+        diagram.connections.push(Connection::new("Client", "Gateway"));
+        diagram.connections.push(Connection::new("Gateway", "Worker"));
+        diagram.connections.push(Connection::new("Scheduler", "Worker"));
+
+        let result = ComponentLayoutEngine::new().layout(&diagram);
+
+        let cluster = bounds_of(&result, "package_Cluster");
+        let runtime = bounds_of(&result, "package_Runtime");
+        assert!(contains(cluster, runtime));
+        assert!(contains(cluster, bounds_of(&result, "component_Gateway")));
+        assert!(contains(runtime, bounds_of(&result, "component_Worker")));
+        assert!(contains(runtime, bounds_of(&result, "component_Scheduler")));
+        assert!(!contains(cluster, bounds_of(&result, "component_Client")));
+
+        // Клиент над кластером, шлюз над вложенным узлом
+        let client = bounds_of(&result, "component_Client");
+        assert!(client.y + client.height < cluster.y);
+        let gateway = bounds_of(&result, "component_Gateway");
+        assert!(gateway.y + gateway.height < runtime.y);
+
+        // Пакет рисуется раньше своего содержимого
+        let index = |id: &str| result.elements.iter().position(|e| e.id == id).unwrap();
+        assert!(index("package_Runtime") < index("component_Worker"));
+        assert_eq!(
+            result
+                .elements
+                .iter()
+                .filter(|e| matches!(e.element_type, ElementType::Edge { .. }))
+                .count(),
+            3
+        );
+    }
 }
@@ -1,5 +1,7 @@
 //! Конфигурация layout
 
+use crate::text::TextMetrics;
+
 /// Конфигурация layout
 #[derive(Debug, Clone)]
 pub struct LayoutConfig {
@@ -15,6 +17,8 @@ pub struct LayoutConfig {
     pub min_node_height: f64,
     /// Размер шрифта по умолчанию
     pub default_font_size: f64,
+    /// Семейство шрифта (CSS-подобный список, как в `Theme.font_family`)
+    pub font_family: String,
 }
 
 impl Default for LayoutConfig {
@@ -26,6 +30,7 @@ impl Default for LayoutConfig {
             min_node_width: 100.0,
             min_node_height: 40.0,
             default_font_size: 14.0,
+            font_family: "sans-serif".to_string(),
         }
     }
 }
@@ -36,6 +41,18 @@ impl LayoutConfig {
         Self::default()
     }
 
+    /// Устанавливает шрифт темы
+    pub fn with_font(mut self, font_family: impl Into<String>, font_size: f64) -> Self {
+        self.font_family = font_family.into();
+        self.default_font_size = font_size;
+        self
+    }
+
+    /// Метрики текста для шрифта конфигурации
+    pub fn text_metrics(&self) -> TextMetrics {
+        TextMetrics::for_font(self.font_family.clone(), self.default_font_size)
+    }
+
     /// Конфигурация для sequence diagrams
     pub fn sequence() -> Self {
         Self {
@@ -45,6 +62,7 @@ impl LayoutConfig {
             min_node_width: 80.0,
             min_node_height: 40.0,
             default_font_size: 13.0,
+            font_family: "sans-serif".to_string(),
         }
     }
 
@@ -57,6 +75,7 @@ impl LayoutConfig {
             min_node_width: 120.0,
             min_node_height: 60.0,
             default_font_size: 12.0,
+            font_family: "sans-serif".to_string(),
         }
     }
 }
@@ -0,0 +1,371 @@
+//! Оформление диаграммы: заголовок, колонтитулы, подпись и легенда
+//!
+//! Работает поверх результата любого layout engine: блоки оформления
+//! размещаются над и под диаграммой, сама диаграмма не сдвигается.
+//!
+//! Сверху вниз: `header`, `title`, `legend top`, диаграмма, `legend`,
+//! `caption`, `footer`. `mainframe` обводит саму диаграмму рамкой
+//! с заголовком, остальные блоки размещаются снаружи рамки.
+
+use plantuml_ast::common::{DiagramMetadata, HorizontalAlign};
+
+use crate::text::TextMetrics;
+use crate::{ElementType, LayoutElement, LayoutResult, Rect};
+
+/// Свойство `LayoutElement`: выравнивание строк текста (`start`, `middle`, `end`)
+pub const PROPERTY_TEXT_ANCHOR: &str = "text-anchor";
+/// Свойство `LayoutElement`: насыщенность шрифта (`bold`)
+pub const PROPERTY_FONT_WEIGHT: &str = "font-weight";
+
+/// Отступ между блоками оформления и диаграммой
+const BLOCK_GAP: f64 = 10.0;
+/// Внутренний отступ рамки легенды
+const LEGEND_PADDING: f64 = 8.0;
+/// Отступ рамки `mainframe` от диаграммы
+const MAINFRAME_PADDING: f64 = 10.0;
+/// Высота вкладки с заголовком `mainframe` (как у фрагментов)
+const MAINFRAME_TAB_HEIGHT: f64 = 20.0;
+
+/// Блок оформления
+struct Block {
+    id: &'static str,
+    text: String,
+    font_size: f64,
+    bold: bool,
+    align: HorizontalAlign,
+    /// Рамка вокруг текста (легенда)
+    framed: bool,
+    width: f64,
+    height: f64,
+}
+
+impl Block {
+    fn new(
+        id: &'static str,
+        text: &str,
+        font_size: f64,
+        bold: bool,
+        align: HorizontalAlign,
+        metrics: &TextMetrics,
+    ) -> Self {
+        let size = metrics.size_at(text, font_size);
+        let width = if bold {
+            metrics.bold_width_at(text, font_size)
+        } else {
+            size.width
+        };
+        Self {
+            id,
+            text: text.to_string(),
+            font_size,
+            bold,
+            align,
+            framed: false,
+            width,
+            height: size.height,
+        }
+    }
+
+    fn framed(mut self) -> Self {
+        self.framed = true;
+        self.width += LEGEND_PADDING * 2.0;
+        self.height += LEGEND_PADDING * 2.0;
+        self
+    }
+
+    /// Элементы блока с левым верхним углом в (`x`, `y`)
+    fn elements(self, x: f64, y: f64) -> Vec<LayoutElement> {
+        let mut elements = Vec::new();
+        let mut text_bounds = Rect::new(x, y, self.width, self.height);
+        let mut anchor = match self.align {
+            HorizontalAlign::Left => "start",
+            HorizontalAlign::Center => "middle",
+            HorizontalAlign::Right => "end",
+        };
+
+        if self.framed {
+            elements.push(LayoutElement::new(
+                format!("{}-frame", self.id),
+                Rect::new(x, y, self.width, self.height),
+                ElementType::Rectangle {
+                    label: String::new(),
+                    corner_radius: 0.0,
+                },
+            ));
+            text_bounds = Rect::new(
+                x + LEGEND_PADDING,
+                y + LEGEND_PADDING,
+                self.width - LEGEND_PADDING * 2.0,
+                self.height - LEGEND_PADDING * 2.0,
+            );
+            // Строки легенды выравниваются по левому краю рамки
+            anchor = "start";
+        }
+
+        let mut text = LayoutElement::new(
+            self.id,
+            text_bounds,
+            ElementType::Text {
+                text: self.text,
+                font_size: self.font_size,
+            },
+        );
+        text.properties
+            .insert(PROPERTY_TEXT_ANCHOR.to_string(), anchor.to_string());
+        if self.bold {
+            text.properties
+                .insert(PROPERTY_FONT_WEIGHT.to_string(), "bold".to_string());
+        }
+        elements.push(text);
+        elements
+    }
+}
+
+/// Добавляет к результату layout заголовок, колонтитулы, подпись и легенду
+///
+/// Без метаданных оформления результат возвращается без изменений.
+pub fn decorate(
+    mut result: LayoutResult,
+    metadata: &DiagramMetadata,
+    metrics: &TextMetrics,
+) -> LayoutResult {
+    let base = metrics.font_size;
+    let small = (base - 2.0).max(8.0);
+
+    if let Some(title) = &metadata.mainframe {
+        result = mainframe(result, title, metrics);
+    }
+
+    // Блоки над диаграммой — от ближнего к дальнему
+    let mut top = Vec::new();
+    let mut bottom = Vec::new();
+
+    if let Some(legend) = &metadata.legend {
+        let block = Block::new("legend", &legend.text, base, false, legend.align, metrics).framed();
+        if legend.top {
+            top.push(block);
+        } else {
+            bottom.push(block);
+        }
+    }
+    if let Some(title) = &metadata.title {
+        top.push(Block::new("title", title, base + 4.0, true, HorizontalAlign::Center, metrics));
+    }
+    if let Some(header) = &metadata.header {
+        let align = metadata.header_align.unwrap_or(HorizontalAlign::Right);
+        top.push(Block::new("header", header, small, false, align, metrics));
+    }
+    if let Some(caption) = &metadata.caption {
+        bottom.push(Block::new("caption", caption, base, false, HorizontalAlign::Center, metrics));
+    }
+    if let Some(footer) = &metadata.footer {
+        let align = metadata.footer_align.unwrap_or(HorizontalAlign::Center);
+        bottom.push(Block::new("footer", footer, small, false, align, metrics));
+    }
+
+    if top.is_empty() && bottom.is_empty() {
+        return result;
+    }
+
+    // Ширина оформления — не меньше ширины диаграммы, по центру диаграммы
+    let bounds = result.bounds;
+    let width = top
+        .iter()
+        .chain(&bottom)
+        .map(|block| block.width)
+        .fold(bounds.width, f64::max);
+    let left = bounds.x + bounds.width / 2.0 - width / 2.0;
+    let block_x = |block: &Block| match block.align {
+        HorizontalAlign::Left => left,
+        HorizontalAlign::Center => left + (width - block.width) / 2.0,
+        HorizontalAlign::Right => left + width - block.width,
+    };
+
+    let mut top_y = bounds.y;
+    for block in top {
+        top_y -= BLOCK_GAP + block.height;
+        let x = block_x(&block);
+        result.elements.extend(block.elements(x, top_y));
+    }
+
+    let mut bottom_y = bounds.y + bounds.height;
+    for block in bottom {
+        bottom_y += BLOCK_GAP;
+        let x = block_x(&block);
+        let height = block.height;
+        result.elements.extend(block.elements(x, bottom_y));
+        bottom_y += height;
+    }
+
+    result.bounds = Rect::new(
+        left.min(bounds.x),
+        top_y,
+        width.max(bounds.width),
+        bottom_y - top_y,
+    );
+    result
+}
+
+/// Ширина вкладки с заголовком у `mainframe` и фрагментов
+///
+/// Заголовок вкладки — полужирный текст основного размера шрифта.
+pub fn tab_width(title: &str, metrics: &TextMetrics) -> f64 {
+    (metrics.bold_width_at(title, metrics.font_size) + 16.0).max(40.0)
+}
+
+/// Обводит диаграмму рамкой с заголовком во вкладке слева сверху
+fn mainframe(mut result: LayoutResult, title: &str, metrics: &TextMetrics) -> LayoutResult {
+    let bounds = result.bounds;
+    let tab_width = tab_width(title, metrics);
+    let frame = Rect::new(
+        bounds.x - MAINFRAME_PADDING,
+        bounds.y - MAINFRAME_PADDING - MAINFRAME_TAB_HEIGHT,
+        (bounds.width + MAINFRAME_PADDING * 2.0).max(tab_width + MAINFRAME_PADDING),
+        bounds.height + MAINFRAME_PADDING * 2.0 + MAINFRAME_TAB_HEIGHT,
+    );
+    result.elements.insert(
+        0,
+        LayoutElement::new(
+            "mainframe",
+            frame,
+            ElementType::Fragment {
+                fragment_type: title.to_string(),
+                sections: Vec::new(),
+            },
+        ),
+    );
+    result.bounds = frame;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plantuml_ast::common::Legend;
+
+    fn diagram() -> LayoutResult {
+        LayoutResult {
+            elements: vec![LayoutElement::new(
+                "node",
+                Rect::new(0.0, 0.0, 200.0, 100.0),
+                ElementType::Rectangle {
+                    label: "node".to_string(),
+                    corner_radius: 0.0,
+                },
+            )],
+            bounds: Rect::new(0.0, 0.0, 200.0, 100.0),
+        }
+    }
+
+    fn element<'a>(result: &'a LayoutResult, id: &str) -> &'a LayoutElement {
+        result.elements.iter().find(|e| e.id == id).unwrap()
+    }
+
+    #[test]
+    fn test_no_metadata_keeps_result() {
+        let result = decorate(diagram(), &DiagramMetadata::default(), &TextMetrics::default());
+        assert_eq!(result.elements.len(), 1);
+        assert_eq!(result.bounds, Rect::new(0.0, 0.0, 200.0, 100.0));
+    }
+
+    #[test]
+    fn test_blocks_order() {
+        let metadata = DiagramMetadata {
+            title: Some("Title".to_string()),
+            header: Some("Header".to_string()),
+            caption: Some("Caption".to_string()),
+            footer: Some("Footer".to_string()),
+            legend: Some(Legend::new("Legend")),
+            ..Default::default()
+        };
+        let result = decorate(diagram(), &metadata, &TextMetrics::default());
+
+        let y = |id: &str| element(&result, id).bounds.y;
+        assert!(y("header") < y("title"));
+        assert!(y("title") + element(&result, "title").bounds.height < 0.0);
+        assert!(y("legend-frame") > 100.0);
+        assert!(y("legend-frame") < y("caption"));
+        assert!(y("caption") < y("footer"));
+
+        // Заголовок по центру, верхний колонтитул справа
+        let title = element(&result, "title");
+        assert!((title.bounds.x + title.bounds.width / 2.0 - 100.0).abs() < 1e-6);
+        assert_eq!(title.properties.get(PROPERTY_FONT_WEIGHT).map(String::as_str), Some("bold"));
+        let header = element(&result, "header");
+        assert!((header.bounds.x + header.bounds.width - 200.0).abs() < 1e-6);
+
+        assert!(result.bounds.y <= y("header"));
+        let footer = element(&result, "footer");
+        assert!(
+            (result.bounds.y + result.bounds.height - (footer.bounds.y + footer.bounds.height)).abs()
+                < 1e-6
+        );
+    }
+
+    #[test]
+    fn test_legend_top_left() {
+        let metadata = DiagramMetadata {
+            legend: Some(Legend {
+                text: "**A**\n* item".to_string(),
+                top: true,
+                align: HorizontalAlign::Left,
+            }),
+            ..Default::default()
+        };
+        let result = decorate(diagram(), &metadata, &TextMetrics::default());
+        let frame = element(&result, "legend-frame");
+        assert_eq!(frame.bounds.x, 0.0);
+        assert!(frame.bounds.y + frame.bounds.height < 0.0);
+        let text = element(&result, "legend");
+        assert_eq!(text.properties.get(PROPERTY_TEXT_ANCHOR).map(String::as_str), Some("start"));
+    }
+
+    #[test]
+    fn test_wide_title_extends_bounds() {
+        let metadata = DiagramMetadata {
+            title: Some("A very long diagram title that is wider than the diagram".to_string()),
+            ..Default::default()
+        };
+        let result = decorate(diagram(), &metadata, &TextMetrics::default());
+        let title = element(&result, "title");
+        assert!(title.bounds.width > 200.0);
+        assert!(result.bounds.x < 0.0);
+        assert!((result.bounds.width - title.bounds.width).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_mainframe_wraps_diagram() {
+        let metadata = DiagramMetadata {
+            mainframe: Some("sd Login".to_string()),
+            title: Some("Title".to_string()),
+            ..Default::default()
+        };
+        let result = decorate(diagram(), &metadata, &TextMetrics::default());
+        let frame = element(&result, "mainframe");
+        assert_eq!(frame.bounds, Rect::new(-10.0, -30.0, 220.0, 140.0));
+        assert!(matches!(&frame.element_type, ElementType::Fragment { fragment_type, .. } if fragment_type == "sd Login"));
+
+        // Заголовок — над рамкой
+        let title = element(&result, "title");
+        assert!(title.bounds.y + title.bounds.height < frame.bounds.y);
+        assert!(result.bounds.y <= title.bounds.y);
+    }
+
+    #[test]
+    fn test_mainframe_tab_measured() {
+        let metrics = TextMetrics::default();
+        // Широкие буквы шире узких при том же числе символов
+        let (wide, narrow) = ("W".repeat(20), "i".repeat(20));
+        assert!(tab_width(&wide, &metrics) > tab_width(&narrow, &metrics));
+
+        // Рамка не уже вкладки с длинным заголовком
+        let title = "sd Очень длинный заголовок диаграммы входа пользователя";
+        let metadata = DiagramMetadata {
+            mainframe: Some(title.to_string()),
+            ..Default::default()
+        };
+        let result = decorate(diagram(), &metadata, &metrics);
+        let frame = element(&result, "mainframe");
+        assert_eq!(frame.bounds.width, tab_width(title, &metrics) + 10.0);
+    }
+}
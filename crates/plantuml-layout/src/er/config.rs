@@ -1,5 +1,7 @@
 //! Конфигурация layout для ER диаграмм
 
+use crate::text::TextMetrics;
+
 /// Конфигурация для ER layout engine
 #[derive(Debug, Clone)]
 pub struct ErLayoutConfig {
@@ -23,6 +25,8 @@ pub struct ErLayoutConfig {
     pub entity_bg_color: &'static str,
     /// Цвет заголовка
     pub header_bg_color: &'static str,
+    /// Метрики текста (шрифт темы)
+    pub text: TextMetrics,
 }
 
 impl Default for ErLayoutConfig {
@@ -38,6 +42,7 @@ impl Default for ErLayoutConfig {
             font_size: 13.0,
             entity_bg_color: "#FEFECE",
             header_bg_color: "#E2E2F0",
+            text: TextMetrics::default(),
         }
     }
 }
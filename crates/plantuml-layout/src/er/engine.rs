@@ -1,16 +1,20 @@
 //! Layout engine для ER диаграмм
 //!
 //! Размещает сущности в виде таблиц с атрибутами.
-//! Использует простой grid layout с оптимизацией для связей.
+//! Использует простой grid layout с оптимизацией для связей; связи
+//! прокладываются в обход сущностей.
 
 use std::collections::HashMap;
 
+use plantuml_ast::common::LineType;
 use plantuml_ast::er::{ErDiagram, Entity};
-use plantuml_model::{Point, Rect, Size};
+use plantuml_model::{Rect, Size};
 
 use crate::er::config::ErLayoutConfig;
+use crate::routing::{EdgeSpec, Router};
+use crate::text::TextMetrics;
 use crate::traits::{LayoutEngine, LayoutResult};
-use crate::{EdgeType, ElementType, LayoutConfig, LayoutElement};
+use crate::{EdgeType, ElementStyle, ElementType, LayoutConfig, LayoutElement};
 
 /// Layout engine для ER диаграмм
 pub struct ErLayoutEngine {
@@ -30,10 +34,17 @@ impl ErLayoutEngine {
         Self { config }
     }
 
+    /// Устанавливает метрики текста (шрифт темы)
+    pub fn with_text_metrics(mut self, text: TextMetrics) -> Self {
+        self.config.text = text;
+        self
+    }
+
     /// Вычисляет размер сущности
     fn calculate_entity_size(&self, entity: &Entity) -> Size {
         let width = self.config.min_entity_width.max(
-            entity.id.name.len() as f64 * 9.0 + self.config.entity_padding * 2.0,
+            self.config.text.bold_width_at(&entity.id.name, self.config.font_size)
+                + self.config.entity_padding * 2.0,
         );
 
         // Находим максимальную ширину атрибута
@@ -43,7 +54,8 @@ impl ErLayoutEngine {
             .map(|a| {
                 let type_str = a.data_type.as_deref().unwrap_or("");
                 let stereo_str = a.stereotype.as_deref().map(|s| format!(" <<{}>>", s)).unwrap_or_default();
-                (a.name.len() + type_str.len() + stereo_str.len() + 3) as f64 * 7.5
+                let line = format!("{} : {}{}", a.name, type_str, stereo_str);
+                self.config.text.width_at(&line, self.config.font_size)
             })
             .fold(0.0, f64::max);
 
@@ -119,7 +131,8 @@ impl ErLayoutEngine {
             ]
             .into_iter()
             .collect(),
-        };
+        }
+        .with_style(&ElementStyle::new().with_fill(entity.background_color.as_ref()));
         elements.push(bg);
 
         // Заголовок
@@ -218,42 +231,35 @@ impl ErLayoutEngine {
         positions: &HashMap<String, Rect>,
         elements: &mut Vec<LayoutElement>,
     ) {
-        for (i, rel) in diagram.relationships.iter().enumerate() {
-            let from_bounds = match positions.get(&rel.from) {
-                Some(b) => b,
-                None => continue,
-            };
-            let to_bounds = match positions.get(&rel.to) {
-                Some(b) => b,
-                None => continue,
-            };
-
-            // Вычисляем точки соединения
-            let from_center = Point::new(
-                from_bounds.x + from_bounds.width / 2.0,
-                from_bounds.y + from_bounds.height / 2.0,
-            );
-            let to_center = Point::new(
-                to_bounds.x + to_bounds.width / 2.0,
-                to_bounds.y + to_bounds.height / 2.0,
-            );
-
-            // Определяем точки на границах
-            let (from_point, to_point) =
-                self.calculate_connection_points(from_bounds, to_bounds, from_center, to_center);
-
+        let visible: Vec<_> = diagram
+            .relationships
+            .iter()
+            .enumerate()
+            .filter_map(|(i, rel)| Some((i, rel, *positions.get(&rel.from)?, *positions.get(&rel.to)?)))
+            .collect();
+        let specs: Vec<EdgeSpec> = visible
+            .iter()
+            .map(|(_, rel, from, to)| EdgeSpec::new(*from, *to).with_label(rel.label.as_deref()))
+            .collect();
+        let router = Router::new(
+            diagram.metadata.line_type.unwrap_or(LineType::Polyline),
+            self.config.text.clone(),
+        )
+        .with_obstacles(positions.values().copied());
+
+        for ((i, rel, _, _), route) in visible.into_iter().zip(router.route_all(&specs)) {
             // Линия связи
-            let edge = LayoutElement {
+            let mut edge = LayoutElement {
                 id: format!("rel_{}", i),
                 element_type: ElementType::Edge {
-                    points: vec![from_point, to_point],
+                    points: Vec::new(),
                     label: rel.label.clone(),
                     arrow_start: false,
                     arrow_end: false,
                     dashed: false,
                     edge_type: EdgeType::Link, from_cardinality: None, to_cardinality: None,
                 },
-                bounds: Rect::from_points(from_point, to_point),
+                bounds: Rect::new(0.0, 0.0, 0.0, 0.0),
                 text: rel.label.clone(),
                 properties: [
                     ("stroke".to_string(), "#181818".to_string()),
@@ -269,54 +275,10 @@ impl ErLayoutEngine {
                 .into_iter()
                 .collect(),
             };
+            route.apply(&mut edge);
             elements.push(edge);
         }
     }
-
-    /// Вычисляет точки соединения на границах прямоугольников
-    fn calculate_connection_points(
-        &self,
-        from: &Rect,
-        to: &Rect,
-        from_center: Point,
-        to_center: Point,
-    ) -> (Point, Point) {
-        // Простой расчёт: соединяем ближайшие стороны
-        let dx = to_center.x - from_center.x;
-        let dy = to_center.y - from_center.y;
-
-        let from_point = if dx.abs() > dy.abs() {
-            // Горизонтальное соединение
-            if dx > 0.0 {
-                Point::new(from.x + from.width, from_center.y)
-            } else {
-                Point::new(from.x, from_center.y)
-            }
-        } else {
-            // Вертикальное соединение
-            if dy > 0.0 {
-                Point::new(from_center.x, from.y + from.height)
-            } else {
-                Point::new(from_center.x, from.y)
-            }
-        };
-
-        let to_point = if dx.abs() > dy.abs() {
-            if dx > 0.0 {
-                Point::new(to.x, to_center.y)
-            } else {
-                Point::new(to.x + to.width, to_center.y)
-            }
-        } else {
-            if dy > 0.0 {
-                Point::new(to_center.x, to.y)
-            } else {
-                Point::new(to_center.x, to.y + to.height)
-            }
-        };
-
-        (from_point, to_point)
-    }
 }
 
 impl Default for ErLayoutEngine {
@@ -0,0 +1,236 @@
+//! Календарь диаграммы Ганта
+//!
+//! Дни шкалы нумеруются от даты начала проекта (день 0). Календарь переводит
+//! номера дней в настоящие даты, знает дни недели, выходные (`saturday are
+//! closed`) и праздники (`2024-12-25 is closed`) и растягивает по ним
+//! продолжительность задач.
+
+use std::collections::HashSet;
+
+use plantuml_ast::gantt::{GanttDate, GanttDiagram, Weekday};
+
+/// Дни недели по порядку, начиная с понедельника
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Monday,
+    Weekday::Tuesday,
+    Weekday::Wednesday,
+    Weekday::Thursday,
+    Weekday::Friday,
+    Weekday::Saturday,
+    Weekday::Sunday,
+];
+
+/// Короткие названия месяцев для заголовка шкалы
+const MONTH_NAMES: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Календарь проекта
+#[derive(Debug, Clone)]
+pub struct GanttCalendar {
+    /// Дата начала проекта в днях от 1970-01-01
+    start: i64,
+    /// Выходные дни недели
+    closed_weekdays: Vec<Weekday>,
+    /// Праздники в днях от 1970-01-01
+    holidays: HashSet<i64>,
+}
+
+impl GanttCalendar {
+    /// Создаёт календарь без выходных с началом в `start`
+    pub fn new(start: &GanttDate) -> Self {
+        Self {
+            start: days_from_civil(start),
+            closed_weekdays: Vec::new(),
+            holidays: HashSet::new(),
+        }
+    }
+
+    /// Календарь диаграммы: начало проекта, выходные и праздники
+    pub fn from_diagram(diagram: &GanttDiagram, start: &GanttDate) -> Self {
+        let mut calendar = Self::new(start);
+        calendar.closed_weekdays = diagram.closed_days.iter().map(|c| c.day).collect();
+        calendar.holidays = diagram.holidays.iter().map(|h| days_from_civil(&h.date)).collect();
+        calendar
+    }
+
+    /// Дата дня шкалы
+    pub fn date(&self, day: u32) -> GanttDate {
+        civil_from_days(self.start + day as i64)
+    }
+
+    /// Номер дня шкалы для даты (даты до начала проекта — день 0)
+    pub fn day_of(&self, date: &GanttDate) -> u32 {
+        (days_from_civil(date) - self.start).max(0) as u32
+    }
+
+    /// День недели дня шкалы
+    pub fn weekday(&self, day: u32) -> Weekday {
+        WEEKDAYS[weekday_index(self.start + day as i64)]
+    }
+
+    /// Выходной ли день недели
+    pub fn is_closed_weekday(&self, day: u32) -> bool {
+        self.closed_weekdays.contains(&self.weekday(day))
+    }
+
+    /// Праздник ли день
+    pub fn is_holiday(&self, day: u32) -> bool {
+        self.holidays.contains(&(self.start + day as i64))
+    }
+
+    /// Нерабочий ли день (выходной или праздник)
+    pub fn is_closed(&self, day: u32) -> bool {
+        self.is_closed_weekday(day) || self.is_holiday(day)
+    }
+
+    /// Первый рабочий день, начиная с `day`
+    pub fn next_open(&self, day: u32) -> u32 {
+        // Если закрыты все дни недели, задача начинается где указано
+        if self.all_closed() {
+            return day;
+        }
+        let mut day = day;
+        while self.is_closed(day) {
+            day += 1;
+        }
+        day
+    }
+
+    /// Конец (не включая) задачи из `working_days` рабочих дней с началом в `start`
+    ///
+    /// Выходные и праздники внутри задачи её растягивают.
+    pub fn end_after(&self, start: u32, working_days: u32) -> u32 {
+        if self.all_closed() {
+            return start + working_days;
+        }
+        let mut day = start;
+        let mut remaining = working_days;
+        while remaining > 0 {
+            if !self.is_closed(day) {
+                remaining -= 1;
+            }
+            day += 1;
+        }
+        day
+    }
+
+    /// Закрыты ли все дни недели
+    fn all_closed(&self) -> bool {
+        WEEKDAYS.iter().all(|day| self.closed_weekdays.contains(day))
+    }
+
+    /// Рабочих дней в неделе
+    pub fn working_days_per_week(&self) -> u32 {
+        let closed = WEEKDAYS
+            .iter()
+            .filter(|day| self.closed_weekdays.contains(day))
+            .count();
+        (WEEKDAYS.len() - closed).max(1) as u32
+    }
+}
+
+/// Количество дней в месяце
+pub fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Високосный ли год
+pub fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+/// Короткое название месяца (1..=12)
+pub fn month_name(month: u32) -> &'static str {
+    MONTH_NAMES[(month.clamp(1, 12) - 1) as usize]
+}
+
+/// Квартал месяца (1..=4)
+pub fn quarter(month: u32) -> u32 {
+    (month.clamp(1, 12) - 1) / 3 + 1
+}
+
+/// Номер дня от 1970-01-01 для даты григорианского календаря
+///
+/// Некорректные месяц и день приводятся к ближайшим допустимым.
+fn days_from_civil(date: &GanttDate) -> i64 {
+    let month = date.month.clamp(1, 12) as i64;
+    let day = date.day.clamp(1, days_in_month(date.year, date.month.clamp(1, 12))) as i64;
+    // Год начинается с марта, чтобы 29 февраля было последним днём года
+    let year = date.year as i64 - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Дата по номеру дня от 1970-01-01
+fn civil_from_days(days: i64) -> GanttDate {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = (if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 }) as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    GanttDate::new(year as i32, month, day)
+}
+
+/// Индекс дня недели (0 — понедельник); 1970-01-01 был четвергом
+fn weekday_index(days: i64) -> usize {
+    (days + 3).rem_euclid(7) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plantuml_ast::gantt::{ClosedDay, Holiday};
+
+    #[test]
+    fn test_dates_and_weekdays() {
+        let calendar = GanttCalendar::new(&GanttDate::new(2024, 1, 29));
+
+        // Переход через конец месяца и високосный февраль
+        assert_eq!(calendar.date(3), GanttDate::new(2024, 2, 1));
+        assert_eq!(calendar.date(31), GanttDate::new(2024, 2, 29));
+        assert_eq!(calendar.date(32), GanttDate::new(2024, 3, 1));
+        assert_eq!(calendar.day_of(&GanttDate::new(2024, 3, 1)), 32);
+        assert_eq!(calendar.day_of(&GanttDate::new(2023, 12, 31)), 0);
+
+        // 2024-01-29 — понедельник
+        assert_eq!(calendar.weekday(0), Weekday::Monday);
+        assert_eq!(calendar.weekday(5), Weekday::Saturday);
+        assert_eq!(GanttCalendar::new(&GanttDate::new(2000, 1, 1)).weekday(0), Weekday::Saturday);
+    }
+
+    #[test]
+    fn test_closed_days_stretch_duration() {
+        let mut diagram = GanttDiagram::new();
+        diagram.closed_days = vec![
+            ClosedDay { day: Weekday::Saturday },
+            ClosedDay { day: Weekday::Sunday },
+        ];
+        diagram.holidays.push(Holiday::new(GanttDate::new(2024, 1, 3)));
+        // 2024-01-01 — понедельник
+        let calendar = GanttCalendar::from_diagram(&diagram, &GanttDate::new(2024, 1, 1));
+
+        assert!(calendar.is_holiday(2));
+        assert!(calendar.is_closed(5));
+        assert!(!calendar.is_closed(7));
+        assert_eq!(calendar.working_days_per_week(), 5);
+
+        // 5 рабочих дней: пн, вт, чт, пт и следующий пн
+        assert_eq!(calendar.end_after(0, 5), 8);
+        // Задача с субботы переносится на понедельник
+        assert_eq!(calendar.next_open(5), 7);
+    }
+}
@@ -1,5 +1,10 @@
 //! Конфигурация layout для Gantt Diagrams
 
+use plantuml_ast::common::Color;
+use plantuml_ast::gantt::GanttScale;
+
+use crate::text::TextMetrics;
+
 /// Конфигурация layout для Gantt Diagrams
 #[derive(Debug, Clone)]
 pub struct GanttLayoutConfig {
@@ -13,14 +18,22 @@ pub struct GanttLayoutConfig {
     pub bar_height: f64,
     /// Вертикальный отступ между строками
     pub row_spacing: f64,
-    /// Ширина одного дня
+    /// Ширина одного дня (масштаб `daily`)
     pub day_width: f64,
     /// Высота заголовка с датами
     pub header_height: f64,
+    /// Отступ секции загрузки ресурсов от задач
+    pub resource_spacing: f64,
     /// Размер шрифта меток
     pub label_font_size: f64,
     /// Размер шрифта дат
     pub date_font_size: f64,
+    /// Заливка выходных и праздников
+    pub closed_day_color: Color,
+    /// Заливка сегодняшнего дня, если цвет не задан в диаграмме
+    pub today_color: Color,
+    /// Метрики текста (шрифт темы)
+    pub text: TextMetrics,
 }
 
 impl Default for GanttLayoutConfig {
@@ -33,8 +46,25 @@ impl Default for GanttLayoutConfig {
             row_spacing: 5.0,
             day_width: 20.0,
             header_height: 40.0,
+            resource_spacing: 20.0,
             label_font_size: 12.0,
             date_font_size: 10.0,
+            closed_day_color: Color::from_hex("#F1F1F1"),
+            today_color: Color::from_hex("#FFE0E0"),
+            text: TextMetrics::default(),
+        }
+    }
+}
+
+impl GanttLayoutConfig {
+    /// Ширина дня для масштаба шкалы (`printscale weekly` и т.д.)
+    pub fn day_width_for(&self, scale: GanttScale) -> f64 {
+        match scale {
+            GanttScale::Daily => self.day_width,
+            GanttScale::Weekly => self.day_width / 2.5,
+            GanttScale::Monthly => self.day_width / 5.0,
+            GanttScale::Quarterly => self.day_width / 10.0,
+            GanttScale::Yearly => self.day_width / 20.0,
         }
     }
 }
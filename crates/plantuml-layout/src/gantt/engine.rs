@@ -4,12 +4,17 @@
 
 use std::collections::HashMap;
 
-use plantuml_ast::gantt::{GanttDate, GanttDiagram, TaskDuration, TaskStart, Weekday};
+use plantuml_ast::common::Color;
+use plantuml_ast::gantt::{
+    GanttDate, GanttDiagram, GanttScale, GanttTask, MilestoneTime, TaskDuration, TaskStart, Weekday,
+};
 use plantuml_model::{Point, Rect};
 
-use super::GanttLayoutConfig;
+use super::calendar::{month_name, quarter};
+use super::{GanttCalendar, GanttLayoutConfig};
+use crate::text::TextMetrics;
 use crate::traits::LayoutResult;
-use crate::{EdgeType, ElementType, LayoutElement};
+use crate::{EdgeType, ElementStyle, ElementType, LayoutElement, PROPERTY_FONT_WEIGHT, PROPERTY_TEXT_ANCHOR};
 
 /// Layout engine для Gantt Diagrams
 pub struct GanttLayoutEngine {
@@ -29,6 +34,12 @@ impl GanttLayoutEngine {
         Self { config }
     }
 
+    /// Устанавливает метрики текста (шрифт темы)
+    pub fn with_text_metrics(mut self, text: TextMetrics) -> Self {
+        self.config.text = text;
+        self
+    }
+
     /// Выполняет layout диаграммы
     pub fn layout(&self, diagram: &GanttDiagram) -> LayoutResult {
         let mut elements = Vec::new();
@@ -38,44 +49,124 @@ impl GanttLayoutEngine {
             .project_start
             .clone()
             .unwrap_or_else(|| GanttDate::new(2024, 1, 1));
+        let calendar = GanttCalendar::from_diagram(diagram, &project_start);
 
-        // Вычисляем позиции задач
-        let task_positions = self.calculate_task_positions(diagram, &project_start);
+        // Вычисляем позиции задач и вех
+        let task_positions = self.calculate_task_positions(diagram, &calendar);
+        let milestone_days = milestone_days(diagram, &task_positions, &calendar);
+        let today = diagram.today.as_ref().map(|date| calendar.day_of(date));
 
-        // Находим общую длительность
-        let total_days = task_positions
+        // Находим общую длительность: до конца последней задачи, вехи и
+        // сегодняшнего дня, округлённую до целой единицы шкалы
+        let last_day = task_positions
             .values()
             .map(|(_start, end)| *end)
             .max()
-            .unwrap_or(30);
+            .unwrap_or(30)
+            .max(milestone_days.iter().map(|day| day + 1).max().unwrap_or(0))
+            .max(today.map_or(0, |day| day + 1));
+        let total_days = scale_end(&calendar, diagram.scale, last_day);
+
+        // Загрузка ресурсов по дням (секция под диаграммой)
+        let resource_load = if diagram.hide_resource_load {
+            Vec::new()
+        } else {
+            resource_load(diagram, &task_positions, &calendar, total_days)
+        };
 
-        let timeline_width = (total_days as f64) * self.config.day_width;
-        let timeline_start_x = self.config.padding + self.config.task_label_width;
+        let day_width = self.config.day_width_for(diagram.scale);
+        let timeline_width = (total_days as f64) * day_width;
+        // Колонка меток — по самому длинному имени задачи, вехи или ресурса
+        let label_width = diagram
+            .tasks
+            .iter()
+            .map(|task| task.name.as_str())
+            .chain(diagram.milestones.iter().map(|milestone| milestone.name.as_str()))
+            .chain(resource_load.iter().map(|(name, _)| name.as_str()))
+            .map(|name| self.config.text.width_at(name, self.config.label_font_size) + 20.0)
+            .fold(self.config.task_label_width, f64::max);
+        let timeline_start_x = self.config.padding + label_width;
+        let grid_start_y = self.config.padding + self.config.header_height;
+        let row_step = self.config.row_height + self.config.row_spacing;
+        // Строки: задачи, затем вехи
+        let num_rows = diagram.tasks.len() + diagram.milestones.len();
+        let grid_height = (num_rows as f64) * row_step;
+        let row_y = |row: usize| grid_start_y + (row as f64) * row_step;
+        let bar_y = |row: usize| row_y(row) + (self.config.row_height - self.config.bar_height) / 2.0;
 
         // 1. Рисуем заголовок с датами
         self.draw_header(
             &mut elements,
             timeline_start_x,
-            &project_start,
+            day_width,
+            &calendar,
+            diagram.scale,
             total_days,
-            &diagram.closed_days.iter().map(|c| c.day).collect::<Vec<_>>(),
         );
 
-        // 2. Рисуем сетку
+        // 2. Выходные, праздники и сегодняшний день под задачами: только
+        // заливка, без рамки — иначе они выглядят как бары задач
+        let cell_style = |color: &Color| ElementStyle {
+            stroke: Some("none".to_string()),
+            ..ElementStyle::new().with_fill(Some(color))
+        };
+        if matches!(diagram.scale, GanttScale::Daily | GanttScale::Weekly) {
+            for day in 0..total_days {
+                let id = if calendar.is_holiday(day) {
+                    format!("holiday_{}", day)
+                } else if calendar.is_closed_weekday(day) {
+                    format!("weekend_{}", day)
+                } else {
+                    continue;
+                };
+                elements.push(LayoutElement::new(
+                    id,
+                    Rect::new(
+                        timeline_start_x + (day as f64) * day_width,
+                        grid_start_y,
+                        day_width,
+                        grid_height,
+                    ),
+                    ElementType::Rectangle {
+                        label: String::new(),
+                        corner_radius: 0.0,
+                    },
+                )
+                .with_style(&cell_style(&self.config.closed_day_color)));
+            }
+        }
+        if let Some(today) = today {
+            let color = diagram.today_color.as_ref().unwrap_or(&self.config.today_color);
+            elements.push(LayoutElement::new(
+                "today",
+                Rect::new(
+                    timeline_start_x + (today as f64) * day_width,
+                    grid_start_y,
+                    day_width.max(2.0),
+                    grid_height,
+                ),
+                ElementType::Rectangle {
+                    label: String::new(),
+                    corner_radius: 0.0,
+                },
+            )
+            .with_style(&cell_style(color)));
+        }
+
+        // 3. Рисуем сетку
         self.draw_grid(
             &mut elements,
             timeline_start_x,
-            timeline_width,
-            diagram.tasks.len(),
+            day_width,
+            &calendar,
+            diagram.scale,
+            num_rows,
             total_days,
-            &diagram.closed_days.iter().map(|c| c.day).collect::<Vec<_>>(),
         );
 
-        // 3. Рисуем задачи
+        // 4. Рисуем задачи
         for (i, task) in diagram.tasks.iter().enumerate() {
-            let row_y = self.config.padding
-                + self.config.header_height
-                + (i as f64) * (self.config.row_height + self.config.row_spacing);
+            let row_y = row_y(i);
 
             // Метка задачи
             elements.push(LayoutElement {
@@ -83,7 +174,7 @@ impl GanttLayoutEngine {
                 bounds: Rect::new(
                     self.config.padding,
                     row_y,
-                    self.config.task_label_width - 10.0,
+                    label_width - 10.0,
                     self.config.row_height,
                 ),
                 text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Text {
@@ -94,9 +185,9 @@ impl GanttLayoutEngine {
 
             // Бар задачи
             if let Some((start_day, end_day)) = task_positions.get(&task.name) {
-                let bar_x = timeline_start_x + (*start_day as f64) * self.config.day_width;
-                let bar_width = ((*end_day - *start_day) as f64) * self.config.day_width;
-                let bar_y = row_y + (self.config.row_height - self.config.bar_height) / 2.0;
+                let bar_x = timeline_start_x + (*start_day as f64) * day_width;
+                let bar_width = ((*end_day - *start_day) as f64) * day_width;
+                let bar_y = bar_y(i);
 
                 // Основной бар
                 elements.push(LayoutElement {
@@ -106,7 +197,7 @@ impl GanttLayoutEngine {
                         label: String::new(),
                         corner_radius: 3.0,
                     },
-                });
+                }.with_style(&ElementStyle::new().with_fill(task.color.as_ref())));
 
                 // Прогресс бар (если есть)
                 if let Some(complete) = task.complete {
@@ -127,10 +218,146 @@ impl GanttLayoutEngine {
                         });
                     }
                 }
+
+                // Ресурсы справа от бара: `Alice, Bob:50%`
+                if !task.resources.is_empty() && !diagram.hide_resource_names {
+                    let text = task
+                        .resources
+                        .iter()
+                        .map(|resource| match resource.percent {
+                            100 => resource.name.clone(),
+                            percent => format!("{}:{}%", resource.name, percent),
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    elements.push(LayoutElement::new(
+                        format!("task_resources_{}", i),
+                        Rect::new(
+                            bar_x + bar_width.max(5.0) + 5.0,
+                            bar_y + (self.config.bar_height - self.config.date_font_size) / 2.0 - 1.0,
+                            self.config.text.width_at(&text, self.config.date_font_size),
+                            self.config.date_font_size + 2.0,
+                        ),
+                        ElementType::Text {
+                            text,
+                            font_size: self.config.date_font_size,
+                        },
+                    ));
+                }
+            }
+        }
+
+        // 5. Рисуем вехи: ромб на границе дня
+        for (i, (milestone, day)) in diagram.milestones.iter().zip(&milestone_days).enumerate() {
+            let row = diagram.tasks.len() + i;
+            elements.push(LayoutElement::new(
+                format!("milestone_label_{}", i),
+                Rect::new(self.config.padding, row_y(row), label_width - 10.0, self.config.row_height),
+                ElementType::Text {
+                    text: milestone.name.clone(),
+                    font_size: self.config.label_font_size,
+                },
+            ));
+            let size = self.config.bar_height;
+            elements.push(LayoutElement::new(
+                format!("milestone_{}", i),
+                Rect::new(
+                    timeline_start_x + (*day as f64) * day_width - size / 2.0,
+                    bar_y(row),
+                    size,
+                    size,
+                ),
+                ElementType::Diamond { label: None },
+            ));
+        }
+
+        // 6. Рисуем зависимости между барами
+        let rows: HashMap<&str, usize> = diagram
+            .tasks
+            .iter()
+            .enumerate()
+            .flat_map(|(i, task)| {
+                std::iter::once((task.name.as_str(), i))
+                    .chain(task.id.as_deref().map(|id| (id, i)))
+            })
+            .collect();
+        let bar_span = |row: usize| {
+            task_positions.get(&diagram.tasks[row].name).map(|(start, end)| {
+                (
+                    timeline_start_x + (*start as f64) * day_width,
+                    timeline_start_x + (*start as f64) * day_width
+                        + (((*end - *start) as f64) * day_width).max(5.0),
+                )
+            })
+        };
+        for (to, task) in diagram.tasks.iter().enumerate() {
+            for (from_id, kind) in task_dependencies(task) {
+                let Some(&from) = rows.get(from_id) else {
+                    continue;
+                };
+                let (Some((from_start, from_end)), Some((to_start, to_end))) =
+                    (bar_span(from), bar_span(to))
+                else {
+                    continue;
+                };
+                let from_mid = bar_y(from) + self.config.bar_height / 2.0;
+                let points = match kind {
+                    // Из конца бара вправо и вниз (вверх) в начало следующего
+                    Dependency::FinishToStart => {
+                        let x = to_start + ((to_end - to_start) / 2.0).min(6.0);
+                        let to_y = if to > from {
+                            bar_y(to)
+                        } else {
+                            bar_y(to) + self.config.bar_height
+                        };
+                        vec![
+                            Point::new(from_end, from_mid),
+                            Point::new(x, from_mid),
+                            Point::new(x, to_y),
+                        ]
+                    }
+                    // Из начала бара влево и в начало другого
+                    Dependency::StartToStart => {
+                        let x = from_start.min(to_start) - 8.0;
+                        let to_mid = bar_y(to) + self.config.bar_height / 2.0;
+                        vec![
+                            Point::new(from_start, from_mid),
+                            Point::new(x, from_mid),
+                            Point::new(x, to_mid),
+                            Point::new(to_start, to_mid),
+                        ]
+                    }
+                };
+                let bounds = points[1..].iter().fold(
+                    Rect::from_points(points[0], points[0]),
+                    |bounds, point| {
+                        Rect::from_points(
+                            Point::new(bounds.x.min(point.x), bounds.y.min(point.y)),
+                            Point::new(
+                                (bounds.x + bounds.width).max(point.x),
+                                (bounds.y + bounds.height).max(point.y),
+                            ),
+                        )
+                    },
+                );
+                elements.push(LayoutElement::new(
+                    format!("dependency_{}_{}", from, to),
+                    bounds,
+                    ElementType::Edge {
+                        points,
+                        label: None,
+                        arrow_start: false,
+                        arrow_end: true,
+                        dashed: false,
+                        edge_type: EdgeType::Association,
+                        from_cardinality: None,
+                        to_cardinality: None,
+                    },
+                ));
             }
         }
 
-        // 4. Рисуем разделители
+        // 7. Рисуем разделители
         let separator_offset = 0;
         for (i, separator) in diagram.separators.iter().enumerate() {
             // Находим позицию разделителя между задачами
@@ -162,24 +389,79 @@ impl GanttLayoutEngine {
             });
         }
 
-        // 5. Title
-        if let Some(ref title) = diagram.metadata.title {
-            elements.push(LayoutElement {
-                id: "title".to_string(),
-                bounds: Rect::new(self.config.padding, 5.0, 500.0, 20.0),
-                text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Text {
-                    text: title.clone(),
-                    font_size: 14.0,
-                },
-            });
+        // 8. Загрузка ресурсов: строка на исполнителя, пиковая загрузка за
+        // единицу шкалы; перегрузка (больше 100%) выделяется
+        let mut content_bottom = grid_start_y + grid_height;
+        if !resource_load.is_empty() {
+            let section_y = content_bottom + self.config.resource_spacing;
+            let cells = unit_cells(&calendar, diagram.scale, total_days);
+            for (r, (name, load)) in resource_load.iter().enumerate() {
+                let y = section_y + (r as f64) * row_step;
+                elements.push(LayoutElement::new(
+                    format!("resource_label_{}", r),
+                    Rect::new(self.config.padding, y, label_width - 10.0, self.config.row_height),
+                    ElementType::Text {
+                        text: name.clone(),
+                        font_size: self.config.label_font_size,
+                    },
+                ));
+
+                for cell in &cells {
+                    let peak = load[cell.start as usize..cell.end as usize]
+                        .iter()
+                        .copied()
+                        .max()
+                        .unwrap_or(0);
+                    if peak == 0 {
+                        continue;
+                    }
+                    let bounds = Rect::new(
+                        timeline_start_x + (cell.start as f64) * day_width,
+                        y,
+                        ((cell.end - cell.start) as f64) * day_width,
+                        self.config.row_height,
+                    );
+                    let overloaded = peak > 100;
+                    if overloaded {
+                        elements.push(LayoutElement::new(
+                            format!("resource_overload_{}_{}", r, cell.start),
+                            bounds,
+                            ElementType::Rectangle {
+                                label: String::new(),
+                                corner_radius: 0.0,
+                            },
+                        ));
+                    }
+                    let text = peak.to_string();
+                    if self.config.text.width_at(&text, self.config.date_font_size) > bounds.width {
+                        continue;
+                    }
+                    let mut label = LayoutElement::new(
+                        format!("resource_load_{}_{}", r, cell.start),
+                        Rect::new(
+                            bounds.x,
+                            y + (self.config.row_height - self.config.date_font_size) / 2.0 - 1.0,
+                            bounds.width,
+                            self.config.date_font_size + 2.0,
+                        ),
+                        ElementType::Text {
+                            text,
+                            font_size: self.config.date_font_size,
+                        },
+                    )
+                    .with_property(PROPERTY_TEXT_ANCHOR, "middle");
+                    if overloaded {
+                        label = label.with_property(PROPERTY_FONT_WEIGHT, "bold");
+                    }
+                    elements.push(label);
+                }
+            }
+            content_bottom = section_y + (resource_load.len() as f64) * row_step;
         }
 
         // Вычисляем общие размеры
         let total_width = timeline_start_x + timeline_width + self.config.padding;
-        let total_height = self.config.padding
-            + self.config.header_height
-            + (diagram.tasks.len() as f64) * (self.config.row_height + self.config.row_spacing)
-            + self.config.padding;
+        let total_height = content_bottom + self.config.padding;
 
         let mut result = LayoutResult {
             elements,
@@ -189,122 +471,181 @@ impl GanttLayoutEngine {
         result
     }
 
-    /// Вычисляет позиции задач (начало и конец в днях)
+    /// Вычисляет позиции задач (начало и конец в днях шкалы, конец не включается)
+    ///
+    /// Задачи начинаются с ближайшего рабочего дня, но не раньше окончания
+    /// задач из `links`; выходные и праздники растягивают продолжительность,
+    /// заданную в днях и неделях. Она задаёт объём работы: `on {Alice:50%}`
+    /// растягивает задачу вдвое, два исполнителя на полный день — сокращают.
+    ///
+    /// Задача может ссылаться на объявленную ниже (повторное упоминание
+    /// `[Build] starts at [Design]'s end`), поэтому задачи размещаются после
+    /// своих зависимостей.
     fn calculate_task_positions(
         &self,
         diagram: &GanttDiagram,
-        _project_start: &GanttDate,
+        calendar: &GanttCalendar,
     ) -> HashMap<String, (u32, u32)> {
         let mut positions: HashMap<String, (u32, u32)> = HashMap::new();
-        let mut current_day = 0u32;
+        let mut placed = vec![false; diagram.tasks.len()];
 
-        for task in &diagram.tasks {
-            let start_day = match &task.start {
-                TaskStart::AfterPrevious => current_day,
-                TaskStart::After(ref id) => {
-                    positions.get(id).map(|(_, end)| *end).unwrap_or(current_day)
-                }
-                TaskStart::AtDate(_) => current_day, // Упрощение
-                TaskStart::With(ref id) => {
-                    positions.get(id).map(|(start, _)| *start).unwrap_or(current_day)
-                }
-                TaskStart::AtEnd(ref id) => {
-                    positions.get(id).map(|(_, end)| *end).unwrap_or(current_day)
-                }
-            };
-
-            let duration = match &task.duration {
-                TaskDuration::Days(d) => *d,
-                TaskDuration::Weeks(w) => w * 7,
-                TaskDuration::Until(_) => 5, // Упрощение
-                TaskDuration::EndsAt(ref id) => {
-                    positions.get(id).map(|(_, end)| end.saturating_sub(start_day)).unwrap_or(5)
-                }
+        let known = |id: &str| {
+            diagram
+                .tasks
+                .iter()
+                .any(|task| task.id.as_deref() == Some(id) || task.name == id)
+        };
+        let references_placed = |task: &GanttTask, positions: &HashMap<String, (u32, u32)>| {
+            task_references(task).all(|id| !known(id) || positions.contains_key(id))
+        };
+        while placed.contains(&false) {
+            let ready: Vec<usize> = (0..diagram.tasks.len())
+                .filter(|&i| !placed[i])
+                .filter(|&i| {
+                    let task = &diagram.tasks[i];
+                    let previous_ready =
+                        i == 0 || placed[i - 1] || !matches!(task.start, TaskStart::AfterPrevious);
+                    previous_ready && references_placed(task, &positions)
+                })
+                .collect();
+            // Цикл: явные ограничения важнее порядка объявления — задача
+            // без неразмещённых ссылок начинается с начала шкалы
+            let ready = if ready.is_empty() {
+                let waiting = (0..diagram.tasks.len()).filter(|&i| !placed[i]);
+                let next = waiting
+                    .clone()
+                    .find(|&i| references_placed(&diagram.tasks[i], &positions))
+                    .or_else(|| waiting.clone().next());
+                next.into_iter().collect()
+            } else {
+                ready
             };
-
-            let end_day = start_day + duration;
-            
-            let task_id = task.id.clone().unwrap_or_else(|| task.name.clone());
-            positions.insert(task_id, (start_day, end_day));
-            positions.insert(task.name.clone(), (start_day, end_day));
-            
-            current_day = end_day;
+            for i in ready {
+                let task = &diagram.tasks[i];
+                let current_day = i
+                    .checked_sub(1)
+                    .and_then(|previous| positions.get(&diagram.tasks[previous].name))
+                    .map_or(0, |(_, end)| *end);
+                let (start_day, end_day) = Self::task_position(task, &positions, calendar, current_day);
+                let task_id = task.id.clone().unwrap_or_else(|| task.name.clone());
+                positions.insert(task_id, (start_day, end_day));
+                positions.insert(task.name.clone(), (start_day, end_day));
+                placed[i] = true;
+            }
         }
 
         positions
     }
 
+    /// Начало и конец задачи по уже размещённым задачам
+    ///
+    /// `current_day` — конец предыдущей по порядку задачи.
+    fn task_position(
+        task: &GanttTask,
+        positions: &HashMap<String, (u32, u32)>,
+        calendar: &GanttCalendar,
+        current_day: u32,
+    ) -> (u32, u32) {
+        let start_day = match &task.start {
+            TaskStart::AfterPrevious => current_day,
+            TaskStart::After(ref id) => {
+                positions.get(id).map(|(_, end)| *end).unwrap_or(current_day)
+            }
+            TaskStart::AtDate(date) => calendar.day_of(date),
+            TaskStart::With(ref id) => {
+                positions.get(id).map(|(start, _)| *start).unwrap_or(current_day)
+            }
+            TaskStart::AtEnd(ref id) => {
+                positions.get(id).map(|(_, end)| *end).unwrap_or(current_day)
+            }
+        };
+        // `requires [T1]` и `[T1] -> [T2]`: не раньше окончания T1
+        let start_day = task
+            .links
+            .iter()
+            .filter_map(|id| positions.get(id).map(|(_, end)| *end))
+            .fold(start_day, u32::max);
+        let start_day = calendar.next_open(start_day);
+
+        // Суммарная занятость исполнителей, в процентах
+        let allocation: u32 = task.resources.iter().map(|resource| resource.percent).sum();
+        let working_days = |work: u32| match allocation {
+            0 => work,
+            allocation => (work * 100).div_ceil(allocation),
+        };
+        let end_day = match &task.duration {
+            TaskDuration::Days(d) => calendar.end_after(start_day, working_days(*d)),
+            TaskDuration::Weeks(w) => calendar.end_after(
+                start_day,
+                working_days(w * calendar.working_days_per_week()),
+            ),
+            // Дата окончания входит в задачу
+            TaskDuration::Until(date) => (calendar.day_of(date) + 1).max(start_day),
+            TaskDuration::EndsAt(ref id) => positions
+                .get(id)
+                .map(|(_, end)| (*end).max(start_day))
+                .unwrap_or_else(|| calendar.end_after(start_day, 1)),
+        };
+        (start_day, end_day)
+    }
+
     /// Рисует заголовок с датами
+    ///
+    /// Верхняя строка — периоды (месяцы или годы), нижняя — единицы шкалы
+    /// (дни, недели, месяцы, кварталы или годы). Подписи, которые не
+    /// помещаются в неполную ячейку на краю шкалы, пропускаются.
     fn draw_header(
         &self,
         elements: &mut Vec<LayoutElement>,
         start_x: f64,
-        project_start: &GanttDate,
+        day_width: f64,
+        calendar: &GanttCalendar,
+        scale: GanttScale,
         total_days: u32,
-        closed_days: &[Weekday],
     ) {
         let header_y = self.config.padding;
+        let rows = [
+            ("period", header_y, period_cells(calendar, scale, total_days)),
+            ("date", header_y + 20.0, unit_cells(calendar, scale, total_days)),
+        ];
 
-        // Метки дней
-        for day in 0..total_days {
-            let x = start_x + (day as f64) * self.config.day_width;
-            let date_day = project_start.day + day;
-
-            // Проверяем выходной ли это день
-            let day_of_week = (day % 7) as usize;
-            let weekdays = [
-                Weekday::Monday,
-                Weekday::Tuesday,
-                Weekday::Wednesday,
-                Weekday::Thursday,
-                Weekday::Friday,
-                Weekday::Saturday,
-                Weekday::Sunday,
-            ];
-            let is_closed = closed_days.contains(&weekdays[day_of_week]);
-
-            // Показываем только каждый 5-й день чтобы не было слишком плотно
-            if day % 5 == 0 || day == 0 {
-                elements.push(LayoutElement {
-                    id: format!("date_{}", day),
-                    bounds: Rect::new(x, header_y + 20.0, self.config.day_width * 5.0, 15.0),
-                    text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Text {
-                        text: format!("{}", date_day),
-                        font_size: self.config.date_font_size,
-                    },
-                });
-            }
-
-            // Фон для выходных
-            if is_closed {
-                elements.push(LayoutElement {
-                    id: format!("weekend_{}", day),
-                    bounds: Rect::new(
-                        x,
-                        header_y + self.config.header_height,
-                        self.config.day_width,
-                        1000.0, // Высокое значение, будет обрезано
-                    ),
-                    text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Rectangle {
-                        label: String::new(),
-                        corner_radius: 0.0,
-                    },
-                });
+        for (prefix, y, cells) in rows {
+            for cell in cells {
+                let width = ((cell.end - cell.start) as f64) * day_width;
+                if self.config.text.width_at(&cell.label, self.config.date_font_size) > width {
+                    continue;
+                }
+                elements.push(
+                    LayoutElement::new(
+                        format!("{}_{}", prefix, cell.start),
+                        Rect::new(start_x + (cell.start as f64) * day_width, y, width, 15.0),
+                        ElementType::Text {
+                            text: cell.label,
+                            font_size: self.config.date_font_size,
+                        },
+                    )
+                    .with_property(PROPERTY_TEXT_ANCHOR, "middle"),
+                );
             }
         }
     }
 
     /// Рисует сетку
+    ///
+    /// Вертикальные линии отделяют недели (масштаб `daily`) или единицы шкалы.
+    #[allow(clippy::too_many_arguments)]
     fn draw_grid(
         &self,
         elements: &mut Vec<LayoutElement>,
         start_x: f64,
-        width: f64,
+        day_width: f64,
+        calendar: &GanttCalendar,
+        scale: GanttScale,
         num_tasks: usize,
         total_days: u32,
-        _closed_days: &[Weekday],
     ) {
         let grid_start_y = self.config.padding + self.config.header_height;
+        let width = (total_days as f64) * day_width;
 
         // Горизонтальные линии
         for i in 0..=num_tasks {
@@ -323,10 +664,20 @@ impl GanttLayoutEngine {
             });
         }
 
-        // Вертикальные линии (каждую неделю)
-        for day in (0..=total_days).step_by(7) {
-            let x = start_x + (day as f64) * self.config.day_width;
-            let grid_height = (num_tasks as f64) * (self.config.row_height + self.config.row_spacing);
+        // Вертикальные линии: начало шкалы, границы недель или единиц, конец шкалы
+        let boundaries: Vec<u32> = match scale {
+            GanttScale::Daily => (1..total_days)
+                .filter(|day| calendar.weekday(*day) == Weekday::Monday)
+                .collect(),
+            _ => unit_cells(calendar, scale, total_days)
+                .iter()
+                .map(|cell| cell.start)
+                .filter(|start| *start > 0)
+                .collect(),
+        };
+        let grid_height = (num_tasks as f64) * (self.config.row_height + self.config.row_spacing);
+        for day in std::iter::once(0).chain(boundaries).chain(std::iter::once(total_days)) {
+            let x = start_x + (day as f64) * day_width;
 
             elements.push(LayoutElement {
                 id: format!("grid_v_{}", day),
@@ -347,6 +698,196 @@ impl GanttLayoutEngine {
     }
 }
 
+/// Вид зависимости между задачами
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dependency {
+    /// Задача начинается после окончания другой
+    FinishToStart,
+    /// Задача начинается вместе с другой
+    StartToStart,
+}
+
+/// Зависимости задачи: ограничение начала и `requires`/`->` связи
+fn task_dependencies(task: &GanttTask) -> Vec<(&str, Dependency)> {
+    let mut dependencies = match &task.start {
+        TaskStart::After(id) | TaskStart::AtEnd(id) => vec![(id.as_str(), Dependency::FinishToStart)],
+        TaskStart::With(id) => vec![(id.as_str(), Dependency::StartToStart)],
+        TaskStart::AfterPrevious | TaskStart::AtDate(_) => Vec::new(),
+    };
+    for link in &task.links {
+        if !dependencies.iter().any(|(id, _)| *id == link) {
+            dependencies.push((link, Dependency::FinishToStart));
+        }
+    }
+    dependencies
+}
+
+/// Задачи, от положения которых зависит задача
+fn task_references(task: &GanttTask) -> impl Iterator<Item = &str> {
+    let start = match &task.start {
+        TaskStart::After(id) | TaskStart::With(id) | TaskStart::AtEnd(id) => Some(id.as_str()),
+        TaskStart::AfterPrevious | TaskStart::AtDate(_) => None,
+    };
+    let end = match &task.duration {
+        TaskDuration::EndsAt(id) => Some(id.as_str()),
+        _ => None,
+    };
+    start.into_iter().chain(end).chain(task.links.iter().map(String::as_str))
+}
+
+/// День (граница дня) каждой вехи
+fn milestone_days(
+    diagram: &GanttDiagram,
+    positions: &HashMap<String, (u32, u32)>,
+    calendar: &GanttCalendar,
+) -> Vec<u32> {
+    let last_end = diagram
+        .tasks
+        .last()
+        .and_then(|task| positions.get(&task.name))
+        .map_or(0, |(_, end)| *end);
+    diagram
+        .milestones
+        .iter()
+        .map(|milestone| match &milestone.happens {
+            MilestoneTime::AfterPrevious => last_end,
+            MilestoneTime::After(id) => positions.get(id).map_or(last_end, |(_, end)| *end),
+            MilestoneTime::AtStart(id) => positions.get(id).map_or(0, |(start, _)| *start),
+            MilestoneTime::AtDate(date) => calendar.day_of(date),
+        })
+        .collect()
+}
+
+/// Загрузка исполнителей по дням шкалы в процентах, в порядке появления
+///
+/// В нерабочие дни задачи не идут, загрузка — 0.
+fn resource_load(
+    diagram: &GanttDiagram,
+    positions: &HashMap<String, (u32, u32)>,
+    calendar: &GanttCalendar,
+    total_days: u32,
+) -> Vec<(String, Vec<u32>)> {
+    let mut load: Vec<(String, Vec<u32>)> = Vec::new();
+    for task in &diagram.tasks {
+        let Some((start, end)) = positions.get(&task.name) else {
+            continue;
+        };
+        for resource in &task.resources {
+            let index = match load.iter().position(|(name, _)| *name == resource.name) {
+                Some(index) => index,
+                None => {
+                    load.push((resource.name.clone(), vec![0; total_days as usize]));
+                    load.len() - 1
+                }
+            };
+            for day in *start..(*end).min(total_days) {
+                if !calendar.is_closed(day) {
+                    load[index].1[day as usize] += resource.percent;
+                }
+            }
+        }
+    }
+    load
+}
+
+/// Ячейка заголовка шкалы: дни `start..end` и подпись
+#[derive(Debug, Clone, PartialEq)]
+struct ScaleCell {
+    start: u32,
+    end: u32,
+    label: String,
+}
+
+/// Ключ единицы шкалы для дня: дни с одинаковым ключом попадают в одну ячейку
+fn unit_key(calendar: &GanttCalendar, scale: GanttScale, day: u32) -> (i64, String) {
+    let date = calendar.date(day);
+    match scale {
+        GanttScale::Daily => (day as i64, date.day.to_string()),
+        // Неделя начинается с понедельника; подпись — первый день недели на шкале
+        GanttScale::Weekly => {
+            let monday = day as i64 - weekday_number(calendar.weekday(day));
+            (monday, date.day.to_string())
+        }
+        GanttScale::Monthly => (
+            date.year as i64 * 12 + date.month as i64,
+            month_name(date.month).to_string(),
+        ),
+        GanttScale::Quarterly => (
+            date.year as i64 * 4 + quarter(date.month) as i64,
+            format!("Q{}", quarter(date.month)),
+        ),
+        GanttScale::Yearly => (date.year as i64, date.year.to_string()),
+    }
+}
+
+/// Ключ периода (верхняя строка заголовка) для дня
+fn period_key(calendar: &GanttCalendar, scale: GanttScale, day: u32) -> Option<(i64, String)> {
+    let date = calendar.date(day);
+    match scale {
+        GanttScale::Daily | GanttScale::Weekly => Some((
+            date.year as i64 * 12 + date.month as i64,
+            format!("{} {}", month_name(date.month), date.year),
+        )),
+        GanttScale::Monthly | GanttScale::Quarterly => {
+            Some((date.year as i64, date.year.to_string()))
+        }
+        GanttScale::Yearly => None,
+    }
+}
+
+/// Ячейки нижней строки заголовка
+fn unit_cells(calendar: &GanttCalendar, scale: GanttScale, total_days: u32) -> Vec<ScaleCell> {
+    group_days(total_days, |day| Some(unit_key(calendar, scale, day)))
+}
+
+/// Ячейки верхней строки заголовка
+fn period_cells(calendar: &GanttCalendar, scale: GanttScale, total_days: u32) -> Vec<ScaleCell> {
+    group_days(total_days, |day| period_key(calendar, scale, day))
+}
+
+/// Объединяет подряд идущие дни с одинаковым ключом в ячейки
+fn group_days(total_days: u32, key: impl Fn(u32) -> Option<(i64, String)>) -> Vec<ScaleCell> {
+    let mut cells: Vec<ScaleCell> = Vec::new();
+    let mut current: Option<i64> = None;
+    for day in 0..total_days {
+        let Some((id, label)) = key(day) else {
+            continue;
+        };
+        match cells.last_mut() {
+            Some(cell) if current == Some(id) => cell.end = day + 1,
+            _ => cells.push(ScaleCell {
+                start: day,
+                end: day + 1,
+                label,
+            }),
+        }
+        current = Some(id);
+    }
+    cells
+}
+
+/// Конец шкалы: `last_day`, продлённый до конца единицы шкалы
+fn scale_end(calendar: &GanttCalendar, scale: GanttScale, last_day: u32) -> u32 {
+    let mut end = last_day.max(1);
+    while unit_key(calendar, scale, end).0 == unit_key(calendar, scale, end - 1).0 {
+        end += 1;
+    }
+    end
+}
+
+/// Номер дня недели (0 — понедельник)
+fn weekday_number(weekday: Weekday) -> i64 {
+    match weekday {
+        Weekday::Monday => 0,
+        Weekday::Tuesday => 1,
+        Weekday::Wednesday => 2,
+        Weekday::Thursday => 3,
+        Weekday::Friday => 4,
+        Weekday::Saturday => 5,
+        Weekday::Sunday => 6,
+    }
+}
+
 impl Default for GanttLayoutEngine {
     fn default() -> Self {
         Self::new()
@@ -356,7 +897,7 @@ impl Default for GanttLayoutEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use plantuml_ast::gantt::GanttTask;
+    use plantuml_ast::gantt::{ClosedDay, GanttMilestone, GanttResource, Holiday};
 
     #[test]
     fn test_layout_simple_gantt() {
@@ -380,9 +921,168 @@ mod tests {
             .push(GanttTask::new("Task 2").lasts_days(3).starts_after("Task 1"));
 
         let engine = GanttLayoutEngine::new();
-        let positions = engine.calculate_task_positions(&diagram, &GanttDate::new(2024, 1, 1));
+        let calendar = GanttCalendar::new(&GanttDate::new(2024, 1, 1));
+        let positions = engine.calculate_task_positions(&diagram, &calendar);
 
         assert_eq!(positions.get("Task 1"), Some(&(0, 5)));
         assert_eq!(positions.get("Task 2"), Some(&(5, 8)));
     }
+
+    #[test]
+    fn test_task_positions_forward_reference() {
+        // Build объявлена раньше Design, но начинается после неё
+        let mut diagram = GanttDiagram::new();
+        diagram.tasks.push(GanttTask::new("Build").lasts_days(2).starts_after("D"));
+        diagram.tasks.push(GanttTask::new("Design").with_id("D").lasts_days(3));
+        diagram.tasks.push(GanttTask::new("Test").lasts_days(1));
+
+        let engine = GanttLayoutEngine::new();
+        let calendar = GanttCalendar::new(&GanttDate::new(2024, 1, 1));
+        let positions = engine.calculate_task_positions(&diagram, &calendar);
+
+        assert_eq!(positions.get("D"), Some(&(0, 3)));
+        assert_eq!(positions.get("Build"), Some(&(3, 5)));
+        assert_eq!(positions.get("Test"), Some(&(3, 4)));
+    }
+
+    #[test]
+    fn test_calendar_positions() {
+        let mut diagram = GanttDiagram::new();
+        // 2024-01-29 — понедельник
+        diagram.project_start = Some(GanttDate::new(2024, 1, 29));
+        diagram.closed_days = vec![
+            ClosedDay { day: Weekday::Saturday },
+            ClosedDay { day: Weekday::Sunday },
+        ];
+        diagram.holidays.push(Holiday::new(GanttDate::new(2024, 2, 1)));
+        diagram.tasks.push(GanttTask::new("Design").lasts_days(5));
+        let mut release = GanttTask::new("Release");
+        release.start = TaskStart::AtDate(GanttDate::new(2024, 2, 10));
+        release.duration = TaskDuration::Until(GanttDate::new(2024, 2, 13));
+        diagram.tasks.push(release);
+        diagram.tasks.push(GanttTask::new("Docs").lasts_days(1).starts_after("Design"));
+
+        let engine = GanttLayoutEngine::new();
+        let calendar = GanttCalendar::from_diagram(&diagram, &GanttDate::new(2024, 1, 29));
+        let positions = engine.calculate_task_positions(&diagram, &calendar);
+
+        // Праздник и выходные растягивают задачу: пн-ср, пт, пн
+        assert_eq!(positions.get("Design"), Some(&(0, 8)));
+        // Старт в субботу переносится на понедельник, дата окончания включается
+        assert_eq!(positions.get("Release"), Some(&(14, 16)));
+        assert_eq!(positions.get("Docs"), Some(&(8, 9)));
+    }
+
+    #[test]
+    fn test_header_dates_and_today() {
+        let mut diagram = GanttDiagram::new();
+        diagram.project_start = Some(GanttDate::new(2024, 1, 29));
+        diagram.today = Some(GanttDate::new(2024, 2, 2));
+        diagram.tasks.push(GanttTask::new("Task").lasts_days(10));
+
+        let result = GanttLayoutEngine::new().layout(&diagram);
+        let text = |id: &str| {
+            result.elements.iter().find(|e| e.id == id).and_then(|e| match &e.element_type {
+                ElementType::Text { text, .. } => Some(text.clone()),
+                _ => None,
+            })
+        };
+
+        // Даты переходят через конец месяца, месяцы подписаны сверху
+        assert_eq!(text("date_2").as_deref(), Some("31"));
+        assert_eq!(text("date_3").as_deref(), Some("1"));
+        assert_eq!(text("period_3").as_deref(), Some("Feb 2024"));
+
+        let today = result.elements.iter().find(|e| e.id == "today").unwrap();
+        assert_eq!(today.bounds.x, 20.0 + 150.0 + 4.0 * 20.0);
+        let style = today.style();
+        assert_eq!(style.fill.map(|fill| fill.to_string()).as_deref(), Some("#FFE0E0"));
+        assert_eq!(style.stroke.as_deref(), Some("none"));
+    }
+
+    #[test]
+    fn test_monthly_scale() {
+        let mut diagram = GanttDiagram::new();
+        diagram.project_start = Some(GanttDate::new(2024, 1, 1));
+        diagram.scale = GanttScale::Monthly;
+        diagram.tasks.push(GanttTask::new("Task").lasts_days(40));
+
+        let result = GanttLayoutEngine::new().layout(&diagram);
+        let labels: Vec<_> = result
+            .elements
+            .iter()
+            .filter(|e| e.id.starts_with("date_"))
+            .filter_map(|e| match &e.element_type {
+                ElementType::Text { text, .. } => Some((e.id.as_str(), text.as_str())),
+                _ => None,
+            })
+            .collect();
+
+        // Шкала продлевается до конца февраля
+        assert_eq!(labels, vec![("date_0", "Jan"), ("date_31", "Feb")]);
+        assert!(result.elements.iter().any(|e| e.id == "grid_v_60"));
+        assert!(result.elements.iter().any(|e| e.id == "period_0"));
+    }
+
+    #[test]
+    fn test_dependencies_resources_and_milestones() {
+        let mut diagram = GanttDiagram::new();
+        let mut design = GanttTask::new("Design").with_id("D").lasts_days(4);
+        design.resources = vec![GanttResource {
+            name: "Alice".to_string(),
+            percent: 50,
+        }];
+        diagram.tasks.push(design);
+        let mut review = GanttTask::new("Review").lasts_days(2);
+        review.start = TaskStart::With("D".to_string());
+        review.resources = vec![GanttResource::new("Alice")];
+        diagram.tasks.push(review);
+        let mut build = GanttTask::new("Build").lasts_days(1).starts_after("D");
+        build.links.push("Review".to_string());
+        diagram.tasks.push(build);
+        diagram.milestones.push(GanttMilestone::after("Done", "Build"));
+
+        let engine = GanttLayoutEngine::new();
+        let calendar = GanttCalendar::new(&GanttDate::new(2024, 1, 1));
+        let positions = engine.calculate_task_positions(&diagram, &calendar);
+        // Половина рабочего дня — задача вдвое длиннее
+        assert_eq!(positions.get("D"), Some(&(0, 8)));
+        assert_eq!(positions.get("Review"), Some(&(0, 2)));
+        assert_eq!(positions.get("Build"), Some(&(8, 9)));
+
+        let result = engine.layout(&diagram);
+        let find = |id: &str| result.elements.iter().find(|e| e.id == id);
+
+        // Finish-to-start заходит в бар сверху, start-to-start — слева
+        let ElementType::Edge { points, arrow_end, .. } = &find("dependency_0_2").unwrap().element_type else {
+            panic!("ожидалась стрелка");
+        };
+        assert!(*arrow_end);
+        assert_eq!(points[0].x, 170.0 + 8.0 * 20.0);
+        assert_eq!(points.last().unwrap().y, find("task_bar_2").unwrap().bounds.y);
+        let ElementType::Edge { points, .. } = &find("dependency_0_1").unwrap().element_type else {
+            panic!("ожидалась стрелка");
+        };
+        assert_eq!(points.last().unwrap().x, 170.0);
+        assert!(find("dependency_1_2").is_some());
+
+        // Веха — на границе окончания задачи, в своей строке
+        let milestone = find("milestone_0").unwrap();
+        assert_eq!(milestone.bounds.x + milestone.bounds.width / 2.0, 170.0 + 9.0 * 20.0);
+        assert!(milestone.bounds.y > find("task_bar_2").unwrap().bounds.y);
+
+        // Загрузка Alice: 150% в первые два дня — перегрузка
+        assert!(find("resource_overload_0_0").is_some());
+        assert!(find("resource_overload_0_2").is_none());
+        let load = find("resource_load_0_2").unwrap();
+        assert!(matches!(&load.element_type, ElementType::Text { text, .. } if text == "50"));
+        assert_eq!(
+            find("resource_load_0_0").unwrap().properties.get(PROPERTY_FONT_WEIGHT).map(String::as_str),
+            Some("bold")
+        );
+        assert!(matches!(
+            &find("task_resources_0").unwrap().element_type,
+            ElementType::Text { text, .. } if text == "Alice:50%"
+        ));
+    }
 }
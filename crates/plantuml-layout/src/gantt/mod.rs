@@ -2,8 +2,10 @@
 //!
 //! Gantt Diagram отображает задачи на горизонтальной временной шкале.
 
+mod calendar;
 mod config;
 mod engine;
 
+pub use calendar::GanttCalendar;
 pub use config::GanttLayoutConfig;
 pub use engine::GanttLayoutEngine;
@@ -1,5 +1,7 @@
 //! Конфигурация layout для JSON диаграмм
 
+use crate::text::TextMetrics;
+
 /// Конфигурация для JSON layout engine
 #[derive(Debug, Clone)]
 pub struct JsonLayoutConfig {
@@ -27,6 +29,8 @@ pub struct JsonLayoutConfig {
     pub number_color: &'static str,
     /// Цвет boolean/null
     pub keyword_color: &'static str,
+    /// Метрики текста (шрифт темы)
+    pub text: TextMetrics,
 }
 
 impl Default for JsonLayoutConfig {
@@ -44,6 +48,7 @@ impl Default for JsonLayoutConfig {
             string_color: "#008000",
             number_color: "#0000FF",
             keyword_color: "#800080",
+            text: TextMetrics::default(),
         }
     }
 }
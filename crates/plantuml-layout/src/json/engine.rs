@@ -7,6 +7,7 @@ use plantuml_ast::json::{JsonDiagram, JsonNode, JsonValue};
 use plantuml_model::{Rect, Size};
 
 use crate::json::config::JsonLayoutConfig;
+use crate::text::TextMetrics;
 use crate::traits::{LayoutEngine, LayoutResult};
 use crate::{ElementType, LayoutConfig, LayoutElement};
 
@@ -28,6 +29,12 @@ impl JsonLayoutEngine {
         Self { config }
     }
 
+    /// Устанавливает метрики текста (шрифт темы)
+    pub fn with_text_metrics(mut self, text: TextMetrics) -> Self {
+        self.config.text = text;
+        self
+    }
+
     /// Вычисляет layout для JSON узла
     fn layout_node(
         &self,
@@ -262,7 +269,7 @@ impl JsonLayoutEngine {
             value_text
         };
 
-        let text_width = display_text.len() as f64 * 8.0 + 20.0;
+        let text_width = self.config.text.width_at(&display_text, self.config.font_size) + 20.0;
         let width = text_width.max(self.config.min_key_width);
 
         let element = LayoutElement {
@@ -6,15 +6,19 @@ pub mod activity;
 pub mod class;
 pub mod component;
 pub mod config;
+pub mod decoration;
 pub mod er;
 pub mod gantt;
 pub mod json;
 pub mod mindmap;
 pub mod network;
 pub mod object;
+pub mod routing;
 pub mod salt;
 pub mod sequence;
 pub mod state;
+pub mod style;
+pub mod text;
 pub mod timing;
 pub mod traits;
 pub mod usecase;
@@ -25,16 +29,32 @@ pub use activity::{ActivityLayoutConfig, ActivityLayoutEngine};
 pub use class::{ClassLayoutConfig, ClassLayoutEngine};
 pub use component::{ComponentLayoutConfig, ComponentLayoutEngine};
 pub use config::LayoutConfig;
+pub use decoration::{decorate, tab_width, PROPERTY_FONT_WEIGHT, PROPERTY_TEXT_ANCHOR};
 pub use er::{ErLayoutConfig, ErLayoutEngine};
 pub use gantt::{GanttLayoutConfig, GanttLayoutEngine};
 pub use json::{JsonLayoutConfig, JsonLayoutEngine};
 pub use mindmap::{MindMapLayoutConfig, MindMapLayoutEngine};
 pub use network::{NetworkLayoutConfig, NetworkLayoutEngine};
 pub use object::{ObjectLayoutConfig, ObjectLayoutEngine};
+pub use plantuml_ast::common::{Link, Span, Stereotype};
 pub use plantuml_model::{Point, Rect, Size};
+pub use routing::{
+    EdgeSpec, Route, Router, Side, PROPERTY_FROM_CARDINALITY_POSITION, PROPERTY_LABEL_POSITION,
+    PROPERTY_LINE_TYPE, PROPERTY_TO_CARDINALITY_POSITION,
+};
 pub use salt::{SaltLayoutConfig, SaltLayoutEngine};
 pub use sequence::{SequenceLayoutConfig, SequenceLayoutEngine};
 pub use state::{StateLayoutConfig, StateLayoutEngine};
+pub use style::{
+    ElementStyle, GradientDirection, Paint, PROPERTY_FILL, PROPERTY_FONT_FAMILY, PROPERTY_FONT_SIZE,
+    PROPERTY_LINE_STYLE, PROPERTY_STROKE, PROPERTY_STROKE_WIDTH, PROPERTY_TEXT_COLOR,
+};
+pub use text::{
+    FallbackMeasurer, LabelCell, LabelLine, LabelLineKind, LabelSpan, SharedMeasurer,
+    TextMeasurer, TextMetrics, CELL_PADDING_X, CELL_PADDING_Y,
+};
+#[cfg(feature = "font-metrics")]
+pub use text::FontMeasurer;
 pub use timing::{TimingLayoutConfig, TimingLayoutEngine};
 pub use traits::{LayoutEngine, LayoutResult};
 pub use usecase::{UseCaseLayoutConfig, UseCaseLayoutEngine};
@@ -70,8 +90,8 @@ impl ZLayer {
         match &element.element_type {
             ElementType::ParticipantBox => ZLayer::Background,
             ElementType::Activation => ZLayer::Activation,
-            // Lifeline: пунктирная линия без стрелок
-            ElementType::Edge { dashed: true, arrow_end: false, arrow_start: false, .. } 
+            // Lifeline: линия без стрелок (пунктирная или `lifelineStrategy solid`)
+            ElementType::Edge { arrow_end: false, arrow_start: false, .. }
                 if element.id.starts_with("lifeline_") => ZLayer::Lifeline,
             // Обычные сообщения (edges)
             ElementType::Edge { .. } => ZLayer::Message,
@@ -91,6 +111,27 @@ impl ZLayer {
     }
 }
 
+/// Свойство с адресом гиперссылки элемента
+pub const PROPERTY_LINK: &str = "link";
+
+/// Свойство с подсказкой гиперссылки элемента
+pub const PROPERTY_TOOLTIP: &str = "tooltip";
+
+/// Свойство с текстом гиперссылки элемента
+pub const PROPERTY_LINK_LABEL: &str = "link_label";
+
+/// Свойство ребра: украшение начала (`circle` или `cross`)
+pub const PROPERTY_START_DECORATION: &str = "start_decoration";
+
+/// Свойство ребра: украшение конца (`circle` или `cross`)
+pub const PROPERTY_END_DECORATION: &str = "end_decoration";
+
+/// Свойство с номером строки исходника (1-indexed), из которой построен элемент
+pub const PROPERTY_SOURCE_LINE: &str = "source_line";
+
+/// Свойство со стереотипами элемента через запятую (`Service,Entity`)
+pub const PROPERTY_STEREOTYPE: &str = "stereotype";
+
 /// Элемент результата layout
 #[derive(Debug, Clone, PartialEq)]
 pub struct LayoutElement {
@@ -129,6 +170,76 @@ impl LayoutElement {
         self.properties.insert(key.into(), value.into());
         self
     }
+
+    /// Добавляет гиперссылку (свойства `link`, `tooltip` и `link_label`)
+    pub fn with_link(mut self, link: Option<&Link>) -> Self {
+        if let Some(link) = link {
+            self.properties.insert(PROPERTY_LINK.to_string(), link.url.clone());
+            if let Some(tooltip) = &link.tooltip {
+                self.properties.insert(PROPERTY_TOOLTIP.to_string(), tooltip.clone());
+            }
+            if let Some(label) = &link.label {
+                self.properties.insert(PROPERTY_LINK_LABEL.to_string(), label.clone());
+            }
+        }
+        self
+    }
+
+    /// Добавляет стиль элемента (свойства `fill`, `stroke`, `text-color` и др.)
+    pub fn with_style(mut self, style: &ElementStyle) -> Self {
+        style.write(&mut self.properties);
+        self
+    }
+
+    /// Стиль элемента
+    pub fn style(&self) -> ElementStyle {
+        ElementStyle::read(&self.properties)
+    }
+
+    /// Добавляет строку исходника (свойство `source_line`); пустой span пропускается
+    pub fn with_source(mut self, span: Span) -> Self {
+        if span.line > 0 {
+            self.properties
+                .insert(PROPERTY_SOURCE_LINE.to_string(), span.line.to_string());
+        }
+        self
+    }
+
+    /// Добавляет стереотипы (свойство `stereotype`) для skinparam `<<name>>`
+    pub fn with_stereotype(mut self, stereotype: Option<&Stereotype>) -> Self {
+        if let Some(stereotype) = stereotype.filter(|s| !s.names.is_empty()) {
+            self.properties
+                .insert(PROPERTY_STEREOTYPE.to_string(), stereotype.names.join(","));
+        }
+        self
+    }
+
+    /// Имена стереотипов элемента
+    pub fn stereotypes(&self) -> Vec<&str> {
+        self.properties
+            .get(PROPERTY_STEREOTYPE)
+            .map(|names| names.split(',').collect())
+            .unwrap_or_default()
+    }
+
+    /// Строка исходника, из которой построен элемент
+    pub fn source_line(&self) -> Option<usize> {
+        self.properties.get(PROPERTY_SOURCE_LINE)?.parse().ok()
+    }
+
+    /// Гиперссылка элемента
+    pub fn link(&self) -> Option<Link> {
+        let url = self.properties.get(PROPERTY_LINK);
+        let tooltip = self.properties.get(PROPERTY_TOOLTIP);
+        if url.is_none() && tooltip.is_none() {
+            return None;
+        }
+        Some(Link {
+            url: url.cloned().unwrap_or_default(),
+            tooltip: tooltip.cloned(),
+            label: self.properties.get(PROPERTY_LINK_LABEL).cloned(),
+        })
+    }
 }
 
 impl Default for ElementType {
@@ -214,6 +325,8 @@ pub enum ElementType {
     Group {
         label: Option<String>,
         children: Vec<LayoutElement>,
+        /// Вид контейнера: определяет форму рамки
+        kind: ContainerKind,
     },
     /// Combined Fragment (alt, opt, loop, etc.) для sequence diagrams
     /// Рендерится как PlantUML: сплошная рамка + пятиугольник заголовка + разделители else
@@ -237,6 +350,35 @@ pub enum ElementType {
         fields: Vec<ClassMember>,
         /// Методы класса
         methods: Vec<ClassMember>,
+        /// Иконка-кружок классификатора (`hide circle` убирает)
+        show_circle: bool,
+        /// Секция полей (`hide fields` убирает вместе с разделителем)
+        show_fields: bool,
+        /// Секция методов (`hide methods` убирает вместе с разделителем)
+        show_methods: bool,
+    },
+    /// Ромб ветвления/слияния activity диаграмм. С подписью вытягивается
+    /// в шестиугольник, внутри которого помещается текст условия
+    Diamond { label: Option<String> },
+    /// Дорожка (swimlane) activity диаграммы: колонка на всю высоту с
+    /// заголовком. Цвет фона — в свойстве `color`
+    Swimlane {
+        /// Название дорожки
+        title: String,
+        /// Высота заголовка
+        header_height: f64,
+    },
+    /// Пакет/namespace: рамка с вкладкой-папкой, название на вкладке.
+    /// Цвет фона — в свойстве `color`
+    Package {
+        /// Название пакета
+        name: String,
+        /// Стереотип (без «»)
+        stereotype: Option<String>,
+        /// Ширина вкладки
+        tab_width: f64,
+        /// Высота вкладки
+        tab_height: f64,
     },
     /// Participant Box для sequence diagrams (фоновая группировка)
     /// Рендерится как цветной прямоугольник с заголовком сверху
@@ -255,6 +397,30 @@ pub enum ClassifierKind {
     Entity,
 }
 
+/// Вид контейнера (кластера) для Group
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContainerKind {
+    /// Прямоугольник с полосой заголовка
+    #[default]
+    Rectangle,
+    /// Пакет: вкладка с названием
+    Package,
+    /// Узел: объёмный параллелепипед
+    Node,
+    /// Папка: вкладка над названием
+    Folder,
+    /// Фрейм: пятиугольник заголовка
+    Frame,
+    /// Облако: сильно скруглённые углы
+    Cloud,
+    /// База данных: цилиндр
+    Database,
+    /// Хранилище: скруглённые углы
+    Storage,
+    /// Очередь: горизонтальный цилиндр
+    Queue,
+}
+
 /// Член класса (поле или метод) с видимостью
 #[derive(Debug, Clone, PartialEq)]
 pub struct ClassMember {
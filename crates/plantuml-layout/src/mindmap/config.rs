@@ -1,5 +1,7 @@
 //! Конфигурация layout для MindMap диаграмм
 
+use crate::text::TextMetrics;
+
 /// Конфигурация для MindMap layout engine
 #[derive(Debug, Clone)]
 pub struct MindMapLayoutConfig {
@@ -21,6 +23,8 @@ pub struct MindMapLayoutConfig {
     pub font_size: f64,
     /// Радиус скругления узлов
     pub corner_radius: f64,
+    /// Метрики текста (шрифт темы)
+    pub text: TextMetrics,
 }
 
 impl Default for MindMapLayoutConfig {
@@ -35,6 +39,7 @@ impl Default for MindMapLayoutConfig {
             node_padding_y: 6.0,
             font_size: 13.0,
             corner_radius: 5.0,
+            text: TextMetrics::default(),
         }
     }
 }
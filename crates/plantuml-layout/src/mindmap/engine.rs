@@ -9,8 +9,9 @@ use plantuml_ast::mindmap::{MindMapDiagram, MindMapNode, NodeStyle};
 use plantuml_model::{Point, Rect};
 
 use super::MindMapLayoutConfig;
+use crate::text::TextMetrics;
 use crate::traits::LayoutResult;
-use crate::{ElementType, LayoutElement};
+use crate::{ElementStyle, ElementType, LayoutElement};
 
 /// Layout engine для MindMap диаграмм
 pub struct MindMapLayoutEngine {
@@ -36,6 +37,12 @@ impl MindMapLayoutEngine {
         Self { config }
     }
 
+    /// Устанавливает метрики текста (шрифт темы)
+    pub fn with_text_metrics(mut self, text: TextMetrics) -> Self {
+        self.config.text = text;
+        self
+    }
+
     /// Выполняет layout для диаграммы
     pub fn layout(&self, diagram: &MindMapDiagram) -> LayoutResult {
         let mut elements = Vec::new();
@@ -160,10 +167,7 @@ impl MindMapLayoutEngine {
 
     /// Вычисляет ширину узла по тексту
     fn calculate_node_width(&self, text: &str) -> f64 {
-        // Считаем символы Unicode правильно
-        let char_count = text.chars().count();
-        let char_width = self.config.font_size * 0.6;
-        let text_width = char_count as f64 * char_width;
+        let text_width = self.config.text.width_at(text, self.config.font_size);
         (text_width + self.config.node_padding_x * 2.0).max(self.config.min_node_width)
     }
 
@@ -194,16 +198,12 @@ impl MindMapLayoutEngine {
         properties.insert("text".to_string(), node.text.clone());
         properties.insert("level".to_string(), node.level.to_string());
 
-        if let Some(color) = &node.color {
-            properties.insert("color".to_string(), color.to_css());
-        }
-        if let Some(bg) = &node.background_color {
-            properties.insert("background_color".to_string(), bg.to_css());
-        }
         if node.style == NodeStyle::Strikethrough {
             properties.insert("strikethrough".to_string(), "true".to_string());
         }
 
+        // `*[#Orange] text` — цвет заливки узла
+        let fill = node.background_color.as_ref().or(node.color.as_ref());
         LayoutElement {
             id: format!("node_{}", id),
             bounds: *rect,
@@ -211,6 +211,7 @@ impl MindMapLayoutEngine {
             text: Some(node.text.clone()),
             properties,
         }
+        .with_style(&ElementStyle::new().with_fill(fill))
     }
 
     /// Создаёт элемент соединения (кривая Безье)
@@ -297,6 +298,37 @@ mod tests {
         assert!(result.elements.len() >= 3);
     }
 
+    #[test]
+    fn test_node_width_uses_glyph_widths() {
+        let engine = MindMapLayoutEngine::new();
+        // Одинаковое число символов, но разная ширина глифов
+        let wide = engine.calculate_node_width("WWWWWWWWWWWWWWW");
+        let narrow = engine.calculate_node_width("iiiiiiiiiiiiiii");
+        assert!(wide > narrow * 2.0);
+    }
+
+    #[test]
+    fn test_custom_measurer() {
+        use crate::text::{TextMeasurer, TextMetrics};
+        use std::sync::Arc;
+
+        #[derive(Debug)]
+        struct Fixed;
+        impl TextMeasurer for Fixed {
+            fn line_width(&self, line: &str, _font_size: f64, _bold: bool) -> f64 {
+                line.chars().count() as f64 * 10.0
+            }
+            fn line_height(&self, font_size: f64) -> f64 {
+                font_size
+            }
+        }
+
+        let metrics = TextMetrics::new(Arc::new(Fixed), "test", 13.0);
+        let engine = MindMapLayoutEngine::new().with_text_metrics(metrics);
+        let padding = engine.config.node_padding_x * 2.0;
+        assert_eq!(engine.calculate_node_width("abcdefghij"), 100.0 + padding);
+    }
+
     #[test]
     fn test_layout_deep_hierarchy() {
         let mut root = MindMapNode::new(1, "Root");
@@ -1,5 +1,7 @@
 //! Конфигурация layout для Network диаграмм
 
+use crate::text::TextMetrics;
+
 /// Конфигурация для Network layout engine
 #[derive(Debug, Clone)]
 pub struct NetworkLayoutConfig {
@@ -25,6 +27,8 @@ pub struct NetworkLayoutConfig {
     pub server_bg_color: &'static str,
     /// Цвет фона группы
     pub group_bg_color: &'static str,
+    /// Метрики текста (шрифт темы)
+    pub text: TextMetrics,
 }
 
 impl Default for NetworkLayoutConfig {
@@ -41,6 +45,7 @@ impl Default for NetworkLayoutConfig {
             network_bg_color: "#E2E2F0",
             server_bg_color: "#FEFECE",
             group_bg_color: "#FFAAAA33",
+            text: TextMetrics::default(),
         }
     }
 }
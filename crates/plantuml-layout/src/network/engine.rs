@@ -9,6 +9,7 @@ use plantuml_ast::network::{DeviceType, NetworkDiagram, Server};
 use plantuml_model::{Point, Rect};
 
 use crate::network::config::NetworkLayoutConfig;
+use crate::text::TextMetrics;
 use crate::traits::{LayoutEngine, LayoutResult};
 use crate::{EdgeType, ElementType, LayoutConfig, LayoutElement};
 
@@ -30,6 +31,12 @@ impl NetworkLayoutEngine {
         Self { config }
     }
 
+    /// Устанавливает метрики текста (шрифт темы)
+    pub fn with_text_metrics(mut self, text: TextMetrics) -> Self {
+        self.config.text = text;
+        self
+    }
+
     /// Собирает информацию о серверах: в каких сетях они присутствуют
     fn collect_server_networks(&self, diagram: &NetworkDiagram) -> HashMap<String, Vec<usize>> {
         let mut server_networks: HashMap<String, Vec<usize>> = HashMap::new();
@@ -443,6 +450,22 @@ impl LayoutEngine for NetworkLayoutEngine {
     type Input = NetworkDiagram;
 
     fn layout(&self, diagram: &Self::Input, _config: &LayoutConfig) -> LayoutResult {
+        // Ширина серверов — по самому длинному имени
+        let widest = diagram
+            .networks
+            .iter()
+            .flat_map(|network| network.members.iter().map(|member| &member.id.name))
+            .chain(diagram.servers.iter().map(|server| &server.id.name))
+            .map(|name| self.config.text.width_at(name, self.config.font_size) + 20.0)
+            .fold(0.0, f64::max);
+        if widest > self.config.server_width {
+            let engine = Self::with_config(NetworkLayoutConfig {
+                server_width: widest,
+                ..self.config.clone()
+            });
+            return engine.layout(diagram, _config);
+        }
+
         let mut elements = Vec::new();
 
         // Собираем информацию о серверах
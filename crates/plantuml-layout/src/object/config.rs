@@ -1,5 +1,7 @@
 //! Конфигурация layout для Object Diagrams
 
+use crate::text::TextMetrics;
+
 /// Конфигурация layout для Object Diagrams
 #[derive(Debug, Clone)]
 pub struct ObjectLayoutConfig {
@@ -15,6 +17,8 @@ pub struct ObjectLayoutConfig {
     pub vertical_spacing: f64,
     /// Отступ от края диаграммы
     pub padding: f64,
+    /// Метрики текста (шрифт темы)
+    pub text: TextMetrics,
 }
 
 impl Default for ObjectLayoutConfig {
@@ -26,6 +30,7 @@ impl Default for ObjectLayoutConfig {
             horizontal_spacing: 60.0,
             vertical_spacing: 50.0,
             padding: 30.0,
+            text: TextMetrics::default(),
         }
     }
 }
@@ -1,14 +1,24 @@
 //! Layout engine для Object Diagrams
 //!
-//! Конвертирует ObjectDiagram в структуру для рендеринга,
-//! используя простой горизонтальный layout.
+//! Конвертирует ObjectDiagram в структуру для рендеринга. Объекты
+//! размещаются алгоритмом Sugiyama по связям с учётом направления
+//! диаграммы и подсказок стрелок (`-up->`, `-[hidden]->`), связи
+//! прокладываются в обход объектов.
 
+use plantuml_ast::common::LineType;
 use plantuml_ast::object::{ObjectDiagram, ObjectLinkType};
-use plantuml_model::{Point, Rect};
+use plantuml_model::{Rect, Size};
 
 use super::ObjectLayoutConfig;
+use crate::class::sugiyama::SugiyamaLayout;
+use crate::class::{ClassLayoutConfig, Graph};
+use crate::routing::{EdgeSpec, Router};
+use crate::text::TextMetrics;
 use crate::traits::LayoutResult;
-use crate::{EdgeType, ElementType, LayoutElement};
+use crate::{EdgeType, ElementStyle, ElementType, LayoutElement};
+
+/// Высота заголовка объекта
+const HEADER_HEIGHT: f64 = 30.0;
 
 /// Layout engine для Object Diagrams
 pub struct ObjectLayoutEngine {
@@ -28,46 +38,86 @@ impl ObjectLayoutEngine {
         Self { config }
     }
 
+    /// Устанавливает метрики текста (шрифт темы)
+    pub fn with_text_metrics(mut self, text: TextMetrics) -> Self {
+        self.config.text = text;
+        self
+    }
+
     /// Выполняет layout диаграммы объектов
     pub fn layout(&self, diagram: &ObjectDiagram) -> LayoutResult {
         let mut elements = Vec::new();
         let mut object_positions: std::collections::HashMap<String, Rect> =
             std::collections::HashMap::new();
 
-        // 1. Размещаем объекты в сетке
-        let objects_per_row = 4;
-        let mut x = self.config.padding;
-        let mut y = self.config.padding;
-        let mut row_max_height = 0.0f64;
+        // 1. Размещаем объекты по связям
+        let mut graph = Graph::new();
+        for object in &diagram.objects {
+            // Высота по числу полей
+            let fields_height = object.fields.len() as f64 * self.config.field_height;
+            let object_height = (HEADER_HEIGHT + fields_height).max(self.config.object_min_height);
+
+            // Ширина по заголовку и полям, но не меньше стандартной
+            let object_width = object
+                .fields
+                .iter()
+                .map(|field| {
+                    let field_text = format!("{} = {}", field.name, field.value);
+                    self.config.text.width_at(&field_text, 12.0) + 10.0
+                })
+                .fold(self.config.text.width(&object.display_name()) + 20.0, f64::max)
+                .max(self.config.object_width);
+
+            graph.add_node(object.name.clone(), Size::new(object_width, object_height));
+        }
+        for link in &diagram.links {
+            let from = diagram.objects.iter().position(|o| o.name == link.from);
+            let to = diagram.objects.iter().position(|o| o.name == link.to);
+            if let (Some(from), Some(to)) = (from, to) {
+                if from != to {
+                    let edge = graph.add_edge(from, to);
+                    graph.edges[edge].direction = link.direction;
+                }
+            }
+        }
+
+        let sugiyama_config = ClassLayoutConfig {
+            margin: self.config.padding,
+            node_horizontal_spacing: self.config.horizontal_spacing,
+            layer_vertical_spacing: self.config.vertical_spacing,
+            min_class_height: 0.0,
+            ..ClassLayoutConfig::default()
+        };
+        SugiyamaLayout::new(&mut graph, &sugiyama_config)
+            .with_direction(diagram.metadata.direction)
+            .run();
+
         let mut max_x = 0.0f64;
         let mut max_y = 0.0f64;
 
-        for (i, object) in diagram.objects.iter().enumerate() {
-            // Рассчитываем высоту объекта
-            let header_height = 30.0;
-            let fields_height = object.fields.len() as f64 * self.config.field_height;
-            let object_height = (header_height + fields_height).max(self.config.object_min_height);
-
-            // Определяем заголовок (с подчёркиванием как в UML)
-            let display_name = object.display_name();
+        for (object, node) in diagram.objects.iter().zip(&graph.nodes) {
+            let (x, y) = (node.x, node.y);
+            let (object_width, object_height) = (node.size.width, node.size.height);
 
             // Создаём bounds
-            let bounds = Rect::new(x, y, self.config.object_width, object_height);
-            object_positions.insert(object.name.clone(), bounds.clone());
+            let bounds = Rect::new(x, y, object_width, object_height);
+            object_positions.insert(object.name.clone(), bounds);
 
-            // Создаём element для объекта
+            // Создаём element для объекта (заголовок с подчёркиванием как в UML)
             elements.push(LayoutElement {
                 id: format!("object_{}", object.name),
-                bounds: bounds.clone(),
+                bounds,
                 text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Rectangle {
-                    label: display_name,
+                    label: object.display_name(),
                     corner_radius: 0.0, // Объекты без скруглённых углов
                 },
-            });
+            }
+            .with_style(&ElementStyle::new().with_fill(object.background_color.as_ref()))
+            .with_stereotype(object.stereotype.as_ref()));
 
             // Добавляем поля как текст
             for (j, field) in object.fields.iter().enumerate() {
-                let field_y = y + header_height + (j as f64 * self.config.field_height);
+                let field_y = y + HEADER_HEIGHT + (j as f64 * self.config.field_height);
                 let field_text = format!("{} = {}", field.name, field.value);
 
                 elements.push(LayoutElement {
@@ -75,7 +125,7 @@ impl ObjectLayoutEngine {
                     bounds: Rect::new(
                         x + 5.0,
                         field_y,
-                        self.config.object_width - 10.0,
+                        object_width - 10.0,
                         self.config.field_height,
                     ),
                     text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Text {
@@ -85,63 +135,52 @@ impl ObjectLayoutEngine {
                 });
             }
 
-            row_max_height = row_max_height.max(object_height);
-            max_x = max_x.max(x + self.config.object_width);
+            max_x = max_x.max(x + object_width);
             max_y = max_y.max(y + object_height);
-
-            // Переход к следующей позиции
-            if (i + 1) % objects_per_row == 0 {
-                x = self.config.padding;
-                y += row_max_height + self.config.vertical_spacing;
-                row_max_height = 0.0;
-            } else {
-                x += self.config.object_width + self.config.horizontal_spacing;
-            }
         }
 
-        // 2. Добавляем связи
-        for link in &diagram.links {
-            if let (Some(from_bounds), Some(to_bounds)) =
-                (object_positions.get(&link.from), object_positions.get(&link.to))
-            {
-                let from_center = from_bounds.center();
-                let to_center = to_bounds.center();
-
-                // Находим точки соединения на границах прямоугольников
-                let (start, end) =
-                    self.find_connection_points(from_bounds, to_bounds, from_center, to_center);
-
-                let dashed = link.link_type.is_dashed();
-
-                elements.push(LayoutElement {
-                    id: format!("link_{}_{}", link.from, link.to),
-                    bounds: Rect::new(
-                        start.x.min(end.x),
-                        start.y.min(end.y),
-                        (end.x - start.x).abs(),
-                        (end.y - start.y).abs(),
+        // 2. Добавляем связи; скрытые только влияли на layout
+        let visible: Vec<_> = diagram
+            .links
+            .iter()
+            .filter(|link| !link.hidden)
+            .filter_map(|link| Some((link, *object_positions.get(&link.from)?, *object_positions.get(&link.to)?)))
+            .collect();
+        let specs: Vec<EdgeSpec> = visible
+            .iter()
+            .map(|(link, from, to)| EdgeSpec::new(*from, *to).with_label(link.label.as_deref()))
+            .collect();
+        let router = Router::new(
+            diagram.metadata.line_type.unwrap_or(LineType::Polyline),
+            self.config.text.clone(),
+        )
+        .with_obstacles(object_positions.values().copied());
+        for ((link, _, _), route) in visible.iter().zip(router.route_all(&specs)) {
+            let mut edge = LayoutElement {
+                id: format!("link_{}_{}", link.from, link.to),
+                bounds: Rect::new(0.0, 0.0, 0.0, 0.0),
+                text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Edge {
+                    points: Vec::new(),
+                    label: link.label.clone(),
+                    arrow_start: matches!(
+                        link.link_type,
+                        ObjectLinkType::Composition | ObjectLinkType::Aggregation
                     ),
-                    text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Edge {
-                        points: vec![start, end],
-                        label: link.label.clone(),
-                        arrow_start: matches!(
-                            link.link_type,
-                            ObjectLinkType::Composition | ObjectLinkType::Aggregation
-                        ),
-                        arrow_end: !matches!(link.link_type, ObjectLinkType::Link),
-                        dashed,
-                        edge_type: match link.link_type {
-                            ObjectLinkType::Composition => EdgeType::Composition,
-                            ObjectLinkType::Aggregation => EdgeType::Aggregation,
-                            ObjectLinkType::Dependency => EdgeType::Dependency,
-                            ObjectLinkType::Association => EdgeType::Association,
-                            ObjectLinkType::Link => EdgeType::Link,
-                        },
-                        from_cardinality: None,
-                        to_cardinality: None,
+                    arrow_end: !matches!(link.link_type, ObjectLinkType::Link),
+                    dashed: link.link_type.is_dashed(),
+                    edge_type: match link.link_type {
+                        ObjectLinkType::Composition => EdgeType::Composition,
+                        ObjectLinkType::Aggregation => EdgeType::Aggregation,
+                        ObjectLinkType::Dependency => EdgeType::Dependency,
+                        ObjectLinkType::Association => EdgeType::Association,
+                        ObjectLinkType::Link => EdgeType::Link,
                     },
-                });
-            }
+                    from_cardinality: None,
+                    to_cardinality: None,
+                },
+            };
+            route.apply(&mut edge);
+            elements.push(edge);
         }
 
         // 3. Возвращаем результат
@@ -157,71 +196,6 @@ impl ObjectLayoutEngine {
         result.calculate_bounds();
         result
     }
-
-    /// Находит точки соединения на границах прямоугольников
-    fn find_connection_points(
-        &self,
-        from_bounds: &Rect,
-        to_bounds: &Rect,
-        from_center: Point,
-        to_center: Point,
-    ) -> (Point, Point) {
-        let start = self.find_intersection_point(from_bounds, from_center, to_center);
-        let end = self.find_intersection_point(to_bounds, to_center, from_center);
-        (start, end)
-    }
-
-    /// Находит точку пересечения линии с прямоугольником
-    fn find_intersection_point(&self, rect: &Rect, from: Point, to: Point) -> Point {
-        let dx = to.x - from.x;
-        let dy = to.y - from.y;
-
-        // Находим пересечение с каждой стороной прямоугольника
-        let mut best_t = f64::INFINITY;
-
-        // Правая сторона
-        if dx.abs() > 0.001 {
-            let t = (rect.x + rect.width - from.x) / dx;
-            let y = from.y + t * dy;
-            if t > 0.0 && t < best_t && y >= rect.y && y <= rect.y + rect.height {
-                best_t = t;
-            }
-        }
-
-        // Левая сторона
-        if dx.abs() > 0.001 {
-            let t = (rect.x - from.x) / dx;
-            let y = from.y + t * dy;
-            if t > 0.0 && t < best_t && y >= rect.y && y <= rect.y + rect.height {
-                best_t = t;
-            }
-        }
-
-        // Нижняя сторона
-        if dy.abs() > 0.001 {
-            let t = (rect.y + rect.height - from.y) / dy;
-            let x = from.x + t * dx;
-            if t > 0.0 && t < best_t && x >= rect.x && x <= rect.x + rect.width {
-                best_t = t;
-            }
-        }
-
-        // Верхняя сторона
-        if dy.abs() > 0.001 {
-            let t = (rect.y - from.y) / dy;
-            let x = from.x + t * dx;
-            if t > 0.0 && t < best_t && x >= rect.x && x <= rect.x + rect.width {
-                best_t = t;
-            }
-        }
-
-        if best_t == f64::INFINITY {
-            // Fallback: центр
-            from
-        } else {
-            Point::new(from.x + best_t * dx, from.y + best_t * dy)
-        }
-    }
 }
 
 impl Default for ObjectLayoutEngine {
@@ -276,4 +250,25 @@ mod tests {
         // 2 объекта + 1 связь
         assert!(result.elements.len() >= 3);
     }
+
+    #[test]
+    fn test_links_define_layers() {
+        use plantuml_ast::common::Direction;
+
+        let mut diagram = ObjectDiagram::new();
+        diagram.add_object(Object::new("a"));
+        diagram.add_object(Object::new("b"));
+        diagram.add_object(Object::new("c"));
+        diagram.add_link(ObjectLink::new("a", "b"));
+        let mut up = ObjectLink::new("a", "c");
+        up.direction = Some(Direction::BottomToTop);
+        diagram.add_link(up);
+
+        let result = ObjectLayoutEngine::new().layout(&diagram);
+        let bounds = |id: &str| result.elements.iter().find(|e| e.id == id).unwrap().bounds;
+
+        let (a, b, c) = (bounds("object_a"), bounds("object_b"), bounds("object_c"));
+        assert!(b.y >= a.y + a.height);
+        assert!(c.y + c.height <= a.y);
+    }
 }
@@ -0,0 +1,981 @@
+//! Маршрутизация рёбер между прямоугольниками узлов
+//!
+//! Общая для class, state, component, use case, object и ER диаграмм.
+//! Рёбра обходят чужие узлы и рамки: ортогональный маршрут ищется
+//! алгоритмом Дейкстры по разреженной сетке из границ препятствий со
+//! штрафом за изгибы, ломаная (`polyline`) и кривая (`spline`) получаются
+//! из него спрямлением участков прямой видимости. Концы рёбер на одной
+//! стороне узла разводятся по её длине, подписи и кардинальности ставятся
+//! в свободные места рядом с линией.
+//!
+//! Размещение подписей передаётся рендереру свойствами ребра
+//! [`PROPERTY_LABEL_POSITION`], [`PROPERTY_FROM_CARDINALITY_POSITION`] и
+//! [`PROPERTY_TO_CARDINALITY_POSITION`] в формате `"x,y"` — левый край и
+//! базовая линия последней строки текста.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use plantuml_ast::common::LineType;
+use plantuml_model::{Point, Rect, Size};
+
+use crate::text::{text_lines, TextMetrics};
+use crate::{ElementType, LayoutElement};
+
+/// Свойство ребра: позиция подписи
+pub const PROPERTY_LABEL_POSITION: &str = "label_position";
+
+/// Свойство ребра: позиция кардинальности у начала
+pub const PROPERTY_FROM_CARDINALITY_POSITION: &str = "from_cardinality_position";
+
+/// Свойство ребра: позиция кардинальности у конца
+pub const PROPERTY_TO_CARDINALITY_POSITION: &str = "to_cardinality_position";
+
+/// Свойство ребра: форма линии (`spline` — сглаживать точки пути)
+pub const PROPERTY_LINE_TYPE: &str = "line_type";
+
+/// Размер шрифта подписей рёбер (как в рендерере)
+const LABEL_FONT_SIZE: f64 = 13.0;
+
+/// Высота строки подписи в рендерере
+const LABEL_LINE_HEIGHT: f64 = LABEL_FONT_SIZE + 2.0;
+
+/// Расстояние от базовой линии до низа строки
+const LABEL_DESCENT: f64 = 3.0;
+
+/// Сторона прямоугольника узла
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Side {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl Side {
+    /// Сторона после транспонирования осей (для горизонтальных раскладок)
+    pub fn transpose(self) -> Self {
+        match self {
+            Self::Top => Self::Left,
+            Self::Left => Self::Top,
+            Self::Bottom => Self::Right,
+            Self::Right => Self::Bottom,
+        }
+    }
+
+    /// Внешняя нормаль стороны
+    fn normal(self) -> (f64, f64) {
+        match self {
+            Self::Top => (0.0, -1.0),
+            Self::Bottom => (0.0, 1.0),
+            Self::Left => (-1.0, 0.0),
+            Self::Right => (1.0, 0.0),
+        }
+    }
+
+    /// Сторона лежит вдоль оси X
+    fn runs_along_x(self) -> bool {
+        matches!(self, Self::Top | Self::Bottom)
+    }
+
+    /// Сторона `from`, обращённая к `to`
+    ///
+    /// Выбирается ось с наибольшим зазором между прямоугольниками, а для
+    /// перекрывающихся — ось наибольшего смещения центров.
+    fn facing(from: &Rect, to: &Rect) -> Self {
+        let gap_x = (to.x - (from.x + from.width)).max(from.x - (to.x + to.width));
+        let gap_y = (to.y - (from.y + from.height)).max(from.y - (to.y + to.height));
+        let (from_center, to_center) = (from.center(), to.center());
+        let vertical = if gap_x > 0.0 || gap_y > 0.0 {
+            gap_y >= gap_x
+        } else {
+            (to_center.y - from_center.y).abs() >= (to_center.x - from_center.x).abs()
+        };
+        match (vertical, vertical && to_center.y >= from_center.y, to_center.x >= from_center.x) {
+            (true, true, _) => Self::Bottom,
+            (true, false, _) => Self::Top,
+            (false, _, true) => Self::Right,
+            (false, _, false) => Self::Left,
+        }
+    }
+
+    /// Точка на стороне со смещением от её середины
+    fn port(self, rect: &Rect, offset: f64) -> Point {
+        let center = rect.center();
+        match self {
+            Self::Top => Point::new(center.x + offset, rect.y),
+            Self::Bottom => Point::new(center.x + offset, rect.y + rect.height),
+            Self::Left => Point::new(rect.x, center.y + offset),
+            Self::Right => Point::new(rect.x + rect.width, center.y + offset),
+        }
+    }
+
+    /// Направление движения по сетке наружу от стороны
+    fn outward(self) -> Heading {
+        match self {
+            Self::Top => Heading::Up,
+            Self::Bottom => Heading::Down,
+            Self::Left => Heading::Left,
+            Self::Right => Heading::Right,
+        }
+    }
+}
+
+/// Ребро для маршрутизации
+#[derive(Debug, Clone, Default)]
+pub struct EdgeSpec {
+    /// Прямоугольник начального узла
+    pub from: Rect,
+    /// Прямоугольник конечного узла
+    pub to: Rect,
+    /// Сторона выхода (None — обращённая к `to`)
+    pub from_side: Option<Side>,
+    /// Сторона входа (None — обращённая к `from`)
+    pub to_side: Option<Side>,
+    /// Подпись
+    pub label: Option<String>,
+    /// Кардинальность у начала
+    pub from_label: Option<String>,
+    /// Кардинальность у конца
+    pub to_label: Option<String>,
+}
+
+impl EdgeSpec {
+    /// Создаёт ребро между узлами
+    pub fn new(from: Rect, to: Rect) -> Self {
+        Self {
+            from,
+            to,
+            ..Self::default()
+        }
+    }
+
+    /// Задаёт стороны выхода и входа
+    pub fn with_sides(mut self, from_side: Option<Side>, to_side: Option<Side>) -> Self {
+        self.from_side = from_side;
+        self.to_side = to_side;
+        self
+    }
+
+    /// Задаёт подпись
+    pub fn with_label(mut self, label: Option<&str>) -> Self {
+        self.label = label.filter(|l| !l.is_empty()).map(str::to_string);
+        self
+    }
+
+    /// Задаёт кардинальности у концов
+    pub fn with_cardinalities(mut self, from: Option<&str>, to: Option<&str>) -> Self {
+        self.from_label = from.filter(|l| !l.is_empty()).map(str::to_string);
+        self.to_label = to.filter(|l| !l.is_empty()).map(str::to_string);
+        self
+    }
+
+    /// Петля (ребро узла в себя)
+    fn is_loop(&self) -> bool {
+        self.from == self.to
+    }
+}
+
+/// Результат маршрутизации ребра
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    /// Точки пути от начала к концу
+    pub points: Vec<Point>,
+    /// Путь рисуется сглаженной кривой
+    pub spline: bool,
+    /// Прямоугольник подписи
+    pub label: Option<Rect>,
+    /// Прямоугольник кардинальности у начала
+    pub from_label: Option<Rect>,
+    /// Прямоугольник кардинальности у конца
+    pub to_label: Option<Rect>,
+}
+
+impl Route {
+    /// Охватывающий прямоугольник пути и подписей
+    pub fn bounds(&self) -> Rect {
+        let mut min = Point::new(f64::MAX, f64::MAX);
+        let mut max = Point::new(f64::MIN, f64::MIN);
+        let corners = [self.label, self.from_label, self.to_label]
+            .into_iter()
+            .flatten()
+            .flat_map(|rect| [rect.top_left(), rect.bottom_right()]);
+        for point in self.points.iter().copied().chain(corners) {
+            min = Point::new(min.x.min(point.x), min.y.min(point.y));
+            max = Point::new(max.x.max(point.x), max.y.max(point.y));
+        }
+        if self.points.is_empty() {
+            return Rect::new(0.0, 0.0, 0.0, 0.0);
+        }
+        Rect::new(min.x, min.y, (max.x - min.x).max(1.0), (max.y - min.y).max(1.0))
+    }
+
+    /// Переносит путь, границы и позиции подписей в элемент ребра
+    pub fn apply(&self, element: &mut LayoutElement) {
+        if let ElementType::Edge { points, .. } = &mut element.element_type {
+            points.clone_from(&self.points);
+        }
+        element.bounds = self.bounds();
+
+        let positions = [
+            (PROPERTY_LABEL_POSITION, self.label),
+            (PROPERTY_FROM_CARDINALITY_POSITION, self.from_label),
+            (PROPERTY_TO_CARDINALITY_POSITION, self.to_label),
+        ];
+        for (key, rect) in positions {
+            if let Some(rect) = rect {
+                let baseline = rect.y + rect.height - LABEL_DESCENT;
+                element.properties.insert(key.to_string(), format!("{},{}", rect.x, baseline));
+            }
+        }
+        if self.spline {
+            element.properties.insert(PROPERTY_LINE_TYPE.to_string(), "spline".to_string());
+        }
+    }
+}
+
+/// Разбирает позицию `"x,y"` из свойства ребра
+pub fn parse_position(value: &str) -> Option<Point> {
+    let (x, y) = value.split_once(',')?;
+    Some(Point::new(x.trim().parse().ok()?, y.trim().parse().ok()?))
+}
+
+/// Сдвигает позиции подписей ребра вместе с его точками
+pub fn translate_positions(element: &mut LayoutElement, dx: f64, dy: f64) {
+    for key in [
+        PROPERTY_LABEL_POSITION,
+        PROPERTY_FROM_CARDINALITY_POSITION,
+        PROPERTY_TO_CARDINALITY_POSITION,
+    ] {
+        if let Some(position) = element.properties.get(key).and_then(|value| parse_position(value)) {
+            element
+                .properties
+                .insert(key.to_string(), format!("{},{}", position.x + dx, position.y + dy));
+        }
+    }
+}
+
+/// Маршрутизатор рёбер
+#[derive(Debug, Clone)]
+pub struct Router {
+    /// Форма линий
+    line_type: LineType,
+    /// Узлы: рёбра и подписи их не пересекают
+    obstacles: Vec<Rect>,
+    /// Рамки (пакеты, системы): рёбра обходят рамки, не содержащие их
+    /// концов, подписи не пересекают границы рамок
+    frames: Vec<Rect>,
+    /// Метрики текста подписей
+    text: TextMetrics,
+    /// Отступ линий от препятствий
+    clearance: f64,
+    /// Наибольший шаг между концами рёбер на одной стороне узла
+    port_spacing: f64,
+    /// Штраф за изгиб в единицах длины
+    bend_penalty: f64,
+}
+
+impl Router {
+    /// Создаёт маршрутизатор без препятствий
+    pub fn new(line_type: LineType, text: TextMetrics) -> Self {
+        Self {
+            line_type,
+            obstacles: Vec::new(),
+            frames: Vec::new(),
+            text,
+            clearance: 10.0,
+            port_spacing: 20.0,
+            bend_penalty: 40.0,
+        }
+    }
+
+    /// Добавляет узлы-препятствия
+    pub fn with_obstacles(mut self, obstacles: impl IntoIterator<Item = Rect>) -> Self {
+        self.obstacles.extend(obstacles);
+        self
+    }
+
+    /// Добавляет рамки групп
+    pub fn with_frames(mut self, frames: impl IntoIterator<Item = Rect>) -> Self {
+        self.frames.extend(frames);
+        self
+    }
+
+    /// Прокладывает все рёбра диаграммы
+    ///
+    /// Рёбра прокладываются вместе: концы на общей стороне узла разводятся,
+    /// подписи не накладываются друг на друга и на линии других рёбер.
+    pub fn route_all(&self, edges: &[EdgeSpec]) -> Vec<Route> {
+        let ports = self.assign_ports(edges);
+
+        let mut routes: Vec<Route> = edges
+            .iter()
+            .zip(&ports)
+            .map(|(edge, ports)| {
+                let points = match ports {
+                    Some((start, start_side, end, end_side)) => {
+                        self.route_path(edge, *start, *start_side, *end, *end_side)
+                    }
+                    None => self.loop_path(&edge.from),
+                };
+                Route {
+                    points,
+                    spline: self.line_type == LineType::Spline,
+                    label: None,
+                    from_label: None,
+                    to_label: None,
+                }
+            })
+            .collect();
+
+        // Подписи ставятся после всех линий, чтобы обходить и чужие линии
+        let segments: Vec<(Point, Point)> = routes
+            .iter()
+            .flat_map(|route| route.points.windows(2).map(|w| (w[0], w[1])))
+            .collect();
+        let mut placed: Vec<Rect> = Vec::new();
+        for (edge, route) in edges.iter().zip(routes.iter_mut()) {
+            let free = |rect: &Rect, placed: &[Rect]| {
+                !self.obstacles.iter().any(|o| overlaps(rect, o))
+                    && !self.frames.iter().any(|f| overlaps(rect, f) && !contains(f, rect))
+                    && !placed.iter().any(|p| overlaps(rect, p))
+                    && !segments.iter().any(|&(a, b)| segment_hits_rect(a, b, rect))
+            };
+
+            let ends = [
+                (edge.from_label.as_deref(), route.points.first().copied(), route.points.get(1).copied()),
+                (edge.to_label.as_deref(), route.points.last().copied(), route.points.iter().rev().nth(1).copied()),
+            ];
+            let mut end_rects = [None, None];
+            for (slot, (text, port, next)) in end_rects.iter_mut().zip(ends) {
+                let (Some(text), Some(port), Some(next)) = (text, port, next) else {
+                    continue;
+                };
+                let size = self.label_size(text);
+                let candidates = Self::end_candidates(port, next, size);
+                let rect = candidates.iter().copied().find(|r| free(r, &placed)).unwrap_or(candidates[0]);
+                placed.push(rect);
+                *slot = Some(rect);
+            }
+            route.from_label = end_rects[0];
+            route.to_label = end_rects[1];
+
+            if let Some(text) = edge.label.as_deref() {
+                let size = self.label_size(text);
+                let candidates = Self::label_candidates(&route.points, size);
+                if let Some(&first) = candidates.first() {
+                    let rect = candidates.iter().copied().find(|r| free(r, &placed)).unwrap_or(first);
+                    placed.push(rect);
+                    route.label = Some(rect);
+                }
+            }
+        }
+
+        routes
+    }
+
+    /// Прокладывает одно ребро
+    pub fn route(&self, edge: &EdgeSpec) -> Route {
+        self.route_all(std::slice::from_ref(edge)).remove(0)
+    }
+
+    /// Размер подписи шрифтом рендерера
+    fn label_size(&self, text: &str) -> Size {
+        let lines = text_lines(text);
+        let width = lines
+            .iter()
+            .map(|line| self.text.width_at(line, LABEL_FONT_SIZE))
+            .fold(0.0, f64::max);
+        Size::new(width, lines.len() as f64 * LABEL_LINE_HEIGHT)
+    }
+
+    // =========================================================================
+    // Концы рёбер
+    // =========================================================================
+
+    /// Выбирает стороны и точки концов рёбер (None — петля)
+    ///
+    /// Концы на одной стороне узла упорядочиваются по положению другого
+    /// конца, чтобы линии не перекрещивались у узла, и разводятся по длине
+    /// стороны.
+    #[allow(clippy::type_complexity)]
+    fn assign_ports(&self, edges: &[EdgeSpec]) -> Vec<Option<(Point, Side, Point, Side)>> {
+        let sides: Vec<(Side, Side)> = edges
+            .iter()
+            .map(|edge| {
+                (
+                    edge.from_side.unwrap_or_else(|| Side::facing(&edge.from, &edge.to)),
+                    edge.to_side.unwrap_or_else(|| Side::facing(&edge.to, &edge.from)),
+                )
+            })
+            .collect();
+
+        // (узел, сторона) → концы рёбер: (индекс ребра, начало?, ключ порядка)
+        let mut groups: HashMap<(RectKey, Side), Vec<(usize, bool, f64)>> = HashMap::new();
+        for (index, (edge, &(from_side, to_side))) in edges.iter().zip(&sides).enumerate() {
+            if edge.is_loop() {
+                continue;
+            }
+            let order = |side: Side, other: &Rect| {
+                let center = other.center();
+                if side.runs_along_x() {
+                    center.x
+                } else {
+                    center.y
+                }
+            };
+            groups
+                .entry((RectKey::new(&edge.from), from_side))
+                .or_default()
+                .push((index, true, order(from_side, &edge.to)));
+            groups
+                .entry((RectKey::new(&edge.to), to_side))
+                .or_default()
+                .push((index, false, order(to_side, &edge.from)));
+        }
+
+        let mut offsets: HashMap<(usize, bool), f64> = HashMap::new();
+        for ((_, side), mut ends) in groups {
+            ends.sort_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(Ordering::Equal).then(a.0.cmp(&b.0)));
+            let (index, is_start, _) = ends[0];
+            let edge = &edges[index];
+            let rect = if is_start { &edge.from } else { &edge.to };
+            let length = if side.runs_along_x() { rect.width } else { rect.height };
+            let step = (length / (ends.len() + 1) as f64).min(self.port_spacing);
+            let middle = (ends.len() - 1) as f64 / 2.0;
+            for (position, &(index, is_start, _)) in ends.iter().enumerate() {
+                offsets.insert((index, is_start), (position as f64 - middle) * step);
+            }
+        }
+
+        edges
+            .iter()
+            .zip(sides)
+            .enumerate()
+            .map(|(index, (edge, (from_side, to_side)))| {
+                if edge.is_loop() {
+                    return None;
+                }
+                let start = from_side.port(&edge.from, offsets[&(index, true)]);
+                let end = to_side.port(&edge.to, offsets[&(index, false)]);
+                Some((start, from_side, end, to_side))
+            })
+            .collect()
+    }
+
+    /// Петля справа от узла
+    fn loop_path(&self, rect: &Rect) -> Vec<Point> {
+        let center = rect.center();
+        let right = rect.x + rect.width;
+        let spread = (rect.height / 4.0).min(self.port_spacing);
+        let out = right + self.clearance * 2.0;
+        vec![
+            Point::new(right, center.y - spread),
+            Point::new(out, center.y - spread),
+            Point::new(out, center.y + spread),
+            Point::new(right, center.y + spread),
+        ]
+    }
+
+    // =========================================================================
+    // Пути
+    // =========================================================================
+
+    /// Путь ребра в выбранной форме
+    fn route_path(&self, edge: &EdgeSpec, start: Point, start_side: Side, end: Point, end_side: Side) -> Vec<Point> {
+        // Рамки, содержащие конец ребра, препятствиями не считаются
+        let encloses = |rect: &Rect| contains(rect, &edge.from) || contains(rect, &edge.to);
+        let others: Vec<Rect> = self
+            .obstacles
+            .iter()
+            .chain(&self.frames)
+            .filter(|rect| !encloses(rect))
+            .copied()
+            .collect();
+
+        let blocks: Vec<Rect> = others
+            .iter()
+            .chain([&edge.from, &edge.to])
+            .map(|rect| inflate(rect, self.clearance))
+            .collect();
+        let ortho = self
+            .ortho_path(start, start_side, end, end_side, &blocks)
+            .unwrap_or_else(|| Self::elbow_path(start, start_side, end));
+
+        match self.line_type {
+            LineType::Ortho => ortho,
+            LineType::Polyline | LineType::Spline => {
+                // Спрямление: концы не заходят внутрь своих узлов,
+                // чужие узлы обходятся с половинным отступом
+                let sight: Vec<Rect> = others
+                    .iter()
+                    .map(|rect| inflate(rect, self.clearance / 2.0))
+                    .chain([inflate(&edge.from, -0.5), inflate(&edge.to, -0.5)])
+                    .collect();
+                Self::shortcut(&ortho, &sight)
+            }
+        }
+    }
+
+    /// Запасной путь с одним или двумя изгибами без учёта препятствий
+    fn elbow_path(start: Point, start_side: Side, end: Point) -> Vec<Point> {
+        if (start.x - end.x).abs() < 1.0 || (start.y - end.y).abs() < 1.0 {
+            return vec![start, end];
+        }
+        if start_side.runs_along_x() {
+            let mid_y = (start.y + end.y) / 2.0;
+            vec![start, Point::new(start.x, mid_y), Point::new(end.x, mid_y), end]
+        } else {
+            let mid_x = (start.x + end.x) / 2.0;
+            vec![start, Point::new(mid_x, start.y), Point::new(mid_x, end.y), end]
+        }
+    }
+
+    /// Ортогональный путь в обход препятствий
+    ///
+    /// Вершины сетки — пересечения границ препятствий и координат концов;
+    /// стоимость — длина плюс штраф за каждый изгиб.
+    fn ortho_path(&self, start: Point, start_side: Side, end: Point, end_side: Side, blocks: &[Rect]) -> Option<Vec<Point>> {
+        let stub = |point: Point, side: Side| {
+            let (nx, ny) = side.normal();
+            Point::new(point.x + nx * self.clearance, point.y + ny * self.clearance)
+        };
+        let (source, target) = (stub(start, start_side), stub(end, end_side));
+        let middle = Point::new((source.x + target.x) / 2.0, (source.y + target.y) / 2.0);
+
+        let mut xs = vec![source.x, target.x, middle.x];
+        let mut ys = vec![source.y, target.y, middle.y];
+        for rect in blocks {
+            xs.extend([rect.x, rect.x + rect.width]);
+            ys.extend([rect.y, rect.y + rect.height]);
+        }
+        let xs = sorted_coordinates(xs);
+        let ys = sorted_coordinates(ys);
+        let find = |values: &[f64], value: f64| values.iter().position(|v| (v - value).abs() < 1e-6);
+        let (si, sj) = (find(&xs, source.x)?, find(&ys, source.y)?);
+        let (ti, tj) = (find(&xs, target.x)?, find(&ys, target.y)?);
+
+        let (width, height) = (xs.len(), ys.len());
+        let state = |i: usize, j: usize, heading: Heading| (j * width + i) * 4 + heading as usize;
+        let mut cost = vec![f64::INFINITY; width * height * 4];
+        let mut previous = vec![usize::MAX; width * height * 4];
+        let mut queue = BinaryHeap::new();
+
+        let first = state(si, sj, start_side.outward());
+        cost[first] = 0.0;
+        queue.push(QueueEntry { cost: 0.0, state: first });
+
+        // Вход в конечный узел — движение против нормали его стороны
+        let arrival = end_side.outward().reverse();
+        let mut best: Option<(f64, usize)> = None;
+
+        while let Some(QueueEntry { cost: current, state: from }) = queue.pop() {
+            if current > cost[from] {
+                continue;
+            }
+            if best.is_some_and(|(best_cost, _)| current >= best_cost) {
+                break;
+            }
+            let heading = Heading::ALL[from % 4];
+            let (i, j) = ((from / 4) % width, (from / 4) / width);
+
+            if (i, j) == (ti, tj) {
+                let turns = match heading {
+                    h if h == arrival => 0.0,
+                    h if h == arrival.reverse() => 2.0,
+                    _ => 1.0,
+                };
+                let total = current + turns * (self.bend_penalty + self.bend_tiebreak(target, middle));
+                if best.map_or(true, |(best_cost, _)| total < best_cost) {
+                    best = Some((total, from));
+                }
+                continue;
+            }
+
+            for next_heading in Heading::ALL {
+                if next_heading == heading.reverse() {
+                    continue;
+                }
+                let (di, dj) = next_heading.delta();
+                let (ni, nj) = (i as isize + di, j as isize + dj);
+                if ni < 0 || nj < 0 || ni as usize >= width || nj as usize >= height {
+                    continue;
+                }
+                let (ni, nj) = (ni as usize, nj as usize);
+                let (a, b) = (Point::new(xs[i], ys[j]), Point::new(xs[ni], ys[nj]));
+                let mid = Point::new((a.x + b.x) / 2.0, (a.y + b.y) / 2.0);
+                if blocks.iter().any(|rect| strictly_inside(mid, rect)) {
+                    continue;
+                }
+
+                let mut step = (b.x - a.x).abs() + (b.y - a.y).abs();
+                if next_heading != heading {
+                    step += self.bend_penalty + self.bend_tiebreak(a, middle);
+                }
+                let next = state(ni, nj, next_heading);
+                if current + step < cost[next] {
+                    cost[next] = current + step;
+                    previous[next] = from;
+                    queue.push(QueueEntry { cost: current + step, state: next });
+                }
+            }
+        }
+
+        let (_, mut current) = best?;
+        let mut grid_points = Vec::new();
+        loop {
+            let cell = current / 4;
+            grid_points.push(Point::new(xs[cell % width], ys[cell / width]));
+            if current == first {
+                break;
+            }
+            current = previous[current];
+        }
+        grid_points.reverse();
+
+        let mut points = vec![start];
+        points.extend(grid_points);
+        points.push(end);
+        Some(simplify(points))
+    }
+
+    /// Малая добавка к штрафу изгиба: из равных путей выигрывает тот, чьи
+    /// изгибы ближе к середине между концами
+    fn bend_tiebreak(&self, bend: Point, middle: Point) -> f64 {
+        0.001 * ((bend.x - middle.x).abs() + (bend.y - middle.y).abs())
+    }
+
+    /// Спрямляет путь: от каждой точки — к самой дальней видимой
+    fn shortcut(points: &[Point], sight: &[Rect]) -> Vec<Point> {
+        let mut result = vec![points[0]];
+        let mut current = 0;
+        while current + 1 < points.len() {
+            let next = (current + 1..points.len())
+                .rev()
+                .find(|&candidate| {
+                    let (a, b) = (points[current], points[candidate]);
+                    !sight.iter().any(|rect| segment_hits_rect(a, b, rect))
+                })
+                .unwrap_or(current + 1);
+            result.push(points[next]);
+            current = next;
+        }
+        result
+    }
+
+    // =========================================================================
+    // Подписи
+    // =========================================================================
+
+    /// Места для подписи: у середин отрезков пути, начиная с длинных и
+    /// центральных, по обе стороны линии
+    fn label_candidates(points: &[Point], size: Size) -> Vec<Rect> {
+        let gap = 4.0;
+        let count = points.len().saturating_sub(1);
+        let mut segments: Vec<usize> = (0..count).collect();
+        let length = |i: usize| (points[i + 1].x - points[i].x).hypot(points[i + 1].y - points[i].y);
+        let centrality = |i: usize| (i as f64 - (count as f64 - 1.0) / 2.0).abs();
+        segments.sort_by(|&a, &b| {
+            length(b)
+                .partial_cmp(&length(a))
+                .unwrap_or(Ordering::Equal)
+                .then(centrality(a).partial_cmp(&centrality(b)).unwrap_or(Ordering::Equal))
+        });
+
+        let mut candidates = Vec::new();
+        for i in segments {
+            let (a, b) = (points[i], points[i + 1]);
+            for t in [0.5, 0.3, 0.7] {
+                let p = Point::new(a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t);
+                if (b.y - a.y).abs() >= (b.x - a.x).abs() {
+                    // Вертикальная или крутая линия: справа, затем слева
+                    candidates.push(Rect::new(p.x + gap, p.y - size.height / 2.0, size.width, size.height));
+                    candidates.push(Rect::new(p.x - gap - size.width, p.y - size.height / 2.0, size.width, size.height));
+                } else {
+                    // Горизонтальная или пологая линия: над, затем под
+                    candidates.push(Rect::new(p.x - size.width / 2.0, p.y - gap - size.height, size.width, size.height));
+                    candidates.push(Rect::new(p.x - size.width / 2.0, p.y + gap, size.width, size.height));
+                }
+            }
+        }
+        candidates
+    }
+
+    /// Места для кардинальности у конца ребра: по обе стороны от линии
+    /// сразу за точкой соединения
+    fn end_candidates(port: Point, next: Point, size: Size) -> Vec<Rect> {
+        let gap = 4.0;
+        let (dx, dy) = (next.x - port.x, next.y - port.y);
+        if dy.abs() >= dx.abs() {
+            let y = if dy >= 0.0 { port.y + gap } else { port.y - gap - size.height };
+            vec![
+                Rect::new(port.x - gap - size.width, y, size.width, size.height),
+                Rect::new(port.x + gap, y, size.width, size.height),
+            ]
+        } else {
+            let x = if dx >= 0.0 { port.x + gap } else { port.x - gap - size.width };
+            vec![
+                Rect::new(x, port.y - gap - size.height, size.width, size.height),
+                Rect::new(x, port.y + gap, size.width, size.height),
+            ]
+        }
+    }
+}
+
+/// Направление движения по сетке
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Heading {
+    Right = 0,
+    Left = 1,
+    Down = 2,
+    Up = 3,
+}
+
+impl Heading {
+    const ALL: [Heading; 4] = [Heading::Right, Heading::Left, Heading::Down, Heading::Up];
+
+    fn reverse(self) -> Self {
+        match self {
+            Self::Right => Self::Left,
+            Self::Left => Self::Right,
+            Self::Down => Self::Up,
+            Self::Up => Self::Down,
+        }
+    }
+
+    /// Шаг по индексам сетки
+    fn delta(self) -> (isize, isize) {
+        match self {
+            Self::Right => (1, 0),
+            Self::Left => (-1, 0),
+            Self::Down => (0, 1),
+            Self::Up => (0, -1),
+        }
+    }
+}
+
+/// Элемент очереди Дейкстры (минимальная стоимость — первой)
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct QueueEntry {
+    cost: f64,
+    state: usize,
+}
+
+impl Eq for QueueEntry {}
+
+impl Ord for QueueEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .partial_cmp(&self.cost)
+            .unwrap_or(Ordering::Equal)
+            .then(other.state.cmp(&self.state))
+    }
+}
+
+impl PartialOrd for QueueEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Ключ прямоугольника узла для группировки концов рёбер
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct RectKey([u64; 4]);
+
+impl RectKey {
+    fn new(rect: &Rect) -> Self {
+        Self([rect.x.to_bits(), rect.y.to_bits(), rect.width.to_bits(), rect.height.to_bits()])
+    }
+}
+
+/// Отсортированные координаты без повторов
+fn sorted_coordinates(mut values: Vec<f64>) -> Vec<f64> {
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    values.dedup_by(|a, b| (*a - *b).abs() < 1e-6);
+    values
+}
+
+/// Убирает повторяющиеся точки и промежуточные точки на одной прямой
+fn simplify(points: Vec<Point>) -> Vec<Point> {
+    let mut result: Vec<Point> = Vec::with_capacity(points.len());
+    for point in points {
+        if result.last().is_some_and(|last| (last.x - point.x).abs() < 1e-6 && (last.y - point.y).abs() < 1e-6) {
+            continue;
+        }
+        if result.len() >= 2 {
+            let (a, b) = (result[result.len() - 2], result[result.len() - 1]);
+            let cross = (b.x - a.x) * (point.y - b.y) - (b.y - a.y) * (point.x - b.x);
+            if cross.abs() < 1e-6 {
+                result.pop();
+            }
+        }
+        result.push(point);
+    }
+    result
+}
+
+/// Прямоугольник, расширенный на `margin` во все стороны
+fn inflate(rect: &Rect, margin: f64) -> Rect {
+    Rect::new(rect.x - margin, rect.y - margin, rect.width + margin * 2.0, rect.height + margin * 2.0)
+}
+
+/// Точка строго внутри прямоугольника
+fn strictly_inside(point: Point, rect: &Rect) -> bool {
+    point.x > rect.x && point.x < rect.x + rect.width && point.y > rect.y && point.y < rect.y + rect.height
+}
+
+/// `outer` целиком содержит `inner`
+fn contains(outer: &Rect, inner: &Rect) -> bool {
+    inner.x >= outer.x
+        && inner.y >= outer.y
+        && inner.x + inner.width <= outer.x + outer.width
+        && inner.y + inner.height <= outer.y + outer.height
+}
+
+/// Прямоугольники пересекаются внутренностями
+fn overlaps(a: &Rect, b: &Rect) -> bool {
+    a.x < b.x + b.width && b.x < a.x + a.width && a.y < b.y + b.height && b.y < a.y + a.height
+}
+
+/// Отрезок проходит через внутренность прямоугольника (отсечение
+/// Лианга — Барски)
+pub fn segment_hits_rect(a: Point, b: Point, rect: &Rect) -> bool {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let (mut t0, mut t1) = (0.0_f64, 1.0_f64);
+    let edges = [
+        (-dx, a.x - rect.x),
+        (dx, rect.x + rect.width - a.x),
+        (-dy, a.y - rect.y),
+        (dy, rect.y + rect.height - a.y),
+    ];
+    for (p, q) in edges {
+        if p == 0.0 {
+            // Параллельно границе: снаружи или на ней — не пересекает
+            if q <= 0.0 {
+                return false;
+            }
+        } else {
+            let t = q / p;
+            if p < 0.0 {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+        }
+    }
+    t1 - t0 > 1e-9
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn router(line_type: LineType) -> Router {
+        Router::new(line_type, TextMetrics::default())
+    }
+
+    fn crosses_any(points: &[Point], rects: &[Rect]) -> bool {
+        points
+            .windows(2)
+            .any(|w| rects.iter().any(|rect| segment_hits_rect(w[0], w[1], rect)))
+    }
+
+    #[test]
+    fn test_ortho_route_avoids_obstacle() {
+        let from = Rect::new(0.0, 0.0, 100.0, 40.0);
+        let to = Rect::new(0.0, 200.0, 100.0, 40.0);
+        let blocker = Rect::new(-20.0, 100.0, 140.0, 40.0);
+
+        let route = router(LineType::Ortho)
+            .with_obstacles([from, to, blocker])
+            .route(&EdgeSpec::new(from, to));
+
+        assert!(!crosses_any(&route.points, &[blocker]));
+        assert_eq!(route.points[0].y, 40.0);
+        assert_eq!(route.points.last().unwrap().y, 200.0);
+        assert!(route
+            .points
+            .windows(2)
+            .all(|w| w[0].x == w[1].x || w[0].y == w[1].y));
+    }
+
+    #[test]
+    fn test_polyline_is_straight_when_clear() {
+        let from = Rect::new(0.0, 0.0, 100.0, 40.0);
+        let to = Rect::new(150.0, 200.0, 100.0, 40.0);
+
+        let route = router(LineType::Polyline)
+            .with_obstacles([from, to])
+            .route(&EdgeSpec::new(from, to));
+        assert_eq!(route.points, vec![Point::new(50.0, 40.0), Point::new(200.0, 200.0)]);
+
+        let spline = router(LineType::Spline).route(&EdgeSpec::new(from, to));
+        assert!(spline.spline);
+    }
+
+    #[test]
+    fn test_shared_side_ports_are_spread() {
+        let parent = Rect::new(100.0, 0.0, 100.0, 40.0);
+        let left = Rect::new(0.0, 200.0, 100.0, 40.0);
+        let right = Rect::new(200.0, 200.0, 100.0, 40.0);
+
+        let routes = router(LineType::Ortho)
+            .with_obstacles([parent, left, right])
+            .route_all(&[EdgeSpec::new(parent, right), EdgeSpec::new(parent, left)]);
+
+        // Концы на нижней стороне разведены и не пересекаются
+        let (to_right, to_left) = (routes[0].points[0], routes[1].points[0]);
+        assert_eq!(to_right.y, 40.0);
+        assert_eq!(to_left.y, 40.0);
+        assert!(to_left.x < to_right.x);
+    }
+
+    #[test]
+    fn test_labels_do_not_collide() {
+        let from = Rect::new(0.0, 0.0, 100.0, 40.0);
+        let to = Rect::new(0.0, 200.0, 100.0, 40.0);
+        let edges = [
+            EdgeSpec::new(from, to).with_label(Some("first")).with_cardinalities(Some("1"), Some("*")),
+            EdgeSpec::new(from, to).with_label(Some("second")),
+        ];
+
+        let routes = router(LineType::Ortho).with_obstacles([from, to]).route_all(&edges);
+        let labels: Vec<Rect> = routes
+            .iter()
+            .flat_map(|route| [route.label, route.from_label, route.to_label])
+            .flatten()
+            .collect();
+        assert_eq!(labels.len(), 4);
+        for (i, a) in labels.iter().enumerate() {
+            assert!(!overlaps(a, &from) && !overlaps(a, &to));
+            assert!(labels[i + 1..].iter().all(|b| !overlaps(a, b)));
+        }
+
+        let mut element = LayoutElement::new(
+            "edge",
+            Rect::new(0.0, 0.0, 0.0, 0.0),
+            ElementType::Edge {
+                points: Vec::new(),
+                label: Some("first".to_string()),
+                arrow_start: false,
+                arrow_end: true,
+                dashed: false,
+                edge_type: crate::EdgeType::Association,
+                from_cardinality: None,
+                to_cardinality: None,
+            },
+        );
+        routes[0].apply(&mut element);
+        let position = parse_position(&element.properties[PROPERTY_LABEL_POSITION]).unwrap();
+        assert_eq!(position.x, routes[0].label.unwrap().x);
+    }
+
+    #[test]
+    fn test_self_loop() {
+        let rect = Rect::new(0.0, 0.0, 100.0, 40.0);
+        let route = router(LineType::Ortho).route(&EdgeSpec::new(rect, rect));
+        assert_eq!(route.points.len(), 4);
+        assert!(route.points.iter().all(|p| p.x >= 100.0));
+    }
+}
@@ -1,5 +1,7 @@
 //! Конфигурация layout для Salt диаграмм
 
+use crate::text::TextMetrics;
+
 /// Конфигурация для Salt layout engine
 #[derive(Debug, Clone)]
 pub struct SaltLayoutConfig {
@@ -29,6 +31,8 @@ pub struct SaltLayoutConfig {
     pub button_color: &'static str,
     /// Цвет текстового поля
     pub textfield_color: &'static str,
+    /// Метрики текста (шрифт темы)
+    pub text: TextMetrics,
 }
 
 impl Default for SaltLayoutConfig {
@@ -47,6 +51,7 @@ impl Default for SaltLayoutConfig {
             border_color: "#888888",
             button_color: "#E0E0E0",
             textfield_color: "#FFFFFF",
+            text: TextMetrics::default(),
         }
     }
 }
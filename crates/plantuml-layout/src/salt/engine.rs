@@ -6,6 +6,7 @@ use plantuml_ast::salt::{BorderStyle, Container, SaltDiagram, SaltWidget, Separa
 use plantuml_model::{Point, Rect};
 
 use crate::salt::config::SaltLayoutConfig;
+use crate::text::TextMetrics;
 use crate::traits::{LayoutEngine, LayoutResult};
 use crate::{EdgeType, ElementType, LayoutConfig, LayoutElement};
 
@@ -32,6 +33,17 @@ impl SaltLayoutEngine {
         }
     }
 
+    /// Устанавливает метрики текста (шрифт темы)
+    pub fn with_text_metrics(mut self, text: TextMetrics) -> Self {
+        self.config.text = text;
+        self
+    }
+
+    /// Ширина текста виджета
+    fn text_width(&self, text: &str) -> f64 {
+        self.config.text.width_at(text, self.config.font_size)
+    }
+
     /// Генерирует уникальный ID элемента
     fn next_id(&mut self, prefix: &str) -> String {
         self.element_id += 1;
@@ -188,7 +200,7 @@ impl SaltLayoutEngine {
 
     /// Рендерит текст
     fn render_text(&mut self, text: &str, x: f64, y: f64, elements: &mut Vec<LayoutElement>) -> (f64, f64) {
-        let width = text.len() as f64 * 8.0 + self.config.cell_padding;
+        let width = self.text_width(text) + self.config.cell_padding;
         let height = self.config.row_height;
 
         let text_elem = LayoutElement {
@@ -216,7 +228,7 @@ impl SaltLayoutEngine {
         y: f64,
         elements: &mut Vec<LayoutElement>,
     ) -> (f64, f64) {
-        let width = label.len() as f64 * 8.0 + self.config.cell_padding * 2.0;
+        let width = self.text_width(label) + self.config.cell_padding * 2.0;
         let width = width.max(self.config.min_cell_width);
         let height = self.config.button_height;
 
@@ -267,7 +279,7 @@ impl SaltLayoutEngine {
         y: f64,
         elements: &mut Vec<LayoutElement>,
     ) -> (f64, f64) {
-        let width = text.len() as f64 * 8.0 + self.config.cell_padding * 2.0;
+        let width = self.text_width(text) + self.config.cell_padding * 2.0;
         let width = width.max(self.config.min_cell_width);
         let height = self.config.textfield_height;
 
@@ -368,7 +380,7 @@ impl SaltLayoutEngine {
                     .collect(),
             };
             elements.push(label_elem);
-            label.len() as f64 * 8.0
+            self.text_width(label)
         } else {
             0.0
         };
@@ -431,7 +443,7 @@ impl SaltLayoutEngine {
                     .collect(),
             };
             elements.push(label_elem);
-            label.len() as f64 * 8.0
+            self.text_width(label)
         } else {
             0.0
         };
@@ -449,7 +461,7 @@ impl SaltLayoutEngine {
         elements: &mut Vec<LayoutElement>,
     ) -> (f64, f64) {
         let text = items.first().map(|s| s.as_str()).unwrap_or("Select...");
-        let width = text.len() as f64 * 8.0 + 30.0;
+        let width = self.text_width(text) + 30.0;
         let height = self.config.textfield_height;
 
         // Фон
@@ -600,7 +612,7 @@ impl SaltLayoutEngine {
             if !node.text.is_empty() {
                 let prefix = if node.level > 0 { "├─ " } else { "" };
                 let text = format!("{}{}", prefix, node.text);
-                let width = text.len() as f64 * 8.0 + indent;
+                let width = engine.text_width(&text) + indent;
 
                 let text_elem = LayoutElement {
                     id: engine.next_id("tree_node"),
@@ -643,7 +655,7 @@ impl SaltLayoutEngine {
         let tab_height = 25.0;
 
         for (i, item) in items.iter().enumerate() {
-            let width = item.len() as f64 * 8.0 + 20.0;
+            let width = self.text_width(item) + 20.0;
             let is_selected = i == selected;
 
             // Фон вкладки
@@ -705,7 +717,7 @@ impl SaltLayoutEngine {
         let menu_height = 22.0;
 
         // Фон меню
-        let total_width = items.iter().map(|i| i.text.len() as f64 * 8.0 + 20.0).sum::<f64>();
+        let total_width = items.iter().map(|i| self.text_width(&i.text) + 20.0).sum::<f64>();
         let bg = LayoutElement {
             id: self.next_id("menu_bg"),
             element_type: ElementType::Rectangle {
@@ -728,7 +740,7 @@ impl SaltLayoutEngine {
                 continue;
             }
 
-            let width = item.text.len() as f64 * 8.0 + 20.0;
+            let width = self.text_width(&item.text) + 20.0;
 
             let text = LayoutElement {
                 id: self.next_id("menu_item"),
@@ -1,5 +1,7 @@
 //! Конфигурация layout для Sequence Diagrams
 
+use crate::text::TextMetrics;
+
 /// Конфигурация layout sequence diagram
 #[derive(Debug, Clone)]
 pub struct SequenceLayoutConfig {
@@ -29,12 +31,22 @@ pub struct SequenceLayoutConfig {
     pub margin: f64,
     /// Размер шрифта
     pub font_size: f64,
-    /// Примерная ширина символа (для расчёта ширины текста)
-    pub char_width: f64,
     /// Высота строки текста
     pub line_height: f64,
     /// Высота заголовка бокса (participant box)
     pub box_title_height: f64,
+    /// Метрики текста (шрифт темы)
+    pub text: TextMetrics,
+    /// Не рисовать нижние блоки участников (`hide footbox`)
+    pub hide_footbox: bool,
+    /// Не рисовать участников без сообщений (`hide unlinked`)
+    pub hide_unlinked: bool,
+    /// Ширина, после которой подписи сообщений переносятся (`maxMessageSize`)
+    pub max_message_size: Option<f64>,
+    /// Подпись ответов под стрелкой (`responseMessageBelowArrow`)
+    pub response_message_below_arrow: bool,
+    /// Сплошные линии жизни (`lifelineStrategy solid`)
+    pub solid_lifelines: bool,
 }
 
 impl Default for SequenceLayoutConfig {
@@ -54,9 +66,14 @@ impl Default for SequenceLayoutConfig {
             note_width: 100.0,            // уменьшено (было 120)
             margin: 15.0,                 // уменьшено (было 20)
             font_size: 13.0,
-            char_width: 7.0,
             line_height: 16.0, // уменьшено (было 18)
             box_title_height: 30.0, // высота для заголовка бокса (отступ от верха box до участников)
+            text: TextMetrics::default(),
+            hide_footbox: false,
+            hide_unlinked: false,
+            max_message_size: None,
+            response_message_below_arrow: false,
+            solid_lifelines: false,
         }
     }
 }
@@ -67,28 +84,55 @@ impl SequenceLayoutConfig {
         Self::default()
     }
 
-    /// Вычисляет примерную ширину текста (с учётом Unicode)
+    /// Вычисляет ширину текста сообщения по метрикам шрифта
     pub fn text_width(&self, text: &str) -> f64 {
-        // Считаем символы, а не байты (для корректной работы с кириллицей)
-        let char_count = text.chars().count();
-        char_count as f64 * self.char_width
+        self.text.width_at(text, self.font_size)
     }
 
     /// Вычисляет ширину участника с учётом имени
     pub fn participant_width_for_name(&self, name: &str) -> f64 {
-        let text_width = self.text_width(name) + 20.0; // padding
+        // Участники рисуются базовым шрифтом темы
+        let text_width = self.text.width(name) + 20.0; // padding
         self.participant_width.max(text_width)
     }
 
+    /// Переносит подпись по словам, чтобы строки не превышали `max_message_size`
+    ///
+    /// Слово длиннее ограничения остаётся на отдельной строке целиком.
+    pub fn wrap_label(&self, label: &str) -> String {
+        let Some(max_width) = self.max_message_size else {
+            return label.to_string();
+        };
+        // Явные переносы `\\n` сохраняются
+        label
+            .replace("\\n", "\n")
+            .split('\n')
+            .map(|line| {
+                let mut lines: Vec<String> = Vec::new();
+                let mut current = String::new();
+                for word in line.split_whitespace() {
+                    let candidate = if current.is_empty() {
+                        word.to_string()
+                    } else {
+                        format!("{} {}", current, word)
+                    };
+                    if !current.is_empty() && self.text_width(&candidate) > max_width {
+                        lines.push(std::mem::replace(&mut current, word.to_string()));
+                    } else {
+                        current = candidate;
+                    }
+                }
+                lines.push(current);
+                lines.join("\n")
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
     /// Вычисляет ширину текста сообщения с отступами
     /// Для многострочного текста возвращает ширину самой длинной строки
     pub fn message_label_width(&self, label: &str) -> f64 {
-        // Разбиваем на строки (учитываем и \n и \\n)
-        let processed = label.replace("\\n", "\n");
-        let max_line_width = processed
-            .split('\n')
-            .map(|line| self.text_width(line))
-            .fold(0.0_f64, f64::max);
-        max_line_width + 16.0 // padding с обеих сторон
+        // Измеритель сам делит текст по \n и \\n
+        self.text_width(label) + 16.0 // padding с обеих сторон
     }
 }
@@ -2,16 +2,47 @@
 //!
 //! Реализация алгоритма размещения элементов sequence diagram.
 
-use plantuml_ast::common::{LineStyle, Note, NotePosition};
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+use plantuml_ast::common::{LineStyle, Link, Note, NotePosition, Span, Stereotype};
 use plantuml_ast::sequence::{
-    Activation, ActivationType, AutonumberCommand, Delay, Divider, Fragment, FragmentType, 
-    Message, ParticipantType, Return, SequenceDiagram, SequenceElement,
+    Activation, ActivationType, ArrowType, AutonumberCommand, Delay, Divider, Duration,
+    EndpointDecoration, EndpointSide, Fragment, FragmentType, Message, MessageEndpoint, ParticipantType, Return,
+    SequenceDiagram, SequenceElement,
 };
 use plantuml_model::{Point, Rect};
 
 use super::config::SequenceLayoutConfig;
 use super::metrics::{DiagramMetrics, ParticipantMetrics};
-use crate::{EdgeType, ElementType, FragmentSection, LayoutConfig, LayoutElement, LayoutResult};
+use crate::text::TextMetrics;
+use crate::{
+    EdgeType, ElementStyle, ElementType, FragmentSection, LayoutConfig, LayoutElement, LayoutResult,
+    PROPERTY_END_DECORATION, PROPERTY_LABEL_POSITION, PROPERTY_START_DECORATION,
+};
+
+/// Расстояние от левого края первого участника до стрелки длительности
+const DURATION_OFFSET: f64 = 10.0;
+
+/// Переносит подписи сообщений и ответов по `maxMessageSize`
+fn wrap_labels(elements: &mut [SequenceElement], config: &SequenceLayoutConfig) {
+    for element in elements {
+        match element {
+            SequenceElement::Message(msg) => msg.label = config.wrap_label(&msg.label),
+            SequenceElement::Return(ret) => {
+                if let Some(label) = &mut ret.label {
+                    *label = config.wrap_label(label);
+                }
+            }
+            SequenceElement::Fragment(fragment) => {
+                for section in &mut fragment.sections {
+                    wrap_labels(&mut section.elements, config);
+                }
+            }
+            _ => {}
+        }
+    }
+}
 
 /// Layout engine для sequence diagrams
 pub struct SequenceLayoutEngine {
@@ -31,8 +62,65 @@ impl SequenceLayoutEngine {
         Self { config }
     }
 
+    /// Устанавливает метрики текста (шрифт темы)
+    pub fn with_text_metrics(mut self, text: TextMetrics) -> Self {
+        self.config.text = text;
+        self
+    }
+
     /// Выполняет layout диаграммы
     pub fn layout(&self, diagram: &SequenceDiagram) -> LayoutResult {
+        // Директивы диаграммы дополняют конфигурацию
+        let display = &diagram.display;
+        let mut config = self.config.clone();
+        config.hide_footbox |= display.hide_footbox;
+        config.hide_unlinked |= display.hide_unlinked;
+        config.max_message_size = display.max_message_size.or(config.max_message_size);
+        config.response_message_below_arrow |= display.response_message_below_arrow;
+        config.solid_lifelines |= display.solid_lifelines;
+
+        // Спрайты в именах участников делают заголовки выше
+        let tallest = diagram
+            .participants
+            .iter()
+            .map(|p| config.text.size_at(&p.id.name, config.text.font_size).height + 10.0)
+            .fold(0.0, f64::max);
+        config.participant_height = config.participant_height.max(tallest);
+
+        let engine = Self::with_config(config);
+        let diagram = engine.prepare(diagram);
+        engine.layout_sized(&diagram)
+    }
+
+    /// Применяет `hide unlinked` и перенос подписей по `maxMessageSize`
+    fn prepare<'a>(&self, diagram: &'a SequenceDiagram) -> Cow<'a, SequenceDiagram> {
+        if !self.config.hide_unlinked && self.config.max_message_size.is_none() {
+            return Cow::Borrowed(diagram);
+        }
+
+        let mut diagram = diagram.clone();
+        if self.config.hide_unlinked {
+            let linked: HashSet<String> = diagram
+                .messages()
+                .iter()
+                .flat_map(|msg| msg.participants())
+                .map(str::to_string)
+                .collect();
+            diagram.participants.retain(|p| {
+                linked.contains(p.id.alias.as_ref().unwrap_or(&p.id.name))
+            });
+            for pbox in &mut diagram.boxes {
+                pbox.participants.retain(|id| linked.contains(id));
+            }
+        }
+        if self.config.max_message_size.is_some() {
+            wrap_labels(&mut diagram.elements, &self.config);
+        }
+        Cow::Owned(diagram)
+    }
+
+    /// Выполняет layout с уже подобранной высотой участников
+    fn layout_sized(&self, diagram: &SequenceDiagram) -> LayoutResult {
         let mut metrics = DiagramMetrics::new();
         let mut elements = Vec::new();
 
@@ -68,11 +156,15 @@ impl SequenceLayoutEngine {
         self.add_activations(&metrics, &mut elements);
 
         // 7. Добавляем нижние блоки участников (footers) - как в PlantUML
-        self.add_participant_footers(&metrics, &mut elements);
-
-        // 8. Вычисляем финальную высоту диаграммы (footer_y + footer_height + margin)
         let footer_y = metrics.current_y - 11.0;
-        let total_height = footer_y + self.config.participant_height + self.config.margin;
+        let total_height = if self.config.hide_footbox {
+            // `hide footbox`: диаграмма заканчивается концом lifelines
+            footer_y + self.config.margin
+        } else {
+            self.add_participant_footers(&metrics, &mut elements);
+            // 8. Вычисляем финальную высоту диаграммы (footer_y + footer_height + margin)
+            footer_y + self.config.participant_height + self.config.margin
+        };
 
         // 9. Обновляем высоту box элементов
         let box_elements: Vec<LayoutElement> = box_elements
@@ -142,11 +234,9 @@ impl SequenceLayoutEngine {
     ) {
         match element {
             SequenceElement::Message(msg) => {
-                let is_self_message = msg.from == msg.to;
-
-                if is_self_message {
+                if msg.is_self_message() {
                     // Self-message: текст справа от петли
-                    if let Some(pm) = metrics.participants.get(&msg.from) {
+                    if let Some(pm) = msg.from.participant().and_then(|name| metrics.participants.get(name)) {
                         let loop_width = 40.0;
                         let text_offset = 5.0; // отступ от петли до текста
                         let text_width = self.config.message_label_width(&msg.label);
@@ -156,8 +246,7 @@ impl SequenceLayoutEngine {
                 } else {
                     // Обычное сообщение: текст слева от стрелки (над ней)
                     // Проверяем, не выходит ли текст за левого участника
-                    let from_x = metrics.lifeline_x(&msg.from, &self.config);
-                    let to_x = metrics.lifeline_x(&msg.to, &self.config);
+                    let (from_x, to_x) = self.message_x(msg, metrics);
                     let left_x = from_x.min(to_x);
                     let text_start = left_x + 5.0; // отступ от lifeline
                     let text_width = self.config.message_label_width(&msg.label);
@@ -166,10 +255,10 @@ impl SequenceLayoutEngine {
                     // Проверяем overflow вправо
                     *max_right = max_right.max(text_end);
 
-                    // Проверяем overflow влево (если сообщение идёт справа налево)
-                    let min_x = metrics.participants.values()
-                        .map(|p| p.center_x - p.width / 2.0)
-                        .fold(f64::MAX, f64::min);
+                    // Проверяем overflow влево (если сообщение идёт справа налево);
+                    // левая рамка совпадает с левым краем первого участника
+                    // или лежит левее него, если под сообщения `[->` нужен отступ
+                    let min_x = metrics.border_left_x;
                     if text_start < min_x {
                         *max_left_overflow = max_left_overflow.max(min_x - text_start);
                     }
@@ -241,9 +330,6 @@ impl SequenceLayoutEngine {
             if let Some(title) = &pbox.title {
                 properties.insert("title".to_string(), title.clone());
             }
-            if let Some(color) = &pbox.color {
-                properties.insert("color".to_string(), color.to_css());
-            }
 
             let box_element = LayoutElement {
                 id: format!("box_{}", i),
@@ -253,7 +339,7 @@ impl SequenceLayoutEngine {
                 properties,
             };
 
-            elements.push(box_element);
+            elements.push(box_element.with_style(&ElementStyle::new().with_fill(pbox.color.as_ref())));
         }
 
         elements
@@ -272,6 +358,14 @@ impl SequenceLayoutEngine {
             std::collections::HashMap::new();
         let mut participant_types: std::collections::HashMap<String, ParticipantType> =
             std::collections::HashMap::new();
+        let mut participant_links: std::collections::HashMap<String, &Link> =
+            std::collections::HashMap::new();
+        let mut participant_spans: std::collections::HashMap<String, Span> =
+            std::collections::HashMap::new();
+        let mut participant_styles: std::collections::HashMap<String, ElementStyle> =
+            std::collections::HashMap::new();
+        let mut participant_stereotypes: std::collections::HashMap<String, &Stereotype> =
+            std::collections::HashMap::new();
 
         for participant in &diagram.participants {
             let name = participant
@@ -284,9 +378,26 @@ impl SequenceLayoutEngine {
             if !participant_order.contains(&name) {
                 participant_order.push(name.clone());
                 participant_names.insert(name.clone(), display_name);
+                if let Some(link) = &participant.link {
+                    participant_links.insert(name.clone(), link);
+                }
+                participant_spans.insert(name.clone(), participant.span);
+                if let Some(stereotype) = &participant.stereotype {
+                    participant_stereotypes.insert(name.clone(), stereotype);
+                }
+                participant_styles.insert(
+                    name.clone(),
+                    ElementStyle::new().with_fill(participant.color.as_ref()),
+                );
                 participant_types.insert(name, participant.participant_type);
             }
         }
+        // Необъявленные участники ведут к первому сообщению с ними
+        for msg in diagram.messages() {
+            for name in msg.participants() {
+                participant_spans.entry(name.to_string()).or_insert(msg.span);
+            }
+        }
 
         // Также собираем участников из сообщений
         self.collect_participants_order(diagram, &mut participant_order);
@@ -319,8 +430,11 @@ impl SequenceLayoutEngine {
         // Теперь с учётом реальных ширин участников
         let spacing_map = self.calculate_participant_spacing(diagram, &participant_order, &participant_widths);
 
-        // Размещаем участников с вычисленными расстояниями
-        let mut x = self.config.margin;
+        // Размещаем участников с вычисленными расстояниями,
+        // оставляя слева место под сообщения с левой рамки
+        metrics.border_left_x = self.config.margin;
+        let first_width = participant_widths.first().copied().unwrap_or(0.0);
+        let mut x = self.config.margin + self.left_gutter(diagram, first_width);
 
         for (i, name) in participant_order.iter().enumerate() {
             let display_name = participant_names.get(name).unwrap_or(name);
@@ -343,6 +457,7 @@ impl SequenceLayoutEngine {
             };
 
             let bounds = Rect::new(x, y, width, self.config.participant_height);
+            let style = participant_styles.remove(name).unwrap_or_default();
 
             metrics.participants.insert(
                 name.clone(),
@@ -352,11 +467,20 @@ impl SequenceLayoutEngine {
                     center_x,
                     width,
                     header_bounds: bounds,
+                    link: participant_links.get(name).map(|link| (*link).clone()),
+                    span: participant_spans.get(name).copied().unwrap_or_default(),
+                    style: style.clone(),
+                    stereotype: participant_stereotypes.get(name).map(|s| (*s).clone()),
                 },
             );
 
             // Создаём визуальный элемент
-            let element = self.create_participant_element(name, display_name, &bounds, ptype);
+            let element = self
+                .create_participant_element(name, display_name, &bounds, ptype)
+                .with_link(participant_links.get(name).copied())
+                .with_source(participant_spans.get(name).copied().unwrap_or_default())
+                .with_style(&style)
+                .with_stereotype(participant_stereotypes.get(name).copied());
             elements.push(element);
 
             // Расстояние до следующего участника
@@ -373,6 +497,82 @@ impl SequenceLayoutEngine {
         }
 
         metrics.max_x = x;
+        metrics.border_right_x = self.border_right_x(diagram, metrics);
+    }
+
+    /// Отступ слева от первого участника под сообщения `[->` и `?->`
+    /// и отметки длительности `{start} <-> {end}`
+    fn left_gutter(&self, diagram: &SequenceDiagram, first_width: f64) -> f64 {
+        let mut durations = Vec::new();
+        collect_durations(&diagram.elements, &mut durations);
+        let duration_gutter = durations
+            .iter()
+            .map(|duration| {
+                let label_width = duration.label.as_deref().map_or(0.0, |label| self.config.text_width(label));
+                DURATION_OFFSET + 5.0 + label_width
+            })
+            .fold(0.0, f64::max);
+
+        let is_left = |endpoint: &MessageEndpoint| {
+            matches!(
+                endpoint,
+                MessageEndpoint::Border(EndpointSide::Left) | MessageEndpoint::Short(EndpointSide::Left)
+            )
+        };
+        diagram
+            .messages()
+            .into_iter()
+            .filter(|msg| is_left(&msg.from) || is_left(&msg.to))
+            .map(|msg| {
+                if matches!(msg.from, MessageEndpoint::Border(_)) || matches!(msg.to, MessageEndpoint::Border(_)) {
+                    // Подпись помещается между рамкой и lifeline
+                    (self.config.message_label_width(&msg.label) + 10.0 - first_width / 2.0).max(20.0)
+                } else {
+                    self.short_arrow_length(&msg.label) - first_width / 2.0
+                }
+            })
+            .fold(duration_gutter, f64::max)
+    }
+
+    /// X правой рамки: правее участников и подписей сообщений `->]`
+    fn border_right_x(&self, diagram: &SequenceDiagram, metrics: &DiagramMetrics) -> f64 {
+        diagram
+            .messages()
+            .into_iter()
+            .filter_map(|msg| {
+                let other = match (&msg.from, &msg.to) {
+                    (MessageEndpoint::Border(EndpointSide::Right), other)
+                    | (other, MessageEndpoint::Border(EndpointSide::Right)) => other,
+                    _ => return None,
+                };
+                let center_x = metrics.participant_center_x(other.participant()?)?;
+                Some(center_x + self.config.message_label_width(&msg.label) + 10.0)
+            })
+            .fold(metrics.max_x + 20.0, f64::max)
+    }
+
+    /// Длина короткой стрелки `?->`: под подпись, но не короче 40px
+    fn short_arrow_length(&self, label: &str) -> f64 {
+        self.config.message_label_width(label).max(40.0)
+    }
+
+    /// X концов сообщения: lifeline участника, рамка диаграммы
+    /// или конец короткой стрелки рядом с участником
+    fn message_x(&self, msg: &Message, metrics: &DiagramMetrics) -> (f64, f64) {
+        let lifeline_x = |endpoint: &MessageEndpoint| {
+            endpoint
+                .participant()
+                .map(|name| metrics.lifeline_x(name, &self.config))
+                .unwrap_or(0.0)
+        };
+        let endpoint_x = |endpoint: &MessageEndpoint, other: &MessageEndpoint| match endpoint {
+            MessageEndpoint::Participant(_) => lifeline_x(endpoint),
+            MessageEndpoint::Border(EndpointSide::Left) => metrics.border_left_x,
+            MessageEndpoint::Border(EndpointSide::Right) => metrics.border_right_x,
+            MessageEndpoint::Short(EndpointSide::Left) => lifeline_x(other) - self.short_arrow_length(&msg.label),
+            MessageEndpoint::Short(EndpointSide::Right) => lifeline_x(other) + self.short_arrow_length(&msg.label),
+        };
+        (endpoint_x(&msg.from, &msg.to), endpoint_x(&msg.to, &msg.from))
     }
 
     /// Собирает порядок участников из сообщений диаграммы
@@ -390,11 +590,10 @@ impl SequenceLayoutEngine {
     ) {
         match element {
             SequenceElement::Message(msg) => {
-                if !order.contains(&msg.from) {
-                    order.push(msg.from.clone());
-                }
-                if !order.contains(&msg.to) {
-                    order.push(msg.to.clone());
+                for name in msg.participants() {
+                    if !order.iter().any(|known| known == name) {
+                        order.push(name.to_string());
+                    }
                 }
             }
             SequenceElement::Fragment(frag) => {
@@ -534,8 +733,8 @@ impl SequenceLayoutEngine {
                 let autonumber_width = if has_autonumber { 45.0 } else { 0.0 };
                 let total_width = text_width + autonumber_width;
                 
-                let from_idx = participant_order.iter().position(|p| p == &msg.from);
-                let to_idx = participant_order.iter().position(|p| p == &msg.to);
+                let from_idx = participant_order.iter().position(|p| msg.from == p.as_str());
+                let to_idx = participant_order.iter().position(|p| msg.to == p.as_str());
                 
                 if let (Some(from_idx), Some(to_idx)) = (from_idx, to_idx) {
                     let (start, end) = if from_idx < to_idx {
@@ -668,6 +867,67 @@ impl SequenceLayoutEngine {
             SequenceElement::Return(ret) => {
                 self.layout_return(ret, metrics, elements);
             }
+            SequenceElement::NewPage(_) => {
+                // Страницы разделяются до layout (SequenceDiagram::split_pages),
+                // при рендеринге одной страницей разрыв игнорируется
+            }
+            SequenceElement::Duration(duration) => {
+                self.layout_duration(duration, metrics, elements);
+            }
+        }
+    }
+
+    /// Размещает отметку длительности между якорями
+    ///
+    /// Вертикальная стрелка в обе стороны слева от первого участника,
+    /// подпись — левее стрелки (место оставлено в `left_gutter`).
+    fn layout_duration(
+        &self,
+        duration: &Duration,
+        metrics: &DiagramMetrics,
+        elements: &mut Vec<LayoutElement>,
+    ) {
+        let (Some(&start_y), Some(&end_y)) =
+            (metrics.anchors.get(&duration.from), metrics.anchors.get(&duration.to))
+        else {
+            return;
+        };
+        let Some(first) = metrics.participants.values().next() else {
+            return;
+        };
+
+        let x = first.center_x - first.width / 2.0 - DURATION_OFFSET;
+        let (top, bottom) = (start_y.min(end_y), start_y.max(end_y));
+        let middle = (top + bottom) / 2.0;
+
+        // Две половины от середины, головки наружу
+        for (suffix, end) in [("top", top), ("bottom", bottom)] {
+            let mut properties = std::collections::HashMap::new();
+            let mut bounds = Rect::new(x - 1.0, middle.min(end), 2.0, (end - middle).abs());
+            let label = duration.label.clone().filter(|_| suffix == "bottom");
+            if let Some(label) = &label {
+                let label_x = x - 5.0 - self.config.text_width(label);
+                properties.insert(PROPERTY_LABEL_POSITION.to_string(), format!("{},{}", label_x, middle + 4.0));
+                // Подпись входит в bounds, чтобы попасть в viewBox
+                bounds.width += bounds.x - label_x;
+                bounds.x = label_x;
+            }
+            elements.push(LayoutElement {
+                id: format!("duration_{}_{}_{}", duration.from, duration.to, suffix),
+                bounds,
+                text: None,
+                properties,
+                element_type: ElementType::Edge {
+                    points: vec![Point::new(x, middle), Point::new(x, end)],
+                    label,
+                    arrow_start: false,
+                    arrow_end: true,
+                    dashed: false,
+                    edge_type: EdgeType::Association,
+                    from_cardinality: None,
+                    to_cardinality: None,
+                },
+            });
         }
     }
 
@@ -724,7 +984,7 @@ impl SequenceLayoutEngine {
         // Извлекаем последний вызов из стека
         if let Some((caller, callee)) = metrics.call_stack.pop() {
             // Создаём return message: callee --> caller
-            let mut msg = Message::new(&callee, &caller, ret.label.clone().unwrap_or_default());
+            let mut msg = Message::new(callee.clone(), caller.clone(), ret.label.clone().unwrap_or_default());
             msg.line_style = LineStyle::Dashed;
             msg.deactivate = true; // Return деактивирует callee
             
@@ -733,10 +993,11 @@ impl SequenceLayoutEngine {
             metrics.last_message_y = y;
 
             // Деактивируем callee
-            metrics.deactivate(&callee);
+            if let Some(name) = callee.participant() {
+                metrics.deactivate(name);
+            }
 
-            let from_x = metrics.lifeline_x(&callee, &self.config);
-            let to_x = metrics.lifeline_x(&caller, &self.config);
+            let (from_x, to_x) = self.message_x(&msg, metrics);
 
             let points = vec![Point::new(from_x, y), Point::new(to_x, y)];
 
@@ -747,11 +1008,21 @@ impl SequenceLayoutEngine {
                 2.0,
             );
 
+            let mut properties = std::collections::HashMap::new();
+            let mut below_height = 0.0;
+            if self.config.response_message_below_arrow && to_x < from_x {
+                if let Some(label) = &ret.label {
+                    let (position, height) = self.label_below_arrow(label, to_x, y);
+                    properties.insert(PROPERTY_LABEL_POSITION.to_string(), position);
+                    below_height = height;
+                }
+            }
+
             let edge = LayoutElement {
-                id: format!("return_{}_{}", callee, caller),
+                id: format!("return_{}_{}", endpoint_id(&callee), endpoint_id(&caller)),
                 bounds,
                 text: None,
-                properties: std::collections::HashMap::new(),
+                properties,
                 element_type: ElementType::Edge {
                     points,
                     label: ret.label.clone(),
@@ -763,10 +1034,32 @@ impl SequenceLayoutEngine {
             };
 
             elements.push(edge);
-            metrics.advance_y(self.config.message_spacing);
+            metrics.advance_y(self.config.message_spacing + below_height);
         }
     }
 
+    /// Подпись под стрелкой (`responseMessageBelowArrow`)
+    ///
+    /// Возвращает позицию для `PROPERTY_LABEL_POSITION` (левый край и базовая
+    /// линия последней строки) и дополнительный шаг по вертикали.
+    fn label_below_arrow(&self, label: &str, left_x: f64, y: f64) -> (String, f64) {
+        let height = self.label_height(label);
+        (format!("{},{}", left_x + 5.0, y + height), height)
+    }
+
+    /// Высота подписи сообщения
+    ///
+    /// Строки раскладывает `TextMetrics::label_lines`, как и renderer:
+    /// крупный текст `<size>`, спрайты и таблицы выше обычной строки.
+    fn label_height(&self, label: &str) -> f64 {
+        self.config
+            .text
+            .label_lines(label, self.config.font_size, false)
+            .iter()
+            .map(|line| line.height)
+            .sum()
+    }
+
     /// Размещает сообщение
     fn layout_message(
         &self,
@@ -774,33 +1067,59 @@ impl SequenceLayoutEngine {
         metrics: &mut DiagramMetrics,
         elements: &mut Vec<LayoutElement>,
     ) {
-        // Сначала вычисляем количество строк текста
-        let line_count = msg.label.matches("\\n").count() + msg.label.matches('\n').count();
-        
-        // Для многострочного текста нужно добавить место ПЕРЕД стрелкой
-        // (текст идёт вверх от стрелки)
-        if line_count > 0 {
-            metrics.advance_y(line_count as f64 * self.config.line_height);
+        // Высота подписи сверх одной строки — место над стрелкой
+        let text_height = self.config.text.measurer().line_height(self.config.font_size);
+        let extra_height = (self.label_height(&msg.label) - text_height).max(0.0);
+
+        // Получаем X координаты ДО активации (чтобы стрелка шла к центру lifeline)
+        let (from_x, to_x) = self.message_x(msg, metrics);
+        let is_self_message = msg.is_self_message();
+
+        // Ответ (стрелка справа налево) с подписью под стрелкой
+        let below_arrow = self.config.response_message_below_arrow
+            && !is_self_message
+            && !msg.label.is_empty()
+            && to_x < from_x;
+
+        // `& A -> B`: на высоте предыдущего сообщения
+        let resume_y = metrics.current_y;
+        let parallel = msg.parallel && metrics.last_message_y > 0.0;
+        if parallel {
+            metrics.current_y = metrics.last_message_y;
+        } else if extra_height > 0.0 && !below_arrow {
+            // Для многострочного текста нужно добавить место ПЕРЕД стрелкой
+            // (текст идёт вверх от стрелки)
+            metrics.advance_y(extra_height);
         }
         
         let y = metrics.current_y;
 
         // Сохраняем Y позицию этого сообщения для последующих активаций
         metrics.last_message_y = y;
+        if let Some(anchor) = &msg.anchor {
+            metrics.anchors.insert(anchor.clone(), y);
+        }
 
-        // Получаем X координаты ДО активации (чтобы стрелка шла к центру lifeline)
-        let from_x = metrics.lifeline_x(&msg.from, &self.config);
-        let to_x = metrics.lifeline_x(&msg.to, &self.config);
+        // Наклонная стрелка `->(10)`: получатель ниже отправителя
+        let slant = match msg.slant {
+            Some(slant) if !is_self_message => slant as f64,
+            _ => 0.0,
+        };
 
         // Обрабатываем активацию на сообщении
         // Важно: активация начинается с Y позиции ЭТОГО сообщения
         if msg.activate {
-            metrics.activate_at(&msg.to, y);
+            if let Some(name) = msg.to.participant() {
+                metrics.activate_at(name, y + slant);
+                metrics.style_activation(name, ElementStyle::new().with_fill(msg.activation_color.as_ref()));
+            }
             // Добавляем в call_stack для return
             metrics.call_stack.push((msg.from.clone(), msg.to.clone()));
         }
         if msg.deactivate {
-            metrics.deactivate(&msg.from);
+            if let Some(name) = msg.from.participant() {
+                metrics.deactivate(name);
+            }
         }
 
         // Получаем номер autonumber (если включен)
@@ -813,9 +1132,6 @@ impl SequenceLayoutEngine {
         // Label сообщения (без номера - он будет отдельным элементом)
         let label = msg.label.clone();
 
-        // Создаём линию сообщения
-        let is_self_message = msg.from == msg.to;
-
         // Вычисляем ширину текста для корректного позиционирования
         let label_width = if label.is_empty() {
             0.0
@@ -836,7 +1152,7 @@ impl SequenceLayoutEngine {
                 Point::new(from_x, y + loop_height),
             ]
         } else {
-            vec![Point::new(from_x, y), Point::new(to_x, y)]
+            vec![Point::new(from_x, y), Point::new(to_x, y + slant)]
         };
 
         // Определяем стиль линии (используется при рендеринге)
@@ -861,7 +1177,7 @@ impl SequenceLayoutEngine {
                 from_x.min(to_x),
                 y - 1.0,
                 (to_x - from_x).abs().max(1.0),
-                2.0,
+                2.0 + slant,
             )
         };
 
@@ -870,9 +1186,28 @@ impl SequenceLayoutEngine {
         if let Some(ref num) = autonumber {
             properties.insert("autonumber".to_string(), num.clone());
         }
+        // Кружок или крест на концах (`o->`, `->o`, `->x`)
+        if let Some(decoration) = msg.source_decoration {
+            properties.insert(PROPERTY_START_DECORATION.to_string(), decoration_name(decoration).to_string());
+        }
+        let head_decoration = match msg.arrow_type {
+            ArrowType::Circle => Some(EndpointDecoration::Circle),
+            ArrowType::Cross => Some(EndpointDecoration::Cross),
+            _ => None,
+        };
+        if let Some(decoration) = head_decoration {
+            properties.insert(PROPERTY_END_DECORATION.to_string(), decoration_name(decoration).to_string());
+        }
+        let below_height = if below_arrow {
+            let (position, height) = self.label_below_arrow(&label, to_x, y);
+            properties.insert(PROPERTY_LABEL_POSITION.to_string(), position);
+            height
+        } else {
+            0.0
+        };
 
         let edge = LayoutElement {
-            id: format!("msg_{}_{}", msg.from, msg.to),
+            id: format!("msg_{}_{}", endpoint_id(&msg.from), endpoint_id(&msg.to)),
             bounds,
             text: None, 
             properties, 
@@ -887,7 +1222,8 @@ impl SequenceLayoutEngine {
                     Some(label)
                 },
                 arrow_start: false,
-                arrow_end: true,
+                // Потерянное сообщение (->x) заканчивается крестом без головки
+                arrow_end: msg.arrow_type != ArrowType::Cross,
                 dashed: is_dashed, // пунктирная линия для --> (response)
                 edge_type: EdgeType::Association, // стандартные стрелки sequence diagram
                 from_cardinality: None,
@@ -895,7 +1231,11 @@ impl SequenceLayoutEngine {
             },
         };
 
-        elements.push(edge);
+        elements.push(
+            edge.with_link(msg.link.as_ref())
+                .with_source(msg.span)
+                .with_style(&message_style(msg)),
+        );
 
         // Продвигаем Y на базовое расстояние между сообщениями
         // (место для многострочного текста уже добавлено ПЕРЕД стрелкой)
@@ -903,9 +1243,12 @@ impl SequenceLayoutEngine {
             // PlantUML self-message: шаг между self-messages ~30px (петля 13px + отступ)
             30.0
         } else {
-            self.config.message_spacing
+            self.config.message_spacing + slant + below_height
         };
         metrics.advance_y(height);
+        if parallel {
+            metrics.current_y = metrics.current_y.max(resume_y);
+        }
     }
 
     /// Размещает фрагмент (alt, opt, loop, etc.)
@@ -1013,13 +1356,13 @@ impl SequenceLayoutEngine {
             for elem in &section.elements {
                 if let SequenceElement::Message(msg) = elem {
                     // Используем границы участника (center_x ± width/2) для полного охвата
-                    if let Some(participant) = metrics.participants.get(&msg.from) {
+                    if let Some(participant) = msg.from.participant().and_then(|name| metrics.participants.get(name)) {
                         let left = participant.center_x - participant.width / 2.0;
                         let right = participant.center_x + participant.width / 2.0;
                         min_x = min_x.min(left);
                         max_x = max_x.max(right);
                     }
-                    if let Some(participant) = metrics.participants.get(&msg.to) {
+                    if let Some(participant) = msg.to.participant().and_then(|name| metrics.participants.get(name)) {
                         let left = participant.center_x - participant.width / 2.0;
                         let right = participant.center_x + participant.width / 2.0;
                         min_x = min_x.min(left);
@@ -1047,6 +1390,11 @@ impl SequenceLayoutEngine {
     ) {
         let y = metrics.current_y;
 
+        // Размер заметки по тексту, но не меньше стандартного
+        let text_size = self.config.text.size_at(&note.text, self.config.text.font_size);
+        let note_width = self.config.note_width.max(text_size.width + 20.0);
+        let note_height = self.config.note_height.max(text_size.height + 10.0);
+
         // Определяем X позицию
         let x = if note.anchors.is_empty() {
             self.config.margin
@@ -1055,10 +1403,10 @@ impl SequenceLayoutEngine {
                 .participant_center_x(&note.anchors[0])
                 .unwrap_or(self.config.margin);
             match note.position {
-                NotePosition::Left => anchor_x - self.config.note_width - 20.0,
+                NotePosition::Left => anchor_x - note_width - 20.0,
                 NotePosition::Right => anchor_x + 20.0,
-                NotePosition::Over => anchor_x - self.config.note_width / 2.0,
-                NotePosition::Top | NotePosition::Bottom => anchor_x - self.config.note_width / 2.0,
+                NotePosition::Over => anchor_x - note_width / 2.0,
+                NotePosition::Top | NotePosition::Bottom => anchor_x - note_width / 2.0,
             }
         } else {
             // Over multiple participants
@@ -1068,10 +1416,10 @@ impl SequenceLayoutEngine {
             let last_x = metrics
                 .participant_center_x(note.anchors.last().unwrap())
                 .unwrap_or(self.config.margin);
-            (first_x + last_x) / 2.0 - self.config.note_width / 2.0
+            (first_x + last_x) / 2.0 - note_width / 2.0
         };
 
-        let bounds = Rect::new(x, y, self.config.note_width, self.config.note_height);
+        let bounds = Rect::new(x, y, note_width, note_height);
 
         let note_elem = LayoutElement {
             id: format!("note_{}", y as u32),
@@ -1082,8 +1430,12 @@ impl SequenceLayoutEngine {
             },
         };
 
-        elements.push(note_elem);
-        metrics.advance_y(self.config.note_height + 10.0);
+        elements.push(
+            note_elem
+                .with_link(note.link.as_ref())
+                .with_style(&ElementStyle::new().with_fill(note.background_color.as_ref())),
+        );
+        metrics.advance_y(note_height + 10.0);
     }
 
     /// Обрабатывает активацию/деактивацию
@@ -1091,6 +1443,7 @@ impl SequenceLayoutEngine {
         match act.activation_type {
             ActivationType::Activate => {
                 metrics.activate(&act.participant);
+                metrics.style_activation(&act.participant, ElementStyle::new().with_fill(act.color.as_ref()));
             }
             ActivationType::Deactivate | ActivationType::Destroy => {
                 metrics.deactivate(&act.participant);
@@ -1175,7 +1528,7 @@ impl SequenceLayoutEngine {
                     text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Activation,
                 };
 
-                elements.push(activation);
+                elements.push(activation.with_style(&info.style));
             }
         }
     }
@@ -1208,7 +1561,7 @@ impl SequenceLayoutEngine {
                     label: None,
                     arrow_start: false,
                     arrow_end: false,
-                    dashed: true, // Lifelines всегда пунктирные
+                    dashed: !self.config.solid_lifelines, // по умолчанию пунктирные
                     edge_type: EdgeType::Link, // линия без маркеров
                     from_cardinality: None,
                     to_cardinality: None,
@@ -1239,11 +1592,60 @@ impl SequenceLayoutEngine {
                     corner_radius: 2.5, // PlantUML style
                 },
             };
-            elements.push(footer);
+            elements.push(
+                footer
+                    .with_link(participant.link.as_ref())
+                    .with_source(participant.span)
+                    .with_style(&participant.style)
+                    .with_stereotype(participant.stereotype.as_ref()),
+            );
+        }
+    }
+}
+
+/// Стиль стрелки сообщения: цвет и стиль линии, кроме пунктира `-->`
+fn message_style(msg: &Message) -> ElementStyle {
+    let style = ElementStyle::new().with_stroke(msg.color.as_ref());
+    match msg.line_style {
+        LineStyle::Dotted | LineStyle::Bold => style.with_line_style(msg.line_style),
+        LineStyle::Solid | LineStyle::Dashed => style,
+    }
+}
+
+/// Рекурсивно собирает отметки длительности, включая вложенные во фрагменты
+fn collect_durations<'a>(elements: &'a [SequenceElement], out: &mut Vec<&'a Duration>) {
+    for element in elements {
+        match element {
+            SequenceElement::Duration(duration) => out.push(duration),
+            SequenceElement::Fragment(frag) => {
+                for section in &frag.sections {
+                    collect_durations(&section.elements, out);
+                }
+            }
+            _ => {}
         }
     }
 }
 
+/// Часть id сообщения для конца: имя участника или сторона рамки
+fn endpoint_id(endpoint: &MessageEndpoint) -> String {
+    match endpoint {
+        MessageEndpoint::Participant(name) => name.clone(),
+        MessageEndpoint::Border(EndpointSide::Left) => "border_left".to_string(),
+        MessageEndpoint::Border(EndpointSide::Right) => "border_right".to_string(),
+        MessageEndpoint::Short(EndpointSide::Left) => "short_left".to_string(),
+        MessageEndpoint::Short(EndpointSide::Right) => "short_right".to_string(),
+    }
+}
+
+/// Значение свойства украшения конца ребра
+fn decoration_name(decoration: EndpointDecoration) -> &'static str {
+    match decoration {
+        EndpointDecoration::Circle => "circle",
+        EndpointDecoration::Cross => "cross",
+    }
+}
+
 impl Default for SequenceLayoutEngine {
     fn default() -> Self {
         Self::new()
@@ -1261,7 +1663,8 @@ impl crate::LayoutEngine for SequenceLayoutEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use plantuml_ast::sequence::Participant;
+    use plantuml_ast::sequence::{Participant, SequenceDisplay};
+    use crate::ZLayer;
 
     #[test]
     fn test_empty_diagram() {
@@ -1475,4 +1878,207 @@ mod tests {
             footer_count
         );
     }
+
+    #[test]
+    fn test_boundary_and_short_messages() {
+        let engine = SequenceLayoutEngine::new();
+        let mut diagram = SequenceDiagram::new();
+
+        let border = |side| MessageEndpoint::Border(side);
+        diagram.add_element(SequenceElement::Message(Message::new(
+            border(EndpointSide::Left),
+            "Alice",
+            "входящий запрос",
+        )));
+        diagram.add_element(SequenceElement::Message(Message::new("Alice", "Bob", "hi")));
+        let mut lost = Message::new("Bob", MessageEndpoint::Short(EndpointSide::Right), "lost");
+        lost.arrow_type = ArrowType::Cross;
+        diagram.add_element(SequenceElement::Message(lost));
+        diagram.add_element(SequenceElement::Message(Message::new(
+            "Bob",
+            border(EndpointSide::Right),
+            "out",
+        )));
+
+        let result = engine.layout(&diagram);
+        let edge = |id: &str| {
+            let element = result.elements.iter().find(|e| e.id == id).unwrap_or_else(|| panic!("{id}"));
+            match &element.element_type {
+                ElementType::Edge { points, arrow_end, .. } => (element, points.clone(), *arrow_end),
+                _ => panic!("{id} is not an edge"),
+            }
+        };
+        let header = |name: &str| {
+            result
+                .elements
+                .iter()
+                .find(|e| e.id == format!("participant_{name}"))
+                .map(|e| e.bounds)
+                .unwrap()
+        };
+
+        // Рамка и короткие стрелки не становятся участниками
+        assert_eq!(result.elements.iter().filter(|e| e.id.starts_with("participant_")).count(), 2);
+
+        // Левая рамка — у края диаграммы, подпись помещается до lifeline
+        let (_, points, _) = edge("msg_border_left_Alice");
+        let alice = header("Alice");
+        assert_eq!(points[0].x, SequenceLayoutConfig::default().margin);
+        assert!(points[1].x - points[0].x >= engine.config.message_label_width("входящий запрос"));
+        assert!(alice.x > points[0].x);
+
+        // Потерянное сообщение: короткая стрелка вправо с крестом без головки
+        let (lost, points, arrow_end) = edge("msg_Bob_short_right");
+        assert!(!arrow_end);
+        assert_eq!(lost.properties.get(PROPERTY_END_DECORATION).map(String::as_str), Some("cross"));
+        assert!((40.0..200.0).contains(&(points[1].x - points[0].x)));
+
+        // Правая рамка — правее последнего участника
+        let (_, points, _) = edge("msg_Bob_border_right");
+        let bob = header("Bob");
+        assert!(points[1].x > bob.x + bob.width);
+    }
+
+    #[test]
+    fn test_message_label_height() {
+        let engine = SequenceLayoutEngine::new();
+        let arrow_y = |label: &str| {
+            let mut diagram = SequenceDiagram::new();
+            diagram.add_element(SequenceElement::Message(Message::new("Alice", "Bob", label)));
+            let result = engine.layout(&diagram);
+            match &result.elements.iter().find(|e| e.id == "msg_Alice_Bob").unwrap().element_type {
+                ElementType::Edge { points, .. } => points[0].y,
+                _ => unreachable!(),
+            }
+        };
+
+        // Место над стрелкой — измеренные высоты строк, а не число `\n`
+        let line = engine.config.text.measurer().line_height(engine.config.font_size);
+        let single = arrow_y("hi");
+        assert_eq!(arrow_y("hi\\nthere"), single + line);
+        let text = &engine.config.text;
+        let big = text.label_lines("<size:30>big</size>", engine.config.font_size, false);
+        assert!(big[0].height > line * 2.0);
+        assert_eq!(arrow_y("<size:30>big</size>"), single + big[0].height - line);
+    }
+
+    #[test]
+    fn test_teoz_parallel_slanted_and_duration() {
+        let engine = SequenceLayoutEngine::new();
+        let mut diagram = SequenceDiagram::new();
+
+        let mut first = Message::new("Alice", "Bob", "req");
+        first.anchor = Some("start".to_string());
+        let mut parallel = Message::new("Bob", "Carol", "copy");
+        parallel.parallel = true;
+        let mut slanted = Message::new("Bob", "Alice", "resp");
+        slanted.slant = Some(10);
+        slanted.anchor = Some("end".to_string());
+        for msg in [first, parallel, slanted] {
+            diagram.add_element(SequenceElement::Message(msg));
+        }
+        diagram.add_element(SequenceElement::Duration(Duration {
+            from: "start".to_string(),
+            to: "end".to_string(),
+            label: Some("10 ms".to_string()),
+        }));
+
+        let result = engine.layout(&diagram);
+        let points = |id: &str| {
+            result
+                .elements
+                .iter()
+                .find_map(|e| match &e.element_type {
+                    ElementType::Edge { points, .. } if e.id == id => Some(points.clone()),
+                    _ => None,
+                })
+                .unwrap_or_else(|| panic!("{id}"))
+        };
+
+        // `&` — на той же высоте, наклонная стрелка опускается к получателю
+        let req = points("msg_Alice_Bob");
+        assert_eq!(points("msg_Bob_Carol")[0].y, req[0].y);
+        let resp = points("msg_Bob_Alice");
+        assert!(resp[0].y > req[0].y);
+        assert_eq!(resp[1].y - resp[0].y, 10.0);
+
+        // Длительность — от якоря start до якоря end левее Alice
+        let top = points("duration_start_end_top");
+        let bottom = points("duration_start_end_bottom");
+        assert_eq!(top[1].y, req[0].y);
+        assert_eq!(bottom[1].y, resp[0].y);
+        let alice = result.elements.iter().find(|e| e.id == "participant_Alice").unwrap();
+        assert!(top[0].x < alice.bounds.x);
+        assert!(top[0].x - engine.config.text_width("10 ms") - 5.0 >= engine.config.margin - 1e-9);
+    }
+
+    #[test]
+    fn test_source_lines() {
+        let mut diagram = SequenceDiagram::new();
+        let mut alice = Participant::new("Alice", ParticipantType::Participant);
+        alice.span = Span::new(10, 27, 2, 1);
+        diagram.add_participant(alice);
+        let mut msg = Message::new("Alice", "Bob", "Hi");
+        msg.span = Span::new(28, 44, 3, 1);
+        diagram.add_element(SequenceElement::Message(msg));
+
+        let result = SequenceLayoutEngine::new().layout(&diagram);
+        let line = |id: &str| result.elements.iter().find(|e| e.id == id).unwrap().source_line();
+
+        assert_eq!(line("participant_Alice"), Some(2));
+        assert_eq!(line("footer_Alice"), Some(2));
+        // Необъявленный участник — строка первого сообщения с ним
+        assert_eq!(line("participant_Bob"), Some(3));
+        assert_eq!(line("msg_Alice_Bob"), Some(3));
+        assert_eq!(line("lifeline_Alice"), None);
+    }
+
+    #[test]
+    fn test_display_directives() {
+        let mut diagram = SequenceDiagram::new();
+        diagram.add_participant(Participant::new("Alice", ParticipantType::Participant));
+        diagram.add_participant(Participant::new("Bob", ParticipantType::Participant));
+        diagram.add_participant(Participant::new("Idle", ParticipantType::Participant));
+        diagram.add_element(SequenceElement::Message(Message::new("Alice", "Bob", "a rather long request text")));
+        diagram.add_element(SequenceElement::Message(Message::new("Bob", "Alice", "ok")));
+        let plain = SequenceLayoutEngine::new().layout(&diagram);
+
+        diagram.display = SequenceDisplay {
+            hide_footbox: true,
+            hide_unlinked: true,
+            max_message_size: Some(60.0),
+            response_message_below_arrow: true,
+            solid_lifelines: true,
+        };
+        let engine = SequenceLayoutEngine::new();
+        let result = engine.layout(&diagram);
+        let element = |id: &str| result.elements.iter().find(|e| e.id == id);
+
+        assert!(element("participant_Idle").is_none());
+        assert!(element("footer_Alice").is_none());
+        assert!(plain.elements.iter().any(|e| e.id == "footer_Alice"));
+        assert!(matches!(
+            element("lifeline_Alice").unwrap().element_type,
+            ElementType::Edge { dashed: false, .. }
+        ));
+        // Сплошная линия жизни остаётся под активациями и сообщениями
+        assert_eq!(ZLayer::from_element(element("lifeline_Alice").unwrap()), ZLayer::Lifeline);
+
+        // Подпись перенесена по словам
+        let request = element("msg_Alice_Bob").unwrap();
+        let ElementType::Edge { label: Some(label), .. } = &request.element_type else {
+            panic!("label expected");
+        };
+        assert!(label.lines().count() > 1);
+        assert!(label.lines().all(|line| !line.contains(' ') || engine.config.text_width(line) <= 60.0));
+
+        // Ответ подписан под стрелкой
+        let response = element("msg_Bob_Alice").unwrap();
+        let ElementType::Edge { points, .. } = &response.element_type else {
+            panic!("edge expected");
+        };
+        let position = response.properties.get(PROPERTY_LABEL_POSITION).unwrap();
+        let (_, baseline) = position.split_once(',').unwrap();
+        assert!(baseline.parse::<f64>().unwrap() > points[0].y);
+    }
 }
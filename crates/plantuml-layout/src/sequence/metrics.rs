@@ -2,8 +2,12 @@
 
 use super::SequenceLayoutConfig;
 use indexmap::IndexMap;
+use plantuml_ast::common::{Link, Span, Stereotype};
+use plantuml_ast::sequence::MessageEndpoint;
 use plantuml_model::Rect;
 
+use crate::ElementStyle;
+
 /// Информация о позиции участника
 #[derive(Debug, Clone)]
 pub struct ParticipantMetrics {
@@ -19,6 +23,14 @@ pub struct ParticipantMetrics {
     /// Прямоугольник заголовка (для будущего использования)
     #[allow(dead_code)]
     pub header_bounds: Rect,
+    /// Гиперссылка участника (для header и footer)
+    pub link: Option<Link>,
+    /// Объявление участника или первое сообщение с ним
+    pub span: Span,
+    /// Стиль участника (для header и footer)
+    pub style: ElementStyle,
+    /// Стереотип участника (для header и footer)
+    pub stereotype: Option<Stereotype>,
 }
 
 /// Информация об активации
@@ -30,6 +42,8 @@ pub struct ActivationInfo {
     pub start_y: f64,
     /// Уровень вложенности (для смещения по X)
     pub level: u32,
+    /// Стиль активации (`activate A #FFBBBB`)
+    pub style: ElementStyle,
 }
 
 /// Состояние autonumber
@@ -78,6 +92,10 @@ pub struct DiagramMetrics {
     pub last_message_y: f64,
     /// Максимальная X координата
     pub max_x: f64,
+    /// X левой рамки для сообщений `[->`
+    pub border_left_x: f64,
+    /// X правой рамки для сообщений `->]`
+    pub border_right_x: f64,
     /// Стек активаций (participant_id -> count)
     pub activation_stack: IndexMap<String, u32>,
     /// Активные активации (стек для каждого участника)
@@ -87,7 +105,9 @@ pub struct DiagramMetrics {
     /// Состояние autonumber
     pub autonumber: AutonumberState,
     /// Стек вызовов для return (caller, callee)
-    pub call_stack: Vec<(String, String)>,
+    pub call_stack: Vec<(MessageEndpoint, MessageEndpoint)>,
+    /// Y сообщений с якорями (`{start} A -> B`)
+    pub anchors: IndexMap<String, f64>,
 }
 
 impl DiagramMetrics {
@@ -98,11 +118,14 @@ impl DiagramMetrics {
             current_y: 0.0,
             last_message_y: 0.0,
             max_x: 0.0,
+            border_left_x: 0.0,
+            border_right_x: 0.0,
             activation_stack: IndexMap::new(),
             active_activations: IndexMap::new(),
             completed_activations: Vec::new(),
             autonumber: AutonumberState::default(),
             call_stack: Vec::new(),
+            anchors: IndexMap::new(),
         }
     }
 
@@ -133,6 +156,7 @@ impl DiagramMetrics {
             participant: name.to_string(),
             start_y,
             level: *level,
+            style: ElementStyle::default(),
         };
 
         self.active_activations
@@ -141,6 +165,13 @@ impl DiagramMetrics {
             .push(info);
     }
 
+    /// Задаёт стиль последней открытой активации участника
+    pub fn style_activation(&mut self, name: &str, style: ElementStyle) {
+        if let Some(info) = self.active_activations.get_mut(name).and_then(|stack| stack.last_mut()) {
+            info.style = style;
+        }
+    }
+
     /// Уменьшает уровень активации и сохраняет завершённую
     pub fn deactivate(&mut self, name: &str) {
         if let Some(level) = self.activation_stack.get_mut(name) {
@@ -219,6 +250,10 @@ impl DiagramMetrics {
                         width,
                         config.participant_height,
                     ),
+                    link: None,
+                    span: Span::empty(),
+                    style: ElementStyle::default(),
+                    stereotype: None,
                 },
             );
 
@@ -1,5 +1,7 @@
 //! Конфигурация layout для State Diagrams
 
+use crate::text::TextMetrics;
+
 /// Конфигурация State Layout Engine
 #[derive(Debug, Clone)]
 pub struct StateLayoutConfig {
@@ -27,6 +29,8 @@ pub struct StateLayoutConfig {
     pub arrow_size: f64,
     /// Отступ текста внутри состояния
     pub text_padding: f64,
+    /// Метрики текста (шрифт темы)
+    pub text: TextMetrics,
 }
 
 impl Default for StateLayoutConfig {
@@ -44,6 +48,7 @@ impl Default for StateLayoutConfig {
             bar_height: 6.0,
             arrow_size: 8.0,
             text_padding: 10.0,
+            text: TextMetrics::default(),
         }
     }
 }
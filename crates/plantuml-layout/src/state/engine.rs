@@ -4,11 +4,16 @@
 //! Поддерживает вложенные (composite) состояния.
 
 use indexmap::{IndexMap, IndexSet};
-use plantuml_ast::state::{State, StateDiagram, StateType};
-use plantuml_model::{Point, Rect};
+use plantuml_ast::common::{InlineStyle, LineType};
+use plantuml_ast::state::{State, StateDiagram, StateType, Transition};
+use plantuml_model::{Rect, Size};
 
 use super::config::StateLayoutConfig;
-use crate::{EdgeType, ElementType, LayoutElement, LayoutResult};
+use crate::class::sugiyama::SugiyamaLayout;
+use crate::class::{ClassLayoutConfig, Graph};
+use crate::routing::{translate_positions, EdgeSpec, Router};
+use crate::text::TextMetrics;
+use crate::{EdgeType, ElementStyle, ElementType, LayoutElement, LayoutResult};
 
 /// Layout engine для state diagrams
 pub struct StateLayoutEngine {
@@ -19,6 +24,9 @@ pub struct StateLayoutEngine {
 const INITIAL_STATE_ID: &str = "[*]_initial";
 const FINAL_STATE_ID: &str = "[*]_final";
 
+/// Видимый переход: откуда, куда, подпись и исходный переход AST
+type VisibleTransition<'a, L> = (&'a String, &'a Rect, &'a String, &'a Rect, L, &'a Transition);
+
 /// Результат layout подсостояний
 struct SubLayoutResult {
     elements: Vec<LayoutElement>,
@@ -38,10 +46,17 @@ impl StateLayoutEngine {
         Self { config }
     }
 
+    /// Устанавливает метрики текста (шрифт темы)
+    pub fn with_text_metrics(mut self, text: TextMetrics) -> Self {
+        self.config.text = text;
+        self
+    }
+
     /// Выполняет layout диаграммы
     pub fn layout(&self, diagram: &StateDiagram) -> LayoutResult {
         let mut elements = Vec::new();
         let mut state_positions: IndexMap<String, Rect> = IndexMap::new();
+        let line_type = diagram.metadata.line_type.unwrap_or(LineType::Polyline);
 
         // Определяем composite состояния и собираем их внутренние состояния
         let composite_states: IndexMap<String, &State> = diagram
@@ -100,7 +115,7 @@ impl StateLayoutEngine {
         }
 
         // Преобразуем переходы верхнего уровня
-        let top_level_transitions: Vec<(String, String, Option<String>)> = diagram
+        let top_level_transitions: Vec<(String, String, &Transition)> = diagram
             .transitions
             .iter()
             .filter(|t| {
@@ -120,139 +135,106 @@ impl StateLayoutEngine {
                 } else {
                     t.to.clone()
                 };
-                let label = t.label();
-                (from, to, if label.is_empty() { None } else { Some(label) })
+                (from, to, t)
             })
             .collect();
 
-        // Определяем уровни состояний верхнего уровня
-        let levels = self.assign_levels(&top_level_states, &top_level_transitions, has_initial, has_final);
-        
-        // Группируем по уровням
-        let mut level_states: IndexMap<usize, Vec<String>> = IndexMap::new();
-        for (state, level) in &levels {
-            level_states
-                .entry(*level)
-                .or_default()
-                .push(state.clone());
-        }
-
         // Сначала делаем layout для composite состояний, чтобы узнать их размеры
         let mut composite_layouts: IndexMap<String, SubLayoutResult> = IndexMap::new();
         
         for (name, composite) in &composite_states {
-            let sub_result = self.layout_composite_content(composite);
+            let sub_result = self.layout_composite_content(composite, line_type);
             composite_layouts.insert(name.clone(), sub_result);
         }
 
-        // Располагаем состояния верхнего уровня
-        // Используем динамический расчёт Y с учётом реальной высоты composite контейнеров
-        let max_level = levels.values().max().copied().unwrap_or(0);
-        
-        // Сначала вычисляем размеры для каждого уровня
-        let mut level_heights: IndexMap<usize, f64> = IndexMap::new();
-        let mut level_widths: IndexMap<usize, f64> = IndexMap::new();
-        
-        for level in 0..=max_level {
-            if let Some(states) = level_states.get(&level) {
-                let max_height = states.iter().map(|name| {
-                    if let Some(layout) = composite_layouts.get(name) {
-                        layout.bounds.height + self.config.margin * 2.0 + 30.0 // header
-                    } else if name == INITIAL_STATE_ID || name == FINAL_STATE_ID {
-                        self.config.node_radius * 2.0
-                    } else {
-                        self.config.state_min_height
-                    }
-                }).fold(0.0f64, f64::max);
-                level_heights.insert(level, max_height);
-                
-                // Вычисляем ширину для центрирования
-                let total_width: f64 = states.iter().map(|name| {
-                    if let Some(layout) = composite_layouts.get(name) {
-                        layout.bounds.width + self.config.margin * 2.0
-                    } else if name == INITIAL_STATE_ID || name == FINAL_STATE_ID {
-                        self.config.node_radius * 2.0
-                    } else {
-                        self.config.state_width
-                    }
-                }).sum::<f64>() + (states.len().saturating_sub(1)) as f64 * self.config.horizontal_spacing;
-                level_widths.insert(level, total_width);
-            }
-        }
-        
-        // Находим максимальную ширину среди всех уровней для центрирования
-        let max_width = level_widths.values().copied().fold(0.0f64, f64::max);
-        let diagram_center_x = self.config.margin + max_width / 2.0;
-        
-        // Вычисляем начальную Y позицию для каждого уровня на основе предыдущих
-        let mut level_y_positions: IndexMap<usize, f64> = IndexMap::new();
-        let mut current_y = self.config.margin;
-        for level in 0..=max_level {
-            level_y_positions.insert(level, current_y);
-            let height = level_heights.get(&level).copied().unwrap_or(self.config.state_min_height);
-            current_y += height + self.config.vertical_spacing;
-        }
-        
-        for level in 0..=max_level {
-            if let Some(states) = level_states.get(&level) {
-                let level_width = level_widths.get(&level).copied().unwrap_or(0.0);
-                
-                // Центрируем относительно общего центра диаграммы
-                let start_x = diagram_center_x - level_width / 2.0;
-                let mut x = start_x;
-                
-                // Получаем Y позицию для данного уровня
-                let y = level_y_positions.get(&level).copied().unwrap_or(self.config.margin);
-
-                for state_name in states {
-                    // Проверяем, это composite состояние?
-                    if let Some(composite) = composite_states.get(state_name) {
-                        let sub_layout = composite_layouts.get(state_name).unwrap();
-                        
-                        // Создаём контейнер composite состояния
-                        let container_elements = self.create_composite_container(
-                            composite,
-                            x,
-                            y,
-                            sub_layout,
-                        );
-                        
-                        // Сохраняем позицию контейнера
-                        let container_rect = Rect::new(
-                            x,
-                            y,
-                            sub_layout.bounds.width + self.config.margin * 2.0,
-                            sub_layout.bounds.height + self.config.margin * 2.0 + 30.0,
-                        );
-                        state_positions.insert(state_name.clone(), container_rect.clone());
-                        
-                        // Добавляем все элементы
-                        elements.extend(container_elements);
-                        
-                        x += container_rect.width + self.config.horizontal_spacing;
-                    } else {
-                        // Обычное состояние
-                        let state_type = self.get_state_type_internal(diagram, state_name);
-                        let (elem, bounds) = self.create_state_element(state_name, state_type, x, y);
-                        state_positions.insert(state_name.clone(), bounds.clone());
-                        elements.push(elem);
-                        
-                        x += bounds.width + self.config.horizontal_spacing;
-                    }
+        // Располагаем состояния верхнего уровня алгоритмом Sugiyama:
+        // направление диаграммы и подсказки переходов (`-up->`, `-[hidden]->`)
+        // задают слои и порядок внутри слоя
+        let mut graph = Graph::new();
+        for name in &top_level_states {
+            let size = if let Some(layout) = composite_layouts.get(name) {
+                Size::new(
+                    layout.bounds.width + self.config.margin * 2.0,
+                    layout.bounds.height + self.config.margin * 2.0 + 30.0, // header
+                )
+            } else if name == INITIAL_STATE_ID || name == FINAL_STATE_ID {
+                Size::new(self.config.node_radius * 2.0, self.config.node_radius * 2.0)
+            } else {
+                Size::new(self.state_width(name), self.config.state_min_height)
+            };
+            graph.add_node(name.clone(), size);
+        }
+        for (from, to, transition) in &top_level_transitions {
+            if let (Some(from), Some(to)) = (top_level_states.get_index_of(from), top_level_states.get_index_of(to)) {
+                // Петли не влияют на размещение
+                if from != to {
+                    let edge = graph.add_edge(from, to);
+                    graph.edges[edge].direction = transition.direction;
                 }
             }
         }
 
-        // Создаём переходы верхнего уровня
-        for (from, to, label) in &top_level_transitions {
-            if let (Some(from_rect), Some(to_rect)) = 
-                (state_positions.get(from), state_positions.get(to)) 
-            {
-                let edge = self.create_transition_element(from, to, label.as_deref(), from_rect, to_rect);
-                elements.push(edge);
+        let sugiyama_config = ClassLayoutConfig {
+            margin: self.config.margin,
+            node_horizontal_spacing: self.config.horizontal_spacing,
+            layer_vertical_spacing: self.config.vertical_spacing,
+            min_class_height: 0.0,
+            ..ClassLayoutConfig::default()
+        };
+        let direction = diagram.metadata.direction;
+        SugiyamaLayout::new(&mut graph, &sugiyama_config)
+            .with_direction(direction)
+            .run();
+
+        for (state_name, node) in top_level_states.iter().zip(&graph.nodes) {
+            let (x, y) = (node.x, node.y);
+
+            // Проверяем, это composite состояние?
+            if let Some(composite) = composite_states.get(state_name) {
+                let sub_layout = composite_layouts.get(state_name).unwrap();
+
+                // Создаём контейнер composite состояния
+                let container_elements = self.create_composite_container(composite, x, y, sub_layout);
+
+                // Сохраняем позицию контейнера
+                let container_rect = Rect::new(x, y, node.size.width, node.size.height);
+                state_positions.insert(state_name.clone(), container_rect);
+
+                // Добавляем все элементы
+                elements.extend(container_elements);
+            } else {
+                // Обычное состояние
+                let state_type = self.get_state_type_internal(diagram, state_name);
+                let (elem, bounds) = self.create_state_element(state_name, state_type, x, y);
+                state_positions.insert(state_name.clone(), bounds);
+                let state = diagram.states.iter().find(|s| s.name == *state_name);
+                elements.push(
+                    elem.with_style(&state_style(state))
+                        .with_stereotype(state.and_then(|s| s.stereotype.as_ref())),
+                );
             }
         }
 
+        // Создаём переходы верхнего уровня; скрытые только влияли на layout
+        let visible: Vec<VisibleTransition<String>> = top_level_transitions
+            .iter()
+            .filter(|(_, _, transition)| !transition.hidden)
+            .filter_map(|(from, to, transition)| {
+                let (from_rect, to_rect) = (state_positions.get(from)?, state_positions.get(to)?);
+                Some((from, from_rect, to, to_rect, transition.label(), *transition))
+            })
+            .collect();
+        let specs: Vec<EdgeSpec> = visible
+            .iter()
+            .map(|(_, from_rect, _, to_rect, label, _)| EdgeSpec::new(**from_rect, **to_rect).with_label(Some(label)))
+            .collect();
+        let router = self.router(line_type, state_positions.values().copied());
+        for ((from, _, to, _, label, transition), route) in visible.iter().zip(router.route_all(&specs)) {
+            let mut edge = Self::create_transition_element("trans", from, to, label).with_style(&transition_style(transition));
+            route.apply(&mut edge);
+            elements.push(edge);
+        }
+
         // Вычисляем bounds
         let mut result = LayoutResult {
             elements,
@@ -268,7 +250,7 @@ impl StateLayoutEngine {
     }
 
     /// Выполняет layout содержимого composite состояния
-    fn layout_composite_content(&self, composite: &State) -> SubLayoutResult {
+    fn layout_composite_content(&self, composite: &State, line_type: LineType) -> SubLayoutResult {
         let mut elements = Vec::new();
         let mut state_positions: IndexMap<String, Rect> = IndexMap::new();
 
@@ -337,7 +319,12 @@ impl StateLayoutEngine {
         // Располагаем внутренние состояния
         let max_level = levels.values().max().copied().unwrap_or(0);
         let inner_margin = 15.0;
-        let inner_state_width = 90.0;
+        // Ширина внутренних состояний — по самому длинному имени
+        let inner_state_width = composite
+            .substates
+            .iter()
+            .map(|s| self.config.text.width(&s.name) + self.config.text_padding * 2.0)
+            .fold(90.0, f64::max);
         let inner_state_height = 35.0;
         let inner_spacing_v = 40.0;
         let inner_spacing_h = 30.0;
@@ -402,7 +389,11 @@ impl StateLayoutEngine {
                         state_name, state_type, x, y, inner_state_width, inner_state_height
                     );
                     state_positions.insert(state_name.clone(), bounds.clone());
-                    elements.push(elem);
+                    let state = composite.substates.iter().find(|s| s.name == *state_name);
+                    elements.push(
+                        elem.with_style(&state_style(state))
+                            .with_stereotype(state.and_then(|s| s.stereotype.as_ref())),
+                    );
                     
                     max_x = max_x.max(bounds.x + bounds.width);
                     max_y = max_y.max(bounds.y + bounds.height);
@@ -413,28 +404,6 @@ impl StateLayoutEngine {
         // Обновляем max_x с учётом пространства для обратных стрелок
         max_x += backward_space;
 
-        // Создаём внутренние переходы
-        // Считаем обратные переходы для уникального offset
-        let mut backward_transition_index = 0;
-        for (from, to, label) in &internal_transitions {
-            if let (Some(from_rect), Some(to_rect)) = 
-                (state_positions.get(from), state_positions.get(to)) 
-            {
-                let dy = (to_rect.y + to_rect.height / 2.0) - (from_rect.y + from_rect.height / 2.0);
-                let is_backward = dy < -20.0;
-                
-                let edge = self.create_inner_transition_indexed(
-                    from, to, label.as_deref(), from_rect, to_rect,
-                    if is_backward { backward_transition_index } else { 0 }
-                );
-                elements.push(edge);
-                
-                if is_backward {
-                    backward_transition_index += 1;
-                }
-            }
-        }
-
         // Общая ширина контента для возврата
         let total_content_width = max_x + inner_margin;
         
@@ -449,16 +418,32 @@ impl StateLayoutEngine {
         // Смещаем все элементы
         for elem in &mut elements {
             elem.bounds.x += center_offset;
-            
-            // Смещаем точки в Edge
-            if let ElementType::Edge { ref mut points, .. } = elem.element_type {
-                for point in points.iter_mut() {
-                    point.x += center_offset;
-                }
-            }
         }
-        
-        // Обновляем state_positions для корректных переходов (уже созданы, не нужно)
+        for rect in state_positions.values_mut() {
+            rect.x += center_offset;
+        }
+
+        // Создаём внутренние переходы по сдвинутым позициям
+        let visible: Vec<VisibleTransition<&Option<String>>> = internal_transitions
+            .iter()
+            .zip(&composite.internal_transitions)
+            .filter_map(|((from, to, label), transition)| {
+                Some((from, state_positions.get(from)?, to, state_positions.get(to)?, label, transition))
+            })
+            .collect();
+        let specs: Vec<EdgeSpec> = visible
+            .iter()
+            .map(|(_, from_rect, _, to_rect, label, _)| {
+                EdgeSpec::new(**from_rect, **to_rect).with_label(label.as_deref())
+            })
+            .collect();
+        let router = self.router(line_type, state_positions.values().copied());
+        for ((from, _, to, _, label, transition), route) in visible.iter().zip(router.route_all(&specs)) {
+            let mut edge = Self::create_transition_element("inner_trans", from, to, label.as_deref().unwrap_or(""))
+                .with_style(&transition_style(transition));
+            route.apply(&mut edge);
+            elements.push(edge);
+        }
         
         SubLayoutResult {
             elements,
@@ -494,7 +479,7 @@ impl StateLayoutEngine {
                 name: composite.name.clone(),
                 header_height,
             },
-        });
+        }.with_style(&state_style(Some(composite))).with_stereotype(composite.stereotype.as_ref()));
         
         // Смещаем все внутренние элементы
         let offset_x = x + padding;
@@ -508,13 +493,14 @@ impl StateLayoutEngine {
             // Обновляем id чтобы был уникальным
             shifted_elem.id = format!("{}_{}", composite.name, shifted_elem.id);
             
-            // Смещаем точки в Edge
+            // Смещаем точки в Edge и позиции подписей
             if let ElementType::Edge { ref mut points, .. } = shifted_elem.element_type {
                 for point in points.iter_mut() {
                     point.x += offset_x;
                     point.y += offset_y;
                 }
             }
+            translate_positions(&mut shifted_elem, offset_x, offset_y);
             
             elements.push(shifted_elem);
         }
@@ -578,95 +564,6 @@ impl StateLayoutEngine {
         }
     }
 
-    /// Создаёт внутренний переход с индексом для уникального offset
-    fn create_inner_transition_indexed(
-        &self,
-        from: &str,
-        to: &str,
-        label: Option<&str>,
-        from_rect: &Rect,
-        to_rect: &Rect,
-        backward_index: usize,
-    ) -> LayoutElement {
-        let from_center_x = from_rect.x + from_rect.width / 2.0;
-        let to_center_x = to_rect.x + to_rect.width / 2.0;
-        let from_center_y = from_rect.y + from_rect.height / 2.0;
-        let to_center_y = to_rect.y + to_rect.height / 2.0;
-
-        let dy = to_center_y - from_center_y;
-        let dx = to_center_x - from_center_x;
-        
-        // Обратный переход (вверх)?
-        let is_backward = dy < -20.0;
-        
-        let points = if is_backward {
-            // Обход справа с уникальным offset для каждого обратного перехода
-            // Стрелка выходит СПРАВА от исходного элемента, входит СПРАВА в целевой
-            // Но с вертикальным смещением чтобы не накладываться на другие стрелки
-            let base_offset = 15.0;
-            let offset = base_offset + backward_index as f64 * 20.0;
-            let right_x = from_rect.x.max(to_rect.x) + from_rect.width.max(to_rect.width) + offset;
-            
-            // Смещаем точки выхода и входа по вертикали, чтобы они не накладывались
-            // Выход: верхняя часть элемента (для обратного перехода)
-            // Вход: нижняя часть элемента
-            let from_y = from_rect.y + from_rect.height * 0.3; // верхняя треть
-            let to_y = to_rect.y + to_rect.height * 0.7; // нижняя треть
-            
-            vec![
-                Point::new(from_rect.x + from_rect.width, from_y),
-                Point::new(right_x, from_y),
-                Point::new(right_x, to_y),
-                Point::new(to_rect.x + to_rect.width, to_y),
-            ]
-        } else if dy > 10.0 {
-            // Переход вниз - прямой переход
-            // Выход снизу по центру, вход сверху по центру
-            vec![
-                Point::new(from_center_x, from_rect.y + from_rect.height),
-                Point::new(to_center_x, to_rect.y),
-            ]
-        } else {
-            // Горизонтальный или небольшой переход
-            if dx > 0.0 {
-                vec![
-                    Point::new(from_rect.x + from_rect.width, from_center_y),
-                    Point::new(to_rect.x, to_center_y),
-                ]
-            } else {
-                vec![
-                    Point::new(from_rect.x, from_center_y),
-                    Point::new(to_rect.x + to_rect.width, to_center_y),
-                ]
-            }
-        };
-
-        let min_x = points.iter().map(|p| p.x).fold(f64::INFINITY, f64::min);
-        let min_y = points.iter().map(|p| p.y).fold(f64::INFINITY, f64::min);
-        let max_x = points.iter().map(|p| p.x).fold(f64::NEG_INFINITY, f64::max);
-        let max_y = points.iter().map(|p| p.y).fold(f64::NEG_INFINITY, f64::max);
-        
-        let from_clean = from.replace(['[', ']', '*', '_'], "");
-        let to_clean = to.replace(['[', ']', '*', '_'], "");
-
-        LayoutElement {
-            id: format!("inner_trans_{}_{}", from_clean, to_clean),
-            bounds: Rect::new(min_x, min_y, (max_x - min_x).max(1.0), (max_y - min_y).max(1.0)),
-            text: None,
-            properties: std::collections::HashMap::new(),
-            element_type: ElementType::Edge {
-                points,
-                label: label.map(|s| s.to_string()),
-                arrow_start: false,
-                arrow_end: true,
-                dashed: false,
-                edge_type: EdgeType::Association,
-                from_cardinality: None,
-                to_cardinality: None,
-            },
-        }
-    }
-
     /// Назначает уровни состояниям
     fn assign_levels(
         &self,
@@ -831,9 +728,16 @@ impl StateLayoutEngine {
         }, bounds)
     }
 
+    /// Ширина простого состояния по имени, но не меньше стандартной
+    fn state_width(&self, name: &str) -> f64 {
+        self.config
+            .state_width
+            .max(self.config.text.width(name) + self.config.text_padding * 2.0)
+    }
+
     /// Создаёт простое состояние
     fn create_simple_state(&self, name: &str, x: f64, y: f64) -> (LayoutElement, Rect) {
-        let bounds = Rect::new(x, y, self.config.state_width, self.config.state_min_height);
+        let bounds = Rect::new(x, y, self.state_width(name), self.config.state_min_height);
         
         (LayoutElement {
             id: format!("state_{}", name),
@@ -910,69 +814,24 @@ impl StateLayoutEngine {
         }, bounds)
     }
 
-    /// Создаёт элемент перехода
-    fn create_transition_element(
-        &self,
-        from: &str,
-        to: &str,
-        label: Option<&str>,
-        from_rect: &Rect,
-        to_rect: &Rect,
-    ) -> LayoutElement {
-        let from_center_x = from_rect.x + from_rect.width / 2.0;
-        let to_center_x = to_rect.x + to_rect.width / 2.0;
-        let from_center_y = from_rect.y + from_rect.height / 2.0;
-        let to_center_y = to_rect.y + to_rect.height / 2.0;
-
-        let dy = to_center_y - from_center_y;
-        
-        let is_backward_transition = dy < -self.config.vertical_spacing * 0.5;
-        let is_to_small = to_rect.width < 30.0 && to_rect.height < 30.0;
-        let is_from_small = from_rect.width < 30.0 && from_rect.height < 30.0;
-        
-        let points = if is_backward_transition {
-            let offset = 50.0;
-            let right_x = from_rect.x.max(to_rect.x) + from_rect.width.max(to_rect.width) + offset;
-            
-            let start = Point::new(from_rect.x + from_rect.width, from_center_y);
-            let corner1 = Point::new(right_x, from_center_y);
-            let corner2 = Point::new(right_x, to_center_y);
-            let end = if is_to_small {
-                Point::new(to_center_x + to_rect.width / 2.0, to_center_y)
-            } else {
-                Point::new(to_rect.x + to_rect.width, to_center_y)
-            };
-            
-            vec![start, corner1, corner2, end]
-        } else if is_from_small && dy > 0.0 {
-            let start = Point::new(from_center_x, from_rect.y + from_rect.height);
-            let end = Point::new(to_center_x, to_rect.y);
-            vec![start, end]
-        } else if is_to_small && dy > 0.0 {
-            let start = Point::new(from_center_x, from_rect.y + from_rect.height);
-            let end = Point::new(to_center_x, to_rect.y);
-            vec![start, end]
-        } else {
-            let (start, end) = self.calculate_connection_points(from_rect, to_rect);
-            vec![start, end]
-        };
+    /// Маршрутизатор переходов в обход состояний
+    fn router(&self, line_type: LineType, obstacles: impl IntoIterator<Item = Rect>) -> Router {
+        Router::new(line_type, self.config.text.clone()).with_obstacles(obstacles)
+    }
 
-        let min_x = points.iter().map(|p| p.x).fold(f64::INFINITY, f64::min);
-        let min_y = points.iter().map(|p| p.y).fold(f64::INFINITY, f64::min);
-        let max_x = points.iter().map(|p| p.x).fold(f64::NEG_INFINITY, f64::max);
-        let max_y = points.iter().map(|p| p.y).fold(f64::NEG_INFINITY, f64::max);
-        
+    /// Создаёт элемент перехода; путь и подпись размещает маршрутизатор
+    fn create_transition_element(prefix: &str, from: &str, to: &str, label: &str) -> LayoutElement {
         let from_clean = from.replace(['[', ']', '*', '_'], "");
         let to_clean = to.replace(['[', ']', '*', '_'], "");
 
         LayoutElement {
-            id: format!("trans_{}_{}", from_clean, to_clean),
-            bounds: Rect::new(min_x, min_y, (max_x - min_x).max(1.0), (max_y - min_y).max(1.0)),
+            id: format!("{}_{}_{}", prefix, from_clean, to_clean),
+            bounds: Rect::new(0.0, 0.0, 0.0, 0.0),
             text: None,
             properties: std::collections::HashMap::new(),
             element_type: ElementType::Edge {
-                points,
-                label: label.map(|s| s.to_string()),
+                points: Vec::new(),
+                label: (!label.is_empty()).then(|| label.to_string()),
                 arrow_start: false,
                 arrow_end: true,
                 dashed: false,
@@ -982,57 +841,29 @@ impl StateLayoutEngine {
             },
         }
     }
+}
 
-    /// Вычисляет точки соединения
-    fn calculate_connection_points(&self, from: &Rect, to: &Rect) -> (Point, Point) {
-        let from_center_x = from.x + from.width / 2.0;
-        let to_center_x = to.x + to.width / 2.0;
-
-        let dx = to_center_x - from_center_x;
-        let dy = (to.y + to.height / 2.0) - (from.y + from.height / 2.0);
+/// Стиль состояния: `#pink;line:red;line.dashed;text:blue`
+fn state_style(state: Option<&State>) -> ElementStyle {
+    let Some(state) = state else {
+        return ElementStyle::new();
+    };
+    ElementStyle::from_inline(&InlineStyle {
+        fill: state.color.clone(),
+        line_color: state.border_color.clone(),
+        line_style: state.line_style,
+        text_color: state.text_color.clone(),
+        ..InlineStyle::default()
+    })
+}
 
-        if dy > 0.0 {
-            let is_to_small = to.width < 30.0 && to.height < 30.0;
-            
-            if is_to_small {
-                let from_x = if dx.abs() < 10.0 {
-                    from_center_x
-                } else if dx > 0.0 {
-                    from_center_x + from.width * 0.2
-                } else {
-                    from_center_x - from.width * 0.2
-                };
-                
-                let start = Point::new(from_x, from.y + from.height);
-                let end = Point::new(to_center_x, to.y);
-                (start, end)
-            } else {
-                let from_x = if dx.abs() < 10.0 {
-                    from_center_x
-                } else if dx > 0.0 {
-                    from_center_x + from.width * 0.2
-                } else {
-                    from_center_x - from.width * 0.2
-                };
-                
-                let to_x = if dx.abs() < 10.0 {
-                    to_center_x
-                } else if dx > 0.0 {
-                    to_center_x - to.width * 0.2
-                } else {
-                    to_center_x + to.width * 0.2
-                };
-                
-                let start = Point::new(from_x, from.y + from.height);
-                let end = Point::new(to_x, to.y);
-                (start, end)
-            }
-        } else {
-            let start = Point::new(from_center_x, from.y);
-            let end = Point::new(to_center_x, to.y + to.height);
-            (start, end)
-        }
-    }
+/// Стиль перехода: `-[#color]->`, `-[#color,dashed]->`
+fn transition_style(transition: &Transition) -> ElementStyle {
+    ElementStyle::from_inline(&InlineStyle {
+        line_color: transition.color.clone(),
+        line_style: transition.line_style,
+        ..InlineStyle::default()
+    })
 }
 
 impl Default for StateLayoutEngine {
@@ -1044,7 +875,6 @@ impl Default for StateLayoutEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use plantuml_ast::state::Transition;
 
     #[test]
     fn test_layout_simple_state_machine() {
@@ -1094,4 +924,26 @@ mod tests {
         
         assert!(!inactive_in_active, "Inactive не должен быть внутри Active");
     }
+
+    #[test]
+    fn test_left_to_right_direction() {
+        use plantuml_ast::common::Direction;
+
+        let mut diagram = StateDiagram::new();
+        diagram.metadata.direction = Direction::LeftToRight;
+        diagram.add_transition(Transition::new("[*]", "Idle"));
+        diagram.add_transition(Transition::new("Idle", "Running"));
+        let mut right = Transition::new("Running", "Error");
+        right.direction = Some(Direction::LeftToRight);
+        diagram.add_transition(right);
+
+        let result = StateLayoutEngine::new().layout(&diagram);
+        let bounds = |id: &str| result.elements.iter().find(|e| e.id == id).unwrap().bounds;
+
+        let (idle, running, error) = (bounds("state_Idle"), bounds("state_Running"), bounds("state_Error"));
+        assert!(running.x >= idle.x + idle.width);
+        // `-right->` при горизонтальном направлении — тот же слой, ниже
+        assert_eq!(error.x, running.x);
+        assert!(error.y >= running.y + running.height);
+    }
 }
@@ -0,0 +1,288 @@
+//! Стиль отдельного элемента: заливка, линия, текст и шрифт
+//!
+//! Layout engines переносят цвета из AST (`Message.color`, `State.color`,
+//! встроенный `#pink;line:red;line.dashed;text:blue`) в [`ElementStyle`] и
+//! сохраняют его в свойствах элемента (`LayoutElement::with_style`).
+//! Рендерер читает стиль обратно (`LayoutElement::style`) и накладывает его
+//! поверх темы.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use plantuml_ast::common::{Color, InlineStyle, LineStyle};
+
+/// Свойство `LayoutElement`: заливка (цвет или градиент `red/blue`)
+pub const PROPERTY_FILL: &str = "fill";
+/// Свойство `LayoutElement`: цвет линии
+pub const PROPERTY_STROKE: &str = "stroke";
+/// Свойство `LayoutElement`: толщина линии
+pub const PROPERTY_STROKE_WIDTH: &str = "stroke-width";
+/// Свойство `LayoutElement`: стиль линии (`dashed`, `dotted`, `bold`)
+pub const PROPERTY_LINE_STYLE: &str = "line-style";
+/// Свойство `LayoutElement`: цвет текста
+pub const PROPERTY_TEXT_COLOR: &str = "text-color";
+/// Свойство `LayoutElement`: семейство шрифта
+pub const PROPERTY_FONT_FAMILY: &str = "font-family";
+/// Свойство `LayoutElement`: размер шрифта
+pub const PROPERTY_FONT_SIZE: &str = "font-size";
+
+/// Направление градиента заливки
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GradientDirection {
+    /// Слева направо: `#red|blue`
+    Horizontal,
+    /// Сверху вниз: `#red-blue`
+    Vertical,
+    /// Из левого верхнего угла в правый нижний: `#red/blue`
+    Diagonal,
+    /// Из левого нижнего угла в правый верхний: `#red\blue`
+    ReverseDiagonal,
+}
+
+impl GradientDirection {
+    /// Разделитель цветов в спецификации
+    fn separator(self) -> char {
+        match self {
+            Self::Horizontal => '|',
+            Self::Vertical => '-',
+            Self::Diagonal => '/',
+            Self::ReverseDiagonal => '\\',
+        }
+    }
+
+    /// Координаты `x1`, `y1`, `x2`, `y2` для `<linearGradient>` в долях фигуры
+    pub fn vector(self) -> (f64, f64, f64, f64) {
+        match self {
+            Self::Horizontal => (0.0, 0.0, 1.0, 0.0),
+            Self::Vertical => (0.0, 0.0, 0.0, 1.0),
+            Self::Diagonal => (0.0, 0.0, 1.0, 1.0),
+            Self::ReverseDiagonal => (0.0, 1.0, 1.0, 0.0),
+        }
+    }
+}
+
+/// Заливка: цвет или двухцветный градиент
+#[derive(Debug, Clone, PartialEq)]
+pub enum Paint {
+    /// Сплошной цвет (CSS)
+    Solid(String),
+    /// Линейный градиент между двумя цветами (CSS)
+    Gradient {
+        from: String,
+        to: String,
+        direction: GradientDirection,
+    },
+}
+
+impl Paint {
+    /// Разбирает `red`, `#FF0000`, `red/blue`, `#red|#blue` и т.п.
+    pub fn parse(spec: &str) -> Option<Self> {
+        let spec = spec.trim();
+        if spec.is_empty() {
+            return None;
+        }
+        let css = |color: &str| Color::parse(color.trim()).to_css();
+
+        let directions = [
+            GradientDirection::Diagonal,
+            GradientDirection::Horizontal,
+            GradientDirection::ReverseDiagonal,
+            GradientDirection::Vertical,
+        ];
+        for direction in directions {
+            if let Some((from, to)) = spec.split_once(direction.separator()) {
+                if !from.trim().is_empty() && !to.trim().is_empty() {
+                    return Some(Self::Gradient {
+                        from: css(from),
+                        to: css(to),
+                        direction,
+                    });
+                }
+            }
+        }
+        // `rgb(...)` и уже готовые CSS значения сохраняются как есть
+        if spec.contains('(') {
+            return Some(Self::Solid(spec.to_string()));
+        }
+        Some(Self::Solid(css(spec)))
+    }
+}
+
+impl fmt::Display for Paint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Solid(color) => write!(f, "{}", color),
+            Self::Gradient {
+                from,
+                to,
+                direction,
+            } => write!(f, "{}{}{}", from, direction.separator(), to),
+        }
+    }
+}
+
+/// Стиль элемента; пустые поля берутся из темы
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ElementStyle {
+    /// Заливка
+    pub fill: Option<Paint>,
+    /// Цвет линии (CSS)
+    pub stroke: Option<String>,
+    /// Толщина линии; заменяет `Theme::line_width`
+    pub stroke_width: Option<f64>,
+    /// Стиль линии
+    pub line_style: Option<LineStyle>,
+    /// Цвет текста (CSS)
+    pub text_color: Option<String>,
+    /// Семейство шрифта
+    pub font_family: Option<String>,
+    /// Размер шрифта
+    pub font_size: Option<f64>,
+}
+
+impl ElementStyle {
+    /// Пустой стиль
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Стиль из встроенной спецификации AST
+    pub fn from_inline(inline: &InlineStyle) -> Self {
+        let mut style = Self::new()
+            .with_fill(inline.fill.as_ref())
+            .with_stroke(inline.line_color.as_ref());
+        style.line_style = inline.line_style;
+        style.stroke_width = inline.line_thickness;
+        style.with_text_color(inline.text_color.as_ref())
+    }
+
+    /// Задаёт заливку, если цвет указан
+    pub fn with_fill(mut self, color: Option<&Color>) -> Self {
+        if let Some(color) = color {
+            self.fill = Paint::parse(&color.to_css());
+        }
+        self
+    }
+
+    /// Задаёт цвет линии, если он указан
+    pub fn with_stroke(mut self, color: Option<&Color>) -> Self {
+        if let Some(color) = color {
+            self.stroke = Some(color.to_css());
+        }
+        self
+    }
+
+    /// Задаёт цвет текста, если он указан
+    pub fn with_text_color(mut self, color: Option<&Color>) -> Self {
+        if let Some(color) = color {
+            self.text_color = Some(color.to_css());
+        }
+        self
+    }
+
+    /// Задаёт стиль линии
+    pub fn with_line_style(mut self, line_style: LineStyle) -> Self {
+        self.line_style = Some(line_style);
+        self
+    }
+
+    /// Дополняет стиль полями `other`, которые заданы в нём
+    pub fn merge(mut self, other: &ElementStyle) -> Self {
+        let other = other.clone();
+        self.fill = other.fill.or(self.fill);
+        self.stroke = other.stroke.or(self.stroke);
+        self.stroke_width = other.stroke_width.or(self.stroke_width);
+        self.line_style = other.line_style.or(self.line_style);
+        self.text_color = other.text_color.or(self.text_color);
+        self.font_family = other.font_family.or(self.font_family);
+        self.font_size = other.font_size.or(self.font_size);
+        self
+    }
+
+    /// Пустой ли стиль
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Записывает заданные поля в свойства элемента
+    pub(crate) fn write(&self, properties: &mut HashMap<String, String>) {
+        let mut set = |key: &str, value: Option<String>| {
+            if let Some(value) = value {
+                properties.insert(key.to_string(), value);
+            }
+        };
+        set(PROPERTY_FILL, self.fill.as_ref().map(Paint::to_string));
+        set(PROPERTY_STROKE, self.stroke.clone());
+        set(PROPERTY_STROKE_WIDTH, self.stroke_width.map(|width| width.to_string()));
+        set(PROPERTY_LINE_STYLE, self.line_style.map(|style| line_style_name(style).to_string()));
+        set(PROPERTY_TEXT_COLOR, self.text_color.clone());
+        set(PROPERTY_FONT_FAMILY, self.font_family.clone());
+        set(PROPERTY_FONT_SIZE, self.font_size.map(|size| size.to_string()));
+    }
+
+    /// Читает стиль из свойств элемента
+    pub(crate) fn read(properties: &HashMap<String, String>) -> Self {
+        let get = |key: &str| properties.get(key).cloned();
+        Self {
+            fill: properties.get(PROPERTY_FILL).and_then(|fill| Paint::parse(fill)),
+            stroke: get(PROPERTY_STROKE),
+            stroke_width: properties.get(PROPERTY_STROKE_WIDTH).and_then(|width| width.parse().ok()),
+            line_style: properties.get(PROPERTY_LINE_STYLE).and_then(|style| LineStyle::parse(style)),
+            text_color: get(PROPERTY_TEXT_COLOR),
+            font_family: get(PROPERTY_FONT_FAMILY),
+            font_size: properties.get(PROPERTY_FONT_SIZE).and_then(|size| size.parse().ok()),
+        }
+    }
+}
+
+/// Имя стиля линии для свойства `line-style`
+fn line_style_name(style: LineStyle) -> &'static str {
+    match style {
+        LineStyle::Solid => "solid",
+        LineStyle::Dashed => "dashed",
+        LineStyle::Dotted => "dotted",
+        LineStyle::Bold => "bold",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paint_parse() {
+        assert_eq!(Paint::parse("#FF0000"), Some(Paint::Solid("#FF0000".into())));
+        assert_eq!(Paint::parse("LightBlue"), Some(Paint::Solid("LightBlue".into())));
+        assert_eq!(
+            Paint::parse("red/blue"),
+            Some(Paint::Gradient {
+                from: "red".into(),
+                to: "blue".into(),
+                direction: GradientDirection::Diagonal,
+            })
+        );
+        assert_eq!(
+            Paint::parse("FF0000|#00F"),
+            Some(Paint::Gradient {
+                from: "#FF0000".into(),
+                to: "#00F".into(),
+                direction: GradientDirection::Horizontal,
+            })
+        );
+        assert_eq!(Paint::parse(""), None);
+    }
+
+    #[test]
+    fn test_style_properties_roundtrip() {
+        let inline = InlineStyle::parse("#red\\blue;line:green;line.dotted;text:white");
+        let style = ElementStyle::from_inline(&inline);
+        assert_eq!(style.stroke.as_deref(), Some("green"));
+        assert_eq!(style.line_style, Some(LineStyle::Dotted));
+
+        let mut properties = HashMap::new();
+        style.write(&mut properties);
+        assert_eq!(properties.get(PROPERTY_FILL).map(String::as_str), Some("red\\blue"));
+        assert_eq!(ElementStyle::read(&properties), style);
+        assert!(ElementStyle::read(&HashMap::new()).is_empty());
+    }
+}
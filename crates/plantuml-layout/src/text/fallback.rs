@@ -0,0 +1,206 @@
+//! Детерминированный измеритель текста без загрузки шрифтов
+//!
+//! Ширины латиницы и кириллицы взяты из таблиц advance width DejaVu Sans —
+//! того же шрифта, что встроен в [`FontMeasurer`](super::FontMeasurer).
+//! Поэтому включение feature `font-metrics` не меняет размеры подписей из
+//! этих символов. Для остальных символов используется оценка по классу:
+//! широкие (CJK, полноширинные формы) — 1 em, прочие — 0.6 em.
+
+use super::{TextMeasurer, LINE_HEIGHT_EM};
+
+/// Единиц на em в таблицах ширин
+const UNITS_PER_EM: f64 = 2048.0;
+
+/// Первый символ латинской таблицы
+const LATIN_START: u32 = 0x20;
+/// Число символов латинской таблицы
+const LATIN_LEN: usize = 0x160;
+/// Первый символ кириллической таблицы
+const CYRILLIC_START: u32 = 0x400;
+/// Число символов кириллической таблицы
+const CYRILLIC_LEN: usize = 0x60;
+
+/// Ширина широкого символа (CJK, полноширинные формы), em
+const WIDE_EM: f64 = 1.0;
+/// Ширина символа, отсутствующего в таблицах, em
+const DEFAULT_EM: f64 = 0.6;
+
+/// Измеритель по встроенным таблицам ширин
+///
+/// Не зависит от окружения, поэтому даёт одинаковые размеры на любой
+/// платформе, включая WASM.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FallbackMeasurer;
+
+impl FallbackMeasurer {
+    /// Создаёт измеритель
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl TextMeasurer for FallbackMeasurer {
+    fn line_width(&self, line: &str, font_size: f64, bold: bool) -> f64 {
+        line.chars().map(|ch| advance_em(ch, bold)).sum::<f64>() * font_size
+    }
+
+    fn line_height(&self, font_size: f64) -> f64 {
+        font_size * LINE_HEIGHT_EM
+    }
+}
+
+/// Ширина символа в em
+pub(crate) fn advance_em(ch: char, bold: bool) -> f64 {
+    table_advance(ch, bold).unwrap_or_else(|| estimate_em(ch))
+}
+
+/// Ширина символа из таблицы DejaVu Sans (если он там есть)
+fn table_advance(ch: char, bold: bool) -> Option<f64> {
+    let code = ch as u32;
+    let (latin, cyrillic) = if bold {
+        (&BOLD_LATIN, &BOLD_CYRILLIC)
+    } else {
+        (&REGULAR_LATIN, &REGULAR_CYRILLIC)
+    };
+
+    let units = if (LATIN_START..LATIN_START + LATIN_LEN as u32).contains(&code) {
+        latin[(code - LATIN_START) as usize]
+    } else if (CYRILLIC_START..CYRILLIC_START + CYRILLIC_LEN as u32).contains(&code) {
+        cyrillic[(code - CYRILLIC_START) as usize]
+    } else {
+        0
+    };
+
+    (units > 0).then(|| units as f64 / UNITS_PER_EM)
+}
+
+/// Оценка ширины символа по его классу, em
+pub(crate) fn estimate_em(ch: char) -> f64 {
+    if ch.is_control() || is_zero_width(ch) {
+        0.0
+    } else if is_wide(ch) {
+        WIDE_EM
+    } else {
+        DEFAULT_EM
+    }
+}
+
+/// Символы нулевой ширины (комбинируемые знаки, ZWJ, селекторы вариантов)
+fn is_zero_width(ch: char) -> bool {
+    matches!(
+        ch as u32,
+        0x0300..=0x036F | 0x200B..=0x200F | 0xFE00..=0xFE0F
+    )
+}
+
+/// Широкие символы по East Asian Width (упрощённо)
+fn is_wide(ch: char) -> bool {
+    matches!(
+        ch as u32,
+        0x1100..=0x115F
+            | 0x2E80..=0xA4CF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6
+            | 0x1F300..=0x1F64F
+            | 0x1F900..=0x1F9FF
+            | 0x20000..=0x3FFFD
+    )
+}
+
+/// Ширины DejaVu Sans для U+0020..U+017F (Basic Latin, Latin-1, Latin Extended-A)
+const REGULAR_LATIN: [u16; LATIN_LEN] = [
+    651, 821, 942, 1716, 1303, 1946, 1597, 563, 799, 799, 1024, 1716, 651, 739, 651, 690,
+    1303, 1303, 1303, 1303, 1303, 1303, 1303, 1303, 1303, 1303, 690, 690, 1716, 1716, 1716, 1087,
+    2048, 1401, 1405, 1430, 1577, 1294, 1178, 1587, 1540, 604, 604, 1343, 1141, 1767, 1532, 1612,
+    1235, 1612, 1423, 1300, 1251, 1499, 1401, 2025, 1403, 1251, 1403, 799, 690, 799, 1716, 1024,
+    1024, 1255, 1300, 1126, 1300, 1260, 721, 1300, 1298, 569, 569, 1186, 569, 1995, 1298, 1253,
+    1300, 1300, 842, 1067, 803, 1298, 1212, 1675, 1212, 1212, 1075, 1303, 690, 1303, 1716, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    651, 821, 1303, 1303, 1303, 1303, 690, 1024, 1024, 2048, 965, 1253, 1716, 739, 2048, 1024,
+    1024, 1716, 821, 821, 1024, 1303, 1303, 651, 1024, 821, 965, 1253, 1985, 1985, 1985, 1087,
+    1401, 1401, 1401, 1401, 1401, 1401, 1995, 1430, 1294, 1294, 1294, 1294, 604, 604, 604, 604,
+    1587, 1532, 1612, 1612, 1612, 1612, 1612, 1716, 1612, 1499, 1499, 1499, 1499, 1251, 1239, 1290,
+    1255, 1255, 1255, 1255, 1255, 1255, 2011, 1126, 1260, 1260, 1260, 1260, 569, 569, 569, 569,
+    1253, 1298, 1253, 1253, 1253, 1253, 1253, 1716, 1253, 1298, 1298, 1298, 1298, 1212, 1300, 1212,
+    1401, 1255, 1401, 1255, 1401, 1255, 1430, 1126, 1430, 1126, 1430, 1126, 1430, 1126, 1577, 1300,
+    1587, 1300, 1294, 1260, 1294, 1260, 1294, 1260, 1294, 1260, 1294, 1260, 1587, 1300, 1587, 1300,
+    1587, 1300, 1587, 1300, 1540, 1298, 1876, 1423, 604, 569, 604, 569, 604, 569, 604, 569,
+    604, 569, 1208, 1138, 604, 569, 1343, 1186, 1186, 1141, 569, 1141, 569, 1141, 768, 1141,
+    700, 1151, 582, 1532, 1298, 1532, 1298, 1532, 1298, 1666, 1532, 1298, 1612, 1253, 1612, 1253,
+    1612, 1253, 2191, 2095, 1423, 842, 1423, 842, 1423, 842, 1300, 1067, 1300, 1067, 1300, 1067,
+    1300, 1067, 1251, 803, 1251, 803, 1251, 803, 1499, 1298, 1499, 1298, 1499, 1298, 1499, 1298,
+    1499, 1298, 1499, 1298, 2025, 1675, 1251, 1212, 1251, 1403, 1075, 1403, 1075, 1403, 1075, 721,
+];
+
+/// Ширины DejaVu Sans для U+0400..U+045F (кириллица)
+const REGULAR_CYRILLIC: [u16; CYRILLIC_LEN] = [
+    1294, 1294, 1610, 1249, 1430, 1300, 604, 604, 604, 2240, 2140, 1610, 1454, 1532, 1248, 1540,
+    1401, 1405, 1405, 1249, 1600, 1294, 2206, 1313, 1532, 1532, 1454, 1540, 1767, 1540, 1612, 1540,
+    1235, 1430, 1251, 1248, 1763, 1403, 1590, 1404, 2190, 2240, 1705, 1807, 1405, 1430, 2211, 1423,
+    1255, 1263, 1207, 1076, 1416, 1260, 1845, 1089, 1331, 1331, 1237, 1309, 1545, 1339, 1253, 1339,
+    1300, 1126, 1193, 1212, 1751, 1212, 1394, 1210, 1874, 1929, 1447, 1617, 1207, 1124, 1724, 1232,
+    1260, 1260, 1280, 1076, 1124, 1067, 569, 569, 569, 1848, 1840, 1335, 1237, 1331, 1212, 1339,
+];
+
+/// Ширины DejaVu Sans Bold для U+0020..U+017F (Basic Latin, Latin-1, Latin Extended-A)
+const BOLD_LATIN: [u16; LATIN_LEN] = [
+    713, 934, 1067, 1716, 1425, 2052, 1786, 627, 936, 936, 1071, 1716, 778, 850, 778, 748,
+    1425, 1425, 1425, 1425, 1425, 1425, 1425, 1425, 1425, 1425, 819, 819, 1716, 1716, 1716, 1188,
+    2048, 1585, 1561, 1503, 1700, 1399, 1399, 1681, 1714, 762, 762, 1587, 1305, 2038, 1714, 1741,
+    1501, 1741, 1577, 1475, 1397, 1663, 1585, 2259, 1579, 1483, 1485, 936, 748, 936, 1716, 1024,
+    1024, 1382, 1466, 1214, 1466, 1389, 891, 1466, 1458, 702, 702, 1362, 702, 2134, 1458, 1407,
+    1466, 1466, 1010, 1219, 979, 1458, 1335, 1892, 1321, 1335, 1192, 1458, 748, 1458, 1716, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    713, 934, 1425, 1425, 1303, 1425, 748, 1024, 1024, 2048, 1155, 1323, 1716, 850, 2048, 1024,
+    1024, 1716, 897, 897, 1024, 1507, 1303, 778, 1024, 897, 1155, 1323, 2120, 2120, 2120, 1188,
+    1585, 1585, 1585, 1585, 1585, 1585, 2222, 1503, 1399, 1399, 1399, 1399, 762, 762, 762, 762,
+    1716, 1714, 1741, 1741, 1741, 1741, 1741, 1716, 1741, 1663, 1663, 1663, 1663, 1483, 1511, 1473,
+    1382, 1382, 1382, 1382, 1382, 1382, 2146, 1214, 1389, 1389, 1389, 1389, 702, 702, 702, 702,
+    1407, 1458, 1407, 1407, 1407, 1407, 1407, 1716, 1407, 1458, 1458, 1458, 1458, 1335, 1466, 1335,
+    1585, 1382, 1585, 1382, 1585, 1382, 1503, 1214, 1503, 1214, 1503, 1214, 1503, 1214, 1700, 1466,
+    1716, 1466, 1399, 1389, 1399, 1389, 1399, 1389, 1399, 1389, 1399, 1389, 1681, 1466, 1681, 1466,
+    1681, 1466, 1681, 1466, 1714, 1458, 1994, 1618, 762, 702, 762, 702, 762, 702, 762, 702,
+    762, 702, 1524, 1404, 762, 702, 1587, 1362, 1362, 1305, 702, 1305, 702, 1305, 982, 1305,
+    1140, 1315, 760, 1714, 1458, 1714, 1458, 1714, 1458, 2013, 1714, 1458, 1741, 1407, 1741, 1407,
+    1741, 1407, 2390, 2241, 1577, 1010, 1577, 1010, 1577, 1010, 1475, 1219, 1475, 1219, 1475, 1219,
+    1475, 1219, 1397, 979, 1397, 979, 1397, 979, 1663, 1458, 1663, 1458, 1663, 1458, 1663, 1458,
+    1663, 1458, 1663, 1458, 2259, 1892, 1483, 1335, 1483, 1485, 1192, 1485, 1192, 1485, 1192, 891,
+];
+
+/// Ширины DejaVu Sans Bold для U+0400..U+045F (кириллица)
+const BOLD_CYRILLIC: [u16; CYRILLIC_LEN] = [
+    1399, 1399, 1799, 1305, 1503, 1475, 762, 762, 762, 2364, 2314, 1799, 1674, 1714, 1579, 1714,
+    1585, 1561, 1561, 1305, 1824, 1399, 2507, 1455, 1714, 1714, 1674, 1701, 2038, 1714, 1741, 1714,
+    1501, 1503, 1397, 1579, 2031, 1579, 1900, 1655, 2530, 2715, 1924, 2122, 1561, 1503, 2404, 1577,
+    1382, 1430, 1296, 1070, 1654, 1389, 2038, 1190, 1435, 1435, 1390, 1500, 1674, 1415, 1407, 1415,
+    1466, 1214, 1187, 1335, 2032, 1321, 1518, 1406, 2175, 2264, 1539, 1852, 1295, 1214, 1991, 1315,
+    1389, 1389, 1462, 1070, 1214, 1219, 702, 702, 702, 2030, 1958, 1504, 1390, 1435, 1335, 1415,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_widths() {
+        let m = FallbackMeasurer::new();
+        // Цифры в DejaVu Sans моноширинные: 1303 / 2048 em
+        let digit = 1303.0 / 2048.0 * 10.0;
+        assert!((m.line_width("0", 10.0, false) - digit).abs() < 1e-9);
+        assert!(m.line_width("W", 14.0, false) > m.line_width("i", 14.0, false));
+        assert!(m.line_width("Ж", 14.0, true) > m.line_width("Ж", 14.0, false));
+    }
+
+    #[test]
+    fn test_estimated_widths() {
+        let m = FallbackMeasurer::new();
+        assert_eq!(m.line_width("中文", 10.0, false), 20.0);
+        assert_eq!(m.line_width("α", 10.0, false), 6.0);
+        assert_eq!(m.line_width("\u{200B}", 10.0, false), 0.0);
+    }
+}
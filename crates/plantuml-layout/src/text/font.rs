@@ -0,0 +1,173 @@
+//! Измеритель текста на базе `ab_glyph`
+//!
+//! По умолчанию использует встроенный DejaVu Sans (см. `fonts/LICENSE`).
+//! При feature `system-fonts` умеет загружать установленные шрифты через
+//! `fontdb`.
+
+use std::fmt;
+
+use ab_glyph::{Font, FontArc};
+
+use super::{fallback, TextMeasurer};
+
+/// Встроенный DejaVu Sans
+const BUNDLED_REGULAR: &[u8] = include_bytes!("../../fonts/DejaVuSans.ttf");
+/// Встроенный DejaVu Sans Bold
+const BUNDLED_BOLD: &[u8] = include_bytes!("../../fonts/DejaVuSans-Bold.ttf");
+
+/// Измеритель по метрикам TrueType/OpenType шрифта
+///
+/// Кернинг не применяется — как и в PlantUML. Символы, которых нет в
+/// шрифте, оцениваются так же, как в [`FallbackMeasurer`](super::FallbackMeasurer).
+#[derive(Clone)]
+pub struct FontMeasurer {
+    name: String,
+    regular: FontArc,
+    bold: FontArc,
+}
+
+impl fmt::Debug for FontMeasurer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FontMeasurer").field("name", &self.name).finish()
+    }
+}
+
+impl FontMeasurer {
+    /// Измеритель со встроенным DejaVu Sans
+    pub fn bundled() -> Self {
+        Self {
+            name: "DejaVu Sans".to_string(),
+            regular: FontArc::try_from_slice(BUNDLED_REGULAR).expect("встроенный шрифт"),
+            bold: FontArc::try_from_slice(BUNDLED_BOLD).expect("встроенный шрифт"),
+        }
+    }
+
+    /// Измеритель по данным шрифтовых файлов
+    ///
+    /// Без полужирного начертания ширины bold текста берутся из обычного.
+    /// Возвращает `None`, если данные не являются шрифтом.
+    pub fn from_data(name: impl Into<String>, regular: Vec<u8>, bold: Option<Vec<u8>>) -> Option<Self> {
+        let regular = FontArc::try_from_vec(regular).ok()?;
+        let bold = match bold {
+            Some(data) => FontArc::try_from_vec(data).ok()?,
+            None => regular.clone(),
+        };
+        Some(Self {
+            name: name.into(),
+            regular,
+            bold,
+        })
+    }
+
+    /// Имя семейства шрифта
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Ширина символа в em
+    fn advance_em(&self, ch: char, bold: bool) -> f64 {
+        let font = if bold { &self.bold } else { &self.regular };
+        let glyph = font.glyph_id(ch);
+        if glyph.0 == 0 {
+            return fallback::estimate_em(ch);
+        }
+        let units_per_em = font.units_per_em().unwrap_or(2048.0) as f64;
+        font.h_advance_unscaled(glyph) as f64 / units_per_em
+    }
+}
+
+impl TextMeasurer for FontMeasurer {
+    fn line_width(&self, line: &str, font_size: f64, bold: bool) -> f64 {
+        line.chars().map(|ch| self.advance_em(ch, bold)).sum::<f64>() * font_size
+    }
+
+    fn line_height(&self, font_size: f64) -> f64 {
+        let font = &self.regular;
+        let units_per_em = font.units_per_em().unwrap_or(2048.0) as f64;
+        let height = font.ascent_unscaled() - font.descent_unscaled() + font.line_gap_unscaled();
+        height as f64 / units_per_em * font_size
+    }
+}
+
+/// Измеритель для установленного шрифта (с кэшем по имени семейства)
+#[cfg(feature = "system-fonts")]
+pub(crate) fn system_measurer(family: &str) -> Option<super::SharedMeasurer> {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex, OnceLock};
+
+    static CACHE: OnceLock<Mutex<HashMap<String, Option<super::SharedMeasurer>>>> = OnceLock::new();
+
+    let key = family.to_lowercase();
+    let cache = CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some(cached) = cache.lock().ok()?.get(&key) {
+        return cached.clone();
+    }
+
+    let loaded = load_system_font(family).map(|m| Arc::new(m) as super::SharedMeasurer);
+    cache.lock().ok()?.insert(key, loaded.clone());
+    loaded
+}
+
+/// Загружает обычное и полужирное начертание установленного семейства
+#[cfg(feature = "system-fonts")]
+fn load_system_font(family: &str) -> Option<FontMeasurer> {
+    use fontdb::{Database, Family, Query, Weight};
+    use std::sync::OnceLock;
+
+    static DATABASE: OnceLock<Database> = OnceLock::new();
+    let db = DATABASE.get_or_init(|| {
+        let mut db = Database::new();
+        db.load_system_fonts();
+        db
+    });
+
+    // fontdb сравнивает имена точно — находим каноническое написание
+    let name = db
+        .faces()
+        .flat_map(|face| face.families.iter())
+        .map(|(name, _)| name)
+        .find(|name| name.eq_ignore_ascii_case(family))?
+        .clone();
+
+    let load = |weight: Weight| {
+        let id = db.query(&Query {
+            families: &[Family::Name(&name)],
+            weight,
+            ..Query::default()
+        })?;
+        db.with_face_data(id, |data, index| {
+            ab_glyph::FontVec::try_from_vec_and_index(data.to_vec(), index).ok()
+        })?
+        .map(FontArc::new)
+    };
+
+    let regular = load(Weight::NORMAL)?;
+    let bold = load(Weight::BOLD).unwrap_or_else(|| regular.clone());
+    Some(FontMeasurer { name, regular, bold })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::FallbackMeasurer;
+
+    #[test]
+    fn test_bundled_line_height() {
+        let m = FontMeasurer::bundled();
+        let expected = FallbackMeasurer::new().line_height(14.0);
+        assert!((m.line_height(14.0) - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_bundled_beyond_tables() {
+        // Греческие буквы есть в DejaVu Sans, но не в таблицах fallback
+        let m = FontMeasurer::bundled();
+        let width = m.line_width("Ω", 10.0, false);
+        assert!(width > 0.0 && width != 6.0);
+    }
+
+    #[test]
+    fn test_from_data_rejects_garbage() {
+        assert!(FontMeasurer::from_data("x", vec![1, 2, 3], None).is_none());
+    }
+}
@@ -0,0 +1,373 @@
+//! Раскладка подписей с creole разметкой и спрайтами `<$name>`
+//!
+//! Общая для layout engines (размеры) и рендерера (позиции частей), чтобы
+//! оформленный текст, списки, таблицы и спрайты занимали ровно то место,
+//! которое под них выделено.
+
+use plantuml_ast::creole::{parse_line, CreoleLine, Inline, TableCell, TextStyle};
+use plantuml_ast::sprite::{Sprite, SpriteRef};
+
+use super::{text_lines, TextMetrics};
+
+/// Ширина символа моноширинного шрифта (DejaVu Sans Mono), em
+const MONOSPACE_ADVANCE_EM: f64 = 0.602;
+
+/// Отступ уровня вложенности списка
+const LIST_INDENT: f64 = 12.0;
+
+/// Горизонтальный отступ текста в ячейке таблицы
+pub const CELL_PADDING_X: f64 = 4.0;
+
+/// Вертикальный отступ текста в ячейке таблицы
+pub const CELL_PADDING_Y: f64 = 2.0;
+
+/// Часть строки подписи
+#[derive(Debug, Clone, PartialEq)]
+pub enum LabelSpan<'a> {
+    /// Текст одного стиля
+    Text {
+        text: String,
+        style: TextStyle,
+        /// Размер шрифта с учётом `<size:N>`
+        font_size: f64,
+        width: f64,
+    },
+    /// Спрайт и его размер с учётом масштаба
+    Sprite {
+        sprite: &'a Sprite,
+        reference: SpriteRef,
+        width: f64,
+        height: f64,
+    },
+}
+
+/// Ячейка строки таблицы
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabelCell<'a> {
+    /// Содержимое ячейки
+    pub spans: Vec<LabelSpan<'a>>,
+    /// Ячейка заголовка (`|=`)
+    pub header: bool,
+    /// Смещение ячейки от начала строки
+    pub x: f64,
+    /// Ширина столбца, общая для всех строк таблицы
+    pub width: f64,
+}
+
+/// Вид строки подписи
+#[derive(Debug, Clone, PartialEq)]
+pub enum LabelLineKind<'a> {
+    /// Текст, в том числе пункт списка с маркером в первой части
+    Text,
+    /// Горизонтальная линия на всю ширину подписи
+    Rule,
+    /// Строка таблицы
+    TableRow(Vec<LabelCell<'a>>),
+}
+
+/// Строка подписи
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabelLine<'a> {
+    /// Вид строки
+    pub kind: LabelLineKind<'a>,
+    /// Части текстовой строки слева направо
+    pub spans: Vec<LabelSpan<'a>>,
+    /// Отступ слева (пункты вложенных списков)
+    pub indent: f64,
+    /// Ширина строки с отступом
+    pub width: f64,
+    /// Высота строки: самый крупный текст или самый высокий спрайт
+    pub height: f64,
+}
+
+impl TextMetrics {
+    /// Раскладывает подпись на строки из оформленного текста и спрайтов
+    ///
+    /// Ссылки на неизвестные спрайты остаются текстом. Столбцы идущих подряд
+    /// строк таблицы выравниваются по самой широкой ячейке.
+    pub fn label_lines<'a>(&'a self, text: &str, font_size: f64, bold: bool) -> Vec<LabelLine<'a>> {
+        let text_height = self.measurer().line_height(font_size);
+        let base = TextStyle {
+            bold,
+            ..TextStyle::default()
+        };
+        let mut numbers: Vec<usize> = Vec::new();
+
+        let mut lines: Vec<LabelLine<'a>> = text_lines(text)
+            .into_iter()
+            .map(|line| match parse_line(line) {
+                CreoleLine::Text(inlines) => {
+                    numbers.clear();
+                    self.text_line(self.spans(&inlines, font_size, &base), 0.0, text_height)
+                }
+                CreoleLine::ListItem {
+                    ordered,
+                    level,
+                    content,
+                } => {
+                    numbers.resize(level, 0);
+                    numbers[level - 1] += 1;
+                    let marker = if ordered {
+                        format!("{}. ", numbers[level - 1])
+                    } else {
+                        "• ".to_string()
+                    };
+                    let mut spans = vec![self.text_span(marker, base.clone(), font_size)];
+                    spans.extend(self.spans(&content, font_size, &base));
+                    self.text_line(spans, (level - 1) as f64 * LIST_INDENT, text_height)
+                }
+                CreoleLine::TableRow(cells) => {
+                    numbers.clear();
+                    self.table_row(&cells, font_size, &base, text_height)
+                }
+                CreoleLine::Rule => {
+                    numbers.clear();
+                    LabelLine {
+                        kind: LabelLineKind::Rule,
+                        spans: Vec::new(),
+                        indent: 0.0,
+                        width: 0.0,
+                        height: text_height / 2.0,
+                    }
+                }
+            })
+            .collect();
+
+        align_tables(&mut lines);
+        lines
+    }
+
+    /// Строка из готовых частей
+    fn text_line<'a>(&self, spans: Vec<LabelSpan<'a>>, indent: f64, text_height: f64) -> LabelLine<'a> {
+        let width = indent + spans.iter().map(LabelSpan::width).sum::<f64>();
+        let height = spans_height(&spans, self, text_height);
+        LabelLine {
+            kind: LabelLineKind::Text,
+            spans,
+            indent,
+            width,
+            height,
+        }
+    }
+
+    /// Строка таблицы; ширины столбцов выравниваются в [`align_tables`]
+    fn table_row<'a>(
+        &'a self,
+        cells: &[TableCell],
+        font_size: f64,
+        base: &TextStyle,
+        text_height: f64,
+    ) -> LabelLine<'a> {
+        let mut height: f64 = 0.0;
+        let cells: Vec<_> = cells
+            .iter()
+            .map(|cell| {
+                let style = TextStyle {
+                    bold: base.bold || cell.header,
+                    ..base.clone()
+                };
+                let spans = self.spans(&cell.content, font_size, &style);
+                height = height.max(spans_height(&spans, self, text_height));
+                LabelCell {
+                    width: spans.iter().map(LabelSpan::width).sum::<f64>() + CELL_PADDING_X * 2.0,
+                    spans,
+                    header: cell.header,
+                    x: 0.0,
+                }
+            })
+            .collect();
+
+        LabelLine {
+            width: cells.iter().map(|cell| cell.width).sum(),
+            kind: LabelLineKind::TableRow(cells),
+            spans: Vec::new(),
+            indent: 0.0,
+            height: height + CELL_PADDING_Y * 2.0,
+        }
+    }
+
+    /// Части строки; неизвестные спрайты — текст их разметки
+    fn spans<'a>(&'a self, inlines: &[Inline], font_size: f64, base: &TextStyle) -> Vec<LabelSpan<'a>> {
+        inlines
+            .iter()
+            .map(|inline| match inline {
+                Inline::Text { text, style } => {
+                    let style = TextStyle {
+                        bold: style.bold || base.bold,
+                        ..style.clone()
+                    };
+                    self.text_span(text.clone(), style, font_size)
+                }
+                Inline::Sprite { reference, markup } => match self.sprites.get(&reference.name) {
+                    Some(sprite) => LabelSpan::Sprite {
+                        width: sprite.width as f64 * reference.scale,
+                        height: sprite.height as f64 * reference.scale,
+                        sprite,
+                        reference: reference.clone(),
+                    },
+                    None => self.text_span(markup.clone(), base.clone(), font_size),
+                },
+            })
+            .collect()
+    }
+
+    /// Текстовая часть с измеренной шириной
+    fn text_span<'a>(&self, text: String, style: TextStyle, font_size: f64) -> LabelSpan<'a> {
+        let font_size = style.size.unwrap_or(font_size);
+        let width = if style.monospace {
+            text.chars().count() as f64 * MONOSPACE_ADVANCE_EM * font_size
+        } else {
+            self.measurer().line_width(&text, font_size, style.bold)
+        };
+        LabelSpan::Text {
+            text,
+            style,
+            font_size,
+            width,
+        }
+    }
+}
+
+/// Высота строки из частей; пустая строка — высота базового текста
+fn spans_height(spans: &[LabelSpan], metrics: &TextMetrics, text_height: f64) -> f64 {
+    if spans.is_empty() {
+        return text_height;
+    }
+    spans
+        .iter()
+        .map(|span| match span {
+            LabelSpan::Text { font_size, .. } => metrics.measurer().line_height(*font_size),
+            LabelSpan::Sprite { height, .. } => *height,
+        })
+        .fold(0.0, f64::max)
+}
+
+/// Выравнивает столбцы идущих подряд строк таблицы
+fn align_tables(lines: &mut [LabelLine]) {
+    let mut start = 0;
+    while start < lines.len() {
+        let end = start
+            + lines[start..]
+                .iter()
+                .take_while(|line| matches!(line.kind, LabelLineKind::TableRow(_)))
+                .count();
+        if end == start {
+            start += 1;
+            continue;
+        }
+
+        let mut columns: Vec<f64> = Vec::new();
+        for line in &lines[start..end] {
+            if let LabelLineKind::TableRow(cells) = &line.kind {
+                for (index, cell) in cells.iter().enumerate() {
+                    if index == columns.len() {
+                        columns.push(0.0);
+                    }
+                    columns[index] = columns[index].max(cell.width);
+                }
+            }
+        }
+
+        for line in &mut lines[start..end] {
+            if let LabelLineKind::TableRow(cells) = &mut line.kind {
+                let mut x = 0.0;
+                for (cell, width) in cells.iter_mut().zip(&columns) {
+                    cell.x = x;
+                    cell.width = *width;
+                    x += width;
+                }
+            }
+            line.width = columns.iter().sum();
+        }
+        start = end;
+    }
+}
+
+impl LabelSpan<'_> {
+    /// Ширина части
+    pub fn width(&self) -> f64 {
+        match self {
+            LabelSpan::Text { width, .. } | LabelSpan::Sprite { width, .. } => *width,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plantuml_ast::sprite::{SpriteData, SpriteTable};
+
+    fn metrics() -> TextMetrics {
+        let mut sprites = SpriteTable::new();
+        sprites.insert(Sprite {
+            name: "icon".to_string(),
+            width: 32,
+            height: 40,
+            data: SpriteData::Gray {
+                levels: 16,
+                pixels: vec![0; 32 * 40],
+            },
+        });
+        TextMetrics::default().with_sprites(sprites)
+    }
+
+    #[test]
+    fn test_sprite_line_size() {
+        let metrics = metrics();
+        let text_width = metrics.width(" Web");
+        let lines = metrics.label_lines("<$icon{scale=0.5}> Web\\nServer", 14.0, false);
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].spans.len(), 2);
+        assert!((lines[0].width - (16.0 + text_width)).abs() < 0.01);
+        assert_eq!(lines[0].height, 20.0);
+        assert!(lines[1].height < 20.0);
+
+        let size = metrics.size_at("<$icon>\nServer", 14.0);
+        assert!(size.height > 40.0);
+        assert_eq!(metrics.width("<$icon>"), 32.0);
+    }
+
+    #[test]
+    fn test_unknown_sprite_is_text() {
+        let metrics = metrics();
+        let lines = metrics.label_lines("<$missing> x", 14.0, false);
+        assert!(matches!(&lines[0].spans[0], LabelSpan::Text { text, .. } if text == "<$missing>"));
+        assert_eq!(metrics.width("<$missing> x"), TextMetrics::default().width("<$missing> x"));
+    }
+
+    #[test]
+    fn test_styled_runs() {
+        let metrics = TextMetrics::default();
+        assert_eq!(metrics.width("**Bold**"), metrics.bold_width_at("Bold", 14.0));
+        assert_eq!(metrics.width("<color:red>Red</color>"), metrics.width("Red"));
+        assert!(metrics.width("<size:28>Big</size>") > metrics.width("Big") * 1.9);
+        assert!(metrics.size_at("<size:28>Big</size>", 14.0).height > metrics.size_at("Big", 14.0).height);
+    }
+
+    #[test]
+    fn test_lists_and_rules() {
+        let metrics = TextMetrics::default();
+        let lines = metrics.label_lines("# one\n## nested\n# two\n----", 14.0, false);
+
+        assert!(matches!(&lines[1].spans[0], LabelSpan::Text { text, .. } if text == "1. "));
+        assert!(matches!(&lines[2].spans[0], LabelSpan::Text { text, .. } if text == "2. "));
+        assert_eq!(lines[1].indent, LIST_INDENT);
+        assert_eq!(lines[3].kind, LabelLineKind::Rule);
+    }
+
+    #[test]
+    fn test_table_columns_aligned() {
+        let metrics = TextMetrics::default();
+        let lines = metrics.label_lines("|= A |= B |\n| long cell | x |", 14.0, false);
+
+        let (LabelLineKind::TableRow(head), LabelLineKind::TableRow(row)) = (&lines[0].kind, &lines[1].kind) else {
+            panic!("ожидаются строки таблицы");
+        };
+        assert_eq!(head[0].width, row[0].width);
+        assert_eq!(head[1].x, row[1].x);
+        assert!(head[0].header);
+        assert!(matches!(&head[0].spans[0], LabelSpan::Text { style, .. } if style.bold));
+        assert_eq!(lines[0].width, lines[1].width);
+    }
+}
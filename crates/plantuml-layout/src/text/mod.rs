@@ -0,0 +1,249 @@
+//! Измерение текста для layout engines
+//!
+//! Все engines считают размеры подписей через [`TextMeasurer`]. По умолчанию
+//! используется детерминированный [`FallbackMeasurer`] с таблицами ширин
+//! DejaVu Sans. Feature `font-metrics` добавляет [`FontMeasurer`] на базе
+//! `ab_glyph` со встроенным DejaVu Sans, а `system-fonts` — поиск системного
+//! шрифта через `fontdb` по `Theme.font_family`.
+
+mod fallback;
+#[cfg(feature = "font-metrics")]
+mod font;
+mod label;
+
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+
+use plantuml_ast::creole::has_markup;
+use plantuml_ast::sprite::SpriteTable;
+use plantuml_model::Size;
+
+pub use fallback::FallbackMeasurer;
+#[cfg(feature = "font-metrics")]
+pub use font::FontMeasurer;
+pub use label::{LabelCell, LabelLine, LabelLineKind, LabelSpan, CELL_PADDING_X, CELL_PADDING_Y};
+
+/// Высота строки DejaVu Sans (ascent + descent), em
+pub(crate) const LINE_HEIGHT_EM: f64 = 1.1640625;
+
+/// Разделяемый измеритель текста
+pub type SharedMeasurer = Arc<dyn TextMeasurer>;
+
+/// Измеритель текста
+pub trait TextMeasurer: fmt::Debug + Send + Sync {
+    /// Ширина одной строки (без переносов) в пикселях
+    fn line_width(&self, line: &str, font_size: f64, bold: bool) -> f64;
+
+    /// Высота строки в пикселях
+    fn line_height(&self, font_size: f64) -> f64;
+
+    /// Размер многострочного текста
+    ///
+    /// Строки разделяются `\n` и экранированным `\\n`.
+    fn measure(&self, text: &str, font_size: f64, bold: bool) -> Size {
+        let lines = text_lines(text);
+        let width = lines
+            .iter()
+            .map(|line| self.line_width(line, font_size, bold))
+            .fold(0.0_f64, f64::max);
+        Size::new(width, lines.len() as f64 * self.line_height(font_size))
+    }
+}
+
+/// Делит подпись на строки по `\n` и экранированному `\\n`
+pub fn text_lines(text: &str) -> Vec<&str> {
+    text.split('\n').flat_map(|line| line.split("\\n")).collect()
+}
+
+/// Измеритель по умолчанию
+///
+/// Встроенный шрифт при feature `font-metrics`, иначе [`FallbackMeasurer`].
+pub fn default_measurer() -> SharedMeasurer {
+    static DEFAULT: OnceLock<SharedMeasurer> = OnceLock::new();
+    DEFAULT
+        .get_or_init(|| {
+            #[cfg(feature = "font-metrics")]
+            let measurer: SharedMeasurer = Arc::new(FontMeasurer::bundled());
+            #[cfg(not(feature = "font-metrics"))]
+            let measurer: SharedMeasurer = Arc::new(FallbackMeasurer::new());
+            measurer
+        })
+        .clone()
+}
+
+/// Измеритель для CSS-подобного списка семейств (`"Segoe UI, Arial, sans-serif"`)
+///
+/// При feature `system-fonts` берётся первое установленное семейство из
+/// списка. Общие семейства (`sans-serif`, `monospace`, …) и отсутствующие
+/// шрифты дают [`default_measurer`], чтобы размеры не зависели от машины.
+pub fn measurer_for_family(family: &str) -> SharedMeasurer {
+    #[cfg(feature = "system-fonts")]
+    {
+        let found = family
+            .split(',')
+            .map(|name| name.trim().trim_matches(|c| c == '"' || c == '\''))
+            .filter(|name| !name.is_empty() && !is_generic_family(name))
+            .find_map(font::system_measurer);
+        if let Some(measurer) = found {
+            return measurer;
+        }
+    }
+    #[cfg(not(feature = "system-fonts"))]
+    let _ = family;
+
+    default_measurer()
+}
+
+/// Общее CSS семейство, не привязанное к конкретному шрифту
+#[cfg_attr(not(feature = "system-fonts"), allow(dead_code))]
+fn is_generic_family(name: &str) -> bool {
+    matches!(
+        name.to_ascii_lowercase().as_str(),
+        "serif" | "sans-serif" | "monospace" | "cursive" | "fantasy" | "system-ui"
+    )
+}
+
+/// Шрифт и измеритель, по которым engine считает размеры подписей
+///
+/// Размеры округляются до сотых пикселя, чтобы координаты в SVG оставались
+/// короткими. Creole разметка (`**bold**`, `<size:18>`, списки, таблицы)
+/// учитывается, ссылки `<$name>` на спрайты диаграммы занимают размер спрайта.
+#[derive(Debug, Clone)]
+pub struct TextMetrics {
+    measurer: SharedMeasurer,
+    sprites: Arc<SpriteTable>,
+    /// Семейство шрифта (`Theme.font_family`)
+    pub font_family: String,
+    /// Базовый размер шрифта (`Theme.font_size`)
+    pub font_size: f64,
+}
+
+impl Default for TextMetrics {
+    fn default() -> Self {
+        Self::new(default_measurer(), "sans-serif", 14.0)
+    }
+}
+
+impl TextMetrics {
+    /// Создаёт метрики с явным измерителем
+    pub fn new(measurer: SharedMeasurer, font_family: impl Into<String>, font_size: f64) -> Self {
+        Self {
+            measurer,
+            sprites: Arc::default(),
+            font_family: font_family.into(),
+            font_size,
+        }
+    }
+
+    /// Создаёт метрики для семейства и размера шрифта темы
+    pub fn for_font(font_family: impl Into<String>, font_size: f64) -> Self {
+        let font_family = font_family.into();
+        Self::new(measurer_for_family(&font_family), font_family, font_size)
+    }
+
+    /// Устанавливает спрайты диаграммы
+    pub fn with_sprites(mut self, sprites: SpriteTable) -> Self {
+        self.sprites = Arc::new(sprites);
+        self
+    }
+
+    /// Используемый измеритель
+    pub fn measurer(&self) -> &dyn TextMeasurer {
+        self.measurer.as_ref()
+    }
+
+    /// Спрайты диаграммы
+    pub fn sprites(&self) -> &SpriteTable {
+        &self.sprites
+    }
+
+    /// Ширина текста базовым шрифтом
+    pub fn width(&self, text: &str) -> f64 {
+        self.width_at(text, self.font_size)
+    }
+
+    /// Ширина текста шрифтом заданного размера
+    pub fn width_at(&self, text: &str, font_size: f64) -> f64 {
+        round(self.measure(text, font_size, false).width)
+    }
+
+    /// Ширина текста полужирным шрифтом заданного размера
+    pub fn bold_width_at(&self, text: &str, font_size: f64) -> f64 {
+        round(self.measure(text, font_size, true).width)
+    }
+
+    /// Размер многострочного текста шрифтом заданного размера
+    pub fn size_at(&self, text: &str, font_size: f64) -> Size {
+        let size = self.measure(text, font_size, false);
+        Size::new(round(size.width), round(size.height))
+    }
+
+    /// Размер текста с учётом creole разметки и спрайтов
+    fn measure(&self, text: &str, font_size: f64, bold: bool) -> Size {
+        if !has_markup(text) {
+            return self.measurer.measure(text, font_size, bold);
+        }
+        let lines = self.label_lines(text, font_size, bold);
+        Size::new(
+            lines.iter().map(|line| line.width).fold(0.0, f64::max),
+            lines.iter().map(|line| line.height).sum(),
+        )
+    }
+
+    /// Высота строки базовым шрифтом
+    pub fn line_height(&self) -> f64 {
+        round(self.measurer.line_height(self.font_size))
+    }
+}
+
+/// Округляет размер до сотых пикселя
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_lines() {
+        assert_eq!(text_lines("a\nb\\nc"), vec!["a", "b", "c"]);
+        assert_eq!(text_lines(""), vec![""]);
+    }
+
+    #[test]
+    fn test_measure_multiline() {
+        let m = FallbackMeasurer::new();
+        let one = m.measure("Hello", 14.0, false);
+        let two = m.measure("Hello\\nHi", 14.0, false);
+        assert_eq!(two.width, one.width);
+        assert_eq!(two.height, one.height * 2.0);
+    }
+
+    #[test]
+    fn test_metrics_scale_with_font_size() {
+        let metrics = TextMetrics::default();
+        let small = metrics.width_at("Participant", 10.0);
+        let large = metrics.width_at("Participant", 20.0);
+        assert!((large - small * 2.0).abs() < 0.02);
+        assert!(metrics.bold_width_at("Participant", 10.0) > small);
+    }
+
+    #[test]
+    fn test_generic_family_uses_default() {
+        let metrics = TextMetrics::for_font("sans-serif", 14.0);
+        let default = TextMetrics::default();
+        assert_eq!(metrics.width("Alice"), default.width("Alice"));
+        assert!(is_generic_family("Monospace"));
+        assert!(!is_generic_family("Arial"));
+    }
+
+    #[test]
+    fn test_default_matches_fallback_for_latin_and_cyrillic() {
+        // Таблицы FallbackMeasurer совпадают со встроенным шрифтом
+        let text = "Hello, Мир! 123";
+        let fallback = FallbackMeasurer::new().line_width(text, 13.0, false);
+        let default = default_measurer().line_width(text, 13.0, false);
+        assert!((fallback - default).abs() < 1e-6);
+    }
+}
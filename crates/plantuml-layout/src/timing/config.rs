@@ -1,5 +1,7 @@
 //! Конфигурация layout для Timing Diagrams
 
+use crate::text::TextMetrics;
+
 /// Конфигурация layout для Timing Diagrams
 #[derive(Debug, Clone)]
 pub struct TimingLayoutConfig {
@@ -21,6 +23,8 @@ pub struct TimingLayoutConfig {
     pub label_font_size: f64,
     /// Размер шрифта временных меток
     pub time_font_size: f64,
+    /// Метрики текста (шрифт темы)
+    pub text: TextMetrics,
 }
 
 impl Default for TimingLayoutConfig {
@@ -35,6 +39,7 @@ impl Default for TimingLayoutConfig {
             concise_line_height: 20.0,
             label_font_size: 12.0,
             time_font_size: 10.0,
+            text: TextMetrics::default(),
         }
     }
 }
@@ -8,8 +8,9 @@ use plantuml_ast::timing::{ParticipantType, StateChange, TimeValue, TimingDiagra
 use plantuml_model::{Point, Rect};
 
 use super::TimingLayoutConfig;
+use crate::text::TextMetrics;
 use crate::traits::LayoutResult;
-use crate::{EdgeType, ElementType, LayoutElement};
+use crate::{EdgeType, ElementStyle, ElementType, LayoutElement};
 
 /// Layout engine для Timing Diagrams
 pub struct TimingLayoutEngine {
@@ -29,6 +30,12 @@ impl TimingLayoutEngine {
         Self { config }
     }
 
+    /// Устанавливает метрики текста (шрифт темы)
+    pub fn with_text_metrics(mut self, text: TextMetrics) -> Self {
+        self.config.text = text;
+        self
+    }
+
     /// Выполняет layout диаграммы
     pub fn layout(&self, diagram: &TimingDiagram) -> LayoutResult {
         let mut elements = Vec::new();
@@ -75,7 +82,14 @@ impl TimingLayoutEngine {
         }
 
         // 4. Рисуем участников и их lanes
-        let timeline_start_x = self.config.padding + self.config.participant_label_width;
+        // Колонка меток — по самому длинному имени участника
+        let label_width = diagram
+            .participants
+            .iter()
+            .map(|p| p.alias.as_deref().unwrap_or(&p.name))
+            .map(|name| self.config.text.width_at(name, self.config.label_font_size) + 20.0)
+            .fold(self.config.participant_label_width, f64::max);
+        let timeline_start_x = self.config.padding + label_width;
         let timeline_width = time_range * self.config.time_scale;
 
         for (i, participant) in diagram.participants.iter().enumerate() {
@@ -88,7 +102,7 @@ impl TimingLayoutEngine {
                 bounds: Rect::new(
                     self.config.padding,
                     lane_y,
-                    self.config.participant_label_width - 10.0,
+                    label_width - 10.0,
                     self.config.lane_height,
                 ),
                 text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Text {
@@ -98,6 +112,7 @@ impl TimingLayoutEngine {
             });
 
             // Рисуем timeline для участника
+            let first = elements.len();
             match participant.participant_type {
                 ParticipantType::Robust => {
                     self.draw_robust_timeline(
@@ -133,6 +148,10 @@ impl TimingLayoutEngine {
                     );
                 }
             }
+            let style = ElementStyle::new().with_fill(participant.color.as_ref());
+            for element in &mut elements[first..] {
+                style.write(&mut element.properties);
+            }
         }
 
         // 5. Рисуем временную ось внизу
@@ -148,19 +167,7 @@ impl TimingLayoutEngine {
             max_time,
         );
 
-        // 6. Title
-        if let Some(ref title) = diagram.metadata.title {
-            elements.push(LayoutElement {
-                id: "title".to_string(),
-                bounds: Rect::new(self.config.padding, 5.0, 500.0, 20.0),
-                text: None, properties: std::collections::HashMap::new(), element_type: ElementType::Text {
-                    text: title.clone(),
-                    font_size: 14.0,
-                },
-            });
-        }
-
-        // 7. Возвращаем результат
+        // 6. Возвращаем результат
         let total_width = timeline_start_x + timeline_width + self.config.padding;
         let total_height = axis_y + 30.0 + self.config.padding;
 
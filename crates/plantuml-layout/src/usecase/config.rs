@@ -1,5 +1,7 @@
 //! Конфигурация layout для Use Case Diagrams
 
+use crate::text::TextMetrics;
+
 /// Конфигурация Use Case Layout Engine
 #[derive(Debug, Clone)]
 pub struct UseCaseLayoutConfig {
@@ -21,6 +23,8 @@ pub struct UseCaseLayoutConfig {
     pub package_padding: f64,
     /// Высота заголовка пакета
     pub package_header_height: f64,
+    /// Метрики текста (шрифт темы)
+    pub text: TextMetrics,
 }
 
 impl Default for UseCaseLayoutConfig {
@@ -35,6 +39,7 @@ impl Default for UseCaseLayoutConfig {
             horizontal_spacing: 80.0, // Increased for actor labels
             package_padding: 25.0,
             package_header_height: 30.0,
+            text: TextMetrics::default(),
         }
     }
 }
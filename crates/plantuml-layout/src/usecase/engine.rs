@@ -1,16 +1,33 @@
 //! Use Case Diagram Layout Engine
 //!
 //! Алгоритм layout для диаграмм вариантов использования.
-//! PlantUML стиль: актёры слева, система справа с use cases внутри.
+//!
+//! Актёры, use cases и системы (пакеты) размещаются алгоритмом Sugiyama
+//! по связям между ними, как в component diagrams: сначала раскладывается
+//! содержимое системы, затем система участвует в общем layout как один
+//! узел своего размера. Use cases вне пакетов собираются в систему
+//! «System», если пакетов нет. Связи прокладываются в обход актёров,
+//! use cases и чужих систем.
 
 use std::collections::HashMap;
 
-use plantuml_ast::common::Direction;
-use plantuml_ast::usecase::{UseCaseDiagram, UseCaseRelationship, UseCaseRelationType};
-use plantuml_model::{Point, Rect};
+use plantuml_ast::common::{Direction, LineType};
+use plantuml_ast::usecase::{UseCase, UseCaseDiagram, UseCaseRelationship, UseCaseRelationType};
+use plantuml_model::{Point, Rect, Size};
 
 use super::config::UseCaseLayoutConfig;
-use crate::{EdgeType, ElementType, LayoutElement, LayoutResult};
+use crate::class::sugiyama::SugiyamaLayout;
+use crate::class::{ClassLayoutConfig, Graph};
+use crate::routing::{EdgeSpec, Router};
+use crate::text::TextMetrics;
+use crate::{EdgeType, ElementStyle, ElementType, LayoutElement, LayoutResult};
+
+/// Система (пакет) с размещёнными use cases в локальных координатах
+struct System<'a> {
+    name: String,
+    use_cases: Vec<(&'a UseCase, Rect)>,
+    size: Size,
+}
 
 /// Layout engine для use case diagrams
 pub struct UseCaseLayoutEngine {
@@ -30,139 +47,137 @@ impl UseCaseLayoutEngine {
         Self { config }
     }
 
+    /// Устанавливает метрики текста (шрифт темы)
+    pub fn with_text_metrics(mut self, text: TextMetrics) -> Self {
+        self.config.text = text;
+        self
+    }
+
     /// Выполняет layout диаграммы
     pub fn layout(&self, diagram: &UseCaseDiagram) -> LayoutResult {
         let mut elements = Vec::new();
         let mut element_positions: HashMap<String, Rect> = HashMap::new();
-
-        let _is_left_to_right = diagram.direction == Direction::LeftToRight;
-
-        // Вычисляем максимальную ширину имён актёров для правильного позиционирования
-        // Кириллица занимает примерно 9 пикселей на символ (font-size 14)
-        let max_actor_label_width = diagram.actors.iter()
-            .map(|a| a.name.chars().count() as f64 * 9.0)
-            .fold(0.0f64, f64::max);
-        
-        // Минимальная ширина для актёра с его label
-        let actor_total_width = self.config.actor_width.max(max_actor_label_width);
-        
-        // Позиция актёров - центрируем по ширине их label
-        let actors_x = self.config.margin + actor_total_width / 2.0;
-
-        // Собираем все use cases (из packages и верхнего уровня)
-        let mut all_usecases: Vec<(&str, Option<&str>)> = Vec::new();
-        
-        for uc in &diagram.use_cases {
-            all_usecases.push((&uc.name, uc.alias.as_deref()));
-        }
-        
-        for pkg in &diagram.packages {
-            for uc in &pkg.use_cases {
-                all_usecases.push((&uc.name, uc.alias.as_deref()));
-            }
-        }
-
-        // Вычисляем размеры системы/rectangle
-        let num_usecases = all_usecases.len().max(1);
-        let system_inner_height = num_usecases as f64 * (self.config.usecase_height + self.config.vertical_spacing);
-        let system_height = system_inner_height + self.config.package_header_height + self.config.package_padding * 2.0;
-        let system_width = self.config.usecase_width + self.config.package_padding * 2.0 + 40.0;
-        
-        // Позиция системы (справа от актёров с учётом их label)
-        let system_x = self.config.margin + actor_total_width + self.config.horizontal_spacing;
-        let system_y = self.config.margin;
-
-        // Если есть packages, создаём System элемент
-        let system_name = if !diagram.packages.is_empty() {
-            diagram.packages[0].name.clone()
+        let direction = diagram.direction;
+
+        // Все use cases одной ширины: по самому длинному имени (текст вписан в эллипс)
+        let usecase_width = diagram
+            .use_cases
+            .iter()
+            .chain(diagram.packages.iter().flat_map(|pkg| &pkg.use_cases))
+            .map(|uc| self.config.text.width(&uc.name) * 1.2 + 30.0)
+            .fold(self.config.usecase_width, f64::max);
+
+        // Системы: пакеты, а без пакетов — «System» со всеми use cases
+        let mut systems: Vec<System> = diagram
+            .packages
+            .iter()
+            .map(|pkg| self.layout_system(&pkg.name, &pkg.use_cases, diagram, usecase_width))
+            .collect();
+        let free_use_cases: &[UseCase] = if systems.is_empty() {
+            systems.push(self.layout_system("System", &diagram.use_cases, diagram, usecase_width));
+            &[]
         } else {
-            "System".to_string()
+            &diagram.use_cases
         };
 
-        // Создаём rectangle системы
-        let system_bounds = Rect::new(system_x, system_y, system_width, system_height);
-        let system_elem = LayoutElement {
-            id: format!("system_{}", system_name.replace(' ', "_")),
-            bounds: system_bounds.clone(),
-            text: None,
-            properties: std::collections::HashMap::new(),
-            element_type: ElementType::System {
-                title: system_name,
-            },
-        };
-        elements.push(system_elem);
-
-        // Размещаем use cases внутри системы (вертикально по центру)
-        let usecases_x = system_x + (system_width - self.config.usecase_width) / 2.0;
-        let usecases_start_y = system_y + self.config.package_header_height + self.config.package_padding;
-
-        for (i, (name, alias)) in all_usecases.iter().enumerate() {
-            let y = usecases_start_y + i as f64 * (self.config.usecase_height + self.config.vertical_spacing);
-            
-            let (elem, bounds) = self.create_usecase_element(name, usecases_x, y);
-            element_positions.insert(name.to_string(), bounds.clone());
-            if let Some(a) = alias {
-                element_positions.insert(a.to_string(), bounds);
+        // Узлы верхнего уровня: актёры, use cases вне систем, системы
+        let mut graph = Graph::new();
+        let mut owners: HashMap<&str, usize> = HashMap::new();
+        for actor in &diagram.actors {
+            let width = self.config.actor_width.max(self.config.text.width(&actor.name));
+            let index = graph.add_node(format!("actor_{}", actor.name), Size::new(width, self.config.actor_height));
+            owners.insert(&actor.name, index);
+            if let Some(alias) = &actor.alias {
+                owners.insert(alias, index);
             }
-            elements.push(elem);
         }
-
-        // Группируем актёров по их связям с use cases
-        // Находим какие актёры связаны с какими use cases
-        let mut actor_usecases: HashMap<String, Vec<String>> = HashMap::new();
-        for rel in &diagram.relationships {
-            // Проверяем, является ли from актёром
-            if diagram.actors.iter().any(|a| a.name == rel.from || a.alias.as_deref() == Some(&rel.from)) {
-                actor_usecases.entry(rel.from.clone()).or_default().push(rel.to.clone());
-            }
-            // Проверяем, является ли to актёром
-            if diagram.actors.iter().any(|a| a.name == rel.to || a.alias.as_deref() == Some(&rel.to)) {
-                actor_usecases.entry(rel.to.clone()).or_default().push(rel.from.clone());
+        for uc in free_use_cases {
+            let index = graph.add_node(format!("usecase_{}", uc.name), Size::new(usecase_width, self.config.usecase_height));
+            Self::insert_names(uc, index, &mut owners);
+        }
+        for system in &systems {
+            let index = graph.add_node(format!("system_{}", system.name), system.size);
+            for (uc, _) in &system.use_cases {
+                Self::insert_names(uc, index, &mut owners);
             }
         }
+        self.add_edges(&mut graph, &owners, diagram);
+        self.run_sugiyama(&mut graph, direction, self.config.margin);
 
-        // Размещаем актёров слева (actors_x уже вычислен выше с учётом ширины label)
-        for actor in &diagram.actors {
-            let actor_id = actor.alias.as_ref().unwrap_or(&actor.name);
-            
-            // Вычисляем среднюю Y позицию use cases, с которыми связан актёр
-            let connected_usecases = actor_usecases.get(actor_id).or_else(|| actor_usecases.get(&actor.name));
-            
-            let y = if let Some(ucs) = connected_usecases {
-                if !ucs.is_empty() {
-                    let total_y: f64 = ucs.iter()
-                        .filter_map(|uc_name| element_positions.get(uc_name))
-                        .map(|rect| rect.y + rect.height / 2.0)
-                        .sum();
-                    let count = ucs.iter().filter(|uc| element_positions.contains_key(*uc)).count();
-                    if count > 0 {
-                        total_y / count as f64 - self.config.actor_height / 2.0
-                    } else {
-                        self.config.margin
-                    }
-                } else {
-                    self.config.margin
-                }
-            } else {
-                // Если актёр не связан ни с чем, размещаем внизу
-                system_y + system_height / 2.0 - self.config.actor_height / 2.0
-            };
-
-            let (elem, bounds) = self.create_actor_element(&actor.name, actors_x, y);
+        let mut nodes = graph.nodes.iter();
+        for (actor, node) in diagram.actors.iter().zip(nodes.by_ref()) {
+            let x = node.x + (node.size.width - self.config.actor_width) / 2.0;
+            let (elem, bounds) = self.create_actor_element(&actor.name, x, node.y);
             element_positions.insert(actor.name.clone(), bounds.clone());
             if let Some(alias) = &actor.alias {
                 element_positions.insert(alias.clone(), bounds);
             }
-            elements.push(elem);
+            let style = ElementStyle::new().with_fill(actor.color.as_ref());
+            elements.push(
+                elem.with_link(actor.link.as_ref())
+                    .with_style(&style)
+                    .with_stereotype(actor.stereotype.as_ref()),
+            );
         }
-
-        // Создаём связи
-        for rel in &diagram.relationships {
-            if let Some(edge) = self.create_relationship_element(rel, &element_positions) {
-                elements.push(edge);
+        for (uc, node) in free_use_cases.iter().zip(nodes.by_ref()) {
+            let bounds = Rect::new(node.x, node.y, node.size.width, node.size.height);
+            self.push_usecase(uc, bounds, &mut elements, &mut element_positions);
+        }
+        for (system, node) in systems.iter().zip(nodes) {
+            let system_bounds = Rect::new(node.x, node.y, node.size.width, node.size.height);
+            let color = diagram
+                .packages
+                .iter()
+                .find(|pkg| pkg.name == system.name)
+                .and_then(|pkg| pkg.color.as_ref());
+            elements.push(LayoutElement {
+                id: format!("system_{}", system.name.replace(' ', "_")),
+                bounds: system_bounds,
+                text: None,
+                properties: std::collections::HashMap::new(),
+                element_type: ElementType::System {
+                    title: system.name.clone(),
+                },
+            }.with_style(&ElementStyle::new().with_fill(color)));
+
+            // Содержимое центрируется по ширине системы
+            let origin = Point::new(
+                node.x + (node.size.width - system.size.width) / 2.0 + self.config.package_padding,
+                node.y + self.config.package_header_height + self.config.package_padding,
+            );
+            for (uc, rect) in &system.use_cases {
+                let bounds = Rect::new(origin.x + rect.x, origin.y + rect.y, rect.width, rect.height);
+                self.push_usecase(uc, bounds, &mut elements, &mut element_positions);
             }
         }
 
+        // Создаём связи; скрытые только влияли на layout
+        let (frames, obstacles): (Vec<&LayoutElement>, Vec<&LayoutElement>) = elements
+            .iter()
+            .partition(|element| matches!(element.element_type, ElementType::System { .. }));
+        let router = Router::new(
+            diagram.metadata.line_type.unwrap_or(LineType::Polyline),
+            self.config.text.clone(),
+        )
+        .with_obstacles(obstacles.iter().map(|element| element.bounds))
+        .with_frames(frames.iter().map(|element| element.bounds));
+
+        let visible: Vec<(&UseCaseRelationship, Rect, Rect)> = diagram
+            .relationships
+            .iter()
+            .filter(|rel| !rel.hidden)
+            .filter_map(|rel| Some((rel, *element_positions.get(&rel.from)?, *element_positions.get(&rel.to)?)))
+            .collect();
+        let specs: Vec<EdgeSpec> = visible
+            .iter()
+            .map(|(rel, from, to)| EdgeSpec::new(*from, *to).with_label(rel.label.as_deref()))
+            .collect();
+        for ((rel, _, _), route) in visible.iter().zip(router.route_all(&specs)) {
+            let mut edge = self.create_relationship_element(rel);
+            route.apply(&mut edge);
+            elements.push(edge);
+        }
+
         // Вычисляем bounds
         let mut result = LayoutResult {
             elements,
@@ -176,6 +191,102 @@ impl UseCaseLayoutEngine {
         result
     }
 
+    /// Раскладывает use cases системы по связям между ними
+    fn layout_system<'a>(
+        &self,
+        name: &str,
+        use_cases: &'a [UseCase],
+        diagram: &UseCaseDiagram,
+        usecase_width: f64,
+    ) -> System<'a> {
+        let mut graph = Graph::new();
+        let mut owners: HashMap<&str, usize> = HashMap::new();
+        for uc in use_cases {
+            let index = graph.add_node(uc.name.clone(), Size::new(usecase_width, self.config.usecase_height));
+            Self::insert_names(uc, index, &mut owners);
+        }
+        self.add_edges(&mut graph, &owners, diagram);
+        self.run_sugiyama(&mut graph, diagram.direction, 0.0);
+
+        let mut inner = Size::zero();
+        let use_cases: Vec<(&UseCase, Rect)> = use_cases
+            .iter()
+            .zip(&graph.nodes)
+            .map(|(uc, node)| {
+                let rect = Rect::new(node.x, node.y, node.size.width, node.size.height);
+                inner.width = inner.width.max(rect.x + rect.width);
+                inner.height = inner.height.max(rect.y + rect.height);
+                (uc, rect)
+            })
+            .collect();
+
+        let title_width = self.config.text.width(name);
+        let size = Size::new(
+            inner.width.max(title_width) + self.config.package_padding * 2.0,
+            inner.height + self.config.package_header_height + self.config.package_padding * 2.0,
+        );
+
+        System {
+            name: name.to_string(),
+            use_cases,
+            size,
+        }
+    }
+
+    /// Имя и алиас use case
+    fn insert_names<'a>(uc: &'a UseCase, index: usize, owners: &mut HashMap<&'a str, usize>) {
+        owners.insert(&uc.name, index);
+        if let Some(alias) = &uc.alias {
+            owners.insert(alias, index);
+        }
+    }
+
+    /// Добавляет в граф связи между разными узлами с подсказками направления
+    fn add_edges(&self, graph: &mut Graph, owners: &HashMap<&str, usize>, diagram: &UseCaseDiagram) {
+        for rel in &diagram.relationships {
+            if let (Some(&from), Some(&to)) = (owners.get(rel.from.as_str()), owners.get(rel.to.as_str())) {
+                if from != to {
+                    let edge = graph.add_edge(from, to);
+                    graph.edges[edge].direction = rel.direction;
+                }
+            }
+        }
+    }
+
+    /// Sugiyama с отступами как у прежней раскладки:
+    /// `vertical_spacing` внутри слоя, `horizontal_spacing` между слоями
+    fn run_sugiyama(&self, graph: &mut Graph, direction: Direction, margin: f64) {
+        let config = ClassLayoutConfig {
+            margin,
+            node_horizontal_spacing: self.config.vertical_spacing,
+            layer_vertical_spacing: self.config.horizontal_spacing,
+            min_class_height: 0.0,
+            ..ClassLayoutConfig::default()
+        };
+        SugiyamaLayout::new(graph, &config).with_direction(direction).run();
+    }
+
+    /// Добавляет элемент use case и запоминает его позицию
+    fn push_usecase(
+        &self,
+        uc: &UseCase,
+        bounds: Rect,
+        elements: &mut Vec<LayoutElement>,
+        positions: &mut HashMap<String, Rect>,
+    ) {
+        let (elem, bounds) = self.create_usecase_element(&uc.name, bounds.x, bounds.y, bounds.width);
+        positions.insert(uc.name.clone(), bounds);
+        if let Some(alias) = &uc.alias {
+            positions.insert(alias.clone(), bounds);
+        }
+        let style = ElementStyle::new().with_fill(uc.color.as_ref());
+        elements.push(
+            elem.with_link(uc.link.as_ref())
+                .with_style(&style)
+                .with_stereotype(uc.stereotype.as_ref()),
+        );
+    }
+
     /// Создаёт элемент актёра (stick figure)
     fn create_actor_element(&self, name: &str, x: f64, y: f64) -> (LayoutElement, Rect) {
         let bounds = Rect::new(x, y, self.config.actor_width, self.config.actor_height);
@@ -195,8 +306,8 @@ impl UseCaseLayoutEngine {
     }
 
     /// Создаёт элемент use case (эллипс)
-    fn create_usecase_element(&self, name: &str, x: f64, y: f64) -> (LayoutElement, Rect) {
-        let bounds = Rect::new(x, y, self.config.usecase_width, self.config.usecase_height);
+    fn create_usecase_element(&self, name: &str, x: f64, y: f64, width: f64) -> (LayoutElement, Rect) {
+        let bounds = Rect::new(x, y, width, self.config.usecase_height);
 
         (
             LayoutElement {
@@ -212,38 +323,24 @@ impl UseCaseLayoutEngine {
         )
     }
 
-    /// Создаёт элемент связи
-    fn create_relationship_element(
-        &self,
-        rel: &UseCaseRelationship,
-        positions: &HashMap<String, Rect>,
-    ) -> Option<LayoutElement> {
-        let from_rect = positions.get(&rel.from)?;
-        let to_rect = positions.get(&rel.to)?;
-
-        let (start, end) = self.calculate_connection_points(from_rect, to_rect);
-
-        let min_x = start.x.min(end.x);
-        let min_y = start.y.min(end.y);
-        let max_x = start.x.max(end.x);
-        let max_y = start.y.max(end.y);
-
+    /// Создаёт элемент связи; путь и подпись размещает маршрутизатор
+    fn create_relationship_element(&self, rel: &UseCaseRelationship) -> LayoutElement {
         let dashed = matches!(
             rel.relation_type,
             UseCaseRelationType::Include | UseCaseRelationType::Extend
         );
 
-        Some(LayoutElement {
+        LayoutElement {
             id: format!(
                 "rel_{}_{}",
                 rel.from.replace(' ', "_"),
                 rel.to.replace(' ', "_")
             ),
-            bounds: Rect::new(min_x, min_y, (max_x - min_x).max(1.0), (max_y - min_y).max(1.0)),
+            bounds: Rect::new(0.0, 0.0, 0.0, 0.0),
             text: None,
             properties: std::collections::HashMap::new(),
             element_type: ElementType::Edge {
-                points: vec![start, end],
+                points: Vec::new(),
                 label: rel.label.clone(),
                 arrow_start: false,
                 // В PlantUML --> всегда показывает стрелку
@@ -259,35 +356,8 @@ impl UseCaseLayoutEngine {
                 from_cardinality: None,
                 to_cardinality: None,
             },
-        })
-    }
-
-    /// Вычисляет точки соединения для связи
-    fn calculate_connection_points(&self, from: &Rect, to: &Rect) -> (Point, Point) {
-        let from_center_x = from.x + from.width / 2.0;
-        let from_center_y = from.y + from.height / 2.0;
-        let to_center_x = to.x + to.width / 2.0;
-        let to_center_y = to.y + to.height / 2.0;
-
-        // Для актёров (узкие) соединяем справа
-        // Для эллипсов соединяем слева
-        let start = if from.width < 50.0 {
-            // Это актёр - соединяем справа
-            Point::new(from.x + from.width, from_center_y)
-        } else {
-            // Это эллипс - соединяем слева
-            Point::new(from.x, from_center_y)
-        };
-
-        let end = if to.width < 50.0 {
-            // Это актёр - соединяем справа
-            Point::new(to.x + to.width, to_center_y)
-        } else {
-            // Это эллипс - соединяем слева
-            Point::new(to.x, to_center_y)
-        };
-
-        (start, end)
+        }
+        .with_style(&ElementStyle::new().with_stroke(rel.color.as_ref()))
     }
 }
 
@@ -351,4 +421,29 @@ mod tests {
 
         assert!(result.elements.len() >= 3);
     }
+
+    #[test]
+    fn test_direction_and_hidden_relationships() {
+        let mut diagram = UseCaseDiagram::new();
+        diagram.actors.push(UseCaseActor::new("User"));
+        diagram.use_cases.push(UseCase::new("Login"));
+        diagram.relationships.push(UseCaseRelationship::new("User", "Login"));
+        let mut hidden = UseCaseRelationship::new("Login", "User");
+        hidden.hidden = true;
+        diagram.relationships.push(hidden);
+
+        let engine = UseCaseLayoutEngine::new();
+        let bounds = |result: &LayoutResult, id: &str| result.elements.iter().find(|e| e.id == id).unwrap().bounds;
+
+        // Сверху вниз: актёр над системой
+        let result = engine.layout(&diagram);
+        assert!(bounds(&result, "actor_User").y + 70.0 <= bounds(&result, "system_System").y);
+        assert!(!result.elements.iter().any(|e| e.id == "rel_Login_User"));
+
+        // Слева направо: актёр левее системы
+        diagram.direction = Direction::LeftToRight;
+        let result = engine.layout(&diagram);
+        let actor = bounds(&result, "actor_User");
+        assert!(actor.x + actor.width <= bounds(&result, "system_System").x);
+    }
 }
@@ -9,6 +9,7 @@ use plantuml_ast::wbs::{WbsDiagram, WbsNode, WbsNodeStyle};
 use plantuml_model::{Point, Rect};
 
 use super::WbsLayoutConfig;
+use crate::text::TextMetrics;
 use crate::traits::LayoutResult;
 use crate::{ElementType, LayoutElement};
 
@@ -30,6 +31,12 @@ impl WbsLayoutEngine {
         Self { config }
     }
 
+    /// Устанавливает метрики текста (шрифт темы)
+    pub fn with_text_metrics(mut self, text: TextMetrics) -> Self {
+        self.config.text = text;
+        self
+    }
+
     /// Выполняет layout для диаграммы
     pub fn layout(&self, diagram: &WbsDiagram) -> LayoutResult {
         let mut elements = Vec::new();
@@ -141,8 +148,7 @@ impl WbsLayoutEngine {
 
     /// Вычисляет ширину узла
     fn calculate_node_width(&self, text: &str) -> f64 {
-        let char_width = self.config.font_size * 0.6;
-        let text_width = text.len() as f64 * char_width;
+        let text_width = self.config.text.width_at(text, self.config.font_size);
         (text_width + self.config.node_padding_x * 2.0).max(self.config.min_node_width)
     }
 
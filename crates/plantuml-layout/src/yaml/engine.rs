@@ -6,6 +6,7 @@ use plantuml_ast::json::JsonDiagram;
 use plantuml_ast::yaml::YamlDiagram;
 
 use crate::json::{JsonLayoutConfig, JsonLayoutEngine};
+use crate::text::TextMetrics;
 use crate::traits::{LayoutEngine, LayoutResult};
 use crate::LayoutConfig;
 
@@ -28,6 +29,12 @@ impl YamlLayoutEngine {
             json_engine: JsonLayoutEngine::with_config(config),
         }
     }
+
+    /// Устанавливает метрики текста (шрифт темы)
+    pub fn with_text_metrics(mut self, text: TextMetrics) -> Self {
+        self.json_engine = self.json_engine.with_text_metrics(text);
+        self
+    }
 }
 
 impl Default for YamlLayoutEngine {
@@ -41,3 +41,51 @@ pub enum ParseError {
     #[error("ошибка грамматики: {0}")]
     GrammarError(String),
 }
+
+impl ParseError {
+    /// Номер строки исходника (1-indexed), если ошибка к ней привязана
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            ParseError::UnexpectedToken { line, .. } | ParseError::SyntaxError { line, .. }
+                if *line > 0 =>
+            {
+                Some(*line)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Синтаксическая ошибка из ошибки pest: строка и описание без фрагмента текста
+///
+/// Фрагмент pest показывает текст после препроцессора, а не исходник
+/// пользователя, и повторяет номер строки.
+pub(crate) fn pest_error<R: pest::RuleType>(e: pest::error::Error<R>) -> ParseError {
+    ParseError::SyntaxError {
+        line: pest_line(&e),
+        message: e.variant.message().into_owned(),
+    }
+}
+
+/// Извлекает номер строки (1-indexed) из ошибки pest
+fn pest_line<R: pest::RuleType>(e: &pest::error::Error<R>) -> usize {
+    match e.line_col {
+        pest::error::LineColLocation::Pos((line, _))
+        | pest::error::LineColLocation::Span((line, _), _) => line,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_syntax_error_line() {
+        let err = ParseError::SyntaxError {
+            line: 3,
+            message: "ошибка".to_string(),
+        };
+        assert_eq!(err.line(), Some(3));
+        assert_eq!(ParseError::UnknownDiagramType.line(), None);
+    }
+}
@@ -9,6 +9,7 @@
 pub mod error;
 pub mod lexer;
 pub mod parsers;
+pub mod sprite;
 
 pub use error::ParseError;
 pub use parsers::{
@@ -17,6 +18,7 @@ pub use parsers::{
     parse_wbs, parse_yaml,
 };
 pub use plantuml_ast::Diagram;
+use plantuml_ast::common::DiagramScale;
 
 /// Результат парсинга
 pub type Result<T> = std::result::Result<T, ParseError>;
@@ -40,9 +42,49 @@ pub type Result<T> = std::result::Result<T, ParseError>;
 /// assert!(diagram.is_ok());
 /// ```
 pub fn parse(source: &str) -> Result<Diagram> {
-    // Определяем тип диаграммы
-    let diagram_type = detect_diagram_type(source)?;
+    // Спрайты общие для всех типов диаграмм
+    let (source, sprites) = sprite::extract_sprites(source)?;
+    let diagram_type = detect_diagram_type(&source)?;
+
+    // В gantt и timing у `scale` собственный смысл (масштаб шкалы времени)
+    let (source, scale) = match diagram_type {
+        DiagramKind::Gantt | DiagramKind::Timing => (source, None),
+        _ => extract_scale(&source),
+    };
+
+    let mut diagram = parse_kind(&source, diagram_type)?;
+    let metadata = diagram.metadata_mut();
+    metadata.sprites = sprites;
+    metadata.scale = scale;
+    Ok(diagram)
+}
 
+/// Извлекает директиву `scale` (последняя побеждает)
+///
+/// Строка директивы заменяется пустой, чтобы номера строк не сдвигались.
+fn extract_scale(source: &str) -> (String, Option<DiagramScale>) {
+    let mut scale = None;
+    let lines: Vec<&str> = source
+        .lines()
+        .map(|line| {
+            let parsed = line
+                .trim()
+                .strip_prefix("scale ")
+                .and_then(DiagramScale::parse);
+            match parsed {
+                Some(parsed) => {
+                    scale = Some(parsed);
+                    ""
+                }
+                None => line,
+            }
+        })
+        .collect();
+    (lines.join("\n"), scale)
+}
+
+/// Парсит исходник диаграммы известного типа
+fn parse_kind(source: &str, diagram_type: DiagramKind) -> Result<Diagram> {
     match diagram_type {
         DiagramKind::Sequence => parse_sequence_diagram(source),
         DiagramKind::Class => parse_class_diagram(source),
@@ -316,6 +358,12 @@ pub fn detect_diagram_type(source: &str) -> Result<DiagramKind> {
         return Ok(DiagramKind::UseCase);
     }
 
+    // rectangle — элемент только диаграмм описания (C4 model, component, deployment);
+    // проверяем РАНЬШЕ Sequence: в них бывают `database`/`queue` и стрелки `-->`
+    if source_lower.contains("rectangle ") && !source_lower.contains("participant ") {
+        return Ok(DiagramKind::Component);
+    }
+
     // Sequence Diagram — проверяем РАНЬШЕ Component!
     // participant и actor (без rectangle/usecase) — явные признаки sequence
     // database/collections/queue вместе с participant — это тоже sequence
@@ -609,6 +657,26 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_parse_scale_directive() {
+        let source = "@startuml\nscale 200 width\nAlice -> Bob\n@enduml";
+        let diagram = parse(source).unwrap();
+        assert_eq!(diagram.metadata().scale, Some(DiagramScale::Width(200.0)));
+
+        let source = "@startuml\nscale 1.5\nclass Foo\n@enduml";
+        let diagram = parse(source).unwrap();
+        assert!(matches!(diagram, Diagram::Class(_)));
+        assert_eq!(diagram.metadata().scale, Some(DiagramScale::Factor(1.5)));
+    }
+
+    #[test]
+    fn test_parse_collects_sprites() {
+        let source = "@startuml\nsprite $dot [1x1/16] {\nF\n}\nAlice -> Bob: <$dot>\n@enduml";
+        let diagram = parse(source).unwrap();
+        assert!(matches!(diagram, Diagram::Sequence(_)));
+        assert!(diagram.metadata().sprites.get("dot").is_some());
+    }
+
     #[test]
     fn test_detect_sequence_with_box() {
         let source = r#"@startuml
@@ -7,11 +7,12 @@ use pest_derive::Parser;
 
 use plantuml_ast::activity::{
     Action, ActionStyle, ActivityDiagram, ActivityElement, Condition, ElseIfBranch, Fork,
-    JoinType, RepeatLoop, WhileLoop,
+    JoinType, RepeatLoop, Swimlane, WhileLoop,
 };
-use plantuml_ast::common::{Color, Note, NotePosition};
+use plantuml_ast::common::{Color, Link, Note, NotePosition};
 
-use crate::{ParseError, Result};
+use super::metadata::extract_metadata;
+use crate::Result;
 
 #[derive(Parser)]
 #[grammar = "grammars/activity.pest"]
@@ -19,18 +20,19 @@ pub struct ActivityParser;
 
 /// Парсит activity diagram из исходного кода
 pub fn parse_activity(source: &str) -> Result<ActivityDiagram> {
+    let (source, metadata) = extract_metadata(source);
+    let source = source.as_str();
     let pairs =
-        ActivityParser::parse(Rule::diagram, source).map_err(|e| ParseError::SyntaxError {
-            line: e.line().to_string().parse().unwrap_or(0),
-            message: e.to_string(),
-        })?;
+        ActivityParser::parse(Rule::diagram, source).map_err(crate::error::pest_error)?;
 
     let mut diagram = ActivityDiagram::new();
+    diagram.metadata = metadata;
 
     for pair in pairs {
         if pair.as_rule() == Rule::diagram {
             for inner in pair.into_inner() {
                 if inner.as_rule() == Rule::body {
+                    diagram.swimlanes = collect_swimlanes(inner.clone());
                     diagram.elements = parse_body(inner);
                 }
             }
@@ -68,7 +70,7 @@ fn parse_statement(pair: pest::iterators::Pair<Rule>) -> Option<ActivityElement>
         Rule::while_stmt => parse_while_stmt(pair).map(ActivityElement::While),
         Rule::repeat_stmt => parse_repeat_stmt(pair).map(ActivityElement::Repeat),
         Rule::fork_stmt => parse_fork_stmt(pair).map(ActivityElement::Fork),
-        Rule::swimlane_stmt => parse_swimlane(pair).map(|s| ActivityElement::SwimlaneChange(s)),
+        Rule::swimlane_stmt => parse_swimlane(pair).map(|s| ActivityElement::SwimlaneChange(s.name)),
         Rule::connector_stmt => parse_connector(pair).map(ActivityElement::Connector),
         Rule::note_stmt | Rule::note_inline | Rule::note_multiline => {
             parse_note(pair).map(ActivityElement::Note)
@@ -101,8 +103,10 @@ fn parse_action(pair: pest::iterators::Pair<Rule>) -> Option<Action> {
                 };
             }
             Rule::action_simple | Rule::action_colored | Rule::action_multiline => {
-                // Рекурсивно обрабатываем вложенные action
-                return parse_action(inner);
+                // Рекурсивно обрабатываем вложенные action, сохраняя цвет
+                let mut action = parse_action(inner)?;
+                action.background_color = background_color.or(action.background_color);
+                return Some(action);
             }
             _ => {}
         }
@@ -336,15 +340,46 @@ fn parse_fork_stmt(pair: pest::iterators::Pair<Rule>) -> Option<Fork> {
 }
 
 /// Парсит swimlane
-fn parse_swimlane(pair: pest::iterators::Pair<Rule>) -> Option<String> {
+fn parse_swimlane(pair: pest::iterators::Pair<Rule>) -> Option<Swimlane> {
+    let mut color = None;
     for inner in pair.into_inner() {
-        if inner.as_rule() == Rule::swimlane_name {
-            return Some(inner.as_str().trim().to_string());
+        match inner.as_rule() {
+            Rule::swimlane_color => color = Some(Color::parse(inner.as_str())),
+            Rule::swimlane_name => {
+                return Some(Swimlane {
+                    name: inner.as_str().trim().to_string(),
+                    color,
+                })
+            }
+            _ => {}
         }
     }
     None
 }
 
+/// Собирает объявления дорожек в порядке первого появления (включая
+/// вложенные блоки); цвет берётся из первого объявления, где он указан
+fn collect_swimlanes(body: pest::iterators::Pair<Rule>) -> Vec<Swimlane> {
+    let mut swimlanes: Vec<Swimlane> = Vec::new();
+    for pair in body.into_inner().flatten() {
+        if pair.as_rule() != Rule::swimlane_stmt {
+            continue;
+        }
+        let Some(swimlane) = parse_swimlane(pair) else {
+            continue;
+        };
+        match swimlanes.iter_mut().find(|s| s.name == swimlane.name) {
+            Some(existing) => {
+                if existing.color.is_none() {
+                    existing.color = swimlane.color;
+                }
+            }
+            None => swimlanes.push(swimlane),
+        }
+    }
+    swimlanes
+}
+
 /// Парсит коннектор
 fn parse_connector(pair: pest::iterators::Pair<Rule>) -> Option<String> {
     for inner in pair.into_inner() {
@@ -384,11 +419,14 @@ fn parse_note(pair: pest::iterators::Pair<Rule>) -> Option<Note> {
         return None;
     }
 
+    let (text, link) = Link::extract(&text);
+
     Some(Note {
         text,
         position,
         anchors: vec![],
         background_color: None,
+        link,
     })
 }
 
@@ -404,20 +442,7 @@ fn extract_label(pair: pest::iterators::Pair<Rule>) -> String {
 
 /// Парсит цвет
 fn parse_color(pair: pest::iterators::Pair<Rule>) -> Option<Color> {
-    // Сначала попробуем распарсить весь текст как цвет
-    let text = pair.as_str();
-    if let Some(hex) = text.strip_prefix('#') {
-        return Some(Color::from_hex(hex));
-    }
-    
-    // Попробуем найти hex_color внутри
-    for inner in pair.into_inner() {
-        if inner.as_rule() == Rule::hex_color {
-            let hex = inner.as_str();
-            return Some(Color::from_hex(hex));
-        }
-    }
-    None
+    Some(Color::parse(pair.as_str()))
 }
 
 #[cfg(test)]
@@ -547,6 +572,34 @@ stop
         assert_eq!(swimlanes.len(), 2);
     }
 
+    #[test]
+    fn test_parse_swimlane_declarations() {
+        let source = r#"
+@startuml
+|#AntiqueWhite|Клиент|
+start
+:заказ;
+|#FF0000|Склад|
+:сборка;
+|Клиент|
+:получение;
+stop
+@enduml
+"#;
+
+        let diagram = parse_activity(source).unwrap();
+
+        assert_eq!(diagram.swimlanes.len(), 2);
+        assert_eq!(diagram.swimlanes[0].name, "Клиент");
+        assert_eq!(diagram.swimlanes[0].color, Some(Color::named("AntiqueWhite")));
+        assert_eq!(diagram.swimlanes[1].name, "Склад");
+        assert_eq!(diagram.swimlanes[1].color, Some(Color::from_hex("FF0000")));
+        assert!(matches!(
+            diagram.elements.last(),
+            Some(ActivityElement::Stop)
+        ));
+    }
+
     #[test]
     fn test_parse_repeat() {
         let source = r#"
@@ -566,4 +619,35 @@ stop
         });
         assert!(repeat.is_some());
     }
+
+    #[test]
+    fn test_parse_action_colors() {
+        let source = r#"
+@startuml
+#pink:named;
+#FF0000:hex;
+#red/blue:gradient;
+:plain;
+@enduml
+"#;
+
+        let diagram = parse_activity(source).unwrap();
+        let colors: Vec<Option<String>> = diagram
+            .elements
+            .iter()
+            .filter_map(|e| match e {
+                ActivityElement::Action(action) => Some(action.background_color.as_ref().map(Color::to_css)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            colors,
+            vec![
+                Some("pink".to_string()),
+                Some("#FF0000".to_string()),
+                Some("red/blue".to_string()),
+                None,
+            ]
+        );
+    }
 }
@@ -0,0 +1,95 @@
+//! Подсказки размещения в тексте стрелок
+//!
+//! Общие для class, component, usecase, state, object и sequence диаграмм:
+//! направление (`-up->`, `-l->`, `.right.>`) и стиль в скобках
+//! (`-[hidden]->`, `-[#red,dashed]up->`).
+
+use plantuml_ast::common::{Color, Direction, LineStyle};
+
+/// Подсказки стрелки
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArrowHints {
+    /// Где цель относительно источника
+    pub direction: Option<Direction>,
+    /// `[hidden]` — связь только для layout
+    pub hidden: bool,
+    /// Цвет линии: `[#red]`
+    pub color: Option<Color>,
+    /// Стиль линии: `[dashed]`, `[dotted]`, `[bold]`
+    pub line_style: Option<LineStyle>,
+}
+
+/// Извлекает подсказки из текста стрелки
+pub fn arrow_hints(arrow: &str) -> ArrowHints {
+    let mut hints = ArrowHints::default();
+    let mut rest = arrow;
+
+    // Стиль в квадратных скобках: `[hidden]`, `[#red,dashed]`
+    while let Some(start) = rest.find('[') {
+        let Some(len) = rest[start..].find(']') else {
+            break;
+        };
+        for part in rest[start + 1..start + len].split([',', ';']).map(str::trim) {
+            if part.eq_ignore_ascii_case("hidden") {
+                hints.hidden = true;
+            } else if let Some(color) = part.strip_prefix('#') {
+                hints.color = Some(Color::parse(color));
+            } else if let Some(style) = LineStyle::parse(part) {
+                hints.line_style = Some(style);
+            }
+        }
+        rest = &rest[start + len + 1..];
+    }
+
+    // Направление — слово между линиями стрелки (не наконечник `o`/`x`)
+    let line: String = arrow.split(['[', ']']).step_by(2).collect();
+    let is_line = |c: Option<char>| matches!(c, Some('-' | '.'));
+    let mut start = 0;
+    for word in line.split(|c: char| !c.is_ascii_alphabetic()) {
+        let end = start + word.len();
+        if !word.is_empty() && is_line(line[..start].chars().last()) && is_line(line[end..].chars().next()) {
+            hints.direction = match word.to_ascii_lowercase().as_str() {
+                "up" | "u" => Some(Direction::BottomToTop),
+                "down" | "do" | "d" => Some(Direction::TopToBottom),
+                "left" | "le" | "l" => Some(Direction::RightToLeft),
+                "right" | "ri" | "r" => Some(Direction::LeftToRight),
+                _ => None,
+            };
+        }
+        start = end + 1;
+    }
+
+    hints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arrow_hints() {
+        assert_eq!(arrow_hints("-->"), ArrowHints::default());
+        assert_eq!(arrow_hints("-up->").direction, Some(Direction::BottomToTop));
+        assert_eq!(arrow_hints("<-l-").direction, Some(Direction::RightToLeft));
+        assert_eq!(arrow_hints(".right.>").direction, Some(Direction::LeftToRight));
+        assert_eq!(arrow_hints("-DOWN->").direction, Some(Direction::TopToBottom));
+        assert_eq!(arrow_hints("o-up-").direction, Some(Direction::BottomToTop));
+        assert_eq!(arrow_hints("o--").direction, None);
+
+        let hidden = arrow_hints("-[hidden]->");
+        assert!(hidden.hidden);
+        assert_eq!(hidden.direction, None);
+
+        let styled = arrow_hints("-[#red,hidden]up->");
+        assert!(styled.hidden);
+        assert_eq!(styled.direction, Some(Direction::BottomToTop));
+        assert_eq!(styled.color, Some(Color::named("red")));
+
+        let dashed = arrow_hints("-[#0000FF,dashed]>");
+        assert_eq!(dashed.color, Some(Color::from_hex("0000FF")));
+        assert_eq!(dashed.line_style, Some(LineStyle::Dashed));
+
+        // Буквы цвета в скобках — не направление
+        assert_eq!(arrow_hints("-[#dodgerblue]->").direction, None);
+    }
+}
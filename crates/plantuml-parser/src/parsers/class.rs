@@ -9,9 +9,13 @@ use plantuml_ast::class::{
     ClassDiagram, Classifier, ClassifierType, Member, Package, Relationship, RelationshipType,
     Visibility,
 };
-use plantuml_ast::common::{Color, LineStyle, Stereotype};
+use plantuml_ast::common::{Color, InlineStyle, LineStyle, Link, Stereotype};
 
-use crate::{ParseError, Result};
+use super::arrow::{arrow_hints, ArrowHints};
+use super::pair_span;
+use super::metadata::extract_metadata;
+use super::visibility::extract_visibility_rules;
+use crate::Result;
 
 #[derive(Parser)]
 #[grammar = "grammars/class.pest"]
@@ -19,12 +23,14 @@ pub struct ClassParser;
 
 /// Парсит class diagram из исходного кода
 pub fn parse_class(source: &str) -> Result<ClassDiagram> {
-    let pairs = ClassParser::parse(Rule::diagram, source).map_err(|e| ParseError::SyntaxError {
-        line: e.line().to_string().parse().unwrap_or(0),
-        message: e.to_string(),
-    })?;
+    let (source, metadata) = extract_metadata(source);
+    let (source, visibility_rules) = extract_visibility_rules(&source);
+    let source = source.as_str();
+    let pairs = ClassParser::parse(Rule::diagram, source).map_err(crate::error::pest_error)?;
 
     let mut diagram = ClassDiagram::new();
+    diagram.metadata = metadata;
+    diagram.visibility_rules = visibility_rules;
     let mut package_stack: Vec<Package> = Vec::new();
 
     for pair in pairs {
@@ -44,6 +50,7 @@ fn process_rule(
     diagram: &mut ClassDiagram,
     package_stack: &mut Vec<Package>,
 ) {
+    let span = pair_span(&pair);
     match pair.as_rule() {
         Rule::class_decl => {
             if let Some(result) = parse_class_decl_with_inheritance(pair, ClassifierType::Class) {
@@ -59,7 +66,10 @@ fn process_rule(
                         from_cardinality: None,
                         to_cardinality: None,
                         line_style: plantuml_ast::common::LineStyle::Solid,
+                        style: InlineStyle::default(),
                         direction: None,
+                        hidden: false,
+                        span,
                     });
                 }
                 // Создаём relationship для implements
@@ -72,7 +82,10 @@ fn process_rule(
                         from_cardinality: None,
                         to_cardinality: None,
                         line_style: plantuml_ast::common::LineStyle::Dashed,
+                        style: InlineStyle::default(),
                         direction: None,
+                        hidden: false,
+                        span,
                     });
                 }
             }
@@ -91,7 +104,10 @@ fn process_rule(
                         from_cardinality: None,
                         to_cardinality: None,
                         line_style: plantuml_ast::common::LineStyle::Solid,
+                        style: InlineStyle::default(),
                         direction: None,
+                        hidden: false,
+                        span,
                     });
                 }
             }
@@ -109,7 +125,10 @@ fn process_rule(
                         from_cardinality: None,
                         to_cardinality: None,
                         line_style: plantuml_ast::common::LineStyle::Solid,
+                        style: InlineStyle::default(),
                         direction: None,
+                        hidden: false,
+                        span,
                     });
                 }
                 for iface in result.implements {
@@ -121,7 +140,10 @@ fn process_rule(
                         from_cardinality: None,
                         to_cardinality: None,
                         line_style: plantuml_ast::common::LineStyle::Dashed,
+                        style: InlineStyle::default(),
                         direction: None,
+                        hidden: false,
+                        span,
                     });
                 }
             }
@@ -200,11 +222,14 @@ fn parse_class_decl_with_inheritance(
     pair: pest::iterators::Pair<Rule>,
     default_type: ClassifierType,
 ) -> Option<ClassDeclResult> {
+    let span = pair_span(&pair);
     let mut name = String::new();
     let mut classifier_type = default_type;
     let mut stereotype: Option<Stereotype> = None;
-    let mut color: Option<Color> = None;
+    let mut style = InlineStyle::default();
     let mut generics: Option<String> = None;
+    let mut link: Option<Link> = None;
+    let mut tags: Vec<String> = Vec::new();
     let mut fields: Vec<Member> = Vec::new();
     let mut methods: Vec<Member> = Vec::new();
     let mut extends: Option<String> = None;
@@ -226,12 +251,18 @@ fn parse_class_decl_with_inheritance(
                 let content = s.trim_start_matches("<<").trim_end_matches(">>");
                 stereotype = Some(Stereotype::new(content));
             }
+            Rule::tag => {
+                tags.push(inner.as_str().trim_start_matches('$').to_string());
+            }
             Rule::color => {
-                color = Some(Color::from_hex(inner.as_str()));
+                style = InlineStyle::parse(inner.as_str());
             }
             Rule::generic_params => {
                 generics = Some(inner.as_str().to_string());
             }
+            Rule::link => {
+                link = Link::parse(inner.as_str());
+            }
             Rule::extends_clause => {
                 // extends_clause = { "extends" ~ ws+ ~ class_name }
                 for ext_inner in inner.into_inner() {
@@ -266,9 +297,15 @@ fn parse_class_decl_with_inheritance(
             fields,
             methods,
             stereotype,
-            background_color: color,
-            border_color: None,
+            background_color: style.fill,
+            border_color: style.line_color,
+            text_color: style.text_color,
+            line_style: style.line_style,
             generics,
+            link,
+            tags,
+            hidden_parts: Default::default(),
+            span,
         },
         extends,
         implements,
@@ -473,6 +510,7 @@ fn parse_visibility(s: &str) -> Visibility {
 
 /// Парсит отношение
 fn parse_relationship(pair: pest::iterators::Pair<Rule>) -> Option<Relationship> {
+    let span = pair_span(&pair);
     let mut from = String::new();
     let mut to = String::new();
     let mut label: Option<String> = None;
@@ -480,6 +518,8 @@ fn parse_relationship(pair: pest::iterators::Pair<Rule>) -> Option<Relationship>
     let mut line_style = LineStyle::Solid;
     let mut from_cardinality: Option<String> = None;
     let mut to_cardinality: Option<String> = None;
+    let mut hints = ArrowHints::default();
+    let mut style = InlineStyle::default();
     let mut seen_arrow = false;
 
     for inner in pair.into_inner() {
@@ -504,10 +544,14 @@ fn parse_relationship(pair: pest::iterators::Pair<Rule>) -> Option<Relationship>
             }
             Rule::relationship_arrow => {
                 seen_arrow = true;
+                hints = arrow_hints(inner.as_str());
                 let (rtype, lstyle) = parse_arrow(inner);
                 rel_type = rtype;
                 line_style = lstyle;
             }
+            Rule::color => {
+                style = InlineStyle::parse(inner.as_str());
+            }
             Rule::relationship_label => {
                 let text = inner.as_str().trim();
                 if !text.is_empty() {
@@ -522,6 +566,10 @@ fn parse_relationship(pair: pest::iterators::Pair<Rule>) -> Option<Relationship>
         return None;
     }
 
+    // У связи `#blue` — цвет линии; стиль после связи важнее стиля в стрелке
+    style.line_color = style.line_color.or(style.fill.take()).or(hints.color);
+    style.line_style = style.line_style.or(hints.line_style);
+
     Some(Relationship {
         from,
         to,
@@ -530,7 +578,10 @@ fn parse_relationship(pair: pest::iterators::Pair<Rule>) -> Option<Relationship>
         from_cardinality,
         to_cardinality,
         line_style,
-        direction: None,
+        style,
+        direction: hints.direction,
+        hidden: hints.hidden,
+        span,
     })
 }
 
@@ -566,7 +617,7 @@ fn parse_arrow(pair: pest::iterators::Pair<Rule>) -> (RelationshipType, LineStyl
         }
     }
 
-    let line_style = if line.contains("..") || line == "." {
+    let line_style = if line.starts_with('.') {
         LineStyle::Dashed
     } else {
         LineStyle::Solid
@@ -613,7 +664,7 @@ fn parse_package_start(pair: pest::iterators::Pair<Rule>) -> Package {
                 stereotype = Some(Stereotype::new(content));
             }
             Rule::color => {
-                color = Some(Color::from_hex(inner.as_str()));
+                color = InlineStyle::parse(inner.as_str()).fill;
             }
             _ => {}
         }
@@ -661,6 +712,7 @@ fn extract_name(pair: pest::iterators::Pair<Rule>) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use plantuml_ast::common::Direction;
 
     #[test]
     fn test_parse_simple_class() {
@@ -684,6 +736,19 @@ class User {
         assert_eq!(user.classifier_type, ClassifierType::Class);
         assert_eq!(user.fields.len(), 2);
         assert_eq!(user.methods.len(), 2);
+        assert_eq!(user.span.line, 2);
+    }
+
+    #[test]
+    fn test_source_spans() {
+        let source = "@startuml\nclass A extends B\n\nA --> C : uses\n@enduml";
+        let diagram = parse_class(source).unwrap();
+
+        let a = diagram.classifiers.iter().find(|c| c.id.name == "A").unwrap();
+        assert_eq!((a.span.line, a.span.column), (2, 1));
+        // Неявное наследование ведёт к объявлению класса
+        assert_eq!(diagram.relationships[0].span.line, 2);
+        assert_eq!(diagram.relationships[1].span.line, 4);
     }
 
     #[test]
@@ -849,4 +914,79 @@ Customer "1" --o "0..*" Order
         assert_eq!(rel.to_cardinality, Some("0..*".to_string()));
         assert_eq!(rel.relationship_type, RelationshipType::Aggregation);
     }
+
+    #[test]
+    fn test_parse_class_link() {
+        let source = r#"@startuml
+class User [[https://example.com/user{Пользователь}]] {
+    -id: Long
+}
+interface Repo [[https://example.com/repo]]
+@enduml"#;
+
+        let diagram = parse_class(source).unwrap();
+        let user = &diagram.classifiers[0];
+        let link = user.link.as_ref().expect("class link");
+        assert_eq!(link.url, "https://example.com/user");
+        assert_eq!(link.tooltip.as_deref(), Some("Пользователь"));
+        assert_eq!(user.fields.len(), 1);
+        assert_eq!(
+            diagram.classifiers[1].link.as_ref().unwrap().url,
+            "https://example.com/repo"
+        );
+    }
+
+    #[test]
+    fn test_parse_arrow_hints() {
+        let source = "@startuml\nleft to right direction\nDog -up-|> Animal\nDog .[hidden]. Cat\nCat *-l- Tail\n@enduml";
+        let diagram = parse_class(source).unwrap();
+
+        assert_eq!(diagram.metadata.direction, Direction::LeftToRight);
+        assert_eq!(diagram.relationships.len(), 3);
+        let up = &diagram.relationships[0];
+        assert_eq!(up.relationship_type, RelationshipType::Inheritance);
+        assert_eq!(up.direction, Some(Direction::BottomToTop));
+        assert!(!up.hidden);
+        let hidden = &diagram.relationships[1];
+        assert!(hidden.hidden);
+        assert_eq!(hidden.line_style, LineStyle::Dashed);
+        assert_eq!(diagram.relationships[2].direction, Some(Direction::RightToLeft));
+        assert_eq!(diagram.relationships[2].relationship_type, RelationshipType::Composition);
+    }
+
+    #[test]
+    fn test_parse_relationship_inline_style() {
+        let source = "@startuml\nFoo --> Bar #blue\nFoo ..> Baz #blue;line.bold;text:red : uses\nBar -[#green,dotted]-> Baz\n@enduml";
+        let diagram = parse_class(source).unwrap();
+
+        assert_eq!(diagram.relationships.len(), 3);
+        let plain = &diagram.relationships[0];
+        assert_eq!(plain.to, "Bar");
+        assert_eq!(plain.style.line_color, Some(Color::parse("blue")));
+        assert_eq!(plain.style.fill, None);
+        assert_eq!(plain.style.line_style, None);
+
+        let bold = &diagram.relationships[1];
+        assert_eq!(bold.to, "Baz");
+        assert_eq!(bold.label.as_deref(), Some("uses"));
+        assert_eq!(bold.line_style, LineStyle::Dashed);
+        assert_eq!(bold.style.line_style, Some(LineStyle::Bold));
+        assert_eq!(bold.style.text_color, Some(Color::parse("red")));
+
+        let hinted = &diagram.relationships[2];
+        assert_eq!(hinted.style.line_color, Some(Color::parse("green")));
+        assert_eq!(hinted.style.line_style, Some(LineStyle::Dotted));
+    }
+
+    #[test]
+    fn test_parse_visibility_rules_and_tags() {
+        let source = "@startuml\nhide empty members\nshow User fields\nclass User <<Entity>> $internal $core {\n  +show()\n}\nremove $internal\n@enduml";
+        let diagram = parse_class(source).unwrap();
+
+        assert_eq!(diagram.visibility_rules.len(), 3);
+        let user = &diagram.classifiers[0];
+        assert_eq!(user.tags, vec!["internal", "core"]);
+        assert_eq!(user.stereotype.as_ref().unwrap().names, vec!["Entity"]);
+        assert_eq!(user.methods[0].name, "show");
+    }
 }
@@ -8,9 +8,12 @@ use pest_derive::Parser;
 use plantuml_ast::component::{
     Component, ComponentDiagram, ComponentPackage, ComponentType, Connection, PackageType,
 };
-use plantuml_ast::common::{Color, Note, NotePosition, Stereotype};
+use plantuml_ast::common::{Color, Link, Note, NotePosition, Stereotype};
 
-use crate::{ParseError, Result};
+use super::arrow::{arrow_hints, ArrowHints};
+use super::metadata::extract_metadata;
+use super::visibility::extract_visibility_rules;
+use crate::Result;
 
 #[derive(Parser)]
 #[grammar = "grammars/component.pest"]
@@ -18,14 +21,14 @@ pub struct ComponentParser;
 
 /// Парсит component diagram из исходного кода
 pub fn parse_component(source: &str) -> Result<ComponentDiagram> {
-    let pairs = ComponentParser::parse(Rule::diagram, source).map_err(|e| {
-        ParseError::SyntaxError {
-            line: e.line().to_string().parse().unwrap_or(0),
-            message: e.to_string(),
-        }
-    })?;
+    let (source, metadata) = extract_metadata(source);
+    let (source, visibility_rules) = extract_visibility_rules(&source);
+    let source = source.as_str();
+    let pairs = ComponentParser::parse(Rule::diagram, source).map_err(crate::error::pest_error)?;
 
     let mut diagram = ComponentDiagram::new();
+    diagram.metadata = metadata;
+    diagram.visibility_rules = visibility_rules;
 
     for pair in pairs {
         if pair.as_rule() == Rule::diagram {
@@ -80,6 +83,7 @@ fn parse_component_def(pair: pest::iterators::Pair<Rule>) -> Option<Component> {
     let mut alias: Option<String> = None;
     let mut component_type = ComponentType::Component;
     let mut stereotype: Option<Stereotype> = None;
+    let mut tags: Vec<String> = Vec::new();
     let mut color: Option<Color> = None;
 
     for inner in pair.into_inner() {
@@ -101,6 +105,9 @@ fn parse_component_def(pair: pest::iterators::Pair<Rule>) -> Option<Component> {
             Rule::stereotype_part => {
                 stereotype = extract_stereotype(inner);
             }
+            Rule::tag_part => {
+                tags.extend(extract_tag(inner));
+            }
             Rule::color_part => {
                 color = extract_color(inner);
             }
@@ -121,6 +128,7 @@ fn parse_component_def(pair: pest::iterators::Pair<Rule>) -> Option<Component> {
         children: Vec::new(),
         interfaces: Vec::new(),
         ports: Vec::new(),
+        tags,
     })
 }
 
@@ -128,6 +136,8 @@ fn parse_component_def(pair: pest::iterators::Pair<Rule>) -> Option<Component> {
 fn parse_interface_def(pair: pest::iterators::Pair<Rule>) -> Option<Component> {
     let mut name = String::new();
     let mut alias: Option<String> = None;
+    let mut stereotype: Option<Stereotype> = None;
+    let mut tags: Vec<String> = Vec::new();
 
     for inner in pair.into_inner() {
         match inner.as_rule() {
@@ -140,6 +150,12 @@ fn parse_interface_def(pair: pest::iterators::Pair<Rule>) -> Option<Component> {
                         Rule::alias_part => {
                             alias = extract_alias(iface_inner);
                         }
+                        Rule::stereotype_part => {
+                            stereotype = extract_stereotype(iface_inner);
+                        }
+                        Rule::tag_part => {
+                            tags.extend(extract_tag(iface_inner));
+                        }
                         _ => {}
                     }
                 }
@@ -156,11 +172,12 @@ fn parse_interface_def(pair: pest::iterators::Pair<Rule>) -> Option<Component> {
         name,
         alias,
         component_type: ComponentType::Interface,
-        stereotype: None,
+        stereotype,
         color: None,
         children: Vec::new(),
         interfaces: Vec::new(),
         ports: Vec::new(),
+        tags,
     })
 }
 
@@ -243,6 +260,7 @@ fn parse_connection(pair: pest::iterators::Pair<Rule>) -> Option<Connection> {
     let mut to = String::new();
     let mut label: Option<String> = None;
     let mut dashed = false;
+    let mut hints = ArrowHints::default();
 
     for inner in pair.into_inner() {
         match inner.as_rule() {
@@ -253,6 +271,7 @@ fn parse_connection(pair: pest::iterators::Pair<Rule>) -> Option<Connection> {
                 to = extract_connection_endpoint(inner);
             }
             Rule::arrow => {
+                hints = arrow_hints(inner.as_str());
                 // Проверяем тип стрелки
                 for arrow_inner in inner.into_inner() {
                     if arrow_inner.as_rule() == Rule::arrow_dashed {
@@ -276,8 +295,10 @@ fn parse_connection(pair: pest::iterators::Pair<Rule>) -> Option<Connection> {
         to,
         connection_type: plantuml_ast::component::ConnectionType::Simple,
         label,
-        color: None,
+        color: hints.color,
         dashed,
+        direction: hints.direction,
+        hidden: hints.hidden,
     })
 }
 
@@ -347,11 +368,14 @@ fn parse_note(pair: pest::iterators::Pair<Rule>) -> Option<Note> {
         return None;
     }
 
+    let (text, link) = Link::extract(&text);
+
     Some(Note {
         text,
         position,
         anchors,
         background_color: None,
+        link,
     })
 }
 
@@ -369,6 +393,9 @@ fn parse_package_type(s: &str) -> PackageType {
         "frame" => PackageType::Frame,
         "cloud" => PackageType::Cloud,
         "rectangle" => PackageType::Rectangle,
+        "database" => PackageType::Database,
+        "storage" => PackageType::Storage,
+        "queue" => PackageType::Queue,
         _ => PackageType::Package,
     }
 }
@@ -428,13 +455,17 @@ fn extract_stereotype(pair: pest::iterators::Pair<Rule>) -> Option<Stereotype> {
     None
 }
 
+/// Имя тега из `$tag`
+fn extract_tag(pair: pest::iterators::Pair<Rule>) -> Option<String> {
+    pair.into_inner()
+        .find(|inner| inner.as_rule() == Rule::tag_name)
+        .map(|inner| inner.as_str().to_string())
+}
+
 fn extract_color(pair: pest::iterators::Pair<Rule>) -> Option<Color> {
     for inner in pair.into_inner() {
         if inner.as_rule() == Rule::color {
-            let text = inner.as_str();
-            if let Some(hex) = text.strip_prefix('#') {
-                return Some(Color::from_hex(hex));
-            }
+            return Some(Color::parse(inner.as_str()));
         }
     }
     None
@@ -475,6 +506,7 @@ fn extract_note_target(pair: pest::iterators::Pair<Rule>) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use plantuml_ast::common::Direction;
 
     #[test]
     fn test_parse_simple_component() {
@@ -615,4 +647,29 @@ node "Outer" {
         assert_eq!(diagram.packages[0].packages[0].name, "Inner");
         assert_eq!(diagram.packages[0].packages[0].components.len(), 1, "Should have 1 component");
     }
+
+    #[test]
+    fn test_parse_arrow_hints() {
+        let source = "@startuml\n[A] -up-> [B]\n[A] .r.> [C]\n[B] -[hidden]- [C]\n@enduml";
+        let diagram = parse_component(source).unwrap();
+
+        assert_eq!(diagram.connections.len(), 3);
+        assert_eq!(diagram.connections[0].direction, Some(Direction::BottomToTop));
+        assert_eq!(diagram.connections[1].direction, Some(Direction::LeftToRight));
+        assert!(diagram.connections[1].dashed);
+        assert!(diagram.connections[2].hidden);
+    }
+
+    #[test]
+    fn test_parse_visibility_rules_and_tags() {
+        let source = "@startuml\ncomponent Api $internal\n[Web] $public\ninterface IApi <<rest>> $internal\nremove $internal\nhide @unlinked\n@enduml";
+        let diagram = parse_component(source).unwrap();
+
+        assert_eq!(diagram.visibility_rules.len(), 2);
+        assert_eq!(diagram.components.len(), 3);
+        assert_eq!(diagram.components[0].tags, vec!["internal"]);
+        assert_eq!(diagram.components[1].tags, vec!["public"]);
+        assert_eq!(diagram.components[2].stereotype.as_ref().unwrap().names, vec!["rest"]);
+        assert_eq!(diagram.components[2].tags, vec!["internal"]);
+    }
 }
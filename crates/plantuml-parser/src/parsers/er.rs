@@ -7,6 +7,7 @@ use pest_derive::Parser;
 
 use plantuml_ast::er::{Attribute, Cardinality, Entity, ErDiagram, ErRelationship};
 
+use super::metadata::extract_metadata;
 use crate::error::ParseError;
 
 #[derive(Parser)]
@@ -15,10 +16,13 @@ struct ErParser;
 
 /// Парсит ER диаграмму
 pub fn parse_er(source: &str) -> crate::Result<ErDiagram> {
+    let (source, metadata) = extract_metadata(source);
+    let source = source.as_str();
     let pairs = ErParser::parse(Rule::er_diagram, source)
         .map_err(|e| ParseError::GrammarError(format!("Ошибка парсинга ER: {}", e)))?;
 
     let mut diagram = ErDiagram::new();
+    diagram.metadata = metadata;
 
     for pair in pairs {
         if pair.as_rule() == Rule::er_diagram {
@@ -69,8 +73,7 @@ fn parse_entity(pair: pest::iterators::Pair<Rule>) -> crate::Result<Entity> {
             }
             Rule::color_spec => {
                 if let Some(color) = inner.into_inner().next() {
-                    entity.background_color =
-                        Some(plantuml_ast::common::Color::from_hex(format!("#{}", color.as_str())));
+                    entity.background_color = Some(plantuml_ast::common::Color::parse(color.as_str()));
                 }
             }
             Rule::entity_member => {
@@ -5,12 +5,14 @@
 use pest::Parser;
 use pest_derive::Parser;
 
+use plantuml_ast::common::Color;
 use plantuml_ast::gantt::{
-    ClosedDay, GanttDate, GanttDiagram, GanttMilestone, GanttScale, GanttSeparator, GanttTask,
-    Holiday, MilestoneTime, TaskDuration, TaskStart, Weekday,
+    ClosedDay, GanttDate, GanttDiagram, GanttMilestone, GanttResource, GanttScale, GanttSeparator,
+    GanttTask, Holiday, MilestoneTime, TaskDuration, TaskStart, Weekday,
 };
 
-use crate::{ParseError, Result};
+use super::metadata::extract_metadata;
+use crate::Result;
 
 #[derive(Parser)]
 #[grammar = "grammars/gantt.pest"]
@@ -18,12 +20,12 @@ pub struct GanttParser;
 
 /// Парсит gantt diagram из исходного кода
 pub fn parse_gantt(source: &str) -> Result<GanttDiagram> {
-    let pairs = GanttParser::parse(Rule::diagram, source).map_err(|e| ParseError::SyntaxError {
-        line: e.line().to_string().parse().unwrap_or(0),
-        message: e.to_string(),
-    })?;
+    let (source, metadata) = extract_metadata(source);
+    let source = source.as_str();
+    let pairs = GanttParser::parse(Rule::diagram, source).map_err(crate::error::pest_error)?;
 
     let mut diagram = GanttDiagram::new();
+    diagram.metadata = metadata;
     let mut last_task_id: Option<String> = None;
 
     for pair in pairs {
@@ -68,16 +70,20 @@ fn parse_body(
                     diagram.holidays.push(holiday);
                 }
             }
+            Rule::today_stmt => {
+                diagram.today_color = parse_color_modifier(inner.clone());
+                diagram.today = parse_today(inner);
+            }
             Rule::task_def => {
-                if let Some(task) = parse_task(inner, last_task_id) {
-                    *last_task_id = task.id.clone().or_else(|| Some(task.name.clone()));
-                    diagram.tasks.push(task);
+                if let Some(task_id) = parse_task(inner, diagram, None) {
+                    *last_task_id = Some(task_id);
                 }
             }
             Rule::then_stmt => {
-                if let Some(task) = parse_then_task(inner, last_task_id) {
-                    *last_task_id = task.id.clone().or_else(|| Some(task.name.clone()));
-                    diagram.tasks.push(task);
+                // then задача начинается после предыдущей
+                let start = last_task_id.clone().map(TaskStart::After);
+                if let Some(task_id) = parse_task(inner, diagram, start) {
+                    *last_task_id = Some(task_id);
                 }
             }
             Rule::milestone_def => {
@@ -88,6 +94,12 @@ fn parse_body(
             Rule::separator => {
                 diagram.separators.push(parse_separator(inner));
             }
+            Rule::link_stmt => {
+                parse_link(inner, diagram);
+            }
+            Rule::hide_stmt => {
+                parse_hide(inner, diagram);
+            }
             _ => {}
         }
     }
@@ -167,46 +179,28 @@ fn parse_holiday(pair: pest::iterators::Pair<Rule>) -> Option<Holiday> {
     })
 }
 
-/// Парсит задачу
-fn parse_task(
-    pair: pest::iterators::Pair<Rule>,
-    _last_task_id: &Option<String>,
-) -> Option<GanttTask> {
-    let mut name = String::new();
-    let mut task = GanttTask::new("");
-
+/// Парсит today is
+fn parse_today(pair: pest::iterators::Pair<Rule>) -> Option<GanttDate> {
     for inner in pair.into_inner() {
-        match inner.as_rule() {
-            Rule::task_name => {
-                name = extract_task_name(inner);
-            }
-            Rule::task_modifiers => {
-                parse_task_modifiers(inner, &mut task);
-            }
-            _ => {}
+        if inner.as_rule() == Rule::date_value {
+            return GanttDate::parse(inner.as_str());
         }
     }
-
-    if name.is_empty() {
-        return None;
-    }
-
-    task.name = name;
-    Some(task)
+    None
 }
 
-/// Парсит then task
-fn parse_then_task(
+/// Парсит задачу
+///
+/// Повторное упоминание задачи по имени или алиасу (`[Build] lasts 5 days`,
+/// затем `[Build] starts at [Design]'s end`) дополняет уже объявленную
+/// задачу, а не создаёт новую. Возвращает идентификатор задачи.
+fn parse_task(
     pair: pest::iterators::Pair<Rule>,
-    last_task_id: &Option<String>,
-) -> Option<GanttTask> {
+    diagram: &mut GanttDiagram,
+    start: Option<TaskStart>,
+) -> Option<String> {
     let mut name = String::new();
-    let mut task = GanttTask::new("");
-
-    // then задача начинается после предыдущей
-    if let Some(ref prev_id) = last_task_id {
-        task.start = TaskStart::After(prev_id.clone());
-    }
+    let mut modifiers = None;
 
     for inner in pair.into_inner() {
         match inner.as_rule() {
@@ -214,7 +208,7 @@ fn parse_then_task(
                 name = extract_task_name(inner);
             }
             Rule::task_modifiers => {
-                parse_task_modifiers(inner, &mut task);
+                modifiers = Some(inner);
             }
             _ => {}
         }
@@ -224,8 +218,23 @@ fn parse_then_task(
         return None;
     }
 
-    task.name = name;
-    Some(task)
+    let index = diagram
+        .tasks
+        .iter()
+        .position(|task| task.id.as_deref() == Some(name.as_str()) || task.name == name)
+        .unwrap_or_else(|| {
+            diagram.tasks.push(GanttTask::new(name));
+            diagram.tasks.len() - 1
+        });
+    let task = &mut diagram.tasks[index];
+
+    if let Some(start) = start {
+        task.start = start;
+    }
+    if let Some(modifiers) = modifiers {
+        parse_task_modifiers(modifiers, task);
+    }
+    Some(task.id.clone().unwrap_or_else(|| task.name.clone()))
 }
 
 /// Парсит модификаторы задачи
@@ -249,8 +258,11 @@ fn parse_task_modifiers(pair: pest::iterators::Pair<Rule>, task: &mut GanttTask)
                     Rule::complete_modifier => {
                         task.complete = parse_complete_modifier(modifier);
                     }
+                    Rule::color_modifier => {
+                        task.color = parse_color_modifier(modifier);
+                    }
                     Rule::on_modifier => {
-                        task.resource = parse_on_modifier(modifier);
+                        task.resources.extend(parse_on_modifier(modifier));
                     }
                     Rule::requires_modifier => {
                         if let Some(ref_id) = extract_task_ref(modifier) {
@@ -305,7 +317,7 @@ fn parse_starts_modifier(pair: pest::iterators::Pair<Rule>) -> TaskStart {
                     return TaskStart::AtEnd(task_id);
                 } else if text.contains("after") {
                     return TaskStart::After(task_id);
-                } else if text.contains("with") {
+                } else if text.contains("with") || text.contains("'s start") {
                     return TaskStart::With(task_id);
                 } else {
                     return TaskStart::After(task_id);
@@ -347,14 +359,48 @@ fn parse_complete_modifier(pair: pest::iterators::Pair<Rule>) -> Option<u8> {
     None
 }
 
-/// Парсит on modifier (resource)
-fn parse_on_modifier(pair: pest::iterators::Pair<Rule>) -> Option<String> {
-    for inner in pair.into_inner() {
-        if inner.as_rule() == Rule::resource_name {
-            return Some(inner.as_str().trim().to_string());
+/// Парсит color modifier: `is colored in #FF0000` / `is colored in Red`
+fn parse_color_modifier(pair: pest::iterators::Pair<Rule>) -> Option<Color> {
+    pair.into_inner()
+        .find(|inner| inner.as_rule() == Rule::color_value)
+        .map(|inner| Color::parse(inner.as_str()))
+}
+
+/// Парсит on modifier (ресурсы)
+fn parse_on_modifier(pair: pest::iterators::Pair<Rule>) -> Vec<GanttResource> {
+    pair.into_inner()
+        .filter(|inner| inner.as_rule() == Rule::resource_name)
+        .filter_map(|inner| GanttResource::parse(inner.as_str()))
+        .collect()
+}
+
+/// Парсит связь `[T1] -> [T2]`: T2 начинается после окончания T1
+fn parse_link(pair: pest::iterators::Pair<Rule>, diagram: &mut GanttDiagram) {
+    let refs: Vec<String> = pair
+        .into_inner()
+        .filter(|inner| inner.as_rule() == Rule::task_ref)
+        .map(extract_task_ref_inner)
+        .collect();
+    if let [from, to] = refs.as_slice() {
+        if let Some(task) = diagram.find_task_mut(to) {
+            if !task.links.contains(from) {
+                task.links.push(from.clone());
+            }
+        }
+    }
+}
+
+/// Парсит hide: `hide resources names`, `hide ressources footbox`
+fn parse_hide(pair: pest::iterators::Pair<Rule>, diagram: &mut GanttDiagram) {
+    let target = pair.as_str().to_lowercase();
+    if target.contains("resources") || target.contains("ressources") {
+        if target.contains("names") {
+            diagram.hide_resource_names = true;
+        }
+        if target.contains("footbox") {
+            diagram.hide_resource_load = true;
         }
     }
-    None
 }
 
 /// Парсит milestone
@@ -387,6 +433,8 @@ fn parse_milestone(pair: pest::iterators::Pair<Rule>) -> Option<GanttMilestone>
 
 /// Парсит milestone time
 fn parse_milestone_time(pair: pest::iterators::Pair<Rule>) -> MilestoneTime {
+    let text = pair.as_str();
+
     for inner in pair.into_inner() {
         match inner.as_rule() {
             Rule::date_value => {
@@ -395,7 +443,11 @@ fn parse_milestone_time(pair: pest::iterators::Pair<Rule>) -> MilestoneTime {
                 }
             }
             Rule::task_ref => {
-                return MilestoneTime::After(extract_task_ref_inner(inner));
+                let task_id = extract_task_ref_inner(inner);
+                if text.contains("'s start") {
+                    return MilestoneTime::AtStart(task_id);
+                }
+                return MilestoneTime::After(task_id);
             }
             _ => {}
         }
@@ -507,6 +559,20 @@ project starts 2024-01-15
         assert!(matches!(diagram.tasks[1].start, TaskStart::After(ref id) if id == "T1"));
     }
 
+    #[test]
+    fn test_parse_task_color() {
+        let source = r#"
+@startgantt
+[Design] lasts 5 days and is colored in Lightblue
+[Build] lasts 3 days and is colored in #FF0000
+@endgantt
+"#;
+
+        let diagram = parse_gantt(source).unwrap();
+        assert_eq!(diagram.tasks[0].color, Some(Color::named("Lightblue")));
+        assert_eq!(diagram.tasks[1].color, Some(Color::from_hex("FF0000")));
+    }
+
     #[test]
     fn test_parse_then_statement() {
         let source = r#"
@@ -563,4 +629,85 @@ sunday are closed
         assert_eq!(diagram.separators.len(), 1);
         assert_eq!(diagram.separators[0].label, Some("Phase 2".to_string()));
     }
+
+    #[test]
+    fn test_parse_calendar() {
+        let source = r#"
+@startgantt
+project starts 2024-01-29
+printscale weekly
+saturday are closed
+2024-02-05 is closed (Release day)
+today is 2024-02-07 and is colored in #AAF
+[Task] ends 2024-02-20
+@endgantt
+"#;
+
+        let diagram = parse_gantt(source).unwrap();
+        assert_eq!(diagram.scale, GanttScale::Weekly);
+        assert_eq!(diagram.holidays[0].name.as_deref(), Some("Release day"));
+        assert_eq!(diagram.today, Some(GanttDate::new(2024, 2, 7)));
+        assert_eq!(diagram.today_color.as_ref().map(Color::to_css).as_deref(), Some("#AAF"));
+        assert!(
+            matches!(diagram.tasks[0].duration, TaskDuration::Until(ref date) if *date == GanttDate::new(2024, 2, 20))
+        );
+    }
+
+    #[test]
+    fn test_parse_dependencies_and_resources() {
+        let source = r#"
+@startgantt
+[Design] as [D] lasts 5 days on {Alice} {Bob:50%}
+[Build] starts at [D]'s start lasts 3 days
+[Test] lasts 2 days
+[D] -> [Test]
+[Release] happens at [Test]'s end
+[Kickoff] happens at [D]'s start
+hide ressources names
+@endgantt
+"#;
+
+        let diagram = parse_gantt(source).unwrap();
+        assert_eq!(diagram.tasks.len(), 3);
+        assert_eq!(
+            diagram.tasks[0].resources,
+            vec![
+                GanttResource::new("Alice"),
+                GanttResource { name: "Bob".to_string(), percent: 50 },
+            ]
+        );
+        assert!(matches!(diagram.tasks[1].start, TaskStart::With(ref id) if id == "D"));
+        assert_eq!(diagram.tasks[2].links, vec!["D".to_string()]);
+
+        assert_eq!(diagram.milestones.len(), 2);
+        assert!(matches!(diagram.milestones[0].happens, MilestoneTime::After(ref id) if id == "Test"));
+        assert!(matches!(diagram.milestones[1].happens, MilestoneTime::AtStart(ref id) if id == "D"));
+        assert!(diagram.hide_resource_names);
+        assert!(!diagram.hide_resource_load);
+    }
+
+    #[test]
+    fn test_parse_repeated_task_merges() {
+        let source = r#"@startgantt
+[Design] as [D] lasts 3 days
+[Build] lasts 5 days on {Bob}
+[Build] starts at [D]'s end
+[D] is colored in Coral
+then [Build] requires [Design]
+@endgantt
+"#;
+
+        let diagram = parse_gantt(source).unwrap();
+        assert_eq!(diagram.tasks.len(), 2);
+
+        let design = &diagram.tasks[0];
+        assert!(matches!(design.duration, TaskDuration::Days(3)));
+        assert!(design.color.is_some());
+
+        let build = &diagram.tasks[1];
+        assert!(matches!(build.duration, TaskDuration::Days(5)));
+        assert!(matches!(build.start, TaskStart::After(ref id) if id == "D"));
+        assert_eq!(build.resources, vec![GanttResource::new("Bob")]);
+        assert_eq!(build.links, vec!["Design".to_string()]);
+    }
 }
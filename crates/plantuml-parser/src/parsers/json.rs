@@ -7,6 +7,7 @@ use pest_derive::Parser;
 
 use plantuml_ast::json::{JsonDiagram, JsonNode};
 
+use super::metadata::extract_metadata;
 use crate::error::ParseError;
 
 #[derive(Parser)]
@@ -15,10 +16,13 @@ struct JsonParser;
 
 /// Парсит JSON диаграмму
 pub fn parse_json(source: &str) -> crate::Result<JsonDiagram> {
+    let (source, metadata) = extract_metadata(source);
+    let source = source.as_str();
     let pairs = JsonParser::parse(Rule::json_diagram, source)
         .map_err(|e| ParseError::GrammarError(format!("Ошибка парсинга JSON: {}", e)))?;
 
     let mut diagram = JsonDiagram::new();
+    diagram.metadata = metadata;
 
     for pair in pairs {
         match pair.as_rule() {
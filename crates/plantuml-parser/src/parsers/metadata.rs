@@ -0,0 +1,347 @@
+//! Директивы оформления, общие для всех типов диаграмм
+//!
+//! `title`, `caption`, `header`, `footer` и `legend` (включая многострочные
+//! `title … end title`, `legend … endlegend`), а также направление
+//! `left to right direction` / `top to bottom direction` и форма линий
+//! `skinparam linetype ortho|polyline|spline` и прагмы `!pragma` разбираются до
+//! грамматики диаграммы. Строки директив заменяются пустыми, чтобы номера строк в
+//! ошибках грамматики не сдвигались.
+
+use plantuml_ast::common::{DiagramMetadata, Direction, HorizontalAlign, Legend, LineType};
+
+/// Извлекает директивы оформления из исходника
+///
+/// Незакрытый многострочный блок директивой не считается и остаётся в
+/// исходнике (например, поле `title` в теле класса).
+pub fn extract_metadata(source: &str) -> (String, DiagramMetadata) {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut metadata = DiagramMetadata::default();
+    let mut output: Vec<&str> = Vec::with_capacity(lines.len());
+    let mut index = 0;
+
+    while index < lines.len() {
+        if let Some(direction) = parse_direction(lines[index]) {
+            metadata.direction = direction;
+            output.push("");
+            index += 1;
+            continue;
+        }
+
+        if let Some(line_type) = parse_line_type(lines[index]) {
+            metadata.line_type = Some(line_type);
+            output.push("");
+            index += 1;
+            continue;
+        }
+
+        if let Some((name, value)) = parse_pragma(lines[index]) {
+            metadata.pragmas.insert(name, value);
+            output.push("");
+            index += 1;
+            continue;
+        }
+
+        let Some(directive) = Directive::parse(lines[index]) else {
+            output.push(lines[index]);
+            index += 1;
+            continue;
+        };
+
+        let (text, consumed) = match directive.text {
+            Some(text) => (text.to_string(), 1),
+            None => match block(&lines[index + 1..], directive.kind.end_markers()) {
+                Some((text, consumed)) => (text, consumed + 1),
+                None => {
+                    output.push(lines[index]);
+                    index += 1;
+                    continue;
+                }
+            },
+        };
+
+        match directive.kind {
+            DirectiveKind::Title => metadata.title = Some(text),
+            DirectiveKind::Caption => metadata.caption = Some(text),
+            DirectiveKind::Mainframe => metadata.mainframe = Some(text),
+            DirectiveKind::Header => {
+                metadata.header = Some(text);
+                metadata.header_align = directive.align;
+            }
+            DirectiveKind::Footer => {
+                metadata.footer = Some(text);
+                metadata.footer_align = directive.align;
+            }
+            DirectiveKind::Legend => {
+                metadata.legend = Some(Legend {
+                    text,
+                    top: directive.top,
+                    align: directive.align.unwrap_or_default(),
+                })
+            }
+        }
+
+        output.extend(std::iter::repeat("").take(consumed));
+        index += consumed;
+    }
+
+    (output.join("\n"), metadata)
+}
+
+/// Директива направления раскладки
+fn parse_direction(line: &str) -> Option<Direction> {
+    let words: Vec<String> = line.split_whitespace().map(str::to_ascii_lowercase).collect();
+    match words.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["left", "to", "right", "direction"] => Some(Direction::LeftToRight),
+        ["top", "to", "bottom", "direction"] => Some(Direction::TopToBottom),
+        _ => None,
+    }
+}
+
+/// `skinparam linetype ortho|polyline|spline`
+fn parse_line_type(line: &str) -> Option<LineType> {
+    let mut words = line.split_whitespace();
+    let skinparam = words.next()?.eq_ignore_ascii_case("skinparam");
+    let name = words.next()?.eq_ignore_ascii_case("linetype");
+    let value = words.next()?;
+    if !skinparam || !name || words.next().is_some() {
+        return None;
+    }
+    LineType::parse(value)
+}
+
+/// `!pragma name [value]` → (имя в нижнем регистре, значение)
+fn parse_pragma(line: &str) -> Option<(String, String)> {
+    let rest = line.trim().strip_prefix("!pragma")?;
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let mut parts = rest.trim().splitn(2, char::is_whitespace);
+    let name = parts.next().filter(|name| !name.is_empty())?;
+    let value = parts.next().unwrap_or("").trim();
+    Some((name.to_ascii_lowercase(), value.to_string()))
+}
+
+/// Тело многострочного блока до закрывающей строки
+///
+/// Возвращает текст и число строк вместе с закрывающей.
+fn block(lines: &[&str], end_markers: &[&str]) -> Option<(String, usize)> {
+    let end = lines
+        .iter()
+        .position(|line| end_markers.contains(&line.trim().to_ascii_lowercase().as_str()))?;
+    let text = lines[..end]
+        .iter()
+        .map(|line| line.trim())
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some((text, end + 1))
+}
+
+/// Вид директивы оформления
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DirectiveKind {
+    Title,
+    Caption,
+    Header,
+    Footer,
+    Legend,
+    Mainframe,
+}
+
+impl DirectiveKind {
+    fn parse(word: &str) -> Option<Self> {
+        match word {
+            "title" => Some(Self::Title),
+            "caption" => Some(Self::Caption),
+            "header" => Some(Self::Header),
+            "footer" => Some(Self::Footer),
+            "legend" => Some(Self::Legend),
+            "mainframe" => Some(Self::Mainframe),
+            _ => None,
+        }
+    }
+
+    /// Закрывающие строки многострочного блока
+    fn end_markers(self) -> &'static [&'static str] {
+        match self {
+            Self::Title => &["end title", "endtitle"],
+            Self::Caption | Self::Mainframe => &[],
+            Self::Header => &["end header", "endheader"],
+            Self::Footer => &["end footer", "endfooter"],
+            Self::Legend => &["end legend", "endlegend"],
+        }
+    }
+}
+
+/// Первая строка директивы
+#[derive(Debug)]
+struct Directive<'a> {
+    kind: DirectiveKind,
+    /// Текст однострочной формы (None — начало блока)
+    text: Option<&'a str>,
+    align: Option<HorizontalAlign>,
+    top: bool,
+}
+
+impl<'a> Directive<'a> {
+    fn parse(line: &'a str) -> Option<Self> {
+        let line = line.trim();
+        let (first, rest) = split_word(line);
+
+        // `left header`, `right footer`
+        let (align, kind, rest) = match HorizontalAlign::parse(first) {
+            Some(align) => {
+                let (word, rest) = split_word(rest);
+                let kind = DirectiveKind::parse(word)
+                    .filter(|kind| matches!(kind, DirectiveKind::Header | DirectiveKind::Footer))?;
+                (Some(align), kind, rest)
+            }
+            None => (None, DirectiveKind::parse(first)?, rest),
+        };
+
+        if kind == DirectiveKind::Legend {
+            return Self::legend(rest);
+        }
+
+        // `title -> Bob` — сообщение участнику с таким именем, а не директива
+        if rest.starts_with(['-', '<', '.', '=', ':']) {
+            return None;
+        }
+        let text = (!rest.is_empty()).then_some(rest);
+        if text.is_none() && matches!(kind, DirectiveKind::Caption | DirectiveKind::Mainframe) {
+            return None;
+        }
+        Some(Self {
+            kind,
+            text,
+            align,
+            top: false,
+        })
+    }
+
+    /// `legend [top|bottom] [left|right|center]` — всегда начало блока
+    fn legend(rest: &str) -> Option<Self> {
+        let mut directive = Self {
+            kind: DirectiveKind::Legend,
+            text: None,
+            align: None,
+            top: false,
+        };
+        for word in rest.split_whitespace() {
+            match word.to_ascii_lowercase().as_str() {
+                "top" => directive.top = true,
+                "bottom" => directive.top = false,
+                other => directive.align = Some(HorizontalAlign::parse(other)?),
+            }
+        }
+        Some(directive)
+    }
+}
+
+/// Делит строку на первое слово и остаток
+fn split_word(line: &str) -> (&str, &str) {
+    match line.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim()),
+        None => (line, ""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_line_directives() {
+        let source = "@startuml\ntitle Заголовок\ncaption Подпись\nleft header Шапка\nfooter Стр. 1\nA -> B\n@enduml";
+        let (rest, metadata) = extract_metadata(source);
+
+        assert_eq!(metadata.title.as_deref(), Some("Заголовок"));
+        assert_eq!(metadata.caption.as_deref(), Some("Подпись"));
+        assert_eq!(metadata.header.as_deref(), Some("Шапка"));
+        assert_eq!(metadata.header_align, Some(HorizontalAlign::Left));
+        assert_eq!(metadata.footer.as_deref(), Some("Стр. 1"));
+        assert_eq!(metadata.footer_align, None);
+        // Номера строк сохраняются
+        assert_eq!(rest, "@startuml\n\n\n\n\nA -> B\n@enduml");
+    }
+
+    #[test]
+    fn test_multiline_blocks() {
+        let source = "@startuml\ntitle\n  **Большой**\n  заголовок\nend title\nlegend top right\n|= A |= B |\n| 1 | 2 |\nendlegend\n@enduml";
+        let (rest, metadata) = extract_metadata(source);
+
+        assert_eq!(metadata.title.as_deref(), Some("**Большой**\nзаголовок"));
+        let legend = metadata.legend.unwrap();
+        assert_eq!(legend.text, "|= A |= B |\n| 1 | 2 |");
+        assert!(legend.top);
+        assert_eq!(legend.align, HorizontalAlign::Right);
+        assert_eq!(rest.lines().count(), source.lines().count());
+        assert!(rest.lines().all(|line| line.is_empty() || line.starts_with('@')));
+    }
+
+    #[test]
+    fn test_not_directives() {
+        // Незакрытый блок и сообщение участнику `title` остаются в исходнике
+        let source = "class Book {\n  title\n}\ntitle -> Bob: hi\nlegend somewhere\nendlegend";
+        let (rest, metadata) = extract_metadata(source);
+        assert_eq!(rest, source);
+        assert!(metadata.title.is_none());
+        assert!(metadata.legend.is_none());
+    }
+
+    #[test]
+    fn test_direction() {
+        let source = "@startuml\nleft to right direction\nA --> B\n@enduml";
+        let (rest, metadata) = extract_metadata(source);
+        assert_eq!(metadata.direction, Direction::LeftToRight);
+        assert_eq!(rest, "@startuml\n\nA --> B\n@enduml");
+
+        let (_, metadata) = extract_metadata("Top  To Bottom Direction");
+        assert_eq!(metadata.direction, Direction::TopToBottom);
+    }
+
+    #[test]
+    fn test_pragmas() {
+        let source = "@startuml
+!pragma teoz true
+!pragma useVerticalIf
+A -> B
+@enduml";
+        let (rest, metadata) = extract_metadata(source);
+        assert_eq!(metadata.pragma("teoz"), Some("true"));
+        assert_eq!(metadata.pragma("useverticalif"), Some(""));
+        assert_eq!(metadata.pragma("other"), None);
+        assert_eq!(rest, "@startuml
+
+
+A -> B
+@enduml");
+
+        assert!(extract_metadata("!pragmatic").1.pragmas.is_empty());
+    }
+
+    #[test]
+    fn test_mainframe() {
+        let (rest, metadata) = extract_metadata("@startuml\nmainframe This is a **mainframe**\nA -> B\n@enduml");
+        assert_eq!(metadata.mainframe.as_deref(), Some("This is a **mainframe**"));
+        assert_eq!(rest, "@startuml\n\nA -> B\n@enduml");
+
+        // Без текста — не директива
+        assert_eq!(extract_metadata("mainframe").1.mainframe, None);
+    }
+
+    #[test]
+    fn test_line_type() {
+        let source = "@startuml\nskinparam linetype ortho\nA --> B\n@enduml";
+        let (rest, metadata) = extract_metadata(source);
+        assert_eq!(metadata.line_type, Some(LineType::Ortho));
+        assert_eq!(rest, "@startuml\n\nA --> B\n@enduml");
+
+        let (_, metadata) = extract_metadata("SkinParam LineType Polyline");
+        assert_eq!(metadata.line_type, Some(LineType::Polyline));
+
+        // Другие skinparam остаются грамматике диаграммы
+        let (rest, metadata) = extract_metadata("skinparam linetype unknown\nskinparam shadowing false");
+        assert!(metadata.line_type.is_none());
+        assert_eq!(rest, "skinparam linetype unknown\nskinparam shadowing false");
+    }
+}
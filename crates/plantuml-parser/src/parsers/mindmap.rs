@@ -11,6 +11,7 @@ use pest_derive::Parser;
 use plantuml_ast::common::Color;
 use plantuml_ast::mindmap::{MindMapDiagram, MindMapNode, NodeDirection, NodeStyle};
 
+use super::metadata::extract_metadata;
 use crate::error::ParseError;
 
 #[derive(Parser)]
@@ -19,10 +20,13 @@ struct MindMapParser;
 
 /// Парсит MindMap диаграмму из исходного кода
 pub fn parse_mindmap(source: &str) -> crate::Result<MindMapDiagram> {
+    let (source, metadata) = extract_metadata(source);
+    let source = source.as_str();
     let pairs = MindMapParser::parse(Rule::mindmap, source)
         .map_err(|e| ParseError::GrammarError(e.to_string()))?;
 
     let mut diagram = MindMapDiagram::new();
+    diagram.metadata = metadata;
     let mut node_stack: Vec<MindMapNode> = Vec::new();
 
     for pair in pairs {
@@ -1,17 +1,20 @@
 //! Парсеры для различных типов диаграмм
 
 pub mod activity;
+mod arrow;
 pub mod class;
 pub mod component;
 pub mod er;
 pub mod gantt;
 pub mod json;
+pub mod metadata;
 pub mod mindmap;
 pub mod object;
 pub mod sequence;
 pub mod state;
 pub mod timing;
 pub mod usecase;
+pub mod visibility;
 pub mod network;
 pub mod salt;
 pub mod wbs;
@@ -33,3 +36,12 @@ pub use network::parse_network;
 pub use salt::parse_salt;
 pub use wbs::parse_wbs;
 pub use yaml::parse_yaml;
+
+use plantuml_ast::common::Span;
+
+/// Позиция пары pest в исходнике
+pub(crate) fn pair_span<R: pest::RuleType>(pair: &pest::iterators::Pair<R>) -> Span {
+    let span = pair.as_span();
+    let (line, column) = span.start_pos().line_col();
+    Span::new(span.start(), span.end(), line, column)
+}
@@ -8,6 +8,7 @@ use pest_derive::Parser;
 use plantuml_ast::common::Color;
 use plantuml_ast::network::{DeviceType, Network, NetworkDiagram, Server, ServerGroup};
 
+use super::metadata::extract_metadata;
 use crate::error::ParseError;
 
 #[derive(Parser)]
@@ -16,6 +17,8 @@ struct NetworkParser;
 
 /// Парсит Network диаграмму
 pub fn parse_network(source: &str) -> crate::Result<NetworkDiagram> {
+    let (source, metadata) = extract_metadata(source);
+    let source = source.as_str();
     // Извлекаем содержимое nwdiag блока
     let nwdiag_content = extract_nwdiag_content(source)?;
     
@@ -23,6 +26,7 @@ pub fn parse_network(source: &str) -> crate::Result<NetworkDiagram> {
         .map_err(|e| ParseError::GrammarError(format!("Ошибка парсинга Network: {}", e)))?;
 
     let mut diagram = NetworkDiagram::new();
+    diagram.metadata = metadata;
 
     for pair in pairs {
         if pair.as_rule() == Rule::network_diagram {
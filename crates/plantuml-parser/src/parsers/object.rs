@@ -5,10 +5,12 @@
 use pest::Parser;
 use pest_derive::Parser;
 
-use plantuml_ast::common::{Note, NotePosition, Stereotype};
+use plantuml_ast::common::{Color, Link, Note, NotePosition, Stereotype};
 use plantuml_ast::object::{Object, ObjectDiagram, ObjectField, ObjectLink, ObjectLinkType};
 
-use crate::{ParseError, Result};
+use super::arrow::{arrow_hints, ArrowHints};
+use super::metadata::extract_metadata;
+use crate::Result;
 
 #[derive(Parser)]
 #[grammar = "grammars/object.pest"]
@@ -16,14 +18,12 @@ pub struct ObjectParser;
 
 /// Парсит object diagram из исходного кода
 pub fn parse_object(source: &str) -> Result<ObjectDiagram> {
-    let pairs = ObjectParser::parse(Rule::diagram, source).map_err(|e| {
-        ParseError::SyntaxError {
-            line: e.line().to_string().parse().unwrap_or(0),
-            message: e.to_string(),
-        }
-    })?;
+    let (source, metadata) = extract_metadata(source);
+    let source = source.as_str();
+    let pairs = ObjectParser::parse(Rule::diagram, source).map_err(crate::error::pest_error)?;
 
     let mut diagram = ObjectDiagram::new();
+    diagram.metadata = metadata;
 
     for pair in pairs {
         if pair.as_rule() == Rule::diagram {
@@ -84,6 +84,7 @@ fn parse_object_with_body(pair: pest::iterators::Pair<Rule>) -> Option<Object> {
     let mut name = String::new();
     let mut class_name: Option<String> = None;
     let mut stereotype: Option<Stereotype> = None;
+    let mut background_color: Option<Color> = None;
     let mut fields = Vec::new();
 
     for inner in pair.into_inner() {
@@ -97,6 +98,9 @@ fn parse_object_with_body(pair: pest::iterators::Pair<Rule>) -> Option<Object> {
             Rule::stereotype_part => {
                 stereotype = extract_stereotype(inner);
             }
+            Rule::color_part => {
+                background_color = extract_color(inner);
+            }
             Rule::object_body => {
                 fields = parse_object_body(inner);
             }
@@ -113,7 +117,7 @@ fn parse_object_with_body(pair: pest::iterators::Pair<Rule>) -> Option<Object> {
         class_name,
         fields,
         stereotype,
-        background_color: None,
+        background_color,
     })
 }
 
@@ -122,6 +126,7 @@ fn parse_object_simple(pair: pest::iterators::Pair<Rule>) -> Option<Object> {
     let mut name = String::new();
     let mut class_name: Option<String> = None;
     let mut stereotype: Option<Stereotype> = None;
+    let mut background_color: Option<Color> = None;
 
     for inner in pair.into_inner() {
         match inner.as_rule() {
@@ -134,6 +139,9 @@ fn parse_object_simple(pair: pest::iterators::Pair<Rule>) -> Option<Object> {
             Rule::stereotype_part => {
                 stereotype = extract_stereotype(inner);
             }
+            Rule::color_part => {
+                background_color = extract_color(inner);
+            }
             _ => {}
         }
     }
@@ -147,7 +155,7 @@ fn parse_object_simple(pair: pest::iterators::Pair<Rule>) -> Option<Object> {
         class_name,
         fields: Vec::new(),
         stereotype,
-        background_color: None,
+        background_color,
     })
 }
 
@@ -265,6 +273,7 @@ fn parse_link(pair: pest::iterators::Pair<Rule>) -> Option<ObjectLink> {
     let mut to = String::new();
     let mut label: Option<String> = None;
     let mut link_type = ObjectLinkType::Association;
+    let mut hints = ArrowHints::default();
 
     for inner in pair.into_inner() {
         match inner.as_rule() {
@@ -275,6 +284,7 @@ fn parse_link(pair: pest::iterators::Pair<Rule>) -> Option<ObjectLink> {
                 to = extract_name(inner);
             }
             Rule::arrow => {
+                hints = arrow_hints(inner.as_str());
                 link_type = parse_arrow_type(inner);
             }
             Rule::link_label => {
@@ -293,6 +303,8 @@ fn parse_link(pair: pest::iterators::Pair<Rule>) -> Option<ObjectLink> {
         to,
         label,
         link_type,
+        direction: hints.direction,
+        hidden: hints.hidden,
     })
 }
 
@@ -346,11 +358,14 @@ fn parse_note(pair: pest::iterators::Pair<Rule>) -> Option<Note> {
         return None;
     }
 
+    let (text, link) = Link::extract(&text);
+
     Some(Note {
         text,
         position,
         anchors,
         background_color: None,
+        link,
     })
 }
 
@@ -409,6 +424,13 @@ fn extract_field_value(pair: pest::iterators::Pair<Rule>) -> String {
     text
 }
 
+/// Цвет из `#color`
+fn extract_color(pair: pest::iterators::Pair<Rule>) -> Option<Color> {
+    pair.into_inner()
+        .find(|inner| inner.as_rule() == Rule::color)
+        .map(|inner| Color::parse(inner.as_str()))
+}
+
 fn extract_stereotype(pair: pest::iterators::Pair<Rule>) -> Option<Stereotype> {
     for inner in pair.into_inner() {
         if inner.as_rule() == Rule::stereotype_name {
@@ -442,6 +464,7 @@ fn extract_quoted_string(pair: pest::iterators::Pair<Rule>) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use plantuml_ast::common::Direction;
 
     #[test]
     fn test_parse_simple_object() {
@@ -532,4 +555,15 @@ map config {
         assert_eq!(diagram.objects[0].class_name, Some("Map".to_string()));
         assert_eq!(diagram.objects[0].fields.len(), 2);
     }
+
+    #[test]
+    fn test_parse_arrow_hints() {
+        let source = "@startuml\nobject a\nobject b\nobject c\na *-right- b\nb -[hidden]-> c\n@enduml";
+        let diagram = parse_object(source).unwrap();
+
+        assert_eq!(diagram.links.len(), 2);
+        assert_eq!(diagram.links[0].link_type, ObjectLinkType::Composition);
+        assert_eq!(diagram.links[0].direction, Some(Direction::LeftToRight));
+        assert!(diagram.links[1].hidden);
+    }
 }
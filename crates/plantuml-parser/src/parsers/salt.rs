@@ -9,6 +9,7 @@ use plantuml_ast::salt::{
     BorderStyle, Container, SaltDiagram, SaltWidget, ScrollbarType, SeparatorType, TreeNode,
 };
 
+use super::metadata::extract_metadata;
 use crate::error::ParseError;
 
 #[derive(Parser)]
@@ -17,6 +18,8 @@ struct SaltParser;
 
 /// Парсит Salt диаграмму
 pub fn parse_salt(source: &str) -> crate::Result<SaltDiagram> {
+    let (source, metadata) = extract_metadata(source);
+    let source = source.as_str();
     // Извлекаем содержимое salt блока
     let salt_content = extract_salt_content(source)?;
 
@@ -24,6 +27,7 @@ pub fn parse_salt(source: &str) -> crate::Result<SaltDiagram> {
         .map_err(|e| ParseError::GrammarError(format!("Ошибка парсинга Salt: {}", e)))?;
 
     let mut diagram = SaltDiagram::new();
+    diagram.metadata = metadata;
 
     for pair in pairs {
         if pair.as_rule() == Rule::salt_diagram {
@@ -5,14 +5,18 @@
 use pest::Parser;
 use pest_derive::Parser;
 
-use plantuml_ast::common::{Color, LineStyle, Note, NotePosition, Stereotype};
+use plantuml_ast::common::{Color, LineStyle, Link, Note, NotePosition, Stereotype};
 use plantuml_ast::sequence::{
     Activation, ActivationType, ArrowType, AutonumberCommand, AutonumberStart, Delay, Divider,
-    Fragment, FragmentSection, FragmentType, Message, Participant, ParticipantBox, ParticipantType,
-    Return, SequenceDiagram, SequenceElement,
+    Duration, EndpointDecoration, EndpointSide, Fragment, FragmentSection, FragmentType, Message,
+    MessageEndpoint, Participant, ParticipantBox, ParticipantType, Return, SequenceDiagram,
+    SequenceDisplay, SequenceElement,
 };
 
-use crate::{ParseError, Result};
+use super::arrow::arrow_hints;
+use super::metadata::extract_metadata;
+use super::pair_span;
+use crate::Result;
 
 #[derive(Parser)]
 #[grammar = "grammars/sequence.pest"]
@@ -26,13 +30,13 @@ type BoxState = (Option<String>, Option<Color>, Vec<String>);
 
 /// Парсит sequence diagram из исходного кода
 pub fn parse_sequence(source: &str) -> Result<SequenceDiagram> {
+    let (source, metadata) = extract_metadata(source);
+    let source = source.as_str();
     let pairs =
-        SequenceParser::parse(Rule::diagram, source).map_err(|e| ParseError::SyntaxError {
-            line: e.line().to_string().parse().unwrap_or(0),
-            message: e.to_string(),
-        })?;
+        SequenceParser::parse(Rule::diagram, source).map_err(crate::error::pest_error)?;
 
     let mut diagram = SequenceDiagram::new();
+    diagram.metadata = metadata;
     let mut fragment_stack: Vec<FragmentStackEntry> = Vec::new();
     let mut current_section_elements: Vec<SequenceElement> = Vec::new();
     let mut current_box: Option<BoxState> = None;
@@ -176,6 +180,26 @@ fn process_rule(
                 diagram.metadata.title = Some(title);
             }
         }
+        Rule::display_stmt => parse_display(pair, &mut diagram.display),
+        Rule::skinparam => {
+            // Учитываются только параметры отображения sequence диаграммы
+            let mut name = "";
+            for inner in pair.into_inner() {
+                match inner.as_rule() {
+                    Rule::skinparam_name => name = inner.as_str(),
+                    Rule::skinparam_value => {
+                        diagram.display.apply_skinparam(name, inner.as_str());
+                    }
+                    Rule::skinparam_line => {
+                        let mut parts = inner.into_inner();
+                        if let (Some(name), Some(value)) = (parts.next(), parts.next()) {
+                            diagram.display.apply_skinparam(name.as_str(), value.as_str());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
         Rule::activate_stmt => {
             if let Some((participant_id, color)) = parse_activate(pair) {
                 let element = SequenceElement::Activation(Activation {
@@ -228,6 +252,17 @@ fn process_rule(
                 }
             }
         }
+        Rule::newpage => {
+            let title = pair
+                .into_inner()
+                .find(|p| p.as_rule() == Rule::newpage_title)
+                .map(|p| p.as_str().trim().to_string())
+                .filter(|t| !t.is_empty());
+            // Разрыв страницы имеет смысл только на верхнем уровне
+            if fragment_stack.is_empty() {
+                diagram.add_element(SequenceElement::NewPage(title));
+            }
+        }
         Rule::return_stmt => {
             let ret = parse_return(pair);
             let element = SequenceElement::Return(ret);
@@ -237,18 +272,28 @@ fn process_rule(
                 current_section_elements.push(element);
             }
         }
+        Rule::duration_stmt => {
+            let element = SequenceElement::Duration(parse_duration(pair));
+            if fragment_stack.is_empty() {
+                diagram.add_element(element);
+            } else {
+                current_section_elements.push(element);
+            }
+        }
         _ => {}
     }
 }
 
 /// Парсит объявление участника
 fn parse_participant(pair: pest::iterators::Pair<Rule>) -> Option<Participant> {
+    let span = pair_span(&pair);
     let mut participant_type = ParticipantType::Participant;
     let mut name = String::new();
     let mut alias: Option<String> = None;
     let mut stereotype: Option<Stereotype> = None;
     let mut color: Option<Color> = None;
     let mut order: Option<i32> = None;
+    let mut link: Option<Link> = None;
 
     for inner in pair.into_inner() {
         match inner.as_rule() {
@@ -290,6 +335,9 @@ fn parse_participant(pair: pest::iterators::Pair<Rule>) -> Option<Participant> {
                     order = Some(n);
                 }
             }
+            Rule::link => {
+                link = Link::parse(inner.as_str());
+            }
             _ => {}
         }
     }
@@ -305,6 +353,8 @@ fn parse_participant(pair: pest::iterators::Pair<Rule>) -> Option<Participant> {
     participant.stereotype = stereotype;
     participant.color = color;
     participant.order = order;
+    participant.link = link;
+    participant.span = span;
 
     Some(participant)
 }
@@ -332,31 +382,59 @@ fn extract_name(pair: pest::iterators::Pair<Rule>) -> String {
 
 /// Парсит сообщение
 fn parse_message(pair: pest::iterators::Pair<Rule>) -> Option<Message> {
-    let mut from = String::new();
-    let mut to = String::new();
+    let span = pair_span(&pair);
+    let mut endpoints: Vec<MessageEndpoint> = Vec::new();
     let mut label = String::new();
     let mut line_style = LineStyle::Solid;
     let mut arrow_type = ArrowType::Normal;
+    let mut arrow_left = false;
+    let mut source_decoration = None;
+    let mut parallel = false;
+    let mut anchor = None;
+    let mut slant = None;
     let mut activate = false;
     let mut deactivate = false;
     let mut create = false;
     let mut destroy = false;
     let mut activation_color: Option<Color> = None;
+    let mut arrow_color: Option<Color> = None;
 
     for inner in pair.into_inner() {
         match inner.as_rule() {
+            Rule::parallel_mark => parallel = true,
+            Rule::anchor => {
+                anchor = inner.into_inner().next().map(|name| name.as_str().to_string());
+            }
+            Rule::arrow_slant => {
+                slant = inner.into_inner().next().and_then(|value| value.as_str().parse().ok());
+            }
             Rule::participant_ref => {
-                let name = inner.as_str().to_string();
-                if from.is_empty() {
-                    from = name;
+                endpoints.push(MessageEndpoint::Participant(inner.as_str().to_string()));
+            }
+            Rule::border_endpoint => {
+                let side = if inner.as_str() == "[" {
+                    EndpointSide::Left
                 } else {
-                    to = name;
-                }
+                    EndpointSide::Right
+                };
+                endpoints.push(MessageEndpoint::Border(side));
+            }
+            Rule::short_endpoint => {
+                // Сторона короткой стрелки — по месту в записи
+                let side = if endpoints.is_empty() {
+                    EndpointSide::Left
+                } else {
+                    EndpointSide::Right
+                };
+                endpoints.push(MessageEndpoint::Short(side));
             }
             Rule::arrow => {
-                let (style, atype) = parse_arrow(inner);
-                line_style = style;
+                let (style, atype, left, decoration, (color, style_override)) = parse_arrow(inner);
+                line_style = style_override.unwrap_or(style);
                 arrow_type = atype;
+                arrow_left = left;
+                source_decoration = decoration;
+                arrow_color = color;
             }
             Rule::target_activation => {
                 let (act, deact, crt, dst, color) = parse_target_activation(inner);
@@ -373,23 +451,31 @@ fn parse_message(pair: pest::iterators::Pair<Rule>) -> Option<Message> {
         }
     }
 
-    if from.is_empty() || to.is_empty() {
-        return None;
-    }
+    let [first, second]: [MessageEndpoint; 2] = endpoints.try_into().ok()?;
+    // У стрелки налево отправитель записан вторым
+    let (from, to) = if arrow_left {
+        (second, first)
+    } else {
+        (first, second)
+    };
 
+    let (label, link) = Link::extract(&label);
     let mut message = Message::new(from, to, label);
+    message.span = span;
+    message.link = link;
     message.line_style = line_style;
     message.arrow_type = arrow_type;
+    message.arrow_left = arrow_left;
+    message.source_decoration = source_decoration;
+    message.parallel = parallel;
+    message.anchor = anchor;
+    message.slant = slant;
     message.activate = activate;
     message.deactivate = deactivate;
     message.create = create;
     message.destroy = destroy;
-    
-    // Если есть цвет активации, сохраняем его в сообщении
-    // (пока используем поле color, которое уже есть)
-    if activation_color.is_some() {
-        message.color = activation_color;
-    }
+    message.color = arrow_color;
+    message.activation_color = activation_color;
 
     Some(message)
 }
@@ -428,36 +514,76 @@ fn parse_target_activation(pair: pest::iterators::Pair<Rule>) -> (bool, bool, bo
     (activate, deactivate, create, destroy, color)
 }
 
+/// Цвет и стиль линии из `[...]` внутри стрелки
+type ArrowStyle = (Option<Color>, Option<LineStyle>);
+
 /// Парсит стрелку
-fn parse_arrow(pair: pest::iterators::Pair<Rule>) -> (LineStyle, ArrowType) {
-    let arrow_str = pair.as_str();
+fn parse_arrow(
+    pair: pest::iterators::Pair<Rule>,
+) -> (LineStyle, ArrowType, bool, Option<EndpointDecoration>, ArrowStyle) {
+    let Some(direction) = pair.into_inner().next() else {
+        return (LineStyle::Solid, ArrowType::Normal, false, None, (None, None));
+    };
+    let left = direction.as_rule() == Rule::arrow_left;
+
+    // Линия стрелки (возможно, разрезанная стилем `-[#red]>`) и украшения
+    let mut line = String::new();
+    let mut style = (None, None);
+    let mut before = None;
+    let mut after = None;
+    for inner in direction.into_inner() {
+        match inner.as_rule() {
+            Rule::arrow_line_right
+            | Rule::arrow_line_left
+            | Rule::arrow_line_head
+            | Rule::arrow_tail_right
+            | Rule::arrow_head_left
+            | Rule::arrow_tail_left => line.push_str(inner.as_str()),
+            Rule::arrow_style => {
+                let hints = arrow_hints(inner.as_str());
+                style = (hints.color, hints.line_style);
+            }
+            Rule::arrow_decoration => {
+                let decoration = if inner.as_str() == "o" {
+                    EndpointDecoration::Circle
+                } else {
+                    EndpointDecoration::Cross
+                };
+                if line.is_empty() {
+                    before = Some(decoration);
+                } else {
+                    after = Some(decoration);
+                }
+            }
+            _ => {}
+        }
+    }
+    // У стрелки налево головка записана слева
+    let (head, source) = if left { (before, after) } else { (after, before) };
 
     // Определяем стиль линии
     // В PlantUML:
     // -> или ->> = solid (сплошная)
     // --> или -->> = dashed (пунктирная, двойной дефис)
     // .> или ..> = dotted (точечная, тоже считаем dashed)
-    let line_style =
-        if arrow_str.contains("..") || arrow_str.starts_with(".") || arrow_str.contains("--") {
-            LineStyle::Dashed
-        } else {
-            LineStyle::Solid
-        };
+    let line_style = if line.contains("..") || line.starts_with('.') || line.contains("--") {
+        LineStyle::Dashed
+    } else {
+        LineStyle::Solid
+    };
 
     // Определяем тип стрелки
-    let arrow_type = if arrow_str.contains(">>") {
-        ArrowType::Thin
-    } else if arrow_str.contains("\\\\") || arrow_str.contains("//") {
-        ArrowType::HalfTop
-    } else if arrow_str.ends_with("o") || arrow_str.contains(">o") {
-        ArrowType::Circle
-    } else if arrow_str.ends_with("x") || arrow_str.contains(">x") {
-        ArrowType::Cross
-    } else {
-        ArrowType::Normal
+    let arrow_type = match head {
+        Some(EndpointDecoration::Circle) => ArrowType::Circle,
+        Some(EndpointDecoration::Cross) => ArrowType::Cross,
+        None if line.contains(">>") || line.contains("<<") => ArrowType::Thin,
+        None if line.contains("\\\\") || line.contains("//") => ArrowType::HalfTop,
+        None if line.ends_with('o') || line.starts_with('o') => ArrowType::Circle,
+        None if line.ends_with('x') || line.starts_with('x') => ArrowType::Cross,
+        None => ArrowType::Normal,
     };
 
-    (line_style, arrow_type)
+    (line_style, arrow_type, left, source, style)
 }
 
 /// Парсит начало фрагмента
@@ -520,6 +646,7 @@ fn parse_note(pair: pest::iterators::Pair<Rule>) -> Option<Note> {
                 Rule::note_left_right | Rule::note_multi | Rule::hnote | Rule::rnote => {
                     parse_note_inner(inner, position, anchors, text);
                 }
+                Rule::note_over_keyword => *position = NotePosition::Over,
                 Rule::note_position => {
                     *position = match inner.as_str().to_lowercase().as_str() {
                         "left" => NotePosition::Left,
@@ -540,11 +667,17 @@ fn parse_note(pair: pest::iterators::Pair<Rule>) -> Option<Note> {
                 Rule::identifier | Rule::simple_identifier => {
                     anchors.push(inner.as_str().to_string());
                 }
-                Rule::note_text | Rule::note_body => {
+                Rule::note_text => {
                     let t = inner.as_str().trim();
                     // Убираем начальное двоеточие если есть
                     *text = t.trim_start_matches(':').trim().to_string();
                 }
+                Rule::note_body => {
+                    // Отступы строк не нужны: creole списки и таблицы
+                    // распознаются по началу строки
+                    let lines: Vec<&str> = inner.as_str().lines().map(str::trim).collect();
+                    *text = lines.join("\n").trim().to_string();
+                }
                 _ => {}
             }
         }
@@ -552,11 +685,14 @@ fn parse_note(pair: pest::iterators::Pair<Rule>) -> Option<Note> {
 
     parse_note_inner(pair, &mut position, &mut anchors, &mut text);
 
+    let (text, link) = Link::extract(&text);
+
     Some(Note {
         position,
         anchors,
         text,
         background_color: None,
+        link,
     })
 }
 
@@ -599,6 +735,24 @@ fn parse_title(pair: pest::iterators::Pair<Rule>) -> Option<String> {
     None
 }
 
+/// Парсит `hide`/`show` для footbox и unlinked
+fn parse_display(pair: pest::iterators::Pair<Rule>, display: &mut SequenceDisplay) {
+    let mut hide = false;
+    for inner in pair.into_inner() {
+        match inner.as_rule() {
+            Rule::display_mode => hide = inner.as_str() == "hide",
+            Rule::display_target => {
+                if inner.as_str().eq_ignore_ascii_case("footbox") {
+                    display.hide_footbox = hide;
+                } else {
+                    display.hide_unlinked = hide;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
 /// Парсит activate
 fn parse_activate(pair: pest::iterators::Pair<Rule>) -> Option<(String, Option<Color>)> {
     let mut participant: Option<String> = None;
@@ -774,6 +928,29 @@ fn parse_return(pair: pest::iterators::Pair<Rule>) -> Return {
     Return { label }
 }
 
+/// Парсит длительность между якорями
+fn parse_duration(pair: pest::iterators::Pair<Rule>) -> Duration {
+    let mut anchors = Vec::new();
+    let mut label = None;
+
+    for inner in pair.into_inner() {
+        match inner.as_rule() {
+            Rule::anchor => anchors.extend(inner.into_inner().map(|name| name.as_str().to_string())),
+            Rule::message_text => {
+                label = Some(inner.as_str().trim().to_string()).filter(|text| !text.is_empty());
+            }
+            _ => {}
+        }
+    }
+
+    let mut anchors = anchors.into_iter();
+    Duration {
+        from: anchors.next().unwrap_or_default(),
+        to: anchors.next().unwrap_or_default(),
+        label,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -891,6 +1068,114 @@ Alice ->> Bob: Async
         }
     }
 
+    #[test]
+    fn test_parse_boundary_messages() {
+        let source = r#"@startuml
+[-> Alice: входящее
+Alice ->] : исходящее
+?-> Bob : найденное
+Bob ->x? : потерянное
+[o-> Alice
+Alice ->o]
+Alice <-- Bob : ответ
+Alice ->x Bob
+@enduml"#;
+
+        let diagram = parse_sequence(source).unwrap();
+        let messages: Vec<&Message> = diagram
+            .elements
+            .iter()
+            .filter_map(|e| match e {
+                SequenceElement::Message(msg) => Some(msg),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(messages.len(), 8);
+
+        assert_eq!(messages[0].from, MessageEndpoint::Border(EndpointSide::Left));
+        assert_eq!(messages[0].to, "Alice");
+        assert_eq!(messages[0].label, "входящее");
+        assert_eq!(messages[1].to, MessageEndpoint::Border(EndpointSide::Right));
+        assert_eq!(messages[2].from, MessageEndpoint::Short(EndpointSide::Left));
+        assert_eq!(messages[3].to, MessageEndpoint::Short(EndpointSide::Right));
+        assert_eq!(messages[3].arrow_type, ArrowType::Cross);
+        assert_eq!(messages[4].source_decoration, Some(EndpointDecoration::Circle));
+        assert_eq!(messages[4].arrow_type, ArrowType::Normal);
+        assert_eq!(messages[5].arrow_type, ArrowType::Circle);
+        assert_eq!(messages[5].source_decoration, None);
+
+        // Стрелка налево: отправитель — Bob
+        assert_eq!(messages[6].from, "Bob");
+        assert_eq!(messages[6].to, "Alice");
+        assert!(messages[6].arrow_left);
+        assert_eq!(messages[6].line_style, LineStyle::Dashed);
+
+        assert_eq!(messages[7].to, "Bob");
+        assert_eq!(messages[7].arrow_type, ArrowType::Cross);
+
+        // Рамка не становится участником
+        assert!(diagram.participants.is_empty());
+    }
+
+    #[test]
+    fn test_parse_teoz_messages() {
+        let source = r#"@startuml
+!pragma teoz true
+{start} Alice -> Bob : start
+& Bob -> Charlie : at the same time
+Bob ->(10) Alice : slanted
+{end} Bob -> Alice : finish
+{start} <-> {end} : some time
+@enduml"#;
+
+        let diagram = parse_sequence(source).unwrap();
+        assert_eq!(diagram.metadata.pragma("teoz"), Some("true"));
+        assert_eq!(diagram.elements.len(), 5);
+
+        let message = |index: usize| match &diagram.elements[index] {
+            SequenceElement::Message(msg) => msg,
+            other => panic!("Expected message, got {:?}", other),
+        };
+        assert_eq!(message(0).anchor.as_deref(), Some("start"));
+        assert!(!message(0).parallel);
+        assert!(message(1).parallel);
+        assert_eq!(message(1).from, "Bob");
+        assert_eq!(message(2).slant, Some(10));
+        assert_eq!(message(2).to, "Alice");
+        assert_eq!(message(3).anchor.as_deref(), Some("end"));
+
+        match &diagram.elements[4] {
+            SequenceElement::Duration(duration) => {
+                assert_eq!(duration.from, "start");
+                assert_eq!(duration.to, "end");
+                assert_eq!(duration.label.as_deref(), Some("some time"));
+            }
+            other => panic!("Expected duration, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_display_directives() {
+        let source = "@startuml\nhide footbox\nhide unlinked\nskinparam maxMessageSize 60\nskinparam lifelineStrategy solid\nAlice -> Bob: Hi\n@enduml";
+        let diagram = parse_sequence(source).unwrap();
+
+        assert!(diagram.display.hide_footbox);
+        assert!(diagram.display.hide_unlinked);
+        assert_eq!(diagram.display.max_message_size, Some(60.0));
+        assert!(diagram.display.solid_lifelines);
+        assert_eq!(diagram.messages().len(), 1);
+    }
+
+    #[test]
+    fn test_source_spans() {
+        let source = "@startuml\nparticipant Alice\n\n  Alice -> Bob: Hi\n@enduml";
+        let diagram = parse_sequence(source).unwrap();
+
+        assert_eq!(diagram.participants[0].span.line, 2);
+        let message = diagram.messages()[0];
+        assert_eq!((message.span.line, message.span.column), (4, 3));
+    }
+
     #[test]
     fn test_parse_fragment() {
         let source = r#"@startuml
@@ -1033,6 +1318,34 @@ note over Alice, Bob: Shared note
         }
     }
 
+    #[test]
+    fn test_parse_note_multiline_creole() {
+        let source = r#"@startuml
+Alice -> Bob: Hello
+note over Alice
+  * one
+  |= A | B |
+end note
+note left of Bob
+  **bold**
+end note
+@enduml"#;
+
+        let diagram = parse_sequence(source).unwrap();
+
+        match (&diagram.elements[1], &diagram.elements[2]) {
+            (SequenceElement::Note(over), SequenceElement::Note(left)) => {
+                assert_eq!(over.position, NotePosition::Over);
+                assert_eq!(over.anchors, vec!["Alice".to_string()]);
+                assert_eq!(over.text, "* one\n|= A | B |");
+                assert_eq!(left.position, NotePosition::Left);
+                assert_eq!(left.anchors, vec!["Bob".to_string()]);
+                assert_eq!(left.text, "**bold**");
+            }
+            other => panic!("Expected notes, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_parse_divider() {
         let source = r#"@startuml
@@ -1221,12 +1534,32 @@ Alice -> Bob++ #FFBBBB: hello
         match &diagram.elements[0] {
             SequenceElement::Message(msg) => {
                 assert!(msg.activate, "Expected activate=true");
-                assert!(msg.color.is_some(), "Expected color for activation");
+                assert!(msg.activation_color.is_some(), "Expected color for activation");
+                assert!(msg.color.is_none(), "Activation color is not the arrow color");
             }
             _ => panic!("Expected Message"),
         }
     }
 
+    #[test]
+    fn test_parse_arrow_style() {
+        let source = "@startuml\nAlice -[#red]> Bob : one\nBob <[#0000FF,dotted]- Alice : two\nAlice -[bold]-> Bob\n@enduml";
+        let diagram = parse_sequence(source).unwrap();
+        let messages = diagram.messages();
+        assert_eq!(messages.len(), 3);
+
+        assert_eq!(messages[0].color.as_ref().map(Color::to_css).as_deref(), Some("red"));
+        assert_eq!(messages[0].line_style, LineStyle::Solid);
+        assert_eq!((messages[0].from.participant(), messages[0].to.participant()), (Some("Alice"), Some("Bob")));
+
+        assert_eq!(messages[1].color.as_ref().map(Color::to_css).as_deref(), Some("#0000FF"));
+        assert_eq!(messages[1].line_style, LineStyle::Dotted);
+        assert_eq!(messages[1].to.participant(), Some("Bob"));
+
+        assert_eq!(messages[2].color, None);
+        assert_eq!(messages[2].line_style, LineStyle::Bold);
+    }
+
     #[test]
     fn test_parse_shortcut_deactivate_activate() {
         // Синтаксис: Bob -->-- Alice : done (деактивация Bob)
@@ -1583,4 +1916,51 @@ autonumber resume
         assert!(has_return, "Expected return statements");
         assert_eq!(return_count, 2, "Expected 2 return statements");
     }
+
+    #[test]
+    fn test_parse_newpage() {
+        let source = r#"@startuml
+Alice -> Bob: Hello
+newpage
+Bob -> Alice: Hi
+newpage Ответы
+Alice -> Bob: Bye
+@enduml"#;
+
+        let diagram = parse_sequence(source).unwrap();
+        let breaks: Vec<_> = diagram
+            .elements
+            .iter()
+            .filter_map(|e| match e {
+                SequenceElement::NewPage(title) => Some(title.clone()),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(breaks, vec![None, Some("Ответы".to_string())]);
+        assert_eq!(diagram.elements.len(), 5);
+    }
+
+    #[test]
+    fn test_parse_links() {
+        let source = r#"@startuml
+participant Alice [[https://example.com{Профиль}]]
+Alice -> Bob: [[https://example.com/api запрос]]
+@enduml"#;
+
+        let diagram = parse_sequence(source).unwrap();
+
+        let alice = &diagram.participants[0];
+        let link = alice.link.as_ref().expect("participant link");
+        assert_eq!(link.url, "https://example.com");
+        assert_eq!(link.tooltip.as_deref(), Some("Профиль"));
+
+        match &diagram.elements[0] {
+            SequenceElement::Message(msg) => {
+                assert_eq!(msg.label, "запрос");
+                assert_eq!(msg.link.as_ref().unwrap().url, "https://example.com/api");
+            }
+            other => panic!("Expected message, got {:?}", other),
+        }
+    }
 }
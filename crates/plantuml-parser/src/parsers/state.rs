@@ -6,9 +6,11 @@ use pest::Parser;
 use pest_derive::Parser;
 
 use plantuml_ast::state::{State, StateDiagram, StateType, Transition};
-use plantuml_ast::common::{Note, NotePosition};
+use plantuml_ast::common::{InlineStyle, Link, Note, NotePosition};
 
-use crate::{ParseError, Result};
+use super::arrow::{arrow_hints, ArrowHints};
+use super::metadata::extract_metadata;
+use crate::Result;
 
 #[derive(Parser)]
 #[grammar = "grammars/state.pest"]
@@ -16,13 +18,13 @@ pub struct StateParser;
 
 /// Парсит state diagram из исходного кода
 pub fn parse_state(source: &str) -> Result<StateDiagram> {
+    let (source, metadata) = extract_metadata(source);
+    let source = source.as_str();
     let pairs =
-        StateParser::parse(Rule::diagram, source).map_err(|e| ParseError::SyntaxError {
-            line: e.line().to_string().parse().unwrap_or(0),
-            message: e.to_string(),
-        })?;
+        StateParser::parse(Rule::diagram, source).map_err(crate::error::pest_error)?;
 
     let mut diagram = StateDiagram::new();
+    diagram.metadata = metadata;
 
     for pair in pairs {
         if pair.as_rule() == Rule::diagram {
@@ -97,6 +99,7 @@ fn parse_state_composite(pair: pest::iterators::Pair<Rule>) -> Option<State> {
     let mut alias: Option<String> = None;
     let mut substates = Vec::new();
     let mut internal_transitions = Vec::new();
+    let mut style = InlineStyle::default();
 
     for inner in pair.into_inner() {
         match inner.as_rule() {
@@ -106,6 +109,9 @@ fn parse_state_composite(pair: pest::iterators::Pair<Rule>) -> Option<State> {
             Rule::state_alias_part => {
                 alias = extract_alias(inner);
             }
+            Rule::state_color_part => {
+                style = extract_style(inner);
+            }
             Rule::body => {
                 // Парсим вложенное тело
                 let mut sub_diagram = StateDiagram::new();
@@ -130,7 +136,10 @@ fn parse_state_composite(pair: pest::iterators::Pair<Rule>) -> Option<State> {
         substates,
         internal_transitions,
         regions: Vec::new(),
-        color: None,
+        color: style.fill,
+        border_color: style.line_color,
+        text_color: style.text_color,
+        line_style: style.line_style,
         entry_action: None,
         exit_action: None,
         do_action: None,
@@ -142,6 +151,7 @@ fn parse_state_simple(pair: pest::iterators::Pair<Rule>) -> Option<State> {
     let mut name = String::new();
     let mut alias: Option<String> = None;
     let mut description: Option<String> = None;
+    let mut style = InlineStyle::default();
 
     for inner in pair.into_inner() {
         match inner.as_rule() {
@@ -151,6 +161,9 @@ fn parse_state_simple(pair: pest::iterators::Pair<Rule>) -> Option<State> {
             Rule::state_alias_part => {
                 alias = extract_alias(inner);
             }
+            Rule::state_color_part => {
+                style = extract_style(inner);
+            }
             Rule::state_description_part => {
                 description = extract_description(inner);
             }
@@ -171,7 +184,10 @@ fn parse_state_simple(pair: pest::iterators::Pair<Rule>) -> Option<State> {
         substates: Vec::new(),
         internal_transitions: Vec::new(),
         regions: Vec::new(),
-        color: None,
+        color: style.fill,
+        border_color: style.line_color,
+        text_color: style.text_color,
+        line_style: style.line_style,
         entry_action: None,
         exit_action: None,
         do_action: None,
@@ -211,6 +227,9 @@ fn parse_state_alias(pair: pest::iterators::Pair<Rule>) -> Option<State> {
         internal_transitions: Vec::new(),
         regions: Vec::new(),
         color: None,
+        border_color: None,
+        text_color: None,
+        line_style: None,
         entry_action: None,
         exit_action: None,
         do_action: None,
@@ -241,6 +260,9 @@ fn parse_state_special(pair: pest::iterators::Pair<Rule>, state_type: StateType)
         internal_transitions: Vec::new(),
         regions: Vec::new(),
         color: None,
+        border_color: None,
+        text_color: None,
+        line_style: None,
         entry_action: None,
         exit_action: None,
         do_action: None,
@@ -254,9 +276,13 @@ fn parse_transition(pair: pest::iterators::Pair<Rule>) -> Option<Transition> {
     let mut event: Option<String> = None;
     let mut guard: Option<String> = None;
     let mut action: Option<String> = None;
+    let mut hints = ArrowHints::default();
 
     for inner in pair.into_inner() {
         match inner.as_rule() {
+            Rule::arrow => {
+                hints = arrow_hints(inner.as_str());
+            }
             Rule::transition_from => {
                 from = extract_transition_endpoint(inner);
             }
@@ -283,7 +309,10 @@ fn parse_transition(pair: pest::iterators::Pair<Rule>) -> Option<Transition> {
         event,
         guard,
         action,
-        color: None,
+        color: hints.color,
+        line_style: hints.line_style,
+        direction: hints.direction,
+        hidden: hints.hidden,
     })
 }
 
@@ -413,11 +442,14 @@ fn parse_note(pair: pest::iterators::Pair<Rule>) -> Option<Note> {
         return None;
     }
 
+    let (text, link) = Link::extract(&text);
+
     Some(Note {
         text,
         position,
         anchors,
         background_color: None,
+        link,
     })
 }
 
@@ -440,6 +472,14 @@ fn extract_name(pair: pest::iterators::Pair<Rule>) -> String {
     text
 }
 
+/// Извлекает встроенный стиль: `#pink;line:red;line.dashed;text:blue`
+fn extract_style(pair: pest::iterators::Pair<Rule>) -> InlineStyle {
+    pair.into_inner()
+        .find(|inner| inner.as_rule() == Rule::state_color)
+        .map(|inner| InlineStyle::parse(inner.as_str()))
+        .unwrap_or_default()
+}
+
 /// Извлекает алиас
 fn extract_alias(pair: pest::iterators::Pair<Rule>) -> Option<String> {
     for inner in pair.into_inner() {
@@ -476,6 +516,7 @@ fn extract_quoted_string(pair: pest::iterators::Pair<Rule>) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use plantuml_ast::common::{Color, Direction, LineStyle};
 
     #[test]
     fn test_parse_simple_transition() {
@@ -607,4 +648,32 @@ join1 --> [*]
         assert!(join.is_some());
         assert_eq!(join.unwrap().state_type, StateType::Join);
     }
+
+    #[test]
+    fn test_parse_arrow_hints() {
+        let source = "@startuml\n[*] --> Idle\nIdle -left-> Busy\nBusy -[#red,hidden]-> Idle\n@enduml";
+        let diagram = parse_state(source).unwrap();
+
+        assert_eq!(diagram.transitions.len(), 3);
+        assert_eq!(diagram.transitions[0].direction, None);
+        assert_eq!(diagram.transitions[1].direction, Some(Direction::RightToLeft));
+        assert!(diagram.transitions[2].hidden);
+    }
+
+    #[test]
+    fn test_parse_inline_style() {
+        let source = "@startuml\nstate Idle #red;line:green;line.dashed;text:white : waiting\nIdle -[#orange,bold]-> Busy\n@enduml";
+        let diagram = parse_state(source).unwrap();
+
+        let idle = &diagram.states[0];
+        assert_eq!(idle.color, Some(Color::parse("red")));
+        assert_eq!(idle.border_color, Some(Color::parse("green")));
+        assert_eq!(idle.text_color, Some(Color::parse("white")));
+        assert_eq!(idle.line_style, Some(LineStyle::Dashed));
+        assert_eq!(idle.description.as_deref(), Some("waiting"));
+
+        let transition = &diagram.transitions[0];
+        assert_eq!(transition.color, Some(Color::parse("orange")));
+        assert_eq!(transition.line_style, Some(LineStyle::Bold));
+    }
 }
@@ -5,11 +5,13 @@
 use pest::Parser;
 use pest_derive::Parser;
 
+use plantuml_ast::common::Color;
 use plantuml_ast::timing::{
     ParticipantType, StateChange, TimeConstraint, TimeValue, TimingDiagram, TimingParticipant,
 };
 
-use crate::{ParseError, Result};
+use super::metadata::extract_metadata;
+use crate::Result;
 
 #[derive(Parser)]
 #[grammar = "grammars/timing.pest"]
@@ -17,14 +19,12 @@ pub struct TimingParser;
 
 /// Парсит timing diagram из исходного кода
 pub fn parse_timing(source: &str) -> Result<TimingDiagram> {
-    let pairs = TimingParser::parse(Rule::diagram, source).map_err(|e| {
-        ParseError::SyntaxError {
-            line: e.line().to_string().parse().unwrap_or(0),
-            message: e.to_string(),
-        }
-    })?;
+    let (source, metadata) = extract_metadata(source);
+    let source = source.as_str();
+    let pairs = TimingParser::parse(Rule::diagram, source).map_err(crate::error::pest_error)?;
 
     let mut diagram = TimingDiagram::new();
+    diagram.metadata = metadata;
     let mut current_time: Option<TimeValue> = None;
 
     for pair in pairs {
@@ -87,6 +87,7 @@ fn parse_participant(pair: pest::iterators::Pair<Rule>) -> Option<TimingParticip
     let mut name = String::new();
     let mut alias: Option<String> = None;
     let mut participant_type = ParticipantType::Robust;
+    let mut color: Option<Color> = None;
 
     for inner in pair.into_inner() {
         match inner.as_rule() {
@@ -106,6 +107,9 @@ fn parse_participant(pair: pest::iterators::Pair<Rule>) -> Option<TimingParticip
                 // Это alias после "as"
                 alias = Some(inner.as_str().to_string());
             }
+            Rule::color => {
+                color = Some(Color::parse(inner.as_str()));
+            }
             _ => {}
         }
     }
@@ -119,7 +123,7 @@ fn parse_participant(pair: pest::iterators::Pair<Rule>) -> Option<TimingParticip
         alias,
         participant_type,
         states: Vec::new(),
-        color: None,
+        color,
     })
 }
 
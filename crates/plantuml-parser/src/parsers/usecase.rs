@@ -5,13 +5,15 @@
 use pest::Parser;
 use pest_derive::Parser;
 
-use plantuml_ast::common::{Direction, Note, NotePosition, Stereotype};
+use plantuml_ast::common::{Color, Link, Note, NotePosition, Stereotype};
 use plantuml_ast::usecase::{
     UseCase, UseCaseActor, UseCaseDiagram, UseCasePackage, UseCaseRelationType,
     UseCaseRelationship,
 };
 
-use crate::{ParseError, Result};
+use super::arrow::{arrow_hints, ArrowHints};
+use super::metadata::extract_metadata;
+use crate::Result;
 
 #[derive(Parser)]
 #[grammar = "grammars/usecase.pest"]
@@ -19,12 +21,14 @@ pub struct UseCaseParser;
 
 /// Парсит use case diagram из исходного кода
 pub fn parse_usecase(source: &str) -> Result<UseCaseDiagram> {
-    let pairs = UseCaseParser::parse(Rule::diagram, source).map_err(|e| ParseError::SyntaxError {
-        line: e.line().to_string().parse().unwrap_or(0),
-        message: e.to_string(),
-    })?;
+    let (source, metadata) = extract_metadata(source);
+    let source = source.as_str();
+    let pairs = UseCaseParser::parse(Rule::diagram, source).map_err(crate::error::pest_error)?;
 
     let mut diagram = UseCaseDiagram::new();
+    // `left to right direction` разбирается вместе с директивами оформления
+    diagram.direction = metadata.direction;
+    diagram.metadata = metadata;
 
     for pair in pairs {
         if pair.as_rule() == Rule::diagram {
@@ -68,12 +72,6 @@ fn parse_body(pair: pest::iterators::Pair<Rule>, diagram: &mut UseCaseDiagram) {
                     diagram.notes.push(note);
                 }
             }
-            Rule::left_to_right => {
-                diagram.direction = Direction::LeftToRight;
-            }
-            Rule::top_to_bottom => {
-                diagram.direction = Direction::TopToBottom;
-            }
             _ => {}
         }
     }
@@ -84,6 +82,8 @@ fn parse_actor_def(pair: pest::iterators::Pair<Rule>) -> Option<UseCaseActor> {
     let mut name = String::new();
     let mut alias: Option<String> = None;
     let mut stereotype: Option<Stereotype> = None;
+    let mut link: Option<Link> = None;
+    let mut color: Option<Color> = None;
 
     for inner in pair.into_inner() {
         match inner.as_rule() {
@@ -103,6 +103,9 @@ fn parse_actor_def(pair: pest::iterators::Pair<Rule>) -> Option<UseCaseActor> {
                         Rule::stereotype_part => {
                             stereotype = extract_stereotype(colon_inner);
                         }
+                        Rule::link_part => {
+                            link = Link::parse(colon_inner.as_str());
+                        }
                         _ => {}
                     }
                 }
@@ -113,6 +116,12 @@ fn parse_actor_def(pair: pest::iterators::Pair<Rule>) -> Option<UseCaseActor> {
             Rule::stereotype_part => {
                 stereotype = extract_stereotype(inner);
             }
+            Rule::link_part => {
+                link = Link::parse(inner.as_str());
+            }
+            Rule::color_part => {
+                color = extract_color(inner);
+            }
             _ => {}
         }
     }
@@ -125,7 +134,8 @@ fn parse_actor_def(pair: pest::iterators::Pair<Rule>) -> Option<UseCaseActor> {
         name,
         alias,
         stereotype,
-        color: None,
+        color,
+        link,
     })
 }
 
@@ -134,6 +144,8 @@ fn parse_usecase_def(pair: pest::iterators::Pair<Rule>) -> Option<UseCase> {
     let mut name = String::new();
     let mut alias: Option<String> = None;
     let mut stereotype: Option<Stereotype> = None;
+    let mut link: Option<Link> = None;
+    let mut color: Option<Color> = None;
 
     for inner in pair.into_inner() {
         match inner.as_rule() {
@@ -153,6 +165,9 @@ fn parse_usecase_def(pair: pest::iterators::Pair<Rule>) -> Option<UseCase> {
                         Rule::stereotype_part => {
                             stereotype = extract_stereotype(paren_inner);
                         }
+                        Rule::link_part => {
+                            link = Link::parse(paren_inner.as_str());
+                        }
                         _ => {}
                     }
                 }
@@ -163,6 +178,12 @@ fn parse_usecase_def(pair: pest::iterators::Pair<Rule>) -> Option<UseCase> {
             Rule::stereotype_part => {
                 stereotype = extract_stereotype(inner);
             }
+            Rule::link_part => {
+                link = Link::parse(inner.as_str());
+            }
+            Rule::color_part => {
+                color = extract_color(inner);
+            }
             _ => {}
         }
     }
@@ -175,7 +196,8 @@ fn parse_usecase_def(pair: pest::iterators::Pair<Rule>) -> Option<UseCase> {
         name,
         alias,
         stereotype,
-        color: None,
+        color,
+        link,
     })
 }
 
@@ -183,6 +205,7 @@ fn parse_usecase_def(pair: pest::iterators::Pair<Rule>) -> Option<UseCase> {
 fn parse_package_def(pair: pest::iterators::Pair<Rule>) -> Option<UseCasePackage> {
     let mut name = String::new();
     let mut use_cases = Vec::new();
+    let mut color: Option<Color> = None;
 
     for inner in pair.into_inner() {
         match inner.as_rule() {
@@ -195,6 +218,9 @@ fn parse_package_def(pair: pest::iterators::Pair<Rule>) -> Option<UseCasePackage
                 parse_body(inner, &mut sub_diagram);
                 use_cases = sub_diagram.use_cases;
             }
+            Rule::color_part => {
+                color = extract_color(inner);
+            }
             _ => {}
         }
     }
@@ -206,7 +232,7 @@ fn parse_package_def(pair: pest::iterators::Pair<Rule>) -> Option<UseCasePackage
     Some(UseCasePackage {
         name,
         use_cases,
-        color: None,
+        color,
     })
 }
 
@@ -217,6 +243,7 @@ fn parse_relationship(pair: pest::iterators::Pair<Rule>) -> Option<UseCaseRelati
     let mut relation_type = UseCaseRelationType::Association;
     let mut label: Option<String> = None;
     let mut is_dashed = false;
+    let mut hints = ArrowHints::default();
 
     for inner in pair.into_inner() {
         match inner.as_rule() {
@@ -227,6 +254,7 @@ fn parse_relationship(pair: pest::iterators::Pair<Rule>) -> Option<UseCaseRelati
                 to = extract_relationship_endpoint(inner);
             }
             Rule::arrow => {
+                hints = arrow_hints(inner.as_str());
                 // Определяем тип связи по стрелке
                 for arrow_inner in inner.into_inner() {
                     match arrow_inner.as_rule() {
@@ -268,9 +296,19 @@ fn parse_relationship(pair: pest::iterators::Pair<Rule>) -> Option<UseCaseRelati
         to,
         relation_type,
         label,
+        color: hints.color,
+        direction: hints.direction,
+        hidden: hints.hidden,
     })
 }
 
+/// Цвет из `#color`
+fn extract_color(pair: pest::iterators::Pair<Rule>) -> Option<Color> {
+    pair.into_inner()
+        .find(|inner| inner.as_rule() == Rule::color)
+        .map(|inner| Color::parse(inner.as_str()))
+}
+
 /// Извлекает endpoint связи
 fn extract_relationship_endpoint(pair: pest::iterators::Pair<Rule>) -> String {
     for inner in pair.into_inner() {
@@ -337,11 +375,14 @@ fn parse_note(pair: pest::iterators::Pair<Rule>) -> Option<Note> {
         return None;
     }
 
+    let (text, link) = Link::extract(&text);
+
     Some(Note {
         text,
         position,
         anchors,
         background_color: None,
+        link,
     })
 }
 
@@ -441,6 +482,7 @@ fn extract_note_target(pair: pest::iterators::Pair<Rule>) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use plantuml_ast::common::Direction;
 
     #[test]
     fn test_parse_simple_actors() {
@@ -546,4 +588,39 @@ rectangle "E-Commerce System" {
         assert_eq!(diagram.relationships[0].from, "User");
         assert_eq!(diagram.relationships[0].to, "Login");
     }
+
+    #[test]
+    fn test_parse_links() {
+        let source = r#"
+@startuml
+actor User [[https://example.com/user]]
+usecase Login [[https://example.com/login{Вход}]]
+(Register) [[https://example.com/register]]
+@enduml
+"#;
+
+        let diagram = parse_usecase(source).unwrap();
+        assert_eq!(
+            diagram.actors[0].link.as_ref().unwrap().url,
+            "https://example.com/user"
+        );
+        let login = diagram.use_cases[0].link.as_ref().unwrap();
+        assert_eq!(login.url, "https://example.com/login");
+        assert_eq!(login.tooltip.as_deref(), Some("Вход"));
+        assert_eq!(
+            diagram.use_cases[1].link.as_ref().unwrap().url,
+            "https://example.com/register"
+        );
+    }
+
+    #[test]
+    fn test_parse_arrow_hints() {
+        let source = "@startuml\nleft to right direction\nactor User\nUser -d-> (Login)\n(Login) .[hidden].> (Logout)\n@enduml";
+        let diagram = parse_usecase(source).unwrap();
+
+        assert_eq!(diagram.direction, Direction::LeftToRight);
+        assert_eq!(diagram.relationships.len(), 2);
+        assert_eq!(diagram.relationships[0].direction, Some(Direction::TopToBottom));
+        assert!(diagram.relationships[1].hidden);
+    }
 }
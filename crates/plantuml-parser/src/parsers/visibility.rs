@@ -0,0 +1,42 @@
+//! Правила видимости `hide`/`show`/`remove`/`restore`
+//!
+//! Общие для class и component диаграмм. Строки правил разбираются до
+//! грамматики диаграммы и заменяются пустыми, чтобы номера строк в ошибках
+//! не сдвигались. Применяются правила после парсинга
+//! (`Diagram::apply_visibility_rules`).
+
+use plantuml_ast::visibility::VisibilityRule;
+
+/// Извлекает правила видимости из исходника в порядке появления
+pub fn extract_visibility_rules(source: &str) -> (String, Vec<VisibilityRule>) {
+    let mut rules = Vec::new();
+    let lines: Vec<&str> = source
+        .lines()
+        .map(|line| match VisibilityRule::parse(line) {
+            Some(rule) => {
+                rules.push(rule);
+                ""
+            }
+            None => line,
+        })
+        .collect();
+    (lines.join("\n"), rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plantuml_ast::visibility::{ElementPart, ElementSelector};
+
+    #[test]
+    fn test_extract_visibility_rules() {
+        let source = "@startuml\nhide empty members\nclass A {\n  show()\n}\nremove $internal\n@enduml";
+        let (rest, rules) = extract_visibility_rules(source);
+
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].part, ElementPart::Members);
+        assert_eq!(rules[1].selector, ElementSelector::Tag("internal".into()));
+        // Метод `show()` остаётся в теле класса, номера строк сохраняются
+        assert_eq!(rest, "@startuml\n\nclass A {\n  show()\n}\n\n@enduml");
+    }
+}
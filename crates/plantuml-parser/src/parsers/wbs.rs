@@ -9,6 +9,7 @@ use pest_derive::Parser;
 
 use plantuml_ast::wbs::{WbsDiagram, WbsNode, WbsNodeStyle};
 
+use super::metadata::extract_metadata;
 use crate::error::ParseError;
 
 #[derive(Parser)]
@@ -17,10 +18,13 @@ struct WbsParser;
 
 /// Парсит WBS диаграмму из исходного кода
 pub fn parse_wbs(source: &str) -> crate::Result<WbsDiagram> {
+    let (source, metadata) = extract_metadata(source);
+    let source = source.as_str();
     let pairs = WbsParser::parse(Rule::wbs, source)
         .map_err(|e| ParseError::GrammarError(e.to_string()))?;
 
     let mut diagram = WbsDiagram::new();
+    diagram.metadata = metadata;
     let mut node_stack: Vec<WbsNode> = Vec::new();
 
     for pair in pairs {
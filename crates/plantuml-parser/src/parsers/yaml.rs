@@ -9,6 +9,7 @@ use pest_derive::Parser;
 use plantuml_ast::json::JsonNode;
 use plantuml_ast::yaml::YamlDiagram;
 
+use super::metadata::extract_metadata;
 use crate::error::ParseError;
 
 #[derive(Parser)]
@@ -17,10 +18,13 @@ struct YamlParser;
 
 /// Парсит YAML диаграмму
 pub fn parse_yaml(source: &str) -> crate::Result<YamlDiagram> {
+    let (source, metadata) = extract_metadata(source);
+    let source = source.as_str();
     let pairs = YamlParser::parse(Rule::yaml_diagram, source)
         .map_err(|e| ParseError::GrammarError(format!("Ошибка парсинга YAML: {}", e)))?;
 
     let mut diagram = YamlDiagram::new();
+    diagram.metadata = metadata;
 
     for pair in pairs {
         match pair.as_rule() {
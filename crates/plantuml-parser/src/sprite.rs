@@ -0,0 +1,367 @@
+//! Определения спрайтов
+//!
+//! Спрайты не зависят от типа диаграммы, поэтому извлекаются из исходника
+//! до парсинга грамматикой:
+//!
+//! - `sprite $name [WxH/16] {` + H строк по W hex-символов + `}`
+//!   (также `/8` и `/4`)
+//! - `sprite $name [WxH/16z] DATA` — сжатые пиксели в кодировке PlantUML
+//! - `sprite $name <svg ...>...</svg>` — в одну или несколько строк
+
+use miniz_oxide::inflate::{decompress_to_vec_with_limit, decompress_to_vec_zlib_with_limit};
+use plantuml_ast::sprite::{Sprite, SpriteData, SpriteTable};
+
+use crate::{ParseError, Result};
+
+/// Извлекает определения спрайтов из исходника
+///
+/// Строки определений заменяются пустыми, чтобы номера строк в ошибках
+/// грамматики не сдвигались.
+pub fn extract_sprites(source: &str) -> Result<(String, SpriteTable)> {
+    let mut table = SpriteTable::new();
+    let mut output = Vec::new();
+    let mut lines = source.lines().enumerate();
+
+    while let Some((index, line)) = lines.next() {
+        let Some(definition) = line.trim().strip_prefix("sprite ") else {
+            output.push(line);
+            continue;
+        };
+        let line_number = index + 1;
+        let error = |message: String| ParseError::SyntaxError {
+            line: line_number,
+            message,
+        };
+
+        let definition = definition.trim();
+        let (name, rest) = definition
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| error("ожидается описание спрайта".to_string()))?;
+        let name = name.trim_start_matches('$');
+        let rest = rest.trim();
+        output.push("");
+
+        let sprite = if rest.starts_with("<svg") {
+            // SVG до закрывающего тега
+            let mut svg = rest.to_string();
+            while !svg.contains("</svg>") {
+                let (_, next) = lines
+                    .next()
+                    .ok_or_else(|| error(format!("спрайт ${}: нет </svg>", name)))?;
+                output.push("");
+                svg.push('\n');
+                svg.push_str(next.trim());
+            }
+            svg_sprite(name, &svg).ok_or_else(|| error(format!("спрайт ${}: нет размеров SVG", name)))?
+        } else {
+            let (format, data) = rest
+                .strip_prefix('[')
+                .and_then(|r| r.split_once(']'))
+                .ok_or_else(|| error(format!("спрайт ${}: ожидается [WxH/16]", name)))?;
+            let format = SpriteFormat::parse(format)
+                .ok_or_else(|| error(format!("спрайт ${}: неверный формат [{}]", name, format)))?;
+
+            // Данные — в той же строке или в блоке { ... }
+            let mut rows = Vec::new();
+            let data = data.trim();
+            if let Some(inline) = data.strip_prefix('{') {
+                rows.extend(inline.split_whitespace().map(str::to_string));
+                loop {
+                    let (_, next) = lines
+                        .next()
+                        .ok_or_else(|| error(format!("спрайт ${}: нет закрывающей }}", name)))?;
+                    output.push("");
+                    let next = next.trim();
+                    if let Some(last) = next.strip_suffix('}') {
+                        rows.extend(last.split_whitespace().map(str::to_string));
+                        break;
+                    }
+                    rows.extend(next.split_whitespace().map(str::to_string));
+                }
+            } else {
+                rows.push(data.to_string());
+            }
+
+            let pixels = format
+                .decode(&rows)
+                .ok_or_else(|| error(format!("спрайт ${}: неверные данные", name)))?;
+            Sprite {
+                name: name.to_string(),
+                width: format.width,
+                height: format.height,
+                data: SpriteData::Gray {
+                    levels: format.levels,
+                    pixels,
+                },
+            }
+        };
+        table.insert(sprite);
+    }
+
+    let mut source_out = output.join("\n");
+    if source.ends_with('\n') {
+        source_out.push('\n');
+    }
+    Ok((source_out, table))
+}
+
+/// Формат `[WxH/16]` или `[WxH/16z]`
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SpriteFormat {
+    width: u32,
+    height: u32,
+    levels: u8,
+    compressed: bool,
+}
+
+impl SpriteFormat {
+    fn parse(format: &str) -> Option<Self> {
+        let (size, depth) = format.trim().split_once('/')?;
+        let (width, height) = size.split_once(['x', 'X'])?;
+        let (levels, compressed) = match depth.strip_suffix('z') {
+            Some(levels) => (levels, true),
+            None => (depth, false),
+        };
+        let levels: u8 = levels.parse().ok()?;
+        if !matches!(levels, 4 | 8 | 16) {
+            return None;
+        }
+        Some(Self {
+            width: width.trim().parse().ok()?,
+            height: height.trim().parse().ok()?,
+            levels,
+            compressed,
+        })
+    }
+
+    /// Уровни всех пикселей; недостающие пиксели — фон
+    ///
+    /// Сжатые данные распаковываются не больше чем на `width × height`
+    /// байт: поток, который разворачивается сильнее, отвергается.
+    fn decode(&self, rows: &[String]) -> Option<Vec<u8>> {
+        let count = self.width as usize * self.height as usize;
+        let max = self.levels - 1;
+
+        let mut pixels = if self.compressed {
+            let bytes = decode_ascii(&rows.concat())?;
+            let data = decompress_to_vec_with_limit(&bytes, count)
+                .or_else(|_| decompress_to_vec_zlib_with_limit(&bytes, count))
+                .ok()?;
+            data.into_iter().map(|level| level.min(max)).collect::<Vec<_>>()
+        } else {
+            let mut pixels = Vec::with_capacity(count);
+            for row in rows.iter().take(self.height as usize) {
+                let mut levels = row
+                    .chars()
+                    .map(|c| c.to_digit(16).map(|level| (level as u8).min(max)))
+                    .collect::<Option<Vec<_>>>()?;
+                levels.resize(self.width as usize, 0);
+                pixels.extend(levels);
+            }
+            pixels
+        };
+
+        pixels.resize(count, 0);
+        Some(pixels)
+    }
+}
+
+/// Декодирует 6-битную кодировку PlantUML (`0-9A-Za-z-_`, 4 символа → 3 байта)
+fn decode_ascii(text: &str) -> Option<Vec<u8>> {
+    let values = text
+        .chars()
+        .map(|c| match c {
+            '0'..='9' => Some(c as u8 - b'0'),
+            'A'..='Z' => Some(c as u8 - b'A' + 10),
+            'a'..='z' => Some(c as u8 - b'a' + 36),
+            '-' => Some(62),
+            '_' => Some(63),
+            _ => None,
+        })
+        .collect::<Option<Vec<u8>>>()?;
+
+    let mut bytes = Vec::with_capacity(values.len() * 3 / 4 + 3);
+    for chunk in values.chunks(4) {
+        // Неполная последняя группа дополняется нулями
+        let mut group = [0u8; 4];
+        group[..chunk.len()].copy_from_slice(chunk);
+        bytes.push((group[0] << 2) | (group[1] >> 4));
+        bytes.push(((group[1] & 0x0F) << 4) | (group[2] >> 2));
+        bytes.push(((group[2] & 0x03) << 6) | group[3]);
+    }
+    Some(bytes)
+}
+
+/// SVG спрайт; размер из `width`/`height` или `viewBox`
+fn svg_sprite(name: &str, svg: &str) -> Option<Sprite> {
+    let tag_end = svg.find('>')?;
+    let open_tag = &svg[..tag_end];
+    let content = svg[tag_end + 1..].rsplit_once("</svg>")?.0.trim();
+    let view_box = attribute(open_tag, "viewBox").map(|view_box| {
+        view_box
+            .split([' ', ','])
+            .filter(|n| !n.is_empty())
+            .map(|n| n.parse::<f64>().ok())
+            .collect::<Option<Vec<_>>>()
+    });
+
+    let dimension = |value: &str| -> Option<f64> { value.trim_end_matches("px").parse().ok() };
+    let (width, height) = match (attribute(open_tag, "width"), attribute(open_tag, "height")) {
+        (Some(width), Some(height)) => (dimension(width)?, dimension(height)?),
+        _ => match view_box.as_ref()? {
+            Some(numbers) if numbers.len() == 4 => (numbers[2], numbers[3]),
+            _ => return None,
+        },
+    };
+    let view_box = match view_box {
+        Some(Some(numbers)) if numbers.len() == 4 => {
+            format!("{} {} {} {}", numbers[0], numbers[1], numbers[2], numbers[3])
+        }
+        _ => format!("0 0 {} {}", width, height),
+    };
+
+    Some(Sprite {
+        name: name.to_string(),
+        width: width.round() as u32,
+        height: height.round() as u32,
+        data: SpriteData::Svg {
+            view_box,
+            content: content.to_string(),
+        },
+    })
+}
+
+/// Значение атрибута открывающего тега
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let mut search = 0;
+    while let Some(offset) = tag[search..].find(name) {
+        let start = search + offset;
+        search = start + name.len();
+        // Имя атрибута целиком: ` width=`, но не `stroke-width=`
+        let preceded = tag[..start].ends_with(char::is_whitespace);
+        let rest = tag[search..].trim_start();
+        if let (true, Some(rest)) = (preceded, rest.strip_prefix('=')) {
+            let rest = rest.trim_start();
+            let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+            let value = &rest[1..];
+            return value.find(quote).map(|end| &value[..end]);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_gray_sprite() {
+        let source = "@startuml\nsprite $dot [3x2/16] {\n0F0\nF8\n}\nAlice -> Bob\n@enduml\n";
+        let (rest, table) = extract_sprites(source).unwrap();
+
+        // Номера строк сохраняются
+        assert_eq!(rest.lines().count(), source.lines().count());
+        assert_eq!(rest.lines().nth(5), Some("Alice -> Bob"));
+
+        let dot = table.get("dot").unwrap();
+        assert_eq!((dot.width, dot.height), (3, 2));
+        assert_eq!(
+            dot.data,
+            SpriteData::Gray {
+                levels: 16,
+                pixels: vec![0, 15, 0, 15, 8, 0],
+            }
+        );
+    }
+
+    #[test]
+    fn test_extract_compressed_sprite() {
+        // 2x2 пикселя [0, 15, 15, 0], deflate + кодировка PlantUML
+        let pixels = [0u8, 15, 15, 0];
+        let compressed = miniz_oxide::deflate::compress_to_vec(&pixels, 9);
+        let encoded = encode_ascii(&compressed);
+        let source = format!("sprite $z [2x2/16z] {}\n", encoded);
+
+        let (_, table) = extract_sprites(&source).unwrap();
+        let z = table.get("z").unwrap();
+        assert_eq!((z.gray(1, 0), z.gray(0, 1), z.gray(0, 0)), (15, 15, 0));
+    }
+
+    #[test]
+    fn test_extract_plantuml_encoded_sprite() {
+        // Спрайт принтера из документации PlantUML (вывод `-encodesprite 8z`)
+        let source = "sprite $printer [15x15/8z] \
+            NOtH3W0W208HxFz_kMAhj7lHWpa1XC716sz0Pq4MVPEWfBHIuxP3L6kbTcizR8tAhzaqFvXwvFfPEqm0\n";
+        let (_, table) = extract_sprites(source).unwrap();
+        let printer = table.get("printer").unwrap();
+        assert_eq!((printer.width, printer.height), (15, 15));
+        // Верх корпуса пуст, низ — сплошное основание
+        assert_eq!((printer.gray(0, 0), printer.gray(3, 0)), (0, 1));
+        assert_eq!((0..15).map(|x| printer.gray(x, 12)).min(), Some(4));
+
+        // 16 уровней, сжатие как у `-encodesprite 16z` (java.util.zip.Deflater,
+        // уровень 9, без заголовка zlib): рамка 15, внутри градиент
+        let source = "sprite $grad [16x16/16z] \
+            NS-t4S0m0CF0awpWZFstjTcHGlSbOAcKMhUjjTx7cFEtSuUWx3mWA3jF2ChE2uAoyuQWx7mWA3lVzPOF\n";
+        let (_, table) = extract_sprites(source).unwrap();
+        let grad = table.get("grad").unwrap();
+        assert_eq!((grad.gray(0, 0), grad.gray(15, 15)), (15, 15));
+        assert_eq!((grad.gray(1, 1), grad.gray(7, 8), grad.gray(14, 14)), (1, 7, 14));
+    }
+
+    #[test]
+    fn test_compressed_sprite_size_limit() {
+        // Поток распаковывается в 8 байт, а спрайт 2x2 — 4 пикселя
+        let compressed = miniz_oxide::deflate::compress_to_vec(&[1u8; 8], 9);
+        let source = format!("sprite $big [2x2/16z] {}\n", encode_ascii(&compressed));
+        assert!(extract_sprites(&source).is_err());
+    }
+
+    #[test]
+    fn test_extract_svg_sprite() {
+        let source = "sprite foo <svg viewBox=\"0 0 24 16\">\n<circle cx=\"8\" cy=\"8\" r=\"8\"/>\n</svg>\nA -> B\n";
+        let (rest, table) = extract_sprites(source).unwrap();
+        assert_eq!(rest, "\n\n\nA -> B\n");
+
+        let foo = table.get("$foo").unwrap();
+        assert_eq!((foo.width, foo.height), (24, 16));
+        assert_eq!(
+            foo.data,
+            SpriteData::Svg {
+                view_box: "0 0 24 16".to_string(),
+                content: "<circle cx=\"8\" cy=\"8\" r=\"8\"/>".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_invalid_sprite_line() {
+        let err = extract_sprites("@startuml\nsprite $x [2x2/3] {\n}\n").unwrap_err();
+        assert_eq!(err.line(), Some(2));
+        assert!(extract_sprites("sprite $x [2x1/16] {\nGG\n}").is_err());
+    }
+
+    #[test]
+    fn test_attribute_whole_name() {
+        let tag = "<svg stroke-width=\"2\" width=\"10\" height='12'";
+        assert_eq!(attribute(tag, "width"), Some("10"));
+        assert_eq!(attribute(tag, "height"), Some("12"));
+    }
+
+    fn encode_ascii(bytes: &[u8]) -> String {
+        const ALPHABET: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz-_";
+        let mut out = String::new();
+        for chunk in bytes.chunks(3) {
+            let mut group = [0u8; 3];
+            group[..chunk.len()].copy_from_slice(chunk);
+            let values = [
+                group[0] >> 2,
+                ((group[0] & 0x03) << 4) | (group[1] >> 4),
+                ((group[1] & 0x0F) << 2) | (group[2] >> 6),
+                group[2] & 0x3F,
+            ];
+            out.extend(values.iter().map(|v| ALPHABET[*v as usize] as char));
+        }
+        out
+    }
+}
@@ -32,34 +32,56 @@ pub fn handle_undef(name: &str, ctx: &mut PreprocessContext) {
 pub fn handle_ifdef(name: &str, ctx: &mut PreprocessContext, is_ifdef: bool) {
     let defined = ctx.is_defined(name);
     let condition = if is_ifdef { defined } else { !defined };
+    push_condition(condition, ctx);
+}
 
+/// Открывает условный блок (`!if`, `!ifdef`, `!ifndef`)
+pub fn push_condition(condition: bool, ctx: &mut PreprocessContext) {
     // Если мы уже внутри ложного условия, вложенное условие тоже ложно
-    let effective = ctx.should_output() && condition;
+    let parent_ok = ctx.should_output();
+    let effective = parent_ok && condition;
 
     ctx.condition_stack.push(effective);
+    // При ложном родителе ни одна ветка не должна выполниться
+    ctx.branch_taken.push(effective || !parent_ok);
     ctx.condition_depth += 1;
 }
 
-/// Обрабатывает !else
-pub fn handle_else(ctx: &mut PreprocessContext) -> Result<()> {
+/// Нужно ли вычислять условие очередного `!elseif`
+///
+/// Условие вычисляется только если родитель активен и ни одна
+/// предыдущая ветка не была выбрана.
+pub fn elseif_pending(ctx: &PreprocessContext) -> bool {
+    !ctx.condition_stack.is_empty() && !ctx.branch_taken.last().copied().unwrap_or(true)
+}
+
+/// Обрабатывает !elseif (условие уже вычислено, если ветка ещё не выбрана)
+pub fn handle_elseif(condition: bool, ctx: &mut PreprocessContext) -> Result<()> {
     if ctx.condition_stack.is_empty() {
         return Err(PreprocessError::UnbalancedCondition);
     }
 
-    // Проверяем родительское условие до мутации
-    let len = ctx.condition_stack.len();
-    let parent_ok = len <= 1 || ctx.condition_stack[..len - 1].iter().all(|&b| b);
-
-    // Инвертируем текущее условие если родитель true
-    if parent_ok {
-        if let Some(last) = ctx.condition_stack.last_mut() {
-            *last = !*last;
-        }
+    let active = elseif_pending(ctx) && condition;
+    if let Some(last) = ctx.condition_stack.last_mut() {
+        *last = active;
+    }
+    if let Some(taken) = ctx.branch_taken.last_mut() {
+        *taken |= active;
     }
 
     Ok(())
 }
 
+/// Обрабатывает !else
+pub fn handle_else(ctx: &mut PreprocessContext) -> Result<()> {
+    if ctx.condition_stack.is_empty() {
+        return Err(PreprocessError::UnbalancedCondition);
+    }
+
+    // Ветка !else активна, только если ни одна предыдущая не выбрана
+    handle_elseif(true, ctx)
+}
+
 /// Обрабатывает !endif
 pub fn handle_endif(ctx: &mut PreprocessContext) -> Result<()> {
     if ctx.condition_stack.is_empty() {
@@ -67,6 +89,7 @@ pub fn handle_endif(ctx: &mut PreprocessContext) -> Result<()> {
     }
 
     ctx.condition_stack.pop();
+    ctx.branch_taken.pop();
     ctx.condition_depth = ctx.condition_depth.saturating_sub(1);
 
     Ok(())
@@ -118,4 +141,41 @@ mod tests {
 
         handle_endif(&mut ctx).unwrap();
     }
+
+    #[test]
+    fn test_elseif_chain() {
+        let mut ctx = PreprocessContext::new();
+
+        push_condition(false, &mut ctx);
+        assert!(elseif_pending(&ctx));
+
+        handle_elseif(true, &mut ctx).unwrap();
+        assert!(ctx.should_output());
+        assert!(!elseif_pending(&ctx));
+
+        // Следующие ветки не выполняются, даже если условие истинно
+        handle_elseif(true, &mut ctx).unwrap();
+        assert!(!ctx.should_output());
+        handle_else(&mut ctx).unwrap();
+        assert!(!ctx.should_output());
+
+        handle_endif(&mut ctx).unwrap();
+        assert!(ctx.should_output());
+    }
+
+    #[test]
+    fn test_nested_inside_false_branch() {
+        let mut ctx = PreprocessContext::new();
+
+        push_condition(false, &mut ctx);
+        push_condition(false, &mut ctx);
+        assert!(!elseif_pending(&ctx));
+
+        handle_else(&mut ctx).unwrap();
+        assert!(!ctx.should_output());
+
+        handle_endif(&mut ctx).unwrap();
+        handle_endif(&mut ctx).unwrap();
+        assert!(ctx.should_output());
+    }
 }
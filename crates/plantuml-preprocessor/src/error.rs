@@ -21,8 +21,8 @@ pub enum PreprocessError {
     #[error("синтаксическая ошибка в директиве: {0}")]
     SyntaxError(String),
 
-    /// Несбалансированные !ifdef/!endif
-    #[error("несбалансированные !ifdef/!endif")]
+    /// Несбалансированные !if/!endif
+    #[error("несбалансированные !if/!endif")]
     UnbalancedCondition,
 
     /// Неизвестная переменная
@@ -36,4 +36,33 @@ pub enum PreprocessError {
     /// Ошибка вычисления выражения
     #[error("ошибка вычисления выражения: {0}")]
     ExpressionError(String),
+
+    /// Не выполнено условие !assert
+    #[error("!assert не выполнено: {0}")]
+    AssertionFailed(String),
+
+    /// Ошибка в строке исходника или включённого файла
+    #[error("строка {line}: {error}")]
+    At {
+        /// Включённый файл (`None` — сам исходник)
+        file: Option<String>,
+        /// Номер строки в файле (с 1)
+        line: usize,
+        /// Исходная ошибка
+        error: Box<PreprocessError>,
+    },
+}
+
+impl PreprocessError {
+    /// Привязывает ошибку к строке файла; уже привязанная ошибка не меняется
+    pub fn at(self, file: Option<&str>, line: usize) -> Self {
+        match self {
+            located @ PreprocessError::At { .. } => located,
+            error => PreprocessError::At {
+                file: file.map(str::to_string),
+                line,
+                error: Box::new(error),
+            },
+        }
+    }
 }
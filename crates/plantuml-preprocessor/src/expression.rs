@@ -0,0 +1,829 @@
+//! Вычисление выражений препроцессора
+//!
+//! Используется в `!if`/`!elseif`, `!while`, `!foreach`, `!return`,
+//! `!assert` и присваиваниях `!$var = ...`.
+//!
+//! Поддерживаются:
+//! - Значения: строки (`"..."`, `'...'`), целые числа, `true`/`false`, JSON (`{...}`, `[...]`)
+//! - Операторы: `||`, `&&`, `==`, `!=`, `<`, `<=`, `>`, `>=`, `+`, `-`, `*`, `/`, `%`, `!`
+//! - Доступ к JSON: `$data.key`, `$list[0]`, `$data["key"]`
+//! - Вызовы: пользовательские `$func(...)` и builtin `%strlen(...)`, `%size(...)` и т.д.
+
+use std::fmt;
+
+use crate::{PreprocessError, Result};
+
+/// Значение выражения препроцессора
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// Строка
+    Str(String),
+    /// Целое число
+    Int(i64),
+    /// Логическое значение
+    Bool(bool),
+    /// JSON объект, массив, null или дробное число
+    Json(serde_json::Value),
+}
+
+impl Value {
+    /// Восстанавливает значение из текстового представления переменной
+    ///
+    /// Переменные хранятся как текст, поэтому тип определяется по содержимому:
+    /// целое число, `true`/`false`, JSON объект/массив или строка.
+    pub fn from_text(text: &str) -> Self {
+        if let Ok(n) = text.parse::<i64>() {
+            return Value::Int(n);
+        }
+        match text {
+            "true" => return Value::Bool(true),
+            "false" => return Value::Bool(false),
+            _ => {}
+        }
+        if text.starts_with('{') || text.starts_with('[') {
+            if let Ok(json) = serde_json::from_str(text) {
+                return Value::Json(json);
+            }
+        }
+        Value::Str(text.to_string())
+    }
+
+    /// Преобразует JSON значение, разворачивая скаляры
+    pub fn from_json(json: serde_json::Value) -> Self {
+        match json {
+            serde_json::Value::String(s) => Value::Str(s),
+            serde_json::Value::Bool(b) => Value::Bool(b),
+            serde_json::Value::Number(n) if n.is_i64() => Value::Int(n.as_i64().unwrap_or(0)),
+            other => Value::Json(other),
+        }
+    }
+
+    /// Преобразует значение в JSON
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Value::Str(s) => serde_json::Value::String(s.clone()),
+            Value::Int(n) => serde_json::Value::from(*n),
+            Value::Bool(b) => serde_json::Value::Bool(*b),
+            Value::Json(json) => json.clone(),
+        }
+    }
+
+    /// Истинность значения в условиях
+    ///
+    /// Ложны: `false`, `0`, пустая строка, `"false"`, `"0"`, JSON `null`,
+    /// пустые массив и объект.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Str(s) => !(s.is_empty() || s == "false" || s == "0"),
+            Value::Int(n) => *n != 0,
+            Value::Bool(b) => *b,
+            Value::Json(serde_json::Value::Null) => false,
+            Value::Json(serde_json::Value::Array(items)) => !items.is_empty(),
+            Value::Json(serde_json::Value::Object(map)) => !map.is_empty(),
+            Value::Json(_) => true,
+        }
+    }
+
+    /// Целочисленное значение (если возможно)
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(n) => Some(*n),
+            Value::Bool(b) => Some(*b as i64),
+            Value::Str(s) => s.trim().parse().ok(),
+            Value::Json(json) => json.as_i64(),
+        }
+    }
+
+    /// Название типа для сообщений об ошибках
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::Str(_) => "строка",
+            Value::Int(_) => "число",
+            Value::Bool(_) => "логическое значение",
+            Value::Json(_) => "JSON",
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Str(s) => write!(f, "{}", s),
+            Value::Int(n) => write!(f, "{}", n),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Json(json) => write!(f, "{}", json),
+        }
+    }
+}
+
+/// Окружение, в котором вычисляется выражение
+pub(crate) trait Scope {
+    /// Значение переменной (`$name` или имя из `!define`)
+    fn variable(&self, name: &str) -> Option<Value>;
+
+    /// Вызов пользовательской функции `$name(...)`
+    fn call(&mut self, name: &str, args: Vec<Value>) -> Result<Value>;
+}
+
+/// Разбирает и вычисляет выражение
+pub(crate) fn evaluate(source: &str, scope: &mut dyn Scope) -> Result<Value> {
+    let expr = parse(source)?;
+    eval(&expr, scope)
+}
+
+/// Делит строку по разделителю вне кавычек и скобок
+pub(crate) fn split_top_level(source: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    let mut start = 0;
+
+    for (i, c) in source.char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' => quote = Some(c),
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            c if c == separator && depth == 0 => {
+                parts.push(&source[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&source[start..]);
+    parts
+}
+
+/// Узел разобранного выражения
+#[derive(Debug, Clone)]
+enum Expr {
+    Literal(Value),
+    Variable(String),
+    Call(String, Vec<Expr>),
+    Builtin(String, Vec<Expr>),
+    Not(Box<Expr>),
+    Negate(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Member(Box<Expr>, String),
+    Index(Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+fn error(message: impl Into<String>) -> PreprocessError {
+    PreprocessError::ExpressionError(message.into())
+}
+
+/// Разбирает выражение целиком
+fn parse(source: &str) -> Result<Expr> {
+    let mut parser = Parser {
+        chars: source.chars().collect(),
+        pos: 0,
+    };
+    let expr = parser.parse_or()?;
+    parser.skip_ws();
+    if parser.pos < parser.chars.len() {
+        let rest: String = parser.chars[parser.pos..].iter().collect();
+        return Err(error(format!("неожиданный текст «{}» в «{}»", rest, source)));
+    }
+    Ok(expr)
+}
+
+/// Рекурсивный нисходящий парсер выражений
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    /// Пропускает пробелы и проверяет наличие оператора
+    fn eat(&mut self, op: &str) -> bool {
+        self.skip_ws();
+        let matches = op
+            .chars()
+            .enumerate()
+            .all(|(i, c)| self.chars.get(self.pos + i) == Some(&c));
+        if matches {
+            self.pos += op.chars().count();
+        }
+        matches
+    }
+
+    fn expect(&mut self, op: &str) -> Result<()> {
+        if self.eat(op) {
+            Ok(())
+        } else {
+            Err(error(format!("ожидалось «{}»", op)))
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut left = self.parse_and()?;
+        while self.eat("||") {
+            let right = self.parse_and()?;
+            left = Expr::Binary(BinaryOp::Or, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut left = self.parse_equality()?;
+        while self.eat("&&") {
+            let right = self.parse_equality()?;
+            left = Expr::Binary(BinaryOp::And, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_equality(&mut self) -> Result<Expr> {
+        let mut left = self.parse_comparison()?;
+        loop {
+            let op = if self.eat("==") {
+                BinaryOp::Eq
+            } else if self.eat("!=") {
+                BinaryOp::Ne
+            } else {
+                return Ok(left);
+            };
+            let right = self.parse_comparison()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr> {
+        let mut left = self.parse_additive()?;
+        loop {
+            let op = if self.eat("<=") {
+                BinaryOp::Le
+            } else if self.eat(">=") {
+                BinaryOp::Ge
+            } else if self.eat("<") {
+                BinaryOp::Lt
+            } else if self.eat(">") {
+                BinaryOp::Gt
+            } else {
+                return Ok(left);
+            };
+            let right = self.parse_additive()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn parse_additive(&mut self) -> Result<Expr> {
+        let mut left = self.parse_multiplicative()?;
+        loop {
+            let op = if self.eat("+") {
+                BinaryOp::Add
+            } else if self.eat("-") {
+                BinaryOp::Sub
+            } else {
+                return Ok(left);
+            };
+            let right = self.parse_multiplicative()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Expr> {
+        let mut left = self.parse_unary()?;
+        loop {
+            let op = if self.eat("*") {
+                BinaryOp::Mul
+            } else if self.eat("/") {
+                BinaryOp::Div
+            } else if self.eat("%") {
+                BinaryOp::Rem
+            } else {
+                return Ok(left);
+            };
+            let right = self.parse_unary()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        self.skip_ws();
+        if self.peek() == Some('!') && self.chars.get(self.pos + 1) != Some(&'=') {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+        if self.eat("-") {
+            return Ok(Expr::Negate(Box::new(self.parse_unary()?)));
+        }
+        self.parse_postfix()
+    }
+
+    fn parse_postfix(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_primary()?;
+        loop {
+            if self.peek() == Some('.') {
+                self.pos += 1;
+                let key = self.identifier();
+                if key.is_empty() {
+                    return Err(error("ожидалось имя поля после «.»"));
+                }
+                expr = Expr::Member(Box::new(expr), key);
+            } else if self.peek() == Some('[') {
+                self.pos += 1;
+                let index = self.parse_or()?;
+                self.expect("]")?;
+                expr = Expr::Index(Box::new(expr), Box::new(index));
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr> {
+        self.skip_ws();
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let expr = self.parse_or()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Some(q @ ('"' | '\'')) => self.string(q).map(|s| Expr::Literal(Value::Str(s))),
+            Some('{') | Some('[') => self.json(),
+            Some(c) if c.is_ascii_digit() => {
+                let digits = self.take_while(|c| c.is_ascii_digit());
+                digits
+                    .parse()
+                    .map(|n| Expr::Literal(Value::Int(n)))
+                    .map_err(|_| error(format!("слишком большое число: {}", digits)))
+            }
+            Some('$') => {
+                self.pos += 1;
+                let name = format!("${}", self.identifier());
+                if name.len() == 1 {
+                    return Err(error("ожидалось имя переменной после «$»"));
+                }
+                if self.peek() == Some('(') {
+                    Ok(Expr::Call(name, self.arguments()?))
+                } else {
+                    Ok(Expr::Variable(name))
+                }
+            }
+            Some('%') => {
+                self.pos += 1;
+                let name = self.identifier();
+                if name.is_empty() || self.peek() != Some('(') {
+                    return Err(error("ожидался вызов builtin функции после «%»"));
+                }
+                Ok(Expr::Builtin(name, self.arguments()?))
+            }
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                let name = self.identifier();
+                Ok(match name.as_str() {
+                    "true" => Expr::Literal(Value::Bool(true)),
+                    "false" => Expr::Literal(Value::Bool(false)),
+                    _ => Expr::Variable(name),
+                })
+            }
+            Some(c) => Err(error(format!("неожиданный символ «{}»", c))),
+            None => Err(error("неожиданный конец выражения")),
+        }
+    }
+
+    fn take_while(&mut self, pred: impl Fn(char) -> bool) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(&pred) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    fn identifier(&mut self) -> String {
+        self.take_while(|c| c.is_alphanumeric() || c == '_')
+    }
+
+    /// Строковый литерал с экранированием `\"`, `\\`, `\t`; `\n` сохраняется для рендерера
+    fn string(&mut self, quote: char) -> Result<String> {
+        self.pos += 1;
+        let mut value = String::new();
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                '\\' => {
+                    let escaped = self.peek().ok_or_else(|| error("незакрытая строка"))?;
+                    self.pos += 1;
+                    match escaped {
+                        // `\n` оставляем как есть: перенос строки делает рендерер метки
+                        'n' => value.push_str("\\n"),
+                        't' => value.push('\t'),
+                        other => value.push(other),
+                    }
+                }
+                c if c == quote => return Ok(value),
+                c => value.push(c),
+            }
+        }
+        Err(error("незакрытая строка"))
+    }
+
+    /// JSON литерал: берём сбалансированный фрагмент и разбираем serde_json
+    fn json(&mut self) -> Result<Expr> {
+        let start = self.pos;
+        let mut depth = 0;
+        let mut in_string = false;
+        let mut escaped = false;
+
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            if in_string {
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    '"' => in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match c {
+                '"' => in_string = true,
+                '{' | '[' => depth += 1,
+                '}' | ']' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let text: String = self.chars[start..self.pos].iter().collect();
+        serde_json::from_str(&text)
+            .map(|json| Expr::Literal(Value::Json(json)))
+            .map_err(|e| error(format!("неверный JSON «{}»: {}", text, e)))
+    }
+
+    /// Список аргументов вызова `(a, b, ...)`
+    fn arguments(&mut self) -> Result<Vec<Expr>> {
+        self.expect("(")?;
+        let mut args = Vec::new();
+        if self.eat(")") {
+            return Ok(args);
+        }
+        loop {
+            args.push(self.parse_or()?);
+            if self.eat(")") {
+                return Ok(args);
+            }
+            self.expect(",")?;
+        }
+    }
+}
+
+/// Вычисляет разобранное выражение
+fn eval(expr: &Expr, scope: &mut dyn Scope) -> Result<Value> {
+    match expr {
+        Expr::Literal(value) => Ok(value.clone()),
+        Expr::Variable(name) => scope
+            .variable(name)
+            .ok_or_else(|| PreprocessError::UnknownVariable(name.clone())),
+        Expr::Call(name, args) => {
+            let args = args
+                .iter()
+                .map(|arg| eval(arg, scope))
+                .collect::<Result<Vec<_>>>()?;
+            scope.call(name, args)
+        }
+        Expr::Builtin(name, args) => {
+            let args = args
+                .iter()
+                .map(|arg| eval(arg, scope))
+                .collect::<Result<Vec<_>>>()?;
+            call_builtin(name, &args, scope)
+        }
+        Expr::Not(inner) => Ok(Value::Bool(!eval(inner, scope)?.is_truthy())),
+        Expr::Negate(inner) => {
+            let value = eval(inner, scope)?;
+            value
+                .as_int()
+                .map(|n| Value::Int(-n))
+                .ok_or_else(|| error(format!("унарный минус неприменим: {}", value.type_name())))
+        }
+        Expr::Binary(BinaryOp::Or, left, right) => {
+            let truthy = eval(left, scope)?.is_truthy() || eval(right, scope)?.is_truthy();
+            Ok(Value::Bool(truthy))
+        }
+        Expr::Binary(BinaryOp::And, left, right) => {
+            let truthy = eval(left, scope)?.is_truthy() && eval(right, scope)?.is_truthy();
+            Ok(Value::Bool(truthy))
+        }
+        Expr::Binary(op, left, right) => {
+            let left = eval(left, scope)?;
+            let right = eval(right, scope)?;
+            binary(*op, &left, &right)
+        }
+        Expr::Member(target, key) => {
+            let target = eval(target, scope)?;
+            member(&target, key)
+        }
+        Expr::Index(target, index) => {
+            let target = eval(target, scope)?;
+            let index = eval(index, scope)?;
+            match (&target, &index) {
+                (Value::Json(serde_json::Value::Array(items)), _) => {
+                    let i = index
+                        .as_int()
+                        .ok_or_else(|| error("индекс массива должен быть числом"))?;
+                    usize::try_from(i)
+                        .ok()
+                        .and_then(|i| items.get(i))
+                        .cloned()
+                        .map(Value::from_json)
+                        .ok_or_else(|| error(format!("индекс {} вне массива", i)))
+                }
+                _ => member(&target, &index.to_string()),
+            }
+        }
+    }
+}
+
+/// Доступ к полю JSON объекта
+fn member(target: &Value, key: &str) -> Result<Value> {
+    match target {
+        Value::Json(serde_json::Value::Object(map)) => map
+            .get(key)
+            .cloned()
+            .map(Value::from_json)
+            .ok_or_else(|| error(format!("нет поля «{}»", key))),
+        other => Err(error(format!(
+            "доступ к полю «{}» неприменим: {}",
+            key,
+            other.type_name()
+        ))),
+    }
+}
+
+/// Бинарные операторы (кроме логических)
+fn binary(op: BinaryOp, left: &Value, right: &Value) -> Result<Value> {
+    let ints = match (left, right) {
+        (Value::Str(_), _) | (_, Value::Str(_)) => None,
+        _ => left.as_int().zip(right.as_int()),
+    };
+
+    let result = match op {
+        BinaryOp::Eq => Value::Bool(values_equal(left, right)),
+        BinaryOp::Ne => Value::Bool(!values_equal(left, right)),
+        BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
+            let ordering = match ints {
+                Some((a, b)) => a.cmp(&b),
+                None => left.to_string().cmp(&right.to_string()),
+            };
+            Value::Bool(match op {
+                BinaryOp::Lt => ordering.is_lt(),
+                BinaryOp::Le => ordering.is_le(),
+                BinaryOp::Gt => ordering.is_gt(),
+                _ => ordering.is_ge(),
+            })
+        }
+        // Сложение чисел или конкатенация строк
+        BinaryOp::Add => match ints {
+            Some((a, b)) => Value::Int(a.wrapping_add(b)),
+            None => Value::Str(format!("{}{}", left, right)),
+        },
+        BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => {
+            let (a, b) = left.as_int().zip(right.as_int()).ok_or_else(|| {
+                error(format!(
+                    "арифметика неприменима: {} и {}",
+                    left.type_name(),
+                    right.type_name()
+                ))
+            })?;
+            Value::Int(match op {
+                BinaryOp::Sub => a.wrapping_sub(b),
+                BinaryOp::Mul => a.wrapping_mul(b),
+                _ if b == 0 => return Err(error("деление на ноль")),
+                BinaryOp::Div => a / b,
+                _ => a % b,
+            })
+        }
+        BinaryOp::Or | BinaryOp::And => unreachable!("логические операторы вычисляются лениво"),
+    };
+
+    Ok(result)
+}
+
+/// Сравнение на равенство: числа — численно, JSON — структурно, иначе как строки
+fn values_equal(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Json(a), Value::Json(b)) => a == b,
+        (Value::Int(_) | Value::Bool(_), Value::Int(_) | Value::Bool(_)) => {
+            left.as_int() == right.as_int()
+        }
+        _ => left.to_string() == right.to_string(),
+    }
+}
+
+/// Builtin функции, доступные в выражениях
+fn call_builtin(name: &str, args: &[Value], scope: &mut dyn Scope) -> Result<Value> {
+    let arg = |i: usize| args.get(i).cloned().unwrap_or(Value::Str(String::new()));
+    let text = |i: usize| arg(i).to_string();
+
+    let value = match name {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        "not" => Value::Bool(!arg(0).is_truthy()),
+        "boolval" => Value::Bool(arg(0).is_truthy()),
+        "strlen" => Value::Int(text(0).chars().count() as i64),
+        "upper" => Value::Str(text(0).to_uppercase()),
+        "lower" => Value::Str(text(0).to_lowercase()),
+        "string" => Value::Str(text(0)),
+        "intval" => Value::Int(arg(0).as_int().unwrap_or(0)),
+        "newline" => Value::Str("\n".to_string()),
+        "substr" => {
+            let start = arg(1).as_int().unwrap_or(0).max(0) as usize;
+            let source = text(0);
+            let chars = source.chars().skip(start);
+            Value::Str(match args.get(2).and_then(Value::as_int) {
+                Some(len) => chars.take(len.max(0) as usize).collect(),
+                None => chars.collect(),
+            })
+        }
+        "strpos" => {
+            let haystack = text(0);
+            Value::Int(
+                haystack
+                    .find(&text(1))
+                    .map(|byte| haystack[..byte].chars().count() as i64)
+                    .unwrap_or(-1),
+            )
+        }
+        "size" => Value::Int(match arg(0) {
+            Value::Json(serde_json::Value::Array(items)) => items.len() as i64,
+            Value::Json(serde_json::Value::Object(map)) => map.len() as i64,
+            other => other.to_string().chars().count() as i64,
+        }),
+        "variable_exists" => Value::Bool(scope.variable(&text(0)).is_some()),
+        "get_variable_value" => scope.variable(&text(0)).unwrap_or(Value::Str(String::new())),
+        "json_key_exists" => Value::Bool(match arg(0) {
+            Value::Json(serde_json::Value::Object(map)) => map.contains_key(&text(1)),
+            _ => false,
+        }),
+        "str2json" => serde_json::from_str(&text(0))
+            .map(Value::Json)
+            .map_err(|e| error(format!("неверный JSON: {}", e)))?,
+        _ => return Err(error(format!("неизвестная функция %{}", name))),
+    };
+
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indexmap::IndexMap;
+
+    /// Простое окружение для тестов: переменные и функция `$double`
+    struct TestScope(IndexMap<String, String>);
+
+    impl Scope for TestScope {
+        fn variable(&self, name: &str) -> Option<Value> {
+            self.0.get(name).map(|v| Value::from_text(v))
+        }
+
+        fn call(&mut self, name: &str, args: Vec<Value>) -> Result<Value> {
+            match name {
+                "$double" => Ok(Value::Int(args[0].as_int().unwrap_or(0) * 2)),
+                _ => Err(error(format!("неизвестная функция {}", name))),
+            }
+        }
+    }
+
+    fn eval_with(source: &str, vars: &[(&str, &str)]) -> Result<Value> {
+        let mut scope = TestScope(
+            vars.iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        );
+        evaluate(source, &mut scope)
+    }
+
+    fn eval_str(source: &str) -> Value {
+        eval_with(source, &[]).unwrap()
+    }
+
+    #[test]
+    fn test_literals_and_arithmetic() {
+        assert_eq!(eval_str("1 + 2 * 3"), Value::Int(7));
+        assert_eq!(eval_str("(1 + 2) * 3"), Value::Int(9));
+        assert_eq!(eval_str("7 % 4 - -1"), Value::Int(4));
+        assert_eq!(eval_str("\"a\" + 'b' + 1"), Value::Str("ab1".to_string()));
+    }
+
+    #[test]
+    fn test_comparison_and_logic() {
+        assert_eq!(eval_str("2 < 10"), Value::Bool(true));
+        assert_eq!(eval_str("\"2\" < \"10\""), Value::Bool(false));
+        assert_eq!(eval_str("1 == 1 && !(2 != 2)"), Value::Bool(true));
+        assert_eq!(eval_str("false || \"\""), Value::Bool(false));
+    }
+
+    #[test]
+    fn test_variables() {
+        let vars = [("$env", "prod"), ("$count", "3")];
+        assert_eq!(
+            eval_with("$env == \"prod\"", &vars).unwrap(),
+            Value::Bool(true)
+        );
+        assert_eq!(eval_with("$count + 1", &vars).unwrap(), Value::Int(4));
+        assert!(matches!(
+            eval_with("$missing", &vars),
+            Err(PreprocessError::UnknownVariable(_))
+        ));
+    }
+
+    #[test]
+    fn test_json_access() {
+        let vars = [("$cfg", r#"{"name": "api", "ports": [80, 443]}"#)];
+        assert_eq!(
+            eval_with("$cfg.name", &vars).unwrap(),
+            Value::Str("api".to_string())
+        );
+        assert_eq!(eval_with("$cfg.ports[1]", &vars).unwrap(), Value::Int(443));
+        assert_eq!(eval_with("%size($cfg.ports)", &vars).unwrap(), Value::Int(2));
+        assert_eq!(
+            eval_str(r#"{"a": [1, {"b": true}]}.a[1].b"#),
+            Value::Bool(true)
+        );
+    }
+
+    #[test]
+    fn test_calls() {
+        assert_eq!(eval_str("$double(21)"), Value::Int(42));
+        assert_eq!(eval_str("%strlen(\"привет\")"), Value::Int(6));
+        assert_eq!(eval_str("%upper(\"a\") + %substr(\"hello\", 1, 3)"), Value::Str("Aell".to_string()));
+        assert!(eval_with("%nope()", &[]).is_err());
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        assert_eq!(eval_str(r#""a\"b\\c""#), Value::Str("a\"b\\c".to_string()));
+        assert_eq!(eval_str(r#""x\ny""#), Value::Str("x\\ny".to_string()));
+        assert_eq!(eval_str(r#""x\ty""#), Value::Str("x\ty".to_string()));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(eval_with("1 +", &[]).is_err());
+        assert!(eval_with("\"open", &[]).is_err());
+        assert!(eval_with("1 / 0", &[]).is_err());
+        assert!(eval_with("Hello_$name", &[]).is_err());
+    }
+
+    #[test]
+    fn test_split_top_level() {
+        assert_eq!(
+            split_top_level(r#"$a, "b, c", $f(1, 2)"#, ','),
+            vec!["$a", r#" "b, c""#, " $f(1, 2)"]
+        );
+        assert_eq!(split_top_level("$x > 1 : \"сообщение\"", ':').len(), 2);
+    }
+
+    #[test]
+    fn test_value_from_text() {
+        assert_eq!(Value::from_text("42"), Value::Int(42));
+        assert_eq!(Value::from_text("true"), Value::Bool(true));
+        assert!(matches!(Value::from_text("[1, 2]"), Value::Json(_)));
+        assert_eq!(Value::from_text("[Alice]"), Value::Str("[Alice]".to_string()));
+    }
+}
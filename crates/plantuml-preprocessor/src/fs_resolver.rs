@@ -4,7 +4,7 @@
 //! - Абсолютных путей
 //! - Относительных путей (относительно базовой директории)
 //! - Путей поиска (search paths)
-//! - Стандартной библиотеки PlantUML (`<stdlib/...>`)
+//! - Путей `<...>` вне встроенной стандартной библиотеки
 
 use std::collections::HashSet;
 use std::fs;
@@ -102,7 +102,7 @@ impl FsFileResolver {
     /// 1. Абсолютный путь (если путь абсолютный)
     /// 2. Относительно base_dir
     /// 3. В каждом из search_paths
-    /// 4. В стандартной библиотеке (для путей вида `<...>`)
+    /// 4. Для путей вида `<...>` — только в search_paths
     fn resolve_path(&self, path: &str) -> Option<PathBuf> {
         let path_str = path.trim();
 
@@ -137,10 +137,11 @@ impl FsFileResolver {
         None
     }
 
-    /// Разрешает путь к стандартной библиотеке
+    /// Разрешает путь `<...>` в search_paths
+    ///
+    /// Встроенную библиотеку проверяет `StdlibFileResolver`, сюда попадают
+    /// только файлы, которых в ней нет.
     fn resolve_stdlib_path(&self, stdlib_path: &str) -> Option<PathBuf> {
-        // TODO: Интеграция с plantuml-stdlib
-        // Пока ищем в search_paths
         for search_path in &self.search_paths {
             let candidate = search_path.join(stdlib_path);
             if candidate.exists() {
@@ -181,24 +181,40 @@ pub fn parse_callable_call(call: &str) -> Option<(String, Vec<String>)> {
 }
 
 /// Ищет вызовы функций в строке и возвращает позиции
-pub fn find_function_calls(line: &str) -> Vec<(usize, usize, String, Vec<String>)> {
+///
+/// Вызовы вида `$name(...)` находятся всегда. Имена без `$` (`Person(...)`,
+/// как в C4-PlantUML) — только если `is_callable` подтверждает, что такая
+/// функция или процедура определена.
+pub fn find_function_calls(
+    line: &str,
+    is_callable: impl Fn(&str) -> bool,
+) -> Vec<(usize, usize, String, Vec<String>)> {
     let mut calls = Vec::new();
     let chars: Vec<char> = line.chars().collect();
     let mut i = 0;
 
     while i < chars.len() {
-        if chars[i] == '$' {
+        let bare = is_identifier_start(chars[i])
+            && (i == 0 || !(is_identifier_char(chars[i - 1]) || matches!(chars[i - 1], '$' | '%')));
+        if chars[i] == '$' || bare {
             // Потенциальный вызов функции
             let start = i;
-            i += 1;
+            let mut name = String::new();
+            if chars[i] == '$' {
+                name.push('$');
+                i += 1;
+            }
 
             // Читаем имя
-            let mut name = String::from("$");
-            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+            while i < chars.len() && is_identifier_char(chars[i]) {
                 name.push(chars[i]);
                 i += 1;
             }
 
+            if bare && !is_callable(&name) {
+                continue;
+            }
+
             // Проверяем наличие (
             if i < chars.len() && chars[i] == '(' {
                 let mut depth = 1;
@@ -215,7 +231,11 @@ pub fn find_function_calls(line: &str) -> Vec<(usize, usize, String, Vec<String>
                 }
 
                 if depth == 0 {
-                    let call_str: String = chars[start..i].iter().collect();
+                    let mut call_str: String = chars[start..i].iter().collect();
+                    if bare {
+                        // parse_callable_call ожидает имя с `$`
+                        call_str.insert(0, '$');
+                    }
                     if let Some((_, args)) = parse_callable_call(&call_str) {
                         calls.push((start, i, name, args));
                     }
@@ -229,6 +249,16 @@ pub fn find_function_calls(line: &str) -> Vec<(usize, usize, String, Vec<String>
     calls
 }
 
+/// Символ может начинать имя функции без `$`
+fn is_identifier_start(ch: char) -> bool {
+    ch.is_alphabetic() || ch == '_'
+}
+
+/// Символ может входить в имя функции
+fn is_identifier_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -278,12 +308,24 @@ mod tests {
 
     #[test]
     fn test_find_function_calls() {
-        let calls = find_function_calls("result = $add(1, 2) + $mul(3, 4)");
+        let calls = find_function_calls("result = $add(1, 2) + $mul(3, 4)", |_| false);
         assert_eq!(calls.len(), 2);
         assert_eq!(calls[0].2, "$add");
         assert_eq!(calls[1].2, "$mul");
     }
 
+    #[test]
+    fn test_find_bare_function_calls() {
+        let line = "Person(user, \"Customer\") --> print(x) $f(1)";
+        let calls = find_function_calls(line, |name| name == "Person");
+        let names: Vec<&str> = calls.iter().map(|c| c.2.as_str()).collect();
+        assert_eq!(names, vec!["Person", "$f"]);
+        assert_eq!(calls[0].3, vec!["user", "Customer"]);
+
+        // Часть другого слова или builtin — не вызов
+        assert!(find_function_calls("MyPerson(x) %Person(y)", |name| name == "Person").is_empty());
+    }
+
     #[test]
     fn test_local_variable_in_function() {
         let mut func = UserCallable::function("$double", vec!["$x".to_string()]);
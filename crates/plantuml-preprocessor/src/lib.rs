@@ -1,27 +1,35 @@
 //! # plantuml-preprocessor
 //!
 //! Препроцессор PlantUML для обработки директив:
-//! - `!include` / `!include_once`
+//! - `!include` / `!include_once` (включая стандартную библиотеку `<...>`)
 //! - `!define` / `!undef`
-//! - `!ifdef` / `!ifndef` / `!else` / `!endif`
-//! - `!$variable = value`
-//! - `!function` / `!procedure`
+//! - `!ifdef` / `!ifndef` / `!if` / `!elseif` / `!else` / `!endif`
+//! - `!while` / `!endwhile`, `!foreach` / `!endfor`
+//! - `!$variable = expression` (строки, числа, логические значения, JSON)
+//! - `!function` / `!procedure` / `!return`
+//! - `!assert` / `!log`
 //! - `!theme`
 //! - `%date()`, `%version()` и другие builtin функции
 
 mod builtins;
 mod directives;
 mod error;
+mod expression;
 mod fs_resolver;
 mod functions;
+mod loops;
+mod stdlib_resolver;
 mod variables;
 
 pub use error::PreprocessError;
+pub use expression::Value;
 pub use fs_resolver::FsFileResolver;
 pub use functions::{CallableKind, UserCallable};
+pub use stdlib_resolver::StdlibFileResolver;
 pub use plantuml_themes::{SkinParams, Theme};
 
 use indexmap::IndexMap;
+use std::collections::HashSet;
 
 /// Обрабатывает PlantUML исходный код (без поддержки !include)
 ///
@@ -66,6 +74,39 @@ impl FileResolver for NoopFileResolver {
     }
 }
 
+/// Происхождение строки вывода препроцессора
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LineOrigin {
+    /// Включённый файл (`None` — сам исходник)
+    pub file: Option<String>,
+    /// Номер строки в этом файле (с 1)
+    pub line: usize,
+    /// Номер строки исходника (с 1): для включённых файлов — строка `!include`
+    pub source_line: usize,
+}
+
+impl LineOrigin {
+    /// Строка `line` файла `file`
+    pub fn new(file: Option<&str>, line: usize) -> Self {
+        Self {
+            file: file.map(str::to_string),
+            line,
+            source_line: line,
+        }
+    }
+}
+
+/// Откуда строки, которые обрабатывает `process_lines`
+#[derive(Debug, Clone, Copy)]
+enum LineSource<'a> {
+    /// Сам исходник: поглощённые строки заменяются пустыми
+    Root,
+    /// Файл `!include`
+    Include(&'a str),
+    /// Тело цикла или процедуры: строки относятся к строке вызова
+    Body,
+}
+
 /// Состояние определения функции/процедуры
 #[derive(Debug, Clone)]
 enum DefiningCallable {
@@ -96,6 +137,24 @@ pub struct PreprocessContext {
     pub theme: Theme,
     /// SkinParam параметры
     pub skin_params: SkinParams,
+    /// Сообщения `!log`
+    pub log_messages: Vec<String>,
+    /// Выбрана ли уже ветка в каждом открытом условии (для `!elseif`/`!else`)
+    branch_taken: Vec<bool>,
+    /// Собираемое тело цикла `!while`/`!foreach`
+    loop_block: Option<loops::LoopBlock>,
+    /// Значение `!return` текущего вызова
+    return_value: Option<Value>,
+    /// Глубина вложенных вызовов функций/процедур
+    call_depth: usize,
+    /// Элемент открытого блока `skinparam <элемент> {`
+    skinparam_block: Option<String>,
+    /// Прежние значения параметров и `!local` переменных каждого вызова
+    call_frames: Vec<Vec<(String, Option<String>)>>,
+    /// Переменные, объявленные `!global` внутри вызовов
+    globals: HashSet<String>,
+    /// Происхождение строк вывода: элемент `i` — строка вывода `i + 1`
+    pub line_map: Vec<LineOrigin>,
 }
 
 impl Default for PreprocessContext {
@@ -109,6 +168,15 @@ impl Default for PreprocessContext {
             defining: DefiningCallable::None,
             theme: Theme::default(),
             skin_params: SkinParams::new(),
+            log_messages: Vec::new(),
+            branch_taken: Vec::new(),
+            loop_block: None,
+            return_value: None,
+            call_depth: 0,
+            skinparam_block: None,
+            call_frames: Vec::new(),
+            globals: HashSet::new(),
+            line_map: Vec::new(),
         }
     }
 }
@@ -175,6 +243,35 @@ impl PreprocessContext {
     }
 }
 
+/// Максимальная глубина вложенных вызовов функций/процедур
+const MAX_CALL_DEPTH: usize = 100;
+
+/// Окружение вычисления выражений: переменные контекста и вызовы функций
+struct CallScope<'a, R: FileResolver> {
+    preprocessor: &'a Preprocessor<R>,
+    ctx: &'a mut PreprocessContext,
+}
+
+impl<R: FileResolver> expression::Scope for CallScope<'_, R> {
+    fn variable(&self, name: &str) -> Option<Value> {
+        self.ctx.get_variable(name).map(|v| Value::from_text(v))
+    }
+
+    fn call(&mut self, name: &str, args: Vec<Value>) -> Result<Value> {
+        let callable = self
+            .ctx
+            .get_callable(name)
+            .cloned()
+            .ok_or_else(|| PreprocessError::ExpressionError(format!("неизвестная функция {}", name)))?;
+        let (output, return_value) = self.preprocessor.invoke(&callable, args, self.ctx)?;
+
+        Ok(match callable.kind {
+            functions::CallableKind::Function => return_value.unwrap_or(Value::Str(String::new())),
+            functions::CallableKind::Procedure => Value::Str(output),
+        })
+    }
+}
+
 /// Препроцессор PlantUML
 pub struct Preprocessor<R: FileResolver = NoopFileResolver> {
     resolver: R,
@@ -208,21 +305,87 @@ impl<R: FileResolver> Preprocessor<R> {
     }
 
     /// Обрабатывает исходный код с заданным контекстом
+    ///
+    /// Строки, которые препроцессор поглощает (директивы, `skinparam`, ложные
+    /// ветки условий), заменяются пустыми: номер строки вывода совпадает с
+    /// номером строки исходника, пока `!include` и циклы не добавят строк.
+    /// Происхождение каждой строки вывода записывается в `ctx.line_map`.
     pub fn process_with_context(
         &self,
         source: &str,
         ctx: &mut PreprocessContext,
     ) -> Result<String> {
-        let mut output = String::new();
+        self.process_lines(source, ctx, LineSource::Root)
+    }
 
-        for line in source.lines() {
+    /// Обрабатывает строки
+    ///
+    /// Для файлов (исходник и `!include`) по окончании в `ctx.line_map`
+    /// остаётся происхождение строк вывода, а ошибки привязываются к строке
+    /// файла (`PreprocessError::At`); строки тела процедуры или цикла
+    /// относятся к строке вызова.
+    fn process_lines(
+        &self,
+        source: &str,
+        ctx: &mut PreprocessContext,
+        lines: LineSource,
+    ) -> Result<String> {
+        let mut line = 0;
+        self.process_file_lines(source, ctx, lines, &mut line)
+            .map_err(|error| match lines {
+                LineSource::Root => error.at(None, line),
+                LineSource::Include(file) => error.at(Some(file), line),
+                LineSource::Body => error,
+            })
+    }
+
+    /// Обрабатывает строки; `origin_line` — строка, на которой остановилась обработка
+    fn process_file_lines(
+        &self,
+        source: &str,
+        ctx: &mut PreprocessContext,
+        lines: LineSource,
+        origin_line: &mut usize,
+    ) -> Result<String> {
+        let mut output = String::new();
+        let file = match lines {
+            LineSource::Include(file) => Some(file),
+            _ => None,
+        };
+        let mut origins = Vec::new();
+        // Длина вывода, строки до которой уже учтены в `origins`
+        let mut recorded = 0;
+        // Открытые в этом файле `!if`, `!while`/`!foreach`, `!function`/`!procedure`
+        let mut open_blocks: Vec<(usize, &str)> = Vec::new();
+
+        for (index, line) in source.lines().enumerate() {
+            if !matches!(lines, LineSource::Body) {
+                record_origins(&mut origins, &output[recorded..], file, *origin_line, ctx);
+                recorded = output.len();
+            }
+            // Поглощённые предыдущие строки заменяются пустыми
+            if matches!(lines, LineSource::Root) {
+                while origins.len() < index {
+                    output.push('\n');
+                    origins.push(LineOrigin::new(file, index));
+                }
+                recorded = output.len();
+            }
+            // Строка, которой приписывается вывод (для цикла — его начало)
+            *origin_line = index + 1;
             let trimmed = line.trim();
 
+            // После !return оставшиеся строки тела функции не выполняются
+            if ctx.return_value.is_some() {
+                break;
+            }
+
             // Если мы определяем функцию/процедуру, собираем тело
             if ctx.is_defining_callable() {
                 if trimmed == "!endfunction" || trimmed == "!endprocedure" {
                     // Завершаем определение
                     self.finish_callable_definition(ctx)?;
+                    open_blocks.pop();
                 } else {
                     // Добавляем строку в тело
                     self.add_line_to_callable(line, ctx);
@@ -230,12 +393,35 @@ impl<R: FileResolver> Preprocessor<R> {
                 continue;
             }
 
+            // Собираем тело цикла до парного !endwhile/!endfor
+            if let Some(block) = ctx.loop_block.as_mut() {
+                if block.push_line(line) {
+                    if let Some(block) = ctx.loop_block.take() {
+                        if let Some((start, _)) = open_blocks.pop() {
+                            *origin_line = start;
+                        }
+                        output.push_str(&self.run_loop(block, ctx)?);
+                    }
+                }
+                continue;
+            }
+
             // Обработка директив препроцессора
             if trimmed.starts_with('!') {
+                let conditions = ctx.condition_stack.len();
                 let included_content = self.process_directive_with_output(trimmed, ctx)?;
                 if let Some(content) = included_content {
                     output.push_str(&content);
                 }
+                if ctx.condition_stack.len() < conditions {
+                    open_blocks.pop();
+                }
+                if ctx.condition_stack.len() > conditions
+                    || ctx.loop_block.is_some()
+                    || ctx.is_defining_callable()
+                {
+                    open_blocks.push((*origin_line, trimmed));
+                }
                 continue;
             }
 
@@ -244,18 +430,32 @@ impl<R: FileResolver> Preprocessor<R> {
                 continue;
             }
 
-            // Обработка skinparam
-            if trimmed.starts_with("skinparam ") {
-                self.handle_skinparam(trimmed, ctx);
-                // Пропускаем строку (она применена к теме)
+            // Обработка skinparam: строкой и блоком `skinparam class { ... }`
+            let skinparam = match ctx.skinparam_block.clone() {
+                Some(_) if trimmed == "}" => {
+                    ctx.skinparam_block = None;
+                    continue;
+                }
+                Some(element) => Some(self.handle_skinparam_entry(&element, trimmed, ctx)),
+                None if trimmed.starts_with("skinparam ") => Some(self.handle_skinparam(trimmed, ctx)),
+                None => None,
+            };
+            if let Some(layout_line) = skinparam {
+                // Форма линий влияет на layout: строку разбирает парсер
+                if let Some(layout_line) = layout_line {
+                    output.push_str(&layout_line);
+                    output.push('\n');
+                }
+                // Остальные строки применены к теме
                 continue;
             }
 
-            // Подстановка переменных
-            let processed = self.substitute_variables(line, ctx);
+            // Вызовы пользовательских функций — до подстановки переменных,
+            // чтобы аргументы вычислялись как выражения, а не как текст
+            let processed = self.process_function_calls(line, ctx)?;
 
-            // Обработка вызовов пользовательских функций
-            let processed = self.process_function_calls(&processed, ctx);
+            // Подстановка переменных
+            let processed = self.substitute_variables(&processed, ctx);
 
             // Обработка builtin функций
             let processed = builtins::process_builtins(&processed);
@@ -264,6 +464,26 @@ impl<R: FileResolver> Preprocessor<R> {
             output.push('\n');
         }
 
+        if ctx.loop_block.take().is_some() && matches!(lines, LineSource::Body) {
+            return Err(PreprocessError::SyntaxError(
+                "!while/!foreach без !endwhile/!endfor".to_string(),
+            ));
+        }
+
+        if !matches!(lines, LineSource::Body) {
+            // Незакрытый блок в конце файла: ошибка указывает на его начало
+            if let Some((start, opening)) = open_blocks.pop() {
+                let error = PreprocessError::SyntaxError(format!(
+                    "{} без {}",
+                    opening,
+                    closing_directive(opening)
+                ));
+                return Err(error.at(file, start));
+            }
+            record_origins(&mut origins, &output[recorded..], file, *origin_line, ctx);
+            ctx.line_map = origins;
+        }
+
         Ok(output)
     }
 
@@ -290,28 +510,311 @@ impl<R: FileResolver> Preprocessor<R> {
             directives::handle_ifdef(rest.trim(), ctx, true);
         } else if let Some(rest) = directive.strip_prefix("ifndef ") {
             directives::handle_ifdef(rest.trim(), ctx, false);
+        } else if let Some(rest) = directive.strip_prefix("if ") {
+            // Условие внутри ложной ветки не вычисляется
+            let condition = ctx.should_output() && self.evaluate(rest.trim(), ctx)?.is_truthy();
+            directives::push_condition(condition, ctx);
+        } else if let Some(rest) = directive.strip_prefix("elseif ") {
+            let condition =
+                directives::elseif_pending(ctx) && self.evaluate(rest.trim(), ctx)?.is_truthy();
+            directives::handle_elseif(condition, ctx)?;
         } else if directive == "else" {
             directives::handle_else(ctx)?;
         } else if directive == "endif" {
             directives::handle_endif(ctx)?;
+        } else if loops::is_loop_start(line) {
+            ctx.loop_block = Some(loops::LoopBlock::parse(directive, ctx.should_output())?);
+        } else if loops::is_loop_end(line) {
+            return Err(PreprocessError::SyntaxError(format!(
+                "{} без !while/!foreach",
+                line
+            )));
+        } else if directive == "return" || directive.starts_with("return ") {
+            self.handle_return(directive["return".len()..].trim(), ctx)?;
+        } else if let Some(rest) = directive.strip_prefix("assert ") {
+            self.handle_assert(rest.trim(), ctx)?;
+        } else if let Some(rest) = directive.strip_prefix("log ") {
+            if ctx.should_output() {
+                let message = self.substitute_variables(rest.trim(), ctx);
+                ctx.log_messages.push(builtins::process_builtins(&message));
+            }
         } else if let Some(rest) = directive.strip_prefix("include ") {
             return self.handle_include(rest.trim(), ctx, false);
         } else if let Some(rest) = directive.strip_prefix("include_once ") {
             return self.handle_include(rest.trim(), ctx, true);
-        } else if let Some(rest) = directive.strip_prefix("function ") {
+        } else if let Some(rest) = strip_callable_prefix(directive, "function ") {
             self.start_function_definition(rest.trim(), ctx)?;
-        } else if let Some(rest) = directive.strip_prefix("procedure ") {
+        } else if let Some(rest) = strip_callable_prefix(directive, "procedure ") {
             self.start_procedure_definition(rest.trim(), ctx)?;
         } else if let Some(rest) = directive.strip_prefix("theme ") {
             self.handle_theme(rest.trim(), ctx)?;
+        } else if directive.starts_with("pragma ") {
+            // Прагмы влияют на layout (`!pragma teoz true`): строку разбирает парсер
+            if ctx.should_output() {
+                return Ok(Some(format!("{}\n", line)));
+            }
         } else if directive.starts_with('$') {
-            // Переменная: !$var = value
-            variables::handle_variable_assignment(directive, ctx)?;
+            // Переменная: !$var = expression
+            self.handle_assignment(directive, ctx)?;
+        } else if let Some(rest) = directive.strip_prefix("local ") {
+            self.handle_scoped_assignment(rest.trim(), false, ctx)?;
+        } else if let Some(rest) = directive.strip_prefix("global ") {
+            self.handle_scoped_assignment(rest.trim(), true, ctx)?;
         }
 
         Ok(None)
     }
 
+    /// Вычисляет выражение в текущем контексте
+    fn evaluate(&self, source: &str, ctx: &mut PreprocessContext) -> Result<Value> {
+        expression::evaluate(
+            source,
+            &mut CallScope {
+                preprocessor: self,
+                ctx,
+            },
+        )
+    }
+
+    /// Вычисляет выражение, а если это не выражение — возвращает текст
+    ///
+    /// Так сохраняется совместимость с прежним синтаксисом вида
+    /// `!return Hello_$name`, где значение — строка с подстановкой переменных.
+    fn evaluate_or_text(&self, source: &str, ctx: &mut PreprocessContext) -> Result<Value> {
+        match self.evaluate(source, ctx) {
+            Err(PreprocessError::ExpressionError(_) | PreprocessError::UnknownVariable(_)) => {
+                let text = self.substitute_variables(source.trim_matches('"'), ctx);
+                Ok(Value::Str(text))
+            }
+            result => result,
+        }
+    }
+
+    /// Обрабатывает `!$var = expression` и `!$var ?= expression`
+    fn handle_assignment(&self, directive: &str, ctx: &mut PreprocessContext) -> Result<()> {
+        if !ctx.should_output() {
+            return Ok(());
+        }
+
+        let Some((name, expr, if_undefined)) = variables::split_assignment(directive) else {
+            return variables::handle_variable_assignment(directive, ctx);
+        };
+        if if_undefined && ctx.is_defined(&name) {
+            return Ok(());
+        }
+
+        match self.evaluate(expr, ctx) {
+            Ok(value) => ctx.set_variable(name, value.to_string()),
+            // Не выражение: прежнее поведение (значение как есть, без кавычек)
+            Err(PreprocessError::ExpressionError(_) | PreprocessError::UnknownVariable(_)) => {
+                variables::handle_variable_assignment(&format!("{} = {}", name, expr), ctx)?;
+            }
+            Err(e) => return Err(e),
+        }
+
+        Ok(())
+    }
+
+    /// Обрабатывает `!local $var = expression` и `!global $var = expression`
+    ///
+    /// Локальная переменная восстанавливается при выходе из вызова,
+    /// глобальная остаётся после него, даже если создана внутри вызова.
+    fn handle_scoped_assignment(
+        &self,
+        directive: &str,
+        global: bool,
+        ctx: &mut PreprocessContext,
+    ) -> Result<()> {
+        if !ctx.should_output() {
+            return Ok(());
+        }
+        let Some((name, _, _)) = variables::split_assignment(directive) else {
+            return Err(PreprocessError::SyntaxError(format!(
+                "ожидалось присваивание переменной: {}",
+                directive
+            )));
+        };
+
+        if global {
+            ctx.globals.insert(name);
+        } else if let Some(frame) = ctx.call_frames.last_mut() {
+            if !frame.iter().any(|(saved, _)| *saved == name) {
+                let old = ctx.variables.get(&name).cloned();
+                frame.push((name, old));
+            }
+        }
+        self.handle_assignment(directive, ctx)
+    }
+
+    /// Обрабатывает `!return [expression]`
+    fn handle_return(&self, expr: &str, ctx: &mut PreprocessContext) -> Result<()> {
+        if !ctx.should_output() {
+            return Ok(());
+        }
+        if ctx.call_depth == 0 {
+            return Err(PreprocessError::SyntaxError(
+                "!return вне !function/!procedure".to_string(),
+            ));
+        }
+
+        let value = if expr.is_empty() {
+            Value::Str(String::new())
+        } else {
+            self.evaluate_or_text(expr, ctx)?
+        };
+        ctx.return_value = Some(value);
+
+        Ok(())
+    }
+
+    /// Обрабатывает `!assert condition [: message]`
+    fn handle_assert(&self, rest: &str, ctx: &mut PreprocessContext) -> Result<()> {
+        if !ctx.should_output() {
+            return Ok(());
+        }
+
+        let (condition, message) = match expression::split_top_level(rest, ':').as_slice() {
+            [condition] => (condition.trim(), None),
+            [condition, ..] => (condition.trim(), Some(rest[condition.len() + 1..].trim())),
+            [] => (rest, None),
+        };
+
+        if self.evaluate(condition, ctx)?.is_truthy() {
+            return Ok(());
+        }
+
+        let message = match message {
+            Some(message) => self.evaluate_or_text(message, ctx)?.to_string(),
+            None => condition.to_string(),
+        };
+        Err(PreprocessError::AssertionFailed(message))
+    }
+
+    /// Выполняет собранный цикл и возвращает его вывод
+    fn run_loop(&self, block: loops::LoopBlock, ctx: &mut PreprocessContext) -> Result<String> {
+        let mut output = String::new();
+        if !block.active {
+            return Ok(output);
+        }
+
+        let body = block.body.join("\n");
+        let too_many = || {
+            PreprocessError::SyntaxError(format!(
+                "цикл превысил {} итераций",
+                loops::MAX_LOOP_ITERATIONS
+            ))
+        };
+
+        match &block.kind {
+            loops::LoopKind::While(condition) => {
+                let mut iterations = 0;
+                while self.evaluate(condition, ctx)?.is_truthy() {
+                    iterations += 1;
+                    if iterations > loops::MAX_LOOP_ITERATIONS {
+                        return Err(too_many());
+                    }
+                    output.push_str(&self.process_lines(&body, ctx, LineSource::Body)?);
+                    if ctx.return_value.is_some() {
+                        break;
+                    }
+                }
+            }
+            loops::LoopKind::Foreach {
+                variable,
+                collection,
+            } => {
+                let items = match self.evaluate(collection, ctx)? {
+                    Value::Json(serde_json::Value::Array(items)) => items,
+                    other => {
+                        return Err(PreprocessError::ExpressionError(format!(
+                            "!foreach ожидает JSON массив, получено: {}",
+                            other
+                        )))
+                    }
+                };
+                if items.len() > loops::MAX_LOOP_ITERATIONS {
+                    return Err(too_many());
+                }
+                for item in items {
+                    ctx.set_variable(variable.clone(), Value::from_json(item).to_string());
+                    output.push_str(&self.process_lines(&body, ctx, LineSource::Body)?);
+                    if ctx.return_value.is_some() {
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(output)
+    }
+
+    /// Выполняет тело функции/процедуры
+    ///
+    /// Параметры, `!local` переменные и переменные, впервые присвоенные в
+    /// теле, локальны для вызова; присваивания уже существующим глобальным
+    /// переменным сохраняются после выхода.
+    /// Возвращает (вывод, значение `!return`).
+    fn invoke(
+        &self,
+        callable: &functions::UserCallable,
+        args: Vec<Value>,
+        ctx: &mut PreprocessContext,
+    ) -> Result<(String, Option<Value>)> {
+        if ctx.call_depth >= MAX_CALL_DEPTH {
+            return Err(PreprocessError::SyntaxError(format!(
+                "превышена глубина вызовов: {}",
+                callable.name
+            )));
+        }
+
+        let existing: HashSet<String> = ctx.variables.keys().cloned().collect();
+        let mut frame = Vec::new();
+        let saved_conditions = std::mem::take(&mut ctx.condition_stack);
+        let saved_branches = std::mem::take(&mut ctx.branch_taken);
+        let saved_depth = std::mem::replace(&mut ctx.condition_depth, 0);
+
+        let mut args = args.into_iter();
+        for param in &callable.parameters {
+            // Параметр может иметь значение по умолчанию: $name = "value"
+            let (name, default) = match param.split_once('=') {
+                Some((name, default)) => (name.trim(), Some(default.trim())),
+                None => (param.trim(), None),
+            };
+            let value = match (args.next(), default) {
+                (Some(value), _) => value,
+                (None, Some(default)) => self.evaluate_or_text(default, ctx)?,
+                (None, None) => Value::Str(String::new()),
+            };
+            if !frame.iter().any(|(saved, _): &(String, _)| saved == name) {
+                frame.push((name.to_string(), ctx.variables.get(name).cloned()));
+            }
+            ctx.set_variable(name, value.to_string());
+        }
+
+        ctx.call_frames.push(frame);
+        ctx.call_depth += 1;
+        let result = self.process_lines(&callable.body.join("\n"), ctx, LineSource::Body);
+        ctx.call_depth -= 1;
+        let frame = ctx.call_frames.pop().unwrap_or_default();
+
+        let return_value = ctx.return_value.take();
+        let globals = &ctx.globals;
+        ctx.variables.retain(|name, _| existing.contains(name) || globals.contains(name));
+        for (name, old) in frame {
+            match old {
+                Some(value) => ctx.set_variable(name, value),
+                None => {
+                    ctx.variables.shift_remove(&name);
+                }
+            }
+        }
+        ctx.condition_stack = saved_conditions;
+        ctx.branch_taken = saved_branches;
+        ctx.condition_depth = saved_depth;
+
+        Ok((result?, return_value))
+    }
+
     /// Обрабатывает !include и возвращает обработанный контент
     fn handle_include(
         &self,
@@ -323,17 +826,19 @@ impl<R: FileResolver> Preprocessor<R> {
             return Ok(None);
         }
 
-        let path = path.trim_matches(|c| c == '<' || c == '>' || c == '"');
+        // `<...>` передаётся resolver'у как есть: так он отличает стандартную
+        // библиотеку от обычных файлов
+        let key = path.trim_matches(|c| c == '<' || c == '>' || c == '"');
 
-        if once && ctx.included_files.contains(&path.to_string()) {
+        if once && ctx.included_files.iter().any(|included| included == key) {
             return Ok(None);
         }
 
         let content = self.resolver.read_file(path)?;
-        ctx.included_files.push(path.to_string());
+        ctx.included_files.push(key.to_string());
 
         // Рекурсивная обработка включённого файла
-        let processed = self.process_with_context(&content, ctx)?;
+        let processed = self.process_lines(&content, ctx, LineSource::Include(key))?;
 
         Ok(Some(processed))
     }
@@ -361,18 +866,32 @@ impl<R: FileResolver> Preprocessor<R> {
         Ok(())
     }
 
-    /// Обрабатывает skinparam
-    fn handle_skinparam(&self, line: &str, ctx: &mut PreprocessContext) {
-        // Формат: skinparam <key> <value>
-        let rest = line.strip_prefix("skinparam ").unwrap_or("");
-        let parts: Vec<&str> = rest.splitn(2, ' ').collect();
-
-        if parts.len() == 2 {
-            let key = parts[0].trim();
-            let value = parts[1].trim();
-            ctx.set_skin_param(key, value);
-            ctx.apply_skin_params();
+    /// Обрабатывает `skinparam <key> <value>` или открывает блок `skinparam <элемент> {`
+    ///
+    /// Возвращает строку для парсера, если параметр влияет на layout.
+    fn handle_skinparam(&self, line: &str, ctx: &mut PreprocessContext) -> Option<String> {
+        let rest = line.strip_prefix("skinparam").unwrap_or("").trim();
+        if let Some(element) = rest.strip_suffix('{') {
+            ctx.skinparam_block = Some(element.trim().to_string());
+            return None;
         }
+        self.handle_skinparam_entry("", rest, ctx)
+    }
+
+    /// Обрабатывает `<key> <value>` блока элемента `element` (`class`, `class<<Service>>`)
+    fn handle_skinparam_entry(&self, element: &str, entry: &str, ctx: &mut PreprocessContext) -> Option<String> {
+        let entry = self.substitute_variables(entry, ctx);
+        let (key, value) = entry.trim().split_once(char::is_whitespace)?;
+        let value = value.trim().trim_matches('"');
+
+        // Стереотип блока переносится в конец ключа: `ClassBackgroundColor<<Service>>`
+        let key = match element.find("<<") {
+            Some(index) => format!("{}{}{}", &element[..index], key, &element[index..]),
+            None => format!("{}{}", element, key),
+        };
+        ctx.set_skin_param(key.as_str(), value);
+        ctx.apply_skin_params();
+        layout_skinparam_key(&key).map(|key| format!("skinparam {} {}", key, value))
     }
 
     /// Начинает определение функции
@@ -438,39 +957,118 @@ impl<R: FileResolver> Preprocessor<R> {
     }
 
     /// Обрабатывает вызовы пользовательских функций в строке
-    fn process_function_calls(&self, line: &str, ctx: &PreprocessContext) -> String {
-        let calls = functions::find_function_calls(line);
+    fn process_function_calls(&self, line: &str, ctx: &mut PreprocessContext) -> Result<String> {
+        let calls = functions::find_function_calls(line, |name| ctx.get_callable(name).is_some());
 
         if calls.is_empty() {
-            return line.to_string();
+            return Ok(line.to_string());
         }
 
-        let mut result = line.to_string();
+        let mut result: Vec<char> = line.chars().collect();
 
         // Обрабатываем вызовы в обратном порядке (чтобы не сбивались индексы)
-        for (start, end, name, args) in calls.into_iter().rev() {
-            if let Some(callable) = ctx.get_callable(&name) {
-                let (output_lines, return_value) = callable.call(&args);
-
-                let replacement = match callable.kind {
-                    functions::CallableKind::Function => {
-                        // Функция: подставляем возвращённое значение
-                        return_value.unwrap_or_default()
-                    }
-                    functions::CallableKind::Procedure => {
-                        // Процедура: подставляем вывод
-                        output_lines.join("\n")
-                    }
-                };
-
-                result = format!("{}{}{}", &result[..start], replacement, &result[end..]);
+        for (start, end, name, _) in calls.into_iter().rev() {
+            let Some(callable) = ctx.get_callable(&name).cloned() else {
+                continue;
+            };
+
+            // Аргументы — выражения; не-выражения передаются как текст
+            let call: String = result[start..end].iter().collect();
+            let inner = &call[name.len() + 1..call.len() - 1];
+            let mut args = Vec::new();
+            if !inner.trim().is_empty() {
+                for arg in expression::split_top_level(inner, ',') {
+                    args.push(self.evaluate_or_text(arg.trim(), ctx)?);
+                }
             }
+
+            let (output, return_value) = self.invoke(&callable, args, ctx)?;
+            let replacement = match callable.kind {
+                // Функция: подставляем возвращённое значение
+                functions::CallableKind::Function => {
+                    return_value.map(|v| v.to_string()).unwrap_or_default()
+                }
+                // Процедура: подставляем вывод
+                functions::CallableKind::Procedure => output.trim_end_matches('\n').to_string(),
+            };
+
+            result.splice(start..end, replacement.chars());
         }
 
-        result
+        Ok(result.into_iter().collect())
+    }
+}
+
+/// Отрезает `function ` / `procedure ` с необязательным `unquoted `
+///
+/// `!unquoted procedure` разрешает передавать аргументы без кавычек; у нас
+/// аргументы, не являющиеся выражениями, и так передаются как текст.
+fn strip_callable_prefix<'a>(directive: &'a str, keyword: &str) -> Option<&'a str> {
+    directive
+        .strip_prefix("unquoted ")
+        .unwrap_or(directive)
+        .strip_prefix(keyword)
+}
+
+/// Директива, закрывающая блок, открытый директивой `opening`
+fn closing_directive(opening: &str) -> &'static str {
+    let keyword = opening
+        .trim_start_matches('!')
+        .trim_start_matches("unquoted ")
+        .split_whitespace()
+        .next()
+        .unwrap_or_default();
+    match keyword {
+        "while" => "!endwhile",
+        "foreach" => "!endfor",
+        "function" => "!endfunction",
+        "procedure" => "!endprocedure",
+        _ => "!endif",
+    }
+}
+
+/// Записывает происхождение строк `added`, выведенных строкой `line` файла
+///
+/// Вывод `!include` берёт происхождение из `ctx.line_map` вложенного файла;
+/// остальные строки (вызовы процедур, циклы) относятся к самой строке `line`.
+fn record_origins(
+    origins: &mut Vec<LineOrigin>,
+    added: &str,
+    file: Option<&str>,
+    line: usize,
+    ctx: &mut PreprocessContext,
+) {
+    let count = added.matches('\n').count();
+    let included = std::mem::take(&mut ctx.line_map);
+    if count > 0 && included.len() == count {
+        origins.extend(included.into_iter().map(|origin| LineOrigin {
+            source_line: line,
+            ..origin
+        }));
+    } else {
+        origins.extend((0..count).map(|_| LineOrigin::new(file, line)));
     }
 }
 
+/// Ключ skinparam, который разбирает парсер, а не тема (`skinparam linetype ortho`,
+/// `skinparam maxMessageSize 80`); префикс блока `skinparam sequence { }` отбрасывается
+fn layout_skinparam_key(key: &str) -> Option<&str> {
+    const LAYOUT_KEYS: [&str; 4] = [
+        "linetype",
+        "maxmessagesize",
+        "responsemessagebelowarrow",
+        "lifelinestrategy",
+    ];
+    let key = match key.get(..8) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("sequence") => &key[8..],
+        _ => key,
+    };
+    LAYOUT_KEYS
+        .iter()
+        .any(|layout| key.eq_ignore_ascii_case(layout))
+        .then_some(key)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -599,9 +1197,48 @@ skinparam backgroundColor #FF0000
 @startuml
 @enduml
 "#;
-        preprocessor.process_with_context(source, &mut ctx).unwrap();
+        let output = preprocessor.process_with_context(source, &mut ctx).unwrap();
 
         assert_eq!(ctx.theme.background_color.to_css(), "#FF0000");
+        assert!(!output.contains("skinparam"));
+
+        // Форма линий остаётся в тексте для парсера
+        let output = preprocessor.process("skinparam linetype ortho\n").unwrap();
+        assert_eq!(output, "skinparam linetype ortho\n");
+        let output = preprocessor.process("skinparam maxMessageSize 80\n").unwrap();
+        assert_eq!(output, "skinparam maxMessageSize 80\n");
+    }
+
+    #[test]
+    fn test_skinparam_block() {
+        let preprocessor = Preprocessor::new();
+        let mut ctx = PreprocessContext::new();
+
+        let source = r##"
+!$border = "#00FF00"
+skinparam class {
+    BackgroundColor PaleGreen
+    BackgroundColor<<Service>> Wheat
+    BorderColor $border
+}
+skinparam participant<<Db>> {
+    FontName "Courier New"
+}
+skinparam sequence {
+    MaxMessageSize 80
+}
+class Foo
+"##;
+        let output = preprocessor.process_with_context(source, &mut ctx).unwrap();
+
+        let get = |key: &str| ctx.skin_params.get(key).map(String::as_str);
+        assert_eq!(get("ClassBackgroundColor"), Some("PaleGreen"));
+        assert_eq!(get("ClassBackgroundColor<<Service>>"), Some("Wheat"));
+        assert_eq!(get("ClassBorderColor"), Some("#00FF00"));
+        assert_eq!(get("ParticipantFontName<<Db>>"), Some("Courier New"));
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[11], "skinparam MaxMessageSize 80");
+        assert_eq!(lines[13], "class Foo");
     }
 
     #[test]
@@ -651,6 +1288,52 @@ participant Alice $COLOR
         assert!(result.contains("participant Alice #FF0000"));
     }
 
+    #[test]
+    fn test_line_map_through_include_and_procedure() {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("inc.puml"), "class Inc1\nclass Inc2\n").unwrap();
+
+        let source = "@startuml\n!include inc.puml\n!procedure $two()\nclass P1\nclass P2\n\
+                      !endprocedure\n$two()\nclass Mine\n@enduml";
+        let preprocessor = Preprocessor::with_resolver(FsFileResolver::new(temp_dir.path()));
+        let mut ctx = PreprocessContext::new();
+        let output = preprocessor.process_with_context(source, &mut ctx).unwrap();
+
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), ctx.line_map.len());
+        let origin = |text: &str| {
+            let index = lines.iter().position(|line| line.trim() == text).unwrap();
+            ctx.line_map[index].clone()
+        };
+        assert_eq!(
+            origin("class Inc2"),
+            LineOrigin {
+                file: Some("inc.puml".to_string()),
+                line: 2,
+                source_line: 2,
+            }
+        );
+        assert_eq!(origin("class P2"), LineOrigin::new(None, 7));
+        assert_eq!(origin("class Mine"), LineOrigin::new(None, 8));
+        assert_eq!(origin("@enduml"), LineOrigin::new(None, 9));
+    }
+
+    #[test]
+    fn test_line_map_through_loop() {
+        let source =
+            "@startuml\n!$i = 0\n!while $i < 2\nclass C$i\n!$i = $i + 1\n!endwhile\nclass End";
+        let mut ctx = PreprocessContext::new();
+        let output = Preprocessor::new().process_with_context(source, &mut ctx).unwrap();
+
+        let lines: Vec<&str> = output.lines().collect();
+        let index = lines.iter().position(|line| *line == "class C1").unwrap();
+        assert_eq!(ctx.line_map[index], LineOrigin::new(None, 3));
+        let index = lines.iter().position(|line| *line == "class End").unwrap();
+        assert_eq!(ctx.line_map[index], LineOrigin::new(None, 7));
+    }
+
     #[test]
     fn test_include_once() {
         use std::io::Write;
@@ -694,7 +1377,13 @@ BODY
 
         assert!(result.is_err());
         let err = result.unwrap_err();
-        assert!(matches!(err, PreprocessError::FileNotFound(_)));
+        match err {
+            PreprocessError::At { file, line, error } => {
+                assert_eq!((file, line), (None, 2));
+                assert!(matches!(*error, PreprocessError::FileNotFound(_)));
+            }
+            other => panic!("ожидалась ошибка со строкой, получено {:?}", other),
+        }
     }
 
     #[test]
@@ -733,4 +1422,278 @@ MAIN_END
         assert!(result.contains("LEVEL1_END"));
         assert!(result.contains("MAIN_END"));
     }
+
+    #[test]
+    fn test_if_elseif_else() {
+        let source = r#"
+!$env = "stage"
+!if $env == "prod"
+production
+!elseif $env == "stage"
+staging
+!else
+development
+!endif
+"#;
+        let result = preprocess(source).unwrap();
+        assert!(result.contains("staging"));
+        assert!(!result.contains("production"));
+        assert!(!result.contains("development"));
+    }
+
+    #[test]
+    fn test_if_not_evaluated_in_false_branch() {
+        // $missing не определена, но ветка не выполняется — ошибки нет
+        let source = "!if 0\n!if $missing == 1\nhidden\n!endif\n!endif\nvisible";
+        let result = preprocess(source).unwrap();
+        assert!(!result.contains("hidden"));
+        assert!(result.contains("visible"));
+    }
+
+    #[test]
+    fn test_while_loop() {
+        let source = r#"
+!$i = 0
+!while $i < 3
+node_$i
+!$i = $i + 1
+!endwhile
+"#;
+        let result = preprocess(source).unwrap();
+        assert!(result.contains("node_0\nnode_1\nnode_2\n"));
+        assert!(!result.contains("node_3"));
+    }
+
+    #[test]
+    fn test_foreach_json() {
+        let source = r#"
+!$services = [{"name": "api", "port": 80}, {"name": "db", "port": 5432}]
+!foreach $svc in $services
+!if $svc.port > 100
+component $svc.name
+!endif
+!endfor
+"#;
+        let mut ctx = PreprocessContext::new();
+        let result = Preprocessor::new()
+            .process_with_context(source, &mut ctx)
+            .unwrap();
+        assert!(result.contains("component db"));
+        assert!(!result.contains("component api"));
+    }
+
+    #[test]
+    fn test_function_with_control_flow() {
+        let source = r#"
+!function $fact($n)
+!if $n <= 1
+!return 1
+!endif
+!return $n * $fact($n - 1)
+!endfunction
+result: $fact(5)
+"#;
+        let result = preprocess(source).unwrap();
+        assert!(result.contains("result: 120"), "{}", result);
+    }
+
+    #[test]
+    fn test_procedure_with_foreach() {
+        let source = r#"
+!procedure $components($names)
+!foreach $name in $names
+component $name
+!endfor
+!endprocedure
+$components(["api", "web"])
+"#;
+        let result = preprocess(source).unwrap();
+        assert!(result.contains("component api\ncomponent web"));
+    }
+
+    #[test]
+    fn test_procedure_locals_do_not_leak() {
+        let source = r#"
+!$name = "global"
+!procedure $show($name)
+!$tmp = 1
+value $name
+!endprocedure
+$show("local")
+after $name
+"#;
+        let mut ctx = PreprocessContext::new();
+        let result = Preprocessor::new()
+            .process_with_context(source, &mut ctx)
+            .unwrap();
+        assert!(result.contains("value local"));
+        assert!(result.contains("after global"));
+        assert!(!ctx.is_defined("$tmp"));
+    }
+
+    #[test]
+    fn test_procedure_updates_global_counter() {
+        let source = r#"
+!$count = 0
+!procedure $step($by = 1)
+!$count = $count + $by
+!$tmp = $count
+!endprocedure
+$step()
+$step(2)
+count $count
+"#;
+        let mut ctx = PreprocessContext::new();
+        let result = Preprocessor::new()
+            .process_with_context(source, &mut ctx)
+            .unwrap();
+        assert!(result.contains("count 3"));
+        assert_eq!(ctx.get_variable("$count").map(String::as_str), Some("3"));
+        assert!(!ctx.is_defined("$by"));
+        assert!(!ctx.is_defined("$tmp"));
+    }
+
+    #[test]
+    fn test_procedure_local_and_global_declarations() {
+        let source = r#"
+!$name = "outer"
+!procedure $mark()
+!local $name = "inner"
+!global $seen = $name
+!endprocedure
+$mark()
+name $name seen $seen
+"#;
+        let result = Preprocessor::new().process(source).unwrap();
+        assert!(result.contains("name outer seen inner"));
+    }
+
+    #[test]
+    fn test_return_exits_loop() {
+        let source = r#"
+!function $first_big($items)
+!foreach $x in $items
+!if $x > 10
+!return $x
+!endif
+!endfor
+!return 0
+!endfunction
+big: $first_big([3, 42, 100])
+"#;
+        let result = preprocess(source).unwrap();
+        assert!(result.contains("big: 42"));
+    }
+
+    #[test]
+    fn test_assert_and_log() {
+        let mut ctx = PreprocessContext::new();
+        let preprocessor = Preprocessor::new();
+
+        let source = "!$n = 2\n!log n = $n\n!assert $n == 2 : \"n должно быть 2\"";
+        preprocessor.process_with_context(source, &mut ctx).unwrap();
+        assert_eq!(ctx.log_messages, vec!["n = 2".to_string()]);
+
+        let err = preprocessor
+            .process("@startuml\n!assert 1 > 2 : \"слишком мало\"")
+            .unwrap_err();
+        assert_eq!(err.to_string(), "строка 2: !assert не выполнено: слишком мало");
+        match err {
+            PreprocessError::At { error, .. } => match *error {
+                PreprocessError::AssertionFailed(message) => assert_eq!(message, "слишком мало"),
+                other => panic!("ожидалась ошибка !assert, получено {:?}", other),
+            },
+            other => panic!("ожидалась ошибка со строкой, получено {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_error_in_included_file_and_procedure() {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("inc.puml"), "class A\n!assert 1 == 2\n").unwrap();
+        let preprocessor = Preprocessor::with_resolver(FsFileResolver::new(temp_dir.path()));
+
+        match preprocessor.process("@startuml\n!include inc.puml\n").unwrap_err() {
+            PreprocessError::At { file, line, .. } => {
+                assert_eq!((file.as_deref(), line), (Some("inc.puml"), 2))
+            }
+            other => panic!("ожидалась ошибка со строкой, получено {:?}", other),
+        }
+
+        let source = "!procedure $check()\n!assert 1 == 2\n!endprocedure\nclass A\n$check()";
+        match preprocessor.process(source).unwrap_err() {
+            PreprocessError::At { file, line, .. } => assert_eq!((file, line), (None, 5)),
+            other => panic!("ожидалась ошибка со строкой, получено {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_conditional_assignment() {
+        let mut ctx = PreprocessContext::new();
+        ctx.set_variable("$env", "prod");
+
+        let source = "!$env ?= \"dev\"\n!$region ?= \"eu\"\n$env-$region";
+        let result = Preprocessor::new()
+            .process_with_context(source, &mut ctx)
+            .unwrap();
+        assert!(result.contains("prod-eu"));
+    }
+
+    #[test]
+    fn test_control_flow_errors() {
+        assert!(preprocess("!while 1\nloop").is_err());
+        assert!(preprocess("!endwhile").is_err());
+        assert!(preprocess("!return 1").is_err());
+        assert!(preprocess("!$i = 0\n!while 1\n!$i = $i + 1\n!endwhile").is_err());
+        assert!(preprocess("!foreach $x in \"text\"\n!endfor").is_err());
+    }
+
+    #[test]
+    fn test_unterminated_blocks() {
+        let cases = [
+            ("@startuml\n!if 1 == 1\nA -> B\n@enduml", 2, "!if 1 == 1 без !endif"),
+            ("@startuml\n!ifdef X\n!if 1\n!endif\nA -> B", 2, "!ifdef X без !endif"),
+            ("!$i = 0\n!while $i < 1\n!$i = $i + 1\nA -> B", 2, "!while $i < 1 без !endwhile"),
+            ("A -> B\n\n!procedure $p()\nB -> C\n", 3, "!procedure $p() без !endprocedure"),
+            ("!function $f()\n!return 1", 1, "!function $f() без !endfunction"),
+            ("!if 1\n!while 0\n!endwhile\n!if 0\n!endif", 1, "!if 1 без !endif"),
+        ];
+        for (source, expected_line, expected) in cases {
+            match preprocess(source).unwrap_err() {
+                PreprocessError::At { file, line, error } => {
+                    assert_eq!((file, line), (None, expected_line), "{}", source);
+                    assert_eq!(
+                        error.to_string(),
+                        format!("синтаксическая ошибка в директиве: {}", expected)
+                    );
+                }
+                other => panic!("ожидалась ошибка со строкой, получено {:?}", other),
+            }
+        }
+        assert!(preprocess("!if 1\n!if 0\n!else\nA\n!endif\n!endif").is_ok());
+    }
+
+    #[test]
+    fn test_pragma_passed_to_parser() {
+        let result = preprocess("!pragma teoz true\n!if 0\n!pragma other\n!endif\nA -> B").unwrap();
+        assert_eq!(
+            result.lines().collect::<Vec<_>>(),
+            vec!["!pragma teoz true", "", "", "", "A -> B"]
+        );
+    }
+
+    #[test]
+    fn test_consumed_lines_keep_numbering() {
+        let source = "@startuml\n!$x = 1\n!procedure $p()\nA -> B\n!endprocedure\n!if $x == 2\nC -> D\n!endif\nskinparam class {\nBorderColor red\n}\nparticipant Api\n$p()\n@enduml";
+        let result = preprocess(source).unwrap();
+        let lines: Vec<&str> = result.lines().collect();
+
+        // Каждая строка вывода стоит на месте своей строки исходника
+        assert_eq!(lines.iter().position(|l| *l == "participant Api"), Some(11));
+        assert_eq!(lines[12], "A -> B");
+        assert_eq!(lines[13], "@enduml");
+    }
 }
+
@@ -0,0 +1,131 @@
+//! Циклы препроцессора: `!while` / `!endwhile` и `!foreach` / `!endfor`
+//!
+//! Тело цикла собирается построчно до парной закрывающей директивы
+//! (с учётом вложенных циклов), после чего выполняется препроцессором.
+
+use crate::{PreprocessError, Result};
+
+/// Максимальное число итераций одного цикла (защита от зацикливания)
+pub const MAX_LOOP_ITERATIONS: usize = 10_000;
+
+/// Вид цикла
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoopKind {
+    /// `!while <условие>`
+    While(String),
+    /// `!foreach $var in <выражение>`
+    Foreach {
+        /// Переменная цикла
+        variable: String,
+        /// Выражение, возвращающее JSON массив
+        collection: String,
+    },
+}
+
+/// Собираемый блок цикла
+#[derive(Debug, Clone)]
+pub struct LoopBlock {
+    /// Вид цикла
+    pub kind: LoopKind,
+    /// Строки тела
+    pub body: Vec<String>,
+    /// Выполнять ли цикл (false внутри ложного условия)
+    pub active: bool,
+    /// Глубина вложенных циклов
+    depth: usize,
+}
+
+impl LoopBlock {
+    /// Разбирает заголовок цикла (директива без `!`)
+    pub fn parse(directive: &str, active: bool) -> Result<Self> {
+        let kind = if let Some(condition) = directive.strip_prefix("while ") {
+            LoopKind::While(condition.trim().to_string())
+        } else if let Some(rest) = directive.strip_prefix("foreach ") {
+            let (variable, collection) = rest.split_once(" in ").ok_or_else(|| {
+                PreprocessError::SyntaxError(format!("ожидалось `!foreach $var in ...`: {}", rest))
+            })?;
+            let variable = variable.trim();
+            if !variable.starts_with('$') {
+                return Err(PreprocessError::SyntaxError(format!(
+                    "переменная цикла должна начинаться с $: {}",
+                    variable
+                )));
+            }
+            LoopKind::Foreach {
+                variable: variable.to_string(),
+                collection: collection.trim().to_string(),
+            }
+        } else {
+            return Err(PreprocessError::SyntaxError(format!(
+                "неизвестный цикл: !{}",
+                directive
+            )));
+        };
+
+        Ok(Self {
+            kind,
+            body: Vec::new(),
+            active,
+            depth: 1,
+        })
+    }
+
+    /// Добавляет строку тела
+    ///
+    /// Возвращает `true`, когда встретилась парная закрывающая директива.
+    pub fn push_line(&mut self, line: &str) -> bool {
+        let trimmed = line.trim();
+        if is_loop_start(trimmed) {
+            self.depth += 1;
+        } else if is_loop_end(trimmed) {
+            self.depth -= 1;
+            if self.depth == 0 {
+                return true;
+            }
+        }
+        self.body.push(line.to_string());
+        false
+    }
+}
+
+/// Строка открывает цикл
+pub fn is_loop_start(trimmed: &str) -> bool {
+    trimmed.starts_with("!while ") || trimmed.starts_with("!foreach ")
+}
+
+/// Строка закрывает цикл
+pub fn is_loop_end(trimmed: &str) -> bool {
+    trimmed == "!endwhile" || trimmed == "!endfor"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_headers() {
+        let block = LoopBlock::parse("while $i < 3", true).unwrap();
+        assert_eq!(block.kind, LoopKind::While("$i < 3".to_string()));
+
+        let block = LoopBlock::parse("foreach $svc in $services.list", true).unwrap();
+        assert_eq!(
+            block.kind,
+            LoopKind::Foreach {
+                variable: "$svc".to_string(),
+                collection: "$services.list".to_string(),
+            }
+        );
+
+        assert!(LoopBlock::parse("foreach svc", true).is_err());
+    }
+
+    #[test]
+    fn test_nested_body() {
+        let mut block = LoopBlock::parse("while $i < 3", true).unwrap();
+        assert!(!block.push_line("!foreach $x in [1]"));
+        assert!(!block.push_line("  $x"));
+        assert!(!block.push_line("!endfor"));
+        assert!(block.push_line("!endwhile"));
+        assert_eq!(block.body.len(), 3);
+    }
+}
@@ -0,0 +1,154 @@
+//! FileResolver для стандартной библиотеки PlantUML
+//!
+//! Пути вида `<C4/C4_Container>` сначала ищутся во встроенной
+//! библиотеке (`plantuml-stdlib`), остальные — и не найденные в ней —
+//! передаются вложенному resolver'у. Встроена только часть файлов
+//! каждой библиотеки, поэтому ошибка для отсутствующего файла известной
+//! библиотеки говорит об этом явно.
+
+use crate::{FileResolver, NoopFileResolver, PreprocessError, Result};
+
+/// FileResolver со встроенной стандартной библиотекой
+///
+/// # Пример
+///
+/// ```rust,ignore
+/// use plantuml_preprocessor::{FsFileResolver, Preprocessor, StdlibFileResolver};
+///
+/// let resolver = StdlibFileResolver::new(FsFileResolver::new("/path/to/project"));
+/// let preprocessor = Preprocessor::with_resolver(resolver);
+/// let result = preprocessor.process("!include <C4/C4_Container>")?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct StdlibFileResolver<R: FileResolver = NoopFileResolver> {
+    /// Resolver для всего, чего нет в stdlib
+    fallback: R,
+}
+
+impl<R: FileResolver> StdlibFileResolver<R> {
+    /// Создаёт resolver поверх `fallback`
+    pub fn new(fallback: R) -> Self {
+        Self { fallback }
+    }
+
+    /// Вложенный resolver
+    pub fn fallback(&self) -> &R {
+        &self.fallback
+    }
+}
+
+/// Путь без угловых скобок, если это `<...>`
+fn stdlib_path(path: &str) -> Option<&str> {
+    path.trim().strip_prefix('<')?.strip_suffix('>')
+}
+
+impl<R: FileResolver> FileResolver for StdlibFileResolver<R> {
+    fn read_file(&self, path: &str) -> Result<String> {
+        if let Some(content) = stdlib_path(path).and_then(plantuml_stdlib::read) {
+            return Ok(content);
+        }
+        self.fallback.read_file(path).map_err(|error| {
+            let Some(library) = stdlib_path(path).and_then(plantuml_stdlib::library) else {
+                return error;
+            };
+            let enabled = plantuml_stdlib::libraries().any(|enabled| enabled.name == library.name);
+            let reason = if enabled {
+                format!(
+                    "во встроенную stdlib входит только часть библиотеки {}, \
+                     остальные файлы подключаются из путей поиска (-I)",
+                    library.name
+                )
+            } else {
+                format!(
+                    "библиотека {} не включена в сборку (feature `{}`)",
+                    library.name, library.feature
+                )
+            };
+            PreprocessError::FileNotFound(format!("{}: {}", path.trim(), reason))
+        })
+    }
+
+    fn file_exists(&self, path: &str) -> bool {
+        stdlib_path(path).is_some_and(plantuml_stdlib::exists) || self.fallback.file_exists(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FsFileResolver, Preprocessor};
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_falls_back_for_plain_paths() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("local.puml"), "Alice -> Bob").unwrap();
+
+        let resolver = StdlibFileResolver::new(FsFileResolver::new(temp_dir.path()));
+        assert_eq!(resolver.read_file("local.puml").unwrap(), "Alice -> Bob");
+        assert!(!resolver.file_exists("<nonexistent/File>"));
+    }
+
+    #[test]
+    fn test_missing_stdlib_file() {
+        let resolver = StdlibFileResolver::new(NoopFileResolver);
+        let result = resolver.read_file("<nonexistent/File>");
+        assert!(matches!(result, Err(PreprocessError::IncludeNotSupported(_))));
+    }
+
+    #[test]
+    fn test_missing_file_of_bundled_library() {
+        let resolver = StdlibFileResolver::new(NoopFileResolver);
+        match resolver.read_file("<awslib/Analytics/Athena>") {
+            Err(PreprocessError::FileNotFound(message)) => {
+                assert!(message.starts_with("<awslib/Analytics/Athena>: "));
+                assert!(message.contains("awslib"));
+            }
+            other => panic!("ожидалась FileNotFound, получено {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_search_paths_after_stdlib() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join("custom")).unwrap();
+        fs::write(temp_dir.path().join("custom/Lib.puml"), "' custom").unwrap();
+
+        let fs = FsFileResolver::new(".").with_search_path(temp_dir.path());
+        let resolver = StdlibFileResolver::new(fs);
+        assert_eq!(resolver.read_file("<custom/Lib>").unwrap(), "' custom");
+    }
+
+    #[test]
+    fn test_include_c4_container() {
+        let preprocessor = Preprocessor::with_resolver(StdlibFileResolver::new(NoopFileResolver));
+        let result = preprocessor.process(C4_CONTAINER_EXAMPLE).unwrap();
+
+        for call in ["Person(", "System_Boundary(", "Container(", "ContainerDb(", "Rel(", "SHOW_"] {
+            assert!(!result.contains(call), "макрос {} не раскрыт:\n{}", call, result);
+        }
+        assert!(result.contains("left to right direction"));
+        assert!(result.contains("as api"));
+        assert!(result.contains("as db"));
+        assert!(result.contains("[HTTPS]"));
+    }
+
+    const C4_CONTAINER_EXAMPLE: &str = r#"@startuml
+!include <C4/C4_Container>
+LAYOUT_LEFT_RIGHT()
+
+Person(user, "Customer", "Buys things online")
+System_Boundary(shop, "Online Shop") {
+    Container(web, "Web App", "React", "Storefront UI")
+    Container(api, "API", "Rust", "Orders and catalog")
+    ContainerDb(db, "Database", "PostgreSQL", "Orders")
+}
+
+Rel(user, web, "Uses", "HTTPS")
+Rel(web, api, "Calls", "JSON/HTTPS")
+Rel(api, db, "Reads/writes", "SQL")
+
+SHOW_LEGEND()
+@enduml"#;
+}
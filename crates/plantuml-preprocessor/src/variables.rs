@@ -1,9 +1,37 @@
 //! Обработка переменных препроцессора
 
+use std::sync::OnceLock;
+
 use indexmap::IndexMap;
+use regex::Regex;
 
+use crate::expression::{self, Scope, Value};
 use crate::{PreprocessContext, PreprocessError, Result};
 
+/// Обращение к JSON в тексте: `$data.key`, `$list[0]`, `$data.items[1].name`
+fn json_path_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"\$[A-Za-z_][A-Za-z0-9_]*(?:\.[A-Za-z_][A-Za-z0-9_]*|\[[0-9]+\])+").unwrap()
+    })
+}
+
+/// Окружение выражений только с переменными (без вызовов функций)
+struct VariableScope<'a>(&'a IndexMap<String, String>);
+
+impl Scope for VariableScope<'_> {
+    fn variable(&self, name: &str) -> Option<Value> {
+        self.0.get(name).map(|v| Value::from_text(v))
+    }
+
+    fn call(&mut self, name: &str, _args: Vec<Value>) -> Result<Value> {
+        Err(PreprocessError::ExpressionError(format!(
+            "вызов {} недоступен при подстановке",
+            name
+        )))
+    }
+}
+
 /// Обрабатывает присваивание переменной: !$var = value
 pub fn handle_variable_assignment(directive: &str, ctx: &mut PreprocessContext) -> Result<()> {
     if !ctx.should_output() {
@@ -27,9 +55,36 @@ pub fn handle_variable_assignment(directive: &str, ctx: &mut PreprocessContext)
     Ok(())
 }
 
+/// Разбирает присваивание `$name = expr` или `$name ?= expr`
+///
+/// Возвращает имя переменной (с `$`), выражение и признак `?=`
+/// (присвоить, только если переменная не определена).
+pub fn split_assignment(directive: &str) -> Option<(String, &str, bool)> {
+    let (left, expr) = directive.split_once('=')?;
+    let (left, if_undefined) = match left.trim_end().strip_suffix('?') {
+        Some(left) => (left, true),
+        None => (left, false),
+    };
+
+    let name = left.trim().trim_start_matches('$');
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+
+    Some((format!("${}", name), expr.trim(), if_undefined))
+}
+
 /// Подставляет переменные в строку
 pub fn substitute(line: &str, variables: &IndexMap<String, String>) -> String {
-    let mut result = line.to_string();
+    // Сначала обращения к полям JSON, пока имя переменной не заменено текстом
+    let mut result = json_path_regex()
+        .replace_all(line, |caps: &regex::Captures| {
+            let path = &caps[0];
+            expression::evaluate(path, &mut VariableScope(variables))
+                .map(|value| value.to_string())
+                .unwrap_or_else(|_| path.to_string())
+        })
+        .to_string();
 
     for (name, value) in variables {
         // Подстановка $name и ${name}
@@ -63,4 +118,29 @@ mod tests {
         let result = substitute("participant $name #$color", &vars);
         assert_eq!(result, "participant Alice ##FF0000");
     }
+
+    #[test]
+    fn test_split_assignment() {
+        assert_eq!(
+            split_assignment("$count = $count + 1"),
+            Some(("$count".to_string(), "$count + 1", false))
+        );
+        assert_eq!(
+            split_assignment("$env ?= \"dev\""),
+            Some(("$env".to_string(), "\"dev\"", true))
+        );
+        assert_eq!(split_assignment("$a b = 1"), None);
+    }
+
+    #[test]
+    fn test_substitute_json_path() {
+        let mut vars = IndexMap::new();
+        vars.insert(
+            "$svc".to_string(),
+            r#"{"name": "api", "ports": [80, 443]}"#.to_string(),
+        );
+
+        let result = substitute("component $svc.name : $svc.ports[1]", &vars);
+        assert_eq!(result, "component api : 443");
+    }
 }
@@ -23,17 +23,22 @@
 //! }
 //! ```
 
+pub mod semantic;
+pub mod sketch;
+pub mod skin;
 pub mod shapes;
+pub mod sprites;
 pub mod svg_renderer;
 
 #[cfg(feature = "png")]
 pub mod png_renderer;
 
 pub use plantuml_layout::{
-    ClassMember, ClassifierKind, EdgeType, ElementType, FragmentSection, 
+    ClassMember, ClassifierKind, ContainerKind, EdgeType, ElementType, FragmentSection,
     LayoutElement, LayoutResult, MemberVisibility, Point, Rect, ZLayer,
 };
-pub use plantuml_themes::Theme;
+pub use plantuml_ast::common::DiagramScale;
+pub use plantuml_themes::{SkinParams, Theme};
 pub use svg_renderer::SvgRenderer;
 
 #[cfg(feature = "png")]
@@ -57,6 +62,12 @@ pub struct RenderOptions {
     pub scale: f64,
     /// Цвет фона (None = прозрачный)
     pub background_color: Option<String>,
+    /// Директива `scale` из исходника диаграммы
+    pub diagram_scale: Option<DiagramScale>,
+    /// Максимальная ширина SVG (None = без ограничений)
+    pub max_width: Option<f64>,
+    /// Максимальная высота SVG (None = без ограничений)
+    pub max_height: Option<f64>,
 }
 
 impl Default for RenderOptions {
@@ -65,6 +76,29 @@ impl Default for RenderOptions {
             xml_header: true,
             scale: 1.0,
             background_color: None, // None = PlantUML default (#FEFECE)
+            diagram_scale: None,
+            max_width: None,
+            max_height: None,
         }
     }
 }
+
+impl RenderOptions {
+    /// Итоговый масштаб для документа исходного размера `width` × `height`
+    ///
+    /// Директива `scale` умножается на `scale`, затем результат уменьшается,
+    /// чтобы документ поместился в `max_width` × `max_height`.
+    pub fn effective_scale(&self, width: f64, height: f64) -> f64 {
+        let mut scale = self.scale;
+        if let Some(diagram_scale) = &self.diagram_scale {
+            scale *= diagram_scale.factor(width, height);
+        }
+        if let Some(max_width) = self.max_width.filter(|_| width > 0.0) {
+            scale = scale.min(max_width / width);
+        }
+        if let Some(max_height) = self.max_height.filter(|_| height > 0.0) {
+            scale = scale.min(max_height / height);
+        }
+        scale
+    }
+}
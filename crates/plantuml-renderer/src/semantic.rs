@@ -0,0 +1,401 @@
+//! Семантическая разметка SVG: стабильные id, CSS классы и строки исходника
+//!
+//! Каждая группа элемента получает:
+//! - `id` — id элемента layout, экранированный для атрибута и CSS селектора;
+//!   классы без префикса типа в id (диаграммы классов) получают его здесь,
+//!   повторы нумеруются (`msg_Alice_Bob`, `msg_Alice_Bob-2`);
+//! - `class` — категория, вид и модификаторы (`entity participant`,
+//!   `link message dashed`);
+//! - `data-source-line` — строка исходника, если её знает layout.
+
+use std::collections::HashSet;
+
+use plantuml_layout::{
+    ClassifierKind, ContainerKind, EdgeType, ElementType, LayoutElement, LayoutResult, ZLayer,
+};
+
+/// Виды элементов по префиксу id: (префикс, категория, вид)
+///
+/// Более длинные префиксы стоят раньше совпадающих с ними коротких.
+const ID_KINDS: &[(&str, &str, &str)] = &[
+    ("participant_label_", "label", "participant"),
+    ("participant_", "entity", "participant"),
+    ("footer_", "entity", "participant"),
+    ("lifeline_", "link", "lifeline"),
+    ("msg_", "link", "message"),
+    ("return_", "link", "message"),
+    ("duration_", "link", "duration"),
+    ("activation_", "decoration", "activation"),
+    ("note_", "note", "note"),
+    ("fragment_", "cluster", "fragment"),
+    ("box_", "cluster", "box"),
+    ("divider_", "label", "divider"),
+    ("delay_", "label", "delay"),
+    ("mainframe", "cluster", "mainframe"),
+    ("legend", "label", "legend"),
+    ("title", "label", "title"),
+    ("header", "label", "header"),
+    ("caption", "label", "caption"),
+    ("footer", "label", "footer"),
+    ("transition_", "link", "transition"),
+    ("edge_", "link", "relation"),
+    ("rel_", "link", "relation"),
+    ("link_", "link", "relation"),
+    ("conn_", "link", "relation"),
+    ("task_bar_", "entity", "task"),
+    ("task_progress_", "decoration", "progress"),
+    ("task_resources_", "label", "resources"),
+    ("dependency_", "link", "dependency"),
+    ("milestone_label_", "label", "milestone"),
+    ("milestone_", "entity", "milestone"),
+    ("resource_label_", "label", "resource"),
+    ("resource_load_", "label", "load"),
+    ("resource_overload_", "decoration", "overload"),
+    ("weekend_", "decoration", "closed"),
+    ("holiday_", "decoration", "holiday"),
+    ("today", "decoration", "today"),
+];
+
+/// Экранирует id для атрибута `id` и CSS селектора `#id`
+///
+/// Буквы (включая не-ASCII), цифры, `_` и `-` сохраняются, остальные символы
+/// заменяются кодом: `Alice Smith` → `Alice_20_Smith`. Id, начинающийся
+/// не с буквы, получает префикс `_`.
+pub fn escape_id(raw: &str) -> String {
+    let mut escaped = String::with_capacity(raw.len());
+    for c in raw.chars() {
+        if c.is_alphanumeric() || c == '_' || c == '-' {
+            escaped.push(c);
+        } else {
+            escaped.push_str(&format!("_{:x}_", c as u32));
+        }
+    }
+    if !escaped.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+        escaped.insert(0, '_');
+    }
+    escaped
+}
+
+/// Выдаёт уникальные id элементам в порядке рендеринга
+#[derive(Debug, Default)]
+pub struct ElementIds {
+    used: HashSet<String>,
+}
+
+impl ElementIds {
+    /// Создаёт пустой набор id
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Стабильный уникальный id элемента
+    pub fn assign(&mut self, element: &LayoutElement) -> String {
+        let base = match &element.element_type {
+            // Классы в layout называются по имени — добавляем вид
+            ElementType::ClassBox { classifier_type, .. }
+                if !element.id.starts_with(&format!("{}_", classifier_kind(*classifier_type))) =>
+            {
+                escape_id(&format!("{}_{}", classifier_kind(*classifier_type), element.id))
+            }
+            _ => escape_id(&element.id),
+        };
+
+        let mut id = base.clone();
+        let mut index = 1;
+        while !self.used.insert(id.clone()) {
+            index += 1;
+            id = format!("{}-{}", base, index);
+        }
+        id
+    }
+}
+
+/// Элементы layout с id их групп в SVG
+///
+/// Повторяет обход `SvgRenderer`: элементы по слоям `ZLayer`, дочерние
+/// элементы групп и секций фрагментов сразу после родителя.
+pub fn rendered_ids(layout: &LayoutResult) -> Vec<(String, &LayoutElement)> {
+    fn visit<'a>(
+        element: &'a LayoutElement,
+        ids: &mut ElementIds,
+        result: &mut Vec<(String, &'a LayoutElement)>,
+    ) {
+        result.push((ids.assign(element), element));
+        match &element.element_type {
+            ElementType::Group { children, .. } => {
+                for child in children {
+                    visit(child, ids, result);
+                }
+            }
+            ElementType::Fragment { sections, .. } => {
+                for child in sections.iter().flat_map(|section| &section.children) {
+                    visit(child, ids, result);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut sorted: Vec<_> = layout.elements.iter().collect();
+    sorted.sort_by_key(|element| ZLayer::from_element(element));
+
+    let mut ids = ElementIds::new();
+    let mut result = Vec::new();
+    for element in sorted {
+        visit(element, &mut ids, &mut result);
+    }
+    result
+}
+
+/// CSS классы элемента: категория, вид и модификаторы
+///
+/// Элементы, которые называются по имени из диаграммы (классы, состояния,
+/// актёры, пакеты), классифицируются по типу: класс `today` — это класс,
+/// а не отметка сегодняшнего дня. Остальные — по id, который дал движок.
+pub fn element_classes(element: &LayoutElement) -> String {
+    let (category, kind) = named_kind(&element.element_type)
+        .or_else(|| engine_id_kind(&element.id))
+        .unwrap_or_else(|| type_kind(&element.element_type));
+
+    let mut classes = vec![category, kind];
+    if element.id.starts_with("return_") {
+        classes.push("return");
+    }
+    if element.id.starts_with("footer_") {
+        classes.push("footer");
+    }
+    if let ElementType::Edge {
+        dashed, edge_type, ..
+    } = &element.element_type
+    {
+        if kind == "relation" {
+            classes.push(edge_kind(*edge_type));
+        }
+        if *dashed {
+            classes.push("dashed");
+        }
+    }
+    classes.join(" ")
+}
+
+/// Категория и вид элемента с пользовательским именем вместо id
+fn named_kind(element_type: &ElementType) -> Option<(&'static str, &'static str)> {
+    match element_type {
+        ElementType::ClassBox { .. }
+        | ElementType::State { .. }
+        | ElementType::CompositeState { .. }
+        | ElementType::Actor { .. }
+        | ElementType::System { .. }
+        | ElementType::Group { .. }
+        | ElementType::Swimlane { .. }
+        | ElementType::Package { .. } => Some(type_kind(element_type)),
+        _ => None,
+    }
+}
+
+/// Категория и вид по id движка
+///
+/// Префиксы с `_` (`msg_`) сравниваются как префиксы, а одиночные id
+/// (`title`, `legend`, `today`) — целиком или с производным суффиксом
+/// через `-` (`legend-frame`): `titleService` и `legendary` — не они.
+fn engine_id_kind(id: &str) -> Option<(&'static str, &'static str)> {
+    ID_KINDS
+        .iter()
+        .find(|(prefix, _, _)| {
+            if prefix.ends_with('_') {
+                id.starts_with(prefix)
+            } else {
+                id.strip_prefix(prefix)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('-'))
+            }
+        })
+        .map(|(_, category, kind)| (*category, *kind))
+}
+
+/// Категория и вид по типу элемента
+fn type_kind(element_type: &ElementType) -> (&'static str, &'static str) {
+    match element_type {
+        ElementType::Rectangle { .. } | ElementType::RoundedRectangle => ("entity", "rectangle"),
+        ElementType::Ellipse { .. } => ("entity", "ellipse"),
+        ElementType::InitialState => ("entity", "initial"),
+        ElementType::FinalState => ("entity", "final"),
+        ElementType::State { .. } => ("entity", "state"),
+        ElementType::CompositeState { .. } => ("cluster", "state"),
+        ElementType::Actor { .. } => ("entity", "actor"),
+        ElementType::System { .. } => ("cluster", "system"),
+        ElementType::Edge { .. } => ("link", "relation"),
+        ElementType::Path => ("link", "path"),
+        ElementType::Text { .. } => ("label", "text"),
+        ElementType::Group { kind, .. } => ("cluster", container_kind(*kind)),
+        ElementType::Fragment { .. } => ("cluster", "fragment"),
+        ElementType::Activation => ("decoration", "activation"),
+        ElementType::ClassBox {
+            classifier_type, ..
+        } => ("entity", classifier_kind(*classifier_type)),
+        ElementType::Diamond { .. } => ("entity", "diamond"),
+        ElementType::Swimlane { .. } => ("cluster", "swimlane"),
+        ElementType::Package { .. } => ("cluster", "package"),
+        ElementType::ParticipantBox => ("cluster", "box"),
+    }
+}
+
+/// Вид классификатора
+fn classifier_kind(kind: ClassifierKind) -> &'static str {
+    match kind {
+        ClassifierKind::Class => "class",
+        ClassifierKind::Interface => "interface",
+        ClassifierKind::AbstractClass => "abstract",
+        ClassifierKind::Enum => "enum",
+        ClassifierKind::Annotation => "annotation",
+        ClassifierKind::Entity => "entity",
+    }
+}
+
+/// Вид контейнера
+fn container_kind(kind: ContainerKind) -> &'static str {
+    match kind {
+        ContainerKind::Rectangle => "rectangle",
+        ContainerKind::Package => "package",
+        ContainerKind::Node => "node",
+        ContainerKind::Folder => "folder",
+        ContainerKind::Frame => "frame",
+        ContainerKind::Cloud => "cloud",
+        ContainerKind::Database => "database",
+        ContainerKind::Storage => "storage",
+        ContainerKind::Queue => "queue",
+    }
+}
+
+/// Вид связи
+fn edge_kind(edge_type: EdgeType) -> &'static str {
+    match edge_type {
+        EdgeType::Association => "association",
+        EdgeType::Inheritance => "inheritance",
+        EdgeType::Realization => "realization",
+        EdgeType::Composition => "composition",
+        EdgeType::Aggregation => "aggregation",
+        EdgeType::Dependency => "dependency",
+        EdgeType::Link => "plain",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plantuml_layout::Rect;
+
+    fn element(id: &str, element_type: ElementType) -> LayoutElement {
+        LayoutElement::new(id, Rect::new(0.0, 0.0, 10.0, 10.0), element_type)
+    }
+
+    fn edge(id: &str, dashed: bool, edge_type: EdgeType) -> LayoutElement {
+        element(
+            id,
+            ElementType::Edge {
+                points: Vec::new(),
+                label: None,
+                arrow_start: false,
+                arrow_end: true,
+                dashed,
+                edge_type,
+                from_cardinality: None,
+                to_cardinality: None,
+            },
+        )
+    }
+
+    #[test]
+    fn test_escape_id() {
+        assert_eq!(escape_id("participant_Alice"), "participant_Alice");
+        assert_eq!(escape_id("Alice Smith"), "Alice_20_Smith");
+        assert_eq!(escape_id("a.b<c>"), "a_2e_b_3c_c_3e_");
+        assert_eq!(escape_id("Пользователь"), "Пользователь");
+        assert_eq!(escape_id("1st"), "_1st");
+    }
+
+    #[test]
+    fn test_unique_ids() {
+        let mut ids = ElementIds::new();
+        let message = edge("msg_Alice_Bob", false, EdgeType::Association);
+        assert_eq!(ids.assign(&message), "msg_Alice_Bob");
+        assert_eq!(ids.assign(&message), "msg_Alice_Bob-2");
+        assert_eq!(ids.assign(&message), "msg_Alice_Bob-3");
+
+        let class = element(
+            "User",
+            ElementType::ClassBox {
+                classifier_type: ClassifierKind::Interface,
+                name: "User".to_string(),
+                stereotype: None,
+                fields: Vec::new(),
+                methods: Vec::new(),
+                show_circle: true,
+                show_fields: true,
+                show_methods: true,
+            },
+        );
+        assert_eq!(ids.assign(&class), "interface_User");
+        assert_eq!(element_classes(&class), "entity interface");
+    }
+
+    #[test]
+    fn test_element_classes() {
+        let participant = element(
+            "participant_Alice",
+            ElementType::Rectangle {
+                label: "Alice".to_string(),
+                corner_radius: 2.5,
+            },
+        );
+        assert_eq!(element_classes(&participant), "entity participant");
+        assert_eq!(
+            element_classes(&edge("return_Bob_Alice", true, EdgeType::Association)),
+            "link message return dashed"
+        );
+        assert_eq!(
+            element_classes(&edge("edge_A_B", true, EdgeType::Realization)),
+            "link relation realization dashed"
+        );
+        assert_eq!(
+            element_classes(&element("title", ElementType::Text { text: "T".to_string(), font_size: 14.0 })),
+            "label title"
+        );
+    }
+
+    #[test]
+    fn test_element_classes_of_named_elements() {
+        let class = |name: &str| {
+            element(
+                name,
+                ElementType::ClassBox {
+                    classifier_type: ClassifierKind::Class,
+                    name: name.to_string(),
+                    stereotype: None,
+                    fields: Vec::new(),
+                    methods: Vec::new(),
+                    show_circle: true,
+                    show_fields: true,
+                    show_methods: true,
+                },
+            )
+        };
+        let rectangle = |id: &str| {
+            element(
+                id,
+                ElementType::Rectangle {
+                    label: id.to_string(),
+                    corner_radius: 0.0,
+                },
+            )
+        };
+
+        // Имя класса совпадает с id движка или его префиксом
+        assert_eq!(element_classes(&class("today")), "entity class");
+        assert_eq!(element_classes(&class("msg_Queue")), "entity class");
+        // Одиночные id движка — только целиком
+        assert_eq!(element_classes(&rectangle("titleService")), "entity rectangle");
+        assert_eq!(element_classes(&rectangle("legendary")), "entity rectangle");
+        assert_eq!(element_classes(&rectangle("today")), "decoration today");
+        assert_eq!(element_classes(&rectangle("legend-frame")), "label legend");
+    }
+}
@@ -0,0 +1,386 @@
+//! Оформление фигур по теме: толщина линий, тени и рукописный стиль
+//!
+//! Рендерер строит обычные SVG фигуры и перед добавлением в группу пропускает
+//! их через [`outline`] (контуры узлов) или [`stroke`] (линии без тени):
+//! - `stroke-width` умножается на `Theme::line_width`;
+//! - при `Theme::handwritten` фигура становится `<path>` с дрожащими
+//!   вершинами и изогнутыми сторонами. Генератор случайных чисел засевается
+//!   геометрией фигуры, поэтому одинаковый исходник даёт одинаковый SVG;
+//! - при `Theme::shadow` залитые контуры получают фильтр [`shadow_filter`].
+
+use std::ops::Deref;
+
+use svg::node::element::{Element, Filter, FilterEffectDropShadow, Path};
+use svg::Node;
+
+use plantuml_themes::Theme;
+
+/// Id фильтра тени в `<defs>`
+pub const SHADOW_FILTER_ID: &str = "shadow";
+
+/// Атрибуты геометрии, которые заменяет `d` рукописного пути
+const GEOMETRY: &[&str] = &[
+    "x", "y", "width", "height", "rx", "ry", "cx", "cy", "r", "x1", "y1", "x2", "y2", "points", "d",
+];
+
+/// Фильтр тени для `<defs>`: размытая смещённая копия контура
+pub fn shadow_filter() -> Filter {
+    Filter::new()
+        .set("id", SHADOW_FILTER_ID)
+        .set("x", "-20%")
+        .set("y", "-20%")
+        .set("width", "150%")
+        .set("height", "150%")
+        .add(
+            FilterEffectDropShadow::new()
+                .set("dx", 3)
+                .set("dy", 3)
+                .set("stdDeviation", 2)
+                .set("flood-color", "#000000")
+                .set("flood-opacity", 0.3),
+        )
+}
+
+/// Контур узла: толщина линии, рукописный стиль и тень
+pub fn outline<N>(node: N, theme: &Theme) -> Box<dyn Node>
+where
+    N: Node + Deref<Target = Element>,
+{
+    styled(node, theme, theme.shadow)
+}
+
+/// Линия или вспомогательная фигура: толщина и рукописный стиль, без тени
+pub fn stroke<N>(node: N, theme: &Theme) -> Box<dyn Node>
+where
+    N: Node + Deref<Target = Element>,
+{
+    styled(node, theme, false)
+}
+
+fn styled<N>(mut node: N, theme: &Theme, shadow: bool) -> Box<dyn Node>
+where
+    N: Node + Deref<Target = Element>,
+{
+    let attributes = node.get_attributes();
+    let stroke_width = attributes
+        .get("stroke-width")
+        .and_then(|width| width.parse::<f64>().ok());
+    let filled = attributes
+        .get("fill")
+        .is_some_and(|fill| !matches!(&**fill, "none" | "transparent"));
+
+    if let Some(width) = stroke_width {
+        if theme.line_width != 1.0 {
+            node.assign("stroke-width", round(width * theme.line_width));
+        }
+    }
+    if shadow && filled {
+        node.assign("filter", format!("url(#{})", SHADOW_FILTER_ID));
+    }
+    if !theme.handwritten {
+        return Box::new(node);
+    }
+
+    let Some(d) = sketch_outline(&node) else {
+        return Box::new(node);
+    };
+    let path = node
+        .get_attributes()
+        .iter()
+        .filter(|(name, _)| !GEOMETRY.contains(&name.as_str()))
+        .fold(Path::new(), |path, (name, value)| path.set(name.as_str(), value.clone()))
+        .set("d", d)
+        .set("stroke-linecap", "round")
+        .set("stroke-linejoin", "round");
+    Box::new(path)
+}
+
+/// Рукописный `d` для фигуры или `None`, если фигуру нельзя перерисовать
+fn sketch_outline(element: &Element) -> Option<String> {
+    let attributes = element.get_attributes();
+    let number = |name: &str| attributes.get(name).and_then(|value| value.parse::<f64>().ok());
+    let mut rng = Rng::seeded(element);
+
+    match element.get_name().as_str() {
+        "rect" => {
+            let (x, y) = (number("x")?, number("y")?);
+            let (width, height) = (number("width")?, number("height")?);
+            let radius = number("rx").unwrap_or(0.0);
+            Some(rng.polyline(&rounded_rect(x, y, width, height, radius), true))
+        }
+        "ellipse" | "circle" => {
+            let (cx, cy) = (number("cx")?, number("cy")?);
+            let (rx, ry) = match number("r") {
+                Some(r) => (r, r),
+                None => (number("rx")?, number("ry")?),
+            };
+            Some(rng.ellipse(cx, cy, rx, ry))
+        }
+        "line" => {
+            let points = [(number("x1")?, number("y1")?), (number("x2")?, number("y2")?)];
+            Some(rng.polyline(&points, false))
+        }
+        "polygon" => {
+            let points = pairs(&numbers(attributes.get("points")?)?)?;
+            Some(rng.polyline(&points, true))
+        }
+        "path" => {
+            let mut d = String::new();
+            for (points, closed) in subpaths(attributes.get("d")?)? {
+                d.push_str(&rng.polyline(&points, closed));
+            }
+            Some(d)
+        }
+        _ => None,
+    }
+}
+
+/// Вершины прямоугольника; скруглённые углы — дугами из нескольких точек
+fn rounded_rect(x: f64, y: f64, width: f64, height: f64, radius: f64) -> Vec<(f64, f64)> {
+    let radius = radius.min(width / 2.0).min(height / 2.0).max(0.0);
+    if radius == 0.0 {
+        return vec![(x, y), (x + width, y), (x + width, y + height), (x, y + height)];
+    }
+
+    // Центры дуг и начальные углы по часовой стрелке от верхнего правого
+    let corners = [
+        (x + width - radius, y + radius, -90.0_f64),
+        (x + width - radius, y + height - radius, 0.0),
+        (x + radius, y + height - radius, 90.0),
+        (x + radius, y + radius, 180.0),
+    ];
+    corners
+        .iter()
+        .flat_map(|&(cx, cy, start)| {
+            (0..=2).map(move |step| {
+                let angle = (start + step as f64 * 45.0).to_radians();
+                (cx + radius * angle.cos(), cy + radius * angle.sin())
+            })
+        })
+        .collect()
+}
+
+/// Подпуть: вершины и признак замкнутости
+type Subpath = (Vec<(f64, f64)>, bool);
+
+/// Подпути `d` из абсолютных команд `M`, `L` и `Z`
+///
+/// Кривые и дуги не перерисовываются: для них `None`.
+fn subpaths(d: &str) -> Option<Vec<Subpath>> {
+    let spaced: String = d
+        .chars()
+        .flat_map(|c| match c {
+            'A'..='Z' | 'a'..='z' => vec![' ', c, ' '],
+            ',' => vec![' '],
+            _ => vec![c],
+        })
+        .collect();
+
+    let mut subpaths: Vec<Subpath> = Vec::new();
+    let mut tokens = spaced.split_whitespace().peekable();
+    while let Some(token) = tokens.next() {
+        match token {
+            "M" => subpaths.push((Vec::new(), false)),
+            "L" => {}
+            "Z" => {
+                subpaths.last_mut()?.1 = true;
+                continue;
+            }
+            _ => return None,
+        }
+        // Пары координат до следующей команды
+        while let Some(x) = tokens.peek().and_then(|token| token.parse::<f64>().ok()) {
+            tokens.next();
+            let y = tokens.next()?.parse::<f64>().ok()?;
+            subpaths.last_mut()?.0.push((x, y));
+        }
+    }
+    subpaths.retain(|(points, _)| points.len() > 1);
+    (!subpaths.is_empty()).then_some(subpaths)
+}
+
+/// Числа списка `x,y x,y`
+fn numbers(list: &str) -> Option<Vec<f64>> {
+    list.split([' ', ','])
+        .filter(|token| !token.is_empty())
+        .map(|token| token.parse().ok())
+        .collect()
+}
+
+/// Точки из плоского списка координат
+fn pairs(numbers: &[f64]) -> Option<Vec<(f64, f64)>> {
+    (numbers.len() >= 4 && numbers.len() % 2 == 0)
+        .then(|| numbers.chunks(2).map(|pair| (pair[0], pair[1])).collect())
+}
+
+/// Округляет до сотых, чтобы SVG оставался компактным
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// Генератор xorshift64*, засеянный геометрией фигуры
+struct Rng(u64);
+
+impl Rng {
+    /// Засевает генератор именем и атрибутами геометрии (FNV-1a)
+    fn seeded(element: &Element) -> Self {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut feed = |text: &str| {
+            for byte in text.bytes() {
+                hash ^= u64::from(byte);
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
+            }
+        };
+        feed(element.get_name());
+        let attributes = element.get_attributes();
+        for name in GEOMETRY {
+            if let Some(value) = attributes.get(*name) {
+                feed(name);
+                feed(value);
+            }
+        }
+        Self(hash.max(1))
+    }
+
+    /// Следующее число в `[0, 1)`
+    fn next(&mut self) -> f64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        (self.0.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Случайное смещение в `[-amount, amount]`
+    fn offset(&mut self, amount: f64) -> f64 {
+        (self.next() * 2.0 - 1.0) * amount
+    }
+
+    /// Ломаная от руки: вершины дрожат, стороны слегка выгибаются
+    ///
+    /// Замкнутая ломаная возвращается к первой вершине с новым смещением —
+    /// как незамкнутый штрих пера.
+    fn polyline(&mut self, points: &[(f64, f64)], closed: bool) -> String {
+        let mut points = points.to_vec();
+        if closed {
+            points.push(points[0]);
+        }
+        let vertices: Vec<(f64, f64)> = points
+            .iter()
+            .map(|(x, y)| (x + self.offset(1.0), y + self.offset(1.0)))
+            .collect();
+
+        let mut d = format!("M{},{}", round(vertices[0].0), round(vertices[0].1));
+        for pair in vertices.windows(2) {
+            let ((x1, y1), (x2, y2)) = (pair[0], pair[1]);
+            let length = (x2 - x1).hypot(y2 - y1).max(f64::EPSILON);
+            // Изгиб поперёк стороны, пропорциональный её длине
+            let bow = self.offset((length * 0.015).clamp(0.2, 2.0));
+            let (nx, ny) = (-(y2 - y1) / length, (x2 - x1) / length);
+            let (mx, my) = ((x1 + x2) / 2.0 + nx * bow, (y1 + y2) / 2.0 + ny * bow);
+            d.push_str(&format!(" Q{},{} {},{}", round(mx), round(my), round(x2), round(y2)));
+        }
+        d
+    }
+
+    /// Эллипс от руки: кривая через точки с дрожащим радиусом
+    fn ellipse(&mut self, cx: f64, cy: f64, rx: f64, ry: f64) -> String {
+        const STEPS: usize = 16;
+        let start = self.next() * std::f64::consts::TAU;
+        let jitter = (rx.max(ry) * 0.04).clamp(0.2, 1.5);
+        let points: Vec<(f64, f64)> = (0..STEPS)
+            .map(|step| {
+                let angle = start + step as f64 * std::f64::consts::TAU / STEPS as f64;
+                (
+                    cx + (rx + self.offset(jitter)) * angle.cos(),
+                    cy + (ry + self.offset(jitter)) * angle.sin(),
+                )
+            })
+            .collect();
+
+        // Сглаживание: квадратичные кривые между серединами соседних точек
+        let middle = |a: (f64, f64), b: (f64, f64)| ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0);
+        let first = middle(points[STEPS - 1], points[0]);
+        let mut d = format!("M{},{}", round(first.0), round(first.1));
+        for i in 0..STEPS {
+            let (control, end) = (points[i], middle(points[i], points[(i + 1) % STEPS]));
+            d.push_str(&format!(
+                " Q{},{} {},{}",
+                round(control.0),
+                round(control.1),
+                round(end.0),
+                round(end.1)
+            ));
+        }
+        d.push_str(" Z");
+        d
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use svg::node::element::{Line, Rectangle};
+
+    fn rect() -> Rectangle {
+        Rectangle::new()
+            .set("x", 10)
+            .set("y", 10)
+            .set("width", 80)
+            .set("height", 40)
+            .set("rx", 2.5)
+            .set("fill", "#E2E2F0")
+            .set("stroke", "#181818")
+            .set("stroke-width", 0.5)
+    }
+
+    #[test]
+    fn test_default_theme_keeps_shapes() {
+        let theme = Theme::default();
+        assert_eq!(outline(rect(), &theme).to_string(), rect().to_string());
+    }
+
+    #[test]
+    fn test_line_width_and_shadow() {
+        let theme = Theme {
+            line_width: 2.0,
+            shadow: true,
+            ..Theme::default()
+        };
+        let svg = outline(rect(), &theme).to_string();
+        assert!(svg.contains(r#"stroke-width="1""#));
+        assert!(svg.contains(r#"filter="url(#shadow)""#));
+
+        // Линии без заливки и вспомогательные фигуры тени не получают
+        let line = Line::new().set("x1", 0).set("y1", 0).set("x2", 10).set("y2", 0).set("stroke-width", 1);
+        assert!(!outline(line, &theme).to_string().contains("filter"));
+        assert!(!stroke(rect(), &theme).to_string().contains("filter"));
+    }
+
+    #[test]
+    fn test_handwritten_is_deterministic() {
+        let theme = Theme::sketchy();
+        let first = outline(rect(), &theme).to_string();
+        assert!(first.starts_with("<path"));
+        assert!(first.contains(r##"fill="#E2E2F0""##));
+        assert!(!first.contains(r#"width="80""#));
+        assert_eq!(first, outline(rect(), &theme).to_string());
+
+        // Пути из отрезков перерисовываются, дуги остаются как есть
+        let path = Path::new().set("d", "M0,0 L50,0 L50,20 Z").set("fill", "none");
+        assert!(outline(path, &theme).to_string().contains(" Q"));
+        let arc = Path::new().set("d", "M0,0 a4,4 0 1,0 8,0").set("fill", "none");
+        assert!(outline(arc, &theme).to_string().contains("a4,4"));
+    }
+
+    #[test]
+    fn test_subpaths() {
+        assert_eq!(
+            subpaths("M0,0 L10,0 L10,5 Z M1,1 L2,2"),
+            Some(vec![
+                (vec![(0.0, 0.0), (10.0, 0.0), (10.0, 5.0)], true),
+                (vec![(1.0, 1.0), (2.0, 2.0)], false),
+            ])
+        );
+        assert_eq!(subpaths("M0,0 C1,1 2,2 3,3"), None);
+    }
+}
@@ -0,0 +1,175 @@
+//! skinparam отдельных элементов: `ParticipantBorderColor`, `NoteBackgroundColor`,
+//! `ClassBackgroundColor<<Service>>`
+//!
+//! Имя элемента skinparam определяется по префиксу id элемента layout или по
+//! его типу, стереотипы — по свойству `stereotype`. Тема элемента строится
+//! [`SkinParams::element_theme`] и разрешается при рендеринге каждого
+//! элемента, а не сводится в общую тему диаграммы.
+
+use plantuml_layout::{ContainerKind, ElementType, LayoutElement};
+use plantuml_themes::{SkinParams, Theme};
+
+/// Имена элементов skinparam по префиксу id
+///
+/// Более длинные префиксы стоят раньше совпадающих с ними коротких.
+const ID_SKINS: &[(&str, &[&str])] = &[
+    ("participant_label_", &[]),
+    ("participant_", &["participant"]),
+    ("footer_", &["participant"]),
+    ("lifeline_", &["lifeline"]),
+    ("activation_", &["lifeline", "activation"]),
+    ("msg_", &["arrow"]),
+    ("return_", &["arrow"]),
+    ("note_", &["note"]),
+    ("fragment_", &["group"]),
+    ("box_", &["box"]),
+    ("divider_", &["divider"]),
+    ("transition_", &["arrow"]),
+    ("trans_", &["arrow"]),
+    ("inner_trans_", &["arrow"]),
+    ("edge_", &["arrow"]),
+    ("rel_", &["arrow"]),
+    ("link_", &["arrow"]),
+    ("conn_", &["arrow"]),
+    ("arrow_", &["arrow"]),
+    ("dependency_", &["arrow"]),
+    ("action_", &["activity"]),
+    ("swimlane_", &["swimlane"]),
+    ("component_", &["component"]),
+    ("database_", &["database"]),
+    ("cloud_", &["cloud"]),
+    ("interface_", &["interface"]),
+    ("queue_", &["queue"]),
+    ("node_", &["node"]),
+    ("folder_", &["folder"]),
+    ("actor_", &["actor"]),
+    ("package_", &["package"]),
+    ("usecase_", &["usecase"]),
+    ("system_", &["rectangle"]),
+    ("object_", &["object"]),
+    ("state_label_", &[]),
+    ("state_", &["state"]),
+    ("inner_state_", &["state"]),
+    ("composite_", &["state"]),
+];
+
+/// Тема элемента с его skinparam; `None`, если параметров для него нет
+pub fn element_theme(params: &SkinParams, theme: &Theme, element: &LayoutElement) -> Option<Theme> {
+    if params.is_empty() {
+        return None;
+    }
+    let skins = element_skins(element);
+    if skins.is_empty() {
+        return None;
+    }
+    params.element_theme(theme, skins, &element.stereotypes())
+}
+
+/// Имена элемента skinparam от общего к частному
+fn element_skins(element: &LayoutElement) -> &'static [&'static str] {
+    // Участник-актёр настраивается параметрами `Actor*`
+    if let ElementType::Actor { .. } = element.element_type {
+        return &["actor"];
+    }
+    // Контейнер настраивается параметрами своего вида: `NodeBackgroundColor`
+    if let ElementType::Group { kind, .. } = element.element_type {
+        return container_skins(kind);
+    }
+    ID_SKINS
+        .iter()
+        .find(|(prefix, _)| element.id.starts_with(prefix))
+        .map(|(_, skins)| *skins)
+        .unwrap_or_else(|| type_skins(&element.element_type))
+}
+
+/// Имена контейнера skinparam по его виду
+fn container_skins(kind: ContainerKind) -> &'static [&'static str] {
+    match kind {
+        ContainerKind::Rectangle => &["rectangle"],
+        ContainerKind::Package => &["package"],
+        ContainerKind::Node => &["node"],
+        ContainerKind::Folder => &["folder"],
+        ContainerKind::Frame => &["frame"],
+        ContainerKind::Cloud => &["cloud"],
+        ContainerKind::Database => &["database"],
+        ContainerKind::Storage => &["storage"],
+        ContainerKind::Queue => &["queue"],
+    }
+}
+
+/// Имена элемента skinparam по типу элемента
+fn type_skins(element_type: &ElementType) -> &'static [&'static str] {
+    match element_type {
+        ElementType::State { .. } | ElementType::CompositeState { .. } => &["state"],
+        ElementType::Ellipse { .. } => &["usecase"],
+        ElementType::System { .. } => &["rectangle"],
+        ElementType::ClassBox { .. } => &["class"],
+        ElementType::Package { .. } => &["package"],
+        ElementType::Diamond { .. } => &["activitydiamond"],
+        ElementType::Swimlane { .. } => &["swimlane"],
+        ElementType::Fragment { .. } => &["group"],
+        ElementType::ParticipantBox => &["box"],
+        ElementType::Edge { .. } => &["arrow"],
+        _ => &[],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plantuml_ast::common::Stereotype;
+    use plantuml_layout::Rect;
+
+    fn element(id: &str, element_type: ElementType) -> LayoutElement {
+        LayoutElement::new(id, Rect::new(0.0, 0.0, 10.0, 10.0), element_type)
+    }
+
+    #[test]
+    fn test_element_theme_by_id_and_stereotype() {
+        let mut params = SkinParams::new();
+        params.set("ParticipantBorderColor", "red");
+        params.set("ActorBackgroundColor", "yellow");
+        params.set("ClassBackgroundColor<<Service>>", "Wheat");
+        let theme = Theme::default();
+
+        let rect = ElementType::Rectangle {
+            label: "Alice".into(),
+            corner_radius: 2.5,
+        };
+        let participant = element_theme(&params, &theme, &element("participant_Alice", rect.clone())).unwrap();
+        assert_eq!(participant.node_border.to_css(), "red");
+        assert!(element_theme(&params, &theme, &element("participant_label_Alice", rect)).is_none());
+
+        let actor = element("participant_Bob", ElementType::Actor { label: "Bob".into() });
+        assert_eq!(element_theme(&params, &theme, &actor).unwrap().node_background.to_css(), "yellow");
+
+        let class = element(
+            "Foo",
+            ElementType::ClassBox {
+                classifier_type: plantuml_layout::ClassifierKind::Class,
+                name: "Foo".into(),
+                stereotype: None,
+                fields: Vec::new(),
+                methods: Vec::new(),
+                show_circle: true,
+                show_fields: true,
+                show_methods: true,
+            },
+        );
+        assert!(element_theme(&params, &theme, &class).is_none());
+        let service = class.with_stereotype(Some(&Stereotype::new("Service")));
+        assert_eq!(element_theme(&params, &theme, &service).unwrap().node_background.to_css(), "Wheat");
+
+        // Контейнер — по своему виду, а не по префиксу id `package_`
+        params.set("NodeBackgroundColor", "Khaki");
+        let node = element(
+            "package_Server",
+            ElementType::Group {
+                label: Some("Server".into()),
+                children: Vec::new(),
+                kind: ContainerKind::Node,
+            },
+        );
+        assert_eq!(element_theme(&params, &theme, &node).unwrap().node_background.to_css(), "Khaki");
+    }
+}
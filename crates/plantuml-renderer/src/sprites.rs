@@ -0,0 +1,100 @@
+//! Отрисовка спрайтов
+//!
+//! Спрайты в оттенках серого рисуются группой `<rect>` по сериям одинаковых
+//! пикселей строки: цвет задаёт `fill` группы, уровень серого — прозрачность.
+//! SVG спрайты вставляются как вложенный `<svg>` со своим `viewBox`.
+
+use plantuml_ast::sprite::{Sprite, SpriteData};
+use svg::node::element::{Group, Rectangle, SVG};
+use svg::node::Blob;
+
+/// Рисует спрайт с левым верхним углом в `(x, y)`
+///
+/// `color` — цвет переднего плана для спрайтов в оттенках серого.
+pub fn sprite_node(sprite: &Sprite, x: f64, y: f64, scale: f64, color: &str) -> Group {
+    let group = Group::new().set("class", format!("sprite sprite-{}", sprite.name));
+
+    match &sprite.data {
+        SpriteData::Gray { levels, pixels } => {
+            let max = f64::from(levels.saturating_sub(1).max(1));
+            let mut body = Group::new()
+                .set("transform", format!("translate({},{}) scale({})", x, y, scale))
+                .set("fill", color)
+                .set("shape-rendering", "crispEdges");
+
+            for (row, line) in pixels.chunks(sprite.width.max(1) as usize).enumerate() {
+                let mut col = 0;
+                while col < line.len() {
+                    let level = line[col];
+                    let run = line[col..].iter().take_while(|l| **l == level).count();
+                    if level > 0 {
+                        let mut rect = Rectangle::new()
+                            .set("x", col)
+                            .set("y", row)
+                            .set("width", run)
+                            .set("height", 1);
+                        if f64::from(level) < max {
+                            let opacity = (f64::from(level) / max * 1000.0).round() / 1000.0;
+                            rect = rect.set("fill-opacity", opacity);
+                        }
+                        body = body.add(rect);
+                    }
+                    col += run;
+                }
+            }
+            group.add(body)
+        }
+        SpriteData::Svg { view_box, content } => group.add(
+            SVG::new()
+                .set("x", x)
+                .set("y", y)
+                .set("width", f64::from(sprite.width) * scale)
+                .set("height", f64::from(sprite.height) * scale)
+                .set("viewBox", view_box.as_str())
+                .add(Blob::new(content.as_str())),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gray_sprite_runs() {
+        let sprite = Sprite {
+            name: "bar".to_string(),
+            width: 4,
+            height: 1,
+            data: SpriteData::Gray {
+                levels: 16,
+                pixels: vec![15, 15, 0, 5],
+            },
+        };
+        let svg = sprite_node(&sprite, 10.0, 20.0, 2.0, "red").to_string();
+
+        assert!(svg.contains("translate(10,20) scale(2)"));
+        assert!(svg.contains("fill=\"red\""));
+        assert_eq!(svg.matches("<rect").count(), 2);
+        assert!(svg.contains("width=\"2\""));
+        assert!(svg.contains("fill-opacity=\"0.333\""));
+    }
+
+    #[test]
+    fn test_svg_sprite() {
+        let sprite = Sprite {
+            name: "dot".to_string(),
+            width: 16,
+            height: 16,
+            data: SpriteData::Svg {
+                view_box: "0 0 8 8".to_string(),
+                content: "<circle cx=\"4\" cy=\"4\" r=\"4\"/>".to_string(),
+            },
+        };
+        let svg = sprite_node(&sprite, 0.0, 0.0, 0.5, "black").to_string();
+
+        assert!(svg.contains("viewBox=\"0 0 8 8\""));
+        assert!(svg.contains("width=\"8\""));
+        assert!(svg.contains("<circle cx=\"4\" cy=\"4\" r=\"4\"/>"));
+    }
+}
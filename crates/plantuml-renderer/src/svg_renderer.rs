@@ -1,17 +1,96 @@
 //! SVG рендерер
 
-use svg::node::element::{Definitions, Group, Marker, Path, Rectangle};
+use svg::node::element::{
+    Anchor, Definitions, Group, LinearGradient, Marker, Path, Rectangle, Stop, TSpan, Text, Title,
+};
 use svg::Document;
 
+use crate::semantic::{element_classes, ElementIds};
+use crate::sketch::{outline, shadow_filter, stroke};
+use crate::skin;
+use crate::sprites::sprite_node;
 use crate::{
-    ClassMember, ClassifierKind, EdgeType, ElementType, FragmentSection, LayoutElement, LayoutResult, 
+    ClassMember, ClassifierKind, ContainerKind, EdgeType, ElementType, FragmentSection, LayoutElement, LayoutResult, 
     MemberVisibility, Point, Rect, RenderOptions, Renderer, ZLayer,
 };
-use plantuml_themes::Theme;
+use plantuml_ast::common::LineStyle;
+use plantuml_ast::creole::{has_markup, TextStyle};
+use plantuml_ast::sprite::{SpriteRef, SpriteTable};
+use plantuml_layout::routing::parse_position;
+use plantuml_layout::{
+    ElementStyle, GradientDirection, LabelLineKind, LabelSpan, Paint, TextMetrics, CELL_PADDING_X,
+    PROPERTY_FONT_WEIGHT, PROPERTY_END_DECORATION, PROPERTY_FROM_CARDINALITY_POSITION,
+    PROPERTY_LABEL_POSITION, PROPERTY_LINE_TYPE, PROPERTY_START_DECORATION, PROPERTY_TEXT_ANCHOR,
+    PROPERTY_TO_CARDINALITY_POSITION, tab_width,
+};
+use plantuml_themes::{Color as ThemeColor, SkinParams, Theme};
+
+/// Скругление состояний при радиусе темы по умолчанию
+const STATE_CORNER_RADIUS: f64 = 10.0;
+
+/// Скругление `RoundedRectangle` при радиусе темы по умолчанию
+const ROUNDED_CORNER_RADIUS: f64 = 8.0;
+
+/// Отступ viewBox от границ диаграммы (как в PlantUML)
+const DOCUMENT_MARGIN: f64 = 5.0;
+
+/// Размещение подписей и форма линии ребра, выбранные маршрутизатором
+#[derive(Debug, Clone, Copy, Default)]
+struct EdgePlacement {
+    /// Левый край и базовая линия подписи
+    label: Option<Point>,
+    /// Позиция кардинальности у начала
+    from_cardinality: Option<Point>,
+    /// Позиция кардинальности у конца
+    to_cardinality: Option<Point>,
+    /// Сглаживать путь кривой
+    spline: bool,
+    /// Украшение у начала (`o->`, `x->`)
+    start_decoration: Option<EndDecoration>,
+    /// Украшение у конца (`->o`, `->x`)
+    end_decoration: Option<EndDecoration>,
+}
+
+/// Украшение конца ребра
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EndDecoration {
+    /// Кружок за концом линии
+    Circle,
+    /// Крест на конце линии
+    Cross,
+}
+
+impl EndDecoration {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "circle" => Some(Self::Circle),
+            "cross" => Some(Self::Cross),
+            _ => None,
+        }
+    }
+}
+
+impl EdgePlacement {
+    fn from_element(element: &LayoutElement) -> Self {
+        let position = |key: &str| element.properties.get(key).and_then(|value| parse_position(value));
+        Self {
+            label: position(PROPERTY_LABEL_POSITION),
+            from_cardinality: position(PROPERTY_FROM_CARDINALITY_POSITION),
+            to_cardinality: position(PROPERTY_TO_CARDINALITY_POSITION),
+            spline: element.properties.get(PROPERTY_LINE_TYPE).is_some_and(|value| value == "spline"),
+            start_decoration: element.properties.get(PROPERTY_START_DECORATION).and_then(|value| EndDecoration::parse(value)),
+            end_decoration: element.properties.get(PROPERTY_END_DECORATION).and_then(|value| EndDecoration::parse(value)),
+        }
+    }
+}
 
 /// SVG рендерер
 pub struct SvgRenderer {
     options: RenderOptions,
+    /// Спрайты для ссылок `<$name>` в подписях
+    sprites: SpriteTable,
+    /// skinparam элементов и стереотипов (`ParticipantBorderColor`)
+    skin_params: SkinParams,
 }
 
 impl SvgRenderer {
@@ -19,12 +98,30 @@ impl SvgRenderer {
     pub fn new() -> Self {
         Self {
             options: RenderOptions::default(),
+            sprites: SpriteTable::new(),
+            skin_params: SkinParams::new(),
         }
     }
 
     /// Создаёт рендерер с опциями
     pub fn with_options(options: RenderOptions) -> Self {
-        Self { options }
+        Self {
+            options,
+            sprites: SpriteTable::new(),
+            skin_params: SkinParams::new(),
+        }
+    }
+
+    /// Устанавливает спрайты диаграммы (`sprite $name ...`)
+    pub fn with_sprites(mut self, sprites: SpriteTable) -> Self {
+        self.sprites = sprites;
+        self
+    }
+
+    /// Устанавливает skinparam, разрешаемые для каждого элемента
+    pub fn with_skin_params(mut self, skin_params: SkinParams) -> Self {
+        self.skin_params = skin_params;
+        self
     }
 
     /// Рендерит в строку
@@ -32,14 +129,39 @@ impl SvgRenderer {
         self.render(layout, theme)
     }
 
+    /// Масштаб документа: отношение его размера к размеру viewBox
+    fn document_scale(&self, bounds: &Rect) -> f64 {
+        self.options.effective_scale(
+            bounds.width + DOCUMENT_MARGIN * 2.0,
+            bounds.height + DOCUMENT_MARGIN * 2.0,
+        )
+    }
+
+    /// Переводит прямоугольник layout в пиксели SVG документа
+    ///
+    /// Учитывает смещение viewBox и масштаб (`scale`, `max_width`, ...).
+    pub fn to_document(&self, layout: &LayoutResult, rect: &Rect) -> Rect {
+        let bounds = &layout.bounds;
+        let scale = self.document_scale(bounds);
+        Rect::new(
+            (rect.x - bounds.x + DOCUMENT_MARGIN) * scale,
+            (rect.y - bounds.y + DOCUMENT_MARGIN) * scale,
+            rect.width * scale,
+            rect.height * scale,
+        )
+    }
+
     /// Создаёт SVG документ
     /// PlantUML стиль: прозрачный/белый фон БЕЗ рамки вокруг диаграммы
     fn create_document(&self, layout: &LayoutResult, theme: &Theme) -> Document {
         let bounds = &layout.bounds;
-        let margin = 5.0; // Минимальный отступ от края (как в PlantUML)
+        let margin = DOCUMENT_MARGIN;
 
-        let width = (bounds.width + margin * 2.0) * self.options.scale;
-        let height = (bounds.height + margin * 2.0) * self.options.scale;
+        let natural_width = bounds.width + margin * 2.0;
+        let natural_height = bounds.height + margin * 2.0;
+        let scale = self.document_scale(bounds);
+        let width = natural_width * scale;
+        let height = natural_height * scale;
 
         let mut doc = Document::new()
             .set("width", width)
@@ -55,6 +177,11 @@ impl SvgRenderer {
             )
             .set("xmlns", "http://www.w3.org/2000/svg");
 
+        // Пространство имён xlink нужно только для ссылок `[[url]]`
+        if has_links(&layout.elements) {
+            doc = doc.set("xmlns:xlink", "http://www.w3.org/1999/xlink");
+        }
+
         // PlantUML по умолчанию НЕ добавляет фон и рамку вокруг диаграммы
         // Фон добавляется только если явно указан через skinparam backgroundColor
         if let Some(bg) = &self.options.background_color {
@@ -110,7 +237,7 @@ impl SvgRenderer {
                     .set("d", "M0,0 L10,4 L0,8")
                     .set("fill", "none")
                     .set("stroke", arrow_color.as_str())
-                    .set("stroke-width", 1),
+                    .set("stroke-width", theme.line_width),
             );
 
         // Маркер наследования (пустой треугольник) - для --|> и ..|>
@@ -129,7 +256,7 @@ impl SvgRenderer {
                     .set("d", "M0,0 L20,10 L0,20 Z")
                     .set("fill", theme.background_color.to_css()) // белый внутри
                     .set("stroke", arrow_color.as_str())
-                    .set("stroke-width", 1),
+                    .set("stroke-width", theme.line_width),
             );
 
         // Маркер композиции (закрашенный ромб) - для *--
@@ -162,20 +289,48 @@ impl SvgRenderer {
                     .set("d", "M0,6 L6,0 L12,6 L6,12 Z")
                     .set("fill", theme.background_color.to_css()) // белый внутри
                     .set("stroke", arrow_color.as_str())
-                    .set("stroke-width", 1),
+                    .set("stroke-width", theme.line_width),
             );
 
-        Definitions::new()
+        let definitions = Definitions::new()
             .add(arrow_marker)
             .add(open_arrow_marker)
             .add(inheritance_marker)
             .add(composition_marker)
-            .add(aggregation_marker)
+            .add(aggregation_marker);
+
+        // Фильтр тени только для тем с `shadow`
+        if theme.shadow {
+            definitions.add(shadow_filter())
+        } else {
+            definitions
+        }
     }
 
     /// Рендерит элемент
-    fn render_element(&self, element: &LayoutElement, theme: &Theme) -> Group {
-        let mut group = Group::new().set("id", element.id.as_str());
+    fn render_element(&self, element: &LayoutElement, theme: &Theme, ids: &mut ElementIds) -> Group {
+        // Id родителя выдаётся раньше id дочерних элементов
+        let id = ids.assign(element);
+        let mut group = Group::new();
+
+        // skinparam элемента и его стереотипов накладываются на тему
+        let skin_theme = skin::element_theme(&self.skin_params, theme, element);
+        let theme = skin_theme.as_ref().unwrap_or(theme);
+
+        // Стиль элемента накладывается поверх; градиент — в собственных defs
+        let style = element.style();
+        let fill = style.fill.as_ref().map(|paint| paint_css(paint, &id));
+        let element_theme;
+        let theme = if style.is_empty() {
+            theme
+        } else {
+            element_theme = styled_theme(theme, &style, fill.as_deref());
+            &element_theme
+        };
+        if let Some(Paint::Gradient { from, to, direction }) = &style.fill {
+            group = group.add(gradient_definition(&id, from, to, *direction));
+        }
+        let dash = style.line_style.and_then(dash_array);
 
         match &element.element_type {
             ElementType::Rectangle {
@@ -226,23 +381,49 @@ impl SvgRenderer {
                     *edge_type,
                     from_cardinality.as_deref(),
                     to_cardinality.as_deref(),
+                    EdgePlacement::from_element(element),
                     theme,
                     group,
                 );
             }
             ElementType::Text { text, font_size } => {
-                group = self.render_text(&element.bounds, text, *font_size, theme, group);
+                let anchor = element.properties.get(PROPERTY_TEXT_ANCHOR).map(|s| s.as_str());
+                let weight = element.properties.get(PROPERTY_FONT_WEIGHT).map(|s| s.as_str());
+                group = self.render_text(
+                    &element.bounds,
+                    text,
+                    *font_size,
+                    anchor,
+                    weight,
+                    theme,
+                    group,
+                );
             }
-            ElementType::Group { label, children } => {
-                group =
-                    self.render_group(&element.bounds, label.as_deref(), children, theme, group);
+            ElementType::Group { label, children, kind } => {
+                group = self.render_group(
+                    &element.bounds,
+                    label.as_deref(),
+                    *kind,
+                    fill.as_deref(),
+                    theme,
+                    group,
+                );
+                for child in children {
+                    group = group.add(self.render_element(child, theme, ids));
+                }
             }
             ElementType::Fragment {
                 fragment_type,
                 sections,
             } => {
-                group =
-                    self.render_fragment(&element.bounds, fragment_type, sections, theme, group);
+                group = self.render_fragment(
+                    &element.bounds,
+                    fragment_type,
+                    sections,
+                    theme,
+                    ids,
+                    group,
+                );
             }
             ElementType::Activation => {
                 group = self.render_activation(&element.bounds, theme, group);
@@ -250,7 +431,7 @@ impl SvgRenderer {
             ElementType::RoundedRectangle => {
                 // Рендерим как прямоугольник со скруглёнными углами
                 let label = element.text.as_deref().unwrap_or("");
-                group = self.render_rectangle(&element.bounds, label, 8.0, theme, group);
+                group = self.render_rectangle(&element.bounds, label, ROUNDED_CORNER_RADIUS, theme, group);
             }
             ElementType::Path => {
                 // Рендерим SVG path (для кривых Безье)
@@ -260,7 +441,7 @@ impl SvgRenderer {
                         .set("fill", "none")
                         .set("stroke", theme.node_border.to_css())
                         .set("stroke-width", 1);
-                    group = group.add(path);
+                    group = group.add(stroke(path, theme));
                 }
             }
             ElementType::ClassBox {
@@ -269,29 +450,92 @@ impl SvgRenderer {
                 stereotype,
                 fields,
                 methods,
+                show_circle,
+                show_fields,
+                show_methods,
             } => {
                 group = self.render_class_box(
                     &element.bounds,
                     *classifier_type,
                     name,
                     stereotype.as_deref(),
-                    fields,
-                    methods,
+                    show_fields.then_some(fields.as_slice()),
+                    show_methods.then_some(methods.as_slice()),
+                    *show_circle,
+                    dash,
                     theme,
                     group,
                 );
             }
+            ElementType::Package {
+                name,
+                stereotype,
+                tab_width,
+                tab_height,
+            } => {
+                let color = fill.as_deref();
+                group = self.render_package(
+                    &element.bounds,
+                    name,
+                    stereotype.as_deref(),
+                    *tab_width,
+                    *tab_height,
+                    color,
+                    theme,
+                    group,
+                );
+            }
+            ElementType::Diamond { label } => {
+                group = self.render_diamond(&element.bounds, label.as_deref(), theme, group);
+            }
+            ElementType::Swimlane { title, header_height } => {
+                let color = fill.as_deref();
+                group = self.render_swimlane(&element.bounds, title, *header_height, color, theme, group);
+            }
             ElementType::ParticipantBox => {
                 // Рендерим box для группировки участников
                 let title = element.text.as_deref();
-                let color = element.properties.get("color").map(|s| s.as_str());
+                let color = fill.as_deref();
                 group = self.render_participant_box(&element.bounds, title, color, theme, group);
             }
         }
 
+        // У класса пунктир только у рамки: иконка и разделители остаются сплошными
+        let is_class_box = matches!(element.element_type, ElementType::ClassBox { .. });
+        if let Some(dash) = dash.filter(|_| !is_class_box) {
+            group = group.set("stroke-dasharray", dash);
+        }
+
+        let mut group = Self::wrap_link(element, group)
+            .set("id", id)
+            .set("class", element_classes(element));
+        if let Some(line) = element.source_line() {
+            group = group.set("data-source-line", line);
+        }
         group
     }
 
+    /// Оборачивает элемент в `<a>` с подсказкой `<title>`, если задана ссылка
+    fn wrap_link(element: &LayoutElement, group: Group) -> Group {
+        let Some(link) = element.link() else {
+            return group;
+        };
+
+        let mut anchor = Anchor::new();
+        if !link.url.is_empty() {
+            anchor = anchor
+                .set("href", link.url.as_str())
+                .set("xlink:href", link.url.as_str())
+                .set("target", "_top");
+        }
+        if let Some(tooltip) = &link.tooltip {
+            anchor = anchor
+                .set("xlink:title", tooltip.as_str())
+                .add(Title::new(tooltip.as_str()));
+        }
+        Group::new().add(anchor.add(group))
+    }
+
     /// Рендерит прямоугольник
     fn render_rectangle(
         &self,
@@ -307,13 +551,16 @@ impl SvgRenderer {
             .set("y", bounds.y)
             .set("width", bounds.width)
             .set("height", bounds.height)
-            .set("rx", corner_radius)
-            .set("ry", corner_radius)
+            .set("rx", rounding(corner_radius, theme))
+            .set("ry", rounding(corner_radius, theme))
             .set("fill", theme.node_background.to_css())
             .set("stroke", theme.node_border.to_css())
             .set("stroke-width", 0.5);
 
-        group = group.add(rect);
+        group = group.add(outline(rect, theme));
+        if label.is_empty() {
+            return group;
+        }
 
         // Текст по центру
         let text = svg::node::element::Text::new(label)
@@ -325,7 +572,7 @@ impl SvgRenderer {
             .set("font-size", theme.font_size)
             .set("fill", theme.text_color.to_css());
 
-        group.add(text)
+        self.add_label(group, text, label, theme)
     }
 
     /// Рендерит эллипс
@@ -350,7 +597,7 @@ impl SvgRenderer {
             .set("stroke", theme.node_border.to_css())
             .set("stroke-width", 1);
 
-        group = group.add(ellipse);
+        group = group.add(outline(ellipse, theme));
 
         if let Some(label) = label {
             let text = svg::node::element::Text::new(label)
@@ -362,7 +609,7 @@ impl SvgRenderer {
                 .set("font-size", theme.font_size)
                 .set("fill", theme.text_color.to_css());
 
-            group = group.add(text);
+            group = self.add_label(group, text, label, theme);
         }
 
         group
@@ -388,7 +635,7 @@ impl SvgRenderer {
             .set("fill", theme.node_border.to_css()) // чёрная заливка
             .set("stroke", "none");
 
-        group.add(circle)
+        group.add(outline(circle, theme))
     }
 
     /// Рендерит UML Final State (bullseye: внешний круг + внутренний заполненный круг)
@@ -413,7 +660,7 @@ impl SvgRenderer {
             .set("stroke", theme.node_border.to_css())
             .set("stroke-width", 1.5);
 
-        group = group.add(outer_circle);
+        group = group.add(outline(outer_circle, theme));
 
         // Внутренний круг (заполненный чёрный)
         let inner_circle = svg::node::element::Ellipse::new()
@@ -424,7 +671,7 @@ impl SvgRenderer {
             .set("fill", theme.node_border.to_css()) // чёрная заливка
             .set("stroke", "none");
 
-        group.add(inner_circle)
+        group.add(stroke(inner_circle, theme))
     }
 
     /// Рендерит UML State (скруглённый прямоугольник с разделителем и названием)
@@ -436,7 +683,7 @@ impl SvgRenderer {
         theme: &Theme,
         mut group: Group,
     ) -> Group {
-        let corner_radius = 10.0;
+        let corner_radius = rounding(STATE_CORNER_RADIUS, theme);
         let header_height = 25.0;
         
         // 1. Основной прямоугольник со скруглёнными углами
@@ -451,7 +698,7 @@ impl SvgRenderer {
             .set("stroke", theme.node_border.to_css())
             .set("stroke-width", 1);
 
-        group = group.add(rect);
+        group = group.add(outline(rect, theme));
 
         // 2. Название состояния (центрировано сверху)
         let name_y = bounds.y + header_height / 2.0 + 5.0;
@@ -465,7 +712,7 @@ impl SvgRenderer {
             .set("font-weight", "bold")
             .set("fill", theme.text_color.to_css());
 
-        group = group.add(name_text);
+        group = self.add_label(group, name_text, name, theme);
 
         // 3. Горизонтальный разделитель (UML style)
         let separator_y = bounds.y + header_height;
@@ -477,7 +724,7 @@ impl SvgRenderer {
             .set("stroke", theme.node_border.to_css())
             .set("stroke-width", 0.5);
 
-        group = group.add(separator);
+        group = group.add(stroke(separator, theme));
 
         // 4. Описание (entry/exit/do actions) если есть
         if let Some(desc) = description {
@@ -504,7 +751,7 @@ impl SvgRenderer {
         theme: &Theme,
         mut group: Group,
     ) -> Group {
-        let corner_radius = 10.0;
+        let corner_radius = rounding(STATE_CORNER_RADIUS, theme);
         
         // 1. Основной прямоугольник контейнера со скруглёнными углами
         let rect = Rectangle::new()
@@ -518,7 +765,7 @@ impl SvgRenderer {
             .set("stroke", theme.node_border.to_css())
             .set("stroke-width", 1.5);
 
-        group = group.add(rect);
+        group = group.add(outline(rect, theme));
 
         // 2. Заголовок состояния (название сверху, жирным, центрировано)
         let name_y = bounds.y + header_height / 2.0 + 2.0;
@@ -532,7 +779,7 @@ impl SvgRenderer {
             .set("font-weight", "bold")
             .set("fill", theme.text_color.to_css());
 
-        group = group.add(name_text);
+        group = self.add_label(group, name_text, name, theme);
 
         // 3. Горизонтальный разделитель под заголовком
         let separator_y = bounds.y + header_height;
@@ -544,7 +791,7 @@ impl SvgRenderer {
             .set("stroke", theme.node_border.to_css())
             .set("stroke-width", 1);
 
-        group = group.add(separator);
+        group = group.add(stroke(separator, theme));
 
         group
     }
@@ -583,7 +830,7 @@ impl SvgRenderer {
             .set("fill", theme.node_background.to_css())
             .set("stroke", theme.node_border.to_css())
             .set("stroke-width", 1.5);
-        group = group.add(head);
+        group = group.add(outline(head, theme));
         
         // 2. Тело (вертикальная линия)
         let body = svg::node::element::Line::new()
@@ -593,7 +840,7 @@ impl SvgRenderer {
             .set("y2", waist_y)
             .set("stroke", theme.node_border.to_css())
             .set("stroke-width", 1.5);
-        group = group.add(body);
+        group = group.add(outline(body, theme));
         
         // 3. Руки (горизонтальная линия)
         let arms = svg::node::element::Line::new()
@@ -603,7 +850,7 @@ impl SvgRenderer {
             .set("y2", arms_y)
             .set("stroke", theme.node_border.to_css())
             .set("stroke-width", 1.5);
-        group = group.add(arms);
+        group = group.add(outline(arms, theme));
         
         // 4. Левая нога
         let left_leg = svg::node::element::Line::new()
@@ -613,7 +860,7 @@ impl SvgRenderer {
             .set("y2", feet_y)
             .set("stroke", theme.node_border.to_css())
             .set("stroke-width", 1.5);
-        group = group.add(left_leg);
+        group = group.add(outline(left_leg, theme));
         
         // 5. Правая нога
         let right_leg = svg::node::element::Line::new()
@@ -623,7 +870,7 @@ impl SvgRenderer {
             .set("y2", feet_y)
             .set("stroke", theme.node_border.to_css())
             .set("stroke-width", 1.5);
-        group = group.add(right_leg);
+        group = group.add(outline(right_leg, theme));
         
         // 6. Текст имени под человечком
         let text_y = feet_y + 15.0;
@@ -634,7 +881,7 @@ impl SvgRenderer {
             .set("font-family", theme.font_family.as_str())
             .set("font-size", theme.font_size)
             .set("fill", theme.text_color.to_css());
-        group = group.add(text);
+        group = self.add_label(group, text, label, theme);
         
         group
     }
@@ -658,7 +905,7 @@ impl SvgRenderer {
             .set("fill", theme.node_background.to_css())
             .set("stroke", theme.node_border.to_css())
             .set("stroke-width", 1);
-        group = group.add(rect);
+        group = group.add(outline(rect, theme));
         
         // 2. Заголовок сверху по центру
         let title_text = svg::node::element::Text::new(title)
@@ -669,7 +916,7 @@ impl SvgRenderer {
             .set("font-size", theme.font_size + 1.0)
             .set("font-weight", "bold")
             .set("fill", theme.text_color.to_css());
-        group = group.add(title_text);
+        group = self.add_label(group, title_text, title, theme);
         
         group
     }
@@ -687,6 +934,7 @@ impl SvgRenderer {
         edge_type: EdgeType,
         from_cardinality: Option<&str>,
         to_cardinality: Option<&str>,
+        placement: EdgePlacement,
         theme: &Theme,
         mut group: Group,
     ) -> Group {
@@ -726,6 +974,8 @@ impl SvgRenderer {
                 points[3].x,
                 points[3].y, // влево к lifeline (конец)
             )
+        } else if placement.spline {
+            smooth_path(points)
         } else {
             // Обычные линии
             let mut d = format!("M{},{}", points[0].x, points[0].y);
@@ -737,7 +987,11 @@ impl SvgRenderer {
 
         // PlantUML использует stroke-width: 0.5 для lifelines, 1 для сообщений
         // Определяем по наличию стрелки - если есть стрелка, это сообщение
-        let stroke_width = if arrow_end || arrow_start { 1.0 } else { 0.5 };
+        let is_message = arrow_end
+            || arrow_start
+            || placement.start_decoration.is_some()
+            || placement.end_decoration.is_some();
+        let stroke_width = if is_message { 1.0 } else { 0.5 };
         
         let mut path = Path::new()
             .set("d", d)
@@ -749,7 +1003,7 @@ impl SvgRenderer {
         // PlantUML использует stroke-dasharray: 5,5 для lifelines, 2,2 для dashed сообщений
         if dashed {
             // Для lifelines (без стрелок) используем 5,5, для dashed сообщений - 2,2
-            let dash_pattern = if arrow_end || arrow_start { "2,2" } else { "5,5" };
+            let dash_pattern = if is_message { "2,2" } else { "5,5" };
             path = path.set("stroke-dasharray", dash_pattern);
         }
 
@@ -776,7 +1030,16 @@ impl SvgRenderer {
             path = path.set("marker-start", marker);
         }
 
-        group = group.add(path);
+        group = group.add(outline(path, theme));
+
+        // Кружок или крест на концах сообщения (`o->`, `->x`)
+        let last = points.len() - 1;
+        if let Some(decoration) = placement.start_decoration {
+            group = group.add(stroke(end_decoration_node(decoration, points[0], points[1], theme), theme));
+        }
+        if let Some(decoration) = placement.end_decoration {
+            group = group.add(stroke(end_decoration_node(decoration, points[last], points[last - 1], theme), theme));
+        }
 
         // Метка сообщения (в стиле PlantUML: текст рядом с линией)
         // По умолчанию в PlantUML: skinparam sequenceMessageAlign left
@@ -791,7 +1054,10 @@ impl SvgRenderer {
             let is_diagonal = points.len() == 2 && !is_vertical && !is_horizontal;
             
             // Позиция текста зависит от типа линии
-            let (base_x, text_y, anchor) = if is_self_message {
+            let (base_x, text_y, anchor) = if let Some(position) = placement.label {
+                // Место выбрано маршрутизатором в обход узлов и других подписей
+                (position.x, position.y, "start")
+            } else if is_self_message {
                 // PlantUML: для self-message текст НАД верхней линией петли
                 let text_start = points[0].x + 5.0;
                 let top_y = points[0].y - 5.0;
@@ -903,42 +1169,50 @@ impl SvgRenderer {
             // Кардинальность у начальной точки (from)
             if let Some(card) = from_cardinality {
                 let p = &points[0];
-                let (text_x, text_y) = if is_vertical {
-                    // Вертикальная линия: текст СЛЕВА, чуть НИЖЕ точки соединения
-                    (p.x - horizontal_offset, p.y + vertical_offset)
+                let text_elem = if let Some(position) = placement.from_cardinality {
+                    self.edge_text(card, position, theme)
                 } else {
-                    // Горизонтальная линия: текст сверху
-                    (p.x + vertical_offset, p.y - horizontal_offset / 2.0)
+                    let (text_x, text_y) = if is_vertical {
+                        // Вертикальная линия: текст СЛЕВА, чуть НИЖЕ точки соединения
+                        (p.x - horizontal_offset, p.y + vertical_offset)
+                    } else {
+                        // Горизонтальная линия: текст сверху
+                        (p.x + vertical_offset, p.y - horizontal_offset / 2.0)
+                    };
+                    svg::node::element::Text::new(card)
+                        .set("x", text_x)
+                        .set("y", text_y)
+                        .set("text-anchor", "end") // выравнивание по правому краю (к линии)
+                        .set("dominant-baseline", "middle")
+                        .set("font-family", theme.font_family.as_str())
+                        .set("font-size", font_size)
+                        .set("fill", theme.text_color.to_css())
                 };
-                let text_elem = svg::node::element::Text::new(card)
-                    .set("x", text_x)
-                    .set("y", text_y)
-                    .set("text-anchor", "end") // выравнивание по правому краю (к линии)
-                    .set("dominant-baseline", "middle")
-                    .set("font-family", theme.font_family.as_str())
-                    .set("font-size", font_size)
-                    .set("fill", theme.text_color.to_css());
                 group = group.add(text_elem);
             }
             
             // Кардинальность у конечной точки (to)
             if let Some(card) = to_cardinality {
                 let p = &points[points.len() - 1];
-                let (text_x, text_y) = if is_vertical {
-                    // Вертикальная линия: текст СЛЕВА, чуть ВЫШЕ точки соединения
-                    (p.x - horizontal_offset, p.y - vertical_offset)
+                let text_elem = if let Some(position) = placement.to_cardinality {
+                    self.edge_text(card, position, theme)
                 } else {
-                    // Горизонтальная линия: текст сверху
-                    (p.x - vertical_offset, p.y - horizontal_offset / 2.0)
+                    let (text_x, text_y) = if is_vertical {
+                        // Вертикальная линия: текст СЛЕВА, чуть ВЫШЕ точки соединения
+                        (p.x - horizontal_offset, p.y - vertical_offset)
+                    } else {
+                        // Горизонтальная линия: текст сверху
+                        (p.x - vertical_offset, p.y - horizontal_offset / 2.0)
+                    };
+                    svg::node::element::Text::new(card)
+                        .set("x", text_x)
+                        .set("y", text_y)
+                        .set("text-anchor", "end") // выравнивание по правому краю (к линии)
+                        .set("dominant-baseline", "middle")
+                        .set("font-family", theme.font_family.as_str())
+                        .set("font-size", font_size)
+                        .set("fill", theme.text_color.to_css())
                 };
-                let text_elem = svg::node::element::Text::new(card)
-                    .set("x", text_x)
-                    .set("y", text_y)
-                    .set("text-anchor", "end") // выравнивание по правому краю (к линии)
-                    .set("dominant-baseline", "middle")
-                    .set("font-family", theme.font_family.as_str())
-                    .set("font-size", font_size)
-                    .set("fill", theme.text_color.to_css());
                 group = group.add(text_elem);
             }
         }
@@ -946,23 +1220,147 @@ impl SvgRenderer {
         group
     }
 
+    /// Однострочная подпись у ребра: левый край и базовая линия в `position`
+    fn edge_text(&self, text: &str, position: Point, theme: &Theme) -> Text {
+        Text::new(text)
+            .set("x", position.x)
+            .set("y", position.y)
+            .set("text-anchor", "start")
+            .set("dominant-baseline", "auto")
+            .set("font-family", theme.font_family.as_str())
+            .set("font-size", 13.0)
+            .set("fill", theme.text_color.to_css())
+    }
+
+    /// Добавляет подпись с creole разметкой и спрайтами `<$name>`
+    ///
+    /// Положение и стиль берутся из готового `text`; подписи без разметки
+    /// добавляются как есть. Строки выводятся отдельными `<text>` с
+    /// `<tspan>` на каждый фрагмент одного стиля.
+    fn add_label(&self, group: Group, text: Text, label: &str, theme: &Theme) -> Group {
+        // Многострочный текст тоже раскладывается построчно
+        if !has_markup(label) && !label.contains('\n') && !label.contains("\\n") {
+            return group.add(text);
+        }
+
+        let attributes = text.get_attributes();
+        let attribute = |name: &str| attributes.get(name).map(|value| value.to_string());
+        let number = |name: &str| attribute(name).and_then(|value| value.parse::<f64>().ok());
+        let x = number("x").unwrap_or(0.0);
+        let y = number("y").unwrap_or(0.0);
+        let font_size = number("font-size").unwrap_or(theme.font_size);
+        let bold = attribute("font-weight").as_deref() == Some("bold");
+        let color = attribute("fill").unwrap_or_else(|| theme.text_color.to_css());
+        let anchor = attribute("text-anchor");
+
+        let metrics =
+            TextMetrics::for_font(theme.font_family.as_str(), font_size).with_sprites(self.sprites.clone());
+        let lines = metrics.label_lines(label, font_size, bold);
+        let block_width = lines.iter().map(|line| line.width).fold(0.0, f64::max);
+        let total_height: f64 = lines.iter().map(|line| line.height).sum();
+        let align = |width: f64| match anchor.as_deref() {
+            Some("middle") => x - width / 2.0,
+            Some("end") => x - width,
+            _ => x,
+        };
+
+        // y текста — середина блока или базовая линия первой строки
+        let mut line_top = match attribute("dominant-baseline").as_deref() {
+            Some("middle") | Some("central") => y - total_height / 2.0,
+            _ => y - metrics.measurer().line_height(font_size) * 0.8,
+        };
+
+        // Атрибуты шрифта строк наследуются от исходного текста
+        let base = attributes
+            .iter()
+            .filter(|(name, _)| !matches!(name.as_str(), "x" | "y" | "text-anchor" | "dominant-baseline"))
+            .fold(Text::new(""), |text, (name, value)| text.set(name.as_str(), value.clone()));
+
+        let mut group = group;
+        for line in &lines {
+            match &line.kind {
+                LabelLineKind::Text => {
+                    let line_x = align(line.width) + line.indent;
+                    group = add_spans(group, &base, &line.spans, line_x, line_top, line.height, &color);
+                }
+                LabelLineKind::Rule => {
+                    let rule_x = coordinate(align(block_width));
+                    let rule_y = coordinate(line_top + line.height / 2.0);
+                    let rule = svg::node::element::Line::new()
+                        .set("x1", rule_x)
+                        .set("y1", rule_y)
+                        .set("x2", coordinate(rule_x + block_width))
+                        .set("y2", rule_y)
+                        .set("stroke", color.as_str())
+                        .set("stroke-width", 1);
+                    group = group.add(stroke(rule, theme));
+                }
+                LabelLineKind::TableRow(cells) => {
+                    let row_x = align(line.width);
+                    for cell in cells {
+                        let mut border = Rectangle::new()
+                            .set("x", coordinate(row_x + cell.x))
+                            .set("y", coordinate(line_top))
+                            .set("width", coordinate(cell.width))
+                            .set("height", line.height)
+                            .set("fill", "none")
+                            .set("stroke", color.as_str())
+                            .set("stroke-width", 0.5);
+                        if cell.header {
+                            border = border
+                                .set("fill", theme.node_border.to_css())
+                                .set("fill-opacity", 0.15);
+                        }
+                        group = group.add(stroke(border, theme));
+                        group = add_spans(
+                            group,
+                            &base,
+                            &cell.spans,
+                            row_x + cell.x + CELL_PADDING_X,
+                            line_top,
+                            line.height,
+                            &color,
+                        );
+                    }
+                }
+            }
+            line_top += line.height;
+        }
+        group
+    }
+
     /// Рендерит текст
+    #[allow(clippy::too_many_arguments)]
     fn render_text(
         &self,
         bounds: &Rect,
         text_content: &str,
         font_size: f64,
+        anchor: Option<&str>,
+        weight: Option<&str>,
         theme: &Theme,
         group: Group,
     ) -> Group {
-        let text = svg::node::element::Text::new(text_content)
-            .set("x", bounds.x)
+        // x точки привязки строк: левый край, центр или правый край
+        let x = match anchor {
+            Some("middle") => bounds.x + bounds.width / 2.0,
+            Some("end") => bounds.x + bounds.width,
+            _ => bounds.x,
+        };
+        let mut text = svg::node::element::Text::new(text_content)
+            .set("x", x)
             .set("y", bounds.y + font_size)
             .set("font-family", theme.font_family.as_str())
             .set("font-size", font_size)
             .set("fill", theme.text_color.to_css());
+        if let Some(anchor) = anchor {
+            text = text.set("text-anchor", anchor);
+        }
+        if let Some(weight) = weight {
+            text = text.set("font-weight", weight);
+        }
 
-        group.add(text)
+        self.add_label(group, text, text_content, theme)
     }
 
     /// Рендерит многострочный текст с поддержкой \n
@@ -982,7 +1380,24 @@ impl SvgRenderer {
         let processed_label = label.replace("\\n", "\n");
         let lines: Vec<&str> = processed_label.split('\n').collect();
         
-        if lines.len() == 1 {
+        if has_markup(label) {
+            // Creole разметка: строки раскладывает add_label по их высотам,
+            // низ блока — под базовой линией y, как у последней строки текста
+            let metrics = TextMetrics::for_font(theme.font_family.as_str(), font_size)
+                .with_sprites(self.sprites.clone());
+            let lines = metrics.label_lines(label, font_size, false);
+            let height: f64 = lines.iter().map(|line| line.height).sum();
+            let descent = metrics.measurer().line_height(font_size) * 0.2;
+            let text = svg::node::element::Text::new(label)
+                .set("x", x)
+                .set("y", y + descent - height / 2.0)
+                .set("text-anchor", anchor)
+                .set("dominant-baseline", "middle")
+                .set("font-family", theme.font_family.as_str())
+                .set("font-size", font_size)
+                .set("fill", theme.text_color.to_css());
+            group = self.add_label(group, text, label, theme);
+        } else if lines.len() == 1 {
             // Одна строка — простой текст
             let text = svg::node::element::Text::new(label)
                 .set("x", x)
@@ -1023,53 +1438,203 @@ impl SvgRenderer {
         group
     }
 
-    /// Рендерит группу (устаревший, для совместимости)
+    /// Рендерит рамку контейнера с названием
+    ///
+    /// Форма зависит от вида контейнера, как у одиночных элементов:
+    /// вкладка у `package` и `folder`, объём у `node`, пятиугольник
+    /// заголовка у `frame`, цилиндр у `database` и `queue`, скругления у
+    /// `cloud` и `storage`. `Rectangle` — рамка с полосой заголовка.
     fn render_group(
         &self,
         bounds: &Rect,
         label: Option<&str>,
-        children: &[LayoutElement],
+        kind: ContainerKind,
+        fill: Option<&str>,
         theme: &Theme,
         mut group: Group,
     ) -> Group {
-        // Рамка группы - СПЛОШНАЯ (как в PlantUML)
-        let rect = Rectangle::new()
-            .set("x", bounds.x)
-            .set("y", bounds.y)
-            .set("width", bounds.width)
-            .set("height", bounds.height)
-            .set("fill", "none")
-            .set("stroke", theme.node_border.to_css())
-            .set("stroke-width", 1);
-
-        group = group.add(rect);
-
-        // Заголовок
-        if let Some(label) = label {
-            let header_bg = Rectangle::new()
-                .set("x", bounds.x)
-                .set("y", bounds.y)
+        const HEADER: f64 = 20.0;
+        const DEPTH: f64 = 8.0;
+
+        let (x, y) = (bounds.x, bounds.y);
+        let (right, bottom) = (x + bounds.width, y + bounds.height);
+        let border = theme.node_border.to_css();
+        let body_fill = fill.unwrap_or("none");
+        let label_width = label.map_or(0.0, |label| {
+            TextMetrics::for_font(theme.font_family.as_str(), theme.font_size).bold_width_at(label, theme.font_size)
+        }) + 16.0;
+        let outline = |d: String, fill: &str| {
+            Path::new()
+                .set("d", d)
+                .set("fill", fill.to_string())
+                .set("stroke", border.clone())
+                .set("stroke-width", 1)
+        };
+        let rounded = |radius: f64| {
+            let radius = radius.min(bounds.width / 2.0).min(bounds.height / 2.0);
+            Rectangle::new()
+                .set("x", x)
+                .set("y", y)
                 .set("width", bounds.width)
-                .set("height", 20.0)
-                .set("fill", theme.node_background.to_css());
-
-            let text = svg::node::element::Text::new(label)
-                .set("x", bounds.x + 5.0)
-                .set("y", bounds.y + 14.0)
-                .set("font-family", theme.font_family.as_str())
-                .set("font-size", theme.font_size)
-                .set("font-weight", "bold")
-                .set("fill", theme.text_color.to_css());
+                .set("height", bounds.height)
+                .set("rx", radius)
+                .set("ry", radius)
+                .set("fill", body_fill)
+                .set("stroke", border.clone())
+                .set("stroke-width", 1)
+        };
 
-            group = group.add(header_bg).add(text);
-        }
+        // Рамка и верх области названия
+        let label_top = match kind {
+            ContainerKind::Rectangle => {
+                let rect = Rectangle::new()
+                    .set("x", x)
+                    .set("y", y)
+                    .set("width", bounds.width)
+                    .set("height", bounds.height)
+                    .set("fill", "none")
+                    .set("stroke", border.clone())
+                    .set("stroke-width", 1);
+                let header_bg = Rectangle::new()
+                    .set("x", x)
+                    .set("y", y)
+                    .set("width", bounds.width)
+                    .set("height", HEADER)
+                    .set("fill", theme.node_background.to_css());
+                group = group.add(stroke(rect, theme));
+                if label.is_some() {
+                    group = group.add(stroke(header_bg, theme));
+                }
+                y
+            }
+            ContainerKind::Package => {
+                return self.render_package(
+                    bounds,
+                    label.unwrap_or_default(),
+                    None,
+                    label_width,
+                    HEADER,
+                    fill,
+                    theme,
+                    group,
+                );
+            }
+            ContainerKind::Folder => {
+                // Вкладка без текста, название под ней
+                let tab_right = x + (label_width / 2.0).min(bounds.width);
+                let d = format!(
+                    "M{x},{y} L{tab_right},{y} L{tab_end},{tab_y} L{right},{tab_y} L{right},{bottom} L{x},{bottom} Z",
+                    tab_end = (tab_right + DEPTH).min(right),
+                    tab_y = y + DEPTH,
+                );
+                group = group.add(stroke(outline(d, body_fill), theme));
+                y + DEPTH
+            }
+            ContainerKind::Node => {
+                // Передняя грань и видимые верхняя и правая грани
+                let front = format!(
+                    "M{x},{top} L{front_right},{top} L{front_right},{bottom} L{x},{bottom} Z",
+                    top = y + DEPTH,
+                    front_right = right - DEPTH,
+                );
+                let faces = format!(
+                    "M{x},{top} L{left},{y} L{right},{y} L{right},{side_bottom} L{front_right},{bottom} \
+                     L{front_right},{top} Z",
+                    top = y + DEPTH,
+                    left = x + DEPTH,
+                    side_bottom = bottom - DEPTH,
+                    front_right = right - DEPTH,
+                );
+                let edge = format!("M{front_right},{top} L{right},{y}", front_right = right - DEPTH, top = y + DEPTH);
+                group = group
+                    .add(stroke(outline(faces, body_fill), theme))
+                    .add(stroke(outline(front, body_fill), theme))
+                    .add(stroke(outline(edge, "none"), theme));
+                y + DEPTH
+            }
+            ContainerKind::Frame => {
+                let rect = Rectangle::new()
+                    .set("x", x)
+                    .set("y", y)
+                    .set("width", bounds.width)
+                    .set("height", bounds.height)
+                    .set("fill", body_fill)
+                    .set("stroke", border.clone())
+                    .set("stroke-width", 1);
+                let notch = DEPTH;
+                let tab_right = x + label_width.min(bounds.width);
+                let pentagon = format!(
+                    "M{x},{y} L{tab_right},{y} L{tab_right},{notch_y} L{notch_x},{header} L{x},{header} Z",
+                    notch_y = y + HEADER - notch,
+                    notch_x = tab_right - notch,
+                    header = y + HEADER,
+                );
+                group = group
+                    .add(stroke(rect, theme))
+                    .add(stroke(outline(pentagon, &theme.node_background.to_css()), theme));
+                y
+            }
+            ContainerKind::Cloud => {
+                group = group.add(stroke(rounded(HEADER), theme));
+                y
+            }
+            ContainerKind::Storage => {
+                group = group.add(stroke(rounded(HEADER / 2.0), theme));
+                y
+            }
+            ContainerKind::Database => {
+                // Цилиндр: тело с дугами сверху и снизу, видимая дуга крышки
+                let radius_x = bounds.width / 2.0;
+                let body = format!(
+                    "M{x},{top} A{radius_x},{DEPTH} 0 0 1 {right},{top} L{right},{base} \
+                     A{radius_x},{DEPTH} 0 0 1 {x},{base} Z",
+                    top = y + DEPTH,
+                    base = bottom - DEPTH,
+                );
+                let lid = format!("M{x},{top} A{radius_x},{DEPTH} 0 0 0 {right},{top}", top = y + DEPTH);
+                group = group
+                    .add(stroke(outline(body, body_fill), theme))
+                    .add(stroke(outline(lid, "none"), theme));
+                y + DEPTH * 2.0
+            }
+            ContainerKind::Queue => {
+                // Горизонтальный цилиндр: видимая дуга правого торца
+                let radius_y = bounds.height / 2.0;
+                let body = format!(
+                    "M{left},{y} L{end},{y} A{DEPTH},{radius_y} 0 0 1 {end},{bottom} L{left},{bottom} \
+                     A{DEPTH},{radius_y} 0 0 1 {left},{y} Z",
+                    left = x + DEPTH,
+                    end = right - DEPTH,
+                );
+                let cap = format!(
+                    "M{end},{y} A{DEPTH},{radius_y} 0 0 0 {end},{bottom}",
+                    end = right - DEPTH,
+                );
+                group = group
+                    .add(stroke(outline(body, body_fill), theme))
+                    .add(stroke(outline(cap, "none"), theme));
+                y
+            }
+        };
 
-        // Дочерние элементы
-        for child in children {
-            group = group.add(self.render_element(child, theme));
+        let Some(label) = label else {
+            return group;
+        };
+        let text_x = match kind {
+            ContainerKind::Queue => x + DEPTH + 5.0,
+            _ => x + 5.0,
+        };
+        let text = svg::node::element::Text::new(label)
+            .set("x", text_x)
+            .set("y", label_top + 14.0)
+            .set("font-family", theme.font_family.as_str())
+            .set("font-size", theme.font_size)
+            .set("font-weight", "bold")
+            .set("fill", theme.text_color.to_css());
+        match kind {
+            ContainerKind::Rectangle => group.add(text),
+            _ => self.add_label(group, text, label, theme),
         }
-
-        group
     }
 
     /// Рендерит Combined Fragment (alt, opt, loop, etc.) в стиле PlantUML
@@ -1079,6 +1644,7 @@ impl SvgRenderer {
         fragment_type: &str,
         sections: &[FragmentSection],
         theme: &Theme,
+        ids: &mut ElementIds,
         mut group: Group,
     ) -> Group {
         // 1. СПЛОШНАЯ рамка фрагмента (как в PlantUML)
@@ -1092,12 +1658,13 @@ impl SvgRenderer {
             .set("stroke", theme.node_border.to_css())
             .set("stroke-width", 1.5);
 
-        group = group.add(rect);
+        group = group.add(stroke(rect, theme));
 
         // 2. Пятиугольный заголовок (pentagon) в левом верхнем углу
         // Размеры: ширина ~50px для "alt", высота ~20px
         let label_text = fragment_type;
-        let label_width = (label_text.len() as f64 * 8.0 + 16.0).max(40.0);
+        let metrics = TextMetrics::for_font(theme.font_family.as_str(), theme.font_size);
+        let label_width = tab_width(label_text, &metrics);
         let label_height = 20.0;
         let notch_size = 8.0; // размер "зазубрины" пятиугольника
 
@@ -1122,7 +1689,7 @@ impl SvgRenderer {
             .set("stroke", theme.node_border.to_css())
             .set("stroke-width", 1.5);
 
-        group = group.add(pentagon);
+        group = group.add(stroke(pentagon, theme));
 
         // Текст типа фрагмента ("alt", "opt", etc.)
         let type_text = svg::node::element::Text::new(label_text)
@@ -1178,7 +1745,7 @@ impl SvgRenderer {
                     .set("stroke-width", 1)
                     .set("stroke-dasharray", "5,3");
 
-                group = group.add(separator_line);
+                group = group.add(stroke(separator_line, theme));
 
                 // Текст условия else слева, НАД линией (с достаточным отступом)
                 let else_label = if let Some(cond) = &section.condition {
@@ -1199,7 +1766,7 @@ impl SvgRenderer {
 
             // Дочерние элементы секции
             for child in &section.children {
-                group = group.add(self.render_element(child, theme));
+                group = group.add(self.render_element(child, theme, ids));
             }
         }
 
@@ -1227,7 +1794,7 @@ impl SvgRenderer {
             .set("fill", fill_color)
             .set("stroke", theme.node_border.to_css())
             .set("stroke-width", 1);
-        group = group.add(rect);
+        group = group.add(stroke(rect, theme));
 
         // Заголовок по центру сверху
         if let Some(title) = title {
@@ -1240,12 +1807,167 @@ impl SvgRenderer {
                 .set("font-size", theme.font_size + 1.0)
                 .set("font-weight", "bold")
                 .set("fill", theme.text_color.to_css());
-            group = group.add(title_text);
+            group = self.add_label(group, title_text, title, theme);
         }
 
         group
     }
 
+    /// Рендерит пакет: вкладка-папка с названием над рамкой содержимого
+    #[allow(clippy::too_many_arguments)]
+    fn render_package(
+        &self,
+        bounds: &Rect,
+        name: &str,
+        stereotype: Option<&str>,
+        tab_width: f64,
+        tab_height: f64,
+        color: Option<&str>,
+        theme: &Theme,
+        mut group: Group,
+    ) -> Group {
+        let fill_color = color.unwrap_or("none");
+        let tab_width = tab_width.min(bounds.width);
+
+        // Контур: вкладка слева сверху и прямоугольник под ней
+        let body_y = bounds.y + tab_height;
+        let outline = format!(
+            "M{x},{y} L{tab_right},{y} L{tab_right},{body_y} L{right},{body_y} L{right},{bottom} L{x},{bottom} Z",
+            x = bounds.x,
+            y = bounds.y,
+            tab_right = bounds.x + tab_width,
+            body_y = body_y,
+            right = bounds.x + bounds.width,
+            bottom = bounds.y + bounds.height,
+        );
+        let path = svg::node::element::Path::new()
+            .set("d", outline)
+            .set("fill", fill_color)
+            .set("stroke", theme.node_border.to_css())
+            .set("stroke-width", 1);
+        let divider = svg::node::element::Line::new()
+            .set("x1", bounds.x)
+            .set("y1", body_y)
+            .set("x2", bounds.x + tab_width)
+            .set("y2", body_y)
+            .set("stroke", theme.node_border.to_css())
+            .set("stroke-width", 1);
+        group = group.add(stroke(path, theme)).add(stroke(divider, theme));
+
+        // Стереотип над названием, оба по центру вкладки
+        let center_x = bounds.x + tab_width / 2.0;
+        let mut name_y = bounds.y + tab_height / 2.0;
+        if let Some(stereotype) = stereotype {
+            let line = tab_height / 2.0;
+            let stereotype_text = svg::node::element::Text::new(format!("«{}»", stereotype))
+                .set("x", center_x)
+                .set("y", bounds.y + line / 2.0 + 2.0)
+                .set("text-anchor", "middle")
+                .set("dominant-baseline", "middle")
+                .set("font-family", theme.font_family.as_str())
+                .set("font-size", theme.font_size - 2.0)
+                .set("font-style", "italic")
+                .set("fill", theme.text_color.to_css());
+            group = group.add(stereotype_text);
+            name_y = bounds.y + line + line / 2.0;
+        }
+
+        let name_text = svg::node::element::Text::new(name)
+            .set("x", center_x)
+            .set("y", name_y)
+            .set("text-anchor", "middle")
+            .set("dominant-baseline", "middle")
+            .set("font-family", theme.font_family.as_str())
+            .set("font-size", theme.font_size)
+            .set("font-weight", "bold")
+            .set("fill", theme.text_color.to_css());
+        self.add_label(group, name_text, name, theme)
+    }
+
+    /// Рендерит ромб ветвления; с подписью — шестиугольник с текстом внутри
+    fn render_diamond(&self, bounds: &Rect, label: Option<&str>, theme: &Theme, mut group: Group) -> Group {
+        let cx = bounds.x + bounds.width / 2.0;
+        let cy = bounds.y + bounds.height / 2.0;
+        let right = bounds.x + bounds.width;
+        let bottom = bounds.y + bounds.height;
+        let points = match label {
+            Some(_) => {
+                let inset = bounds.height / 2.0;
+                format!(
+                    "{},{} {},{} {},{} {},{} {},{} {},{}",
+                    bounds.x, cy,
+                    bounds.x + inset, bounds.y,
+                    right - inset, bounds.y,
+                    right, cy,
+                    right - inset, bottom,
+                    bounds.x + inset, bottom,
+                )
+            }
+            None => format!(
+                "{},{} {},{} {},{} {},{}",
+                cx, bounds.y, right, cy, cx, bottom, bounds.x, cy
+            ),
+        };
+        let polygon = svg::node::element::Polygon::new()
+            .set("points", points)
+            .set("fill", theme.node_background.to_css())
+            .set("stroke", theme.node_border.to_css())
+            .set("stroke-width", 0.5);
+        group = group.add(outline(polygon, theme));
+
+        let Some(label) = label else {
+            return group;
+        };
+        let text = svg::node::element::Text::new(label)
+            .set("x", cx)
+            .set("y", cy)
+            .set("text-anchor", "middle")
+            .set("dominant-baseline", "middle")
+            .set("font-family", theme.font_family.as_str())
+            .set("font-size", theme.font_size)
+            .set("fill", theme.text_color.to_css());
+        self.add_label(group, text, label, theme)
+    }
+
+    /// Рендерит дорожку activity диаграммы: колонка с заголовком сверху
+    fn render_swimlane(
+        &self,
+        bounds: &Rect,
+        title: &str,
+        header_height: f64,
+        color: Option<&str>,
+        theme: &Theme,
+        mut group: Group,
+    ) -> Group {
+        let rect = Rectangle::new()
+            .set("x", bounds.x)
+            .set("y", bounds.y)
+            .set("width", bounds.width)
+            .set("height", bounds.height)
+            .set("fill", color.unwrap_or("none"))
+            .set("stroke", theme.node_border.to_css())
+            .set("stroke-width", 1);
+        let separator = svg::node::element::Line::new()
+            .set("x1", bounds.x)
+            .set("y1", bounds.y + header_height)
+            .set("x2", bounds.x + bounds.width)
+            .set("y2", bounds.y + header_height)
+            .set("stroke", theme.node_border.to_css())
+            .set("stroke-width", 1);
+        group = group.add(stroke(rect, theme)).add(stroke(separator, theme));
+
+        let title_text = svg::node::element::Text::new(title)
+            .set("x", bounds.x + bounds.width / 2.0)
+            .set("y", bounds.y + header_height / 2.0)
+            .set("text-anchor", "middle")
+            .set("dominant-baseline", "middle")
+            .set("font-family", theme.font_family.as_str())
+            .set("font-size", theme.font_size)
+            .set("font-weight", "bold")
+            .set("fill", theme.text_color.to_css());
+        self.add_label(group, title_text, title, theme)
+    }
+
     /// Рендерит Activation box (белый фон, чёрная рамка)
     fn render_activation(&self, bounds: &Rect, theme: &Theme, group: Group) -> Group {
         // Activation box: белый фон (как в PlantUML)
@@ -1258,10 +1980,13 @@ impl SvgRenderer {
             .set("stroke", theme.node_border.to_css())
             .set("stroke-width", 1);
 
-        group.add(rect)
+        group.add(outline(rect, theme))
     }
 
     /// Рендерит ClassBox (класс/интерфейс/enum) в стиле PlantUML
+    ///
+    /// Скрытая секция (`None`) не рисуется вместе со своим разделителем;
+    /// `dash` применяется только к рамке.
     #[allow(clippy::too_many_arguments)]
     fn render_class_box(
         &self,
@@ -1269,8 +1994,10 @@ impl SvgRenderer {
         classifier_type: ClassifierKind,
         name: &str,
         stereotype: Option<&str>,
-        fields: &[ClassMember],
-        methods: &[ClassMember],
+        fields: Option<&[ClassMember]>,
+        methods: Option<&[ClassMember]>,
+        show_circle: bool,
+        dash: Option<&str>,
         theme: &Theme,
         mut group: Group,
     ) -> Group {
@@ -1284,12 +2011,16 @@ impl SvgRenderer {
             .set("y", bounds.y)
             .set("width", bounds.width)
             .set("height", bounds.height)
-            .set("rx", 2.5)
-            .set("ry", 2.5)
+            .set("rx", theme.corner_radius)
+            .set("ry", theme.corner_radius)
             .set("fill", theme.node_background.to_css())
             .set("stroke", theme.node_border.to_css())
             .set("stroke-width", 0.5);
-        group = group.add(rect);
+        let rect = match dash {
+            Some(dash) => rect.set("stroke-dasharray", dash),
+            None => rect,
+        };
+        group = group.add(outline(rect, theme));
 
         let mut current_y = bounds.y + padding;
 
@@ -1305,38 +2036,45 @@ impl SvgRenderer {
             ClassifierKind::Entity => ("#CCCCCC", "E"),        // серый
         };
 
-        // Круг иконки
-        let icon_circle = svg::node::element::Ellipse::new()
-            .set("cx", icon_x)
-            .set("cy", icon_y)
-            .set("rx", icon_size)
-            .set("ry", icon_size)
-            .set("fill", icon_fill)
-            .set("stroke", theme.node_border.to_css())
-            .set("stroke-width", 1);
-        group = group.add(icon_circle);
-
-        // Буква в иконке
-        let icon_text = svg::node::element::Text::new(icon_letter)
-            .set("x", icon_x)
-            .set("y", icon_y + 4.0)
-            .set("text-anchor", "middle")
-            .set("font-family", theme.font_family.as_str())
-            .set("font-size", 12)
-            .set("font-weight", "bold")
-            .set("fill", "#000000");
-        group = group.add(icon_text);
+        // Круг иконки (`hide circle` убирает)
+        if show_circle {
+            let icon_circle = svg::node::element::Ellipse::new()
+                .set("cx", icon_x)
+                .set("cy", icon_y)
+                .set("rx", icon_size)
+                .set("ry", icon_size)
+                .set("fill", icon_fill)
+                .set("stroke", theme.node_border.to_css())
+                .set("stroke-width", 1);
+            group = group.add(stroke(icon_circle, theme));
+
+            // Буква в иконке
+            let icon_text = svg::node::element::Text::new(icon_letter)
+                .set("x", icon_x)
+                .set("y", icon_y + 4.0)
+                .set("text-anchor", "middle")
+                .set("font-family", theme.font_family.as_str())
+                .set("font-size", 12)
+                .set("font-weight", "bold")
+                .set("fill", "#000000");
+            group = group.add(icon_text);
+        }
 
         // 3. Стереотип (если есть)
-        let name_x = icon_x + icon_size + 5.0;
+        let name_x = if show_circle { icon_x + icon_size + 5.0 } else { bounds.x + padding };
         if let Some(stereo) = stereotype {
-            let stereo_text = svg::node::element::Text::new(format!("«{}»", stereo))
+            // `<<$name>>` — спрайт вместо текста стереотипа
+            let sprite_label = SpriteRef::from_stereotype(stereo)
+                .filter(|reference| self.sprites.get(&reference.name).is_some())
+                .map(|_| format!("<{}>", stereo.trim().trim_start_matches('<').trim_end_matches('>')));
+            let stereo_label = sprite_label.unwrap_or_else(|| format!("«{}»", stereo));
+            let stereo_text = svg::node::element::Text::new(stereo_label.as_str())
                 .set("x", name_x)
                 .set("y", current_y + 10.0)
                 .set("font-family", theme.font_family.as_str())
                 .set("font-size", 10)
                 .set("fill", theme.text_color.to_css());
-            group = group.add(stereo_text);
+            group = self.add_label(group, stereo_text, &stereo_label, theme);
             current_y += 12.0;
         }
 
@@ -1348,22 +2086,24 @@ impl SvgRenderer {
             .set("font-size", theme.font_size)
             .set("font-weight", "bold")
             .set("fill", theme.text_color.to_css());
-        group = group.add(name_text);
+        group = self.add_label(group, name_text, name, theme);
         current_y += line_height + padding;
 
         // 5. Разделитель после имени
-        let separator1 = svg::node::element::Line::new()
-            .set("x1", bounds.x + 1.0)
-            .set("y1", current_y)
-            .set("x2", bounds.x + bounds.width - 1.0)
-            .set("y2", current_y)
-            .set("stroke", theme.node_border.to_css())
-            .set("stroke-width", 0.5);
-        group = group.add(separator1);
-        current_y += padding;
+        if fields.is_some() || methods.is_some() {
+            let separator1 = svg::node::element::Line::new()
+                .set("x1", bounds.x + 1.0)
+                .set("y1", current_y)
+                .set("x2", bounds.x + bounds.width - 1.0)
+                .set("y2", current_y)
+                .set("stroke", theme.node_border.to_css())
+                .set("stroke-width", 0.5);
+            group = group.add(stroke(separator1, theme));
+            current_y += padding;
+        }
 
         // 6. Поля
-        for field in fields {
+        for field in fields.unwrap_or_default() {
             group = self.render_class_member(
                 bounds.x + padding,
                 current_y,
@@ -1376,18 +2116,20 @@ impl SvgRenderer {
         }
 
         // 7. Разделитель между полями и методами
-        let separator2 = svg::node::element::Line::new()
-            .set("x1", bounds.x + 1.0)
-            .set("y1", current_y)
-            .set("x2", bounds.x + bounds.width - 1.0)
-            .set("y2", current_y)
-            .set("stroke", theme.node_border.to_css())
-            .set("stroke-width", 0.5);
-        group = group.add(separator2);
-        current_y += padding;
+        if fields.is_some() && methods.is_some() {
+            let separator2 = svg::node::element::Line::new()
+                .set("x1", bounds.x + 1.0)
+                .set("y1", current_y)
+                .set("x2", bounds.x + bounds.width - 1.0)
+                .set("y2", current_y)
+                .set("stroke", theme.node_border.to_css())
+                .set("stroke-width", 0.5);
+            group = group.add(stroke(separator2, theme));
+            current_y += padding;
+        }
 
         // 8. Методы
-        for method in methods {
+        for method in methods.unwrap_or_default() {
             group = self.render_class_member(
                 bounds.x + padding,
                 current_y,
@@ -1403,6 +2145,9 @@ impl SvgRenderer {
     }
 
     /// Рендерит член класса (поле или метод) с иконкой видимости
+    ///
+    /// `skinparam classAttributeIconSize` задаёт размер иконки (по умолчанию 10);
+    /// при 0 видимость пишется символом перед текстом (`+name`).
     fn render_class_member(
         &self,
         x: f64,
@@ -1412,7 +2157,17 @@ impl SvgRenderer {
         theme: &Theme,
         mut group: Group,
     ) -> Group {
-        let icon_radius = 3.0;
+        let icon_size = self
+            .skin_params
+            .get("classAttributeIconSize")
+            .and_then(|size| size.parse::<f64>().ok())
+            .unwrap_or(10.0);
+        if icon_size <= 0.0 {
+            let label = format!("{}{}", visibility_symbol(member.visibility), member.text);
+            return self.render_member_text(x, y, member, &label, theme, group);
+        }
+
+        let icon_radius = icon_size * 0.3;
         let icon_x = x + icon_radius;
         let icon_y = y + 8.0;
 
@@ -1432,12 +2187,24 @@ impl SvgRenderer {
             .set("fill", fill_color)
             .set("stroke", stroke_color)
             .set("stroke-width", 1);
-        group = group.add(icon);
+        group = group.add(stroke(icon, theme));
 
-        // Текст члена
         let text_x = icon_x + icon_radius + 5.0;
-        let mut text = svg::node::element::Text::new(&member.text)
-            .set("x", text_x)
+        self.render_member_text(text_x, y, member, &member.text, theme, group)
+    }
+
+    /// Рендерит текст члена класса начиная с `x`
+    fn render_member_text(
+        &self,
+        x: f64,
+        y: f64,
+        member: &ClassMember,
+        label: &str,
+        theme: &Theme,
+        group: Group,
+    ) -> Group {
+        let mut text = svg::node::element::Text::new(label)
+            .set("x", x)
             .set("y", y + 12.0)
             .set("font-family", theme.font_family.as_str())
             .set("font-size", theme.font_size)
@@ -1453,7 +2220,7 @@ impl SvgRenderer {
             text = text.set("font-style", "italic");
         }
 
-        group.add(text)
+        self.add_label(group, text, label, theme)
     }
 }
 
@@ -1474,8 +2241,9 @@ impl Renderer for SvgRenderer {
         let mut sorted_elements: Vec<_> = layout.elements.iter().collect();
         sorted_elements.sort_by_key(|e| ZLayer::from_element(e));
 
+        let mut ids = ElementIds::new();
         for element in sorted_elements {
-            let rendered = self.render_element(element, theme);
+            let rendered = self.render_element(element, theme, &mut ids);
             doc = doc.add(rendered);
         }
 
@@ -1487,6 +2255,271 @@ impl Renderer for SvgRenderer {
     }
 }
 
+/// Добавляет строку подписи: `<text>` с `<tspan>` на фрагмент и спрайты
+///
+/// `base` — `<text>` с атрибутами шрифта подписи, `top` и `height` — полоса
+/// строки, по середине которой выравниваются текст и спрайты.
+fn add_spans(
+    mut group: Group,
+    base: &Text,
+    spans: &[LabelSpan],
+    x: f64,
+    top: f64,
+    height: f64,
+    color: &str,
+) -> Group {
+    let middle = top + height / 2.0;
+    let mut text = base
+        .clone()
+        .set("y", coordinate(middle))
+        .set("dominant-baseline", "middle")
+        .set("xml:space", "preserve");
+    let mut has_text = false;
+    let mut sprites = Vec::new();
+
+    let mut span_x = x;
+    for span in spans {
+        match span {
+            LabelSpan::Text {
+                text: content,
+                style,
+                font_size,
+                width,
+            } => {
+                if let Some(background) = &style.background {
+                    group = group.add(
+                        Rectangle::new()
+                            .set("x", coordinate(span_x))
+                            .set("y", coordinate(middle - font_size * 0.6))
+                            .set("width", coordinate(*width))
+                            .set("height", font_size * 1.2)
+                            .set("fill", background.as_str()),
+                    );
+                }
+                text = text.add(style_tspan(TSpan::new(content.as_str()).set("x", coordinate(span_x)), style, *font_size));
+                has_text = true;
+            }
+            LabelSpan::Sprite {
+                sprite,
+                reference,
+                height: sprite_height,
+                ..
+            } => {
+                let sprite_color = reference.color.as_deref().unwrap_or(color);
+                sprites.push(sprite_node(
+                    sprite,
+                    coordinate(span_x),
+                    coordinate(middle - sprite_height / 2.0),
+                    reference.scale,
+                    sprite_color,
+                ));
+            }
+        }
+        span_x += span.width();
+    }
+
+    if has_text {
+        group = group.add(text);
+    }
+    sprites.into_iter().fold(group, Group::add)
+}
+
+/// Путь через точки кривой Катмулла — Рома, переведённой в кубические
+/// сегменты Безье (`skinparam linetype spline`)
+fn smooth_path(points: &[Point]) -> String {
+    let mut d = format!("M{},{}", points[0].x, points[0].y);
+    let last = points.len() - 1;
+    for i in 0..last {
+        let before = points[i.saturating_sub(1)];
+        let (from, to) = (points[i], points[i + 1]);
+        let after = points[(i + 2).min(last)];
+        d.push_str(&format!(
+            " C{},{} {},{} {},{}",
+            from.x + (to.x - before.x) / 6.0,
+            from.y + (to.y - before.y) / 6.0,
+            to.x - (after.x - from.x) / 6.0,
+            to.y - (after.y - from.y) / 6.0,
+            to.x,
+            to.y
+        ));
+    }
+    d
+}
+
+/// Кружок или крест на конце ребра `end`; `neighbour` — соседняя точка пути
+///
+/// Кружок стоит за концом линии (за головкой стрелки), крест — на самом конце.
+fn end_decoration_node(decoration: EndDecoration, end: Point, neighbour: Point, theme: &Theme) -> Path {
+    const RADIUS: f64 = 4.0;
+    let (dx, dy) = (end.x - neighbour.x, end.y - neighbour.y);
+    let length = dx.hypot(dy).max(f64::EPSILON);
+    let (ux, uy) = (dx / length, dy / length);
+
+    let (d, fill) = match decoration {
+        EndDecoration::Circle => {
+            let (cx, cy) = (end.x + ux * RADIUS, end.y + uy * RADIUS);
+            let d = format!(
+                "M{},{} a{r},{r} 0 1,0 {},0 a{r},{r} 0 1,0 {},0",
+                coordinate(cx - RADIUS),
+                coordinate(cy),
+                2.0 * RADIUS,
+                -2.0 * RADIUS,
+                r = RADIUS
+            );
+            (d, theme.background_color.to_css())
+        }
+        EndDecoration::Cross => {
+            let size = RADIUS + 1.0;
+            let d = format!(
+                "M{},{} L{},{} M{},{} L{},{}",
+                coordinate(end.x - size),
+                coordinate(end.y - size),
+                coordinate(end.x + size),
+                coordinate(end.y + size),
+                coordinate(end.x - size),
+                coordinate(end.y + size),
+                coordinate(end.x + size),
+                coordinate(end.y - size)
+            );
+            (d, "none".to_string())
+        }
+    };
+
+    Path::new()
+        .set("d", d)
+        .set("fill", fill)
+        .set("stroke", theme.arrow_color.to_css())
+        .set("stroke-width", 1.5)
+}
+
+/// Есть ли среди элементов (включая вложенные группы) ссылки
+fn has_links(elements: &[LayoutElement]) -> bool {
+    elements.iter().any(|element| {
+        element.link().is_some()
+            || matches!(&element.element_type, ElementType::Group { children, .. } if has_links(children))
+    })
+}
+
+/// Символ видимости члена класса без иконки
+fn visibility_symbol(visibility: MemberVisibility) -> char {
+    match visibility {
+        MemberVisibility::Public => '+',
+        MemberVisibility::Private => '-',
+        MemberVisibility::Protected => '#',
+        MemberVisibility::Package => '~',
+    }
+}
+
+/// CSS заливки: цвет или ссылка на градиент элемента
+fn paint_css(paint: &Paint, id: &str) -> String {
+    match paint {
+        Paint::Solid(color) => color.clone(),
+        Paint::Gradient { .. } => format!("url(#{}_fill)", id),
+    }
+}
+
+/// Тема элемента: цвета, толщина линий и шрифт из его стиля
+fn styled_theme(theme: &Theme, style: &ElementStyle, fill: Option<&str>) -> Theme {
+    let mut theme = theme.clone();
+    if let Some(fill) = fill {
+        theme.node_background = ThemeColor::new(fill);
+    }
+    if let Some(stroke) = &style.stroke {
+        theme.node_border = ThemeColor::new(stroke.as_str());
+        theme.arrow_color = ThemeColor::new(stroke.as_str());
+    }
+    if let Some(width) = style.stroke_width {
+        theme.line_width = width;
+    } else if style.line_style == Some(LineStyle::Bold) {
+        theme.line_width *= 2.0;
+    }
+    if let Some(color) = &style.text_color {
+        theme.text_color = ThemeColor::new(color.as_str());
+    }
+    if let Some(family) = &style.font_family {
+        theme.font_family = family.clone();
+    }
+    if let Some(size) = style.font_size {
+        theme.font_size = size;
+    }
+    theme
+}
+
+/// `<linearGradient>` заливки элемента с id `{id}_fill`
+fn gradient_definition(id: &str, from: &str, to: &str, direction: GradientDirection) -> Definitions {
+    let (x1, y1, x2, y2) = direction.vector();
+    let gradient = LinearGradient::new()
+        .set("id", format!("{}_fill", id))
+        .set("x1", x1)
+        .set("y1", y1)
+        .set("x2", x2)
+        .set("y2", y2)
+        .add(Stop::new().set("offset", 0).set("stop-color", from))
+        .add(Stop::new().set("offset", 1).set("stop-color", to));
+    Definitions::new().add(gradient)
+}
+
+/// `stroke-dasharray` для стиля линии
+fn dash_array(style: LineStyle) -> Option<&'static str> {
+    match style {
+        LineStyle::Dashed => Some("5,5"),
+        LineStyle::Dotted => Some("1,3"),
+        LineStyle::Solid | LineStyle::Bold => None,
+    }
+}
+
+/// Радиус скругления с учётом `Theme::corner_radius`
+///
+/// Радиусы layout и рендерера заданы для темы по умолчанию (2.5) и
+/// масштабируются пропорционально радиусу темы.
+fn rounding(radius: f64, theme: &Theme) -> f64 {
+    let default = Theme::default().corner_radius;
+    if default == 0.0 {
+        return radius;
+    }
+    coordinate(radius * theme.corner_radius / default)
+}
+
+/// Округляет координату до сотых, чтобы SVG оставался компактным
+fn coordinate(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// Атрибуты `<tspan>` по стилю фрагмента
+fn style_tspan(mut tspan: TSpan, style: &TextStyle, font_size: f64) -> TSpan {
+    if style.bold {
+        tspan = tspan.set("font-weight", "bold");
+    }
+    if style.italic {
+        tspan = tspan.set("font-style", "italic");
+    }
+    if style.monospace {
+        tspan = tspan.set("font-family", "monospace");
+    }
+    if let Some(color) = &style.color {
+        tspan = tspan.set("fill", color.as_str());
+    }
+    if style.size.is_some() {
+        tspan = tspan.set("font-size", font_size);
+    }
+    let decoration = [
+        (style.underline || style.wave, "underline"),
+        (style.strike, "line-through"),
+    ]
+    .iter()
+    .filter(|(on, _)| *on)
+    .map(|(_, decoration)| *decoration)
+    .collect::<Vec<_>>()
+    .join(" ");
+    if !decoration.is_empty() {
+        tspan = tspan.set("text-decoration", decoration);
+    }
+    if style.wave {
+        tspan = tspan.set("text-decoration-style", "wavy");
+    }
+    tspan
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1523,4 +2556,160 @@ mod tests {
         assert!(svg.contains("<rect"));
         assert!(svg.contains("Hello"));
     }
+
+    #[test]
+    fn test_render_theme_strokes() {
+        let renderer = SvgRenderer::new();
+        let layout = LayoutResult {
+            elements: vec![LayoutElement::new(
+                "test",
+                Rect::new(10.0, 10.0, 100.0, 50.0),
+                ElementType::Rectangle {
+                    label: "Hello".to_string(),
+                    corner_radius: 2.5,
+                },
+            )],
+            bounds: Rect::new(0.0, 0.0, 120.0, 70.0),
+        };
+
+        // Тень: фильтр в defs и на контуре
+        let svg = renderer.render(&layout, &Theme::classic());
+        assert!(svg.contains(r#"<filter height="150%" id="shadow""#));
+        assert!(svg.contains(r#"filter="url(#shadow)""#));
+        assert!(svg.contains(r#"rx="5""#));
+
+        // Рукописная тема: контур — путь, линии толще, вывод стабилен
+        let sketchy = renderer.render(&layout, &Theme::sketchy());
+        assert!(!sketchy.contains("<rect"));
+        assert!(sketchy.contains(r#"stroke-width="1""#));
+        assert!(!sketchy.contains("url(#shadow)"));
+        assert_eq!(sketchy, renderer.render(&layout, &Theme::sketchy()));
+    }
+
+    #[test]
+    fn test_render_element_style() {
+        let renderer = SvgRenderer::new();
+        let mut style = ElementStyle::from_inline(&plantuml_ast::common::InlineStyle::parse(
+            "#red/blue;line:green;line.dashed;text:white",
+        ));
+        style.stroke_width = Some(3.0);
+        let layout = LayoutResult {
+            elements: vec![LayoutElement::new(
+                "styled",
+                Rect::new(10.0, 10.0, 100.0, 50.0),
+                ElementType::Rectangle {
+                    label: "Hello".to_string(),
+                    corner_radius: 2.5,
+                },
+            )
+            .with_style(&style)],
+            bounds: Rect::new(0.0, 0.0, 120.0, 70.0),
+        };
+
+        let svg = renderer.render(&layout, &Theme::default());
+        assert!(svg.contains(r#"<linearGradient id="styled_fill" x1="0" x2="1" y1="0" y2="1">"#));
+        assert!(svg.contains(r#"fill="url(#styled_fill)""#));
+        assert!(svg.contains(r#"stroke="green""#));
+        assert!(svg.contains(r#"stroke-width="1.5""#));
+        assert!(svg.contains(r#"stroke-dasharray="5,5""#));
+        assert!(svg.contains(r#"fill="white""#));
+    }
+
+    #[test]
+    fn test_render_class_dashed_outline() {
+        let renderer = SvgRenderer::new();
+        let style =
+            ElementStyle::from_inline(&plantuml_ast::common::InlineStyle::parse("line.dashed"));
+        let layout = LayoutResult {
+            elements: vec![LayoutElement::new(
+                "Foo",
+                Rect::new(10.0, 10.0, 100.0, 60.0),
+                ElementType::ClassBox {
+                    classifier_type: ClassifierKind::Class,
+                    name: "Foo".to_string(),
+                    stereotype: None,
+                    fields: vec![ClassMember::new(MemberVisibility::Private, "id: Int")],
+                    methods: vec![ClassMember::new(MemberVisibility::Public, "run()")],
+                    show_circle: true,
+                    show_fields: true,
+                    show_methods: true,
+                },
+            )
+            .with_style(&style)],
+            bounds: Rect::new(0.0, 0.0, 120.0, 80.0),
+        };
+
+        let svg = renderer.render(&layout, &Theme::default());
+        // Пунктир только у рамки: иконка и разделители сплошные
+        assert_eq!(svg.matches("stroke-dasharray").count(), 1);
+        let rect = svg.split("<rect").find(|part| part.contains("stroke-dasharray")).unwrap();
+        assert!(rect.contains(r#"height="60""#));
+    }
+
+    #[test]
+    fn test_render_link() {
+        let renderer = SvgRenderer::new();
+        let link = plantuml_layout::Link {
+            url: "https://example.com".to_string(),
+            tooltip: Some("Подсказка".to_string()),
+            label: None,
+        };
+        let layout = LayoutResult {
+            elements: vec![LayoutElement::new(
+                "linked",
+                Rect::new(10.0, 10.0, 100.0, 50.0),
+                ElementType::Rectangle {
+                    label: "Hello".to_string(),
+                    corner_radius: 0.0,
+                },
+            )
+            .with_link(Some(&link))],
+            bounds: Rect::new(0.0, 0.0, 120.0, 70.0),
+        };
+
+        let svg = renderer.render(&layout, &Theme::default());
+        assert!(svg.contains("xlink:href=\"https://example.com\""));
+        assert!(svg.contains("<title>Подсказка</title>"));
+        assert_eq!(svg.matches("id=\"linked\"").count(), 1);
+    }
+
+    fn label_svg(label: &str) -> String {
+        let layout = LayoutResult {
+            elements: vec![LayoutElement::new(
+                "test",
+                Rect::new(10.0, 10.0, 200.0, 120.0),
+                ElementType::Rectangle {
+                    label: label.to_string(),
+                    corner_radius: 0.0,
+                },
+            )],
+            bounds: Rect::new(0.0, 0.0, 220.0, 140.0),
+        };
+        SvgRenderer::new().render(&layout, &Theme::default())
+    }
+
+    #[test]
+    fn test_render_creole_runs() {
+        let svg = label_svg("**Bold** <color:red>red</color> --gone--");
+        assert!(svg.contains("<tspan font-weight=\"bold\""));
+        assert!(svg.contains("fill=\"red\""));
+        assert!(svg.contains("text-decoration=\"line-through\""));
+        assert!(!svg.contains("**"));
+    }
+
+    #[test]
+    fn test_render_creole_blocks() {
+        let svg = label_svg("Title\n----\n* item\n|= A | B |");
+        assert!(svg.contains("<line"));
+        assert!(svg.contains("• "));
+        // Рамка основного прямоугольника и две ячейки
+        assert_eq!(svg.matches("<rect").count(), 3);
+    }
+
+    #[test]
+    fn test_plain_label_unchanged() {
+        let svg = label_svg("a -- b");
+        assert!(!svg.contains("<tspan"));
+        assert!(svg.contains("a -- b"));
+    }
 }
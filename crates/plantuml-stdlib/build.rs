@@ -0,0 +1,78 @@
+//! Сжимает файлы включённых библиотек и генерирует их индекс
+//!
+//! Каждая библиотека из `stdlib/` подключается своей cargo feature. Файлы
+//! сжимаются deflate в `OUT_DIR`, а `stdlib_index.rs` перечисляет их вместе
+//! с путями вида `C4/C4_Context.puml`.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Библиотеки: (cargo feature, директория в `stdlib/`)
+const LIBRARIES: &[(&str, &str)] = &[
+    ("c4", "C4"),
+    ("aws", "awslib"),
+    ("azure", "azure"),
+    ("kubernetes", "kubernetes"),
+    ("material", "material"),
+];
+
+fn main() {
+    let root = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR"));
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").expect("OUT_DIR"));
+    println!("cargo:rerun-if-changed=stdlib");
+
+    let mut index = String::from("&[\n");
+    for (feature, dir) in LIBRARIES {
+        let enabled = env::var_os(format!("CARGO_FEATURE_{}", feature.to_uppercase())).is_some();
+        if !enabled {
+            continue;
+        }
+
+        let mut files = Vec::new();
+        collect_files(&root.join("stdlib").join(dir), &mut files);
+        files.sort();
+
+        for file in files {
+            let relative = file
+                .strip_prefix(root.join("stdlib"))
+                .expect("файл внутри stdlib")
+                .to_string_lossy()
+                .replace('\\', "/");
+            let data = fs::read(&file).expect("чтение файла stdlib");
+            let compressed = miniz_oxide::deflate::compress_to_vec(&data, 9);
+
+            let target = out_dir.join("stdlib").join(format!("{}.deflate", relative));
+            fs::create_dir_all(target.parent().expect("родительская директория"))
+                .expect("создание директории в OUT_DIR");
+            fs::write(&target, compressed).expect("запись сжатого файла");
+
+            index.push_str(&format!(
+                "    ({:?}, include_bytes!({:?})),\n",
+                relative,
+                target.display().to_string()
+            ));
+        }
+    }
+    index.push_str("]\n");
+
+    fs::write(out_dir.join("stdlib_index.rs"), index).expect("запись индекса stdlib");
+}
+
+/// Рекурсивно собирает `.puml` и `.iuml` файлы директории
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(&path, files);
+        } else if matches!(
+            path.extension().and_then(|ext| ext.to_str()),
+            Some("puml" | "iuml")
+        ) {
+            files.push(path);
+        }
+    }
+}
@@ -1,33 +1,198 @@
 //! # plantuml-stdlib
 //!
-//! Стандартная библиотека PlantUML: иконки, спрайты, макросы.
+//! Стандартная библиотека PlantUML для `!include <...>`.
 //!
-//! Включает:
-//! - AWS Architecture Icons
-//! - Azure Icons  
-//! - Kubernetes Icons
-//! - C4 Model
-//! - Material Design Icons
-//! - и другие
+//! Каждая библиотека встраивается в бинарник в сжатом виде и подключается
+//! своей cargo feature (все включены по умолчанию):
+//!
+//! | Feature | Пример пути |
+//! |---|---|
+//! | `c4` | `<C4/C4_Container>` |
+//! | `aws` | `<awslib/Compute/EC2>` |
+//! | `azure` | `<azure/Compute/AzureVirtualMachine>` |
+//! | `kubernetes` | `<kubernetes/k8s-sprites-unlabeled-25pct>` |
+//! | `material` | `<material/account>` |
+//!
+//! **Встроена только часть стандартной библиотеки PlantUML.** Это компактные
+//! совместимые реализации, а не файлы оригинальных библиотек: пути и имена
+//! макросов совпадают, иконки заменены упрощёнными пиктограммами, а из
+//! каждой библиотеки взяты лишь основные файлы (`files()` перечисляет все):
+//!
+//! - `C4` — `C4`, `C4_Context`, `C4_Container`, `C4_Component`,
+//!   `C4_Deployment`, `C4_Dynamic`; `SHOW_LEGEND()` не рисует легенду
+//! - `awslib` — `AWSCommon` и около десяти иконок (`Compute/EC2`,
+//!   `Compute/Lambda`, `Database/RDS`, …); например, `Analytics/Athena` нет
+//! - `azure`, `kubernetes`, `material` — несколько распространённых иконок
+//!
+//! Файлы, которых здесь нет, препроцессор ищет в путях поиска (`-I`):
+//! туда можно положить оригинальные библиотеки.
+//!
+//! ```rust
+//! # #[cfg(feature = "c4")]
+//! # {
+//! let source = plantuml_stdlib::read("C4/C4_Container").unwrap();
+//! assert!(source.contains("!procedure Container("));
+//! # }
+//! ```
+
+use miniz_oxide::inflate::decompress_to_vec;
+
+/// Встроенные файлы: (путь, содержимое в deflate)
+static FILES: &[(&str, &[u8])] = include!(concat!(env!("OUT_DIR"), "/stdlib_index.rs"));
+
+/// Встроенная библиотека
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Library {
+    /// Имя директории (`C4`, `awslib`, ...)
+    pub name: &'static str,
+    /// Cargo feature, подключающая библиотеку
+    pub feature: &'static str,
+    /// Краткое описание
+    pub description: &'static str,
+}
+
+/// Все известные библиотеки (включённые и нет)
+pub const LIBRARIES: &[Library] = &[
+    Library {
+        name: "C4",
+        feature: "c4",
+        description: "C4 model: контекст, контейнеры, компоненты, развёртывание",
+    },
+    Library {
+        name: "awslib",
+        feature: "aws",
+        description: "Иконки AWS",
+    },
+    Library {
+        name: "azure",
+        feature: "azure",
+        description: "Иконки Azure",
+    },
+    Library {
+        name: "kubernetes",
+        feature: "kubernetes",
+        description: "Спрайты объектов Kubernetes",
+    },
+    Library {
+        name: "material",
+        feature: "material",
+        description: "Иконки Material Design",
+    },
+];
+
+/// Библиотеки, включённые в сборку
+pub fn libraries() -> impl Iterator<Item = &'static Library> {
+    LIBRARIES.iter().filter(|library| {
+        let prefix = format!("{}/", library.name);
+        FILES.iter().any(|(path, _)| path.starts_with(&prefix))
+    })
+}
+
+/// Библиотека пути `<C4/C4_Context>` (включённая в сборку или нет)
+pub fn library(path: &str) -> Option<&'static Library> {
+    let path = path.trim().trim_start_matches('<');
+    LIBRARIES
+        .iter()
+        .find(|library| path.strip_prefix(library.name).is_some_and(|rest| rest.starts_with('/')))
+}
+
+/// Пути всех встроенных файлов (`C4/C4_Context.puml`, ...)
+pub fn files() -> impl Iterator<Item = &'static str> {
+    FILES.iter().map(|(path, _)| *path)
+}
+
+/// Находит встроенный файл
+///
+/// Принимает путь как в `!include <...>`: угловые скобки и расширение
+/// `.puml`/`.iuml` необязательны.
+fn find(path: &str) -> Option<&'static [u8]> {
+    let path = path.trim().trim_start_matches('<').trim_end_matches('>');
+    let candidates = [
+        path.to_string(),
+        format!("{}.puml", path),
+        format!("{}.iuml", path),
+    ];
+    candidates
+        .iter()
+        .find_map(|candidate| FILES.iter().find(|(file, _)| file == candidate))
+        .map(|(_, data)| *data)
+}
+
+/// Проверить, существует ли файл в stdlib
+pub fn exists(path: &str) -> bool {
+    find(path).is_some()
+}
 
-// TODO: Реализовать загрузку и управление стандартной библиотекой
+/// Прочитать файл stdlib
+///
+/// Возвращает `None`, если файла нет или его библиотека отключена.
+pub fn read(path: &str) -> Option<String> {
+    let data = decompress_to_vec(find(path)?).ok()?;
+    String::from_utf8(data).ok()
+}
 
-/// Получить спрайт по имени
-pub fn get_sprite(_name: &str) -> Option<&'static str> {
-    // TODO: Реализовать
-    None
+/// Получить определение спрайта по имени
+///
+/// Возвращает блок `sprite $name [...] {...}` из первой библиотеки, где
+/// спрайт определён. Имя принимается как с `$`, так и без.
+pub fn get_sprite(name: &str) -> Option<String> {
+    let name = name.trim_start_matches('$');
+    let prefixes = [format!("sprite ${} ", name), format!("sprite {} ", name)];
+    find_block(
+        |line| prefixes.iter().any(|prefix| line.starts_with(prefix)),
+        |first| first.ends_with('{').then_some("}"),
+    )
 }
 
-/// Получить макрос по имени
-pub fn get_macro(_name: &str) -> Option<&'static str> {
-    // TODO: Реализовать
-    None
+/// Получить определение макроса (`!procedure`, `!function`, `!define`) по имени
+pub fn get_macro(name: &str) -> Option<String> {
+    let definition = |line: &str| {
+        let line = line.strip_prefix("!unquoted ").unwrap_or(line);
+        [
+            ("!procedure ", Some("!endprocedure")),
+            ("!function ", Some("!endfunction")),
+            ("!define ", None),
+        ]
+        .into_iter()
+        .find_map(|(keyword, end)| {
+            let rest = line.strip_prefix(keyword)?;
+            (macro_name(rest) == name).then_some(end)
+        })
+    };
+    find_block(|line| definition(line).is_some(), |first| definition(first).flatten())
 }
 
-/// Проверить, существует ли элемент в stdlib
-pub fn exists(_path: &str) -> bool {
-    // TODO: Реализовать
-    false
+/// Имя в определении макроса: `Name($a, $b)` или `NAME value`
+fn macro_name(definition: &str) -> &str {
+    let end = definition
+        .find(|c: char| c == '(' || c.is_whitespace())
+        .unwrap_or(definition.len());
+    &definition[..end]
+}
+
+/// Ищет во всех файлах блок, начинающийся строкой `is_start`
+///
+/// `end_of` по первой строке возвращает закрывающую строку блока или `None`
+/// для однострочного определения.
+fn find_block(
+    is_start: impl Fn(&str) -> bool,
+    end_of: impl Fn(&str) -> Option<&'static str>,
+) -> Option<String> {
+    files().filter_map(read).find_map(|source| {
+        let mut lines = source.lines();
+        let first = lines.by_ref().find(|line| is_start(line.trim()))?;
+
+        let mut block = vec![first];
+        if let Some(end) = end_of(first.trim()) {
+            for line in lines {
+                block.push(line);
+                if line.trim() == end {
+                    break;
+                }
+            }
+        }
+        Some(block.join("\n"))
+    })
 }
 
 #[cfg(test)]
@@ -35,7 +200,53 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_placeholder() {
-        assert!(get_sprite("aws/Compute").is_none());
+    fn test_missing_file() {
+        assert!(!exists("nonexistent/File"));
+        assert!(read("nonexistent/File").is_none());
+        assert!(get_sprite("nonexistent").is_none());
+        assert!(get_macro("nonexistent").is_none());
+    }
+
+    #[test]
+    fn test_library_of_path() {
+        assert_eq!(library("<awslib/Analytics/Athena>").map(|l| l.feature), Some("aws"));
+        assert_eq!(library("C4/C4_Context").map(|l| l.name), Some("C4"));
+        assert!(library("<C4_Context>").is_none());
+        assert!(library("<custom/Lib>").is_none());
+    }
+
+    #[test]
+    fn test_libraries_match_files() {
+        for library in libraries() {
+            assert!(files().any(|path| path.starts_with(library.name)));
+        }
+    }
+
+    #[cfg(feature = "c4")]
+    #[test]
+    fn test_read_c4() {
+        assert!(exists("<C4/C4_Container>"));
+        assert!(exists("C4/C4_Container.puml"));
+        let source = read("C4/C4_Container").unwrap();
+        assert!(source.contains("!include_once <C4/C4_Context>"));
+
+        let definition = get_macro("Container").unwrap();
+        assert!(definition.starts_with("!procedure Container("));
+        assert!(definition.ends_with("!endprocedure"));
+    }
+
+    #[cfg(feature = "aws")]
+    #[test]
+    fn test_aws_sprite() {
+        let sprite = get_sprite("$EC2").unwrap();
+        assert!(sprite.starts_with("sprite $EC2 [32x32/16] {"));
+        assert!(sprite.ends_with('}'));
+        assert_eq!(sprite.lines().count(), 34);
+    }
+
+    #[cfg(feature = "kubernetes")]
+    #[test]
+    fn test_kubernetes_sprite() {
+        assert!(get_sprite("pod").is_some());
     }
 }
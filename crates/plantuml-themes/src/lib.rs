@@ -204,11 +204,44 @@ impl Theme {
 }
 
 /// SkinParam параметры
+///
+/// Ключи сравниваются без учёта регистра, префикс `sequence` необязателен
+/// (`sequenceArrowColor` = `ArrowColor`). Параметр элемента — имя элемента
+/// и свойство (`ParticipantBorderColor`, `NoteBackgroundColor`); параметр
+/// стереотипа дополнительно заканчивается на `<<имя>>`
+/// (`ClassBackgroundColor<<Service>>`).
 #[derive(Debug, Clone, Default)]
 pub struct SkinParams {
     params: std::collections::HashMap<String, String>,
 }
 
+/// Свойство элемента в skinparam
+#[derive(Debug, Clone, Copy)]
+enum ElementProperty {
+    BackgroundColor,
+    BorderColor,
+    FontColor,
+    FontName,
+    FontSize,
+    Thickness,
+    RoundCorner,
+    Shadowing,
+}
+
+/// Суффиксы параметров элемента (`ParticipantBorderColor` → `bordercolor`)
+const ELEMENT_PROPERTIES: &[(&str, ElementProperty)] = &[
+    ("backgroundcolor", ElementProperty::BackgroundColor),
+    ("bordercolor", ElementProperty::BorderColor),
+    ("fontcolor", ElementProperty::FontColor),
+    ("fontname", ElementProperty::FontName),
+    ("fontsize", ElementProperty::FontSize),
+    ("borderthickness", ElementProperty::Thickness),
+    ("thickness", ElementProperty::Thickness),
+    ("roundcorner", ElementProperty::RoundCorner),
+    ("shadowing", ElementProperty::Shadowing),
+    ("color", ElementProperty::BorderColor),
+];
+
 impl SkinParams {
     /// Создаёт пустые параметры
     pub fn new() -> Self {
@@ -217,37 +250,132 @@ impl SkinParams {
 
     /// Устанавливает параметр
     pub fn set(&mut self, key: impl Into<String>, value: impl Into<String>) {
-        self.params.insert(key.into(), value.into());
+        self.params.insert(normalize_key(&key.into()), value.into());
     }
 
     /// Получает параметр
     pub fn get(&self, key: &str) -> Option<&String> {
-        self.params.get(key)
+        self.params.get(&normalize_key(key))
     }
 
-    /// Применяет параметры к теме
+    /// Пусты ли параметры
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+
+    /// Применяет глобальные параметры к теме
     pub fn apply_to(&self, theme: &mut Theme) {
         if let Some(v) = self.get("backgroundColor") {
-            theme.background_color = Color::new(v);
+            theme.background_color = css_color(v);
         }
         if let Some(v) = self.get("defaultFontName") {
             theme.font_family = v.clone();
         }
-        if let Some(v) = self.get("defaultFontSize") {
-            if let Ok(size) = v.parse() {
-                theme.font_size = size;
-            }
+        if let Some(size) = self.get("defaultFontSize").and_then(|v| v.parse().ok()) {
+            theme.font_size = size;
+        }
+        if let Some(v) = self.get("defaultFontColor") {
+            theme.text_color = css_color(v);
         }
         if let Some(v) = self.get("handwritten") {
-            theme.handwritten = v == "true";
+            theme.handwritten = parse_bool(v);
         }
         if let Some(v) = self.get("shadowing") {
-            theme.shadow = v == "true";
+            theme.shadow = parse_bool(v);
+        }
+        if let Some(radius) = self.get("roundCorner").and_then(|v| v.parse().ok()) {
+            theme.corner_radius = radius;
+        }
+        // Маркеры стрелок общие для диаграммы, поэтому цвет стрелок глобальный
+        if let Some(v) = self.get("arrowColor") {
+            theme.arrow_color = css_color(v);
+        }
+    }
+
+    /// Тема элемента с параметрами `{element}{свойство}` и стереотипов
+    ///
+    /// `elements` перечисляются от общего к частному (`["class", "interface"]`),
+    /// параметры стереотипа перекрывают параметры элемента. Возвращает `None`,
+    /// если ни один параметр не задан.
+    pub fn element_theme(&self, theme: &Theme, elements: &[&str], stereotypes: &[&str]) -> Option<Theme> {
+        if self.params.is_empty() {
+            return None;
+        }
+        let mut element_theme = theme.clone();
+        let mut changed = false;
+        for element in elements {
+            let element = element.to_lowercase();
+            for (suffix, property) in ELEMENT_PROPERTIES {
+                let key = format!("{}{}", element, suffix);
+                let scoped = stereotypes
+                    .iter()
+                    .map(|stereotype| format!("{}<<{}>>", key, stereotype.trim().to_lowercase()));
+                for key in std::iter::once(key.clone()).chain(scoped) {
+                    if let Some(value) = self.params.get(&key) {
+                        changed |= apply_property(&mut element_theme, *property, value);
+                    }
+                }
+            }
         }
-        // TODO: Добавить больше параметров
+        changed.then_some(element_theme)
+    }
+}
+
+/// Нормализует ключ: нижний регистр, без пробелов и префикса `sequence`
+fn normalize_key(key: &str) -> String {
+    let key: String = key.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_lowercase();
+    match key.strip_prefix("sequence") {
+        Some(rest) if !rest.is_empty() && !rest.starts_with("<<") => rest.to_string(),
+        _ => key,
+    }
+}
+
+/// Применяет свойство элемента; возвращает false для некорректного значения
+fn apply_property(theme: &mut Theme, property: ElementProperty, value: &str) -> bool {
+    match property {
+        ElementProperty::BackgroundColor => theme.node_background = css_color(value),
+        ElementProperty::BorderColor => {
+            theme.node_border = css_color(value);
+            theme.arrow_color = css_color(value);
+        }
+        ElementProperty::FontColor => theme.text_color = css_color(value),
+        ElementProperty::FontName => theme.font_family = value.to_string(),
+        ElementProperty::FontSize => match value.parse() {
+            Ok(size) => theme.font_size = size,
+            Err(_) => return false,
+        },
+        ElementProperty::Thickness => match value.parse() {
+            Ok(width) => theme.line_width = width,
+            Err(_) => return false,
+        },
+        ElementProperty::RoundCorner => match value.parse() {
+            Ok(radius) => theme.corner_radius = radius,
+            Err(_) => return false,
+        },
+        ElementProperty::Shadowing => theme.shadow = parse_bool(value),
+    }
+    true
+}
+
+/// Цвет skinparam: `#FF0000` и `red` как есть, `#LightBlue` без `#`
+fn css_color(value: &str) -> Color {
+    let value = value.trim();
+    match value.strip_prefix('#') {
+        Some(name) if !is_hex_color(name) => Color::new(name),
+        _ => Color::new(value),
     }
 }
 
+/// Шестнадцатеричный цвет без `#`: `FFF`, `FF0000`, `FF000080`
+fn is_hex_color(value: &str) -> bool {
+    matches!(value.len(), 3 | 6 | 8) && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Логическое значение skinparam (`true`, `True`)
+fn parse_bool(value: &str) -> bool {
+    value.trim().eq_ignore_ascii_case("true")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -274,4 +402,43 @@ mod tests {
 
         assert_eq!(theme.background_color.to_css(), "#FF0000");
     }
+
+    #[test]
+    fn test_skin_params_global_keys() {
+        let mut params = SkinParams::new();
+        params.set("DefaultFontColor", "#Navy");
+        params.set("sequenceArrowColor", "red");
+        params.set("RoundCorner", "10");
+        params.set("Shadowing", "True");
+
+        let mut theme = Theme::default();
+        params.apply_to(&mut theme);
+
+        assert_eq!(theme.text_color.to_css(), "Navy");
+        assert_eq!(theme.arrow_color.to_css(), "red");
+        assert_eq!(theme.corner_radius, 10.0);
+        assert!(theme.shadow);
+        assert_eq!(params.get("arrowcolor").map(String::as_str), Some("red"));
+    }
+
+    #[test]
+    fn test_skin_params_element_theme() {
+        let mut params = SkinParams::new();
+        params.set("ParticipantBorderColor", "#FF0000");
+        params.set("ParticipantBackgroundColor", "white");
+        params.set("ParticipantBackgroundColor<<Service>>", "#LightBlue");
+        params.set("ArrowThickness", "2");
+
+        let theme = Theme::default();
+        let participant = params.element_theme(&theme, &["participant"], &[]).unwrap();
+        assert_eq!(participant.node_border.to_css(), "#FF0000");
+        assert_eq!(participant.node_background.to_css(), "white");
+
+        let service = params.element_theme(&theme, &["participant"], &["Service"]).unwrap();
+        assert_eq!(service.node_background.to_css(), "LightBlue");
+
+        let arrow = params.element_theme(&theme, &["arrow"], &[]).unwrap();
+        assert_eq!(arrow.line_width, 2.0);
+        assert!(params.element_theme(&theme, &["note"], &[]).is_none());
+    }
 }
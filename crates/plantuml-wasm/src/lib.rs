@@ -74,6 +74,22 @@ pub fn parse_to_json(source: &str) -> Result<String, JsValue> {
         .map_err(|e: serde_json::Error| JsValue::from_str(&e.to_string()))
 }
 
+/// Возвращает карту гиперссылок элементов в JSON
+///
+/// @param source - PlantUML исходный код
+/// @returns JSON массив `{ id, url, tooltip, label, x, y, width, height }` (x/y в пикселях SVG)
+#[wasm_bindgen]
+pub fn links_to_json(source: &str) -> Result<String, JsValue> {
+    #[cfg(feature = "console_error_panic_hook")]
+    set_panic_hook();
+
+    let links = plantuml_core::element_links(source, &RenderOptions::default())
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    serde_json::to_string(&links)
+        .map_err(|e: serde_json::Error| JsValue::from_str(&e.to_string()))
+}
+
 /// Возвращает версию библиотеки
 #[wasm_bindgen]
 pub fn version() -> String {
@@ -97,4 +113,14 @@ mod tests {
     fn test_version() {
         assert!(!version().is_empty());
     }
+
+    #[test]
+    fn test_links_to_json() {
+        let json = links_to_json(
+            "@startuml\nclass Foo [[https://example.com{Docs}]]\n@enduml",
+        )
+        .unwrap();
+        assert!(json.contains("\"url\":\"https://example.com\""));
+        assert!(json.contains("\"tooltip\":\"Docs\""));
+    }
 }